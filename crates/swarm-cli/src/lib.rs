@@ -0,0 +1,153 @@
+//! Swarm CLI - Operator Commands
+//!
+//! The commands behind the `swarm` binary: publishing files as documents,
+//! and rendering what a running coordinator and file discovery report
+//! through their administration subjects. Everything goes through a `MessageBroker`, so
+//! the commands run against the in-memory broker in tests.
+
+use anyhow::Result;
+use std::path::Path;
+use swarm_comms::{TaskStatusReport, WorkerSummary};
+use swarm_core::prelude::*;
+use swarm_core::{FileState, InventoryReport};
+use swarm_documents::{walk_directory, DEFAULT_MAX_SCAN_DEPTH};
+use swarm_gateway::document_from_bytes;
+
+/// Source recorded on documents published by `swarm ingest`
+pub const CLI_SOURCE: &str = "cli";
+
+/// Publish a file, or every file below a directory, as documents on `subject`
+pub async fn ingest(broker: &dyn MessageBroker, subject: &str, path: &Path) -> Result<Vec<Document>> {
+    let mut files = if tokio::fs::metadata(path).await?.is_dir() {
+        walk_directory(path, DEFAULT_MAX_SCAN_DEPTH, |_| false).await?.files
+    } else {
+        vec![path.to_path_buf()]
+    };
+    files.sort();
+    
+    let mut documents = Vec::with_capacity(files.len());
+    for file in files {
+        let filename = file.file_name().map_or_else(|| file.display().to_string(), |name| name.to_string_lossy().into_owned());
+        let document = document_from_bytes(&filename, tokio::fs::read(&file).await?, CLI_SOURCE);
+        broker.publish(subject, &serde_json::to_vec(&document)?).await?;
+        tracing::debug!("Published {} as document {}", file.display(), document.id);
+        documents.push(document);
+    }
+    Ok(documents)
+}
+
+/// One line per worker
+pub fn format_workers(workers: &[WorkerSummary]) -> String {
+    if workers.is_empty() {
+        return "no workers registered".to_string();
+    }
+    workers.iter()
+        .map(|worker| format!(
+            "{}  {:<20} {:<12} {}/{} tasks{}",
+            worker.worker_id,
+            worker.name,
+            format!("{:?}", worker.status),
+            worker.in_flight_tasks,
+            worker.max_concurrent_tasks,
+            if worker.available { "" } else { "  (unavailable)" },
+        ))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn format_task_status(report: &TaskStatusReport) -> String {
+    match (&report.status, report.worker_id) {
+        (None, _) => format!("{}  unknown", report.task_id),
+        (Some(status), Some(worker_id)) => format!("{}  {:?} to worker {}", report.task_id, status, worker_id),
+        (Some(status), None) => format!("{}  {:?}", report.task_id, status),
+    }
+}
+
+pub fn format_stats(stats: &CoordinatorStats) -> String {
+    [
+        format!("workers:          {} ({} active)", stats.total_workers, stats.active_workers),
+        format!("tasks processed:  {}", stats.total_tasks_processed),
+        format!("tasks/second:     {:.2}", stats.tasks_per_second),
+        format!("avg processing:   {:.1} ms", stats.average_processing_time_ms),
+        format!("error rate:       {:.1}%", stats.error_rate * 100.0),
+        format!("pending tasks:    {}", stats.saturation.pending_tasks),
+        format!("rejected tasks:   {}", stats.saturation.rejected_submissions),
+    ]
+    .join("\n")
+}
+
+/// Counts per state, then one line per listed file
+pub fn format_inventory(report: &InventoryReport) -> String {
+    let mut lines: Vec<String> = [FileState::Discovered, FileState::Queued, FileState::Processed].iter()
+        .map(|state| format!("{:<12} {}", format!("{:?}:", state).to_lowercase(), report.counts.get(state).copied().unwrap_or(0)))
+        .collect();
+    for entry in &report.files {
+        let age = (report.generated_at - entry.state_since).num_seconds();
+        lines.push(format!("{:<10} {:>8}s  {}", format!("{:?}", entry.state).to_lowercase(), age, entry.path.display()));
+    }
+    lines.join("\n")
+}
+
+/// A message as `swarm tail` prints it; payloads that are not text are summarized
+pub fn format_message(message: &Message) -> String {
+    let payload = match std::str::from_utf8(&message.payload) {
+        Ok(text) => text.to_string(),
+        Err(_) => format!("<{} bytes>", message.payload.len()),
+    };
+    format!("{} {} {}", message.timestamp.format("%H:%M:%S%.3f"), message.subject, payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swarm_core::MemoryBroker;
+    
+    #[tokio::test]
+    async fn test_ingest_publishes_every_file_below_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "plain text").unwrap();
+        std::fs::write(dir.path().join("nested").join("README.md"), "# Title").unwrap();
+        
+        let broker = MemoryBroker::new();
+        let mut incoming = broker.subscribe("swarm.documents.incoming").await.unwrap();
+        let documents = ingest(&broker, "swarm.documents.incoming", dir.path()).await.unwrap();
+        assert_eq!(documents.len(), 2);
+        
+        let mut published = Vec::new();
+        while let Some(message) = incoming.next_message().unwrap() {
+            let document: Document = serde_json::from_slice(&message.payload).unwrap();
+            assert_eq!(document.metadata["source"], CLI_SOURCE);
+            published.push(document.filename);
+        }
+        published.sort();
+        assert_eq!(published, vec!["README.md", "notes.txt"]);
+        
+        let single = ingest(&broker, "swarm.documents.incoming", &dir.path().join("notes.txt")).await.unwrap();
+        assert_eq!(single[0].document_type, DocumentType::Text);
+    }
+    
+    #[test]
+    fn test_inventory_lists_counts_then_files() {
+        let now = chrono::Utc::now();
+        let report = InventoryReport {
+            generated_at: now,
+            total: 2,
+            counts: [(FileState::Discovered, 1), (FileState::Processed, 1)].into(),
+            age_buckets: Default::default(),
+            files: vec![swarm_core::InventoryEntry {
+                path: "/in/a.txt".into(),
+                state: FileState::Discovered,
+                size_bytes: 10,
+                discovered_at: now - chrono::Duration::seconds(90),
+                state_since: now - chrono::Duration::seconds(90),
+            }],
+        };
+        assert_eq!(format_inventory(&report).lines().collect::<Vec<_>>(), vec![
+            "discovered:  1",
+            "queued:      0",
+            "processed:   1",
+            "discovered       90s  /in/a.txt",
+        ]);
+    }
+}
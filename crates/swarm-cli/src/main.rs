@@ -0,0 +1,185 @@
+//! Swarm CLI
+//!
+//! Operator commands against a running swarm over its message broker.
+//!
+//! Usage: `swarm [--config <file>] [--nats-url <url>] <command>`, e.g.
+//! `swarm ingest ./docs`, `swarm workers list`, `swarm tasks status <id>`,
+//! `swarm stats` or `swarm tail 'swarm.tasks.>'`. The broker is selected by
+//! the `broker` section of the configuration file and `SWARM_BROKER__*`
+//! environment variables (NATS by default); `--nats-url` or `NATS_URL`
+//! overrides the NATS server.
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+use swarm_cli::{format_inventory, format_message, format_stats, format_task_status, format_workers, ingest};
+use swarm_comms::{AdminClient, BrokerConfig, MessageRouter, MessageRoutingConfig};
+use swarm_core::{cancellable_sleep, CancellationToken, FileState, InventoryQuery, LayeredConfig, BROKER_SECTION};
+use uuid::Uuid;
+
+#[derive(Parser)]
+#[command(name = "swarm", about = "Administer an Aprio swarm")]
+struct Cli {
+    /// Configuration file whose `broker` section selects the message broker
+    #[arg(long, env = "SWARM_CONFIG")]
+    config: Option<PathBuf>,
+    
+    /// NATS server to connect to, overriding the configured one
+    #[arg(long, env = "NATS_URL")]
+    nats_url: Option<String>,
+    
+    /// How long to wait for the coordinator to answer, in milliseconds
+    #[arg(long, default_value_t = 5000)]
+    timeout_ms: u64,
+    
+    /// Print replies as JSON
+    #[arg(long)]
+    json: bool,
+    
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Publish a file, or every file below a directory, for processing
+    Ingest {
+        path: PathBuf,
+        
+        /// Subject to publish on (defaults to the incoming documents subject)
+        #[arg(long)]
+        subject: Option<String>,
+    },
+    /// Inspect the coordinator's workers
+    Workers {
+        #[command(subcommand)]
+        command: WorkersCommand,
+    },
+    /// Inspect tasks
+    Tasks {
+        #[command(subcommand)]
+        command: TasksCommand,
+    },
+    /// Show coordinator statistics
+    Stats,
+    /// Show which discovered files are still waiting to be processed
+    Inventory {
+        /// Only list files in this state: discovered, queued or processed
+        #[arg(long, value_parser = parse_file_state)]
+        state: Option<FileState>,
+        
+        /// Only list files that have been in their state at least this many seconds
+        #[arg(long)]
+        min_age_secs: Option<u64>,
+        
+        /// List at most this many files
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Print messages on a subject as they arrive
+    Tail {
+        /// Subject to watch; NATS wildcards are allowed
+        subject: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkersCommand {
+    /// List registered workers
+    List,
+}
+
+#[derive(Subcommand)]
+enum TasksCommand {
+    /// Show where a task is in processing
+    Status { task_id: Uuid },
+}
+
+fn parse_file_state(value: &str) -> Result<FileState, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("unknown state '{}', expected discovered, queued or processed", value))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Logs go to stderr and only when something is wrong, so replies can be piped
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(tracing::Level::WARN)
+        .init();
+    
+    let cli = Cli::parse();
+    let layered = match &cli.config {
+        Some(path) => LayeredConfig::from_file(path)?,
+        None => LayeredConfig::new(),
+    };
+    let mut broker_config: BrokerConfig = layered.with_env("SWARM").section(BROKER_SECTION)?;
+    if let (BrokerConfig::Nats(nats), Some(url)) = (&mut broker_config, &cli.nats_url) {
+        nats.url = url.clone();
+    }
+    let broker = broker_config.connect().await?;
+    let admin = AdminClient::new(broker.clone()).with_timeout(Duration::from_millis(cli.timeout_ms));
+    
+    match cli.command {
+        Command::Ingest { path, subject } => {
+            let router = MessageRouter::new(MessageRoutingConfig::default());
+            let subject = subject.unwrap_or_else(|| router.document_incoming_subject().as_str().to_string());
+            for document in ingest(broker.as_ref(), &subject, &path).await? {
+                println!("{}  {} ({})", document.id, document.filename, document.document_type);
+            }
+        }
+        Command::Workers { command: WorkersCommand::List } => {
+            let workers = admin.workers().await?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&workers)?);
+            } else {
+                println!("{}", format_workers(&workers));
+            }
+        }
+        Command::Tasks { command: TasksCommand::Status { task_id } } => {
+            let report = admin.task_status(task_id).await?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{}", format_task_status(&report));
+            }
+        }
+        Command::Stats => {
+            let stats = admin.stats().await?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                println!("{}", format_stats(&stats));
+            }
+        }
+        Command::Inventory { state, min_age_secs, limit } => {
+            let report = admin.inventory(&InventoryQuery { state, min_age_secs, limit }).await?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{}", format_inventory(&report));
+            }
+        }
+        Command::Tail { subject } => {
+            let cancel = CancellationToken::new();
+            let stopper = cancel.clone();
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    stopper.cancel();
+                }
+            });
+            
+            let mut subscription = broker.subscribe(&subject).await?;
+            loop {
+                while let Some(message) = subscription.next_message()? {
+                    println!("{}", format_message(&message));
+                }
+                if cancellable_sleep(&cancel, Duration::from_millis(50)).await {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,101 @@
+//! Coordinator Administration
+//!
+//! A running `NatsCoordinator` answers requests on the `swarm.admin`
+//! subjects, so operators can inspect a swarm from outside of it: the
+//! workers it knows, where a task is in processing, and its statistics.
+//! The discovery inventory is answered by the `DiscoveryPublisher` of
+//! `swarm-documents` on `ADMIN_INVENTORY_SUBJECT`. `AdminClient` sends these
+//! requests through any `MessageBroker`.
+
+use super::*;
+use std::sync::Arc;
+use std::time::Duration;
+use swarm_core::{
+    CoordinatorStats, InventoryQuery, InventoryReport, MessageBroker, TaskStatus, WorkerStatus, WorkerType,
+    ADMIN_INVENTORY_SUBJECT,
+};
+use uuid::Uuid;
+
+/// Subject answering with the coordinator's workers
+pub const ADMIN_WORKERS_SUBJECT: &str = "swarm.admin.workers";
+
+/// Subject answering with the status of a task
+pub const ADMIN_TASK_STATUS_SUBJECT: &str = "swarm.admin.tasks.status";
+
+/// Subject answering with the coordinator's statistics
+pub const ADMIN_STATS_SUBJECT: &str = "swarm.admin.stats";
+
+/// Default time to wait for the coordinator to answer
+pub const DEFAULT_ADMIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A worker as the coordinator sees it
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct WorkerSummary {
+    pub worker_id: Uuid,
+    pub name: String,
+    pub worker_type: WorkerType,
+    pub status: WorkerStatus,
+    
+    /// Whether the worker's heartbeats are current
+    pub available: bool,
+    pub in_flight_tasks: usize,
+    pub max_concurrent_tasks: usize,
+}
+
+/// Status of a task asked for by ID
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TaskStatusReport {
+    pub task_id: Uuid,
+    
+    /// `None` when the coordinator does not know the task
+    pub status: Option<TaskStatus>,
+    
+    /// Worker the task was dispatched to, while it is processed
+    pub worker_id: Option<Uuid>,
+}
+
+/// Request for the status of a task
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TaskStatusRequest {
+    pub task_id: Uuid,
+}
+
+/// Sends administration requests to a running coordinator
+pub struct AdminClient {
+    broker: Arc<dyn MessageBroker>,
+    timeout: Duration,
+}
+
+impl AdminClient {
+    pub fn new(broker: Arc<dyn MessageBroker>) -> Self {
+        Self { broker, timeout: DEFAULT_ADMIN_TIMEOUT }
+    }
+    
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    
+    /// Workers registered with the coordinator, ordered by name
+    pub async fn workers(&self) -> Result<Vec<WorkerSummary>> {
+        self.request(ADMIN_WORKERS_SUBJECT, &[]).await
+    }
+    
+    pub async fn task_status(&self, task_id: Uuid) -> Result<TaskStatusReport> {
+        self.request(ADMIN_TASK_STATUS_SUBJECT, &serde_json::to_vec(&TaskStatusRequest { task_id })?).await
+    }
+    
+    pub async fn stats(&self) -> Result<CoordinatorStats> {
+        self.request(ADMIN_STATS_SUBJECT, &[]).await
+    }
+    
+    /// Lifecycle of discovered files, listing those matching `query`
+    pub async fn inventory(&self, query: &InventoryQuery) -> Result<InventoryReport> {
+        self.request(ADMIN_INVENTORY_SUBJECT, &serde_json::to_vec(query)?).await
+    }
+    
+    async fn request<T: serde::de::DeserializeOwned>(&self, subject: &str, payload: &[u8]) -> Result<T> {
+        let reply = self.broker.request(subject, payload, self.timeout).await?;
+        Ok(serde_json::from_slice(&reply.payload)?)
+    }
+}
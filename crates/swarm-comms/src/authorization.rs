@@ -0,0 +1,204 @@
+//! Subject Authorization
+//!
+//! Multi-team clusters give each component its own NATS credentials and
+//! restrict which subjects it may publish and subscribe to. The broker
+//! silently drops messages a client is not allowed to publish, and rejects
+//! subscriptions only asynchronously, so a misconfigured component looks
+//! healthy while doing nothing. Components therefore declare the permissions
+//! they were granted (mirroring the server's `permissions` block) and the
+//! `MessageRouter` checks every subject the component's role uses against
+//! them at startup, failing with a report of everything that would be denied.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use crate::{MessageError, MessageResult};
+
+pub use swarm_core::subject_matches;
+
+/// Credentials a component connects to NATS with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NatsCredentials {
+    UserPassword { user: String, password: String },
+    Token { token: String },
+    /// NKey seed
+    NKey { seed: String },
+    /// `.creds` file with a user JWT and NKey seed
+    CredentialsFile { path: PathBuf },
+}
+
+impl NatsCredentials {
+    /// Connect options authenticating with these credentials
+    pub async fn connect_options(&self) -> std::io::Result<async_nats::ConnectOptions> {
+        Ok(match self {
+            NatsCredentials::UserPassword { user, password } => async_nats::ConnectOptions::with_user_and_password(user.clone(), password.clone()),
+            NatsCredentials::Token { token } => async_nats::ConnectOptions::with_token(token.clone()),
+            NatsCredentials::NKey { seed } => async_nats::ConnectOptions::with_nkey(seed.clone()),
+            NatsCredentials::CredentialsFile { path } => async_nats::ConnectOptions::with_credentials_file(path).await?,
+        })
+    }
+}
+
+/// Allow and deny lists of subject patterns (`*` and `>` wildcards)
+///
+/// As on the NATS server, an empty allow list allows everything and deny
+/// entries take precedence.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermissionRules {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl PermissionRules {
+    /// Whether `subject` (which may itself be a wildcard subscription) is permitted
+    pub fn permits(&self, subject: &str) -> bool {
+        let allowed = self.allow.is_empty() || self.allow.iter().any(|pattern| subject_matches(pattern, subject));
+        allowed && !self.deny.iter().any(|pattern| subjects_overlap(pattern, subject))
+    }
+}
+
+/// Publish and subscribe permissions granted to a component
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubjectPermissions {
+    #[serde(default)]
+    pub publish: PermissionRules,
+    #[serde(default)]
+    pub subscribe: PermissionRules,
+}
+
+impl SubjectPermissions {
+    pub fn permits(&self, operation: SubjectOperation, subject: &str) -> bool {
+        match operation {
+            SubjectOperation::Publish => self.publish.permits(subject),
+            SubjectOperation::Subscribe => self.subscribe.permits(subject),
+        }
+    }
+    
+    /// `permits`, as an error naming the operation and subject
+    pub fn check(&self, operation: SubjectOperation, subject: &str) -> MessageResult<()> {
+        if !self.permits(operation, subject) {
+            return Err(MessageError::PermissionDenied { operation, subject: subject.to_string() });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SubjectOperation {
+    Publish,
+    Subscribe,
+}
+
+impl fmt::Display for SubjectOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubjectOperation::Publish => write!(f, "publish"),
+            SubjectOperation::Subscribe => write!(f, "subscribe"),
+        }
+    }
+}
+
+/// Pipeline components, each using a fixed set of routed subjects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ComponentRole {
+    /// Publishes incoming documents
+    DocumentReader,
+    /// Consumes incoming documents and publishes results and errors
+    DocumentProcessor,
+    /// Assigns tasks and tracks workers and results
+    Coordinator,
+    /// Executes assigned tasks and reports on itself
+    Worker,
+}
+
+impl fmt::Display for ComponentRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// One subject a component needs, and how
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SubjectUse {
+    pub subject: String,
+    pub operation: SubjectOperation,
+}
+
+impl SubjectUse {
+    pub fn publish(subject: &str) -> Self {
+        Self { subject: subject.to_string(), operation: SubjectOperation::Publish }
+    }
+    
+    pub fn subscribe(subject: &str) -> Self {
+        Self { subject: subject.to_string(), operation: SubjectOperation::Subscribe }
+    }
+}
+
+/// Subjects a component needs but is not permitted to use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorizationReport {
+    pub role: ComponentRole,
+    pub denied: Vec<SubjectUse>,
+}
+
+impl fmt::Display for AuthorizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is not permitted to use {} subject(s):", self.role, self.denied.len())?;
+        for denied in &self.denied {
+            write!(f, "\n  - {} {}", denied.operation, denied.subject)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether any subject matches both `a` and `b`, so a deny entry also
+/// blocks wildcard subscriptions that would receive denied subjects
+pub fn subjects_overlap(a: &str, b: &str) -> bool {
+    let mut a_tokens = a.split('.');
+    let mut b_tokens = b.split('.');
+    loop {
+        match (a_tokens.next(), b_tokens.next()) {
+            (Some(">"), Some(_)) | (Some(_), Some(">")) => return true,
+            (Some(x), Some(y)) if x == y || x == "*" || y == "*" => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_subject_patterns() {
+        assert!(subject_matches("swarm.tasks.*", "swarm.tasks.results"));
+        assert!(!subject_matches("swarm.tasks.*", "swarm.tasks.results.eu"));
+        assert!(subject_matches("swarm.>", "swarm.tasks.results.eu"));
+        assert!(!subject_matches("swarm.>", "swarm"));
+        assert!(subject_matches("swarm.tasks.*", "swarm.tasks.*"));
+        assert!(!subject_matches("swarm.tasks.*", "swarm.tasks.>"));
+        assert!(!subject_matches("swarm.tasks.results", "swarm.tasks.*"));
+        
+        assert!(subjects_overlap("swarm.tasks.deadletter", "swarm.tasks.>"));
+        assert!(subjects_overlap("swarm.*.results", "swarm.tasks.*"));
+        assert!(!subjects_overlap("swarm.documents.*", "swarm.tasks.*"));
+    }
+    
+    #[test]
+    fn test_permission_rules() {
+        let rules = PermissionRules {
+            allow: vec!["team-a.>".to_string(), "swarm.tasks.*".to_string()],
+            deny: vec!["swarm.tasks.deadletter".to_string()],
+        };
+        assert!(rules.permits("team-a.documents.incoming"));
+        assert!(rules.permits("swarm.tasks.results"));
+        assert!(!rules.permits("swarm.tasks.deadletter"));
+        assert!(!rules.permits("swarm.tasks.*"), "the wildcard would receive denied subjects");
+        assert!(!rules.permits("team-b.documents.incoming"));
+        assert!(PermissionRules::default().permits("anything.at.all"));
+    }
+}
@@ -0,0 +1,134 @@
+//! Batch Publishing
+//!
+//! Publishing many small messages one at a time pays the per-message round
+//! trip for each of them. A batch is either pipelined, each message sent
+//! without waiting for the previous one, or wrapped in a single envelope
+//! message that subscriptions unpack again before delivery. Every batch
+//! reports how long it took, and the broker keeps running latency counters.
+
+use super::*;
+use chrono::Utc;
+use swarm_core::{Message, MessageHeaders};
+use uuid::Uuid;
+
+/// Header marking an envelope and holding the number of messages it wraps
+pub const BATCH_SIZE_HEADER: &str = "batch-size";
+
+/// How `publish_batch` sends its messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode {
+    /// Publish every message on its own through the pipelined publisher
+    #[default]
+    Pipelined,
+    /// Wrap all messages in a single envelope message
+    Envelope,
+}
+
+/// Outcome of a published batch
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BatchReport {
+    pub mode: BatchMode,
+    
+    /// Messages in the batch
+    pub messages: usize,
+    
+    /// Messages whose publish failed (pipelined batches only)
+    pub failed: usize,
+    
+    /// Serialized size of the batch in bytes
+    pub bytes: usize,
+    
+    /// From the first send to the last publish completing
+    pub latency_ms: u64,
+}
+
+/// Counters of published batches
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BatchStats {
+    pub batches: u64,
+    pub messages: u64,
+    pub failed: u64,
+    pub last_latency_ms: u64,
+    pub max_latency_ms: u64,
+    pub total_latency_ms: u64,
+}
+
+impl BatchStats {
+    /// Count a published batch
+    pub fn record(&mut self, report: &BatchReport) {
+        self.batches += 1;
+        self.messages += report.messages as u64;
+        self.failed += report.failed as u64;
+        self.last_latency_ms = report.latency_ms;
+        self.max_latency_ms = self.max_latency_ms.max(report.latency_ms);
+        self.total_latency_ms += report.latency_ms;
+    }
+    
+    /// Mean latency per batch in milliseconds
+    pub fn average_latency_ms(&self) -> f64 {
+        if self.batches == 0 { 0.0 } else { self.total_latency_ms as f64 / self.batches as f64 }
+    }
+}
+
+/// Wrap messages in a single envelope message on `subject`
+pub fn envelope(subject: &str, messages: &[Message]) -> MessageResult<Message> {
+    let mut headers = MessageHeaders::new();
+    headers.insert(CONTENT_TYPE_HEADER, MessageCodec::Json.content_type());
+    headers.insert(BATCH_SIZE_HEADER, messages.len().to_string());
+    
+    Ok(Message {
+        id: Uuid::new_v4(),
+        subject: subject.to_string(),
+        payload: serde_json::to_vec(messages)?,
+        headers,
+        timestamp: Utc::now(),
+        ttl_ms: None,
+    })
+}
+
+/// Unpack the messages of an envelope; `None` if the message is no envelope
+pub fn unwrap_envelope(message: &Message) -> Option<MessageResult<Vec<Message>>> {
+    message.headers.get(BATCH_SIZE_HEADER)?;
+    Some(serde_json::from_slice(&message.payload).map_err(MessageError::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn create_test_message(payload: &str) -> Message {
+        Message {
+            id: Uuid::new_v4(),
+            subject: "swarm.documents.incoming".to_string(),
+            payload: payload.as_bytes().to_vec(),
+            headers: MessageHeaders::new(),
+            timestamp: Utc::now(),
+            ttl_ms: Some(60_000),
+        }
+    }
+    
+    #[test]
+    fn test_envelope_roundtrip() {
+        let messages = vec![create_test_message("a"), create_test_message("b")];
+        let wrapped = envelope("swarm.documents.incoming", &messages).unwrap();
+        assert_eq!(wrapped.headers.get(BATCH_SIZE_HEADER), Some("2"));
+        assert_eq!(wrapped.ttl_ms, None);
+        
+        let unwrapped = unwrap_envelope(&wrapped).unwrap().unwrap();
+        assert_eq!(unwrapped.iter().map(|m| m.id).collect::<Vec<_>>(), messages.iter().map(|m| m.id).collect::<Vec<_>>());
+        assert_eq!(unwrapped[1].payload, b"b");
+        assert!(unwrap_envelope(&messages[0]).is_none());
+    }
+    
+    #[test]
+    fn test_batch_stats_track_latency() {
+        let mut stats = BatchStats::default();
+        stats.record(&BatchReport { messages: 10, latency_ms: 30, ..Default::default() });
+        stats.record(&BatchReport { messages: 5, failed: 1, latency_ms: 10, ..Default::default() });
+        
+        assert_eq!((stats.batches, stats.messages, stats.failed), (2, 15, 1));
+        assert_eq!((stats.last_latency_ms, stats.max_latency_ms), (10, 30));
+        assert_eq!(stats.average_latency_ms(), 20.0);
+    }
+}
@@ -0,0 +1,105 @@
+//! Broker Selection
+//!
+//! `BrokerConfig` chooses the message broker backend from configuration,
+//! e.g. the `broker` section of a `LayeredConfig`. The `backend` key names
+//! the backend and the remaining keys are its configuration:
+//!
+//! ```toml
+//! [broker]
+//! backend = "kafka"
+//! bootstrap_servers = "kafka-1:9092,kafka-2:9092"
+//! ```
+//!
+//! The `memory` backend connects the components of one process without any
+//! external infrastructure, e.g. for tests and single-node deployments. The
+//! Kafka backend is only available with the `kafka` feature.
+
+use super::*;
+use std::sync::Arc;
+use swarm_core::{MemoryBroker, MessageBroker};
+
+/// Message broker backend and its configuration
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum BrokerConfig {
+    Nats(Box<NatsConfig>),
+    /// In-process broker; only components sharing the connected broker can talk to each other
+    Memory,
+    #[cfg(feature = "kafka")]
+    Kafka(Box<KafkaConfig>),
+}
+
+impl Default for BrokerConfig {
+    fn default() -> Self {
+        Self::Nats(Box::default())
+    }
+}
+
+impl BrokerConfig {
+    /// Name of the selected backend
+    pub fn backend(&self) -> &'static str {
+        match self {
+            Self::Nats(_) => "nats",
+            Self::Memory => "memory",
+            #[cfg(feature = "kafka")]
+            Self::Kafka(_) => "kafka",
+        }
+    }
+    
+    /// Connect to the selected backend
+    pub async fn connect(&self) -> MessageResult<Arc<dyn MessageBroker>> {
+        tracing::info!("Connecting to the {} message broker", self.backend());
+        Ok(match self {
+            Self::Nats(config) => Arc::new(NatsBroker::new(config.as_ref().clone()).await?),
+            Self::Memory => Arc::new(MemoryBroker::new()),
+            #[cfg(feature = "kafka")]
+            Self::Kafka(config) => Arc::new(KafkaBroker::new(config.as_ref().clone())?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swarm_core::{LayeredConfig, BROKER_SECTION};
+    
+    #[test]
+    fn test_backend_is_selected_by_tag() {
+        let mut value = serde_json::to_value(NatsConfig { url: "nats://nats-1:4222".to_string(), ..Default::default() }).unwrap();
+        value["backend"] = serde_json::json!("nats");
+        let config: BrokerConfig = serde_json::from_value(value).unwrap();
+        assert!(matches!(&config, BrokerConfig::Nats(nats) if nats.url == "nats://nats-1:4222"));
+        assert_eq!(BrokerConfig::default().backend(), "nats");
+        
+        let memory: BrokerConfig = serde_json::from_value(serde_json::json!({"backend": "memory"})).unwrap();
+        assert_eq!(memory.backend(), "memory");
+        
+        let unknown = serde_json::from_value::<BrokerConfig>(serde_json::json!({"backend": "carrier-pigeon"}));
+        assert!(unknown.is_err());
+    }
+    
+    #[test]
+    fn test_layered_section_overrides_the_defaults() {
+        let env = |vars: &[(&str, &str)]| vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect::<Vec<_>>();
+        let layered = LayeredConfig::new().with_env_vars("SWARM", env(&[("SWARM_BROKER__URL", "nats://nats-1:4222")]));
+        let config: BrokerConfig = layered.section(BROKER_SECTION).unwrap();
+        let BrokerConfig::Nats(nats) = config else { panic!("expected the nats backend") };
+        assert_eq!(nats.url, "nats://nats-1:4222");
+        assert_eq!(nats.connection_timeout_ms, NatsConfig::default().connection_timeout_ms);
+        
+        let layered = LayeredConfig::new().with_env_vars("SWARM", env(&[("SWARM_BROKER__BACKEND", "memory")]));
+        assert_eq!(layered.section::<BrokerConfig>(BROKER_SECTION).unwrap().backend(), "memory");
+    }
+    
+    #[cfg(feature = "kafka")]
+    #[test]
+    fn test_kafka_backend_fills_in_defaults() {
+        let config: BrokerConfig = serde_json::from_value(serde_json::json!({
+            "backend": "kafka",
+            "bootstrap_servers": "kafka-1:9092",
+        })).unwrap();
+        let BrokerConfig::Kafka(kafka) = config else { panic!("expected the kafka backend") };
+        assert_eq!(kafka.bootstrap_servers, "kafka-1:9092");
+        assert_eq!(kafka.client_id, KafkaConfig::default().client_id);
+    }
+}
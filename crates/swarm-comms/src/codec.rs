@@ -0,0 +1,124 @@
+//! Message Codecs
+//!
+//! Payloads are JSON unless the sender picks another codec. MessagePack
+//! encodes the same serde types far more compactly, which matters for
+//! embedded vectors and binary document content. The codec is recorded in
+//! the `content-type` header, so receivers decode any payload transparently.
+//! Brokers send the whole `Message` as JSON, in which the payload bytes are
+//! base64, so an encoded payload costs about a third more on the wire.
+
+use swarm_core::Message;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Message header naming the codec of the payload
+pub use swarm_core::CONTENT_TYPE_HEADER;
+
+/// Encoding of a message payload
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageCodec {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl MessageCodec {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            MessageCodec::Json => "application/json",
+            MessageCodec::MessagePack => "application/msgpack",
+        }
+    }
+    
+    /// Codec for a content type, ignoring parameters such as `charset`
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        match mime.to_ascii_lowercase().as_str() {
+            "application/json" => Some(MessageCodec::Json),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => Some(MessageCodec::MessagePack),
+            _ => None,
+        }
+    }
+    
+    /// Codec a message was encoded with; messages without a content type are JSON
+    pub fn of(message: &Message) -> Result<Self> {
+        match message.headers.content_type() {
+            Some(content_type) => Self::from_content_type(content_type)
+                .ok_or_else(|| anyhow::anyhow!("unsupported content type {}", content_type)),
+            None => Ok(MessageCodec::Json),
+        }
+    }
+    
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(match self {
+            MessageCodec::Json => serde_json::to_vec(value)?,
+            // Field names are kept so `serde(default)` fields stay optional
+            MessageCodec::MessagePack => rmp_serde::to_vec_named(value)?,
+        })
+    }
+    
+    pub fn decode<T: DeserializeOwned>(&self, payload: &[u8]) -> Result<T> {
+        Ok(match self {
+            MessageCodec::Json => serde_json::from_slice(payload)?,
+            MessageCodec::MessagePack => rmp_serde::from_slice(payload)?,
+        })
+    }
+    
+    /// Decode a message payload with the codec named in its headers
+    pub fn decode_message<T: DeserializeOwned>(message: &Message) -> Result<T> {
+        Self::of(message)?.decode(&message.payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swarm_core::MessageHeaders;
+    
+    #[test]
+    fn test_content_type_lookup() {
+        assert_eq!(MessageCodec::from_content_type("application/json; charset=utf-8"), Some(MessageCodec::Json));
+        assert_eq!(MessageCodec::from_content_type("application/x-msgpack"), Some(MessageCodec::MessagePack));
+        assert_eq!(MessageCodec::from_content_type("application/protobuf"), None);
+        
+        let mut message = Message {
+            id: uuid::Uuid::new_v4(),
+            subject: "swarm.test".to_string(),
+            payload: MessageCodec::MessagePack.encode(&vec![1u32, 2, 3]).unwrap(),
+            headers: MessageHeaders::new(),
+            timestamp: chrono::Utc::now(),
+            ttl_ms: None,
+        };
+        assert!(MessageCodec::decode_message::<Vec<u32>>(&message).is_err());
+        message.headers.insert(CONTENT_TYPE_HEADER, MessageCodec::MessagePack.content_type());
+        assert_eq!(MessageCodec::decode_message::<Vec<u32>>(&message).unwrap(), vec![1, 2, 3]);
+        message.headers.insert(CONTENT_TYPE_HEADER, "text/csv".to_string());
+        assert!(MessageCodec::of(&message).unwrap_err().to_string().contains("text/csv"));
+    }
+    
+    #[test]
+    fn test_encoded_payloads_stay_compact_on_the_wire() {
+        let vector: Vec<f32> = (0..1024).map(|i| i as f32 / 7.0).collect();
+        let payload = MessageCodec::MessagePack.encode(&vector).unwrap();
+        let message = Message {
+            id: uuid::Uuid::new_v4(),
+            subject: "swarm.test".to_string(),
+            payload: payload.clone(),
+            headers: MessageHeaders::new(),
+            timestamp: chrono::Utc::now(),
+            ttl_ms: None,
+        };
+        
+        // What brokers put on the wire
+        let wire = serde_json::to_vec(&message).unwrap();
+        assert!(wire.len() < payload.len() * 4 / 3 + 256, "{} bytes for a {} byte payload", wire.len(), payload.len());
+        assert_eq!(serde_json::from_slice::<Message>(&wire).unwrap(), message);
+        
+        // Messages written with the payload as an array of numbers still decode
+        let mut legacy = serde_json::to_value(&message).unwrap();
+        legacy["payload"] = serde_json::json!(payload);
+        assert_eq!(serde_json::from_value::<Message>(legacy).unwrap().payload, payload);
+    }
+}
@@ -0,0 +1,600 @@
+//! Kafka Message Broker Implementation
+//!
+//! `KafkaBroker` implements the MessageBroker trait on Apache Kafka, for
+//! deployments that standardize on it rather than on NATS. Subjects map to
+//! topics of the same name below an optional prefix; wildcard subjects
+//! (`swarm.documents.*`, `swarm.>`) subscribe to every matching topic
+//! through a regex subscription. The subject, message ID, TTL and headers
+//! travel as Kafka record headers, so subscribers receive the same
+//! `Message` a NATS subscriber would.
+//!
+//! A plain subscription joins a consumer group of its own and receives every
+//! message, like a NATS subscription; `subscribe_queue` joins a shared
+//! consumer group, so each message is delivered to one member of the group.
+//! Requests are answered through the `_INBOX` topic, which every broker
+//! reads from its end when it sends its first request; the topic has to
+//! exist (or be auto-created) before requests can be sent.
+//!
+//! Like `NatsBroker`, it checks the configured subject permissions before
+//! publishing or subscribing, and drops or dead-letters messages received
+//! after their TTL.
+
+use super::*;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::message::{Header, Headers, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::{ClientConfig, Offset, TopicPartitionList};
+use std::sync::Arc;
+use std::time::Duration;
+use swarm_core::{Message, MessageBroker, MessageBrokerStats, MessageHeaders, MessageSubscription, Subject, REPLY_TO_HEADER};
+use tokio::sync::{oneshot, Mutex, OnceCell, RwLock};
+use uuid::Uuid;
+use chrono::Utc;
+use async_trait::async_trait;
+
+/// Record header carrying the subject a message was published to
+pub const KAFKA_SUBJECT_HEADER: &str = "swarm-subject";
+
+/// Record header carrying the message ID
+pub const KAFKA_MESSAGE_ID_HEADER: &str = "swarm-message-id";
+
+/// Record header carrying the message TTL in milliseconds
+pub const KAFKA_TTL_HEADER: &str = "swarm-ttl-ms";
+
+/// Topic, below the prefix, that replies to requests are published to
+pub const KAFKA_INBOX_TOPIC: &str = "_INBOX";
+
+/// Kafka connection configuration
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct KafkaConfig {
+    /// Comma-separated `host:port` list of bootstrap brokers
+    pub bootstrap_servers: String,
+    
+    /// Client ID reported to the brokers, also prefixing the consumer groups of plain subscriptions
+    pub client_id: String,
+    
+    /// Prefix of every topic, e.g. `staging.`
+    pub topic_prefix: String,
+    
+    /// How long a publish may wait for the brokers to acknowledge it in milliseconds
+    pub message_timeout_ms: u64,
+    
+    /// Consumer group session timeout in milliseconds
+    pub session_timeout_ms: u64,
+    
+    /// Maximum message size in bytes
+    pub max_message_size: usize,
+    
+    /// Further librdkafka properties (security protocol, SASL, ...) set on every client
+    pub properties: HashMap<String, String>,
+    
+    /// Subject permissions granted to this component, checked before use
+    pub permissions: Option<SubjectPermissions>,
+    
+    /// What to do with messages received after their TTL
+    pub expired_messages: ExpiredMessagePolicy,
+}
+
+impl Default for KafkaConfig {
+    fn default() -> Self {
+        Self {
+            bootstrap_servers: "localhost:9092".to_string(),
+            client_id: "aprio-swarm".to_string(),
+            topic_prefix: String::new(),
+            message_timeout_ms: 5000,
+            session_timeout_ms: 10_000,
+            max_message_size: 1024 * 1024, // 1MB
+            properties: HashMap::new(),
+            permissions: None,
+            expired_messages: ExpiredMessagePolicy::default(),
+        }
+    }
+}
+
+impl KafkaConfig {
+    /// Topic that messages published to `subject` are written to
+    ///
+    /// Characters Kafka does not allow in topic names are replaced with `_`,
+    /// and all inbox subjects share the inbox topic.
+    pub fn topic_for_subject(&self, subject: &str) -> String {
+        let subject = if subject.starts_with("_INBOX.") { KAFKA_INBOX_TOPIC } else { subject };
+        let topic: String = subject.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') { c } else { '_' })
+            .collect();
+        format!("{}{}", self.topic_prefix, topic)
+    }
+    
+    /// Topic subscription for `subject`: its topic, or a regex for a wildcard subject
+    pub fn topic_subscription(&self, subject: &str) -> String {
+        if !subject.split('.').any(|token| token == "*" || token == ">") {
+            return self.topic_for_subject(subject);
+        }
+        let pattern = subject.split('.')
+            .map(|token| match token {
+                "*" => "[^.]+".to_string(),
+                ">" => ".+".to_string(),
+                token => self.topic_for_subject(token).trim_start_matches(self.topic_prefix.as_str()).replace('.', "\\."),
+            })
+            .collect::<Vec<_>>()
+            .join("\\.");
+        format!("^{}{}$", self.topic_prefix.replace('.', "\\."), pattern)
+    }
+    
+    fn client_config(&self) -> ClientConfig {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", &self.bootstrap_servers)
+            .set("client.id", &self.client_id)
+            .set("message.timeout.ms", self.message_timeout_ms.to_string())
+            .set("message.max.bytes", self.max_message_size.max(1000).to_string());
+        for (key, value) in &self.properties {
+            config.set(key, value);
+        }
+        config
+    }
+    
+    fn consumer(&self, group: &str) -> MessageResult<StreamConsumer> {
+        self.client_config()
+            .remove("message.timeout.ms")
+            .set("group.id", group)
+            .set("session.timeout.ms", self.session_timeout_ms.to_string())
+            .set("auto.offset.reset", "latest")
+            .set("enable.auto.commit", "true")
+            .create()
+            .map_err(|e| MessageError::Connection { message: format!("Failed to create Kafka consumer: {}", e) })
+    }
+}
+
+type PendingReplies = Arc<Mutex<HashMap<String, oneshot::Sender<Message>>>>;
+
+/// Kafka message broker implementation
+pub struct KafkaBroker {
+    config: KafkaConfig,
+    producer: FutureProducer,
+    stats: Arc<RwLock<BrokerStats>>,
+    subject_counters: Arc<RwLock<HashMap<String, Arc<SubjectCounters>>>>,
+    pending_replies: PendingReplies,
+    inbox: OnceCell<()>,
+}
+
+impl KafkaBroker {
+    /// Create a broker; connections to the brokers are made when first used
+    pub fn new(config: KafkaConfig) -> MessageResult<Self> {
+        let producer = config.client_config()
+            .create()
+            .map_err(|e| MessageError::Connection { message: format!("Failed to create Kafka producer: {}", e) })?;
+        tracing::info!("Created Kafka broker for {}", config.bootstrap_servers);
+        
+        Ok(Self {
+            config,
+            producer,
+            stats: Arc::new(RwLock::new(BrokerStats::default())),
+            subject_counters: Arc::new(RwLock::new(HashMap::new())),
+            pending_replies: Arc::new(Mutex::new(HashMap::new())),
+            inbox: OnceCell::new(),
+        })
+    }
+    
+    pub fn config(&self) -> &KafkaConfig {
+        &self.config
+    }
+    
+    /// Get broker statistics
+    pub async fn get_stats(&self) -> BrokerStats {
+        let mut stats = self.stats.read().await.clone();
+        stats.subjects = self.subject_stats().await;
+        stats
+    }
+    
+    /// Export current statistics to a metrics collector
+    pub async fn export_metrics(&self, collector: &dyn swarm_core::MetricsCollector) {
+        self.get_stats().await.export_metrics(collector, "kafka");
+    }
+    
+    /// Counters and consumer lag per subject
+    pub async fn subject_stats(&self) -> HashMap<String, SubjectStats> {
+        let counters = self.subject_counters.read().await;
+        counters.iter().map(|(subject, counters)| (subject.clone(), counters.snapshot())).collect()
+    }
+    
+    async fn counters_for(&self, subject: &str) -> Arc<SubjectCounters> {
+        if let Some(counters) = self.subject_counters.read().await.get(subject) {
+            return counters.clone();
+        }
+        self.subject_counters.write().await.entry(subject.to_string()).or_default().clone()
+    }
+    
+    /// Check that the configured permissions allow an operation on a subject
+    fn check_permission(&self, operation: SubjectOperation, subject: &str) -> MessageResult<()> {
+        match &self.config.permissions {
+            Some(permissions) => permissions.check(operation, subject),
+            None => Ok(()),
+        }
+    }
+    
+    /// Check every subject `role` uses against the configured permissions
+    ///
+    /// Call at startup to fail fast on a misconfigured deployment.
+    pub fn authorize(&self, router: &MessageRouter, role: ComponentRole) -> MessageResult<()> {
+        match &self.config.permissions {
+            Some(permissions) => router.authorize(role, permissions),
+            None => Ok(()),
+        }
+    }
+    
+    /// Publish a message to the topic of `subject`
+    pub async fn publish_message(&self, subject: &Subject, message: &Message) -> MessageResult<()> {
+        self.check_permission(SubjectOperation::Publish, subject.as_str())?;
+        let counters = self.counters_for(subject.as_str()).await;
+        if message.payload.len() > self.config.max_message_size {
+            counters.record_error();
+            self.stats.write().await.error_count += 1;
+            return Err(MessageError::MessageTooLarge { size: message.payload.len(), max_size: self.config.max_message_size });
+        }
+        
+        if let Err(e) = send_record(&self.producer, &self.config, subject.as_str(), message).await {
+            counters.record_error();
+            self.stats.write().await.error_count += 1;
+            return Err(e);
+        }
+        
+        counters.record_sent();
+        self.stats.write().await.messages_sent += 1;
+        Ok(())
+    }
+    
+    /// Publish a request and wait up to `timeout` for its reply
+    pub async fn request_message(&self, subject: &Subject, message: &Message, timeout: Duration) -> MessageResult<Message> {
+        self.inbox.get_or_try_init(|| self.read_inbox()).await?;
+        
+        let reply_to = format!("_INBOX.{}", Uuid::new_v4().simple());
+        let (tx, rx) = oneshot::channel();
+        self.pending_replies.lock().await.insert(reply_to.clone(), tx);
+        let mut request = message.clone();
+        request.headers.insert(REPLY_TO_HEADER, reply_to.clone());
+        
+        let reply = match self.publish_message(subject, &request).await {
+            Ok(()) => tokio::time::timeout(timeout, rx).await,
+            Err(e) => {
+                self.pending_replies.lock().await.remove(&reply_to);
+                return Err(e);
+            }
+        };
+        match reply {
+            Ok(Ok(reply)) => Ok(reply),
+            _ => {
+                self.pending_replies.lock().await.remove(&reply_to);
+                Err(MessageError::Timeout { message: format!("Request to {} timed out after {:?}", subject, timeout) })
+            }
+        }
+    }
+    
+    /// Start reading replies, from the current end of every inbox partition
+    ///
+    /// The partitions are assigned at their high watermarks rather than
+    /// joined through a consumer group, so no reply published after this
+    /// returns can be missed while the group rebalances.
+    async fn read_inbox(&self) -> MessageResult<()> {
+        let group = format!("{}.inbox.{}", self.config.client_id, Uuid::new_v4().simple());
+        let consumer = self.config.consumer(&group)?;
+        let topic = self.config.topic_for_subject(KAFKA_INBOX_TOPIC);
+        let timeout = Duration::from_millis(self.config.message_timeout_ms);
+        
+        let metadata = consumer.fetch_metadata(Some(&topic), timeout)
+            .map_err(|e| MessageError::Connection { message: format!("Failed to read metadata of {}: {}", topic, e) })?;
+        let mut assignment = TopicPartitionList::new();
+        for partition in metadata.topics().iter().flat_map(|topic| topic.partitions()) {
+            let (_, high) = consumer.fetch_watermarks(&topic, partition.id(), timeout)
+                .map_err(|e| MessageError::Connection { message: format!("Failed to read offsets of {}: {}", topic, e) })?;
+            assignment.add_partition_offset(&topic, partition.id(), Offset::Offset(high))
+                .map_err(|e| MessageError::Subscription { message: e.to_string() })?;
+        }
+        if assignment.count() == 0 {
+            return Err(MessageError::Connection { message: format!("Kafka inbox topic {} has no partitions; create it to send requests", topic) });
+        }
+        consumer.assign(&assignment)
+            .map_err(|e| MessageError::Subscription { message: format!("Failed to assign {}: {}", topic, e) })?;
+        
+        let pending_replies = self.pending_replies.clone();
+        tokio::spawn(async move {
+            loop {
+                let reply = match consumer.recv().await {
+                    Ok(record) => decode_record(&record, ""),
+                    Err(e) => {
+                        tracing::warn!("Failed to read the Kafka inbox: {}", e);
+                        continue;
+                    }
+                };
+                if let Some(tx) = pending_replies.lock().await.remove(&reply.subject) {
+                    let _ = tx.send(reply);
+                }
+            }
+        });
+        tracing::info!("Reading replies from Kafka topic {}", topic);
+        Ok(())
+    }
+    
+    /// Subscribe to a subject; the subscription receives every message published to it
+    pub async fn subscribe_to_subject(&self, subject: &Subject) -> MessageResult<BrokerSubscription> {
+        let group = format!("{}.{}", self.config.client_id, Uuid::new_v4().simple());
+        self.subscribe_in_group(subject, &group).await
+    }
+    
+    /// Subscribe to a subject as a member of the consumer group `group`
+    ///
+    /// Each message is delivered to exactly one member of `group`, so a pool
+    /// of workers subscribed with the same group shares the load.
+    pub async fn subscribe_queue(&self, subject: &Subject, group: &str) -> MessageResult<BrokerSubscription> {
+        self.subscribe_in_group(subject, group).await
+    }
+    
+    async fn subscribe_in_group(&self, subject: &Subject, group: &str) -> MessageResult<BrokerSubscription> {
+        self.check_permission(SubjectOperation::Subscribe, subject.as_str())?;
+        let subject = subject.as_str().to_string();
+        let consumer = self.config.consumer(group)?;
+        let topics = self.config.topic_subscription(&subject);
+        consumer.subscribe(&[&topics])
+            .map_err(|e| MessageError::Subscription { message: format!("Failed to subscribe to {}: {}", topics, e) })?;
+        
+        let (tx, rx) = mpsc::unbounded_channel();
+        let counters = self.counters_for(&subject).await;
+        let stats = self.stats.clone();
+        let producer = self.producer.clone();
+        let config = self.config.clone();
+        let pattern = subject.clone();
+        let forwarded = counters.clone();
+        tokio::spawn(async move {
+            loop {
+                let record = tokio::select! {
+                    _ = tx.closed() => break,
+                    record = consumer.recv() => record,
+                };
+                let message = match record {
+                    Ok(record) => decode_record(&record, &config.topic_prefix),
+                    Err(e) => {
+                        tracing::warn!("Failed to read from Kafka subscription {}: {}", pattern, e);
+                        forwarded.record_error();
+                        stats.write().await.error_count += 1;
+                        continue;
+                    }
+                };
+                // Topic names lose characters, so wildcard matches are checked on the subject itself
+                if !subject_matches(&pattern, &message.subject) {
+                    continue;
+                }
+                if MessageValidator::is_expired_at(&message, Utc::now()) {
+                    expire(&producer, &config, &stats, message).await;
+                    continue;
+                }
+                forwarded.record_delivered();
+                if tx.send(message).is_err() {
+                    forwarded.record_undelivered();
+                    break;
+                }
+                stats.write().await.messages_received += 1;
+            }
+            stats.write().await.active_subscriptions -= 1;
+            tracing::debug!("Kafka subscription {} closed", pattern);
+        });
+        
+        self.stats.write().await.active_subscriptions += 1;
+        tracing::info!("Subscribed to {} in consumer group {}", topics, group);
+        Ok(BrokerSubscription::with_counters(subject, rx, counters))
+    }
+    
+    /// Wait for outstanding publishes to be delivered
+    pub async fn flush(&self) -> MessageResult<()> {
+        let producer = self.producer.clone();
+        let timeout = Duration::from_millis(self.config.message_timeout_ms);
+        tokio::task::spawn_blocking(move || producer.flush(timeout))
+            .await
+            .map_err(|e| MessageError::General(e.into()))?
+            .map_err(|e| MessageError::Connection { message: format!("Failed to flush Kafka producer: {}", e) })
+    }
+}
+
+/// Write a message to the topic of `subject`
+async fn send_record(producer: &FutureProducer, config: &KafkaConfig, subject: &str, message: &Message) -> MessageResult<()> {
+    let topic = config.topic_for_subject(subject);
+    let key = message.id.to_string();
+    let record = FutureRecord::to(&topic)
+        .key(&key)
+        .payload(&message.payload)
+        .headers(record_headers(subject, message))
+        .timestamp(message.timestamp.timestamp_millis());
+    let timeout = Duration::from_millis(config.message_timeout_ms);
+    producer.send(record, timeout).await
+        .map_err(|(e, _)| MessageError::Connection { message: format!("Failed to publish to {}: {}", topic, e) })?;
+    tracing::debug!("Published message {} to topic {}", message.id, topic);
+    Ok(())
+}
+
+/// Drop or dead-letter a message received after its TTL
+async fn expire(producer: &FutureProducer, config: &KafkaConfig, stats: &RwLock<BrokerStats>, message: Message) {
+    stats.write().await.expired_count += 1;
+    tracing::debug!("Message {} on {} expired, {:?}", message.id, message.subject, config.expired_messages);
+    if config.expired_messages == ExpiredMessagePolicy::Drop {
+        return;
+    }
+    
+    let dead_letter = expired_dead_letter(message, Utc::now());
+    if let Err(e) = send_record(producer, config, EXPIRED_MESSAGE_SUBJECT, &dead_letter).await {
+        tracing::error!("Failed to dead-letter expired message {}: {}", dead_letter.id, e);
+        stats.write().await.error_count += 1;
+    }
+}
+
+/// Record headers carrying a message's subject, ID, TTL and headers
+fn record_headers(subject: &str, message: &Message) -> OwnedHeaders {
+    let message_id = message.id.to_string();
+    let ttl_ms = message.ttl_ms.map(|ttl_ms| ttl_ms.to_string());
+    let mut headers = OwnedHeaders::new()
+        .insert(Header { key: KAFKA_SUBJECT_HEADER, value: Some(subject) })
+        .insert(Header { key: KAFKA_MESSAGE_ID_HEADER, value: Some(&message_id) });
+    if let Some(ttl_ms) = &ttl_ms {
+        headers = headers.insert(Header { key: KAFKA_TTL_HEADER, value: Some(ttl_ms) });
+    }
+    for (name, value) in message.headers.iter() {
+        headers = headers.insert(Header { key: name, value: Some(value) });
+    }
+    headers
+}
+
+/// The message a record carries; records without swarm headers get their subject from the topic
+fn decode_record(record: &impl rdkafka::Message, topic_prefix: &str) -> Message {
+    let mut message = Message {
+        id: Uuid::new_v4(),
+        subject: record.topic().strip_prefix(topic_prefix).unwrap_or(record.topic()).to_string(),
+        payload: record.payload().unwrap_or_default().to_vec(),
+        headers: MessageHeaders::new(),
+        timestamp: record.timestamp().to_millis().and_then(chrono::DateTime::from_timestamp_millis).unwrap_or_else(Utc::now),
+        ttl_ms: None,
+    };
+    for header in record.headers().into_iter().flat_map(|headers| headers.iter()) {
+        let value = String::from_utf8_lossy(header.value.unwrap_or_default()).into_owned();
+        match header.key {
+            KAFKA_SUBJECT_HEADER => message.subject = value,
+            KAFKA_MESSAGE_ID_HEADER => message.id = value.parse().unwrap_or(message.id),
+            KAFKA_TTL_HEADER => message.ttl_ms = value.parse().ok(),
+            name => {
+                message.headers.insert(name.to_string(), value);
+            }
+        }
+    }
+    message
+}
+
+#[async_trait]
+impl MessageBroker for KafkaBroker {
+    async fn publish(&self, subject: &str, message: &[u8]) -> Result<()> {
+        let message = Message {
+            id: Uuid::new_v4(),
+            subject: subject.to_string(),
+            payload: message.to_vec(),
+            headers: MessageHeaders::new(),
+            timestamp: Utc::now(),
+            ttl_ms: None,
+        };
+        
+        self.publish_message(&Subject::parse(subject)?, &message).await?;
+        Ok(())
+    }
+    
+    async fn request(&self, subject: &str, message: &[u8], timeout: Duration) -> Result<Message> {
+        let message = Message {
+            id: Uuid::new_v4(),
+            subject: subject.to_string(),
+            payload: message.to_vec(),
+            headers: MessageHeaders::new(),
+            timestamp: Utc::now(),
+            ttl_ms: None,
+        };
+        
+        Ok(self.request_message(&Subject::parse(subject)?, &message, timeout).await?)
+    }
+    
+    async fn subscribe(&self, subject: &str) -> Result<Box<dyn MessageSubscription>> {
+        let subscription = self.subscribe_to_subject(&Subject::parse(subject)?).await?;
+        Ok(Box::new(subscription))
+    }
+    
+    async fn get_stats(&self) -> MessageBrokerStats {
+        let stats = self.get_stats().await;
+        MessageBrokerStats {
+            total_messages_sent: stats.messages_sent,
+            total_messages_received: stats.messages_received,
+            active_subscriptions: stats.active_subscriptions,
+            queue_depth: stats.subjects.values().map(|subject| subject.pending as usize).sum(),
+            error_count: stats.error_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdkafka::message::{OwnedMessage, Timestamp};
+    
+    #[test]
+    fn test_subjects_map_to_topics() {
+        let config = KafkaConfig { topic_prefix: "staging.".to_string(), ..Default::default() };
+        assert_eq!(config.topic_for_subject("swarm.documents.incoming"), "staging.swarm.documents.incoming");
+        assert_eq!(config.topic_for_subject("swarm.results.worker:1"), "staging.swarm.results.worker_1");
+        assert_eq!(config.topic_for_subject("_INBOX.4f2a"), "staging._INBOX");
+        
+        assert_eq!(config.topic_subscription("swarm.documents.incoming"), "staging.swarm.documents.incoming");
+        assert_eq!(config.topic_subscription("swarm.documents.*"), "^staging\\.swarm\\.documents\\.[^.]+$");
+        assert_eq!(config.topic_subscription("swarm.>"), "^staging\\.swarm\\..+$");
+    }
+    
+    #[test]
+    fn test_messages_round_trip_through_record_headers() {
+        let mut message = Message {
+            id: Uuid::new_v4(),
+            subject: "swarm.results.worker:1".to_string(),
+            payload: b"{\"ok\":true}".to_vec(),
+            headers: MessageHeaders::new(),
+            timestamp: chrono::DateTime::from_timestamp_millis(1_700_000_000_000).unwrap(),
+            ttl_ms: Some(30_000),
+        };
+        message.headers.insert(REPLY_TO_HEADER, "_INBOX.4f2a");
+        
+        let record = OwnedMessage::new(
+            Some(message.payload.clone()),
+            Some(message.id.to_string().into_bytes()),
+            "staging.swarm.results.worker_1".to_string(),
+            Timestamp::CreateTime(message.timestamp.timestamp_millis()),
+            0,
+            42,
+            Some(record_headers(&message.subject, &message)),
+        );
+        assert_eq!(decode_record(&record, "staging."), message);
+        
+        let bare = OwnedMessage::new(Some(b"raw".to_vec()), None, "staging.swarm.events".to_string(), Timestamp::NotAvailable, 0, 0, None);
+        let decoded = decode_record(&bare, "staging.");
+        assert_eq!((decoded.subject.as_str(), decoded.payload.as_slice()), ("swarm.events", b"raw".as_slice()));
+    }
+    
+    #[tokio::test]
+    async fn test_permissions_are_checked_before_use() {
+        let permissions = SubjectPermissions {
+            publish: PermissionRules { allow: vec!["swarm.results.>".to_string()], deny: Vec::new() },
+            subscribe: PermissionRules { allow: vec!["swarm.documents.*".to_string()], deny: Vec::new() },
+        };
+        let broker = KafkaBroker::new(KafkaConfig { permissions: Some(permissions), ..Default::default() }).unwrap();
+        let message = Message {
+            id: Uuid::new_v4(),
+            subject: "swarm.documents.incoming".to_string(),
+            payload: b"{}".to_vec(),
+            headers: MessageHeaders::new(),
+            timestamp: Utc::now(),
+            ttl_ms: None,
+        };
+        
+        let published = broker.publish_message(&Subject::parse("swarm.documents.incoming").unwrap(), &message).await;
+        assert!(matches!(published, Err(MessageError::PermissionDenied { operation: SubjectOperation::Publish, .. })));
+        let subscribed = broker.subscribe_to_subject(&Subject::parse("swarm.results.worker").unwrap()).await;
+        assert!(matches!(subscribed, Err(MessageError::PermissionDenied { operation: SubjectOperation::Subscribe, .. })));
+        assert_eq!(broker.get_stats().await.messages_sent, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_expired_messages_are_counted_and_dropped() {
+        let config = KafkaConfig::default();
+        let producer: FutureProducer = config.client_config().create().unwrap();
+        let stats = RwLock::new(BrokerStats::default());
+        let message = Message {
+            id: Uuid::new_v4(),
+            subject: "swarm.documents.incoming".to_string(),
+            payload: Vec::new(),
+            headers: MessageHeaders::new(),
+            timestamp: Utc::now() - chrono::Duration::seconds(60),
+            ttl_ms: Some(1000),
+        };
+        assert!(MessageValidator::is_expired_at(&message, Utc::now()));
+        
+        expire(&producer, &config, &stats, message).await;
+        let stats = stats.read().await;
+        assert_eq!((stats.expired_count, stats.error_count), (1, 0));
+    }
+}
@@ -3,23 +3,43 @@
 //! This crate provides concrete implementations of messaging capabilities
 //! for the Aprio Swarm system using NATS as the message broker.
 
-use swarm_core::prelude::*;
 use anyhow::Result;
-use async_trait::async_trait;
 use std::collections::HashMap;
 use tokio::sync::mpsc;
-use uuid::Uuid;
-use chrono::Utc;
 
 // Core modules
+pub mod admin;
+pub mod authorization;
+pub mod batch;
+pub mod broker_config;
+pub mod codec;
+#[cfg(feature = "kafka")]
+pub mod kafka_broker;
 pub mod nats_broker;
+pub mod nats_coordinator;
 pub mod message_subscription;
 pub mod message_serialization;
+pub mod result_publisher;
+pub mod pipelined_publisher;
+pub mod watchdog;
+pub mod subscription_liveness;
 
 // Re-export main components
+pub use admin::*;
+pub use authorization::*;
+pub use batch::*;
+pub use broker_config::*;
+pub use codec::*;
+#[cfg(feature = "kafka")]
+pub use kafka_broker::*;
 pub use nats_broker::*;
+pub use nats_coordinator::*;
 pub use message_subscription::*;
 pub use message_serialization::*;
+pub use result_publisher::*;
+pub use pipelined_publisher::*;
+pub use watchdog::*;
+pub use subscription_liveness::*;
 
 /// NATS connection configuration
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -30,12 +50,16 @@ pub struct NatsConfig {
     /// Connection timeout in milliseconds
     pub connection_timeout_ms: u64,
     
-    /// Maximum number of reconnection attempts
+    /// Maximum number of attempts to re-create a subscription whose stream ended
     pub max_reconnect_attempts: u32,
     
-    /// Reconnection delay in milliseconds
+    /// Delay before the first reconnection attempt in milliseconds, doubled on every further attempt
     pub reconnect_delay_ms: u64,
     
+    /// Upper bound of the reconnection delay in milliseconds
+    #[serde(default = "default_max_reconnect_delay_ms")]
+    pub max_reconnect_delay_ms: u64,
+    
     /// Maximum message size in bytes
     pub max_message_size: usize,
     
@@ -50,6 +74,60 @@ pub struct NatsConfig {
     
     /// TLS CA path (if TLS enabled)
     pub tls_ca_path: Option<String>,
+    
+    /// Pipelined publishing (in-flight limit, flushing, JetStream acks)
+    #[serde(default)]
+    pub publisher: PublisherConfig,
+    
+    /// Whether `publish_batch` pipelines messages or wraps them in an envelope
+    #[serde(default)]
+    pub batch_mode: BatchMode,
+    
+    /// Tracing sampling for messages without a propagated decision
+    #[serde(default)]
+    pub tracing_sampling: swarm_core::SamplingConfig,
+    
+    /// Credentials of this component (user/password, token, NKey or `.creds` file)
+    #[serde(default)]
+    pub credentials: Option<NatsCredentials>,
+    
+    /// Subject permissions granted to these credentials, checked before use
+    #[serde(default)]
+    pub permissions: Option<SubjectPermissions>,
+    
+    /// What subscriptions do with messages received after their TTL
+    #[serde(default)]
+    pub expired_messages: ExpiredMessagePolicy,
+    
+    /// Detection and restart of dead or hung subscription tasks
+    #[serde(default)]
+    pub subscription_liveness: SubscriptionLivenessConfig,
+}
+
+/// Subject expired messages are dead-lettered to
+pub const EXPIRED_MESSAGE_SUBJECT: &str = "swarm.messages.expired";
+
+/// Header recording when a dead-lettered message was found expired
+pub const EXPIRED_AT_HEADER: &str = "expired-at";
+
+/// Handling of messages received after their TTL
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiredMessagePolicy {
+    /// Discard the message
+    #[default]
+    Drop,
+    /// Republish the message to `EXPIRED_MESSAGE_SUBJECT`
+    DeadLetter,
+}
+
+/// Expired message as republished to `EXPIRED_MESSAGE_SUBJECT`
+///
+/// The TTL is cleared so the dead letter itself never expires.
+pub(crate) fn expired_dead_letter(mut message: swarm_core::Message, now: chrono::DateTime<chrono::Utc>) -> swarm_core::Message {
+    message.headers.insert(EXPIRED_AT_HEADER, now.to_rfc3339());
+    message.ttl_ms = None;
+    message
 }
 
 impl Default for NatsConfig {
@@ -59,18 +137,42 @@ impl Default for NatsConfig {
             connection_timeout_ms: 5000,
             max_reconnect_attempts: 10,
             reconnect_delay_ms: 1000,
+            max_reconnect_delay_ms: default_max_reconnect_delay_ms(),
             max_message_size: 1024 * 1024, // 1MB
             enable_tls: false,
             tls_cert_path: None,
             tls_key_path: None,
             tls_ca_path: None,
+            publisher: PublisherConfig::default(),
+            batch_mode: BatchMode::default(),
+            tracing_sampling: swarm_core::SamplingConfig::default(),
+            credentials: None,
+            permissions: None,
+            expired_messages: ExpiredMessagePolicy::default(),
+            subscription_liveness: SubscriptionLivenessConfig::default(),
         }
     }
 }
 
-/// NATS connection statistics
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct NatsStats {
+fn default_max_reconnect_delay_ms() -> u64 {
+    30_000
+}
+
+impl NatsConfig {
+    /// Delay before reconnection attempt `attempt` (counting from 1)
+    pub fn reconnect_delay(&self, attempt: usize) -> std::time::Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as u32;
+        let delay_ms = self.reconnect_delay_ms.saturating_mul(1u64 << exponent);
+        std::time::Duration::from_millis(delay_ms.min(self.max_reconnect_delay_ms))
+    }
+}
+
+/// Message broker statistics
+///
+/// Shared by the NATS and Kafka brokers; the connection fields are only
+/// tracked by NATS.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct BrokerStats {
     /// Total messages sent
     pub messages_sent: u64,
     
@@ -94,19 +196,84 @@ pub struct NatsStats {
     
     /// Error count
     pub error_count: u64,
+    
+    /// Messages received after their TTL and not delivered
+    #[serde(default)]
+    pub expired_count: u64,
+    
+    /// Subscriptions restarted after their forwarding task died or hung
+    #[serde(default)]
+    pub resubscription_count: u64,
+    
+    /// Per-subject counters, keyed by subject (or subscription pattern)
+    #[serde(default)]
+    pub subjects: HashMap<String, SubjectStats>,
+    
+    /// Pipelined publisher counters
+    #[serde(default)]
+    pub publisher: PublisherStats,
+    
+    /// Batch publishing counters and latency
+    #[serde(default)]
+    pub batches: BatchStats,
+}
+
+/// Per-subject message counters and consumer lag
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SubjectStats {
+    /// Messages published to this subject
+    pub messages_sent: u64,
+    
+    /// Messages delivered to the subscription on this subject
+    pub messages_received: u64,
+    
+    /// Publish, delivery and deserialization errors
+    pub error_count: u64,
+    
+    /// Messages delivered but not yet consumed
+    pub pending: u64,
+    
+    /// Age of the most recently consumed message in milliseconds
+    pub last_lag_ms: u64,
+    
+    /// Largest observed consumer lag in milliseconds
+    pub max_lag_ms: u64,
+    
+    /// When the last message was delivered to the subscription
+    #[serde(default)]
+    pub last_message_at: Option<chrono::DateTime<chrono::Utc>>,
+    
+    /// Times the subscription was restarted after its forwarding task died or hung
+    #[serde(default)]
+    pub restarts: u64,
 }
 
-impl Default for NatsStats {
-    fn default() -> Self {
-        Self {
-            messages_sent: 0,
-            messages_received: 0,
-            active_subscriptions: 0,
-            is_connected: false,
-            last_connected: None,
-            last_disconnected: None,
-            reconnection_count: 0,
-            error_count: 0,
+impl BrokerStats {
+    /// Export global and per-subject counters to a metrics collector
+    ///
+    /// Metric names start with `backend`, e.g. `nats.messages_sent`.
+    pub fn export_metrics(&self, collector: &dyn swarm_core::MetricsCollector, backend: &str) {
+        let metric = |name: &str| format!("{}.{}", backend, name);
+        collector.record_metric(&metric("messages_sent"), self.messages_sent as f64, &[]);
+        collector.record_metric(&metric("messages_received"), self.messages_received as f64, &[]);
+        collector.record_metric(&metric("active_subscriptions"), self.active_subscriptions as f64, &[]);
+        collector.record_metric(&metric("error_count"), self.error_count as f64, &[]);
+        collector.record_metric(&metric("expired_count"), self.expired_count as f64, &[]);
+        collector.record_metric(&metric("resubscription_count"), self.resubscription_count as f64, &[]);
+        collector.record_metric(&metric("publisher.pending"), self.publisher.pending as f64, &[]);
+        collector.record_metric(&metric("batch.count"), self.batches.batches as f64, &[]);
+        collector.record_metric(&metric("batch.last_latency_ms"), self.batches.last_latency_ms as f64, &[]);
+        collector.record_metric(&metric("batch.average_latency_ms"), self.batches.average_latency_ms(), &[]);
+        
+        for (subject, stats) in &self.subjects {
+            let tags = [("subject", subject.as_str())];
+            collector.record_metric(&metric("subject.messages_sent"), stats.messages_sent as f64, &tags);
+            collector.record_metric(&metric("subject.messages_received"), stats.messages_received as f64, &tags);
+            collector.record_metric(&metric("subject.error_count"), stats.error_count as f64, &tags);
+            collector.record_metric(&metric("subject.pending"), stats.pending as f64, &tags);
+            collector.record_metric(&metric("subject.lag_ms"), stats.last_lag_ms as f64, &tags);
+            collector.record_metric(&metric("subject.max_lag_ms"), stats.max_lag_ms as f64, &tags);
+            collector.record_metric(&metric("subject.restarts"), stats.restarts as f64, &tags);
         }
     }
 }
@@ -134,6 +301,15 @@ pub enum MessageError {
     
     #[error("Timeout error: {message}")]
     Timeout { message: String },
+    
+    #[error("Not permitted to {operation} on {subject}")]
+    PermissionDenied { operation: SubjectOperation, subject: String },
+    
+    #[error("Authorization error: {0}")]
+    Unauthorized(AuthorizationReport),
+    
+    #[error("Invalid subject: {0}")]
+    InvalidSubject(#[from] swarm_core::SubjectError),
 }
 
 /// Result type for messaging operations
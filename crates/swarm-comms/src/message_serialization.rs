@@ -1,10 +1,11 @@
 //! Message Serialization Utilities
 //!
 //! This module provides utilities for serializing and deserializing
-//! messages for NATS communication.
+//! messages for NATS communication. Payloads are JSON unless a
+//! `MessageCodec` is given; deserialization follows the `content-type` header.
 
-use super::*;
-use swarm_core::{Document, DocumentType, DocumentContent, Task, TaskType, TaskPriority, TaskStatus, TaskPayload, TaskResult, WorkerStatus, Message, DocumentProcessingType, DocumentProcessingOptions};
+use swarm_core::{ClaimCheck, DeadLetter, Document, Task, TaskPayload, TaskResult, WorkerStatus, Message, MessageHeaders, SamplingDecision, SchemaKind, TraceContext, DEAD_LETTER_SUBJECT, TRACE_SAMPLED_HEADER};
+use crate::codec::{MessageCodec, CONTENT_TYPE_HEADER};
 use anyhow::Result;
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -13,20 +14,49 @@ use chrono::Utc;
 /// Message serialization utilities
 pub struct MessageSerializer;
 
+/// Carry a tracing sampling decision made upstream in the message headers
+fn propagate_sampling(headers: &mut MessageHeaders, decision: Option<SamplingDecision>) {
+    if let Some(decision) = decision {
+        headers.insert(TRACE_SAMPLED_HEADER, decision.as_str());
+    }
+}
+
+/// Carry the trace context of the latest stage in the message headers
+fn propagate_trace(headers: &mut MessageHeaders, context: Option<TraceContext>) {
+    if let Some(context) = context {
+        context.inject(headers);
+    }
+}
+
+/// Continue downstream from the trace context a message was published with
+fn extract_trace(message: &Message, metadata: &mut HashMap<String, serde_json::Value>) {
+    if let Some(context) = TraceContext::from_headers(&message.headers) {
+        context.record(metadata);
+    }
+}
+
 impl MessageSerializer {
     /// Serialize a document to a message
     pub fn serialize_document(document: &Document) -> Result<Message> {
-        let payload = serde_json::to_vec(document)?;
+        Self::serialize_document_with(document, MessageCodec::Json)
+    }
+    
+    /// Serialize a document to a message with the given codec
+    pub fn serialize_document_with(document: &Document, codec: MessageCodec) -> Result<Message> {
+        let payload = codec.encode(document)?;
         
         Ok(Message {
             id: Uuid::new_v4(),
             subject: "swarm.documents.incoming".to_string(),
             payload,
             headers: {
-                let mut headers = HashMap::new();
-                headers.insert("content-type".to_string(), "application/json".to_string());
-                headers.insert("document-type".to_string(), format!("{:?}", document.document_type));
-                headers.insert("document-id".to_string(), document.id.to_string());
+                let mut headers = MessageHeaders::new();
+                headers.insert(CONTENT_TYPE_HEADER, codec.content_type());
+                headers.insert("document-type", format!("{:?}", document.document_type));
+                headers.insert("document-id", document.id.to_string());
+                headers.extend(SchemaKind::Document.headers());
+                propagate_sampling(&mut headers, SamplingDecision::from_metadata(&document.metadata));
+                propagate_trace(&mut headers, TraceContext::from_metadata(&document.metadata));
                 headers
             },
             timestamp: Utc::now(),
@@ -36,24 +66,57 @@ impl MessageSerializer {
     
     /// Deserialize a message to a document
     pub fn deserialize_document(message: &Message) -> Result<Document> {
-        let document: Document = serde_json::from_slice(&message.payload)?;
+        let mut document: Document = MessageCodec::decode_message(message)?;
+        extract_trace(message, &mut document.metadata);
         Ok(document)
     }
     
+    /// Serialize a document, checking its content in if the message would exceed `max_message_size`
+    ///
+    /// The oversized content is uploaded to the claim check's store and the
+    /// message carries a `DocumentContent::Reference` to it instead.
+    pub async fn serialize_document_claim_checked(document: &Document, claim_check: &ClaimCheck, max_message_size: usize) -> Result<Message> {
+        let message = Self::serialize_document(document)?;
+        if serde_json::to_vec(&message)?.len() <= max_message_size {
+            return Ok(message);
+        }
+        
+        let checked_in = claim_check.check_in(document).await?;
+        tracing::debug!("Checked in {} bytes of document {} to {}", document.size_bytes, document.id, claim_check.store().storage_id());
+        Self::serialize_document(&checked_in)
+    }
+    
+    /// Deserialize a document, fetching checked-in content back from the claim check's store
+    pub async fn deserialize_document_claim_checked(message: &Message, claim_check: &ClaimCheck) -> Result<Document> {
+        claim_check.check_out(Self::deserialize_document(message)?).await
+    }
+    
     /// Serialize a task to a message
     pub fn serialize_task(task: &Task) -> Result<Message> {
-        let payload = serde_json::to_vec(task)?;
+        Self::serialize_task_with(task, MessageCodec::Json)
+    }
+    
+    /// Serialize a task to a message with the given codec
+    pub fn serialize_task_with(task: &Task, codec: MessageCodec) -> Result<Message> {
+        let payload = codec.encode(task)?;
         
         Ok(Message {
             id: Uuid::new_v4(),
             subject: "swarm.tasks.assignments".to_string(),
             payload,
             headers: {
-                let mut headers = HashMap::new();
-                headers.insert("content-type".to_string(), "application/json".to_string());
-                headers.insert("task-type".to_string(), format!("{:?}", task.task_type));
-                headers.insert("task-id".to_string(), task.id.to_string());
-                headers.insert("priority".to_string(), format!("{:?}", task.priority));
+                let mut headers = MessageHeaders::new();
+                headers.insert(CONTENT_TYPE_HEADER, codec.content_type());
+                headers.insert("task-type", format!("{:?}", task.task_type));
+                headers.insert("task-id", task.id.to_string());
+                headers.insert("priority", format!("{:?}", task.priority));
+                headers.extend(SchemaKind::Task.headers());
+                let decision = SamplingDecision::from_metadata(&task.metadata).or(match &task.payload {
+                    TaskPayload::Document { document, .. } => SamplingDecision::from_metadata(&document.metadata),
+                    _ => None,
+                });
+                propagate_sampling(&mut headers, decision);
+                propagate_trace(&mut headers, TraceContext::from_task(task));
                 headers
             },
             timestamp: Utc::now(),
@@ -63,23 +126,32 @@ impl MessageSerializer {
     
     /// Deserialize a message to a task
     pub fn deserialize_task(message: &Message) -> Result<Task> {
-        let task: Task = serde_json::from_slice(&message.payload)?;
+        let mut task: Task = MessageCodec::decode_message(message)?;
+        extract_trace(message, &mut task.metadata);
         Ok(task)
     }
     
     /// Serialize a task result to a message
     pub fn serialize_task_result(result: &TaskResult) -> Result<Message> {
-        let payload = serde_json::to_vec(result)?;
+        Self::serialize_task_result_with(result, MessageCodec::Json)
+    }
+    
+    /// Serialize a task result to a message with the given codec
+    pub fn serialize_task_result_with(result: &TaskResult, codec: MessageCodec) -> Result<Message> {
+        let payload = codec.encode(result)?;
         
         Ok(Message {
             id: Uuid::new_v4(),
             subject: "swarm.tasks.results".to_string(),
             payload,
             headers: {
-                let mut headers = HashMap::new();
-                headers.insert("content-type".to_string(), "application/json".to_string());
-                headers.insert("task-id".to_string(), result.task_id.to_string());
-                headers.insert("status".to_string(), format!("{:?}", result.status));
+                let mut headers = MessageHeaders::new();
+                headers.insert(CONTENT_TYPE_HEADER, codec.content_type());
+                headers.insert("task-id", result.task_id.to_string());
+                headers.insert("status", format!("{:?}", result.status));
+                headers.extend(SchemaKind::TaskResult.headers());
+                propagate_sampling(&mut headers, SamplingDecision::from_metadata(&result.metadata));
+                propagate_trace(&mut headers, TraceContext::from_metadata(&result.metadata));
                 headers
             },
             timestamp: Utc::now(),
@@ -89,22 +161,60 @@ impl MessageSerializer {
     
     /// Deserialize a message to a task result
     pub fn deserialize_task_result(message: &Message) -> Result<TaskResult> {
-        let result: TaskResult = serde_json::from_slice(&message.payload)?;
+        let mut result: TaskResult = MessageCodec::decode_message(message)?;
+        extract_trace(message, &mut result.metadata);
         Ok(result)
     }
     
+    /// Serialize a permanently failed task to a dead-letter message
+    pub fn serialize_dead_letter(dead_letter: &DeadLetter) -> Result<Message> {
+        Self::serialize_dead_letter_with(dead_letter, MessageCodec::Json)
+    }
+    
+    /// Serialize a permanently failed task to a dead-letter message with the given codec
+    pub fn serialize_dead_letter_with(dead_letter: &DeadLetter, codec: MessageCodec) -> Result<Message> {
+        let payload = codec.encode(dead_letter)?;
+        
+        Ok(Message {
+            id: Uuid::new_v4(),
+            subject: DEAD_LETTER_SUBJECT.to_string(),
+            payload,
+            headers: {
+                let mut headers = MessageHeaders::new();
+                headers.insert(CONTENT_TYPE_HEADER, codec.content_type());
+                headers.insert("task-id", dead_letter.task.id.to_string());
+                headers.insert("task-type", format!("{:?}", dead_letter.task.task_type));
+                headers.insert("attempts", dead_letter.errors.len().to_string());
+                headers
+            },
+            timestamp: Utc::now(),
+            // Dead letters are kept until someone looks at them
+            ttl_ms: None,
+        })
+    }
+    
+    /// Deserialize a dead-letter message
+    pub fn deserialize_dead_letter(message: &Message) -> Result<DeadLetter> {
+        MessageCodec::decode_message(message)
+    }
+    
     /// Serialize a worker status to a message
     pub fn serialize_worker_status(worker_id: Uuid, status: &WorkerStatus) -> Result<Message> {
-        let payload = serde_json::to_vec(status)?;
+        Self::serialize_worker_status_with(worker_id, status, MessageCodec::Json)
+    }
+    
+    /// Serialize a worker status to a message with the given codec
+    pub fn serialize_worker_status_with(worker_id: Uuid, status: &WorkerStatus, codec: MessageCodec) -> Result<Message> {
+        let payload = codec.encode(status)?;
         
         Ok(Message {
             id: Uuid::new_v4(),
             subject: "swarm.workers.status".to_string(),
             payload,
             headers: {
-                let mut headers = HashMap::new();
-                headers.insert("content-type".to_string(), "application/json".to_string());
-                headers.insert("worker-id".to_string(), worker_id.to_string());
+                let mut headers = MessageHeaders::new();
+                headers.insert(CONTENT_TYPE_HEADER, codec.content_type());
+                headers.insert("worker-id", worker_id.to_string());
                 headers
             },
             timestamp: Utc::now(),
@@ -114,8 +224,7 @@ impl MessageSerializer {
     
     /// Deserialize a message to a worker status
     pub fn deserialize_worker_status(message: &Message) -> Result<WorkerStatus> {
-        let status: WorkerStatus = serde_json::from_slice(&message.payload)?;
-        Ok(status)
+        MessageCodec::decode_message(message)
     }
     
     /// Create a heartbeat message
@@ -127,17 +236,17 @@ impl MessageSerializer {
             "status": "alive"
         });
         
-        let payload = serde_json::to_vec(&heartbeat_data)?;
+        let payload = MessageCodec::Json.encode(&heartbeat_data)?;
         
         Ok(Message {
             id: Uuid::new_v4(),
             subject: "swarm.heartbeat".to_string(),
             payload,
             headers: {
-                let mut headers = HashMap::new();
-                headers.insert("content-type".to_string(), "application/json".to_string());
-                headers.insert("component-id".to_string(), component_id.to_string());
-                headers.insert("component-type".to_string(), component_type.to_string());
+                let mut headers = MessageHeaders::new();
+                headers.insert(CONTENT_TYPE_HEADER, MessageCodec::Json.content_type());
+                headers.insert("component-id", component_id.to_string());
+                headers.insert("component-type", component_type.to_string());
                 headers
             },
             timestamp: Utc::now(),
@@ -160,17 +269,17 @@ impl MessageSerializer {
             "timestamp": Utc::now()
         });
         
-        let payload = serde_json::to_vec(&error_data)?;
+        let payload = MessageCodec::Json.encode(&error_data)?;
         
         Ok(Message {
             id: Uuid::new_v4(),
             subject: "swarm.errors".to_string(),
             payload,
             headers: {
-                let mut headers = HashMap::new();
-                headers.insert("content-type".to_string(), "application/json".to_string());
-                headers.insert("error-type".to_string(), error_type.to_string());
-                headers.insert("component-id".to_string(), component_id.to_string());
+                let mut headers = MessageHeaders::new();
+                headers.insert(CONTENT_TYPE_HEADER, MessageCodec::Json.content_type());
+                headers.insert("error-type", error_type.to_string());
+                headers.insert("component-id", component_id.to_string());
                 headers
             },
             timestamp: Utc::now(),
@@ -185,9 +294,7 @@ pub struct MessageValidator;
 impl MessageValidator {
     /// Validate a message structure
     pub fn validate_message(message: &Message) -> Result<()> {
-        if message.subject.is_empty() {
-            return Err(anyhow::anyhow!("Message subject cannot be empty"));
-        }
+        swarm_core::Subject::parse(message.subject.as_str())?;
         
         if message.payload.is_empty() {
             return Err(anyhow::anyhow!("Message payload cannot be empty"));
@@ -217,6 +324,26 @@ impl MessageValidator {
         Ok(())
     }
     
+    /// Whether a message's TTL ran out before `now`; messages without a TTL never expire
+    pub fn is_expired_at(message: &Message, now: chrono::DateTime<Utc>) -> bool {
+        message.ttl_ms.is_some_and(|ttl_ms| now - message.timestamp > chrono::Duration::milliseconds(ttl_ms as i64))
+    }
+    
+    /// Reject a message whose TTL has run out
+    pub fn validate_message_expiry(message: &Message) -> Result<()> {
+        if Self::is_expired_at(message, Utc::now()) {
+            return Err(anyhow::anyhow!(
+                "Message {} on {} expired (sent {}, TTL {} ms)",
+                message.id,
+                message.subject,
+                message.timestamp.to_rfc3339(),
+                message.ttl_ms.unwrap_or_default()
+            ));
+        }
+        
+        Ok(())
+    }
+    
     /// Validate message TTL
     pub fn validate_message_ttl(message: &Message) -> Result<()> {
         if let Some(ttl_ms) = message.ttl_ms {
@@ -236,6 +363,7 @@ impl MessageValidator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use swarm_core::{DocumentType, DocumentContent, TaskType, TaskPriority, TaskStatus, TaskPayload, DocumentProcessingType, DocumentProcessingOptions};
     
     #[test]
     fn test_serialize_document() {
@@ -253,6 +381,8 @@ mod tests {
         assert_eq!(message.subject, "swarm.documents.incoming");
         assert!(message.headers.contains_key("document-type"));
         assert!(message.headers.contains_key("document-id"));
+        assert_eq!(message.headers["schema"], SchemaKind::Document.url());
+        assert_eq!(&message.headers["schema-version"], swarm_core::SCHEMA_VERSION);
         
         let deserialized = MessageSerializer::deserialize_document(&message).unwrap();
         assert_eq!(deserialized.filename, document.filename);
@@ -290,13 +420,137 @@ mod tests {
         
         let message = MessageSerializer::serialize_task(&task).unwrap();
         assert_eq!(message.subject, "swarm.tasks.assignments");
+        assert!(!message.headers.contains_key(TRACE_SAMPLED_HEADER));
         assert!(message.headers.contains_key("task-type"));
         assert!(message.headers.contains_key("task-id"));
         assert!(message.headers.contains_key("priority"));
+        assert_eq!(message.headers["schema"], SchemaKind::Task.url());
         
         let deserialized = MessageSerializer::deserialize_task(&message).unwrap();
         assert_eq!(deserialized.id, task.id);
         assert_eq!(deserialized.priority, task.priority);
+        
+        // A sampling decision made by the reader travels with the task
+        let mut sampled_task = task.clone();
+        if let TaskPayload::Document { document, .. } = &mut sampled_task.payload {
+            SamplingDecision::Sampled.record(&mut document.metadata);
+        }
+        let message = MessageSerializer::serialize_task(&sampled_task).unwrap();
+        assert_eq!(&message.headers[TRACE_SAMPLED_HEADER], "1");
+    }
+    
+    #[test]
+    fn test_trace_context_travels_in_headers() {
+        let mut document = Document {
+            id: Uuid::new_v4(),
+            filename: "test.txt".to_string(),
+            document_type: DocumentType::Text,
+            content: DocumentContent::Text("text".to_string()),
+            metadata: HashMap::new(),
+            created_at: Utc::now(),
+            size_bytes: 4,
+        };
+        let scan = TraceContext::root(document.id, SamplingDecision::Sampled);
+        scan.record(&mut document.metadata);
+        
+        let mut message = MessageSerializer::serialize_document(&document).unwrap();
+        assert_eq!(message.headers[swarm_core::TRACEPARENT_HEADER], scan.to_string());
+        
+        // Subscribers continue from the context the message was published with
+        let publish = scan.child();
+        publish.inject(&mut message.headers);
+        let received = MessageSerializer::deserialize_document(&message).unwrap();
+        let context = TraceContext::from_metadata(&received.metadata).unwrap();
+        assert_eq!((context.trace_id(), context.span_id()), (document.id.as_u128(), publish.span_id()));
+    }
+    
+    #[test]
+    fn test_message_pack_codec_roundtrip() {
+        let vectors: Vec<Vec<f32>> = (0..16).map(|i| (0..128).map(|d| (i * 128 + d) as f32 / 7.0).collect()).collect();
+        let task = Task {
+            id: Uuid::new_v4(),
+            task_type: TaskType::Custom { name: "index".to_string(), version: "1".to_string() },
+            priority: TaskPriority::Normal,
+            status: TaskStatus::Pending,
+            payload: TaskPayload::Vector { vectors, metadata: HashMap::new() }.pack_vectors().unwrap(),
+            created_at: Utc::now(),
+            deadline: None,
+            retry_count: 0,
+            max_retries: 3,
+            metadata: HashMap::from([("tenant".to_string(), serde_json::json!({"id": 7}))]),
+        };
+        
+        let json = MessageSerializer::serialize_task(&task).unwrap();
+        let packed = MessageSerializer::serialize_task_with(&task, MessageCodec::MessagePack).unwrap();
+        assert_eq!(&packed.headers[CONTENT_TYPE_HEADER], "application/msgpack");
+        assert!(packed.payload.len() * 4 < json.payload.len() * 3);
+        assert_eq!(MessageSerializer::deserialize_task(&packed).unwrap(), task);
+        assert_eq!(MessageSerializer::deserialize_task(&json).unwrap(), task);
+        
+        let document = Document {
+            id: Uuid::new_v4(),
+            filename: "scan.pdf".to_string(),
+            document_type: DocumentType::Pdf,
+            content: DocumentContent::Binary((0..=255).collect()),
+            metadata: HashMap::new(),
+            created_at: Utc::now(),
+            size_bytes: 256,
+        };
+        let message = MessageSerializer::serialize_document_with(&document, MessageCodec::MessagePack).unwrap();
+        assert_eq!(MessageSerializer::deserialize_document(&message).unwrap(), document);
+    }
+    
+    #[tokio::test]
+    async fn test_oversized_document_is_claim_checked() {
+        let claim_check = ClaimCheck::new(std::sync::Arc::new(swarm_core::MemoryDocumentStore::new("shared")));
+        let document = Document {
+            id: Uuid::new_v4(),
+            filename: "report.txt".to_string(),
+            document_type: DocumentType::Text,
+            content: DocumentContent::Text("quarterly figures ".repeat(1000)),
+            metadata: HashMap::new(),
+            created_at: Utc::now(),
+            size_bytes: 18_000,
+        };
+        
+        let small = MessageSerializer::serialize_document_claim_checked(&document, &claim_check, 1024 * 1024).await.unwrap();
+        assert_eq!(MessageSerializer::deserialize_document(&small).unwrap(), document);
+        
+        let large = MessageSerializer::serialize_document_claim_checked(&document, &claim_check, 4096).await.unwrap();
+        assert!(serde_json::to_vec(&large).unwrap().len() <= 4096);
+        assert!(matches!(MessageSerializer::deserialize_document(&large).unwrap().content, DocumentContent::Reference { .. }));
+        assert_eq!(MessageSerializer::deserialize_document_claim_checked(&large, &claim_check).await.unwrap(), document);
+    }
+    
+    #[test]
+    fn test_serialize_dead_letter() {
+        let task = Task {
+            id: Uuid::new_v4(),
+            task_type: TaskType::Custom { name: "test".to_string(), version: "1".to_string() },
+            priority: TaskPriority::Normal,
+            status: TaskStatus::Failed,
+            payload: TaskPayload::Custom { data: vec![1, 2, 3], format: "raw".to_string() },
+            created_at: Utc::now(),
+            deadline: None,
+            retry_count: 1,
+            max_retries: 1,
+            metadata: HashMap::new(),
+        };
+        let dead_letter = DeadLetter {
+            task,
+            errors: (1..=2).map(|attempt| swarm_core::FailedAttempt {
+                attempt,
+                worker_id: None,
+                error: format!("attempt {} failed", attempt),
+                failed_at: Utc::now(),
+            }).collect(),
+            dead_lettered_at: Utc::now(),
+        };
+        
+        let message = MessageSerializer::serialize_dead_letter(&dead_letter).unwrap();
+        assert_eq!(message.subject, "swarm.tasks.deadletter");
+        assert_eq!(&message.headers["attempts"], "2");
+        assert_eq!(MessageSerializer::deserialize_dead_letter(&message).unwrap(), dead_letter);
     }
     
     #[test]
@@ -341,7 +595,7 @@ mod tests {
             id: Uuid::new_v4(),
             subject: "test.subject".to_string(),
             payload: b"test".to_vec(),
-            headers: HashMap::new(),
+            headers: MessageHeaders::new(),
             timestamp: Utc::now(),
             ttl_ms: None,
         };
@@ -352,7 +606,7 @@ mod tests {
             id: Uuid::new_v4(),
             subject: "".to_string(), // Empty subject
             payload: b"test".to_vec(),
-            headers: HashMap::new(),
+            headers: MessageHeaders::new(),
             timestamp: Utc::now(),
             ttl_ms: None,
         };
@@ -366,7 +620,7 @@ mod tests {
             id: Uuid::new_v4(),
             subject: "test.subject".to_string(),
             payload: vec![0; 1000], // 1000 bytes
-            headers: HashMap::new(),
+            headers: MessageHeaders::new(),
             timestamp: Utc::now(),
             ttl_ms: None,
         };
@@ -374,4 +628,24 @@ mod tests {
         assert!(MessageValidator::validate_message_size(&message, 2000).is_ok());
         assert!(MessageValidator::validate_message_size(&message, 500).is_err());
     }
+    
+    #[test]
+    fn test_validate_message_expiry() {
+        let mut message = Message {
+            id: Uuid::new_v4(),
+            subject: "test.subject".to_string(),
+            payload: b"test".to_vec(),
+            headers: MessageHeaders::new(),
+            timestamp: Utc::now() - chrono::Duration::seconds(10),
+            ttl_ms: None,
+        };
+        assert!(MessageValidator::validate_message_expiry(&message).is_ok());
+        
+        message.ttl_ms = Some(60_000);
+        assert!(MessageValidator::validate_message_expiry(&message).is_ok());
+        assert!(MessageValidator::is_expired_at(&message, Utc::now() + chrono::Duration::minutes(1)));
+        
+        message.ttl_ms = Some(5_000);
+        assert!(MessageValidator::validate_message_expiry(&message).unwrap_err().to_string().contains("expired"));
+    }
 }
@@ -1,15 +1,20 @@
 //! Message Subscription Implementation
 //!
 //! This module provides utilities for managing message subscriptions
-//! and handling message streams.
+//! and handling message streams. Subjects in the routing configuration are
+//! typed `Subject`s, validated when the configuration is loaded; the
+//! router's accessors are the way components name the subjects they use.
+//!
+//! A component subscribed to a wildcard subject (`swarm.documents.*`)
+//! registers a handler per subject with the `MessageSubscriptionManager`
+//! and lets it dispatch the messages it receives.
 
-use super::*;
-use swarm_core::Message;
+use crate::authorization::{AuthorizationReport, ComponentRole, SubjectPermissions, SubjectUse};
+use crate::{MessageError, MessageResult, BrokerSubscription};
+use swarm_core::{subject_matches, Message, Subject};
 use anyhow::Result;
 use std::collections::HashMap;
 use tokio::sync::mpsc;
-use uuid::Uuid;
-use chrono::Utc;
 
 /// Message subscription manager
 pub struct MessageSubscriptionManager {
@@ -27,6 +32,9 @@ impl MessageSubscriptionManager {
     }
     
     /// Register a message handler for a subject
+    ///
+    /// The subject may be a wildcard pattern, handling the messages no more
+    /// specific handler is registered for.
     pub fn register_handler<F>(&mut self, subject: &str, handler: F)
     where
         F: Fn(Message) -> Result<()> + Send + Sync + 'static,
@@ -50,6 +58,38 @@ impl MessageSubscriptionManager {
         Ok(())
     }
     
+    /// Hand a message to the handler registered for its subject
+    ///
+    /// A handler registered for the exact subject is preferred, then the
+    /// wildcard handler with the most literal tokens, `*` handlers before
+    /// `>` ones; ties go to the alphabetically first pattern. Returns
+    /// whether a handler was found.
+    pub fn dispatch(&self, message: Message) -> Result<bool> {
+        let handler = self.message_handlers.iter()
+            .filter(|(pattern, _)| subject_matches(pattern, &message.subject))
+            .max_by_key(|&(pattern, _)| (specificity(pattern), std::cmp::Reverse(pattern.as_str())))
+            .map(|(_, handler)| handler);
+        match handler {
+            Some(handler) => handler(message).map(|()| true),
+            None => {
+                tracing::debug!("No handler for message on {}", message.subject);
+                Ok(false)
+            }
+        }
+    }
+    
+    /// Dispatch the messages of a subscription until it is closed
+    ///
+    /// Handler errors are logged and do not stop the dispatcher.
+    pub async fn dispatch_subscription(&self, subscription: &mut BrokerSubscription) {
+        while let Some(message) = subscription.recv().await {
+            let subject = message.subject.clone();
+            if let Err(e) = self.dispatch(message) {
+                tracing::error!("Handler for {} failed: {}", subject, e);
+            }
+        }
+    }
+    
     /// Get all active subscription subjects
     pub fn get_subscription_subjects(&self) -> Vec<String> {
         self.subscriptions.keys().cloned().collect()
@@ -62,6 +102,12 @@ impl MessageSubscriptionManager {
     }
 }
 
+/// How specific a handler pattern is: literal tokens first, then no trailing `>`
+fn specificity(pattern: &str) -> (usize, bool) {
+    let literals = pattern.split('.').filter(|token| *token != "*" && *token != ">").count();
+    (literals, !pattern.ends_with('>'))
+}
+
 impl Default for MessageSubscriptionManager {
     fn default() -> Self {
         Self::new()
@@ -72,7 +118,7 @@ impl Default for MessageSubscriptionManager {
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct MessageRoutingConfig {
     /// Default subject prefix
-    pub subject_prefix: String,
+    pub subject_prefix: Subject,
     
     /// Document processing subjects
     pub document_subjects: DocumentSubjects,
@@ -88,59 +134,82 @@ pub struct MessageRoutingConfig {
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DocumentSubjects {
     /// Incoming documents
-    pub incoming: String,
+    pub incoming: Subject,
     
     /// Document processing results
-    pub results: String,
+    pub results: Subject,
     
     /// Document errors
-    pub errors: String,
+    pub errors: Subject,
+    
+    /// Queue group document workers share incoming documents through
+    #[serde(default = "default_worker_queue")]
+    pub worker_queue: String,
+}
+
+fn default_worker_queue() -> String {
+    "document-workers".to_string()
 }
 
 /// Task-related subjects
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct TaskSubjects {
     /// Task assignments
-    pub assignments: String,
+    pub assignments: Subject,
     
     /// Task results
-    pub results: String,
+    pub results: Subject,
     
     /// Task status updates
-    pub status: String,
+    pub status: Subject,
+    
+    /// Permanently failed tasks
+    #[serde(default = "default_deadletter_subject")]
+    pub deadletter: Subject,
+}
+
+fn default_deadletter_subject() -> Subject {
+    subject(swarm_core::DEAD_LETTER_SUBJECT)
+}
+
+/// Parse a built-in subject
+fn subject(subject: &str) -> Subject {
+    Subject::parse(subject).expect("built-in subjects are valid")
 }
 
 /// Worker-related subjects
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct WorkerSubjects {
     /// Worker registration
-    pub registration: String,
+    pub registration: Subject,
     
     /// Worker health checks
-    pub health: String,
+    pub health: Subject,
     
     /// Worker status updates
-    pub status: String,
+    pub status: Subject,
 }
 
 impl Default for MessageRoutingConfig {
     fn default() -> Self {
         Self {
-            subject_prefix: "swarm".to_string(),
+            subject_prefix: subject("swarm"),
             document_subjects: DocumentSubjects {
-                incoming: "swarm.documents.incoming".to_string(),
-                results: "swarm.documents.results".to_string(),
-                errors: "swarm.documents.errors".to_string(),
+                incoming: subject("swarm.documents.incoming"),
+                results: subject("swarm.documents.results"),
+                errors: subject("swarm.documents.errors"),
+                worker_queue: default_worker_queue(),
             },
             task_subjects: TaskSubjects {
-                assignments: "swarm.tasks.assignments".to_string(),
-                results: "swarm.tasks.results".to_string(),
-                status: "swarm.tasks.status".to_string(),
+                assignments: subject("swarm.tasks.assignments"),
+                results: subject(swarm_core::TASK_RESULTS_SUBJECT),
+                status: subject("swarm.tasks.status"),
+                deadletter: default_deadletter_subject(),
             },
             worker_subjects: WorkerSubjects {
-                registration: "swarm.workers.registration".to_string(),
-                health: "swarm.workers.health".to_string(),
-                status: "swarm.workers.status".to_string(),
+                registration: subject(swarm_core::WORKER_REGISTRATION_SUBJECT),
+                health: subject(swarm_core::WORKER_HEALTH_SUBJECT),
+                status: subject("swarm.workers.status"),
             },
         }
     }
@@ -158,64 +227,115 @@ impl MessageRouter {
     }
     
     /// Get document incoming subject
-    pub fn document_incoming_subject(&self) -> &str {
+    pub fn document_incoming_subject(&self) -> &Subject {
         &self.config.document_subjects.incoming
     }
     
     /// Get document results subject
-    pub fn document_results_subject(&self) -> &str {
+    pub fn document_results_subject(&self) -> &Subject {
         &self.config.document_subjects.results
     }
     
     /// Get document errors subject
-    pub fn document_errors_subject(&self) -> &str {
+    pub fn document_errors_subject(&self) -> &Subject {
         &self.config.document_subjects.errors
     }
     
+    /// Get the queue group document workers subscribe with
+    pub fn document_worker_queue(&self) -> &str {
+        &self.config.document_subjects.worker_queue
+    }
+    
     /// Get task assignment subject
-    pub fn task_assignment_subject(&self) -> &str {
+    pub fn task_assignment_subject(&self) -> &Subject {
         &self.config.task_subjects.assignments
     }
     
     /// Get task results subject
-    pub fn task_results_subject(&self) -> &str {
+    pub fn task_results_subject(&self) -> &Subject {
         &self.config.task_subjects.results
     }
     
     /// Get task status subject
-    pub fn task_status_subject(&self) -> &str {
+    pub fn task_status_subject(&self) -> &Subject {
         &self.config.task_subjects.status
     }
     
+    /// Get task dead-letter subject
+    pub fn task_deadletter_subject(&self) -> &Subject {
+        &self.config.task_subjects.deadletter
+    }
+    
     /// Get worker registration subject
-    pub fn worker_registration_subject(&self) -> &str {
+    pub fn worker_registration_subject(&self) -> &Subject {
         &self.config.worker_subjects.registration
     }
     
     /// Get worker health subject
-    pub fn worker_health_subject(&self) -> &str {
+    pub fn worker_health_subject(&self) -> &Subject {
         &self.config.worker_subjects.health
     }
     
     /// Get worker status subject
-    pub fn worker_status_subject(&self) -> &str {
+    pub fn worker_status_subject(&self) -> &Subject {
         &self.config.worker_subjects.status
     }
     
-    /// Create a subject with prefix
-    pub fn create_subject(&self, components: &[&str]) -> String {
-        let mut subject = self.config.subject_prefix.clone();
-        for component in components {
-            subject.push('.');
-            subject.push_str(component);
+    /// Subjects a component in `role` publishes and subscribes to
+    pub fn subjects_for(&self, role: ComponentRole) -> Vec<SubjectUse> {
+        match role {
+            ComponentRole::DocumentReader => vec![
+                SubjectUse::publish(self.document_incoming_subject().as_str()),
+            ],
+            ComponentRole::DocumentProcessor => vec![
+                SubjectUse::subscribe(self.document_incoming_subject().as_str()),
+                SubjectUse::publish(self.document_results_subject().as_str()),
+                SubjectUse::publish(self.document_errors_subject().as_str()),
+            ],
+            ComponentRole::Coordinator => vec![
+                SubjectUse::publish(self.task_assignment_subject().as_str()),
+                SubjectUse::publish(self.task_deadletter_subject().as_str()),
+                SubjectUse::subscribe(self.task_results_subject().as_str()),
+                SubjectUse::subscribe(self.task_status_subject().as_str()),
+                SubjectUse::subscribe(self.worker_registration_subject().as_str()),
+                SubjectUse::subscribe(self.worker_health_subject().as_str()),
+                SubjectUse::subscribe(self.worker_status_subject().as_str()),
+            ],
+            ComponentRole::Worker => vec![
+                SubjectUse::subscribe(self.task_assignment_subject().as_str()),
+                SubjectUse::publish(self.task_results_subject().as_str()),
+                SubjectUse::publish(self.task_status_subject().as_str()),
+                SubjectUse::publish(self.worker_registration_subject().as_str()),
+                SubjectUse::publish(self.worker_health_subject().as_str()),
+                SubjectUse::publish(self.worker_status_subject().as_str()),
+            ],
+        }
+    }
+    
+    /// Check every subject `role` uses against its permissions, reporting all denials at once
+    pub fn authorize(&self, role: ComponentRole, permissions: &SubjectPermissions) -> MessageResult<()> {
+        let denied: Vec<SubjectUse> = self.subjects_for(role).into_iter()
+            .filter(|used| !permissions.permits(used.operation, &used.subject))
+            .collect();
+        if denied.is_empty() {
+            Ok(())
+        } else {
+            Err(MessageError::Unauthorized(AuthorizationReport { role, denied }))
         }
-        subject
+    }
+    
+    /// Create a subject with prefix
+    pub fn create_subject(&self, components: &[&str]) -> MessageResult<Subject> {
+        Ok(self.config.subject_prefix.child(components)?)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use swarm_core::MessageHeaders;
+    use uuid::Uuid;
+    use chrono::Utc;
     
     #[test]
     fn test_message_subscription_manager() {
@@ -229,7 +349,7 @@ mod tests {
             id: Uuid::new_v4(),
             subject: "test.subject".to_string(),
             payload: b"test message".to_vec(),
-            headers: HashMap::new(),
+            headers: MessageHeaders::new(),
             timestamp: Utc::now(),
             ttl_ms: None,
         };
@@ -242,15 +362,72 @@ mod tests {
         assert_eq!(received.payload, message.payload);
     }
     
+    #[test]
+    fn test_dispatch_prefers_the_most_specific_handler() {
+        use std::sync::{Arc, Mutex};
+        
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let mut manager = MessageSubscriptionManager::new();
+        for pattern in ["swarm.documents.incoming", "swarm.documents.*", "swarm.>"] {
+            let handled = handled.clone();
+            manager.register_handler(pattern, move |message| {
+                handled.lock().unwrap().push((pattern, message.subject));
+                Ok(())
+            });
+        }
+        manager.register_handler("swarm.tasks.results", |_| anyhow::bail!("result store unavailable"));
+        
+        let message = |subject: &str| Message {
+            id: Uuid::new_v4(),
+            subject: subject.to_string(),
+            payload: Vec::new(),
+            headers: MessageHeaders::new(),
+            timestamp: Utc::now(),
+            ttl_ms: None,
+        };
+        for subject in ["swarm.documents.incoming", "swarm.documents.errors", "swarm.workers.health"] {
+            assert!(manager.dispatch(message(subject)).unwrap());
+        }
+        assert!(!manager.dispatch(message("metrics.cpu")).unwrap());
+        assert!(manager.dispatch(message("swarm.tasks.results")).is_err());
+        
+        assert_eq!(handled.lock().unwrap().clone(), vec![
+            ("swarm.documents.incoming", "swarm.documents.incoming".to_string()),
+            ("swarm.documents.*", "swarm.documents.errors".to_string()),
+            ("swarm.>", "swarm.workers.health".to_string()),
+        ]);
+    }
+    
     #[test]
     fn test_message_routing_config() {
         let config = MessageRoutingConfig::default();
         assert_eq!(config.subject_prefix, "swarm");
         assert_eq!(config.document_subjects.incoming, "swarm.documents.incoming");
+        assert_eq!(config.document_subjects.worker_queue, "document-workers");
         assert_eq!(config.task_subjects.assignments, "swarm.tasks.assignments");
         assert_eq!(config.worker_subjects.registration, "swarm.workers.registration");
     }
     
+    #[test]
+    fn test_document_subjects_default_worker_queue() {
+        let subjects: DocumentSubjects = serde_json::from_str(
+            r#"{"incoming": "a.incoming", "results": "a.results", "errors": "a.errors"}"#
+        ).unwrap();
+        assert_eq!(subjects.worker_queue, "document-workers");
+    }
+    
+    #[test]
+    fn test_routing_config_rejects_malformed_subjects() {
+        let mut config = serde_json::to_value(MessageRoutingConfig::default()).unwrap();
+        config["task_subjects"]["results"] = "swarm..results".into();
+        let error = serde_json::from_value::<MessageRoutingConfig>(config).unwrap_err();
+        assert!(error.to_string().contains("empty token"));
+        
+        let router = MessageRouter::new(MessageRoutingConfig::default());
+        assert!(matches!(router.create_subject(&["tasks", "*", "eu"]), Ok(subject) if subject.is_wildcard()));
+        assert!(matches!(router.create_subject(&["task results"]), Err(MessageError::InvalidSubject(_))));
+    }
+    
     #[test]
     fn test_message_router() {
         let config = MessageRoutingConfig::default();
@@ -258,9 +435,32 @@ mod tests {
         
         assert_eq!(router.document_incoming_subject(), "swarm.documents.incoming");
         assert_eq!(router.task_assignment_subject(), "swarm.tasks.assignments");
+        assert_eq!(router.task_deadletter_subject(), "swarm.tasks.deadletter");
         assert_eq!(router.worker_registration_subject(), "swarm.workers.registration");
         
-        let custom_subject = router.create_subject(&["custom", "subject"]);
+        let custom_subject = router.create_subject(&["custom", "subject"]).unwrap();
         assert_eq!(custom_subject, "swarm.custom.subject");
     }
+    
+    #[test]
+    fn test_router_reports_every_denied_subject() {
+        use crate::authorization::{PermissionRules, SubjectOperation};
+        
+        let router = MessageRouter::new(MessageRoutingConfig::default());
+        let worker = SubjectPermissions {
+            publish: PermissionRules { allow: vec!["swarm.tasks.*".to_string(), "swarm.workers.>".to_string()], deny: Vec::new() },
+            subscribe: PermissionRules { allow: vec!["swarm.tasks.assignments".to_string()], deny: Vec::new() },
+        };
+        assert!(router.authorize(ComponentRole::Worker, &worker).is_ok());
+        
+        let Err(MessageError::Unauthorized(report)) = router.authorize(ComponentRole::Coordinator, &worker) else {
+            panic!("the coordinator needs more than a worker's permissions");
+        };
+        assert_eq!(report.denied.len(), 5);
+        assert!(report.denied.contains(&SubjectUse::subscribe("swarm.tasks.results")));
+        assert!(report.denied.iter().all(|denied| denied.operation == SubjectOperation::Subscribe));
+        let message = MessageError::Unauthorized(report).to_string();
+        assert!(message.contains("Coordinator is not permitted to use 5 subject(s)"));
+        assert!(message.contains("subscribe swarm.workers.health"));
+    }
 }
@@ -1,14 +1,21 @@
 //! NATS Message Broker Implementation
 //!
 //! This module provides a concrete implementation of the MessageBroker trait
-//! using NATS as the underlying message broker.
+//! using NATS as the underlying message broker. With a metrics collector
+//! attached, the latency of every published message is recorded as an SLI.
+//! Batches are published with `publish_batch`; subscriptions unpack batch
+//! envelopes and deliver the messages they wrap one by one.
 
 use super::*;
-use swarm_core::{MessageBroker, MessageSubscription, Message, MessageBrokerStats};
+use crate::subscription_liveness::{watch_forwarder, ForwarderExit};
+use swarm_core::{ClaimCheck, Document, MessageBroker, MessageSubscription, Message, MessageBrokerStats, MessageHeaders, SamplingDecision, Sli, SliRecorder, Subject, TraceContext, TraceSampler, REPLY_TO_HEADER, TRACE_SAMPLED_HEADER};
+use std::borrow::Cow;
 use anyhow::Result;
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 use chrono::Utc;
@@ -21,27 +28,186 @@ use bytes::Bytes;
 pub struct NatsBroker {
     client: Arc<async_nats::Client>,
     config: NatsConfig,
-    stats: Arc<RwLock<NatsStats>>,
+    stats: Arc<RwLock<BrokerStats>>,
     subscriptions: Arc<RwLock<HashMap<String, mpsc::UnboundedSender<Message>>>>,
+    subject_counters: Arc<RwLock<HashMap<String, Arc<SubjectCounters>>>>,
+    publisher: PipelinedPublisher,
+    sampler: TraceSampler,
+    claim_check: Option<ClaimCheck>,
+    sli: SliRecorder,
+}
+
+/// Live per-subject counters shared between the broker and its subscriptions
+#[derive(Debug, Default)]
+pub struct SubjectCounters {
+    messages_sent: AtomicU64,
+    messages_received: AtomicU64,
+    error_count: AtomicU64,
+    pending: AtomicU64,
+    last_lag_ms: AtomicU64,
+    max_lag_ms: AtomicU64,
+    last_message_ms: AtomicI64,
+    last_tick_ms: AtomicI64,
+    restarts: AtomicU64,
+}
+
+impl SubjectCounters {
+    /// Record a message published to the subject
+    pub(crate) fn record_sent(&self) {
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record a publish, delivery or deserialization error
+    pub(crate) fn record_error(&self) {
+        self.error_count.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record a message handed to the subscriber, before it is consumed
+    pub(crate) fn record_delivered(&self) {
+        self.messages_received.fetch_add(1, Ordering::Relaxed);
+        self.pending.fetch_add(1, Ordering::Relaxed);
+        self.last_message_ms.store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+    
+    /// Record that the forwarding task is alive
+    pub(crate) fn record_tick(&self) {
+        self.last_tick_ms.store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+    
+    /// Time since the forwarding task last ticked
+    pub(crate) fn silent_ms(&self, now: chrono::DateTime<Utc>) -> u64 {
+        (now.timestamp_millis() - self.last_tick_ms.load(Ordering::Relaxed)).max(0) as u64
+    }
+    
+    /// Count a restart of the subscription, returning the total
+    fn record_restart(&self) -> u64 {
+        self.restarts.fetch_add(1, Ordering::Relaxed) + 1
+    }
+    
+    /// Undo a delivery whose receiver has gone away
+    pub(crate) fn record_undelivered(&self) {
+        self.messages_received.fetch_sub(1, Ordering::Relaxed);
+        self.decrement_pending();
+    }
+    
+    /// Record a message taken off the subscription by its consumer
+    fn record_consumed(&self, message: &Message) {
+        self.decrement_pending();
+        
+        let lag_ms = (Utc::now() - message.timestamp).num_milliseconds().max(0) as u64;
+        self.last_lag_ms.store(lag_ms, Ordering::Relaxed);
+        self.max_lag_ms.fetch_max(lag_ms, Ordering::Relaxed);
+    }
+    
+    fn decrement_pending(&self) {
+        let _ = self.pending.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
+    
+    /// Snapshot the counters
+    pub fn snapshot(&self) -> SubjectStats {
+        SubjectStats {
+            messages_sent: self.messages_sent.load(Ordering::Relaxed),
+            messages_received: self.messages_received.load(Ordering::Relaxed),
+            error_count: self.error_count.load(Ordering::Relaxed),
+            pending: self.pending.load(Ordering::Relaxed),
+            last_lag_ms: self.last_lag_ms.load(Ordering::Relaxed),
+            max_lag_ms: self.max_lag_ms.load(Ordering::Relaxed),
+            last_message_at: match self.last_message_ms.load(Ordering::Relaxed) {
+                0 => None,
+                millis => chrono::DateTime::from_timestamp_millis(millis),
+            },
+            restarts: self.restarts.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// What a forwarding task subscribes to, so it can subscribe again
+#[derive(Debug, Clone)]
+struct SubscriptionTarget {
+    subject: String,
+    queue_group: Option<String>,
+}
+
+impl SubscriptionTarget {
+    fn is_wildcard(&self) -> bool {
+        self.subject.split('.').any(|token| token == "*" || token == ">")
+    }
+    
+    async fn subscribe(&self, client: &async_nats::Client) -> MessageResult<async_nats::Subscriber> {
+        let subscription = match &self.queue_group {
+            Some(group) => client.queue_subscribe(self.subject.clone(), group.clone()).await,
+            None => client.subscribe(self.subject.clone()).await,
+        };
+        subscription.map_err(|e| MessageError::Nats(e.into()))
+    }
+}
+
+/// Update connection statistics from a client connection event
+async fn record_connection_event(stats: &RwLock<BrokerStats>, event: &async_nats::Event) {
+    let mut stats = stats.write().await;
+    match event {
+        async_nats::Event::Connected => {
+            if !stats.is_connected && stats.last_disconnected.is_some() {
+                stats.reconnection_count += 1;
+                tracing::info!("Reconnected to NATS server");
+            }
+            stats.is_connected = true;
+            stats.last_connected = Some(Utc::now());
+        }
+        async_nats::Event::Disconnected => {
+            stats.is_connected = false;
+            stats.last_disconnected = Some(Utc::now());
+            tracing::warn!("Disconnected from NATS server, reconnecting");
+        }
+        event => tracing::debug!("NATS connection event: {}", event),
+    }
 }
 
 impl NatsBroker {
     /// Create a new NATS broker
+    ///
+    /// The client reconnects on its own with the configured backoff and
+    /// replays its subscriptions once connected again.
     pub async fn new(config: NatsConfig) -> MessageResult<Self> {
         tracing::info!("Connecting to NATS server: {}", config.url);
         
-        let client = async_nats::connect(&config.url).await
+        let stats = Arc::new(RwLock::new(BrokerStats::default()));
+        let options = match &config.credentials {
+            Some(credentials) => credentials.connect_options().await
+                .map_err(|e| MessageError::Connection {
+                    message: format!("Failed to load NATS credentials: {}", e)
+                })?,
+            None => async_nats::ConnectOptions::new(),
+        };
+        let backoff = config.clone();
+        let event_stats = stats.clone();
+        let options = options
+            .connection_timeout(Duration::from_millis(config.connection_timeout_ms))
+            .reconnect_delay_callback(move |attempt| backoff.reconnect_delay(attempt))
+            .event_callback(move |event| {
+                let stats = event_stats.clone();
+                async move { record_connection_event(&stats, &event).await }
+            });
+        let client = options.connect(&config.url).await
             .map_err(|e| MessageError::Connection { 
                 message: format!("Failed to connect to NATS: {}", e) 
             })?;
         
-        let stats = Arc::new(RwLock::new(NatsStats {
-            is_connected: true,
-            last_connected: Some(Utc::now()),
-            ..Default::default()
-        }));
+        {
+            let mut stats = stats.write().await;
+            stats.is_connected = true;
+            stats.last_connected = Some(Utc::now());
+        }
         
         let subscriptions = Arc::new(RwLock::new(HashMap::new()));
+        let subject_counters = Arc::new(RwLock::new(HashMap::new()));
+        
+        let transport: Arc<dyn PublishTransport> = match config.publisher.ack_mode {
+            AckMode::None => Arc::new(client.clone()),
+            AckMode::JetStream => Arc::new(async_nats::jetstream::new(client.clone())),
+        };
+        let publisher = PipelinedPublisher::new(transport, &config.publisher);
+        let sampler = TraceSampler::new(config.tracing_sampling.clone());
         
         tracing::info!("✅ Connected to NATS server successfully");
         
@@ -50,12 +216,57 @@ impl NatsBroker {
             config,
             stats,
             subscriptions,
+            subject_counters,
+            publisher,
+            sampler,
+            claim_check: None,
+            sli: SliRecorder::default(),
         })
     }
     
-    /// Get current statistics
-    pub async fn get_stats(&self) -> NatsStats {
-        self.stats.read().await.clone()
+    /// Offload documents too large for `max_message_size` to a document store
+    pub fn with_claim_check(mut self, claim_check: ClaimCheck) -> Self {
+        self.claim_check = Some(claim_check);
+        self
+    }
+    
+    /// Record the publish latency of every message to `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<dyn swarm_core::MetricsCollector>) -> Self {
+        self.sli = SliRecorder::new(metrics);
+        self
+    }
+    
+    /// Get current statistics, including per-subject counters
+    pub async fn get_stats(&self) -> BrokerStats {
+        let mut stats = self.stats.read().await.clone();
+        stats.subjects = self.subject_stats().await;
+        stats.publisher = self.publisher.stats();
+        stats
+    }
+    
+    /// Get per-subject counters and consumer lag
+    pub async fn subject_stats(&self) -> HashMap<String, SubjectStats> {
+        self.subject_counters.read().await
+            .iter()
+            .map(|(subject, counters)| (subject.clone(), counters.snapshot()))
+            .collect()
+    }
+    
+    /// Export current statistics to a metrics collector
+    pub async fn export_metrics(&self, collector: &dyn swarm_core::MetricsCollector) {
+        self.get_stats().await.export_metrics(collector, "nats");
+    }
+    
+    /// Get or create the counters for a subject
+    async fn counters_for(&self, subject: &str) -> Arc<SubjectCounters> {
+        if let Some(counters) = self.subject_counters.read().await.get(subject) {
+            return counters.clone();
+        }
+        
+        self.subject_counters.write().await
+            .entry(subject.to_string())
+            .or_default()
+            .clone()
     }
     
     /// Check if broker is connected
@@ -63,71 +274,305 @@ impl NatsBroker {
         self.stats.read().await.is_connected
     }
     
-    /// Publish a message to a subject
-    pub async fn publish_message(&self, subject: &str, message: &Message) -> MessageResult<()> {
+    /// Make sure a message carries a sampling decision, deciding if it has none
+    fn with_sampling_decision<'a>(&self, message: &'a Message) -> (SamplingDecision, Cow<'a, Message>) {
+        if let Some(decision) = SamplingDecision::from_headers(&message.headers) {
+            return (decision, Cow::Borrowed(message));
+        }
+        let decision = self.sampler.sample_message(message);
+        let mut message = message.clone();
+        message.headers.insert(TRACE_SAMPLED_HEADER, decision.as_str());
+        (decision, Cow::Owned(message))
+    }
+    
+    /// Continue a message's trace with a span for `stage`, carried on in its headers
+    fn continue_trace<'a>(&self, decision: SamplingDecision, stage: &'static str, message: Cow<'a, Message>) -> (tracing::Span, Cow<'a, Message>) {
+        let Some(parent) = TraceContext::from_headers(&message.headers) else {
+            return (self.sampler.span(decision, stage, message.id), message);
+        };
+        let (context, span) = parent.with_decision(decision).continue_span(stage, message.id);
+        let mut message = message.into_owned();
+        context.inject(&mut message.headers);
+        (span, Cow::Owned(message))
+    }
+    
+    /// Check that the configured permissions allow an operation on a subject
+    ///
+    /// The server would drop the message or subscription without telling the
+    /// caller, so this fails locally instead.
+    fn check_permission(&self, operation: SubjectOperation, subject: &str) -> MessageResult<()> {
+        match &self.config.permissions {
+            Some(permissions) => permissions.check(operation, subject),
+            None => Ok(()),
+        }
+    }
+    
+    /// Check every subject `role` uses against the configured permissions
+    ///
+    /// Call at startup to fail fast on a misconfigured deployment.
+    pub fn authorize(&self, router: &MessageRouter, role: ComponentRole) -> MessageResult<()> {
+        match &self.config.permissions {
+            Some(permissions) => router.authorize(role, permissions),
+            None => Ok(()),
+        }
+    }
+    
+    /// Serialize a message, rejecting it if it exceeds `max_message_size`
+    fn serialize_checked(&self, message: &Message, counters: &SubjectCounters) -> MessageResult<Vec<u8>> {
         let serialized = serde_json::to_vec(message)?;
-        
         if serialized.len() > self.config.max_message_size {
+            counters.record_error();
             return Err(MessageError::MessageTooLarge {
                 size: serialized.len(),
                 max_size: self.config.max_message_size,
             });
         }
+        Ok(serialized)
+    }
+    
+    /// Publish a message to a subject
+    pub async fn publish_message(&self, subject: &Subject, message: &Message) -> MessageResult<()> {
+        let subject = subject.as_str();
+        self.check_permission(SubjectOperation::Publish, subject)?;
+        let counters = self.counters_for(subject).await;
+        let (decision, message) = self.with_sampling_decision(message);
+        let (span, message) = self.continue_trace(decision, "publish", message);
+        let serialized = self.serialize_checked(&message, &counters)?;
         
-        self.client.publish(subject.to_string(), Bytes::from(serialized)).await
-            .map_err(|e| MessageError::Nats(e.into()))?;
+        let start_time = std::time::Instant::now();
+        if let Err(e) = self.client.publish(subject.to_string(), Bytes::from(serialized)).await {
+            counters.record_error();
+            self.stats.write().await.error_count += 1;
+            return Err(MessageError::Nats(e.into()));
+        }
         
         // Update statistics
         {
             let mut stats = self.stats.write().await;
             stats.messages_sent += 1;
         }
+        counters.record_sent();
+        self.sli.observe_elapsed(Sli::PublishLatency, start_time, &[("subject", subject)]);
         
-        tracing::debug!("Published message to subject: {}", subject);
+        span.in_scope(|| {
+            tracing::debug!("Published message to subject: {}", subject);
+        });
         Ok(())
     }
     
+    /// Send a request and wait up to `timeout` for the reply
+    ///
+    /// Responders see the reply subject in `Message::reply_to`. A reply that
+    /// is not a serialized `Message` is returned with its raw payload.
+    pub async fn request_message(&self, subject: &Subject, message: &Message, timeout: Duration) -> MessageResult<Message> {
+        let subject = subject.as_str();
+        self.check_permission(SubjectOperation::Publish, subject)?;
+        let counters = self.counters_for(subject).await;
+        let (decision, message) = self.with_sampling_decision(message);
+        let (span, message) = self.continue_trace(decision, "request", message);
+        let serialized = self.serialize_checked(&message, &counters)?;
+        
+        let response = match tokio::time::timeout(timeout, self.client.request(subject.to_string(), Bytes::from(serialized))).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => {
+                counters.record_error();
+                self.stats.write().await.error_count += 1;
+                return Err(MessageError::Nats(e.into()));
+            }
+            Err(_) => {
+                counters.record_error();
+                self.stats.write().await.error_count += 1;
+                return Err(MessageError::Timeout {
+                    message: format!("no reply on {} within {:?}", subject, timeout),
+                });
+            }
+        };
+        
+        {
+            let mut stats = self.stats.write().await;
+            stats.messages_sent += 1;
+            stats.messages_received += 1;
+        }
+        counters.record_sent();
+        
+        span.in_scope(|| {
+            tracing::debug!("Received reply to request on subject: {}", subject);
+        });
+        Ok(serde_json::from_slice::<Message>(&response.payload).unwrap_or_else(|_| Message {
+            id: Uuid::new_v4(),
+            subject: response.subject.to_string(),
+            payload: response.payload.to_vec(),
+            headers: MessageHeaders::new(),
+            timestamp: Utc::now(),
+            ttl_ms: None,
+        }))
+    }
+    
+    /// Publish a document, checking oversized content in to the configured store
+    ///
+    /// Without a claim check, documents too large to send fail with `MessageTooLarge`.
+    pub async fn publish_document(&self, subject: &Subject, document: &Document) -> MessageResult<()> {
+        let message = match &self.claim_check {
+            Some(claim_check) => MessageSerializer::serialize_document_claim_checked(document, claim_check, self.config.max_message_size).await?,
+            None => MessageSerializer::serialize_document(document)?,
+        };
+        self.publish_message(subject, &message).await
+    }
+    
+    /// Publish a permanently failed task to the dead-letter subject
+    pub async fn publish_dead_letter(&self, dead_letter: &swarm_core::DeadLetter) -> MessageResult<()> {
+        let message = MessageSerializer::serialize_dead_letter(dead_letter)?;
+        self.publish_message(&Subject::parse(message.subject.as_str())?, &message).await
+    }
+    
+    /// Publish without waiting for the previous publish to complete
+    ///
+    /// Waits only while `publisher.max_in_flight` publishes are outstanding;
+    /// await the returned handle to confirm delivery.
+    pub async fn publish_pipelined(&self, subject: &Subject, message: &Message) -> MessageResult<PublishHandle> {
+        let subject = subject.as_str();
+        self.check_permission(SubjectOperation::Publish, subject)?;
+        let counters = self.counters_for(subject).await;
+        let (_, message) = self.with_sampling_decision(message);
+        let serialized = self.serialize_checked(&message, &counters)?;
+        
+        let handle = self.publisher.publish(subject, Bytes::from(serialized)).await?;
+        counters.record_sent();
+        self.stats.write().await.messages_sent += 1;
+        Ok(handle)
+    }
+    
+    /// Publish several messages to a subject as one batch
+    ///
+    /// With `BatchMode::Pipelined` every message is sent without waiting for
+    /// the previous one and failed publishes are counted in the report; with
+    /// `BatchMode::Envelope` the batch goes out as a single message, which
+    /// has to fit in `max_message_size`.
+    pub async fn publish_batch(&self, subject: &Subject, messages: &[Message]) -> MessageResult<BatchReport> {
+        let subject_str = subject.as_str();
+        self.check_permission(SubjectOperation::Publish, subject_str)?;
+        let counters = self.counters_for(subject_str).await;
+        let messages: Vec<Message> = messages.iter()
+            .map(|message| self.with_sampling_decision(message).1.into_owned())
+            .collect();
+        let mode = self.config.batch_mode;
+        
+        let start_time = std::time::Instant::now();
+        let report = match mode {
+            BatchMode::Pipelined => {
+                // Nothing is sent unless every message fits
+                let serialized = messages.iter()
+                    .map(|message| self.serialize_checked(message, &counters))
+                    .collect::<MessageResult<Vec<_>>>()?;
+                let bytes = serialized.iter().map(Vec::len).sum();
+                let mut handles = Vec::with_capacity(serialized.len());
+                for payload in serialized {
+                    handles.push(self.publisher.publish(subject_str, Bytes::from(payload)).await?);
+                }
+                let mut failed = 0;
+                for handle in handles {
+                    if let Err(e) = handle.confirmed().await {
+                        tracing::warn!("Batch publish to {} failed: {}", subject_str, e);
+                        failed += 1;
+                    }
+                }
+                BatchReport { mode, messages: messages.len(), failed, bytes, latency_ms: 0 }
+            }
+            BatchMode::Envelope => {
+                let serialized = self.serialize_checked(&envelope(subject_str, &messages)?, &counters)?;
+                let bytes = serialized.len();
+                self.publisher.publish(subject_str, Bytes::from(serialized)).await?.confirmed().await?;
+                BatchReport { mode, messages: messages.len(), failed: 0, bytes, latency_ms: 0 }
+            }
+        };
+        let report = BatchReport { latency_ms: start_time.elapsed().as_millis() as u64, ..report };
+        
+        let sent = (report.messages - report.failed) as u64;
+        counters.messages_sent.fetch_add(sent, Ordering::Relaxed);
+        counters.error_count.fetch_add(report.failed as u64, Ordering::Relaxed);
+        {
+            let mut stats = self.stats.write().await;
+            stats.messages_sent += sent;
+            stats.error_count += report.failed as u64;
+            stats.batches.record(&report);
+        }
+        self.sli.observe_elapsed(Sli::PublishLatency, start_time, &[("subject", subject_str), ("batch", "true")]);
+        
+        tracing::debug!("Published batch of {} messages to {} in {}ms", report.messages, subject_str, report.latency_ms);
+        Ok(report)
+    }
+    
+    /// Wait for outstanding pipelined publishes and flush the connection
+    pub async fn flush(&self) -> MessageResult<()> {
+        self.publisher.flush().await
+    }
+    
+    /// Pipelined publishes queued or awaiting completion
+    pub fn pending_publishes(&self) -> u64 {
+        self.publisher.pending()
+    }
+    
     /// Subscribe to a subject and return a message receiver
-    pub async fn subscribe_to_subject(&self, subject: &str) -> MessageResult<mpsc::UnboundedReceiver<Message>> {
+    ///
+    /// The subject may be a wildcard pattern (`swarm.documents.*`,
+    /// `swarm.>`); messages received through it carry the subject they were
+    /// published to, so a `MessageSubscriptionManager` can dispatch them to
+    /// per-subject handlers.
+    pub async fn subscribe_to_subject(&self, subject: &Subject) -> MessageResult<BrokerSubscription> {
+        let subject = subject.as_str();
+        self.check_permission(SubjectOperation::Subscribe, subject)?;
+        let target = SubscriptionTarget { subject: subject.to_string(), queue_group: None };
+        let subscription = target.subscribe(&self.client).await?;
+        
+        let receiver = self.forward(target, subscription).await;
+        tracing::info!("Subscribed to subject: {}", subject);
+        Ok(receiver)
+    }
+    
+    /// Subscribe to a subject as a member of a queue group
+    ///
+    /// Each message is delivered to exactly one member of `group`, so a pool
+    /// of workers subscribed with the same group shares the load.
+    pub async fn subscribe_queue(&self, subject: &Subject, group: &str) -> MessageResult<BrokerSubscription> {
+        let subject = subject.as_str();
+        self.check_permission(SubjectOperation::Subscribe, subject)?;
+        let target = SubscriptionTarget { subject: subject.to_string(), queue_group: Some(group.to_string()) };
+        let subscription = target.subscribe(&self.client).await?;
+        
+        let receiver = self.forward(target, subscription).await;
+        tracing::info!("Subscribed to subject: {} in queue group: {}", subject, group);
+        Ok(receiver)
+    }
+    
+    /// Forward messages from a NATS subscription to a new receiver
+    ///
+    /// If the subscription's stream ends, or its forwarding task panics or
+    /// hangs, while the subject is still subscribed, it is re-created with
+    /// the reconnection backoff; once `max_reconnect_attempts` fail, the
+    /// receiver is closed.
+    async fn forward(&self, target: SubscriptionTarget, subscription: async_nats::Subscriber) -> BrokerSubscription {
+        let subject = target.subject.clone();
         let (tx, rx) = mpsc::unbounded_channel();
+        let counters = self.counters_for(&subject).await;
         
         // Store the sender for cleanup
         {
             let mut subscriptions = self.subscriptions.write().await;
-            subscriptions.insert(subject.to_string(), tx.clone());
-        }
-        
-        // Create NATS subscription
-        let mut subscription = self.client.subscribe(subject.to_string()).await
-            .map_err(|e| MessageError::Nats(e.into()))?;
-        
-        // Spawn task to handle incoming messages
-        let stats = self.stats.clone();
-        tokio::spawn(async move {
-            while let Some(nats_message) = subscription.next().await {
-                match serde_json::from_slice::<Message>(&nats_message.payload) {
-                    Ok(message) => {
-                        if let Err(e) = tx.send(message) {
-                            tracing::error!("Failed to send message to receiver: {}", e);
-                            break;
-                        }
-                        
-                        // Update statistics
-                        {
-                            let mut stats = stats.write().await;
-                            stats.messages_received += 1;
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!("Failed to deserialize message: {}", e);
-                        {
-                            let mut stats = stats.write().await;
-                            stats.error_count += 1;
-                        }
-                    }
-                }
-            }
-        });
+            subscriptions.insert(subject.clone(), tx.clone());
+        }
+        
+        // Spawn a supervised task to handle incoming messages
+        let forwarder = Forwarder {
+            target,
+            tx,
+            counters: counters.clone(),
+            stats: self.stats.clone(),
+            sampler: self.sampler.clone(),
+            client: self.client.clone(),
+            config: self.config.clone(),
+            subscriptions: self.subscriptions.clone(),
+        };
+        tokio::spawn(forwarder.supervise(subscription));
         
         // Update statistics
         {
@@ -135,8 +580,7 @@ impl NatsBroker {
             stats.active_subscriptions += 1;
         }
         
-        tracing::info!("Subscribed to subject: {}", subject);
-        Ok(rx)
+        BrokerSubscription::with_counters(subject, rx, counters)
     }
     
     /// Unsubscribe from a subject
@@ -159,6 +603,10 @@ impl NatsBroker {
     
     /// Close the broker connection
     pub async fn close(self) -> MessageResult<()> {
+        if let Err(e) = self.flush().await {
+            tracing::warn!("Failed to flush pending publishes on close: {}", e);
+        }
+        
         // Close all subscriptions
         {
             let subscriptions = self.subscriptions.read().await;
@@ -179,6 +627,222 @@ impl NatsBroker {
     }
 }
 
+/// A subscription's forwarding task, with what it needs to be restarted
+#[derive(Clone)]
+struct Forwarder {
+    target: SubscriptionTarget,
+    tx: mpsc::UnboundedSender<Message>,
+    counters: Arc<SubjectCounters>,
+    stats: Arc<RwLock<BrokerStats>>,
+    sampler: TraceSampler,
+    client: Arc<async_nats::Client>,
+    config: NatsConfig,
+    subscriptions: Arc<RwLock<HashMap<String, mpsc::UnboundedSender<Message>>>>,
+}
+
+impl Forwarder {
+    /// Whether the receiver and the subscription are still there
+    async fn is_wanted(&self) -> bool {
+        !self.tx.is_closed() && self.subscriptions.read().await.contains_key(&self.target.subject)
+    }
+    
+    /// Run the forwarding task, replacing it whenever it panics or stops ticking
+    async fn supervise(self, subscription: async_nats::Subscriber) {
+        self.counters.record_tick();
+        let mut handle = tokio::spawn(self.clone().run(subscription));
+        while let ForwarderExit::Failed(reason) = watch_forwarder(&mut handle, &self.counters, &self.config.subscription_liveness).await {
+            if !self.is_wanted().await {
+                break;
+            }
+            tracing::error!("Forwarding task for {} failed ({:?}), resubscribing", self.target.subject, reason);
+            self.announce_restart(reason).await;
+            match resubscribe(&self.client, &self.target, &self.config).await {
+                Some(subscription) => {
+                    self.counters.record_tick();
+                    handle = tokio::spawn(self.clone().run(subscription));
+                }
+                None => {
+                    self.stats.write().await.error_count += 1;
+                    break;
+                }
+            }
+        }
+    }
+    
+    /// Count a restart and publish it to `SUBSCRIPTION_RESTARTED_SUBJECT`
+    async fn announce_restart(&self, reason: RestartReason) {
+        self.stats.write().await.resubscription_count += 1;
+        let restart = SubscriptionRestart {
+            subject: self.target.subject.clone(),
+            queue_group: self.target.queue_group.clone(),
+            reason,
+            restarts: self.counters.record_restart(),
+            restarted_at: Utc::now(),
+        };
+        let published = match restart.to_message().and_then(|message| Ok(serde_json::to_vec(&message)?)) {
+            Ok(payload) => self.client.publish(SUBSCRIPTION_RESTARTED_SUBJECT.to_string(), Bytes::from(payload)).await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = published {
+            tracing::warn!("Failed to announce restart of subscription to {}: {}", self.target.subject, e);
+        }
+    }
+    
+    /// Forward messages, ticking while alive and re-creating the subscription if its stream ends
+    async fn run(self, mut subscription: async_nats::Subscriber) {
+        let mut tick = tokio::time::interval(self.config.subscription_liveness.tick_interval());
+        loop {
+            loop {
+                let nats_message = tokio::select! {
+                    next = subscription.next() => match next {
+                        Some(nats_message) => nats_message,
+                        None => break,
+                    },
+                    _ = tick.tick() => {
+                        self.counters.record_tick();
+                        continue;
+                    }
+                };
+                self.counters.record_tick();
+                if !self.deliver(nats_message).await {
+                    return;
+                }
+            }
+            
+            if !self.is_wanted().await {
+                return;
+            }
+            tracing::warn!("Subscription to {} ended, resubscribing", self.target.subject);
+            let resubscribed = resubscribe(&self.client, &self.target, &self.config);
+            tokio::pin!(resubscribed);
+            // Keep ticking through the backoff so the supervisor does not take over
+            let resubscribed = loop {
+                tokio::select! {
+                    resubscribed = &mut resubscribed => break resubscribed,
+                    _ = tick.tick() => self.counters.record_tick(),
+                }
+            };
+            subscription = match resubscribed {
+                Some(subscription) => subscription,
+                None => {
+                    self.stats.write().await.error_count += 1;
+                    return;
+                }
+            };
+        }
+    }
+    
+    /// Hand one NATS message to the receiver; false once the receiver is gone
+    ///
+    /// Batch envelopes are unpacked and their messages delivered in order.
+    async fn deliver(&self, nats_message: async_nats::Message) -> bool {
+        let message = match serde_json::from_slice::<Message>(&nats_message.payload) {
+            Ok(message) => message,
+            Err(e) => {
+                self.record_undecodable(e.into()).await;
+                return true;
+            }
+        };
+        let messages = match unwrap_envelope(&message) {
+            None => vec![message],
+            Some(Ok(messages)) => messages,
+            Some(Err(e)) => {
+                self.record_undecodable(e).await;
+                return true;
+            }
+        };
+        
+        for mut message in messages {
+            // Handlers of a wildcard subscription need the subject the message was published to
+            if self.target.is_wildcard() {
+                message.subject = nats_message.subject.to_string();
+            }
+            if !self.deliver_message(message, nats_message.reply.as_deref()).await {
+                return false;
+            }
+        }
+        true
+    }
+    
+    /// Count a message that could not be deserialized
+    async fn record_undecodable(&self, error: MessageError) {
+        tracing::error!("Failed to deserialize message: {}", error);
+        self.counters.record_error();
+        self.stats.write().await.error_count += 1;
+    }
+    
+    /// Hand one decoded message to the receiver; false once the receiver is gone
+    async fn deliver_message(&self, mut message: Message, reply: Option<&str>) -> bool {
+        if MessageValidator::is_expired_at(&message, Utc::now()) {
+            expire(&self.client, &self.stats, self.config.expired_messages, message).await;
+            return true;
+        }
+        
+        if let Some(reply) = reply {
+            message.headers.insert(REPLY_TO_HEADER, reply.to_string());
+        }
+        let decision = self.sampler.sample_message(&message);
+        let span = match TraceContext::from_headers(&message.headers) {
+            Some(publish) => publish.with_decision(decision).continue_span("deliver", message.id).1,
+            None => self.sampler.span(decision, "deliver", message.id),
+        };
+        span.in_scope(|| {
+            tracing::debug!("Delivering message from subject: {}", message.subject);
+        });
+        // Count before sending so a fast consumer never sees pending underflow
+        self.counters.record_delivered();
+        if let Err(e) = self.tx.send(message) {
+            self.counters.record_undelivered();
+            tracing::error!("Failed to send message to receiver: {}", e);
+            return false;
+        }
+        
+        // Update statistics
+        {
+            let mut stats = self.stats.write().await;
+            stats.messages_received += 1;
+        }
+        true
+    }
+}
+
+/// Drop or dead-letter a message received after its TTL
+async fn expire(client: &async_nats::Client, stats: &RwLock<BrokerStats>, policy: ExpiredMessagePolicy, message: Message) {
+    stats.write().await.expired_count += 1;
+    tracing::debug!("Message {} on {} expired, {:?}", message.id, message.subject, policy);
+    if policy == ExpiredMessagePolicy::Drop {
+        return;
+    }
+    
+    let dead_letter = expired_dead_letter(message, Utc::now());
+    let published = match serde_json::to_vec(&dead_letter) {
+        Ok(payload) => client.publish(EXPIRED_MESSAGE_SUBJECT.to_string(), Bytes::from(payload)).await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = published {
+        tracing::error!("Failed to dead-letter expired message {}: {}", dead_letter.id, e);
+        stats.write().await.error_count += 1;
+    }
+}
+
+/// Re-create a subscription, backing off between attempts
+async fn resubscribe(client: &async_nats::Client, target: &SubscriptionTarget, config: &NatsConfig) -> Option<async_nats::Subscriber> {
+    for attempt in 1..=config.max_reconnect_attempts as usize {
+        tokio::time::sleep(config.reconnect_delay(attempt)).await;
+        match target.subscribe(client).await {
+            Ok(subscription) => {
+                tracing::info!("Resubscribed to {} after {} attempt(s)", target.subject, attempt);
+                return Some(subscription);
+            }
+            Err(e) => tracing::warn!("Resubscribing to {} failed (attempt {}): {}", target.subject, attempt, e),
+        }
+    }
+    tracing::error!("Giving up on subscription to {} after {} attempts", target.subject, config.max_reconnect_attempts);
+    None
+}
+
 #[async_trait]
 impl MessageBroker for NatsBroker {
     async fn publish(&self, subject: &str, message: &[u8]) -> Result<()> {
@@ -187,18 +851,31 @@ impl MessageBroker for NatsBroker {
             id: Uuid::new_v4(),
             subject: subject.to_string(),
             payload: message.to_vec(),
-            headers: HashMap::new(),
+            headers: MessageHeaders::new(),
             timestamp: Utc::now(),
             ttl_ms: None,
         };
         
-        self.publish_message(subject, &message).await?;
+        self.publish_message(&Subject::parse(subject)?, &message).await?;
         Ok(())
     }
     
+    async fn request(&self, subject: &str, message: &[u8], timeout: Duration) -> Result<Message> {
+        let message = Message {
+            id: Uuid::new_v4(),
+            subject: subject.to_string(),
+            payload: message.to_vec(),
+            headers: MessageHeaders::new(),
+            timestamp: Utc::now(),
+            ttl_ms: None,
+        };
+        
+        Ok(self.request_message(&Subject::parse(subject)?, &message, timeout).await?)
+    }
+    
     async fn subscribe(&self, subject: &str) -> Result<Box<dyn MessageSubscription>> {
-        let receiver = self.subscribe_to_subject(subject).await?;
-        Ok(Box::new(NatsMessageSubscription::new(subject.to_string(), receiver)))
+        let subscription = self.subscribe_to_subject(&Subject::parse(subject)?).await?;
+        Ok(Box::new(subscription))
     }
     
     async fn get_stats(&self) -> MessageBrokerStats {
@@ -213,23 +890,53 @@ impl MessageBroker for NatsBroker {
     }
 }
 
-/// NATS message subscription implementation
-pub struct NatsMessageSubscription {
+/// Subscription to a subject on a message broker
+pub struct BrokerSubscription {
     subject: String,
     receiver: mpsc::UnboundedReceiver<Message>,
+    counters: Arc<SubjectCounters>,
 }
 
-impl NatsMessageSubscription {
+impl BrokerSubscription {
     pub fn new(subject: String, receiver: mpsc::UnboundedReceiver<Message>) -> Self {
-        Self { subject, receiver }
+        Self::with_counters(subject, receiver, Arc::default())
+    }
+    
+    /// Create a subscription that reports consumption to shared subject counters
+    pub fn with_counters(subject: String, receiver: mpsc::UnboundedReceiver<Message>, counters: Arc<SubjectCounters>) -> Self {
+        Self { subject, receiver, counters }
+    }
+    
+    /// Subject this subscription is bound to
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+    
+    /// Wait for the next message
+    pub async fn recv(&mut self) -> Option<Message> {
+        let message = self.receiver.recv().await?;
+        self.counters.record_consumed(&message);
+        Some(message)
+    }
+    
+    /// Take the next message if one is already waiting
+    pub fn try_recv(&mut self) -> Result<Message, mpsc::error::TryRecvError> {
+        let message = self.receiver.try_recv()?;
+        self.counters.record_consumed(&message);
+        Ok(message)
+    }
+    
+    /// Counters and lag for this subscription's subject
+    pub fn stats(&self) -> SubjectStats {
+        self.counters.snapshot()
     }
 }
 
-impl MessageSubscription for NatsMessageSubscription {
+impl MessageSubscription for BrokerSubscription {
     fn next_message(&mut self) -> Result<Option<Message>> {
         // This is a blocking call, but we need to make it async-compatible
         // In a real implementation, you'd want to use async channels
-        match self.receiver.try_recv() {
+        match self.try_recv() {
             Ok(message) => Ok(Some(message)),
             Err(mpsc::error::TryRecvError::Empty) => Ok(None),
             Err(mpsc::error::TryRecvError::Disconnected) => Ok(None),
@@ -267,12 +974,132 @@ mod tests {
     
     #[test]
     fn test_nats_stats_default() {
-        let stats = NatsStats::default();
+        let stats = BrokerStats::default();
         assert_eq!(stats.messages_sent, 0);
         assert_eq!(stats.messages_received, 0);
         assert_eq!(stats.active_subscriptions, 0);
         assert!(!stats.is_connected);
         assert_eq!(stats.reconnection_count, 0);
         assert_eq!(stats.error_count, 0);
+        assert!(stats.subjects.is_empty());
+    }
+    
+    #[test]
+    fn test_reconnect_delay_backs_off_exponentially() {
+        let config = NatsConfig { reconnect_delay_ms: 500, max_reconnect_delay_ms: 3000, ..Default::default() };
+        let delays: Vec<u64> = (1..=5).map(|attempt| config.reconnect_delay(attempt).as_millis() as u64).collect();
+        assert_eq!(delays, vec![500, 1000, 2000, 3000, 3000]);
+        assert_eq!(config.reconnect_delay(usize::MAX).as_millis(), 3000);
+    }
+    
+    #[tokio::test]
+    async fn test_connection_events_update_stats() {
+        let stats = RwLock::new(BrokerStats::default());
+        record_connection_event(&stats, &async_nats::Event::Connected).await;
+        assert!(stats.read().await.is_connected);
+        assert_eq!(stats.read().await.reconnection_count, 0);
+        
+        record_connection_event(&stats, &async_nats::Event::Disconnected).await;
+        assert!(!stats.read().await.is_connected);
+        assert!(stats.read().await.last_disconnected.is_some());
+        
+        record_connection_event(&stats, &async_nats::Event::Connected).await;
+        record_connection_event(&stats, &async_nats::Event::Connected).await;
+        let stats = stats.read().await;
+        assert!(stats.is_connected);
+        assert_eq!(stats.reconnection_count, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_subscription_tracks_pending_and_lag() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let counters = Arc::new(SubjectCounters::default());
+        let mut subscription = BrokerSubscription::with_counters("swarm.test".to_string(), rx, counters.clone());
+        
+        for age_ms in [500, 20] {
+            counters.record_delivered();
+            tx.send(Message {
+                id: Uuid::new_v4(),
+                subject: "swarm.test".to_string(),
+                payload: Vec::new(),
+                headers: MessageHeaders::new(),
+                timestamp: Utc::now() - chrono::Duration::milliseconds(age_ms),
+                ttl_ms: None,
+            }).unwrap();
+        }
+        assert_eq!(subscription.stats().pending, 2);
+        
+        subscription.recv().await.unwrap();
+        let stats = subscription.stats();
+        assert_eq!(stats.pending, 1);
+        assert!(stats.last_lag_ms >= 500);
+        
+        subscription.try_recv().unwrap();
+        let stats = subscription.stats();
+        assert_eq!(stats.messages_received, 2);
+        assert_eq!(stats.pending, 0);
+        assert!(stats.last_lag_ms < 500);
+        assert!(stats.max_lag_ms >= 500);
+    }
+    
+    #[test]
+    fn test_expired_dead_letter_keeps_message_without_ttl() {
+        let sent = Utc::now() - chrono::Duration::minutes(10);
+        let message = Message {
+            id: Uuid::new_v4(),
+            subject: "swarm.tasks".to_string(),
+            payload: b"task".to_vec(),
+            headers: MessageHeaders::new(),
+            timestamp: sent,
+            ttl_ms: Some(60_000),
+        };
+        assert!(MessageValidator::is_expired_at(&message, Utc::now()));
+        
+        let dead_letter = expired_dead_letter(message.clone(), Utc::now());
+        assert_eq!((dead_letter.id, &dead_letter.subject, dead_letter.timestamp), (message.id, &message.subject, sent));
+        assert!(dead_letter.headers.contains_key(EXPIRED_AT_HEADER));
+        assert!(!MessageValidator::is_expired_at(&dead_letter, Utc::now()));
+        
+        let config: NatsConfig = serde_json::from_value(serde_json::json!({
+            "url": "nats://localhost:4222",
+            "connection_timeout_ms": 5000,
+            "max_reconnect_attempts": 10,
+            "reconnect_delay_ms": 1000,
+            "max_message_size": 1024,
+            "enable_tls": false,
+            "tls_cert_path": null,
+            "tls_key_path": null,
+            "tls_ca_path": null,
+            "expired_messages": "dead_letter",
+        })).unwrap();
+        assert_eq!(config.expired_messages, ExpiredMessagePolicy::DeadLetter);
+        assert_eq!(NatsConfig::default().expired_messages, ExpiredMessagePolicy::Drop);
+    }
+    
+    #[test]
+    fn test_export_metrics_tags_subjects() {
+        struct Recorder(std::sync::Mutex<Vec<String>>);
+        
+        impl swarm_core::MetricsCollector for Recorder {
+            fn record_metric(&self, name: &str, value: f64, tags: &[(&str, &str)]) {
+                let tags: Vec<String> = tags.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+                self.0.lock().unwrap().push(format!("{}{{{}}} {}", name, tags.join(","), value));
+            }
+            fn increment_counter(&self, _name: &str, _tags: &[(&str, &str)]) {}
+            fn record_histogram(&self, _name: &str, _value: f64, _tags: &[(&str, &str)]) {}
+            fn get_metrics(&self) -> HashMap<String, swarm_core::MetricValue> {
+                HashMap::new()
+            }
+        }
+        
+        let mut stats = BrokerStats { messages_sent: 3, ..Default::default() };
+        stats.subjects.insert("swarm.results".to_string(), SubjectStats { pending: 7, ..Default::default() });
+        
+        let recorder = Recorder(std::sync::Mutex::new(Vec::new()));
+        stats.export_metrics(&recorder, "nats");
+        
+        let recorded = recorder.0.lock().unwrap();
+        assert!(recorded.contains(&"nats.messages_sent{} 3".to_string()));
+        assert!(recorded.contains(&"nats.subject.pending{subject=swarm.results} 7".to_string()));
     }
 }
@@ -0,0 +1,823 @@
+//! NATS Coordinator
+//!
+//! A `WorkerCoordinator` whose workers run on other machines and are only
+//! reached through the broker. Workers register on the registration subject
+//! and receive their tasks on a subject of their own below
+//! `swarm.tasks.assignments`; tasks submitted to `swarm.tasks.assignments`
+//! itself are queued and dispatched to the best scoring available worker.
+//! Results are consumed from `swarm.tasks.results`. Workers report health
+//! through heartbeats on `swarm.workers.health`; a worker that misses too
+//! many is marked unavailable and its unfinished tasks are dispatched again.
+//! Failed tasks are retried with backoff until `max_retries` is exhausted
+//! and then published to the dead-letter subject.
+//! Workers are sent at most `max_concurrent_tasks` tasks at a time, and the
+//! queue can be capped for `try_submit_task`. Administration requests on
+//! the `swarm.admin` subjects are answered while the coordinator runs.
+
+use super::*;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use swarm_core::{
+    cancellable_sleep, supports_task_type, CancellationToken, CoordinatorStats, DeadLetter, FailedAttempt, Heartbeat,
+    LivenessConfig, LivenessTracker, Message, MessageBroker, MessageSubscription, QueueSaturation, RegistrationAck,
+    RetryPolicy, SchedulingWeights, ScheduledRetry, Service, Subject, SwarmError, SwarmResult, Task, TaskResult, TaskStatus, TaskType, TraceContext, Worker, WorkerConfig, WorkerCoordinator,
+    WorkerDeregistration, WorkerRegistration, WorkerStatus, WORKER_DEREGISTRATION_SUBJECT,
+};
+use swarm_core::types::PerformanceProfile;
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Finished tasks whose status is remembered for administration requests
+pub const FINISHED_TASK_HISTORY: usize = 10_000;
+
+/// Distributed coordinator configuration
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NatsCoordinatorConfig {
+    /// Heartbeat interval and tolerated misses of remote workers
+    pub liveness: LivenessConfig,
+    
+    /// Weights used to pick a worker for a task
+    #[serde(default)]
+    pub scheduling: SchedulingWeights,
+    
+    /// Interval at which subscriptions are checked while idle
+    pub poll_interval_ms: u64,
+    
+    /// Queued tasks above which `try_submit_task` refuses tasks (unlimited when unset)
+    #[serde(default)]
+    pub max_pending_tasks: Option<usize>,
+    
+    /// Backoff between retries of failed tasks
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+}
+
+impl Default for NatsCoordinatorConfig {
+    fn default() -> Self {
+        Self {
+            liveness: LivenessConfig::default(),
+            scheduling: SchedulingWeights::default(),
+            poll_interval_ms: 10,
+            max_pending_tasks: None,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+}
+
+/// A worker known only through the broker
+struct RemoteWorker {
+    config: WorkerConfig,
+    subject: Subject,
+    status: WorkerStatus,
+    in_flight: HashSet<Uuid>,
+}
+
+impl RemoteWorker {
+    fn accepts_tasks(&self) -> bool {
+        !matches!(self.status, WorkerStatus::Error(_) | WorkerStatus::Shutdown | WorkerStatus::Unavailable)
+            && self.in_flight.len() < self.config.max_concurrent_tasks
+    }
+}
+
+/// Subscriptions consumed while the coordinator runs
+struct Subscriptions {
+    submissions: Box<dyn MessageSubscription>,
+    results: Box<dyn MessageSubscription>,
+    health: Box<dyn MessageSubscription>,
+    registrations: Box<dyn MessageSubscription>,
+    deregistrations: Box<dyn MessageSubscription>,
+    admin: Vec<Box<dyn MessageSubscription>>,
+}
+
+/// Coordinator dispatching tasks to remote workers over the broker
+///
+/// Usually backed by a `NatsBroker`; any `MessageBroker` carries the same
+/// protocol.
+pub struct NatsCoordinator {
+    broker: Arc<dyn MessageBroker>,
+    router: MessageRouter,
+    config: NatsCoordinatorConfig,
+    workers: HashMap<Uuid, RemoteWorker>,
+    liveness: LivenessTracker,
+    pending: VecDeque<Task>,
+    in_flight: HashMap<Uuid, (Uuid, Task)>,
+    retrying: HashMap<Uuid, ScheduledRetry>,
+    failed_attempts: HashMap<Uuid, Vec<FailedAttempt>>,
+    tasks_completed: u64,
+    tasks_failed: u64,
+    processing_time_ms: u64,
+    started_at: DateTime<Utc>,
+    rejected_submissions: u64,
+    result_subscribers: Vec<mpsc::UnboundedSender<TaskResult>>,
+    dead_letter_subscribers: Vec<mpsc::UnboundedSender<DeadLetter>>,
+    finished: HashMap<Uuid, TaskStatus>,
+    finished_order: VecDeque<Uuid>,
+}
+
+impl NatsCoordinator {
+    pub fn new(broker: Arc<dyn MessageBroker>, router: MessageRouter) -> Self {
+        Self::with_config(broker, router, NatsCoordinatorConfig::default())
+    }
+    
+    pub fn with_config(broker: Arc<dyn MessageBroker>, router: MessageRouter, config: NatsCoordinatorConfig) -> Self {
+        Self {
+            broker,
+            router,
+            liveness: LivenessTracker::new(config.liveness.clone()),
+            config,
+            workers: HashMap::new(),
+            pending: VecDeque::new(),
+            in_flight: HashMap::new(),
+            retrying: HashMap::new(),
+            failed_attempts: HashMap::new(),
+            tasks_completed: 0,
+            tasks_failed: 0,
+            processing_time_ms: 0,
+            started_at: Utc::now(),
+            rejected_submissions: 0,
+            result_subscribers: Vec::new(),
+            dead_letter_subscribers: Vec::new(),
+            finished: HashMap::new(),
+            finished_order: VecDeque::new(),
+        }
+    }
+    
+    /// Receive every result consumed from the result subject
+    pub fn subscribe_results(&mut self) -> mpsc::UnboundedReceiver<TaskResult> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.result_subscribers.push(sender);
+        receiver
+    }
+    
+    /// Receive every task that fails permanently from now on
+    pub fn subscribe_dead_letters(&mut self) -> mpsc::UnboundedReceiver<DeadLetter> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.dead_letter_subscribers.push(sender);
+        receiver
+    }
+    
+    /// Subject the tasks of a worker are published on
+    pub fn assignment_subject(&self, worker_id: Uuid) -> MessageResult<Subject> {
+        Ok(self.router.task_assignment_subject().child(&[&worker_id.to_string()])?)
+    }
+    
+    /// Register a remote worker, returning the subject its tasks are published on
+    ///
+    /// A worker registering again keeps the tasks it was assigned before.
+    pub fn register_remote(&mut self, config: WorkerConfig) -> MessageResult<Subject> {
+        let worker_id = config.id;
+        let subject = self.assignment_subject(worker_id)?;
+        let in_flight = self.workers.remove(&worker_id).map(|worker| worker.in_flight).unwrap_or_default();
+        self.workers.insert(worker_id, RemoteWorker { config, subject: subject.clone(), status: WorkerStatus::Idle, in_flight });
+        self.liveness.track(worker_id, Utc::now());
+        tracing::info!("Registered remote worker {} on {}", worker_id, subject);
+        Ok(subject)
+    }
+    
+    /// Remove a worker, queueing its unfinished tasks for dispatch to others
+    pub fn unregister_remote(&mut self, worker_id: Uuid) -> bool {
+        let Some(worker) = self.workers.remove(&worker_id) else {
+            tracing::warn!("Attempted to unregister unknown worker {}", worker_id);
+            return false;
+        };
+        self.liveness.forget(worker_id);
+        let requeued = self.requeue(worker.in_flight);
+        tracing::info!("Unregistered remote worker {}, requeued {} task(s)", worker_id, requeued);
+        true
+    }
+    
+    /// Queue a task for dispatch once a worker is available
+    pub fn submit_task(&mut self, task: Task) {
+        tracing::debug!("Submitted task {} of type {}", task.id, task.task_type);
+        self.pending.push_back(task);
+    }
+    
+    /// Queue a task unless `max_pending_tasks` are queued already
+    pub fn try_submit_task(&mut self, task: Task) -> SwarmResult<()> {
+        let pending = self.pending.len();
+        if let Some(capacity) = self.config.max_pending_tasks.filter(|max| pending >= *max) {
+            self.rejected_submissions += 1;
+            tracing::warn!("Refused task {}: queue full with {} pending tasks", task.id, pending);
+            return Err(SwarmError::QueueFull { pending, capacity });
+        }
+        self.submit_task(task);
+        Ok(())
+    }
+    
+    /// Publish a task to the best scoring available worker
+    pub async fn dispatch(&mut self, mut task: Task) -> Result<Uuid> {
+        let scheduling = &self.config.scheduling;
+        let (worker_id, subject) = self.workers.iter()
+            .filter(|(worker_id, worker)| self.liveness.is_available(worker_id) && worker.accepts_tasks())
+            .filter_map(|(worker_id, worker)| {
+                let score = scheduling.score(&worker.config, &task, worker.in_flight.len())?;
+                Some((score.score, *worker_id, &worker.subject))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0).then(b.1.cmp(&a.1)))
+            .map(|(_, worker_id, subject)| (worker_id, subject.clone()))
+            .ok_or_else(|| anyhow::anyhow!("no available worker for task {} of type {}", task.id, task.task_type))?;
+        
+        let span = TraceContext::continue_task(&mut task, "assign");
+        self.broker.publish(subject.as_str(), &serde_json::to_vec(&task)?).instrument(span.clone()).await?;
+        if let Some(worker) = self.workers.get_mut(&worker_id) {
+            worker.in_flight.insert(task.id);
+        }
+        span.in_scope(|| tracing::debug!("Dispatched task {} to worker {}", task.id, worker_id));
+        self.in_flight.insert(task.id, (worker_id, task));
+        Ok(worker_id)
+    }
+    
+    /// Dispatch queued tasks in order while workers are available
+    pub async fn dispatch_pending(&mut self) -> Result<usize> {
+        let mut dispatched = 0;
+        let mut waiting = VecDeque::new();
+        while let Some(task) = self.pending.pop_front() {
+            if !self.workers.values().any(|worker| supports_task_type(&worker.config, &task.task_type)) {
+                waiting.push_back(task);
+                continue;
+            }
+            match self.dispatch(task.clone()).await {
+                Ok(_) => dispatched += 1,
+                Err(e) => {
+                    tracing::debug!("Task {} stays queued: {}", task.id, e);
+                    waiting.push_back(task);
+                }
+            }
+        }
+        self.pending = waiting;
+        Ok(dispatched)
+    }
+    
+    /// Record a result published by a worker
+    ///
+    /// Results of tasks that are not in flight, such as duplicates, are
+    /// ignored. A failed task is retried after its backoff until
+    /// `max_retries` is exhausted, then published as a dead letter.
+    pub async fn handle_result(&mut self, result: TaskResult) -> Result<()> {
+        let span = TraceContext::from_metadata(&result.metadata)
+            .map_or_else(tracing::Span::none, |process| process.continue_span("result", result.task_id).1);
+        let Some(dead_letter) = span.in_scope(|| self.record_result(result)) else {
+            return Ok(());
+        };
+        self.dead_letter_subscribers.retain(|subscriber| subscriber.send(dead_letter.clone()).is_ok());
+        let message = MessageSerializer::serialize_dead_letter(&dead_letter)?;
+        self.broker.publish(self.router.task_deadletter_subject().as_str(), &message.payload).instrument(span).await
+    }
+    
+    /// Account for a result, returning the dead letter of a task that failed permanently
+    fn record_result(&mut self, result: TaskResult) -> Option<DeadLetter> {
+        let Some((worker_id, task)) = self.in_flight.remove(&result.task_id) else {
+            tracing::debug!("Ignoring result for task {} that is not in flight", result.task_id);
+            return None;
+        };
+        if let Some(worker) = self.workers.get_mut(&worker_id) {
+            worker.in_flight.remove(&result.task_id);
+        }
+        self.processing_time_ms += result.processing_time_ms;
+        self.result_subscribers.retain(|subscriber| subscriber.send(result.clone()).is_ok());
+        if result.status != TaskStatus::Failed {
+            self.failed_attempts.remove(&task.id);
+            self.tasks_completed += 1;
+            self.remember_finished(task.id, result.status);
+            return None;
+        }
+        
+        let error = result.error.clone().unwrap_or_else(|| "unknown error".to_string());
+        let mut errors = self.failed_attempts.remove(&task.id).unwrap_or_default();
+        errors.push(FailedAttempt { attempt: task.retry_count + 1, worker_id: Some(worker_id), error, failed_at: result.completed_at });
+        if task.retry_count < task.max_retries {
+            let retry_at = Utc::now() + self.config.retry_policy.backoff(task.retry_count + 1);
+            tracing::warn!("Task {} failed (attempt {} of {}), retrying at {}: {}",
+                task.id, task.retry_count + 1, task.max_retries + 1, retry_at, errors[errors.len() - 1].error);
+            self.failed_attempts.insert(task.id, errors);
+            let task = Task { retry_count: task.retry_count + 1, status: TaskStatus::Retrying, ..task };
+            self.retrying.insert(task.id, ScheduledRetry { task, retry_at });
+            return None;
+        }
+        
+        tracing::error!("Task {} failed permanently after {} attempts", task.id, errors.len());
+        self.tasks_failed += 1;
+        self.remember_finished(task.id, TaskStatus::Failed);
+        Some(DeadLetter { task, errors, dead_lettered_at: Utc::now() })
+    }
+    
+    /// Move failed tasks whose retry backoff has passed back into the queue
+    pub fn release_due_retries(&mut self, now: DateTime<Utc>) -> usize {
+        let mut due: Vec<(DateTime<Utc>, Uuid)> = self.retrying.values()
+            .filter(|retry| retry.retry_at <= now)
+            .map(|retry| (retry.retry_at, retry.task.id))
+            .collect();
+        due.sort();
+        for (_, task_id) in &due {
+            if let Some(retry) = self.retrying.remove(task_id) {
+                tracing::debug!("Requeueing task {} for retry", task_id);
+                self.pending.push_back(Task { status: TaskStatus::Pending, ..retry.task });
+            }
+        }
+        due.len()
+    }
+    
+    fn remember_finished(&mut self, task_id: Uuid, status: TaskStatus) {
+        if self.finished.insert(task_id, status).is_none() {
+            self.finished_order.push_back(task_id);
+            if self.finished_order.len() > FINISHED_TASK_HISTORY {
+                if let Some(forgotten) = self.finished_order.pop_front() {
+                    self.finished.remove(&forgotten);
+                }
+            }
+        }
+    }
+    
+    /// Record a worker's heartbeat and reported status
+    pub fn handle_heartbeat(&mut self, heartbeat: &Heartbeat, now: DateTime<Utc>) {
+        let Some(worker) = self.workers.get_mut(&heartbeat.worker_id) else {
+            return tracing::debug!("Heartbeat from unknown worker {}", heartbeat.worker_id);
+        };
+        worker.status = heartbeat.status.clone();
+        if self.liveness.record(heartbeat, now) {
+            tracing::info!("Remote worker {} is available again", heartbeat.worker_id);
+        }
+    }
+    
+    /// Mark workers silent for too long unavailable and requeue their tasks
+    pub fn check_liveness(&mut self, now: DateTime<Utc>) -> Vec<Uuid> {
+        let expired = self.liveness.check(now);
+        for worker_id in &expired {
+            let Some(worker) = self.workers.get_mut(worker_id) else { continue };
+            worker.status = WorkerStatus::Unavailable;
+            let in_flight = std::mem::take(&mut worker.in_flight);
+            let requeued = self.requeue(in_flight);
+            tracing::warn!("Remote worker {} missed its heartbeats, requeued {} task(s)", worker_id, requeued);
+        }
+        expired
+    }
+    
+    /// Put in-flight tasks back at the front of the queue, in ID order
+    fn requeue(&mut self, task_ids: HashSet<Uuid>) -> usize {
+        let mut task_ids: Vec<Uuid> = task_ids.into_iter().collect();
+        task_ids.sort();
+        let mut requeued = 0;
+        for task_id in task_ids.into_iter().rev() {
+            if let Some((_, task)) = self.in_flight.remove(&task_id) {
+                self.pending.push_front(task);
+                requeued += 1;
+            }
+        }
+        requeued
+    }
+    
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+    
+    pub fn pending_tasks(&self) -> usize {
+        self.pending.len()
+    }
+    
+    pub fn in_flight_tasks(&self) -> usize {
+        self.in_flight.len()
+    }
+    
+    pub fn retrying_tasks(&self) -> usize {
+        self.retrying.len()
+    }
+    
+    /// Registered workers, ordered by name
+    pub fn worker_summaries(&self) -> Vec<WorkerSummary> {
+        let mut workers: Vec<WorkerSummary> = self.workers.iter()
+            .map(|(worker_id, worker)| WorkerSummary {
+                worker_id: *worker_id,
+                name: worker.config.name.clone(),
+                worker_type: worker.config.worker_type.clone(),
+                status: worker.status.clone(),
+                available: self.liveness.is_available(worker_id),
+                in_flight_tasks: worker.in_flight.len(),
+                max_concurrent_tasks: worker.config.max_concurrent_tasks,
+            })
+            .collect();
+        workers.sort_by(|a, b| a.name.cmp(&b.name).then(a.worker_id.cmp(&b.worker_id)));
+        workers
+    }
+    
+    /// Where a task is: queued, dispatched, or one of the recently finished
+    pub fn task_status(&self, task_id: Uuid) -> TaskStatusReport {
+        let (status, worker_id) = if let Some((worker_id, _)) = self.in_flight.get(&task_id) {
+            (Some(TaskStatus::Assigned), Some(*worker_id))
+        } else if self.pending.iter().any(|task| task.id == task_id) {
+            (Some(TaskStatus::Pending), None)
+        } else if self.retrying.contains_key(&task_id) {
+            (Some(TaskStatus::Retrying), None)
+        } else {
+            (self.finished.get(&task_id).cloned(), None)
+        };
+        TaskStatusReport { task_id, status, worker_id }
+    }
+    
+    async fn subscribe(&self) -> Result<Subscriptions> {
+        Ok(Subscriptions {
+            submissions: self.broker.subscribe(self.router.task_assignment_subject().as_str()).await?,
+            results: self.broker.subscribe(self.router.task_results_subject().as_str()).await?,
+            health: self.broker.subscribe(self.router.worker_health_subject().as_str()).await?,
+            registrations: self.broker.subscribe(self.router.worker_registration_subject().as_str()).await?,
+            deregistrations: self.broker.subscribe(WORKER_DEREGISTRATION_SUBJECT).await?,
+            admin: vec![
+                self.broker.subscribe(ADMIN_WORKERS_SUBJECT).await?,
+                self.broker.subscribe(ADMIN_TASK_STATUS_SUBJECT).await?,
+                self.broker.subscribe(ADMIN_STATS_SUBJECT).await?,
+            ],
+        })
+    }
+    
+    /// Handle everything received since the last poll
+    async fn poll(&mut self, subscriptions: &mut Subscriptions) -> Result<()> {
+        while let Some(message) = subscriptions.registrations.next_message()? {
+            self.handle_registration(&message).await?;
+        }
+        while let Some(message) = subscriptions.deregistrations.next_message()? {
+            match serde_json::from_slice::<WorkerDeregistration>(&message.payload) {
+                Ok(deregistration) => {
+                    self.unregister_remote(deregistration.worker_id);
+                }
+                Err(e) => tracing::warn!("Ignoring malformed deregistration: {}", e),
+            }
+        }
+        let now = Utc::now();
+        while let Some(message) = subscriptions.health.next_message()? {
+            match serde_json::from_slice::<Heartbeat>(&message.payload) {
+                Ok(heartbeat) => self.handle_heartbeat(&heartbeat, now),
+                Err(e) => tracing::warn!("Ignoring malformed heartbeat: {}", e),
+            }
+        }
+        while let Some(message) = subscriptions.results.next_message()? {
+            match MessageSerializer::deserialize_task_result(&message) {
+                Ok(result) => self.handle_result(result).await?,
+                Err(e) => tracing::warn!("Ignoring malformed task result: {}", e),
+            }
+        }
+        while let Some(message) = subscriptions.submissions.next_message()? {
+            match MessageSerializer::deserialize_task(&message) {
+                Ok(task) => self.submit_task(task),
+                Err(e) => tracing::warn!("Ignoring malformed task submission: {}", e),
+            }
+        }
+        self.check_liveness(now);
+        self.release_due_retries(now);
+        self.dispatch_pending().await?;
+        for admin in &mut subscriptions.admin {
+            while let Some(message) = admin.next_message()? {
+                self.handle_admin(&message).await?;
+            }
+        }
+        Ok(())
+    }
+    
+    async fn handle_admin(&self, message: &Message) -> Result<()> {
+        let Some(reply_to) = message.reply_to() else {
+            tracing::warn!("Administration request on {} has no reply subject", message.subject);
+            return Ok(());
+        };
+        let reply = match message.subject.as_str() {
+            ADMIN_WORKERS_SUBJECT => serde_json::to_vec(&self.worker_summaries())?,
+            ADMIN_STATS_SUBJECT => serde_json::to_vec(&self.get_stats().await)?,
+            _ => match serde_json::from_slice::<TaskStatusRequest>(&message.payload) {
+                Ok(request) => serde_json::to_vec(&self.task_status(request.task_id))?,
+                Err(e) => {
+                    tracing::warn!("Ignoring malformed task status request: {}", e);
+                    return Ok(());
+                }
+            },
+        };
+        self.broker.publish(reply_to, &reply).await
+    }
+    
+    async fn handle_registration(&mut self, message: &Message) -> Result<()> {
+        let ack = match serde_json::from_slice::<WorkerRegistration>(&message.payload) {
+            Ok(registration) => match self.register_remote(registration.config) {
+                Ok(subject) => RegistrationAck {
+                    worker_id: registration.worker_id,
+                    accepted: true,
+                    assignment_subject: Some(subject.into_string()),
+                    reason: None,
+                },
+                Err(e) => RegistrationAck::refused(registration.worker_id, e.to_string()),
+            },
+            Err(e) => RegistrationAck::refused(Uuid::nil(), format!("malformed registration: {}", e)),
+        };
+        match message.reply_to() {
+            Some(reply_to) => self.broker.publish(reply_to, &serde_json::to_vec(&ack)?).await,
+            None => {
+                tracing::warn!("Registration of worker {} has no reply subject", ack.worker_id);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl WorkerCoordinator for NatsCoordinator {
+    /// Register a worker that consumes its assignment subject itself
+    async fn register_worker(&mut self, worker: Box<dyn Worker>) -> Result<()> {
+        let performance_profile = worker.capabilities().first()
+            .map(|capability| capability.performance_profile.clone())
+            .unwrap_or(PerformanceProfile { avg_processing_time_ms: 1000, memory_usage_mb: 0, cpu_intensity: 0.5, throughput_per_second: 1.0 });
+        self.register_remote(WorkerConfig {
+            id: worker.id(),
+            name: worker.name().to_string(),
+            worker_type: worker.worker_type(),
+            max_concurrent_tasks: worker.max_concurrent_tasks(),
+            capabilities: worker.capabilities().to_vec(),
+            performance_profile,
+            health_check_interval_ms: self.config.liveness.heartbeat_interval_ms,
+            shutdown_timeout_ms: 30_000,
+            metadata: HashMap::new(),
+        })?;
+        Ok(())
+    }
+    
+    async fn unregister_worker(&mut self, worker_id: Uuid) -> Result<()> {
+        if self.unregister_remote(worker_id) {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("unknown worker {}", worker_id))
+        }
+    }
+    
+    async fn get_available_workers(&self, task_type: &TaskType) -> Vec<Uuid> {
+        let mut available: Vec<Uuid> = self.workers.iter()
+            .filter(|(worker_id, worker)| self.liveness.is_available(worker_id) && worker.accepts_tasks())
+            .filter(|(_, worker)| supports_task_type(&worker.config, task_type))
+            .map(|(worker_id, _)| *worker_id)
+            .collect();
+        available.sort();
+        available
+    }
+    
+    async fn assign_task(&mut self, task: Task) -> Result<Uuid> {
+        self.dispatch(task).await
+    }
+    
+    async fn get_worker_status(&self, worker_id: Uuid) -> Result<WorkerStatus> {
+        self.workers.get(&worker_id)
+            .map(|worker| worker.status.clone())
+            .ok_or_else(|| anyhow::anyhow!("unknown worker {}", worker_id))
+    }
+    
+    async fn get_stats(&self) -> CoordinatorStats {
+        let processed = self.tasks_completed + self.tasks_failed;
+        let elapsed_s = (Utc::now() - self.started_at).num_milliseconds().max(1) as f32 / 1000.0;
+        CoordinatorStats {
+            total_workers: self.workers.len(),
+            active_workers: self.workers.values().filter(|worker| !worker.in_flight.is_empty()).count(),
+            total_tasks_processed: processed,
+            tasks_per_second: processed as f32 / elapsed_s,
+            average_processing_time_ms: if processed == 0 { 0.0 } else { self.processing_time_ms as f32 / processed as f32 },
+            error_rate: if processed == 0 { 0.0 } else { self.tasks_failed as f32 / processed as f32 },
+            fairness: Default::default(),
+            saturation: QueueSaturation {
+                pending_tasks: self.pending.len(),
+                max_pending_tasks: self.config.max_pending_tasks,
+                rejected_submissions: self.rejected_submissions,
+                full_worker_queues: self.workers.values()
+                    .filter(|worker| worker.in_flight.len() >= worker.config.max_concurrent_tasks)
+                    .count(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl Service for NatsCoordinator {
+    fn name(&self) -> &str {
+        "nats-coordinator"
+    }
+    
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        let mut subscriptions = self.subscribe().await?;
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+        loop {
+            self.poll(&mut subscriptions).await?;
+            if cancellable_sleep(&cancel, poll_interval).await {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swarm_core::{
+        DocumentProcessingType, DocumentType, MemoryBroker, TaskPayload, TaskPriority, WorkerType,
+        WORKER_REGISTRATION_SUBJECT,
+    };
+    
+    fn create_test_config(max_concurrent_tasks: usize) -> WorkerConfig {
+        WorkerConfig {
+            id: Uuid::new_v4(),
+            name: "remote".to_string(),
+            worker_type: WorkerType::DocumentProcessor { supported_types: vec![DocumentType::Text] },
+            max_concurrent_tasks,
+            capabilities: Vec::new(),
+            performance_profile: PerformanceProfile { avg_processing_time_ms: 100, memory_usage_mb: 64, cpu_intensity: 0.5, throughput_per_second: 10.0 },
+            health_check_interval_ms: 1000,
+            shutdown_timeout_ms: 1000,
+            metadata: HashMap::new(),
+        }
+    }
+    
+    fn create_test_task() -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            task_type: TaskType::DocumentProcessing { document_type: DocumentType::Text, processing_type: DocumentProcessingType::TextExtraction },
+            priority: TaskPriority::Normal,
+            status: TaskStatus::Pending,
+            payload: TaskPayload::Custom { data: Vec::new(), format: "raw".to_string() },
+            created_at: Utc::now(),
+            deadline: None,
+            retry_count: 0,
+            max_retries: 3,
+            metadata: HashMap::new(),
+        }
+    }
+    
+    fn create_test_result(task_id: Uuid) -> TaskResult {
+        TaskResult {
+            task_id,
+            status: TaskStatus::Completed,
+            result: None,
+            error: None,
+            processing_time_ms: 40,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        }
+    }
+    
+    fn create_test_coordinator(broker: Arc<MemoryBroker>) -> NatsCoordinator {
+        let config = NatsCoordinatorConfig {
+            liveness: LivenessConfig { heartbeat_interval_ms: 100, missed_heartbeats: 2 },
+            ..Default::default()
+        };
+        NatsCoordinator::with_config(broker, MessageRouter::new(MessageRoutingConfig::default()), config)
+    }
+    
+    #[tokio::test]
+    async fn test_remote_worker_receives_submitted_task_and_reports_result() {
+        let broker = Arc::new(MemoryBroker::new());
+        let mut coordinator = create_test_coordinator(broker.clone());
+        let mut results = coordinator.subscribe_results();
+        let cancel = CancellationToken::new();
+        let running = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { coordinator.run(cancel).await.map(|_| coordinator) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        
+        // The worker registers and listens on the subject it is given
+        let config = create_test_config(2);
+        let registration = serde_json::to_vec(&WorkerRegistration::new(config.clone())).unwrap();
+        let reply = broker.request(WORKER_REGISTRATION_SUBJECT, &registration, Duration::from_secs(1)).await.unwrap();
+        let ack: RegistrationAck = serde_json::from_slice(&reply.payload).unwrap();
+        let subject = ack.assignment_subject.unwrap();
+        assert_eq!(subject, format!("swarm.tasks.assignments.{}", config.id));
+        let mut assignments = broker.subscribe(&subject).await.unwrap();
+        
+        // A task submitted to the assignment subject is dispatched to it
+        let task = create_test_task();
+        let submission = MessageSerializer::serialize_task(&task).unwrap();
+        broker.publish("swarm.tasks.assignments", &submission.payload).await.unwrap();
+        let assigned: Task = loop {
+            if let Some(message) = assignments.next_message().unwrap() {
+                break serde_json::from_slice(&message.payload).unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        };
+        assert_eq!(assigned.id, task.id);
+        
+        broker.publish("swarm.tasks.results", &serde_json::to_vec(&create_test_result(task.id)).unwrap()).await.unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(1), results.recv()).await.unwrap().unwrap();
+        assert_eq!(result.task_id, task.id);
+        
+        cancel.cancel();
+        let coordinator = running.await.unwrap().unwrap();
+        assert_eq!(coordinator.in_flight_tasks(), 0);
+        let stats = coordinator.get_stats().await;
+        assert_eq!((stats.total_workers, stats.total_tasks_processed), (1, 1));
+        assert_eq!(stats.average_processing_time_ms, 40.0);
+    }
+    
+    #[tokio::test]
+    async fn test_admin_requests_are_answered_while_running() {
+        let broker = Arc::new(MemoryBroker::new());
+        let mut coordinator = create_test_coordinator(broker.clone());
+        let config = create_test_config(1);
+        coordinator.register_remote(config.clone()).unwrap();
+        let (dispatched, queued, finished) = (create_test_task(), create_test_task(), create_test_task());
+        coordinator.dispatch(finished.clone()).await.unwrap();
+        coordinator.handle_result(create_test_result(finished.id)).await.unwrap();
+        coordinator.dispatch(dispatched.clone()).await.unwrap();
+        coordinator.submit_task(queued.clone());
+        
+        let cancel = CancellationToken::new();
+        let running = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { coordinator.run(cancel).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let admin = AdminClient::new(broker.clone()).with_timeout(Duration::from_secs(1));
+        
+        let workers = admin.workers().await.unwrap();
+        assert_eq!(workers.len(), 1);
+        assert_eq!((workers[0].worker_id, workers[0].in_flight_tasks), (config.id, 1));
+        
+        let report = admin.task_status(dispatched.id).await.unwrap();
+        assert_eq!((report.status, report.worker_id), (Some(TaskStatus::Assigned), Some(config.id)));
+        assert_eq!(admin.task_status(queued.id).await.unwrap().status, Some(TaskStatus::Pending));
+        assert_eq!(admin.task_status(finished.id).await.unwrap().status, Some(TaskStatus::Completed));
+        assert_eq!(admin.task_status(Uuid::new_v4()).await.unwrap().status, None);
+        
+        let stats = admin.stats().await.unwrap();
+        assert_eq!((stats.total_workers, stats.total_tasks_processed, stats.saturation.pending_tasks), (1, 1, 1));
+        
+        cancel.cancel();
+        running.await.unwrap().unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_silent_worker_tasks_move_to_healthy_worker() {
+        let broker = Arc::new(MemoryBroker::new());
+        let mut coordinator = create_test_coordinator(broker.clone());
+        let (silent, healthy) = (create_test_config(1), create_test_config(1));
+        coordinator.register_remote(silent.clone()).unwrap();
+        let mut healthy_assignments = broker.subscribe(coordinator.register_remote(healthy.clone()).unwrap().as_str()).await.unwrap();
+        
+        // Each worker takes one task; a third has to wait
+        for _ in 0..3 {
+            coordinator.submit_task(create_test_task());
+        }
+        assert_eq!(coordinator.dispatch_pending().await.unwrap(), 2);
+        assert_eq!(coordinator.pending_tasks(), 1);
+        assert_eq!(coordinator.get_available_workers(&create_test_task().task_type).await, Vec::<Uuid>::new());
+        
+        // Only the healthy worker keeps sending heartbeats and finishes its task
+        let later = Utc::now() + chrono::Duration::milliseconds(150);
+        let heartbeat = Heartbeat { worker_id: healthy.id, status: WorkerStatus::Idle, current_load: 0, sent_at: later };
+        coordinator.handle_heartbeat(&heartbeat, later);
+        let first: Task = serde_json::from_slice(&healthy_assignments.next_message().unwrap().unwrap().payload).unwrap();
+        coordinator.handle_result(create_test_result(first.id)).await.unwrap();
+        
+        assert_eq!(coordinator.check_liveness(later + chrono::Duration::milliseconds(100)), vec![silent.id]);
+        assert_eq!(coordinator.get_worker_status(silent.id).await.unwrap(), WorkerStatus::Unavailable);
+        assert_eq!(coordinator.pending_tasks(), 2);
+        
+        assert_eq!(coordinator.dispatch_pending().await.unwrap(), 1);
+        assert!(healthy_assignments.next_message().unwrap().is_some());
+        assert_eq!(coordinator.pending_tasks(), 1);
+        assert_eq!(coordinator.get_available_workers(&create_test_task().task_type).await, Vec::<Uuid>::new());
+    }
+    
+    #[tokio::test]
+    async fn test_failed_task_is_retried_then_dead_lettered() {
+        let broker = Arc::new(MemoryBroker::new());
+        let config = NatsCoordinatorConfig {
+            retry_policy: RetryPolicy { initial_backoff_ms: 100, multiplier: 2.0, max_backoff_ms: 1000 },
+            ..Default::default()
+        };
+        let mut coordinator = NatsCoordinator::with_config(broker.clone(), MessageRouter::new(MessageRoutingConfig::default()), config);
+        let mut dead_letters = coordinator.subscribe_dead_letters();
+        let mut published = broker.subscribe("swarm.tasks.deadletter").await.unwrap();
+        let mut assignments = broker.subscribe(coordinator.register_remote(create_test_config(1)).unwrap().as_str()).await.unwrap();
+        let task = Task { max_retries: 1, ..create_test_task() };
+        let failure = |task_id| TaskResult { status: TaskStatus::Failed, error: Some("boom".to_string()), ..create_test_result(task_id) };
+        
+        coordinator.submit_task(task.clone());
+        coordinator.dispatch_pending().await.unwrap();
+        assert_eq!(serde_json::from_slice::<Task>(&assignments.next_message().unwrap().unwrap().payload).unwrap().retry_count, 0);
+        coordinator.handle_result(failure(task.id)).await.unwrap();
+        assert_eq!(coordinator.task_status(task.id).status, Some(TaskStatus::Retrying));
+        
+        // Nothing is released before the backoff has passed
+        assert_eq!(coordinator.release_due_retries(Utc::now()), 0);
+        assert_eq!(coordinator.release_due_retries(Utc::now() + chrono::Duration::milliseconds(200)), 1);
+        coordinator.dispatch_pending().await.unwrap();
+        assert_eq!(serde_json::from_slice::<Task>(&assignments.next_message().unwrap().unwrap().payload).unwrap().retry_count, 1);
+        
+        // A duplicate of the first failure is not counted
+        coordinator.handle_result(failure(task.id)).await.unwrap();
+        coordinator.handle_result(failure(task.id)).await.unwrap();
+        let dead_letter = dead_letters.try_recv().unwrap();
+        assert_eq!(dead_letter.task.id, task.id);
+        assert_eq!(dead_letter.errors.iter().map(|attempt| attempt.attempt).collect::<Vec<_>>(), vec![1, 2]);
+        let message = published.next_message().unwrap().unwrap();
+        assert_eq!(serde_json::from_slice::<DeadLetter>(&message.payload).unwrap(), dead_letter);
+        assert!(dead_letters.try_recv().is_err());
+        
+        let stats = coordinator.get_stats().await;
+        assert_eq!((stats.total_tasks_processed, stats.error_rate), (1, 1.0));
+        assert_eq!((coordinator.in_flight_tasks(), coordinator.retrying_tasks()), (0, 0));
+        assert_eq!(coordinator.task_status(task.id).status, Some(TaskStatus::Failed));
+    }
+}
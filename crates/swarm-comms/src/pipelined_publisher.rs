@@ -0,0 +1,382 @@
+//! Pipelined Publisher
+//!
+//! Publishes without waiting for each message to complete before sending the
+//! next. Messages are sent in order by a single dispatcher, up to
+//! `max_in_flight` publishes (or unacknowledged JetStream publishes) are
+//! outstanding at once, and callers wait for a free slot when the limit is
+//! reached. The connection is flushed periodically and on demand.
+
+use super::*;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinHandle;
+
+/// Resolves once the server acknowledged a publish
+pub type AckFuture = BoxFuture<'static, MessageResult<()>>;
+
+/// How publishes are confirmed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AckMode {
+    /// Core NATS: a publish completes once it is written to the connection
+    #[default]
+    None,
+    /// JetStream: a publish completes when the stream acknowledges it
+    JetStream,
+}
+
+/// Pipelined publisher configuration
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PublisherConfig {
+    /// Maximum publishes outstanding at once
+    pub max_in_flight: usize,
+    
+    /// Interval between connection flushes in milliseconds (0 disables periodic flushing)
+    pub flush_interval_ms: u64,
+    
+    pub ack_mode: AckMode,
+}
+
+impl Default for PublisherConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 256,
+            flush_interval_ms: 100,
+            ack_mode: AckMode::None,
+        }
+    }
+}
+
+/// Connection the pipelined publisher writes to
+#[async_trait]
+pub trait PublishTransport: Send + Sync + 'static {
+    /// Send a payload; returns a future for the server acknowledgement, if the transport has one
+    async fn send(&self, subject: String, payload: Bytes) -> MessageResult<Option<AckFuture>>;
+    
+    /// Flush buffered publishes to the server
+    async fn flush(&self) -> MessageResult<()>;
+}
+
+#[async_trait]
+impl PublishTransport for async_nats::Client {
+    async fn send(&self, subject: String, payload: Bytes) -> MessageResult<Option<AckFuture>> {
+        self.publish(subject, payload).await.map_err(|e| MessageError::Nats(e.into()))?;
+        Ok(None)
+    }
+    
+    async fn flush(&self) -> MessageResult<()> {
+        async_nats::Client::flush(self).await.map_err(|e| MessageError::Nats(e.into()))
+    }
+}
+
+#[async_trait]
+impl PublishTransport for async_nats::jetstream::Context {
+    async fn send(&self, subject: String, payload: Bytes) -> MessageResult<Option<AckFuture>> {
+        let ack = self.publish(subject, payload).await.map_err(|e| MessageError::Nats(e.into()))?;
+        Ok(Some(Box::pin(async move {
+            ack.await.map(|_| ()).map_err(|e| MessageError::Nats(e.into()))
+        })))
+    }
+    
+    async fn flush(&self) -> MessageResult<()> {
+        // Acknowledgements already confirm delivery
+        Ok(())
+    }
+}
+
+/// Pipelined publisher counters
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PublisherStats {
+    /// Publishes queued or awaiting completion
+    pub pending: u64,
+    pub published: u64,
+    pub failed: u64,
+    pub flushes: u64,
+}
+
+#[derive(Debug, Default)]
+struct PublisherCounters {
+    pending: AtomicU64,
+    published: AtomicU64,
+    failed: AtomicU64,
+    flushes: AtomicU64,
+}
+
+impl PublisherCounters {
+    fn complete(&self, result: &MessageResult<()>) {
+        match result {
+            Ok(()) => self.published.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.failed.fetch_add(1, Ordering::Relaxed),
+        };
+        self.pending.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Completion of a single pipelined publish
+#[derive(Debug)]
+pub struct PublishHandle {
+    receiver: oneshot::Receiver<MessageResult<()>>,
+}
+
+impl PublishHandle {
+    /// Wait until the publish was sent (and acknowledged, with `AckMode::JetStream`)
+    pub async fn confirmed(self) -> MessageResult<()> {
+        self.receiver.await.unwrap_or_else(|_| {
+            Err(MessageError::Connection {
+                message: "publisher stopped before the publish completed".to_string(),
+            })
+        })
+    }
+}
+
+struct PublishRequest {
+    subject: String,
+    payload: Bytes,
+    permit: OwnedSemaphorePermit,
+    reply: oneshot::Sender<MessageResult<()>>,
+}
+
+/// Publisher with a bounded number of outstanding publishes
+pub struct PipelinedPublisher {
+    transport: Arc<dyn PublishTransport>,
+    requests: mpsc::UnboundedSender<PublishRequest>,
+    permits: Arc<Semaphore>,
+    max_in_flight: u32,
+    counters: Arc<PublisherCounters>,
+    dispatcher: JoinHandle<()>,
+}
+
+impl PipelinedPublisher {
+    /// Start a publisher and its dispatcher task
+    pub fn new(transport: Arc<dyn PublishTransport>, config: &PublisherConfig) -> Self {
+        let max_in_flight = config.max_in_flight.clamp(1, Semaphore::MAX_PERMITS) as u32;
+        let counters = Arc::new(PublisherCounters::default());
+        let (requests, receiver) = mpsc::unbounded_channel();
+        let flush_interval = (config.flush_interval_ms > 0).then(|| Duration::from_millis(config.flush_interval_ms));
+        let dispatcher = tokio::spawn(dispatch(transport.clone(), receiver, counters.clone(), flush_interval));
+        
+        Self {
+            transport,
+            requests,
+            permits: Arc::new(Semaphore::new(max_in_flight as usize)),
+            max_in_flight,
+            counters,
+            dispatcher,
+        }
+    }
+    
+    /// Queue a publish, waiting while `max_in_flight` publishes are outstanding
+    pub async fn publish(&self, subject: impl Into<String>, payload: Bytes) -> MessageResult<PublishHandle> {
+        let permit = self.permits.clone().acquire_owned().await.map_err(|_| MessageError::Connection {
+            message: "publisher is closed".to_string(),
+        })?;
+        
+        let (reply, receiver) = oneshot::channel();
+        self.counters.pending.fetch_add(1, Ordering::Relaxed);
+        let request = PublishRequest { subject: subject.into(), payload, permit, reply };
+        if self.requests.send(request).is_err() {
+            self.counters.pending.fetch_sub(1, Ordering::Relaxed);
+            return Err(MessageError::Connection {
+                message: "publisher dispatcher has stopped".to_string(),
+            });
+        }
+        Ok(PublishHandle { receiver })
+    }
+    
+    /// Wait for all outstanding publishes, then flush the connection
+    pub async fn flush(&self) -> MessageResult<()> {
+        let all = self.permits.acquire_many(self.max_in_flight).await.map_err(|_| MessageError::Connection {
+            message: "publisher is closed".to_string(),
+        })?;
+        drop(all);
+        
+        self.transport.flush().await?;
+        self.counters.flushes.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+    
+    /// Publishes queued or awaiting completion
+    pub fn pending(&self) -> u64 {
+        self.counters.pending.load(Ordering::Relaxed)
+    }
+    
+    pub fn stats(&self) -> PublisherStats {
+        PublisherStats {
+            pending: self.pending(),
+            published: self.counters.published.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            flushes: self.counters.flushes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for PipelinedPublisher {
+    fn drop(&mut self) {
+        self.dispatcher.abort();
+    }
+}
+
+/// Send queued publishes in order and flush periodically
+async fn dispatch(
+    transport: Arc<dyn PublishTransport>,
+    mut requests: mpsc::UnboundedReceiver<PublishRequest>,
+    counters: Arc<PublisherCounters>,
+    flush_interval: Option<Duration>,
+) {
+    let mut flush_timer = flush_interval.map(tokio::time::interval);
+    let mut unflushed = false;
+    
+    loop {
+        let request = tokio::select! {
+            request = requests.recv() => match request {
+                Some(request) => request,
+                None => break,
+            },
+            _ = async { flush_timer.as_mut().expect("guarded by select condition").tick().await }, if flush_timer.is_some() && unflushed => {
+                match transport.flush().await {
+                    Ok(()) => {
+                        counters.flushes.fetch_add(1, Ordering::Relaxed);
+                        unflushed = false;
+                    }
+                    Err(e) => tracing::warn!("Periodic publisher flush failed: {}", e),
+                }
+                continue;
+            }
+        };
+        
+        let PublishRequest { subject, payload, permit, reply } = request;
+        match transport.send(subject, payload).await {
+            Ok(None) => {
+                unflushed = true;
+                let result = Ok(());
+                counters.complete(&result);
+                drop(permit);
+                let _ = reply.send(result);
+            }
+            Ok(Some(ack)) => {
+                // Acknowledgements are awaited concurrently; the slot stays taken until then
+                let counters = counters.clone();
+                tokio::spawn(async move {
+                    let result = ack.await;
+                    counters.complete(&result);
+                    drop(permit);
+                    let _ = reply.send(result);
+                });
+            }
+            Err(e) => {
+                tracing::error!("Pipelined publish failed: {}", e);
+                let result = Err(e);
+                counters.complete(&result);
+                drop(permit);
+                let _ = reply.send(result);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    
+    /// Transport recording sends, with acknowledgements released by the test
+    #[derive(Default)]
+    struct TestTransport {
+        sent: Mutex<Vec<String>>,
+        acks: Mutex<Vec<oneshot::Sender<()>>>,
+        with_acks: bool,
+        flushes: AtomicU64,
+    }
+    
+    #[async_trait]
+    impl PublishTransport for TestTransport {
+        async fn send(&self, subject: String, payload: Bytes) -> MessageResult<Option<AckFuture>> {
+            if payload.as_ref() == b"fail" {
+                return Err(MessageError::Connection { message: "rejected".to_string() });
+            }
+            self.sent.lock().unwrap().push(subject);
+            if !self.with_acks {
+                return Ok(None);
+            }
+            
+            let (ack, acked) = oneshot::channel();
+            self.acks.lock().unwrap().push(ack);
+            Ok(Some(Box::pin(async move {
+                acked.await.map_err(|_| MessageError::Timeout { message: "no ack".to_string() })
+            })))
+        }
+        
+        async fn flush(&self) -> MessageResult<()> {
+            self.flushes.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+    
+    fn create_test_publisher(transport: Arc<TestTransport>, max_in_flight: usize, flush_interval_ms: u64) -> PipelinedPublisher {
+        PipelinedPublisher::new(transport, &PublisherConfig {
+            max_in_flight,
+            flush_interval_ms,
+            ack_mode: AckMode::None,
+        })
+    }
+    
+    #[tokio::test]
+    async fn test_publishes_in_order_and_counts() {
+        let transport = Arc::new(TestTransport::default());
+        let publisher = create_test_publisher(transport.clone(), 4, 0);
+        
+        let mut handles = Vec::new();
+        for i in 0..10 {
+            handles.push(publisher.publish(format!("swarm.{}", i), Bytes::from_static(b"ok")).await.unwrap());
+        }
+        let failed = publisher.publish("swarm.bad", Bytes::from_static(b"fail")).await.unwrap();
+        for handle in handles {
+            handle.confirmed().await.unwrap();
+        }
+        assert!(failed.confirmed().await.is_err());
+        
+        publisher.flush().await.unwrap();
+        let expected: Vec<String> = (0..10).map(|i| format!("swarm.{}", i)).collect();
+        assert_eq!(*transport.sent.lock().unwrap(), expected);
+        assert_eq!(publisher.stats(), PublisherStats { pending: 0, published: 10, failed: 1, flushes: 1 });
+    }
+    
+    #[tokio::test]
+    async fn test_unacknowledged_publishes_apply_backpressure() {
+        let transport = Arc::new(TestTransport { with_acks: true, ..Default::default() });
+        let publisher = create_test_publisher(transport.clone(), 2, 0);
+        
+        let first = publisher.publish("swarm.a", Bytes::from_static(b"1")).await.unwrap();
+        let _second = publisher.publish("swarm.b", Bytes::from_static(b"2")).await.unwrap();
+        assert_eq!(publisher.pending(), 2);
+        
+        // No slot is free until an acknowledgement arrives
+        let third = tokio::time::timeout(Duration::from_millis(50), publisher.publish("swarm.c", Bytes::new())).await;
+        assert!(third.is_err());
+        
+        transport.acks.lock().unwrap().remove(0).send(()).unwrap();
+        first.confirmed().await.unwrap();
+        let third = tokio::time::timeout(Duration::from_secs(1), publisher.publish("swarm.c", Bytes::new())).await;
+        assert!(third.is_ok());
+        assert_eq!(publisher.stats().published, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_periodic_flush_after_publishes() {
+        let transport = Arc::new(TestTransport::default());
+        let publisher = create_test_publisher(transport.clone(), 8, 10);
+        
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(transport.flushes.load(Ordering::Relaxed), 0);
+        
+        publisher.publish("swarm.a", Bytes::new()).await.unwrap().confirmed().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(transport.flushes.load(Ordering::Relaxed), 1);
+        assert_eq!(publisher.stats().flushes, 1);
+    }
+}
@@ -0,0 +1,277 @@
+//! Result Publisher
+//!
+//! Publishes task results to output subjects. Each subject can have a field
+//! projection that strips heavy fields (embeddings, extracted text, ...) from
+//! the published payload, while the result sink always receives the full result.
+
+use swarm_core::{MessageBroker, ResultSink, TaskResult};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Field projection applied to results published on one subject
+///
+/// Field paths are relative to the result data (e.g. the
+/// `DocumentProcessingResult`) and may use dots for nested fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ResultProjection {
+    /// Keep only these top-level fields (all fields when unset)
+    #[serde(default)]
+    pub include_fields: Option<Vec<String>>,
+    
+    /// Remove these fields
+    #[serde(default)]
+    pub exclude_fields: Vec<String>,
+}
+
+impl ResultProjection {
+    /// Projection that removes the given fields
+    pub fn excluding(fields: &[&str]) -> Self {
+        Self {
+            include_fields: None,
+            exclude_fields: fields.iter().map(|field| field.to_string()).collect(),
+        }
+    }
+    
+    fn apply(&self, data: &mut serde_json::Map<String, serde_json::Value>) {
+        if let Some(include_fields) = &self.include_fields {
+            data.retain(|field, _| include_fields.contains(field));
+        }
+        
+        for path in &self.exclude_fields {
+            remove_path(data, path);
+        }
+    }
+}
+
+/// Per-subject result projections
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ResultProjectionConfig {
+    /// Projection by output subject; subjects without an entry get full results
+    #[serde(default)]
+    pub subjects: HashMap<String, ResultProjection>,
+}
+
+impl ResultProjectionConfig {
+    /// Add a projection for a subject
+    pub fn with_projection(mut self, subject: impl Into<String>, projection: ResultProjection) -> Self {
+        self.subjects.insert(subject.into(), projection);
+        self
+    }
+    
+    /// Serialize a result as it should be published on a subject
+    pub fn project(&self, subject: &str, result: &TaskResult) -> Result<Vec<u8>> {
+        let Some(projection) = self.subjects.get(subject) else {
+            return Ok(serde_json::to_vec(result)?);
+        };
+        
+        let mut value = serde_json::to_value(result)?;
+        
+        // Result data is externally tagged: {"result": {"DocumentProcessing": {...}}}
+        let data = value.get_mut("result")
+            .and_then(|data| data.as_object_mut())
+            .and_then(|variant| variant.values_mut().next())
+            .and_then(|data| data.as_object_mut());
+        if let Some(data) = data {
+            projection.apply(data);
+        }
+        
+        Ok(serde_json::to_vec(&value)?)
+    }
+}
+
+fn remove_path(data: &mut serde_json::Map<String, serde_json::Value>, path: &str) {
+    match path.split_once('.') {
+        Some((field, rest)) => {
+            if let Some(nested) = data.get_mut(field).and_then(|value| value.as_object_mut()) {
+                remove_path(nested, rest);
+            }
+        }
+        None => {
+            data.remove(path);
+        }
+    }
+}
+
+/// Publishes results to output subjects and an optional full-result sink
+pub struct ResultPublisher {
+    broker: Arc<dyn MessageBroker>,
+    subjects: Vec<String>,
+    projections: ResultProjectionConfig,
+    sink: Option<Arc<dyn ResultSink>>,
+}
+
+impl ResultPublisher {
+    /// Create a publisher sending full results to the given subjects
+    pub fn new(broker: Arc<dyn MessageBroker>, subjects: Vec<String>) -> Self {
+        Self {
+            broker,
+            subjects,
+            projections: ResultProjectionConfig::default(),
+            sink: None,
+        }
+    }
+    
+    /// Set per-subject projections
+    pub fn with_projections(mut self, projections: ResultProjectionConfig) -> Self {
+        self.projections = projections;
+        self
+    }
+    
+    /// Write full, unprojected results to a sink
+    pub fn with_sink(mut self, sink: Arc<dyn ResultSink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+    
+    /// Publish a result to the sink and all output subjects
+    pub async fn publish(&self, result: &TaskResult) -> Result<()> {
+        if let Some(sink) = &self.sink {
+            sink.write(result).await?;
+        }
+        
+        for subject in &self.subjects {
+            let payload = self.projections.project(subject, result)?;
+            self.broker.publish(subject, &payload).await?;
+            tracing::debug!("Published result {} to {} ({} bytes)", result.task_id, subject, payload.len());
+        }
+        
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use chrono::Utc;
+    use swarm_core::{DocumentProcessingResult, Message, MessageBrokerStats, MessageSubscription, TaskResultData, TaskStatus};
+    use tokio::sync::Mutex;
+    use uuid::Uuid;
+    
+    #[derive(Default)]
+    struct RecordingBroker {
+        published: Mutex<Vec<(String, Vec<u8>)>>,
+    }
+    
+    #[async_trait]
+    impl MessageBroker for RecordingBroker {
+        async fn publish(&self, subject: &str, message: &[u8]) -> Result<()> {
+            self.published.lock().await.push((subject.to_string(), message.to_vec()));
+            Ok(())
+        }
+        
+        async fn subscribe(&self, _subject: &str) -> Result<Box<dyn MessageSubscription>> {
+            anyhow::bail!("not supported")
+        }
+        
+        async fn request(&self, _subject: &str, _message: &[u8], _timeout: std::time::Duration) -> Result<Message> {
+            anyhow::bail!("not supported")
+        }
+        
+        async fn get_stats(&self) -> MessageBrokerStats {
+            MessageBrokerStats {
+                total_messages_sent: 0,
+                total_messages_received: 0,
+                active_subscriptions: 0,
+                queue_depth: 0,
+                error_count: 0,
+            }
+        }
+    }
+    
+    #[derive(Default)]
+    struct RecordingSink {
+        results: Mutex<Vec<TaskResult>>,
+    }
+    
+    #[async_trait]
+    impl ResultSink for RecordingSink {
+        async fn write(&self, result: &TaskResult) -> Result<()> {
+            self.results.lock().await.push(result.clone());
+            Ok(())
+        }
+    }
+    
+    fn create_test_result() -> TaskResult {
+        TaskResult {
+            task_id: Uuid::new_v4(),
+            status: TaskStatus::Completed,
+            result: Some(TaskResultData::DocumentProcessing(DocumentProcessingResult {
+                document_id: Uuid::new_v4(),
+                extracted_text: Some("full text".to_string()),
+                metadata: HashMap::from([("pages".to_string(), serde_json::json!({ "count": 3, "sizes": [1, 2, 3] }))]),
+                language: Some("en".to_string()),
+                keywords: vec!["rust".to_string()],
+                sentiment: Some(0.4),
+                classification: None,
+                embeddings: Some(vec![0.1; 8]),
+                processing_time_ms: 12,
+                processed_at: Utc::now(),
+                warnings: Vec::new(),
+            })),
+            error: None,
+            processing_time_ms: 12,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        }
+    }
+    
+    fn document_result(payload: &[u8]) -> DocumentProcessingResult {
+        let result: TaskResult = serde_json::from_slice(payload).unwrap();
+        match result.result {
+            Some(TaskResultData::DocumentProcessing(result)) => result,
+            other => panic!("unexpected result data: {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_projection_excludes_fields() {
+        let config = ResultProjectionConfig::default()
+            .with_projection("results.light", ResultProjection::excluding(&["embeddings", "extracted_text", "metadata.pages.sizes"]));
+        let result = create_test_result();
+        
+        let light = document_result(&config.project("results.light", &result).unwrap());
+        assert_eq!(light.embeddings, None);
+        assert_eq!(light.extracted_text, None);
+        assert_eq!(light.keywords, vec!["rust".to_string()]);
+        assert_eq!(light.metadata["pages"], serde_json::json!({ "count": 3 }));
+        
+        let full = document_result(&config.project("results.full", &result).unwrap());
+        assert_eq!(full.embeddings, Some(vec![0.1; 8]));
+    }
+    
+    #[test]
+    fn test_projection_includes_fields() {
+        let projection = ResultProjection {
+            include_fields: Some(vec!["document_id".into(), "processing_time_ms".into(), "processed_at".into(), "language".into()]),
+            exclude_fields: Vec::new(),
+        };
+        let config = ResultProjectionConfig::default().with_projection("results.lang", projection);
+        
+        let projected = document_result(&config.project("results.lang", &create_test_result()).unwrap());
+        assert_eq!(projected.language, Some("en".to_string()));
+        assert!(projected.keywords.is_empty());
+        assert_eq!(projected.sentiment, None);
+    }
+    
+    #[tokio::test]
+    async fn test_publisher_keeps_full_results_for_sink() {
+        let broker = Arc::new(RecordingBroker::default());
+        let sink = Arc::new(RecordingSink::default());
+        let publisher = ResultPublisher::new(broker.clone(), vec!["results.full".to_string(), "results.light".to_string()])
+            .with_projections(ResultProjectionConfig::default()
+                .with_projection("results.light", ResultProjection::excluding(&["embeddings"])))
+            .with_sink(sink.clone());
+        
+        let result = create_test_result();
+        publisher.publish(&result).await.unwrap();
+        
+        assert_eq!(sink.results.lock().await.as_slice(), &[result]);
+        let published = broker.published.lock().await;
+        assert_eq!(published.len(), 2);
+        assert!(document_result(&published[0].1).embeddings.is_some());
+        assert!(document_result(&published[1].1).embeddings.is_none());
+        assert!(published[1].1.len() < published[0].1.len());
+    }
+}
@@ -0,0 +1,174 @@
+//! Subscription Liveness
+//!
+//! Each subscription is forwarded by a background task. If that task panics
+//! or hangs, the subscription silently stops delivering. Forwarding tasks
+//! tick while they are alive. A supervisor watches every forwarding task,
+//! and when one dies or stops ticking it subscribes again and starts a fresh
+//! task on the same receiver. Every restart is counted and announced on
+//! `SUBSCRIPTION_RESTARTED_SUBJECT`.
+
+use crate::nats_broker::SubjectCounters;
+use crate::CONTENT_TYPE_HEADER;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use swarm_core::{Message, MessageHeaders};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Subject subscription restarts are announced on
+pub const SUBSCRIPTION_RESTARTED_SUBJECT: &str = "swarm.subscriptions.restarted";
+
+/// How forwarding tasks are watched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionLivenessConfig {
+    /// Interval at which forwarding tasks tick, and the supervisor checks them
+    pub tick_interval_ms: u64,
+    
+    /// Time without a tick after which a forwarding task counts as hung (0 to only catch panics)
+    pub stale_after_ms: u64,
+}
+
+impl Default for SubscriptionLivenessConfig {
+    fn default() -> Self {
+        Self {
+            tick_interval_ms: 1000,
+            stale_after_ms: 30_000,
+        }
+    }
+}
+
+impl SubscriptionLivenessConfig {
+    pub fn tick_interval(&self) -> Duration {
+        Duration::from_millis(self.tick_interval_ms.max(1))
+    }
+}
+
+/// Why a subscription was restarted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RestartReason {
+    /// The forwarding task panicked
+    Panicked { message: String },
+    /// The forwarding task stopped ticking
+    Stale { silent_ms: u64 },
+}
+
+/// Event announcing a restarted subscription
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionRestart {
+    pub subject: String,
+    pub queue_group: Option<String>,
+    pub reason: RestartReason,
+    
+    /// Restarts of this subject so far, including this one
+    pub restarts: u64,
+    pub restarted_at: DateTime<Utc>,
+}
+
+impl SubscriptionRestart {
+    /// The event as a message on `SUBSCRIPTION_RESTARTED_SUBJECT`
+    pub fn to_message(&self) -> Result<Message> {
+        let mut headers = MessageHeaders::new();
+        headers.insert(CONTENT_TYPE_HEADER, "application/json");
+        Ok(Message {
+            id: Uuid::new_v4(),
+            subject: SUBSCRIPTION_RESTARTED_SUBJECT.to_string(),
+            payload: serde_json::to_vec(self)?,
+            headers,
+            timestamp: self.restarted_at,
+            ttl_ms: None,
+        })
+    }
+}
+
+/// How a watched forwarding task ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ForwarderExit {
+    /// The task returned or was cancelled; the subscription is over
+    Finished,
+    /// The task died or hung and should be replaced
+    Failed(RestartReason),
+}
+
+/// Wait until a forwarding task finishes, panics or stops ticking
+///
+/// A hung task is aborted before returning.
+pub(crate) async fn watch_forwarder(handle: &mut JoinHandle<()>, counters: &SubjectCounters, config: &SubscriptionLivenessConfig) -> ForwarderExit {
+    let mut check = tokio::time::interval(config.tick_interval());
+    loop {
+        tokio::select! {
+            joined = &mut *handle => {
+                return match joined {
+                    Err(e) if e.is_panic() => ForwarderExit::Failed(RestartReason::Panicked { message: panic_message(e.into_panic()) }),
+                    _ => ForwarderExit::Finished,
+                };
+            }
+            _ = check.tick() => {
+                let silent_ms = counters.silent_ms(Utc::now());
+                if config.stale_after_ms > 0 && silent_ms > config.stale_after_ms {
+                    handle.abort();
+                    return ForwarderExit::Failed(RestartReason::Stale { silent_ms });
+                }
+            }
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or_else(|| "unknown panic".to_string(), |message| message.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn config(stale_after_ms: u64) -> SubscriptionLivenessConfig {
+        SubscriptionLivenessConfig { tick_interval_ms: 10, stale_after_ms }
+    }
+    
+    #[tokio::test]
+    async fn test_watch_reports_panicked_and_finished_tasks() {
+        let counters = SubjectCounters::default();
+        counters.record_tick();
+        
+        let mut panicking = tokio::spawn(async { panic!("malformed payload") });
+        assert_eq!(watch_forwarder(&mut panicking, &counters, &config(0)).await,
+            ForwarderExit::Failed(RestartReason::Panicked { message: "malformed payload".to_string() }));
+        
+        let mut finished = tokio::spawn(async {});
+        assert_eq!(watch_forwarder(&mut finished, &counters, &config(0)).await, ForwarderExit::Finished);
+    }
+    
+    #[tokio::test]
+    async fn test_watch_aborts_tasks_that_stop_ticking() {
+        let counters = SubjectCounters::default();
+        counters.record_tick();
+        
+        let mut hung = tokio::spawn(std::future::pending::<()>());
+        match watch_forwarder(&mut hung, &counters, &config(50)).await {
+            ForwarderExit::Failed(RestartReason::Stale { silent_ms }) => assert!(silent_ms > 50),
+            exit => panic!("unexpected {:?}", exit),
+        }
+        assert!(hung.await.unwrap_err().is_cancelled());
+    }
+    
+    #[test]
+    fn test_restart_event_message() {
+        let restart = SubscriptionRestart {
+            subject: "swarm.tasks".to_string(),
+            queue_group: Some("workers".to_string()),
+            reason: RestartReason::Stale { silent_ms: 40_000 },
+            restarts: 2,
+            restarted_at: Utc::now(),
+        };
+        let message = restart.to_message().unwrap();
+        assert_eq!(message.subject, SUBSCRIPTION_RESTARTED_SUBJECT);
+        assert_eq!(serde_json::from_slice::<SubscriptionRestart>(&message.payload).unwrap(), restart);
+        assert_eq!(serde_json::to_value(&restart.reason).unwrap(), serde_json::json!({"kind": "stale", "silent_ms": 40_000}));
+    }
+}
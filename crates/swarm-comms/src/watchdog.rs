@@ -0,0 +1,395 @@
+//! Pipeline Watchdog
+//!
+//! Black-box health check for the whole pipeline. At a fixed interval the
+//! watchdog submits a synthetic probe document, waits for its result to be
+//! published, and measures the end-to-end latency. A probe that fails or
+//! does not complete within the SLA raises an alert.
+
+use super::*;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use swarm_core::{
+    CancellationToken, DocumentBuilder, DocumentContent, DocumentProcessingOptions, DocumentProcessingType, DocumentType,
+    MetricsCollector, Service, Task, TaskPayload, TaskPriority, TaskResult, TaskStatus, TaskType,
+};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Task metadata key marking synthetic probe tasks
+pub const PROBE_METADATA_KEY: &str = "watchdog_probe";
+
+/// Watchdog configuration
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WatchdogConfig {
+    /// Interval between probes in milliseconds
+    pub probe_interval_ms: u64,
+    
+    /// Time a probe may take from submission to published result
+    pub sla_ms: u64,
+    
+    /// Text of the probe document
+    pub probe_text: String,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            probe_interval_ms: 60_000,
+            sla_ms: 30_000,
+            probe_text: "Watchdog probe document. The pipeline extracts this text end to end.".to_string(),
+        }
+    }
+}
+
+/// Where probes are submitted and their results observed
+#[async_trait]
+pub trait ProbeTarget: Send + Sync {
+    /// Submit a probe task for processing
+    async fn submit(&self, task: Task) -> Result<()>;
+    
+    /// Wait for the next published result; `None` once no more can arrive
+    async fn next_result(&self) -> Option<TaskResult>;
+}
+
+/// Probes a swarm through the task assignment and result subjects
+pub struct NatsProbeTarget {
+    broker: Arc<NatsBroker>,
+    router: MessageRouter,
+    results: Mutex<BrokerSubscription>,
+}
+
+impl NatsProbeTarget {
+    /// Subscribe to published results
+    pub async fn new(broker: Arc<NatsBroker>, router: MessageRouter) -> Result<Self> {
+        let results = broker.subscribe_to_subject(router.task_results_subject()).await?;
+        Ok(Self {
+            broker,
+            router,
+            results: Mutex::new(results),
+        })
+    }
+}
+
+#[async_trait]
+impl ProbeTarget for NatsProbeTarget {
+    async fn submit(&self, task: Task) -> Result<()> {
+        let message = MessageSerializer::serialize_task(&task)?;
+        self.broker.publish_message(self.router.task_assignment_subject(), &message).await?;
+        Ok(())
+    }
+    
+    async fn next_result(&self) -> Option<TaskResult> {
+        let mut results = self.results.lock().await;
+        loop {
+            let message = results.recv().await?;
+            match MessageSerializer::deserialize_task_result(&message) {
+                Ok(result) => return Some(result),
+                Err(e) => tracing::warn!("Skipping malformed task result on {}: {}", results.subject(), e),
+            }
+        }
+    }
+}
+
+/// How a probe ended
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ProbeStatus {
+    Completed,
+    /// The pipeline returned a non-completed result
+    Failed { status: TaskStatus, error: Option<String> },
+    /// No result within the SLA
+    TimedOut,
+    /// The probe could not be submitted
+    SubmitFailed { error: String },
+}
+
+/// Result of one probe
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ProbeOutcome {
+    pub probe_id: Uuid,
+    pub submitted_at: DateTime<Utc>,
+    pub status: ProbeStatus,
+    
+    /// Submission to published result; `None` if no result arrived
+    pub latency_ms: Option<u64>,
+}
+
+impl ProbeOutcome {
+    pub fn is_healthy(&self) -> bool {
+        self.status == ProbeStatus::Completed
+    }
+}
+
+/// Change in pipeline health
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum WatchdogEvent {
+    /// A probe failed or breached the SLA
+    Alert { outcome: ProbeOutcome, consecutive_failures: u32 },
+    /// A probe succeeded after one or more failures
+    Recovered { outcome: ProbeOutcome },
+}
+
+/// Periodically probes the pipeline end to end
+pub struct PipelineWatchdog {
+    config: WatchdogConfig,
+    target: Arc<dyn ProbeTarget>,
+    metrics: Option<Arc<dyn MetricsCollector>>,
+    subscribers: std::sync::Mutex<Vec<mpsc::UnboundedSender<WatchdogEvent>>>,
+    consecutive_failures: AtomicU32,
+}
+
+impl PipelineWatchdog {
+    pub fn new(config: WatchdogConfig, target: Arc<dyn ProbeTarget>) -> Self {
+        Self {
+            config,
+            target,
+            metrics: None,
+            subscribers: std::sync::Mutex::new(Vec::new()),
+            consecutive_failures: AtomicU32::new(0),
+        }
+    }
+    
+    /// Record probe latency and failures
+    pub fn with_metrics(mut self, metrics: Arc<dyn MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    /// Subscribe to alerts and recoveries
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<WatchdogEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+    
+    /// Probes failed in a row
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures.load(Ordering::Relaxed)
+    }
+    
+    /// Submit one probe and follow it to its published result
+    pub async fn probe(&self) -> ProbeOutcome {
+        let task = self.probe_task();
+        let probe_id = task.id;
+        let submitted_at = Utc::now();
+        let start_time = Instant::now();
+        
+        let status = match self.target.submit(task).await {
+            Ok(()) => self.await_result(probe_id).await,
+            Err(e) => ProbeStatus::SubmitFailed { error: e.to_string() },
+        };
+        let latency_ms = match status {
+            ProbeStatus::Completed | ProbeStatus::Failed { .. } => Some(start_time.elapsed().as_millis() as u64),
+            ProbeStatus::TimedOut | ProbeStatus::SubmitFailed { .. } => None,
+        };
+        
+        let outcome = ProbeOutcome { probe_id, submitted_at, status, latency_ms };
+        self.record(&outcome);
+        outcome
+    }
+    
+    /// Probe now and then every `probe_interval_ms`
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move { self.run(CancellationToken::new()).await })
+    }
+    
+    /// Probe now and then every `probe_interval_ms` until cancelled; a probe
+    /// in progress finishes first
+    pub async fn run(&self, cancel: CancellationToken) {
+        let mut interval = tokio::time::interval(Duration::from_millis(self.config.probe_interval_ms.max(1)));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => break,
+                _ = interval.tick() => {
+                    self.probe().await;
+                }
+            }
+        }
+    }
+    
+    async fn await_result(&self, probe_id: Uuid) -> ProbeStatus {
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(self.config.sla_ms);
+        loop {
+            match tokio::time::timeout_at(deadline, self.target.next_result()).await {
+                Ok(Some(result)) if result.task_id == probe_id => {
+                    return match result.status {
+                        TaskStatus::Completed => ProbeStatus::Completed,
+                        status => ProbeStatus::Failed { status, error: result.error },
+                    };
+                }
+                // Other results, including late results of earlier probes
+                Ok(Some(_)) => continue,
+                Ok(None) | Err(_) => return ProbeStatus::TimedOut,
+            }
+        }
+    }
+    
+    fn record(&self, outcome: &ProbeOutcome) {
+        if let Some(metrics) = &self.metrics {
+            match outcome.latency_ms {
+                Some(latency_ms) => metrics.record_histogram("watchdog.probe_latency_ms", latency_ms as f64, &[]),
+                None => metrics.increment_counter("watchdog.probe_timeouts", &[]),
+            }
+            if !outcome.is_healthy() {
+                metrics.increment_counter("watchdog.probe_failures", &[]);
+            }
+        }
+        
+        let event = if outcome.is_healthy() {
+            tracing::debug!("Watchdog probe {} completed in {:?}ms", outcome.probe_id, outcome.latency_ms);
+            if self.consecutive_failures.swap(0, Ordering::Relaxed) == 0 {
+                return;
+            }
+            tracing::info!("Pipeline recovered: probe {} completed", outcome.probe_id);
+            WatchdogEvent::Recovered { outcome: outcome.clone() }
+        } else {
+            let consecutive_failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+            tracing::error!("Watchdog probe {} failed ({:?}), {} failure(s) in a row",
+                outcome.probe_id, outcome.status, consecutive_failures);
+            WatchdogEvent::Alert { outcome: outcome.clone(), consecutive_failures }
+        };
+        self.subscribers.lock().unwrap().retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+    
+    fn probe_task(&self) -> Task {
+        let document = DocumentBuilder::new("watchdog-probe.txt".to_string(), DocumentContent::Text(self.config.probe_text.clone()))
+            .document_type(DocumentType::Text)
+            .build();
+        Task {
+            id: Uuid::new_v4(),
+            task_type: TaskType::DocumentProcessing {
+                document_type: DocumentType::Text,
+                processing_type: DocumentProcessingType::TextExtraction,
+            },
+            priority: TaskPriority::High,
+            status: TaskStatus::Pending,
+            payload: TaskPayload::Document {
+                document,
+                processing_options: DocumentProcessingOptions::default(),
+            },
+            created_at: Utc::now(),
+            deadline: Some(Utc::now() + chrono::Duration::milliseconds(self.config.sla_ms as i64)),
+            retry_count: 0,
+            max_retries: 0,
+            metadata: HashMap::from([(PROBE_METADATA_KEY.to_string(), serde_json::json!(true))]),
+        }
+    }
+}
+
+#[async_trait]
+impl Service for PipelineWatchdog {
+    fn name(&self) -> &str {
+        "watchdog"
+    }
+    
+    async fn run(&mut self, cancel: CancellationToken) -> anyhow::Result<()> {
+        PipelineWatchdog::run(self, cancel).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Answers each probe after a delay, or never when `respond` is false
+    struct EchoTarget {
+        delay: Duration,
+        respond: std::sync::atomic::AtomicBool,
+        results_tx: mpsc::UnboundedSender<TaskResult>,
+        results_rx: Mutex<mpsc::UnboundedReceiver<TaskResult>>,
+    }
+    
+    impl EchoTarget {
+        fn new(delay: Duration) -> Self {
+            let (results_tx, results_rx) = mpsc::unbounded_channel();
+            Self {
+                delay,
+                respond: std::sync::atomic::AtomicBool::new(true),
+                results_tx,
+                results_rx: Mutex::new(results_rx),
+            }
+        }
+    }
+    
+    #[async_trait]
+    impl ProbeTarget for EchoTarget {
+        async fn submit(&self, task: Task) -> Result<()> {
+            assert_eq!(task.metadata[PROBE_METADATA_KEY], true);
+            if !self.respond.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            
+            // An unrelated result is published first
+            let mut other = create_test_result(Uuid::new_v4());
+            other.status = TaskStatus::Failed;
+            let results = self.results_tx.clone();
+            let delay = self.delay;
+            tokio::spawn(async move {
+                let _ = results.send(other);
+                tokio::time::sleep(delay).await;
+                let _ = results.send(create_test_result(task.id));
+            });
+            Ok(())
+        }
+        
+        async fn next_result(&self) -> Option<TaskResult> {
+            self.results_rx.lock().await.recv().await
+        }
+    }
+    
+    fn create_test_result(task_id: Uuid) -> TaskResult {
+        TaskResult {
+            task_id,
+            status: TaskStatus::Completed,
+            result: None,
+            error: None,
+            processing_time_ms: 1,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        }
+    }
+    
+    fn create_test_watchdog(target: Arc<EchoTarget>, sla_ms: u64) -> PipelineWatchdog {
+        PipelineWatchdog::new(WatchdogConfig { sla_ms, ..Default::default() }, target)
+    }
+    
+    #[tokio::test]
+    async fn test_probe_measures_latency() {
+        let watchdog = create_test_watchdog(Arc::new(EchoTarget::new(Duration::from_millis(20))), 2000);
+        let mut events = watchdog.subscribe_events();
+        
+        let outcome = watchdog.probe().await;
+        assert_eq!(outcome.status, ProbeStatus::Completed);
+        assert!(outcome.latency_ms.unwrap() >= 20);
+        assert_eq!(watchdog.consecutive_failures(), 0);
+        assert!(events.try_recv().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_sla_breach_alerts_and_recovers() {
+        let target = Arc::new(EchoTarget::new(Duration::from_millis(1)));
+        let watchdog = create_test_watchdog(target.clone(), 50);
+        let mut events = watchdog.subscribe_events();
+        
+        target.respond.store(false, Ordering::Relaxed);
+        for expected_failures in 1..=2 {
+            let outcome = watchdog.probe().await;
+            assert_eq!(outcome.status, ProbeStatus::TimedOut);
+            assert_eq!(outcome.latency_ms, None);
+            assert!(matches!(events.recv().await, Some(WatchdogEvent::Alert { consecutive_failures, .. })
+                if consecutive_failures == expected_failures));
+        }
+        
+        target.respond.store(true, Ordering::Relaxed);
+        let outcome = watchdog.probe().await;
+        assert!(outcome.is_healthy());
+        assert!(matches!(events.recv().await, Some(WatchdogEvent::Recovered { outcome: recovered }) if recovered == outcome));
+        assert_eq!(watchdog.consecutive_failures(), 0);
+    }
+}
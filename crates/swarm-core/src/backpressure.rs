@@ -0,0 +1,61 @@
+//! Backpressure
+//!
+//! Channels between the coordinator and its workers are bounded, so a fast
+//! publisher cannot queue tasks or results until a slow process runs out of
+//! memory. A worker whose task channel is full is passed over when tasks are
+//! distributed, and its share waits in the coordinator's queue; workers wait
+//! to hand in results while the result channel is full. The coordinator's
+//! queue itself can be capped, in which case `try_submit_task` refuses tasks
+//! with `SwarmError::QueueFull` instead of queueing them.
+
+use serde::{Deserialize, Serialize};
+
+/// Default capacity of a worker's task channel
+pub const DEFAULT_TASK_QUEUE_CAPACITY: usize = 64;
+
+/// Default capacity of the result channel
+pub const DEFAULT_RESULT_QUEUE_CAPACITY: usize = 1024;
+
+/// Capacities of the coordinator's channels and queue
+///
+/// Bounding the task and result channels keeps a slow worker or consumer
+/// from buffering without limit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackpressureConfig {
+    /// Tasks a worker's channel holds before the worker is passed over
+    #[serde(default = "default_task_queue_capacity")]
+    pub task_queue_capacity: usize,
+    
+    /// Results the result channel holds before workers wait to send
+    #[serde(default = "default_result_queue_capacity")]
+    pub result_queue_capacity: usize,
+    
+    /// Queued tasks above which `try_submit_task` refuses tasks (unlimited when unset)
+    #[serde(default)]
+    pub max_pending_tasks: Option<usize>,
+}
+
+pub fn default_task_queue_capacity() -> usize {
+    DEFAULT_TASK_QUEUE_CAPACITY
+}
+
+pub fn default_result_queue_capacity() -> usize {
+    DEFAULT_RESULT_QUEUE_CAPACITY
+}
+
+impl Default for BackpressureConfig {
+    fn default() -> Self {
+        Self {
+            task_queue_capacity: DEFAULT_TASK_QUEUE_CAPACITY,
+            result_queue_capacity: DEFAULT_RESULT_QUEUE_CAPACITY,
+            max_pending_tasks: None,
+        }
+    }
+}
+
+impl BackpressureConfig {
+    /// Whether a queue holding `pending` tasks accepts another
+    pub fn accepts(&self, pending: usize) -> bool {
+        self.max_pending_tasks.is_none_or(|max| pending < max)
+    }
+}
@@ -0,0 +1,58 @@
+//! Base64 Bytes
+//!
+//! Serde helper for byte fields: base64 in human-readable formats such as
+//! JSON, raw bytes in binary ones. JSON would otherwise write every byte as
+//! a number, several times the size of the bytes themselves. Arrays of
+//! numbers are still read, so values written before are not lost.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::{Deserializer, Serializer};
+use std::fmt;
+
+pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    } else {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    struct BytesVisitor;
+    
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+        
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("base64 text, bytes or an array of bytes")
+        }
+        
+        fn visit_str<E: Error>(self, encoded: &str) -> Result<Vec<u8>, E> {
+            STANDARD.decode(encoded).map_err(Error::custom)
+        }
+        
+        fn visit_bytes<E: Error>(self, bytes: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(bytes.to_vec())
+        }
+        
+        fn visit_byte_buf<E: Error>(self, bytes: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(bytes)
+        }
+        
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+    
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(BytesVisitor)
+    } else {
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
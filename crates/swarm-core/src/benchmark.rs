@@ -0,0 +1,127 @@
+//! Startup Benchmark Reports
+//!
+//! Workers can measure their processors at startup (see
+//! `swarm_worker::run_benchmark`) and register with the measured latency
+//! and throughput of every task type instead of a hand-written profile. The
+//! report travels in the worker's metadata, so the coordinator's capacity
+//! estimates can start from measurements before any real task finished.
+
+use crate::types::{PerformanceProfile, TaskType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Worker metadata key the startup benchmark report is registered under
+pub const BENCHMARK_METADATA_KEY: &str = "startup_benchmark";
+
+/// Measurements for one task type
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TaskTypeBenchmark {
+    pub task_type: TaskType,
+    pub samples: usize,
+    pub failures: usize,
+    /// Mean latency of the successful samples
+    pub avg_latency_ms: f64,
+    pub throughput_per_second: f64,
+}
+
+impl TaskTypeBenchmark {
+    /// Whether any sample succeeded
+    pub fn is_measured(&self) -> bool {
+        self.failures < self.samples
+    }
+}
+
+/// Outcome of a startup benchmark
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BenchmarkReport {
+    pub task_types: Vec<TaskTypeBenchmark>,
+    pub measured_at: DateTime<Utc>,
+}
+
+impl BenchmarkReport {
+    /// Measurements of the task types any sample succeeded for
+    pub fn measured(&self) -> impl Iterator<Item = &TaskTypeBenchmark> {
+        self.task_types.iter().filter(|benchmark| benchmark.is_measured())
+    }
+    
+    /// Profile over the measured task types among `task_types`, keeping the
+    /// memory and CPU figures of `base`; `None` if none was measured
+    pub fn profile_for(&self, task_types: &[TaskType], base: &PerformanceProfile) -> Option<PerformanceProfile> {
+        let measured = self.measured()
+            .filter(|benchmark| task_types.contains(&benchmark.task_type))
+            .collect::<Vec<_>>();
+        if measured.is_empty() {
+            return None;
+        }
+        let count = measured.len() as f64;
+        let latency_ms = measured.iter().map(|benchmark| benchmark.avg_latency_ms).sum::<f64>() / count;
+        let throughput = measured.iter().map(|benchmark| benchmark.throughput_per_second).sum::<f64>() / count;
+        Some(PerformanceProfile {
+            avg_processing_time_ms: latency_ms.ceil() as u64,
+            throughput_per_second: throughput as f32,
+            ..base.clone()
+        })
+    }
+    
+    /// Profile over every measured task type
+    pub fn profile(&self, base: &PerformanceProfile) -> Option<PerformanceProfile> {
+        let task_types = self.measured().map(|benchmark| benchmark.task_type.clone()).collect::<Vec<_>>();
+        self.profile_for(&task_types, base)
+    }
+    
+    /// The report a worker registered with, if it ran a startup benchmark
+    pub fn from_metadata(metadata: &HashMap<String, serde_json::Value>) -> Option<Self> {
+        let value = metadata.get(BENCHMARK_METADATA_KEY)?;
+        serde_json::from_value(value.clone()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DocumentProcessingType, DocumentType};
+    
+    fn benchmark(document_type: DocumentType, avg_latency_ms: f64, failures: usize) -> TaskTypeBenchmark {
+        TaskTypeBenchmark {
+            task_type: TaskType::DocumentProcessing {
+                document_type,
+                processing_type: DocumentProcessingType::TextExtraction,
+            },
+            samples: 3,
+            failures,
+            avg_latency_ms,
+            throughput_per_second: if failures < 3 { 1000.0 / avg_latency_ms } else { 0.0 },
+        }
+    }
+    
+    #[test]
+    fn test_profiles_average_measured_types() {
+        let report = BenchmarkReport {
+            task_types: vec![
+                benchmark(DocumentType::Text, 10.0, 0),
+                benchmark(DocumentType::Pdf, 30.5, 1),
+                benchmark(DocumentType::Image, 0.0, 3),
+            ],
+            measured_at: Utc::now(),
+        };
+        let base = PerformanceProfile {
+            avg_processing_time_ms: 1,
+            memory_usage_mb: 256,
+            cpu_intensity: 0.8,
+            throughput_per_second: 1.0,
+        };
+        
+        assert_eq!(report.measured().count(), 2);
+        let profile = report.profile(&base).unwrap();
+        assert_eq!(profile.avg_processing_time_ms, 21);
+        assert_eq!(profile.memory_usage_mb, 256);
+        
+        let image_only = [report.task_types[2].task_type.clone()];
+        assert!(report.profile_for(&image_only, &base).is_none());
+        
+        let metadata = HashMap::from([(BENCHMARK_METADATA_KEY.to_string(), serde_json::to_value(&report).unwrap())]);
+        assert_eq!(BenchmarkReport::from_metadata(&metadata), Some(report));
+        assert!(BenchmarkReport::from_metadata(&HashMap::new()).is_none());
+    }
+}
@@ -0,0 +1,16 @@
+//! Swarm Schema
+//!
+//! Writes the JSON Schemas of the published message and result types.
+//!
+//! Usage: `cargo run -p swarm-core --features schema --bin swarm-schema [output-dir]`
+//! (defaults to `schemas`)
+
+use std::path::PathBuf;
+
+fn main() -> anyhow::Result<()> {
+    let directory = std::env::args().nth(1).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("schemas"));
+    for path in swarm_core::schema::write_schemas(&directory)? {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
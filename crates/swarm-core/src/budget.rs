@@ -0,0 +1,230 @@
+//! Analysis Budgets
+//!
+//! Language detection, keyword counting and sentiment scoring take time in
+//! proportion to the text they see, so one huge document can stall a worker
+//! for minutes. Each stage has a work budget: at most `max_bytes` of text
+//! and `max_tokens` whitespace-separated tokens. Text over the byte budget
+//! is analyzed as its head plus evenly spaced samples of the rest, so the
+//! end of a document still counts, and the result notes what was left out.
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Samples taken from text past the head when a byte budget is exceeded
+pub const DEFAULT_SAMPLE_WINDOWS: usize = 8;
+
+/// Work limit of one analysis stage; unset limits are unlimited
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkBudget {
+    /// Bytes of text analyzed
+    pub max_bytes: Option<usize>,
+    
+    /// Tokens analyzed, counted after the byte budget is applied
+    pub max_tokens: Option<usize>,
+    
+    /// Windows sampled from the text past the head, 0 to analyze the head only
+    pub sample_windows: usize,
+}
+
+impl Default for WorkBudget {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+impl WorkBudget {
+    pub fn unlimited() -> Self {
+        Self {
+            max_bytes: None,
+            max_tokens: None,
+            sample_windows: DEFAULT_SAMPLE_WINDOWS,
+        }
+    }
+    
+    /// At most `max_bytes` of text, sampled past the head
+    pub fn bytes(max_bytes: usize) -> Self {
+        Self {
+            max_bytes: Some(max_bytes),
+            ..Self::unlimited()
+        }
+    }
+    
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+    
+    pub fn with_sample_windows(mut self, sample_windows: usize) -> Self {
+        self.sample_windows = sample_windows;
+        self
+    }
+    
+    /// The part of `text` a stage may analyze
+    pub fn apply<'a>(&self, text: &'a str) -> BudgetedText<'a> {
+        let mut budgeted = BudgetedText {
+            text: Cow::Borrowed(text),
+            total_bytes: text.len(),
+            sampled: false,
+            truncated: false,
+        };
+        
+        if let Some(max_bytes) = self.max_bytes.filter(|max_bytes| text.len() > *max_bytes) {
+            budgeted.truncated = true;
+            budgeted.sampled = self.sample_windows > 0;
+            budgeted.text = if budgeted.sampled {
+                Cow::Owned(sample(text, max_bytes, self.sample_windows))
+            } else {
+                Cow::Borrowed(&text[..floor_char_boundary(text, max_bytes)])
+            };
+        }
+        
+        if let Some(max_tokens) = self.max_tokens {
+            let end = match max_tokens.checked_sub(1).and_then(|last| budgeted.text.split_whitespace().nth(last)) {
+                Some(last_token) => last_token.as_ptr() as usize - budgeted.text.as_ptr() as usize + last_token.len(),
+                None if max_tokens == 0 => 0,
+                None => budgeted.text.len(),
+            };
+            if budgeted.text[end..].split_whitespace().next().is_some() {
+                budgeted.truncated = true;
+                budgeted.text = match budgeted.text {
+                    Cow::Borrowed(text) => Cow::Borrowed(&text[..end]),
+                    Cow::Owned(mut text) => {
+                        text.truncate(end);
+                        Cow::Owned(text)
+                    }
+                };
+            }
+        }
+        budgeted
+    }
+}
+
+/// Head of `text` plus `windows` evenly spaced samples, `max_bytes` in total
+fn sample(text: &str, max_bytes: usize, windows: usize) -> String {
+    let head_end = floor_char_boundary(text, max_bytes / 2);
+    // Each window is preceded by a newline
+    let window_bytes = (max_bytes - head_end).saturating_sub(windows) / windows;
+    let mut sampled = String::with_capacity(max_bytes);
+    sampled.push_str(&text[..head_end]);
+    if window_bytes == 0 {
+        return sampled;
+    }
+    
+    // The last window ends at the end of the text
+    let span = text.len() - head_end - window_bytes;
+    // A single window is taken from the very end
+    let gaps = windows.saturating_sub(1).max(1);
+    for window in 0..windows {
+        let position = if windows == 1 { 1 } else { window };
+        let start = ceil_char_boundary(text, head_end + span * position / gaps);
+        let end = floor_char_boundary(text, start + window_bytes);
+        sampled.push('\n');
+        sampled.push_str(&text[start..end]);
+    }
+    sampled
+}
+
+fn ceil_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Text selected by a work budget
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetedText<'a> {
+    pub text: Cow<'a, str>,
+    
+    /// Size of the whole text
+    pub total_bytes: usize,
+    
+    /// Whether the text past the head was sampled
+    pub sampled: bool,
+    
+    /// Whether anything was left out
+    pub truncated: bool,
+}
+
+impl BudgetedText<'_> {
+    /// Bytes the stage analyzes
+    pub fn analyzed_bytes(&self) -> usize {
+        self.text.len()
+    }
+}
+
+/// Work budgets of the text analysis stages
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AnalysisBudgets {
+    pub language: WorkBudget,
+    pub keywords: WorkBudget,
+    pub sentiment: WorkBudget,
+}
+
+impl Default for AnalysisBudgets {
+    fn default() -> Self {
+        Self {
+            language: WorkBudget::bytes(64 * 1024),
+            keywords: WorkBudget::bytes(8 * 1024 * 1024),
+            sentiment: WorkBudget::bytes(1024 * 1024),
+        }
+    }
+}
+
+impl AnalysisBudgets {
+    pub fn unlimited() -> Self {
+        Self {
+            language: WorkBudget::unlimited(),
+            keywords: WorkBudget::unlimited(),
+            sentiment: WorkBudget::unlimited(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_text_within_budget_is_borrowed() {
+        let budgeted = WorkBudget::bytes(100).with_max_tokens(10).apply("a small text");
+        assert!(matches!(budgeted.text, Cow::Borrowed("a small text")));
+        assert!(!budgeted.truncated);
+        assert_eq!(WorkBudget::unlimited().apply("x").analyzed_bytes(), 1);
+    }
+    
+    #[test]
+    fn test_over_budget_text_is_sampled_to_its_end() {
+        let text = format!("{}{}", "start ".repeat(1000), "finale ".repeat(10));
+        let budgeted = WorkBudget::bytes(400).with_sample_windows(4).apply(&text);
+        assert!(budgeted.truncated && budgeted.sampled);
+        assert!(budgeted.analyzed_bytes() <= 400);
+        assert!(budgeted.text.starts_with("start start"));
+        assert!(budgeted.text.ends_with("finale "));
+        assert_eq!(budgeted.total_bytes, text.len());
+        
+        let head = WorkBudget::bytes(5).with_sample_windows(0).apply("åäöåäö");
+        assert_eq!(head.text, "åä");
+        assert!(!head.sampled);
+    }
+    
+    #[test]
+    fn test_token_budget_cuts_after_last_token() {
+        let budgeted = WorkBudget::unlimited().with_max_tokens(3).apply("  one two\nthree four five");
+        assert_eq!(budgeted.text, "  one two\nthree");
+        assert!(budgeted.truncated);
+        assert!(!WorkBudget::unlimited().with_max_tokens(3).apply("one two three ").truncated);
+        assert_eq!(WorkBudget::unlimited().with_max_tokens(0).apply("one").text, "");
+    }
+}
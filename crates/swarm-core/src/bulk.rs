@@ -0,0 +1,254 @@
+//! Bulk Task Administration
+//!
+//! Operators act on many tasks at once: "cancel everything from tenant X
+//! submitted in the last hour", "requeue all failed PDF tasks". A filter
+//! selects tasks by tenant, type, status and submission time; a dry run
+//! previews the matches without recording anything, and progress is
+//! reported while the action is applied.
+
+use crate::types::{DocumentType, Task, TaskStatus, TaskType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Task metadata key holding the submitting tenant
+pub const TENANT_KEY: &str = "tenant";
+
+/// Selection of coordinator tasks; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TaskFilter {
+    /// Tasks whose `tenant` metadata equals this value
+    pub tenant: Option<String>,
+    
+    pub task_type: Option<TaskType>,
+    
+    /// Document processing tasks of this document type, any processing type
+    pub document_type: Option<DocumentType>,
+    
+    /// `Pending`, `Processing` (in flight), `Retrying` or `Failed` (dead-lettered)
+    pub status: Option<TaskStatus>,
+    
+    /// Only tasks created at or after this time
+    pub from: Option<DateTime<Utc>>,
+    
+    /// Only tasks created before this time
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl TaskFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+    
+    pub fn with_task_type(mut self, task_type: TaskType) -> Self {
+        self.task_type = Some(task_type);
+        self
+    }
+    
+    pub fn with_document_type(mut self, document_type: DocumentType) -> Self {
+        self.document_type = Some(document_type);
+        self
+    }
+    
+    pub fn with_status(mut self, status: TaskStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+    
+    /// Only tasks created in `[from, to)`
+    pub fn with_time_range(mut self, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Self {
+        self.from = from;
+        self.to = to;
+        self
+    }
+    
+    /// Whether a task currently in `status` is selected
+    pub fn matches(&self, task: &Task, status: &TaskStatus) -> bool {
+        let tenant_matches = match &self.tenant {
+            Some(tenant) => task.metadata.get(TENANT_KEY).and_then(|value| value.as_str()) == Some(tenant.as_str()),
+            None => true,
+        };
+        tenant_matches
+            && self.task_type.as_ref().is_none_or(|task_type| *task_type == task.task_type)
+            && self.document_type.as_ref().is_none_or(|wanted| matches!(
+                &task.task_type,
+                TaskType::DocumentProcessing { document_type, .. } if document_type == wanted
+            ))
+            && self.status.as_ref().is_none_or(|wanted| wanted == status)
+            && self.from.is_none_or(|from| task.created_at >= from)
+            && self.to.is_none_or(|to| task.created_at < to)
+    }
+}
+
+/// What to do with the selected tasks
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkAction {
+    /// Remove the tasks; a worker already processing a cancelled task is
+    /// not interrupted, but its failure is no longer retried
+    Cancel,
+    
+    /// Queue in-flight, retrying and failed tasks again; failed tasks get
+    /// their retries back. Pending tasks are skipped.
+    Requeue,
+}
+
+impl BulkAction {
+    /// Whether the action changes a task in `status`
+    pub fn applies_to(&self, status: &TaskStatus) -> bool {
+        match self {
+            BulkAction::Cancel => true,
+            BulkAction::Requeue => *status != TaskStatus::Pending,
+        }
+    }
+}
+
+/// A bulk action over the tasks selected by a filter
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BulkOperation {
+    pub action: BulkAction,
+    pub filter: TaskFilter,
+    
+    /// Only report the matches, change nothing
+    #[serde(default)]
+    pub dry_run: bool,
+    
+    /// Recorded as the reason of every requeue or cancellation
+    pub reason: String,
+}
+
+impl BulkOperation {
+    pub fn new(action: BulkAction, filter: TaskFilter) -> Self {
+        let reason = match action {
+            BulkAction::Cancel => "bulk cancel",
+            BulkAction::Requeue => "bulk requeue",
+        };
+        Self {
+            action,
+            filter,
+            dry_run: false,
+            reason: reason.to_string(),
+        }
+    }
+    
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+    
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = reason.into();
+        self
+    }
+}
+
+/// A task selected by a bulk operation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BulkMatch {
+    pub task_id: Uuid,
+    pub task_type: TaskType,
+    
+    /// Status when the operation started
+    pub status: TaskStatus,
+    pub created_at: DateTime<Utc>,
+    
+    /// Whether the action changes this task (or would, for a dry run)
+    pub affected: bool,
+}
+
+impl BulkMatch {
+    pub fn new(task: &Task, status: TaskStatus, action: BulkAction) -> Self {
+        Self {
+            task_id: task.id,
+            task_type: task.task_type.clone(),
+            affected: action.applies_to(&status),
+            status,
+            created_at: task.created_at,
+        }
+    }
+}
+
+/// Progress of a running bulk operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BulkProgress {
+    /// Matches handled so far
+    pub processed: usize,
+    pub total: usize,
+}
+
+/// Outcome of a bulk operation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BulkReport {
+    pub action: BulkAction,
+    pub dry_run: bool,
+    
+    /// Matches in submission order
+    pub matches: Vec<BulkMatch>,
+    
+    /// Tasks that were changed (0 for a dry run)
+    pub applied: usize,
+}
+
+impl BulkReport {
+    /// Tasks the action changes or would change
+    pub fn affected(&self) -> usize {
+        self.matches.iter().filter(|task| task.affected).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_builder::TaskBuilder;
+    use crate::types::{DocumentProcessingType, TaskPayload};
+    use std::collections::HashMap;
+    
+    fn create_test_task(tenant: &str, task_type: TaskType) -> Task {
+        TaskBuilder::new(task_type, TaskPayload::Custom { data: Vec::new(), format: "raw".to_string() })
+            .metadata(TENANT_KEY, serde_json::json!(tenant))
+            .build()
+    }
+    
+    fn pdf_extraction() -> TaskType {
+        TaskType::DocumentProcessing {
+            document_type: DocumentType::Pdf,
+            processing_type: DocumentProcessingType::TextExtraction,
+        }
+    }
+    
+    #[test]
+    fn test_filter_matches_every_set_field() {
+        let task = create_test_task("acme", pdf_extraction());
+        let hour_ago = task.created_at - chrono::Duration::hours(1);
+        
+        assert!(TaskFilter::new().matches(&task, &TaskStatus::Pending));
+        let filter = TaskFilter::new()
+            .with_tenant("acme")
+            .with_task_type(pdf_extraction())
+            .with_document_type(DocumentType::Pdf)
+            .with_status(TaskStatus::Failed)
+            .with_time_range(Some(hour_ago), None);
+        assert!(filter.matches(&task, &TaskStatus::Failed));
+        assert!(!filter.matches(&task, &TaskStatus::Pending));
+        assert!(!filter.clone().with_document_type(DocumentType::Word).matches(&task, &TaskStatus::Failed));
+        assert!(!filter.clone().with_tenant("other").matches(&task, &TaskStatus::Failed));
+        assert!(!filter.clone().with_time_range(None, Some(hour_ago)).matches(&task, &TaskStatus::Failed));
+        assert!(!TaskFilter::new().with_tenant("acme").matches(&Task { metadata: HashMap::new(), ..task }, &TaskStatus::Pending));
+    }
+    
+    #[test]
+    fn test_requeue_skips_pending_tasks() {
+        let task = create_test_task("acme", pdf_extraction());
+        assert!(!BulkMatch::new(&task, TaskStatus::Pending, BulkAction::Requeue).affected);
+        assert!(BulkMatch::new(&task, TaskStatus::Retrying, BulkAction::Requeue).affected);
+        assert!(BulkMatch::new(&task, TaskStatus::Pending, BulkAction::Cancel).affected);
+        
+        let operation = BulkOperation::new(BulkAction::Cancel, TaskFilter::new()).dry_run();
+        assert_eq!((operation.dry_run, operation.reason.as_str()), (true, "bulk cancel"));
+    }
+}
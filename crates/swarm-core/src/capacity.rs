@@ -0,0 +1,550 @@
+//! Capacity Planning
+//!
+//! Answers "what if" questions about the worker fleet. The coordinator learns
+//! the processing time of each task type from finished tasks and keeps the
+//! arrival times of recent submissions; `CapacityPlanner` replays those
+//! arrivals against a hypothetical fleet, dispatching each task to the worker
+//! slot that frees up first among those supporting it, and projects queue
+//! depths, latencies and utilization.
+
+use crate::benchmark::BenchmarkReport;
+use crate::coordinator_events::{CoordinatorEvent, EventRecord, Projection};
+use crate::fairness::percentile;
+use crate::types::{TaskStatus, TaskType, WorkerConfig};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+/// Capacity planning configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacityConfig {
+    /// How far back task arrivals are kept for replay
+    pub arrival_window_secs: u64,
+    
+    /// Maximum number of arrivals kept, oldest dropped first
+    pub max_arrivals: usize,
+    
+    /// Weight of the newest sample in the processing time average, from 0.0 to 1.0
+    pub smoothing: f64,
+    
+    /// Processing time assumed for task types that never finished
+    pub default_processing_time_ms: u64,
+}
+
+impl Default for CapacityConfig {
+    fn default() -> Self {
+        Self {
+            arrival_window_secs: 3600,
+            max_arrivals: 100_000,
+            smoothing: 0.2,
+            default_processing_time_ms: 100,
+        }
+    }
+}
+
+/// Learns processing times per task type from finished tasks
+///
+/// Folded from coordinator events: submissions remember each task's type,
+/// completions update an exponentially weighted moving average. Until a
+/// task type has completed, the latency measured by the startup benchmark
+/// of the most recently registered worker supporting it is used.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessingTimeEstimator {
+    smoothing: f64,
+    default_ms: u64,
+    estimates_ms: HashMap<TaskType, f64>,
+    samples: HashMap<TaskType, u64>,
+    benchmarked_ms: HashMap<TaskType, f64>,
+    submitted: HashMap<Uuid, TaskType>,
+}
+
+impl ProcessingTimeEstimator {
+    pub fn new(config: &CapacityConfig) -> Self {
+        Self {
+            smoothing: config.smoothing.clamp(0.0, 1.0),
+            default_ms: config.default_processing_time_ms,
+            ..Default::default()
+        }
+    }
+    
+    /// Record one observed processing time
+    pub fn observe(&mut self, task_type: &TaskType, processing_time_ms: u64) {
+        let sample = processing_time_ms as f64;
+        let estimate = self.estimates_ms.entry(task_type.clone()).or_insert(sample);
+        *estimate += self.smoothing * (sample - *estimate);
+        *self.samples.entry(task_type.clone()).or_insert(0) += 1;
+    }
+    
+    /// Learned processing time of a task type, if any task of it completed
+    pub fn learned_ms(&self, task_type: &TaskType) -> Option<f64> {
+        self.estimates_ms.get(task_type).copied()
+    }
+    
+    /// Processing time of a task type measured by a worker's startup benchmark
+    pub fn benchmarked_ms(&self, task_type: &TaskType) -> Option<f64> {
+        self.benchmarked_ms.get(task_type).copied()
+    }
+    
+    /// Processing time of a task type: learned, else benchmarked, else the configured default
+    pub fn estimate_ms(&self, task_type: &TaskType) -> f64 {
+        self.learned_ms(task_type)
+            .or_else(|| self.benchmarked_ms(task_type))
+            .unwrap_or(self.default_ms as f64)
+    }
+    
+    /// Number of completions the estimate of a task type is based on
+    pub fn samples(&self, task_type: &TaskType) -> u64 {
+        self.samples.get(task_type).copied().unwrap_or(0)
+    }
+}
+
+impl Projection for ProcessingTimeEstimator {
+    fn apply(&mut self, record: &EventRecord) {
+        match &record.event {
+            CoordinatorEvent::WorkerRegistered { config, .. } => {
+                if let Some(report) = BenchmarkReport::from_metadata(&config.metadata) {
+                    for benchmark in report.measured() {
+                        self.benchmarked_ms.insert(benchmark.task_type.clone(), benchmark.avg_latency_ms);
+                    }
+                }
+            }
+            CoordinatorEvent::TaskSubmitted { task } => {
+                self.submitted.insert(task.id, task.task_type.clone());
+            }
+            CoordinatorEvent::TaskFinished { task_id, status, processing_time_ms } => {
+                if let Some(task_type) = self.submitted.remove(task_id) {
+                    if *status == TaskStatus::Completed {
+                        self.observe(&task_type, *processing_time_ms);
+                    }
+                }
+            }
+            CoordinatorEvent::TaskDropped { task_id, .. } | CoordinatorEvent::TaskDeadLettered { task_id, .. } => {
+                self.submitted.remove(task_id);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A task submission kept for replay
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskArrival {
+    pub at: DateTime<Utc>,
+    pub task_type: TaskType,
+}
+
+/// Recent task arrivals, folded from coordinator events
+#[derive(Debug, Clone, Default)]
+pub struct ArrivalStatistics {
+    window: chrono::Duration,
+    max_arrivals: usize,
+    arrivals: VecDeque<TaskArrival>,
+}
+
+impl ArrivalStatistics {
+    pub fn new(config: &CapacityConfig) -> Self {
+        Self {
+            window: chrono::Duration::seconds(config.arrival_window_secs as i64),
+            max_arrivals: config.max_arrivals.max(1),
+            arrivals: VecDeque::new(),
+        }
+    }
+    
+    /// Record an arrival, dropping those that fell out of the window
+    pub fn record(&mut self, at: DateTime<Utc>, task_type: TaskType) {
+        self.arrivals.push_back(TaskArrival { at, task_type });
+        while self.arrivals.len() > self.max_arrivals
+            || self.arrivals.front().is_some_and(|oldest| at - oldest.at > self.window) {
+            self.arrivals.pop_front();
+        }
+    }
+    
+    /// Kept arrivals, oldest first
+    pub fn arrivals(&self) -> impl Iterator<Item = &TaskArrival> {
+        self.arrivals.iter()
+    }
+    
+    /// Number of kept arrivals per task type
+    pub fn counts(&self) -> HashMap<TaskType, usize> {
+        let mut counts = HashMap::new();
+        for arrival in &self.arrivals {
+            *counts.entry(arrival.task_type.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+impl Projection for ArrivalStatistics {
+    fn apply(&mut self, record: &EventRecord) {
+        if let CoordinatorEvent::TaskSubmitted { task } = &record.event {
+            self.record(record.recorded_at, task.task_type.clone());
+        }
+    }
+}
+
+/// A group of identical workers in a hypothetical fleet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedWorkers {
+    pub name: String,
+    
+    /// Task types the workers accept; empty accepts every type
+    pub task_types: Vec<TaskType>,
+    
+    pub max_concurrent_tasks: usize,
+    
+    pub count: usize,
+}
+
+impl PlannedWorkers {
+    pub fn new(name: &str, task_types: Vec<TaskType>, count: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            task_types,
+            max_concurrent_tasks: 1,
+            count,
+        }
+    }
+    
+    /// `count` workers configured like `config`
+    pub fn like(config: &WorkerConfig, count: usize) -> Self {
+        Self {
+            name: config.name.clone(),
+            task_types: config.capabilities.iter()
+                .flat_map(|capability| capability.supported_task_types.iter().cloned())
+                .collect(),
+            max_concurrent_tasks: config.max_concurrent_tasks.max(1),
+            count,
+        }
+    }
+    
+    pub fn with_max_concurrent_tasks(mut self, max_concurrent_tasks: usize) -> Self {
+        self.max_concurrent_tasks = max_concurrent_tasks.max(1);
+        self
+    }
+    
+    pub fn supports(&self, task_type: &TaskType) -> bool {
+        self.task_types.is_empty() || self.task_types.contains(task_type)
+    }
+}
+
+/// Projected behaviour of one task type
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskTypeProjection {
+    pub tasks: usize,
+    pub estimated_processing_time_ms: u64,
+    pub mean_wait_ms: u64,
+    pub p95_wait_ms: u64,
+    pub mean_latency_ms: u64,
+    pub p95_latency_ms: u64,
+    pub max_queue_depth: usize,
+    /// Tasks no planned worker supports
+    pub unservable: usize,
+}
+
+/// Projected behaviour of one planned worker group
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkerGroupProjection {
+    pub name: String,
+    pub workers: usize,
+    pub tasks: usize,
+    /// Share of worker slot time spent processing, from 0.0 to 1.0
+    pub utilization: f32,
+}
+
+/// Outcome of replaying arrivals against a planned fleet
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CapacityProjection {
+    pub tasks: usize,
+    /// Time from the first arrival to the last completion
+    pub makespan_ms: u64,
+    pub mean_queue_depth: f32,
+    pub max_queue_depth: usize,
+    /// Tasks still queued when the last one arrived; a growing backlog
+    /// means the fleet cannot keep up
+    pub backlog_at_last_arrival: usize,
+    pub p95_latency_ms: u64,
+    pub by_task_type: HashMap<String, TaskTypeProjection>,
+    pub worker_groups: Vec<WorkerGroupProjection>,
+}
+
+/// Replays task arrivals against hypothetical fleets
+#[derive(Debug, Clone, Copy)]
+pub struct CapacityPlanner<'a> {
+    arrivals: &'a ArrivalStatistics,
+    estimator: &'a ProcessingTimeEstimator,
+}
+
+/// One simulated task: arrival, start and end in ms after the first arrival
+struct SimulatedTask<'a> {
+    task_type: &'a TaskType,
+    arrival: f64,
+    start: f64,
+    end: f64,
+}
+
+impl<'a> CapacityPlanner<'a> {
+    pub fn new(arrivals: &'a ArrivalStatistics, estimator: &'a ProcessingTimeEstimator) -> Self {
+        Self { arrivals, estimator }
+    }
+    
+    /// Project how `fleet` would have handled the recorded arrivals
+    pub fn simulate(&self, fleet: &[PlannedWorkers]) -> CapacityProjection {
+        let Some(first) = self.arrivals.arrivals().next() else {
+            return CapacityProjection {
+                worker_groups: fleet.iter().map(idle_group).collect(),
+                ..Default::default()
+            };
+        };
+        
+        // Time each worker slot becomes free, per group
+        let mut slots: Vec<Vec<f64>> = fleet.iter()
+            .map(|group| vec![0.0; group.count * group.max_concurrent_tasks.max(1)])
+            .collect();
+        let mut busy_ms = vec![0.0; fleet.len()];
+        let mut group_tasks = vec![0usize; fleet.len()];
+        let mut simulated = Vec::new();
+        let mut unservable: HashMap<&TaskType, usize> = HashMap::new();
+        
+        for arrival in self.arrivals.arrivals() {
+            let at = (arrival.at - first.at).num_milliseconds().max(0) as f64;
+            let earliest = fleet.iter().enumerate()
+                .filter(|(_, group)| group.supports(&arrival.task_type))
+                .flat_map(|(group, _)| slots[group].iter().enumerate().map(move |(slot, free_at)| (group, slot, *free_at)))
+                .min_by(|a, b| a.2.total_cmp(&b.2));
+            let Some((group, slot, free_at)) = earliest else {
+                *unservable.entry(&arrival.task_type).or_insert(0) += 1;
+                continue;
+            };
+            
+            let processing_ms = self.estimator.estimate_ms(&arrival.task_type);
+            let start = at.max(free_at);
+            slots[group][slot] = start + processing_ms;
+            busy_ms[group] += processing_ms;
+            group_tasks[group] += 1;
+            simulated.push(SimulatedTask { task_type: &arrival.task_type, arrival: at, start, end: start + processing_ms });
+        }
+        
+        let makespan = simulated.iter().map(|task| task.end).fold(0.0, f64::max);
+        let last_arrival = self.arrivals.arrivals().last().map(|last| (last.at - first.at).num_milliseconds().max(0) as f64).unwrap_or(0.0);
+        let total_wait: f64 = simulated.iter().map(|task| task.start - task.arrival).sum();
+        
+        let mut by_type: HashMap<&TaskType, Vec<&SimulatedTask>> = HashMap::new();
+        for task in &simulated {
+            by_type.entry(task.task_type).or_default().push(task);
+        }
+        let mut by_task_type: HashMap<String, TaskTypeProjection> = by_type.iter()
+            .map(|(task_type, tasks)| (task_type.to_string(), self.project_task_type(task_type, tasks)))
+            .collect();
+        for (task_type, count) in unservable {
+            let projection = by_task_type.entry(task_type.to_string()).or_default();
+            projection.tasks += count;
+            projection.unservable = count;
+            projection.estimated_processing_time_ms = self.estimator.estimate_ms(task_type).round() as u64;
+        }
+        
+        let all: Vec<&SimulatedTask> = simulated.iter().collect();
+        CapacityProjection {
+            tasks: self.arrivals.arrivals().count(),
+            makespan_ms: makespan.round() as u64,
+            mean_queue_depth: if makespan > 0.0 { (total_wait / makespan) as f32 } else { 0.0 },
+            max_queue_depth: max_queue_depth(&all),
+            backlog_at_last_arrival: simulated.iter().filter(|task| task.arrival <= last_arrival && task.start > last_arrival).count(),
+            p95_latency_ms: percentile_ms(all.iter().map(|task| task.end - task.arrival)),
+            by_task_type,
+            worker_groups: fleet.iter().enumerate()
+                .map(|(index, group)| {
+                    let capacity_ms = makespan * slots[index].len() as f64;
+                    WorkerGroupProjection {
+                        name: group.name.clone(),
+                        workers: group.count,
+                        tasks: group_tasks[index],
+                        utilization: if capacity_ms > 0.0 { (busy_ms[index] / capacity_ms) as f32 } else { 0.0 },
+                    }
+                })
+                .collect(),
+        }
+    }
+    
+    fn project_task_type(&self, task_type: &TaskType, tasks: &[&SimulatedTask]) -> TaskTypeProjection {
+        let waits: Vec<f64> = tasks.iter().map(|task| task.start - task.arrival).collect();
+        let latencies: Vec<f64> = tasks.iter().map(|task| task.end - task.arrival).collect();
+        TaskTypeProjection {
+            tasks: tasks.len(),
+            estimated_processing_time_ms: self.estimator.estimate_ms(task_type).round() as u64,
+            mean_wait_ms: mean_ms(&waits),
+            p95_wait_ms: percentile_ms(waits.iter().copied()),
+            mean_latency_ms: mean_ms(&latencies),
+            p95_latency_ms: percentile_ms(latencies.iter().copied()),
+            max_queue_depth: max_queue_depth(tasks),
+            unservable: 0,
+        }
+    }
+}
+
+fn idle_group(group: &PlannedWorkers) -> WorkerGroupProjection {
+    WorkerGroupProjection { name: group.name.clone(), workers: group.count, tasks: 0, utilization: 0.0 }
+}
+
+/// Most tasks waiting at once: a sweep over arrivals and starts
+fn max_queue_depth(tasks: &[&SimulatedTask]) -> usize {
+    let mut changes: Vec<(f64, i64)> = tasks.iter()
+        .filter(|task| task.start > task.arrival)
+        .flat_map(|task| [(task.arrival, 1), (task.start, -1)])
+        .collect();
+    // Starts before arrivals at the same instant
+    changes.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+    let mut depth = 0i64;
+    let mut max_depth = 0i64;
+    for (_, change) in changes {
+        depth += change;
+        max_depth = max_depth.max(depth);
+    }
+    max_depth as usize
+}
+
+fn mean_ms(samples: &[f64]) -> u64 {
+    if samples.is_empty() {
+        return 0;
+    }
+    (samples.iter().sum::<f64>() / samples.len() as f64).round() as u64
+}
+
+fn percentile_ms(samples: impl Iterator<Item = f64>) -> u64 {
+    let samples: VecDeque<u64> = samples.map(|sample| sample.round() as u64).collect();
+    percentile(&samples, 0.95).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::{TaskTypeBenchmark, BENCHMARK_METADATA_KEY};
+    use crate::coordinator_events::CoordinatorEventLog;
+    use crate::task_builder::TaskBuilder;
+    use crate::types::{DocumentProcessingType, DocumentType, PerformanceProfile, Task, TaskPayload, WorkerType};
+    
+    fn pdf() -> TaskType {
+        TaskType::DocumentProcessing { document_type: DocumentType::Pdf, processing_type: DocumentProcessingType::TextExtraction }
+    }
+    
+    fn text() -> TaskType {
+        TaskType::DocumentProcessing { document_type: DocumentType::Text, processing_type: DocumentProcessingType::TextExtraction }
+    }
+    
+    fn create_test_task(task_type: TaskType) -> Task {
+        TaskBuilder::new(task_type, TaskPayload::Custom { data: Vec::new(), format: "raw".to_string() }).build()
+    }
+    
+    /// Ten PDFs arriving 100ms apart, each taking 300ms
+    fn create_test_history() -> (ArrivalStatistics, ProcessingTimeEstimator) {
+        let config = CapacityConfig::default();
+        let mut estimator = ProcessingTimeEstimator::new(&config);
+        let mut log = CoordinatorEventLog::new();
+        let task = create_test_task(pdf());
+        log.append(CoordinatorEvent::TaskSubmitted { task: task.clone() });
+        log.append(CoordinatorEvent::TaskFinished { task_id: task.id, status: TaskStatus::Completed, processing_time_ms: 300 });
+        estimator.replay(log.records());
+        
+        let mut arrivals = ArrivalStatistics::new(&config);
+        let start = Utc::now();
+        for i in 0..10 {
+            arrivals.record(start + chrono::Duration::milliseconds(i * 100), pdf());
+        }
+        (arrivals, estimator)
+    }
+    
+    #[test]
+    fn test_estimator_learns_from_completed_tasks() {
+        let mut estimator = ProcessingTimeEstimator::new(&CapacityConfig { smoothing: 0.5, ..Default::default() });
+        let mut log = CoordinatorEventLog::new();
+        for (processing_time_ms, status) in [(100, TaskStatus::Completed), (200, TaskStatus::Completed), (9_000, TaskStatus::Failed)] {
+            let task = create_test_task(pdf());
+            log.append(CoordinatorEvent::TaskSubmitted { task: task.clone() });
+            log.append(CoordinatorEvent::TaskFinished { task_id: task.id, status, processing_time_ms });
+        }
+        estimator.replay(log.records());
+        
+        assert_eq!(estimator.learned_ms(&pdf()), Some(150.0));
+        assert_eq!(estimator.samples(&pdf()), 2);
+        assert_eq!(estimator.learned_ms(&text()), None);
+        assert_eq!(estimator.estimate_ms(&text()), 100.0);
+    }
+    
+    #[test]
+    fn test_estimator_starts_from_registered_benchmarks() {
+        let report = BenchmarkReport {
+            task_types: vec![TaskTypeBenchmark {
+                task_type: text(),
+                samples: 3,
+                failures: 0,
+                avg_latency_ms: 12.5,
+                throughput_per_second: 80.0,
+            }],
+            measured_at: Utc::now(),
+        };
+        let config = WorkerConfig {
+            id: Uuid::new_v4(),
+            name: "benchmarked".to_string(),
+            worker_type: WorkerType::Custom { name: "test".to_string(), version: "1".to_string() },
+            max_concurrent_tasks: 1,
+            capabilities: Vec::new(),
+            performance_profile: PerformanceProfile {
+                avg_processing_time_ms: 13,
+                memory_usage_mb: 64,
+                cpu_intensity: 0.5,
+                throughput_per_second: 80.0,
+            },
+            health_check_interval_ms: 1000,
+            shutdown_timeout_ms: 1000,
+            metadata: HashMap::from([(BENCHMARK_METADATA_KEY.to_string(), serde_json::to_value(&report).unwrap())]),
+        };
+        
+        let mut estimator = ProcessingTimeEstimator::new(&CapacityConfig::default());
+        let mut log = CoordinatorEventLog::new();
+        log.append(CoordinatorEvent::WorkerRegistered { worker_id: config.id, config });
+        estimator.replay(log.records());
+        assert_eq!(estimator.estimate_ms(&text()), 12.5);
+        
+        let task = create_test_task(text());
+        log.append(CoordinatorEvent::TaskSubmitted { task: task.clone() });
+        log.append(CoordinatorEvent::TaskFinished { task_id: task.id, status: TaskStatus::Completed, processing_time_ms: 40 });
+        estimator.replay(&log.records()[1..]);
+        assert_eq!(estimator.estimate_ms(&text()), 40.0);
+        assert_eq!(estimator.benchmarked_ms(&text()), Some(12.5));
+    }
+    
+    #[test]
+    fn test_adding_workers_shortens_the_queue() {
+        let (arrivals, estimator) = create_test_history();
+        let planner = CapacityPlanner::new(&arrivals, &estimator);
+        
+        let one = planner.simulate(&[PlannedWorkers::new("pdf", vec![pdf()], 1)]);
+        let projection = &one.by_task_type[&pdf().to_string()];
+        assert_eq!(one.tasks, 10);
+        assert_eq!(one.makespan_ms, 3_000);
+        assert_eq!(projection.p95_latency_ms, 2_100);
+        assert!(one.max_queue_depth >= 5 && one.backlog_at_last_arrival >= 5);
+        assert!((one.worker_groups[0].utilization - 1.0).abs() < 1e-6);
+        
+        let four = planner.simulate(&[PlannedWorkers::new("pdf", vec![pdf()], 1), PlannedWorkers::new("pdf", vec![pdf()], 3)]);
+        assert_eq!(four.max_queue_depth, 0);
+        assert_eq!(four.p95_latency_ms, 300);
+        assert_eq!(four.by_task_type[&pdf().to_string()].mean_wait_ms, 0);
+        assert_eq!(four.worker_groups.iter().map(|group| group.tasks).sum::<usize>(), 10);
+        assert!(four.worker_groups.iter().all(|group| group.utilization > 0.0 && group.utilization <= 1.0));
+    }
+    
+    #[test]
+    fn test_unsupported_task_types_are_unservable() {
+        let (arrivals, estimator) = create_test_history();
+        let projection = CapacityPlanner::new(&arrivals, &estimator).simulate(&[PlannedWorkers::new("text", vec![text()], 2)]);
+        
+        assert_eq!(projection.by_task_type[&pdf().to_string()].unservable, 10);
+        assert_eq!(projection.worker_groups[0].tasks, 0);
+        assert_eq!(projection.makespan_ms, 0);
+        
+        let empty = CapacityPlanner::new(&ArrivalStatistics::default(), &estimator).simulate(&[PlannedWorkers::new("text", vec![text()], 2)]);
+        assert_eq!(empty.tasks, 0);
+        assert_eq!(empty.worker_groups.len(), 1);
+    }
+}
@@ -0,0 +1,211 @@
+//! Build Compatibility
+//!
+//! Components built from different swarm crate versions can disagree on the
+//! shape of the messages they exchange without either side failing outright.
+//! Workers report the crate versions and published schema hashes they were
+//! built with in their registration metadata under [`BUILD_INFO_KEY`]; the
+//! coordinator compares them with its own build and lists every mismatch in
+//! a `CompatibilityReport`.
+
+use crate::schema::{SchemaKind, SCHEMA_VERSION};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Worker metadata key holding the worker's `BuildInfo`
+pub const BUILD_INFO_KEY: &str = "build_info";
+
+/// Crate versions and message schemas a component was built with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// Versions of the swarm crates, keyed by crate name
+    pub crate_versions: BTreeMap<String, String>,
+    
+    pub schema_version: String,
+    
+    /// Hashes of the published schemas, keyed by schema file name
+    pub schema_hashes: BTreeMap<String, String>,
+}
+
+impl BuildInfo {
+    /// Build of this swarm-core
+    pub fn current() -> Self {
+        Self {
+            crate_versions: BTreeMap::from([("swarm-core".to_string(), env!("CARGO_PKG_VERSION").to_string())]),
+            schema_version: SCHEMA_VERSION.to_string(),
+            schema_hashes: SchemaKind::ALL.iter()
+                .map(|kind| (kind.file_name().to_string(), kind.fingerprint()))
+                .collect(),
+        }
+    }
+    
+    /// Add the version of another swarm crate
+    pub fn with_crate(mut self, name: impl Into<String>, version: impl Into<String>) -> Self {
+        self.crate_versions.insert(name.into(), version.into());
+        self
+    }
+    
+    /// Build info reported in metadata, if any
+    pub fn from_metadata(metadata: &HashMap<String, serde_json::Value>) -> Option<Self> {
+        serde_json::from_value(metadata.get(BUILD_INFO_KEY)?.clone()).ok()
+    }
+    
+    /// Report this build in metadata
+    pub fn insert_into(&self, metadata: &mut HashMap<String, serde_json::Value>) {
+        if let Ok(value) = serde_json::to_value(self) {
+            metadata.insert(BUILD_INFO_KEY.to_string(), value);
+        }
+    }
+    
+    /// Differences of `other` from this build
+    ///
+    /// Crates only one side reports are not compared.
+    pub fn compare(&self, other: &BuildInfo) -> Vec<CompatibilityIssue> {
+        let mut issues = Vec::new();
+        if self.schema_version != other.schema_version {
+            issues.push(CompatibilityIssue::incompatible(format!(
+                "schema version {} (expected {})", other.schema_version, self.schema_version)));
+        }
+        for (schema, hash) in &self.schema_hashes {
+            match other.schema_hashes.get(schema) {
+                Some(other_hash) if other_hash != hash => issues.push(CompatibilityIssue::incompatible(format!(
+                    "{} differs (hash {}, expected {})", schema, other_hash, hash))),
+                None => issues.push(CompatibilityIssue::warning(format!("{} not reported", schema))),
+                _ => {}
+            }
+        }
+        for (name, version) in &self.crate_versions {
+            let Some(other_version) = other.crate_versions.get(name) else { continue };
+            if version == other_version {
+                continue;
+            }
+            let message = format!("{} {} (expected {})", name, other_version, version);
+            if semver_compatible(version, other_version) {
+                issues.push(CompatibilityIssue::warning(message));
+            } else {
+                issues.push(CompatibilityIssue::incompatible(message));
+            }
+        }
+        issues
+    }
+}
+
+/// Whether two versions are semver compatible (same major, or same minor below 1.0)
+fn semver_compatible(a: &str, b: &str) -> bool {
+    let parts = |version: &str| -> Vec<u64> { version.split('.').take(2).map(|part| part.parse().unwrap_or(0)).collect() };
+    let (a, b) = (parts(a), parts(b));
+    match (a.first(), b.first()) {
+        (Some(0), Some(0)) => a.get(1) == b.get(1),
+        (major_a, major_b) => major_a == major_b,
+    }
+}
+
+/// How serious a build difference is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum CompatibilitySeverity {
+    /// Expected to interoperate, e.g. a different patch version
+    Warning,
+    /// Messages may not deserialize the same way on both sides
+    Incompatible,
+}
+
+/// One difference between two builds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompatibilityIssue {
+    pub severity: CompatibilitySeverity,
+    pub message: String,
+}
+
+impl CompatibilityIssue {
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self { severity: CompatibilitySeverity::Warning, message: message.into() }
+    }
+    
+    pub fn incompatible(message: impl Into<String>) -> Self {
+        Self { severity: CompatibilitySeverity::Incompatible, message: message.into() }
+    }
+}
+
+/// Compatibility of one component with the reference build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentCompatibility {
+    pub component: String,
+    
+    /// Build the component reported, if it reported one
+    pub build: Option<BuildInfo>,
+    
+    pub issues: Vec<CompatibilityIssue>,
+}
+
+impl ComponentCompatibility {
+    pub fn is_compatible(&self) -> bool {
+        self.issues.iter().all(|issue| issue.severity != CompatibilitySeverity::Incompatible)
+    }
+}
+
+/// Builds of a deployment's components compared with a reference build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompatibilityReport {
+    pub reference: BuildInfo,
+    pub components: Vec<ComponentCompatibility>,
+}
+
+impl CompatibilityReport {
+    pub fn new(reference: BuildInfo) -> Self {
+        Self { reference, components: Vec::new() }
+    }
+    
+    /// Compare a component's reported build with the reference
+    pub fn check(&mut self, component: impl Into<String>, build: Option<BuildInfo>) -> &ComponentCompatibility {
+        let issues = match &build {
+            Some(build) => self.reference.compare(build),
+            None => vec![CompatibilityIssue::warning("no build info reported")],
+        };
+        self.components.push(ComponentCompatibility { component: component.into(), build, issues });
+        self.components.last().expect("component was just added")
+    }
+    
+    /// Whether no component is incompatible with the reference
+    pub fn is_compatible(&self) -> bool {
+        self.components.iter().all(ComponentCompatibility::is_compatible)
+    }
+    
+    /// Components with at least one incompatibility
+    pub fn incompatible(&self) -> impl Iterator<Item = &ComponentCompatibility> {
+        self.components.iter().filter(|component| !component.is_compatible())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_build_info_roundtrips_through_metadata() {
+        let build = BuildInfo::current().with_crate("swarm-worker", "0.1.0");
+        let mut metadata = HashMap::new();
+        build.insert_into(&mut metadata);
+        assert_eq!(BuildInfo::from_metadata(&metadata), Some(build.clone()));
+        assert!(BuildInfo::current().compare(&build).is_empty());
+        assert_eq!(build.schema_hashes.len(), SchemaKind::ALL.len());
+    }
+    
+    #[test]
+    fn test_report_flags_schema_drift_and_version_mismatch() {
+        let reference = BuildInfo::current().with_crate("swarm-core", "0.4.2");
+        let mut report = CompatibilityReport::new(reference.clone());
+        
+        let patched = reference.clone().with_crate("swarm-core", "0.4.7");
+        assert!(report.check("patched", Some(patched)).is_compatible());
+        
+        let mut drifted = reference.clone().with_crate("swarm-core", "0.5.0");
+        drifted.schema_hashes.insert("task.schema.json".to_string(), "0000".to_string());
+        let drifted = report.check("drifted", Some(drifted)).clone();
+        assert_eq!(drifted.issues.len(), 2);
+        assert!(drifted.issues.iter().all(|issue| issue.severity == CompatibilitySeverity::Incompatible));
+        
+        report.check("legacy", None);
+        assert!(!report.is_compatible());
+        let incompatible: Vec<&str> = report.incompatible().map(|component| component.component.as_str()).collect();
+        assert_eq!(incompatible, vec!["drifted"]);
+    }
+}
@@ -0,0 +1,179 @@
+//! Store Compression
+//!
+//! Persisted results, event logs, snapshots and registry records are mostly
+//! JSON and compress well. Values are written as zstd frames when
+//! compression is enabled; reading is transparent, since zstd frames are
+//! recognized by their magic number and anything else is returned as is, so
+//! stores written before compression was enabled stay readable. Every
+//! compressor counts the bytes it was given and the bytes it stored.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// First bytes of every zstd frame
+pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Compression settings of a store
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompressionConfig {
+    /// Write new values compressed; existing values are read either way
+    pub enabled: bool,
+    
+    /// zstd level, 1 (fastest) to 22 (smallest)
+    pub level: i32,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level: 3,
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// Compression enabled at `level`
+    pub fn zstd(level: i32) -> Self {
+        Self { enabled: true, level }
+    }
+}
+
+/// Bytes given to and stored by a compressor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompressionStats {
+    pub values_written: u64,
+    pub uncompressed_bytes: u64,
+    pub stored_bytes: u64,
+}
+
+impl CompressionStats {
+    /// Uncompressed size divided by stored size (1.0 before anything was written)
+    pub fn ratio(&self) -> f64 {
+        if self.stored_bytes == 0 {
+            1.0
+        } else {
+            self.uncompressed_bytes as f64 / self.stored_bytes as f64
+        }
+    }
+    
+    /// Record one written value
+    pub fn record(&mut self, uncompressed_bytes: usize, stored_bytes: usize) {
+        self.values_written += 1;
+        self.uncompressed_bytes += uncompressed_bytes as u64;
+        self.stored_bytes += stored_bytes as u64;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    values_written: AtomicU64,
+    uncompressed_bytes: AtomicU64,
+    stored_bytes: AtomicU64,
+}
+
+/// Compresses values on write and decompresses them on read
+///
+/// Clones share statistics.
+#[derive(Debug, Clone, Default)]
+pub struct Compressor {
+    config: CompressionConfig,
+    counters: Arc<Counters>,
+}
+
+impl Compressor {
+    pub fn new(config: CompressionConfig) -> Self {
+        Self {
+            config,
+            counters: Arc::default(),
+        }
+    }
+    
+    pub fn config(&self) -> &CompressionConfig {
+        &self.config
+    }
+    
+    /// Bytes to store for `value`
+    pub fn compress<'a>(&self, value: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        let stored = if self.config.enabled {
+            Cow::Owned(zstd::bulk::compress(value, self.config.level)?)
+        } else {
+            Cow::Borrowed(value)
+        };
+        self.counters.values_written.fetch_add(1, Ordering::Relaxed);
+        self.counters.uncompressed_bytes.fetch_add(value.len() as u64, Ordering::Relaxed);
+        self.counters.stored_bytes.fetch_add(stored.len() as u64, Ordering::Relaxed);
+        Ok(stored)
+    }
+    
+    /// Original bytes of a stored value, compressed or not
+    pub fn decompress<'a>(&self, stored: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        decompress(stored)
+    }
+    
+    /// Serialize `value` as JSON and compress it
+    pub fn to_vec<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(self.compress(&serde_json::to_vec(value)?)?.into_owned())
+    }
+    
+    /// Decompress and deserialize a value written by `to_vec` (or plain JSON)
+    pub fn from_slice<T: serde::de::DeserializeOwned>(&self, stored: &[u8]) -> Result<T> {
+        Ok(serde_json::from_slice(&self.decompress(stored)?)?)
+    }
+    
+    /// Bytes written so far
+    pub fn stats(&self) -> CompressionStats {
+        CompressionStats {
+            values_written: self.counters.values_written.load(Ordering::Relaxed),
+            uncompressed_bytes: self.counters.uncompressed_bytes.load(Ordering::Relaxed),
+            stored_bytes: self.counters.stored_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Whether stored bytes are a zstd frame
+pub fn is_compressed(stored: &[u8]) -> bool {
+    stored.starts_with(&ZSTD_MAGIC)
+}
+
+/// Original bytes of a stored value, compressed or not
+pub fn decompress(stored: &[u8]) -> Result<Cow<'_, [u8]>> {
+    if !is_compressed(stored) {
+        return Ok(Cow::Borrowed(stored));
+    }
+    Ok(Cow::Owned(zstd::stream::decode_all(stored)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_values_round_trip_and_are_counted() {
+        let value = serde_json::json!({ "text": "extracted text ".repeat(200) });
+        let compressor = Compressor::new(CompressionConfig::zstd(3));
+        let stored = compressor.to_vec(&value).unwrap();
+        assert!(is_compressed(&stored));
+        assert_eq!(compressor.from_slice::<serde_json::Value>(&stored).unwrap(), value);
+        
+        let stats = compressor.clone().stats();
+        assert_eq!(stats.values_written, 1);
+        assert_eq!(stats.stored_bytes, stored.len() as u64);
+        assert!(stats.ratio() > 10.0);
+    }
+    
+    #[test]
+    fn test_uncompressed_values_stay_readable() {
+        let plain = Compressor::default();
+        let stored = plain.to_vec(&vec![1, 2, 3]).unwrap();
+        assert_eq!(stored, b"[1,2,3]");
+        assert_eq!(plain.stats().ratio(), 1.0);
+        
+        let compressing = Compressor::new(CompressionConfig::zstd(19));
+        assert_eq!(compressing.from_slice::<Vec<i32>>(&stored).unwrap(), vec![1, 2, 3]);
+        assert_eq!(CompressionStats::default().ratio(), 1.0);
+    }
+}
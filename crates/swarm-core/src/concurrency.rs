@@ -0,0 +1,256 @@
+//! Adaptive Concurrency
+//!
+//! AIMD concurrency limit driven by a latency objective. Completed work
+//! reports its latency; after every window of samples the controller compares
+//! the window's p95 with the target and either raises the limit by a step
+//! (additive increase) or multiplies it by a factor below one (multiplicative
+//! decrease). Work acquires a permit before it starts, so a lowered limit takes
+//! effect as in-flight work completes.
+
+use crate::fairness::percentile;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Adaptive concurrency configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveConcurrencyConfig {
+    /// p95 latency to stay under, in milliseconds
+    pub target_p95_ms: u64,
+    
+    /// Lowest limit the controller backs off to
+    pub min_limit: usize,
+    
+    /// Highest limit the controller grows to
+    pub max_limit: usize,
+    
+    /// Completed samples per adjustment
+    pub window_size: usize,
+    
+    /// Limit increase while under target
+    pub increase_step: usize,
+    
+    /// Limit multiplier while over target, between 0.0 and 1.0
+    pub decrease_factor: f64,
+}
+
+impl Default for AdaptiveConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            target_p95_ms: 2_000,
+            min_limit: 1,
+            max_limit: 64,
+            window_size: 20,
+            increase_step: 1,
+            decrease_factor: 0.75,
+        }
+    }
+}
+
+/// Snapshot of the controller state
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConcurrencyStats {
+    /// Current concurrency limit
+    pub limit: usize,
+    
+    /// Work currently holding a permit
+    pub in_flight: usize,
+    
+    /// p95 latency of the last completed window
+    pub last_p95_ms: Option<u64>,
+    
+    /// Number of limit increases and decreases
+    pub increases: u64,
+    pub decreases: u64,
+}
+
+#[derive(Debug)]
+struct ControllerState {
+    stats: ConcurrencyStats,
+    window: VecDeque<u64>,
+}
+
+/// Latency-driven concurrency limiter
+#[derive(Debug)]
+pub struct AdaptiveConcurrencyController {
+    config: AdaptiveConcurrencyConfig,
+    state: Mutex<ControllerState>,
+    released: Notify,
+}
+
+impl AdaptiveConcurrencyController {
+    /// Create a controller starting at `initial_limit` (clamped to the configured bounds)
+    pub fn new(config: AdaptiveConcurrencyConfig, initial_limit: usize) -> Self {
+        let min_limit = config.min_limit.max(1);
+        let limit = initial_limit.clamp(min_limit, config.max_limit.max(min_limit));
+        Self {
+            state: Mutex::new(ControllerState {
+                stats: ConcurrencyStats { limit, ..Default::default() },
+                window: VecDeque::with_capacity(config.window_size),
+            }),
+            config,
+            released: Notify::new(),
+        }
+    }
+    
+    /// Current concurrency limit
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap().stats.limit
+    }
+    
+    pub fn stats(&self) -> ConcurrencyStats {
+        self.state.lock().unwrap().stats.clone()
+    }
+    
+    /// Wait until work may start under the current limit
+    pub async fn acquire(self: &Arc<Self>) -> ConcurrencyPermit {
+        loop {
+            // Register for wakeups before checking, so a release in between is not missed
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+            
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.stats.in_flight < state.stats.limit {
+                    state.stats.in_flight += 1;
+                    return ConcurrencyPermit { controller: self.clone(), started_at: Instant::now() };
+                }
+            }
+            released.await;
+        }
+    }
+    
+    /// Record the latency of one completed piece of work
+    pub fn record(&self, latency: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.window.push_back(latency.as_millis() as u64);
+        if state.window.len() < self.config.window_size.max(1) {
+            return;
+        }
+        
+        let p95 = percentile(&state.window, 0.95);
+        state.window.clear();
+        state.stats.last_p95_ms = p95;
+        
+        let min_limit = self.config.min_limit.max(1);
+        let max_limit = self.config.max_limit.max(min_limit);
+        let limit = state.stats.limit;
+        if p95.is_some_and(|p95| p95 > self.config.target_p95_ms) {
+            let decreased = ((limit as f64 * self.config.decrease_factor) as usize).max(min_limit);
+            if decreased < limit {
+                tracing::debug!("p95 latency {:?}ms over target, lowering concurrency limit to {}", p95, decreased);
+                state.stats.limit = decreased;
+                state.stats.decreases += 1;
+            }
+        } else {
+            let increased = (limit + self.config.increase_step).min(max_limit);
+            if increased > limit {
+                state.stats.limit = increased;
+                state.stats.increases += 1;
+                drop(state);
+                self.released.notify_waiters();
+            }
+        }
+    }
+    
+    fn release(&self) {
+        self.state.lock().unwrap().stats.in_flight -= 1;
+        self.released.notify_one();
+    }
+}
+
+/// Permission to run one piece of work; released when dropped
+#[derive(Debug)]
+pub struct ConcurrencyPermit {
+    controller: Arc<AdaptiveConcurrencyController>,
+    started_at: Instant,
+}
+
+impl ConcurrencyPermit {
+    /// Record the latency since the permit was acquired and release it
+    pub fn complete(self) {
+        self.controller.record(self.started_at.elapsed());
+    }
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        self.controller.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn create_test_controller(initial_limit: usize) -> Arc<AdaptiveConcurrencyController> {
+        Arc::new(AdaptiveConcurrencyController::new(AdaptiveConcurrencyConfig {
+            target_p95_ms: 100,
+            min_limit: 2,
+            max_limit: 8,
+            window_size: 10,
+            increase_step: 1,
+            decrease_factor: 0.5,
+        }, initial_limit))
+    }
+    
+    fn record_window(controller: &AdaptiveConcurrencyController, latency_ms: u64) {
+        for _ in 0..10 {
+            controller.record(Duration::from_millis(latency_ms));
+        }
+    }
+    
+    #[test]
+    fn test_aimd_adjusts_limit_within_bounds() {
+        let controller = create_test_controller(4);
+        record_window(&controller, 50);
+        assert_eq!(controller.limit(), 5);
+        for _ in 0..10 {
+            record_window(&controller, 50);
+        }
+        assert_eq!(controller.limit(), 8);
+        
+        record_window(&controller, 500);
+        assert_eq!(controller.limit(), 4);
+        record_window(&controller, 500);
+        record_window(&controller, 500);
+        assert_eq!(controller.limit(), 2);
+        
+        let stats = controller.stats();
+        assert_eq!((stats.increases, stats.decreases), (4, 2));
+        assert_eq!(stats.last_p95_ms, Some(500));
+        assert_eq!(create_test_controller(100).limit(), 8);
+    }
+    
+    #[tokio::test]
+    async fn test_permits_respect_limit() {
+        let controller = create_test_controller(2);
+        let first = controller.acquire().await;
+        let _second = controller.acquire().await;
+        assert_eq!(controller.stats().in_flight, 2);
+        
+        let waiting = tokio::spawn({
+            let controller = controller.clone();
+            async move { controller.acquire().await.complete() }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        
+        first.complete();
+        tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap().unwrap();
+        assert_eq!(controller.stats().in_flight, 1);
+        
+        // Raising the limit wakes waiters without a release
+        let _third = controller.acquire().await;
+        let waiting = tokio::spawn({
+            let controller = controller.clone();
+            async move { drop(controller.acquire().await) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        record_window(&controller, 10);
+        tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap().unwrap();
+    }
+}
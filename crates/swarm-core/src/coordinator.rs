@@ -1,51 +1,381 @@
 //! Swarm coordination and management
+//!
+//! `SwarmCoordinator` queues tasks, assigns them to registered workers and
+//! records their results. Its state is event sourced: every change is
+//! appended to a `CoordinatorEventLog` and folded into a `CoordinatorState`
+//! projection. Optional behaviour is switched on through its `with_*`
+//! builders.
 
-use crate::{Task, TaskResult, WorkerConfig, SwarmResult};
+use crate::{Task, TaskResult, TaskStatus, WorkerConfig, SwarmError, SwarmResult, CoordinatorStats, QueueSaturation};
+use crate::backpressure::BackpressureConfig;
+use crate::coordinator_events::{CoordinatorEvent, CoordinatorEventLog, CoordinatorSnapshot, CoordinatorState, EventRecord, Projection};
+use crate::fairness::{StarvationConfig, StarvationDetector};
+use crate::liveness::{Heartbeat, LivenessConfig, LivenessTracker};
+use crate::bulk::{BulkAction, BulkMatch, BulkOperation, BulkProgress, BulkReport, TaskFilter};
+use crate::capacity::{ArrivalStatistics, CapacityConfig, CapacityPlanner, CapacityProjection, PlannedWorkers, ProcessingTimeEstimator};
+use crate::retry::{DeadLetter, FailedAttempt, RetryPolicy};
+use crate::worker_registry::WorkerRegistry;
+use crate::task_schedules::{CronScheduler, ScheduleStore, TaskSchedule};
+use crate::compatibility::{BuildInfo, CompatibilityReport, CompatibilitySeverity};
+use crate::document_groups::{GroupMembership, GroupProgress, GroupResult, GroupStatus, GroupTracker, TaskGroup, GROUP_PARENT_KEY};
+use crate::idempotency::{ResultCache, ResultCacheStats};
+use crate::workflow::{StageStatus, Workflow, WorkflowResult, WorkflowStatus, WorkflowTracker};
+use crate::registration::{RegistrationRequest, WorkerRegistration};
+use crate::priority_rules::PriorityRules;
+use crate::scheduling::{FailureDomain, SchedulingWeights, SpreadLevel, SPREAD_GROUP_KEY};
+use crate::service::{CancellationToken, Service};
+use crate::sli::{Sli, SliRecorder};
+use crate::trace_context::TraceContext;
+use crate::traits::MetricsCollector;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
 use std::collections::HashMap;
+use std::sync::Arc;
 use uuid::Uuid;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{Duration, MissedTickBehavior};
 use tracing::{info, warn, error, debug};
 
+/// Assigns queued tasks to workers and records their results
+///
+/// Each task goes to the available worker whose capabilities, preferred
+/// type, performance profile and load score best for it. `run` distributes
+/// tasks until its cancellation token fires.
 pub struct SwarmCoordinator {
     workers: HashMap<Uuid, WorkerHandle>,
-    task_queue: Vec<Task>,
-    result_receiver: Option<mpsc::UnboundedReceiver<TaskResult>>,
-    result_sender: mpsc::UnboundedSender<TaskResult>,
+    state: CoordinatorState,
+    events: CoordinatorEventLog,
+    event_subscribers: Vec<mpsc::UnboundedSender<EventRecord>>,
+    result_receiver: Option<mpsc::Receiver<TaskResult>>,
+    result_sender: mpsc::Sender<TaskResult>,
+    backpressure: BackpressureConfig,
+    rejected_submissions: u64,
+    starvation: StarvationDetector,
+    liveness: LivenessTracker,
+    heartbeat_receiver: mpsc::UnboundedReceiver<Heartbeat>,
+    heartbeat_sender: mpsc::UnboundedSender<Heartbeat>,
+    retry_policy: RetryPolicy,
+    dead_letter_subscribers: Vec<mpsc::UnboundedSender<DeadLetter>>,
+    worker_registry: Option<Arc<WorkerRegistry>>,
+    schedules: CronScheduler,
+    schedule_store: Option<Arc<ScheduleStore>>,
+    processing_times: ProcessingTimeEstimator,
+    arrivals: ArrivalStatistics,
+    priority_rules: PriorityRules,
+    scheduling: SchedulingWeights,
+    spread: Option<SpreadLevel>,
+    sli: SliRecorder,
+    groups: GroupTracker,
+    group_subscribers: Vec<mpsc::UnboundedSender<GroupResult>>,
+    group_joiners: HashMap<String, Vec<oneshot::Sender<GroupResult>>>,
+    workflows: WorkflowTracker,
+    workflow_subscribers: Vec<mpsc::UnboundedSender<WorkflowResult>>,
+    results: Option<ResultCache>,
+    registration_receiver: Option<mpsc::UnboundedReceiver<RegistrationRequest>>,
 }
 
 struct WorkerHandle {
-    task_sender: mpsc::UnboundedSender<Task>,
+    task_sender: mpsc::Sender<Task>,
     worker_id: Uuid,
-    config: WorkerConfig,
 }
 
 impl SwarmCoordinator {
     pub fn new() -> Self {
-        let (result_sender, result_receiver) = mpsc::unbounded_channel();
+        let backpressure = BackpressureConfig::default();
+        let (result_sender, result_receiver) = mpsc::channel(backpressure.result_queue_capacity);
+        let (heartbeat_sender, heartbeat_receiver) = mpsc::unbounded_channel();
         Self {
             workers: HashMap::new(),
-            task_queue: Vec::new(),
+            state: CoordinatorState::default(),
+            events: CoordinatorEventLog::new(),
+            event_subscribers: Vec::new(),
             result_receiver: Some(result_receiver),
             result_sender,
+            backpressure,
+            rejected_submissions: 0,
+            starvation: StarvationDetector::default(),
+            liveness: LivenessTracker::default(),
+            heartbeat_receiver,
+            heartbeat_sender,
+            retry_policy: RetryPolicy::default(),
+            dead_letter_subscribers: Vec::new(),
+            worker_registry: None,
+            schedules: CronScheduler::new(),
+            schedule_store: None,
+            processing_times: ProcessingTimeEstimator::new(&CapacityConfig::default()),
+            arrivals: ArrivalStatistics::new(&CapacityConfig::default()),
+            priority_rules: PriorityRules::default(),
+            scheduling: SchedulingWeights::default(),
+            spread: None,
+            sli: SliRecorder::default(),
+            groups: GroupTracker::new(),
+            group_subscribers: Vec::new(),
+            group_joiners: HashMap::new(),
+            workflows: WorkflowTracker::new(),
+            workflow_subscribers: Vec::new(),
+            results: None,
+            registration_receiver: None,
         }
     }
-
-    pub fn register_worker(&mut self, worker_id: Uuid, config: WorkerConfig) -> mpsc::UnboundedReceiver<Task> {
-        let (task_sender, task_receiver) = mpsc::unbounded_channel();
+    
+    /// Restore a coordinator from a snapshot and the events recorded after it
+    ///
+    /// Worker channels do not survive a restart: workers known to the restored
+    /// state are unregistered and their in-flight tasks requeued, in assignment
+    /// order, so recovery always produces the same queue.
+    pub fn restore(snapshot: &CoordinatorSnapshot, records: &[EventRecord]) -> Self {
+        let state = snapshot.restore(records);
+        let mut coordinator = Self::new();
+        coordinator.events = CoordinatorEventLog::starting_after(state.last_sequence);
+        
+        let requeued: Vec<Uuid> = state.assignments().iter().map(|assignment| assignment.task.id).collect();
+        let mut workers: Vec<Uuid> = state.workers.keys().copied().collect();
+        workers.sort();
+        coordinator.state = state;
+        
+        for task_id in requeued {
+            coordinator.record(CoordinatorEvent::TaskRequeued { task_id, reason: "coordinator restarted".to_string() });
+        }
+        for worker_id in workers {
+            coordinator.record(CoordinatorEvent::WorkerUnregistered { worker_id });
+        }
+        
+        info!("Restored coordinator at event {} with {} pending tasks",
+            coordinator.state.last_sequence, coordinator.state.pending.len());
+        coordinator
+    }
+    
+    /// Use custom channel capacities and a cap on queued tasks
+    ///
+    /// Workers whose task channel is full are passed over, and
+    /// `try_submit_task` refuses tasks once `max_pending_tasks` are queued.
+    /// Replaces the result channel, so senders taken before are disconnected.
+    pub fn with_backpressure(mut self, config: BackpressureConfig) -> Self {
+        let (result_sender, result_receiver) = mpsc::channel(config.result_queue_capacity.max(1));
+        self.result_sender = result_sender;
+        self.result_receiver = Some(result_receiver);
+        self.backpressure = config;
+        self
+    }
+    
+    /// Use custom queue wait thresholds for starvation warnings
+    pub fn with_starvation_config(mut self, config: StarvationConfig) -> Self {
+        self.starvation = StarvationDetector::new(config);
+        self
+    }
+    
+    /// Use a custom heartbeat interval and missed-heartbeat limit
+    pub fn with_liveness_config(mut self, config: LivenessConfig) -> Self {
+        self.liveness = LivenessTracker::new(config);
+        self
+    }
+    
+    /// Use a custom backoff between retries of failed tasks
+    ///
+    /// A task still failing after its last retry goes to dead-letter subscribers.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+    
+    /// Keep a persistent history of worker membership in `registry`
+    pub fn with_worker_registry(mut self, registry: Arc<WorkerRegistry>) -> Self {
+        self.worker_registry = Some(registry);
+        self
+    }
+    
+    /// Answer repeated tasks from the last `capacity` completed results
+    ///
+    /// Tasks whose idempotency key already completed are answered from the
+    /// cache, and repeated results of a finished task are ignored.
+    pub fn with_result_cache(mut self, capacity: usize) -> Self {
+        self.results = Some(ResultCache::new(capacity));
+        self
+    }
+    
+    /// Keep task schedules in `store`, resuming the ones stored before
+    ///
+    /// Stored schedules keep their next run, so runs missed while the
+    /// coordinator was down are caught up once.
+    pub fn with_schedule_store(mut self, store: Arc<ScheduleStore>) -> Self {
+        match store.schedules() {
+            Ok(schedules) => {
+                for schedule in schedules {
+                    let id = schedule.id.clone();
+                    if let Err(e) = self.schedules.insert(schedule, Utc::now()) {
+                        warn!("Skipping stored schedule {}: {}", id, e);
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to load task schedules: {}", e),
+        }
+        self.schedule_store = Some(store);
+        self
+    }
+    
+    /// Use a custom arrival window and processing time smoothing for capacity planning
+    pub fn with_capacity_config(mut self, config: CapacityConfig) -> Self {
+        self.processing_times = ProcessingTimeEstimator::new(&config);
+        self.arrivals = ArrivalStatistics::new(&config);
+        self
+    }
+    
+    /// Escalate the priority of submitted document tasks whose content matches a rule
+    pub fn with_priority_rules(mut self, rules: PriorityRules) -> Self {
+        self.priority_rules = rules;
+        self
+    }
+    
+    /// Weigh capability match, preference, performance and load differently when assigning tasks
+    pub fn with_scheduling_weights(mut self, weights: SchedulingWeights) -> Self {
+        self.scheduling = weights;
+        self
+    }
+    
+    /// Keep tasks of a `spread_group` on distinct hosts or zones
+    pub fn with_failure_domain_spread(mut self, level: SpreadLevel) -> Self {
+        self.spread = Some(level);
+        self
+    }
+    
+    /// Record queue wait and end-to-end latency SLIs to `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<dyn MetricsCollector>) -> Self {
+        self.sli = SliRecorder::new(metrics);
+        self
+    }
+    
+    /// Receive every task that fails permanently from now on
+    pub fn subscribe_dead_letters(&mut self) -> mpsc::UnboundedReceiver<DeadLetter> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.dead_letter_subscribers.push(sender);
+        receiver
+    }
+    
+    /// Let workers register and deregister through the returned sender while running
+    ///
+    /// `run` keeps going while the sender, or a clone of it, is alive.
+    pub fn accept_registrations(&mut self) -> mpsc::UnboundedSender<RegistrationRequest> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.registration_receiver = Some(receiver);
+        sender
+    }
+    
+    /// Receive the result of every document group that finishes from now on
+    pub fn subscribe_group_results(&mut self) -> mpsc::UnboundedReceiver<GroupResult> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.group_subscribers.push(sender);
+        receiver
+    }
+    
+    /// Receive the result of every workflow that finishes from now on
+    pub fn subscribe_workflow_results(&mut self) -> mpsc::UnboundedReceiver<WorkflowResult> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.workflow_subscribers.push(sender);
+        receiver
+    }
+    
+    /// Receive every event recorded from now on (audit logs, dashboards, replication)
+    pub fn subscribe_events(&mut self) -> mpsc::UnboundedReceiver<EventRecord> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.event_subscribers.push(sender);
+        receiver
+    }
+    
+    /// Append an event to the log and apply it to the state projection
+    fn record(&mut self, event: CoordinatorEvent) {
+        let record = self.events.append(event).clone();
+        self.state.apply(&record);
+        self.processing_times.apply(&record);
+        self.arrivals.apply(&record);
+        if let Some(registry) = &self.worker_registry {
+            if let Err(e) = registry.apply(&record) {
+                warn!("Failed to update worker registry: {}", e);
+            }
+        }
+        self.event_subscribers.retain(|subscriber| subscriber.send(record.clone()).is_ok());
+        
+        self.groups.apply(&record);
+        for result in self.groups.take_finished() {
+            self.finish_group(result);
+        }
+        
+        self.workflows.apply(&record);
+        for task in self.workflows.take_ready() {
+            self.submit_task(task);
+        }
+        for result in self.workflows.take_finished() {
+            self.finish_workflow(result);
+        }
+    }
+    
+    /// Drop the queued stages of a failed workflow and hand the result to subscribers
+    fn finish_workflow(&mut self, result: WorkflowResult) {
+        match result.status {
+            WorkflowStatus::Completed => info!("Workflow {} completed", result.workflow_id),
+            WorkflowStatus::Failed => warn!("Workflow {} failed", result.workflow_id),
+        }
+        for task_id in result.cancelled_tasks() {
+            let queued = self.state.pending.iter().any(|task| task.id == task_id) || self.state.retrying.contains_key(&task_id);
+            if queued {
+                self.starvation.forget(task_id);
+                self.record(CoordinatorEvent::TaskDropped { task_id, reason: format!("workflow {} failed", result.workflow_id) });
+            }
+        }
+        self.workflow_subscribers.retain(|subscriber| subscriber.send(result.clone()).is_ok());
+    }
+    
+    /// Drop the queued members of a failed group and hand the result to subscribers
+    fn finish_group(&mut self, result: GroupResult) {
+        match result.status {
+            GroupStatus::Completed => info!("Document group {} completed", result.group_id),
+            GroupStatus::Failed => warn!("Document group {} failed", result.group_id),
+        }
+        for task_id in result.cancelled_members() {
+            let queued = self.state.pending.iter().any(|task| task.id == task_id) || self.state.retrying.contains_key(&task_id);
+            if queued {
+                self.starvation.forget(task_id);
+                self.record(CoordinatorEvent::TaskDropped { task_id, reason: format!("document group {} failed", result.group_id) });
+            }
+        }
+        for joiner in self.group_joiners.remove(&result.group_id).unwrap_or_default() {
+            let _ = joiner.send(result.clone());
+        }
+        self.group_subscribers.retain(|subscriber| subscriber.send(result.clone()).is_ok());
+    }
+    
+    /// Register a worker, returning the channel its tasks are sent on
+    ///
+    /// Workers built against a different crate version or message schema are flagged.
+    pub fn register_worker(&mut self, worker_id: Uuid, config: WorkerConfig) -> mpsc::Receiver<Task> {
+        let (task_sender, task_receiver) = mpsc::channel(self.backpressure.task_queue_capacity.max(1));
         
         let handle = WorkerHandle {
             task_sender,
             worker_id,
-            config,
         };
         
+        let mut compatibility = CompatibilityReport::new(BuildInfo::current());
+        for issue in &compatibility.check(&config.name, BuildInfo::from_metadata(&config.metadata)).issues {
+            match issue.severity {
+                CompatibilitySeverity::Incompatible => warn!("Worker {} is incompatible: {}", worker_id, issue.message),
+                CompatibilitySeverity::Warning => debug!("Worker {} build differs: {}", worker_id, issue.message),
+            }
+        }
+        
         self.workers.insert(worker_id, handle);
+        self.liveness.track(worker_id, Utc::now());
+        self.record(CoordinatorEvent::WorkerRegistered { worker_id, config });
         info!("Registered worker {}", worker_id);
         task_receiver
     }
-
+    
+    /// Unregister a worker; false if it was not registered
     pub fn unregister_worker(&mut self, worker_id: Uuid) -> bool {
         if self.workers.remove(&worker_id).is_some() {
+            self.liveness.forget(worker_id);
+            self.record(CoordinatorEvent::WorkerUnregistered { worker_id });
             info!("Unregistered worker {}", worker_id);
             true
         } else {
@@ -53,100 +383,1246 @@ impl SwarmCoordinator {
             false
         }
     }
-
-    pub fn submit_task(&mut self, task: Task) {
+    
+    /// Queue a task, escalating its priority if a content priority rule matches
+    pub fn submit_task(&mut self, mut task: Task) {
+        // Escalating before the event is recorded keeps replays independent of the rules
+        if let Some(rule) = self.priority_rules.apply(&mut task) {
+            info!("Task {} escalated to {} by priority rule {}", task.id, task.priority, rule);
+        }
+        let replayed = match &mut self.results {
+            Some(cache) if cache.has_finished(task.id) => {
+                debug!("Ignoring redelivered task {}: it finished already", task.id);
+                return;
+            }
+            Some(cache) => cache.replay(&task),
+            None => None,
+        };
         debug!("Submitted task {} of type {}", task.id, task.task_type);
-        self.task_queue.push(task);
+        self.record(CoordinatorEvent::TaskSubmitted { task });
+        if let Some(result) = replayed {
+            debug!("Task {} answered from the result cache", result.task_id);
+            self.record_result(&result);
+        }
     }
-
-    pub async fn start(&mut self) -> SwarmResult<()> {
-        info!("Starting swarm coordinator with {} workers", self.workers.len());
+    
+    /// Submit a task unless the queue holds `max_pending_tasks` already
+    pub fn try_submit_task(&mut self, task: Task) -> SwarmResult<()> {
+        let pending = self.state.pending.len();
+        if !self.backpressure.accepts(pending) {
+            self.rejected_submissions += 1;
+            let capacity = self.backpressure.max_pending_tasks.unwrap_or_default();
+            warn!("Refused task {}: queue full with {} pending tasks", task.id, pending);
+            return Err(SwarmError::QueueFull { pending, capacity });
+        }
+        self.submit_task(task);
+        Ok(())
+    }
+    
+    /// Add or replace a recurring task schedule, first running after now
+    pub fn add_schedule(&mut self, mut schedule: TaskSchedule) -> SwarmResult<()> {
+        schedule.next_run = None;
+        let schedule = self.schedules.insert(schedule, Utc::now())?.clone();
+        info!("Scheduled {} ({}), next run at {:?}", schedule.id, schedule.cron, schedule.next_run);
+        self.store_schedule(&schedule)
+    }
+    
+    /// Remove a schedule; returns whether it existed
+    pub fn remove_schedule(&mut self, id: &str) -> SwarmResult<bool> {
+        let removed = self.schedules.remove(id).is_some();
+        if let Some(store) = &self.schedule_store {
+            store.remove(id).map_err(|e| SwarmError::Schedule(format!("failed to remove schedule {}: {}", id, e)))?;
+        }
+        Ok(removed)
+    }
+    
+    /// Pause or resume a schedule; returns whether it exists
+    ///
+    /// A resumed schedule next runs at its first occurrence after now.
+    pub fn set_schedule_enabled(&mut self, id: &str, enabled: bool) -> SwarmResult<bool> {
+        let Some(schedule) = self.schedules.set_enabled(id, enabled, Utc::now()).cloned() else {
+            return Ok(false);
+        };
+        self.store_schedule(&schedule)?;
+        Ok(true)
+    }
+    
+    /// Recurring task schedules, ordered by id
+    pub fn schedules(&self) -> impl Iterator<Item = &TaskSchedule> {
+        self.schedules.schedules()
+    }
+    
+    /// Submit a task for every schedule due at `now`
+    pub fn submit_due_schedules(&mut self, now: DateTime<Utc>) -> usize {
+        let due = self.schedules.take_due(now);
+        let submitted = due.len();
+        for (task, schedule) in due {
+            info!("Schedule {} submitted task {}", schedule.id, task.id);
+            self.submit_task(task);
+            if let Err(e) = self.store_schedule(&schedule) {
+                warn!("{}", e);
+            }
+        }
+        submitted
+    }
+    
+    fn store_schedule(&self, schedule: &TaskSchedule) -> SwarmResult<()> {
+        match &self.schedule_store {
+            Some(store) => store.put(schedule)
+                .map_err(|e| SwarmError::Schedule(format!("failed to store schedule {}: {}", schedule.id, e))),
+            None => Ok(()),
+        }
+    }
+    
+    /// Submit the tasks of a document group together, linked as its members
+    ///
+    /// The group result goes to subscribers once every member completed; a
+    /// member failing for good fails the group and drops its queued members.
+    pub fn submit_group(&mut self, group_id: impl Into<String>, tasks: Vec<Task>) -> TaskGroup {
+        let membership = GroupMembership::new(group_id, tasks.len());
+        debug!("Submitting document group {} with {} tasks", membership.group_id, tasks.len());
+        self.submit_members(membership, None, tasks)
+    }
+    
+    /// Submit the child tasks a task was split into as a group named after the parent
+    ///
+    /// The group result names the parent and combines the children's result metadata.
+    pub fn fan_out(&mut self, parent_task_id: Uuid, tasks: Vec<Task>) -> TaskGroup {
+        let membership = GroupMembership::new(parent_task_id.to_string(), tasks.len());
+        debug!("Fanning task {} out into {} tasks", parent_task_id, tasks.len());
+        self.submit_members(membership, Some(parent_task_id), tasks)
+    }
+    
+    fn submit_members(&mut self, membership: GroupMembership, parent_task_id: Option<Uuid>, tasks: Vec<Task>) -> TaskGroup {
+        let group = TaskGroup {
+            group_id: membership.group_id.clone(),
+            parent_task_id,
+            task_ids: tasks.iter().map(|task| task.id).collect(),
+        };
+        for mut task in tasks {
+            membership.insert_into(&mut task.metadata);
+            if let Some(parent) = parent_task_id {
+                task.metadata.insert(GROUP_PARENT_KEY.to_string(), serde_json::json!(parent.to_string()));
+            }
+            self.submit_task(task);
+        }
+        group
+    }
+    
+    /// Wait for an open group to finish; `None` if no such group is open
+    pub fn join_group(&mut self, group_id: &str) -> Option<oneshot::Receiver<GroupResult>> {
+        self.groups.progress(group_id)?;
+        let (sender, receiver) = oneshot::channel();
+        self.group_joiners.entry(group_id.to_string()).or_default().push(sender);
+        Some(receiver)
+    }
+    
+    /// Progress of an open group
+    pub fn group_progress(&self, group_id: &str) -> Option<GroupProgress> {
+        self.groups.progress(group_id)
+    }
+    
+    /// Submit a workflow; each stage is submitted once the stages it depends on completed
+    pub fn submit_workflow(&mut self, workflow: Workflow) -> SwarmResult<()> {
+        debug!("Submitting workflow {} with {} stages", workflow.id, workflow.stages.len());
+        self.workflows.add(workflow)?;
+        for task in self.workflows.take_ready() {
+            self.submit_task(task);
+        }
+        Ok(())
+    }
+    
+    /// Stage statuses of a running workflow, in declaration order
+    pub fn workflow_progress(&self, workflow_id: &str) -> Option<Vec<(&str, StageStatus)>> {
+        self.workflows.progress(workflow_id)
+    }
+    
+    /// Compare the builds registered workers reported with this coordinator's, in worker ID order
+    pub fn compatibility_report(&self) -> CompatibilityReport {
+        let mut workers: Vec<(&Uuid, &WorkerConfig)> = self.state.workers.iter().collect();
+        workers.sort_by_key(|(worker_id, _)| **worker_id);
         
-        // Start result processing task
-        if let Some(mut result_receiver) = self.result_receiver.take() {
-            let result_processor = tokio::spawn(async move {
-                while let Some(result) = result_receiver.recv().await {
-                    match result.status {
-                        crate::TaskStatus::Completed => {
-                            info!("Task {} completed in {}ms", result.task_id, result.processing_time_ms);
-                        }
-                        crate::TaskStatus::Failed => {
-                            error!("Task {} failed", result.task_id);
-                        }
-                        _ => {}
-                    }
+        let mut report = CompatibilityReport::new(BuildInfo::current());
+        for (worker_id, config) in workers {
+            report.check(format!("{} ({})", config.name, worker_id), BuildInfo::from_metadata(&config.metadata));
+        }
+        report
+    }
+    
+    /// Record the result of a dispatched task
+    ///
+    /// A failed task with retries left is scheduled for another attempt;
+    /// one without is dead-lettered.
+    pub fn record_result(&mut self, result: &TaskResult) {
+        if let Some(cache) = &mut self.results {
+            if cache.has_finished(result.task_id) {
+                debug!("Ignoring duplicate result of task {}", result.task_id);
+                cache.record_duplicate();
+                return;
+            }
+            if let Some(assignment) = self.state.in_flight.get(&result.task_id) {
+                cache.insert(&assignment.task, result);
+            }
+        }
+        self.groups.record_result(result);
+        self.workflows.record_result(result);
+        let failed = match self.state.in_flight.get(&result.task_id) {
+            Some(assignment) if result.status == crate::TaskStatus::Failed => assignment.clone(),
+            assignment => {
+                if let Some(assignment) = assignment {
+                    self.observe_end_to_end(&assignment.task, result);
                 }
-            });
-            
-            // Start task distribution loop
-            self.distribute_tasks().await?;
-            
-            // Wait for result processor to finish
-            let _ = result_processor.await;
+                return self.record(CoordinatorEvent::task_finished(result));
+            }
+        };
+        
+        let error = result.error.clone().unwrap_or_else(|| "unknown error".to_string());
+        let task = &failed.task;
+        if task.retry_count < task.max_retries {
+            let retry_at = Utc::now() + self.retry_policy.backoff(task.retry_count + 1);
+            warn!("Task {} failed (attempt {} of {}), retrying at {}: {}",
+                task.id, task.retry_count + 1, task.max_retries + 1, retry_at, error);
+            self.record(CoordinatorEvent::TaskRetryScheduled { task_id: task.id, error, retry_at });
+            return;
         }
         
-        Ok(())
+        let mut errors = self.state.failed_attempts.get(&task.id).cloned().unwrap_or_default();
+        errors.push(FailedAttempt {
+            attempt: task.retry_count + 1,
+            worker_id: Some(failed.worker_id),
+            error,
+            failed_at: result.completed_at,
+        });
+        let dead_letter = DeadLetter {
+            task: task.clone(),
+            errors,
+            dead_lettered_at: Utc::now(),
+        };
+        
+        error!("Task {} failed permanently after {} attempts", task.id, dead_letter.errors.len());
+        self.observe_end_to_end(task, result);
+        self.record(CoordinatorEvent::task_finished(result));
+        self.record(CoordinatorEvent::TaskDeadLettered { task_id: task.id, attempts: dead_letter.errors.len() as u32 });
+        self.dead_letter_subscribers.retain(|subscriber| subscriber.send(dead_letter.clone()).is_ok());
     }
-
-    async fn distribute_tasks(&mut self) -> SwarmResult<()> {
-        info!("Starting task distribution loop");
+    
+    /// Move failed tasks whose retry backoff has passed back into the queue
+    pub fn release_due_retries(&mut self, now: DateTime<Utc>) -> usize {
+        let mut due: Vec<(DateTime<Utc>, Uuid)> = self.state.retrying.values()
+            .filter(|retry| retry.retry_at <= now)
+            .map(|retry| (retry.retry_at, retry.task.id))
+            .collect();
+        due.sort();
         
-        while !self.task_queue.is_empty() || !self.workers.is_empty() {
-            // Distribute pending tasks to available workers
-            self.distribute_pending_tasks().await;
+        for (_, task_id) in &due {
+            debug!("Requeueing task {} for retry", task_id);
+            self.record(CoordinatorEvent::TaskRetryDue { task_id: *task_id });
+        }
+        due.len()
+    }
+    
+    /// Record a heartbeat from a registered worker
+    pub fn record_heartbeat(&mut self, heartbeat: &Heartbeat) {
+        if !self.workers.contains_key(&heartbeat.worker_id) {
+            debug!("Ignoring heartbeat from unknown worker {}", heartbeat.worker_id);
+            return;
+        }
+        
+        if let Some(registry) = &self.worker_registry {
+            if let Err(e) = registry.record_seen(heartbeat.worker_id, Utc::now()) {
+                warn!("Failed to update worker registry: {}", e);
+            }
+        }
+        if self.liveness.record(heartbeat, Utc::now()) {
+            info!("Worker {} is available again", heartbeat.worker_id);
+            self.record(CoordinatorEvent::WorkerAvailable { worker_id: heartbeat.worker_id });
+        }
+    }
+    
+    /// Process received heartbeats and mark silent workers unavailable
+    ///
+    /// In-flight tasks of a worker that became unavailable are requeued.
+    /// Returns the workers that became unavailable.
+    pub fn check_liveness(&mut self, now: DateTime<Utc>) -> Vec<Uuid> {
+        while let Ok(heartbeat) = self.heartbeat_receiver.try_recv() {
+            self.record_heartbeat(&heartbeat);
+        }
+        
+        let missed_heartbeats = self.liveness.config().missed_heartbeats;
+        let unavailable = self.liveness.check(now);
+        for worker_id in &unavailable {
+            warn!("Worker {} missed {} heartbeats, marking it unavailable", worker_id, missed_heartbeats);
+            let requeued: Vec<Uuid> = self.state.assignments().iter()
+                .filter(|assignment| assignment.worker_id == *worker_id)
+                .map(|assignment| assignment.task.id)
+                .collect();
             
-            // Small delay to prevent busy waiting
-            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+            self.record(CoordinatorEvent::WorkerUnavailable { worker_id: *worker_id, missed_heartbeats });
+            for task_id in requeued {
+                self.record(CoordinatorEvent::TaskRequeued { task_id, reason: format!("worker {} unavailable", worker_id) });
+            }
         }
+        unavailable
+    }
+    
+    pub async fn start(&mut self) -> SwarmResult<()> {
+        self.run(CancellationToken::new()).await
+    }
+    
+    /// Distribute tasks and record their results until cancelled or idle
+    ///
+    /// Returns once `cancel` is cancelled, or when no task is queued or
+    /// retrying and no worker is registered. Results are recorded between
+    /// distribution rounds, never in the middle of one.
+    pub async fn run(&mut self, cancel: CancellationToken) -> SwarmResult<()> {
+        let Some(mut results) = self.result_receiver.take() else {
+            warn!("Swarm coordinator is already running");
+            return Ok(());
+        };
+        info!("Starting swarm coordinator with {} workers", self.workers.len());
+        let mut registrations = self.registration_receiver.take();
         
-        info!("Task distribution complete");
+        let mut ticker = tokio::time::interval(Duration::from_millis(10));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        while registrations.is_some() || !self.state.pending.is_empty() || !self.state.retrying.is_empty() || !self.workers.is_empty() {
+            let mut registrations_closed = false;
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => break,
+                Some(result) = results.recv() => self.handle_result(&result),
+                request = next_registration(&mut registrations), if registrations.is_some() => match request {
+                    Some(request) => self.handle_registration(request),
+                    None => registrations_closed = true,
+                },
+                _ = ticker.tick() => {
+                    self.check_liveness(Utc::now());
+                    self.release_due_retries(Utc::now());
+                    self.submit_due_schedules(Utc::now());
+                    self.distribute_pending_tasks().await;
+                }
+            }
+            if registrations_closed {
+                registrations = None;
+            }
+        }
+        
+        // Results delivered before stopping still count
+        while let Ok(result) = results.try_recv() {
+            self.handle_result(&result);
+        }
+        self.result_receiver = Some(results);
+        self.registration_receiver = registrations;
+        info!("Swarm coordinator stopped");
         Ok(())
     }
-
+    
+    fn handle_registration(&mut self, request: RegistrationRequest) {
+        match request {
+            RegistrationRequest::Register { registration, tasks } => {
+                let WorkerRegistration { worker_id, config } = *registration;
+                let receiver = self.register_worker(worker_id, config);
+                if tasks.send(receiver).is_err() {
+                    warn!("Registration of worker {} was abandoned", worker_id);
+                    self.unregister_worker(worker_id);
+                }
+            }
+            RegistrationRequest::Deregister { worker_id } => {
+                let requeued: Vec<Uuid> = self.state.assignments().iter()
+                    .filter(|assignment| assignment.worker_id == worker_id)
+                    .map(|assignment| assignment.task.id)
+                    .collect();
+                for task_id in requeued {
+                    self.record(CoordinatorEvent::TaskRequeued { task_id, reason: format!("worker {} deregistered", worker_id) });
+                }
+                self.unregister_worker(worker_id);
+            }
+        }
+    }
+    
+    /// Record the latency from a task's creation to its final result
+    fn observe_end_to_end(&self, task: &Task, result: &TaskResult) {
+        let status = format!("{:?}", result.status).to_lowercase();
+        self.sli.observe_since(Sli::EndToEndLatency, task.created_at,
+            &[("task_type", &task.task_type.to_string()), ("status", &status)]);
+    }
+    
+    fn handle_result(&mut self, result: &TaskResult) {
+        let span = TraceContext::from_metadata(&result.metadata)
+            .map_or_else(tracing::Span::none, |process| process.continue_span("result", result.task_id).1);
+        span.in_scope(|| match result.status {
+            crate::TaskStatus::Completed => {
+                info!("Task {} completed in {}ms", result.task_id, result.processing_time_ms);
+            }
+            crate::TaskStatus::Failed => {
+                error!("Task {} failed", result.task_id);
+            }
+            _ => {}
+        });
+        self.record_result(result);
+    }
+    
     async fn distribute_pending_tasks(&mut self) {
-        if self.task_queue.is_empty() || self.workers.is_empty() {
+        self.starvation.check_queue(&self.state.pending, Utc::now());
+        
+        if self.state.pending.is_empty() || self.workers.is_empty() {
             return;
         }
-
-        let mut tasks_to_remove = Vec::new();
         
-        for (task_index, task) in self.task_queue.iter().enumerate() {
-            // Find an available worker (simple round-robin for now)
-            if let Some((worker_id, handle)) = self.workers.iter().next() {
-                debug!("Distributing task {} to worker {}", task.id, worker_id);
+        let mut events = Vec::new();
+        
+        let mut in_flight: HashMap<Uuid, usize> = HashMap::new();
+        for assignment in self.state.in_flight.values() {
+            *in_flight.entry(assignment.worker_id).or_default() += 1;
+        }
+        
+        for task in &self.state.pending {
+            if let Some(handle) = self.best_worker_for(task, &in_flight).and_then(|worker_id| self.workers.get(&worker_id)) {
+                let worker_name = self.state.workers.get(&handle.worker_id).map(|config| config.name.as_str()).unwrap_or_default();
+                let mut assigned = task.clone();
+                TraceContext::continue_task(&mut assigned, "assign").in_scope(|| {
+                    debug!("Distributing task {} to worker {} ({})", task.id, handle.worker_id, worker_name);
+                });
                 
-                if let Err(e) = handle.task_sender.send(task.clone()) {
-                    error!("Failed to send task to worker {}: {}", worker_id, e);
-                    // Remove the worker if it's no longer responding
-                    tasks_to_remove.push(task_index);
+                if let Err(e) = handle.task_sender.try_send(assigned) {
+                    if matches!(e, mpsc::error::TrySendError::Full(_)) {
+                        debug!("Task channel of worker {} is full, task {} stays queued", handle.worker_id, task.id);
+                        continue;
+                    }
+                    error!("Failed to send task to worker {}: {}", handle.worker_id, e);
+                    self.starvation.forget(task.id);
+                    events.push(CoordinatorEvent::TaskDropped { task_id: task.id, reason: e.to_string() });
                 } else {
-                    tasks_to_remove.push(task_index);
+                    self.starvation.record_dispatch(task, Utc::now());
+                    self.sli.observe_since(Sli::QueueWait, task.created_at, &[("task_type", &task.task_type.to_string())]);
+                    events.push(CoordinatorEvent::TaskAssigned { task_id: task.id, worker_id: handle.worker_id });
                     break; // Only send one task at a time for simplicity
                 }
             }
         }
         
-        // Remove distributed tasks (in reverse order to maintain indices)
-        for &index in tasks_to_remove.iter().rev() {
-            self.task_queue.remove(index);
+        for event in events {
+            self.record(event);
         }
     }
-
+    
+    /// Available worker scoring highest for a task; ties go to the lower worker ID
+    ///
+    /// Tasks of a spread group prefer failure domains not yet running one of
+    /// the group, but still run in a used domain if no other worker qualifies.
+    fn best_worker_for(&self, task: &Task, in_flight: &HashMap<Uuid, usize>) -> Option<Uuid> {
+        let candidates: Vec<(Uuid, f64)> = self.workers.values()
+            .filter(|handle| !self.state.unavailable_workers.contains(&handle.worker_id))
+            .filter(|handle| handle.task_sender.capacity() > 0)
+            .filter_map(|handle| {
+                let config = self.state.workers.get(&handle.worker_id)?;
+                let load = in_flight.get(&handle.worker_id).copied().unwrap_or_default();
+                Some((handle.worker_id, self.scheduling.score(config, task, load)?.score))
+            })
+            .collect();
+        
+        let used_domains = self.used_domains(task);
+        let spread: Vec<(Uuid, f64)> = match (&used_domains, self.spread) {
+            (Some(used), Some(level)) => candidates.iter()
+                .filter(|(worker_id, _)| !used.contains(&self.domain_of(*worker_id).key(level, *worker_id)))
+                .copied()
+                .collect(),
+            _ => Vec::new(),
+        };
+        if used_domains.is_some() && spread.is_empty() && !candidates.is_empty() {
+            warn!("No worker outside the failure domains of task {}'s spread group, placing it in a used one", task.id);
+        }
+        
+        if spread.is_empty() { candidates } else { spread }.into_iter()
+            .max_by(|(a, a_score), (b, b_score)| a_score.total_cmp(b_score).then_with(|| b.cmp(a)))
+            .map(|(worker_id, _)| worker_id)
+    }
+    
+    fn domain_of(&self, worker_id: Uuid) -> FailureDomain {
+        self.state.workers.get(&worker_id).map(FailureDomain::of).unwrap_or_default()
+    }
+    
+    /// Failure domains running in-flight tasks of the task's spread group, if spreading applies
+    fn used_domains(&self, task: &Task) -> Option<std::collections::HashSet<String>> {
+        let level = self.spread?;
+        let group = task.metadata.get(SPREAD_GROUP_KEY)?;
+        Some(self.state.in_flight.values()
+            .filter(|assignment| assignment.task.metadata.get(SPREAD_GROUP_KEY) == Some(group))
+            .map(|assignment| self.domain_of(assignment.worker_id).key(level, assignment.worker_id))
+            .collect())
+    }
+    
     pub fn worker_count(&self) -> usize {
         self.workers.len()
     }
-
+    
     pub fn pending_tasks(&self) -> usize {
-        self.task_queue.len()
+        self.state.pending.len()
     }
-
-    pub fn get_result_sender(&self) -> mpsc::UnboundedSender<TaskResult> {
+    
+    pub fn get_result_sender(&self) -> mpsc::Sender<TaskResult> {
         self.result_sender.clone()
     }
+    
+    /// Sender workers use to deliver heartbeats
+    pub fn get_heartbeat_sender(&self) -> mpsc::UnboundedSender<Heartbeat> {
+        self.heartbeat_sender.clone()
+    }
+    
+    /// Current state projection
+    pub fn state(&self) -> &CoordinatorState {
+        &self.state
+    }
+    
+    /// Counters of the result cache, if one is attached
+    pub fn result_cache_stats(&self) -> Option<ResultCacheStats> {
+        self.results.as_ref().map(ResultCache::stats)
+    }
+    
+    /// Recorded events
+    pub fn events(&self) -> &CoordinatorEventLog {
+        &self.events
+    }
+    
+    /// Learned processing times per task type
+    pub fn processing_times(&self) -> &ProcessingTimeEstimator {
+        &self.processing_times
+    }
+    
+    /// The registered, available workers as a fleet plan, one group per worker
+    ///
+    /// Start from this to ask what adding or removing workers would change.
+    pub fn current_fleet(&self) -> Vec<PlannedWorkers> {
+        let mut workers: Vec<(&Uuid, &WorkerConfig)> = self.state.workers.iter()
+            .filter(|(worker_id, _)| !self.state.unavailable_workers.contains(worker_id))
+            .collect();
+        workers.sort_by_key(|(worker_id, _)| **worker_id);
+        workers.into_iter().map(|(_, config)| PlannedWorkers::like(config, 1)).collect()
+    }
+    
+    /// Project how `fleet` would have handled the recently submitted tasks
+    pub fn plan_capacity(&self, fleet: &[PlannedWorkers]) -> CapacityProjection {
+        CapacityPlanner::new(&self.arrivals, &self.processing_times).simulate(fleet)
+    }
+    
+    /// Tasks selected by `filter` with their current status, oldest first
+    ///
+    /// Searches queued (`Pending`), in-flight (`Processing`), retrying and
+    /// permanently failed tasks.
+    pub fn find_tasks(&self, filter: &TaskFilter) -> Vec<(&Task, TaskStatus)> {
+        let mut found: Vec<(&Task, TaskStatus)> = self.state.pending.iter().map(|task| (task, TaskStatus::Pending))
+            .chain(self.state.assignments().into_iter().map(|assignment| (&assignment.task, TaskStatus::Processing)))
+            .chain(self.state.retrying.values().map(|retry| (&retry.task, TaskStatus::Retrying)))
+            .chain(self.state.failed.values().map(|task| (task, TaskStatus::Failed)))
+            .filter(|(task, status)| filter.matches(task, status))
+            .collect();
+        found.sort_by_key(|(task, _)| (task.created_at, task.id));
+        found
+    }
+    
+    /// Cancel or requeue every task matching the operation's filter
+    ///
+    /// A dry run only reports the matches. Otherwise each affected task is
+    /// dropped or requeued with the operation's reason, and `progress` is
+    /// called after every match.
+    pub fn run_bulk(&mut self, operation: &BulkOperation, mut progress: impl FnMut(BulkProgress)) -> BulkReport {
+        let matches: Vec<BulkMatch> = self.find_tasks(&operation.filter).into_iter()
+            .map(|(task, status)| BulkMatch::new(task, status, operation.action))
+            .collect();
+        let mut report = BulkReport {
+            action: operation.action,
+            dry_run: operation.dry_run,
+            matches,
+            applied: 0,
+        };
+        if operation.dry_run {
+            info!("Bulk {:?} dry run matched {} tasks", operation.action, report.matches.len());
+            return report;
+        }
+        
+        let total = report.matches.len();
+        for (index, task) in report.matches.iter().enumerate() {
+            if task.affected {
+                let (task_id, reason) = (task.task_id, operation.reason.clone());
+                let event = match operation.action {
+                    BulkAction::Cancel => {
+                        self.starvation.forget(task_id);
+                        CoordinatorEvent::TaskDropped { task_id, reason }
+                    }
+                    BulkAction::Requeue => CoordinatorEvent::TaskRequeued { task_id, reason },
+                };
+                self.record(event);
+                report.applied += 1;
+            }
+            progress(BulkProgress { processed: index + 1, total });
+        }
+        info!("Bulk {:?} applied to {} of {} matched tasks", operation.action, report.applied, total);
+        report
+    }
+    
+    /// Snapshot the current state and drop the events it covers
+    pub fn snapshot(&mut self) -> CoordinatorSnapshot {
+        let snapshot = CoordinatorSnapshot::of(&self.state);
+        self.events.truncate_through(snapshot.state.last_sequence);
+        snapshot
+    }
+    
+    /// Coordinator statistics, including queue fairness
+    pub fn get_stats(&self) -> CoordinatorStats {
+        let finished = self.state.tasks_completed + self.state.tasks_failed;
+        CoordinatorStats {
+            total_workers: self.workers.len(),
+            active_workers: self.workers.len() - self.state.unavailable_workers.len(),
+            total_tasks_processed: self.state.tasks_dispatched,
+            tasks_per_second: 0.0,
+            average_processing_time_ms: 0.0,
+            error_rate: if finished == 0 { 0.0 } else { self.state.tasks_failed as f32 / finished as f32 },
+            fairness: self.starvation.report(),
+            saturation: self.saturation(),
+        }
+    }
+    
+    /// How close the queue and channels are to their capacity
+    pub fn saturation(&self) -> QueueSaturation {
+        let result_queue_capacity = self.result_sender.max_capacity();
+        QueueSaturation {
+            pending_tasks: self.state.pending.len(),
+            max_pending_tasks: self.backpressure.max_pending_tasks,
+            rejected_submissions: self.rejected_submissions,
+            full_worker_queues: self.workers.values().filter(|handle| handle.task_sender.capacity() == 0).count(),
+            result_queue_depth: result_queue_capacity - self.result_sender.capacity(),
+            result_queue_capacity,
+        }
+    }
+}
+
+#[async_trait]
+impl Service for SwarmCoordinator {
+    fn name(&self) -> &str {
+        "coordinator"
+    }
+    
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        Ok(SwarmCoordinator::run(self, cancel).await?)
+    }
 }
 
 impl Default for SwarmCoordinator {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+/// Next registration request, or `None` once every sender is gone
+async fn next_registration(registrations: &mut Option<mpsc::UnboundedReceiver<RegistrationRequest>>) -> Option<RegistrationRequest> {
+    match registrations {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PerformanceProfile, TaskPayload, TaskPriority, TaskStatus, TaskType, WorkerStatus, WorkerType};
+    
+    fn create_test_config(name: &str) -> WorkerConfig {
+        WorkerConfig {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            worker_type: WorkerType::Custom { name: "test".to_string(), version: "1".to_string() },
+            max_concurrent_tasks: 1,
+            capabilities: Vec::new(),
+            performance_profile: PerformanceProfile {
+                avg_processing_time_ms: 10,
+                memory_usage_mb: 16,
+                cpu_intensity: 0.1,
+                throughput_per_second: 100.0,
+            },
+            health_check_interval_ms: 1000,
+            shutdown_timeout_ms: 1000,
+            metadata: HashMap::new(),
+        }
+    }
+    
+    fn create_test_task() -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            task_type: TaskType::Custom { name: "test".to_string(), version: "1".to_string() },
+            priority: TaskPriority::Normal,
+            status: TaskStatus::Pending,
+            payload: TaskPayload::Custom { data: Vec::new(), format: "raw".to_string() },
+            created_at: Utc::now(),
+            deadline: None,
+            retry_count: 0,
+            max_retries: 3,
+            metadata: HashMap::new(),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_changes_are_recorded_as_events() {
+        let mut coordinator = SwarmCoordinator::new();
+        let mut audit = coordinator.subscribe_events();
+        let worker_id = Uuid::new_v4();
+        let mut tasks = coordinator.register_worker(worker_id, create_test_config("w1"));
+        // No retries, so the failure below is final
+        let task = Task { max_retries: 0, ..create_test_task() };
+        
+        coordinator.submit_task(task.clone());
+        coordinator.distribute_pending_tasks().await;
+        assert_eq!(tasks.recv().await.unwrap().id, task.id);
+        assert_eq!(coordinator.pending_tasks(), 0);
+        assert_eq!(coordinator.state().in_flight[&task.id].worker_id, worker_id);
+        
+        coordinator.record_result(&crate::TaskResult {
+            task_id: task.id,
+            status: TaskStatus::Failed,
+            result: None,
+            error: Some("boom".to_string()),
+            processing_time_ms: 3,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        });
+        assert!(coordinator.state().in_flight.is_empty());
+        assert_eq!(coordinator.get_stats().error_rate, 1.0);
+        
+        let mut audited = Vec::new();
+        while let Ok(record) = audit.try_recv() {
+            audited.push(record);
+        }
+        assert_eq!(audited, coordinator.events().records());
+        assert_eq!(CoordinatorState::from_records(&audited), *coordinator.state());
+    }
+    
+    #[tokio::test]
+    async fn test_bulk_requeue_and_cancel_by_filter() {
+        let mut coordinator = SwarmCoordinator::new();
+        let worker_id = Uuid::new_v4();
+        let _tasks = coordinator.register_worker(worker_id, create_test_config("w1"));
+        let tenant_task = |tenant: &str| {
+            let mut task = Task { max_retries: 0, ..create_test_task() };
+            task.metadata.insert(crate::bulk::TENANT_KEY.to_string(), serde_json::json!(tenant));
+            task
+        };
+        let (failed, queued, other) = (tenant_task("acme"), tenant_task("acme"), tenant_task("globex"));
+        
+        coordinator.submit_task(failed.clone());
+        coordinator.distribute_pending_tasks().await;
+        coordinator.record_result(&crate::TaskResult {
+            task_id: failed.id,
+            status: TaskStatus::Failed,
+            result: None,
+            error: Some("boom".to_string()),
+            processing_time_ms: 3,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        });
+        coordinator.submit_task(queued.clone());
+        coordinator.submit_task(other.clone());
+        assert_eq!(coordinator.state().failed[&failed.id].status, TaskStatus::Failed);
+        
+        let requeue = BulkOperation::new(BulkAction::Requeue, TaskFilter::new().with_status(TaskStatus::Failed));
+        let preview = coordinator.run_bulk(&requeue.clone().dry_run(), |_| panic!("dry runs report no progress"));
+        assert_eq!((preview.affected(), preview.applied), (1, 0));
+        assert!(coordinator.state().pending.iter().all(|task| task.id != failed.id));
+        
+        let report = coordinator.run_bulk(&requeue, |_| {});
+        assert_eq!(report.applied, 1);
+        assert!(coordinator.state().failed.is_empty());
+        let requeued = coordinator.state().pending.last().unwrap();
+        assert_eq!((requeued.id, requeued.retry_count, requeued.status.clone()), (failed.id, 0, TaskStatus::Pending));
+        
+        let mut progress = Vec::new();
+        let cancel = BulkOperation::new(BulkAction::Cancel, TaskFilter::new().with_tenant("acme"));
+        let report = coordinator.run_bulk(&cancel, |update| progress.push(update));
+        assert_eq!(report.applied, 2);
+        assert_eq!(progress.last(), Some(&BulkProgress { processed: 2, total: 2 }));
+        assert_eq!(coordinator.state().pending, vec![other]);
+        assert_eq!(CoordinatorState::from_records(coordinator.events().records()), *coordinator.state());
+    }
+    
+    #[tokio::test]
+    async fn test_silent_worker_is_marked_unavailable_and_tasks_requeued() {
+        let mut coordinator = SwarmCoordinator::new()
+            .with_liveness_config(LivenessConfig { heartbeat_interval_ms: 100, missed_heartbeats: 2 });
+        let (silent, alive) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut silent_tasks = coordinator.register_worker(silent, create_test_config("silent"));
+        let task = create_test_task();
+        coordinator.submit_task(task.clone());
+        coordinator.distribute_pending_tasks().await;
+        assert_eq!(silent_tasks.recv().await.unwrap().id, task.id);
+        let mut alive_tasks = coordinator.register_worker(alive, create_test_config("alive"));
+        
+        let heartbeats = coordinator.get_heartbeat_sender();
+        let heartbeat = |worker_id| Heartbeat { worker_id, status: WorkerStatus::Idle, current_load: 0, sent_at: Utc::now() };
+        heartbeats.send(heartbeat(alive)).unwrap();
+        assert!(coordinator.check_liveness(Utc::now()).is_empty());
+        
+        tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+        heartbeats.send(heartbeat(alive)).unwrap();
+        assert_eq!(coordinator.check_liveness(Utc::now() + chrono::Duration::milliseconds(60)), vec![silent]);
+        assert!(coordinator.state().unavailable_workers.contains(&silent));
+        assert_eq!(coordinator.state().pending, vec![task.clone()]);
+        assert_eq!(coordinator.get_stats().active_workers, 1);
+        
+        // Requeued work only goes to available workers
+        coordinator.distribute_pending_tasks().await;
+        assert_eq!(alive_tasks.recv().await.unwrap().id, task.id);
+        assert!(silent_tasks.try_recv().is_err());
+        
+        coordinator.record_heartbeat(&heartbeat(silent));
+        assert!(coordinator.state().unavailable_workers.is_empty());
+        assert!(matches!(
+            coordinator.events().records().last().map(|record| &record.event),
+            Some(CoordinatorEvent::WorkerAvailable { worker_id }) if *worker_id == silent
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_restore_requeues_in_flight_tasks() {
+        let mut coordinator = SwarmCoordinator::new();
+        let _tasks = coordinator.register_worker(Uuid::new_v4(), create_test_config("w1"));
+        let (first, second, third) = (create_test_task(), create_test_task(), create_test_task());
+        
+        coordinator.submit_task(first.clone());
+        coordinator.submit_task(second.clone());
+        coordinator.distribute_pending_tasks().await;
+        let snapshot = coordinator.snapshot();
+        assert!(coordinator.events().records().is_empty());
+        
+        coordinator.distribute_pending_tasks().await;
+        coordinator.submit_task(third.clone());
+        
+        let restored = SwarmCoordinator::restore(&snapshot, coordinator.events().records());
+        let pending: Vec<Uuid> = restored.state().pending.iter().map(|task| task.id).collect();
+        assert_eq!(pending, vec![third.id, first.id, second.id]);
+        assert!(restored.state().workers.is_empty());
+        assert_eq!(restored.worker_count(), 0);
+        
+        let again = SwarmCoordinator::restore(&snapshot, coordinator.events().records());
+        assert_eq!(again.state(), restored.state());
+    }
+    
+    fn failed_result(task_id: Uuid, error: &str) -> crate::TaskResult {
+        crate::TaskResult {
+            task_id,
+            status: TaskStatus::Failed,
+            result: None,
+            error: Some(error.to_string()),
+            processing_time_ms: 1,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_failed_task_is_retried_then_dead_lettered() {
+        let metrics = Arc::new(crate::sli::MemoryMetricsCollector::new());
+        let mut coordinator = SwarmCoordinator::new()
+            .with_retry_policy(RetryPolicy { initial_backoff_ms: 100, multiplier: 2.0, max_backoff_ms: 1000 })
+            .with_metrics(metrics.clone());
+        let mut dead_letters = coordinator.subscribe_dead_letters();
+        let worker_id = Uuid::new_v4();
+        let mut tasks = coordinator.register_worker(worker_id, create_test_config("w1"));
+        let task = Task { max_retries: 1, ..create_test_task() };
+        coordinator.submit_task(task.clone());
+        
+        coordinator.distribute_pending_tasks().await;
+        assert_eq!(tasks.recv().await.unwrap().retry_count, 0);
+        coordinator.record_result(&failed_result(task.id, "first"));
+        
+        let retry = &coordinator.state().retrying[&task.id];
+        assert_eq!(retry.task.status, TaskStatus::Retrying);
+        assert!(coordinator.state().pending.is_empty());
+        
+        // Nothing is released before the backoff has passed
+        assert_eq!(coordinator.release_due_retries(Utc::now()), 0);
+        assert_eq!(coordinator.release_due_retries(Utc::now() + chrono::Duration::milliseconds(150)), 1);
+        coordinator.distribute_pending_tasks().await;
+        assert_eq!(tasks.recv().await.unwrap().retry_count, 1);
+        coordinator.record_result(&failed_result(task.id, "second"));
+        
+        let dead_letter = dead_letters.try_recv().unwrap();
+        assert_eq!(dead_letter.task.id, task.id);
+        assert_eq!(dead_letter.task.payload, task.payload);
+        let errors: Vec<&str> = dead_letter.errors.iter().map(|attempt| attempt.error.as_str()).collect();
+        assert_eq!(errors, vec!["first", "second"]);
+        assert_eq!(dead_letter.errors[1].attempt, 2);
+        
+        let state = coordinator.state();
+        assert!(state.retrying.is_empty() && state.failed_attempts.is_empty() && state.in_flight.is_empty());
+        assert_eq!((state.tasks_failed, state.tasks_dead_lettered), (1, 1));
+        assert_eq!(CoordinatorState::from_records(coordinator.events().records()), *state);
+        
+        // Both dispatches waited in the queue; only the final outcome ends the task
+        assert_eq!(metrics.histogram(Sli::QueueWait.name()).len(), 2);
+        assert_eq!(metrics.histogram(Sli::EndToEndLatency.name()).len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_failed_group_member_drops_queued_members() {
+        let mut coordinator = SwarmCoordinator::new();
+        let mut group_results = coordinator.subscribe_group_results();
+        let mut tasks = coordinator.register_worker(Uuid::new_v4(), create_test_config("w1"));
+        let members: Vec<Task> = (0..3).map(|_| Task { max_retries: 0, ..create_test_task() }).collect();
+        coordinator.submit_group("invoice-17", members.clone());
+        
+        coordinator.distribute_pending_tasks().await;
+        let first = tasks.recv().await.unwrap();
+        assert_eq!(first.metadata[crate::document_groups::DOCUMENT_GROUP_KEY], "invoice-17");
+        coordinator.record_result(&failed_result(first.id, "unreadable attachment"));
+        
+        let result = group_results.try_recv().unwrap();
+        assert_eq!(result.group_id, "invoice-17");
+        assert_eq!(result.status, GroupStatus::Failed);
+        assert_eq!(result.cancelled_members().count(), 2);
+        assert!(coordinator.state().pending.is_empty());
+        assert_eq!(CoordinatorState::from_records(coordinator.events().records()), *coordinator.state());
+    }
+    
+    #[tokio::test]
+    async fn test_fanned_out_children_can_be_joined() {
+        let mut coordinator = SwarmCoordinator::new();
+        let mut tasks = coordinator.register_worker(Uuid::new_v4(), create_test_config("w1"));
+        let parent = Uuid::new_v4();
+        let group = coordinator.fan_out(parent, (0..3).map(|_| create_test_task()).collect());
+        assert_eq!((group.group_id.clone(), group.len()), (parent.to_string(), 3));
+        let joined = coordinator.join_group(&group.group_id).unwrap();
+        assert!(coordinator.join_group("unknown").is_none());
+        
+        for _ in 0..3 {
+            coordinator.distribute_pending_tasks().await;
+            let chunk = tasks.recv().await.unwrap();
+            assert!(group.contains(chunk.id));
+            let mut result = TaskResult { status: TaskStatus::Completed, error: None, ..failed_result(chunk.id, "") };
+            result.metadata.insert("chunks".to_string(), serde_json::json!(1));
+            coordinator.record_result(&result);
+        }
+        
+        let result = joined.await.unwrap();
+        assert_eq!((result.status, result.parent_task_id), (GroupStatus::Completed, Some(parent)));
+        assert_eq!((result.results.len(), result.processing_time_ms), (3, 3));
+        assert_eq!(result.metadata["chunks"], 1);
+        assert!(coordinator.group_progress(&group.group_id).is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_repeated_tasks_and_results_are_handled_once() {
+        let mut coordinator = SwarmCoordinator::new().with_result_cache(16);
+        let mut tasks = coordinator.register_worker(Uuid::new_v4(), create_test_config("w1"));
+        let task = create_test_task();
+        coordinator.submit_task(task.clone());
+        coordinator.distribute_pending_tasks().await;
+        let assigned = tasks.recv().await.unwrap();
+        
+        let result = TaskResult { status: TaskStatus::Completed, error: None, ..failed_result(assigned.id, "") };
+        coordinator.record_result(&result);
+        coordinator.record_result(&result);
+        coordinator.submit_task(task.clone());
+        assert_eq!((coordinator.state().tasks_completed, coordinator.pending_tasks()), (1, 0));
+        
+        // The same work under a new id is answered without a worker
+        let again = Task { id: Uuid::new_v4(), ..task };
+        coordinator.submit_task(again);
+        coordinator.distribute_pending_tasks().await;
+        assert!(tasks.try_recv().is_err());
+        assert_eq!((coordinator.state().tasks_completed, coordinator.pending_tasks()), (2, 0));
+        assert_eq!(coordinator.result_cache_stats(), Some(ResultCacheStats { entries: 1, hits: 1, duplicates: 1 }));
+    }
+    
+    #[tokio::test]
+    async fn test_workflow_stages_wait_for_their_parents() {
+        let mut coordinator = SwarmCoordinator::new();
+        let mut workflow_results = coordinator.subscribe_workflow_results();
+        let mut tasks = coordinator.register_worker(Uuid::new_v4(), create_test_config("w1"));
+        let workflow = Workflow::new("doc-9")
+            .stage("extract", create_test_task(), &[])
+            .stage("chunk", Task { max_retries: 0, ..create_test_task() }, &["extract"]);
+        coordinator.submit_workflow(workflow.clone()).unwrap();
+        assert!(matches!(coordinator.submit_workflow(workflow), Err(SwarmError::Workflow(_))));
+        assert_eq!(coordinator.pending_tasks(), 1);
+        
+        coordinator.distribute_pending_tasks().await;
+        let extract = tasks.recv().await.unwrap();
+        assert_eq!(coordinator.workflow_progress("doc-9").unwrap(), vec![("extract", StageStatus::Released), ("chunk", StageStatus::Waiting)]);
+        coordinator.record_result(&TaskResult { status: TaskStatus::Completed, error: None, ..failed_result(extract.id, "") });
+        
+        coordinator.distribute_pending_tasks().await;
+        let chunk = tasks.recv().await.unwrap();
+        assert_eq!(chunk.metadata[crate::workflow::WORKFLOW_STAGE_KEY], "chunk");
+        coordinator.record_result(&failed_result(chunk.id, "no text"));
+        
+        let result = workflow_results.try_recv().unwrap();
+        assert_eq!((result.workflow_id.as_str(), result.status), ("doc-9", WorkflowStatus::Failed));
+        assert_eq!(result.stages[1].error.as_deref(), Some("no text"));
+        assert!(coordinator.workflow_progress("doc-9").is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_run_records_results_until_cancelled() {
+        let mut coordinator = SwarmCoordinator::new();
+        let mut tasks = coordinator.register_worker(Uuid::new_v4(), create_test_config("w1"));
+        let results = coordinator.get_result_sender();
+        let task = create_test_task();
+        coordinator.submit_task(task.clone());
+        
+        let cancel = CancellationToken::new();
+        let token = cancel.clone();
+        let run = tokio::spawn(async move {
+            coordinator.run(token).await.map(|_| coordinator)
+        });
+        assert_eq!(tasks.recv().await.unwrap().id, task.id);
+        results.send(TaskResult {
+            task_id: task.id,
+            status: TaskStatus::Completed,
+            result: None,
+            error: None,
+            processing_time_ms: 5,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        }).await.unwrap();
+        cancel.cancel();
+        
+        // A registered worker keeps the loop alive; only the token stops it
+        let mut coordinator = tokio::time::timeout(Duration::from_secs(1), run).await.unwrap().unwrap().unwrap();
+        assert!(coordinator.state().in_flight.is_empty());
+        assert_eq!(coordinator.get_stats().error_rate, 0.0);
+        assert_eq!(coordinator.processing_times().learned_ms(&task.task_type), Some(5.0));
+        
+        // The result receiver is handed back, so the loop can be run again
+        let cancelled = CancellationToken::new();
+        cancelled.cancel();
+        coordinator.run(cancelled).await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_full_channels_hold_tasks_back() {
+        let mut coordinator = SwarmCoordinator::new().with_backpressure(BackpressureConfig {
+            task_queue_capacity: 1,
+            max_pending_tasks: Some(2),
+            ..Default::default()
+        });
+        let mut tasks = coordinator.register_worker(Uuid::new_v4(), create_test_config("w1"));
+        coordinator.try_submit_task(create_test_task()).unwrap();
+        coordinator.try_submit_task(create_test_task()).unwrap();
+        let refused = coordinator.try_submit_task(create_test_task());
+        assert!(matches!(refused, Err(SwarmError::QueueFull { pending: 2, capacity: 2 })));
+        
+        // The second task waits in the queue until the worker takes the first
+        coordinator.distribute_pending_tasks().await;
+        coordinator.distribute_pending_tasks().await;
+        let saturation = coordinator.get_stats().saturation;
+        assert_eq!((saturation.pending_tasks, saturation.full_worker_queues, saturation.rejected_submissions), (1, 1, 1));
+        assert!(saturation.is_saturated());
+        
+        tasks.recv().await.unwrap();
+        coordinator.distribute_pending_tasks().await;
+        assert_eq!(coordinator.pending_tasks(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_worker_registry_outlives_workers() {
+        let registry = Arc::new(WorkerRegistry::temporary().unwrap());
+        let mut coordinator = SwarmCoordinator::new().with_worker_registry(registry.clone());
+        let worker_id = Uuid::new_v4();
+        let _tasks = coordinator.register_worker(worker_id, create_test_config("w1"));
+        coordinator.record_heartbeat(&Heartbeat { worker_id, status: WorkerStatus::Idle, current_load: 0, sent_at: Utc::now() });
+        assert_eq!(registry.fleet_at(Utc::now()).unwrap().active_workers, 1);
+        
+        coordinator.unregister_worker(worker_id);
+        let record = registry.get(worker_id).unwrap().unwrap();
+        assert_eq!(record.name, "w1");
+        assert!(!record.is_active());
+        assert_eq!(registry.fleet_at(Utc::now()).unwrap().active_workers, 0);
+        assert_eq!(registry.fleet_at(record.first_seen).unwrap().active_workers, 1);
+    }
+    
+    #[test]
+    fn test_schedules_submit_tasks_and_survive_restarts() {
+        use crate::task_schedules::{TaskTemplate, SCHEDULE_KEY};
+        use crate::types::{TaskPriority, TaskPayload, TaskType, TextAnalysisType};
+        
+        let store = Arc::new(ScheduleStore::temporary().unwrap());
+        let mut coordinator = SwarmCoordinator::new().with_schedule_store(store.clone());
+        let template = TaskTemplate::new(
+            TaskType::TextAnalysis { analysis_type: TextAnalysisType::KeywordExtraction },
+            TaskPriority::Low,
+            TaskPayload::Custom { data: Vec::new(), format: "rescan".to_string() },
+        );
+        coordinator.add_schedule(TaskSchedule::new("rescan", "* * * * *", template).unwrap()).unwrap();
+        assert!(coordinator.add_schedule(TaskSchedule { cron: "nightly".to_string(), ..store.get("rescan").unwrap().unwrap() }).is_err());
+        
+        let due_at = coordinator.schedules().next().unwrap().next_run.unwrap();
+        assert_eq!(coordinator.submit_due_schedules(due_at - chrono::Duration::seconds(1)), 0);
+        assert_eq!(coordinator.submit_due_schedules(due_at), 1);
+        assert_eq!(coordinator.state().pending[0].metadata[SCHEDULE_KEY], "rescan");
+        
+        // A new coordinator picks the schedule up where the old one left off
+        let restarted = SwarmCoordinator::new().with_schedule_store(store.clone());
+        let schedule = restarted.schedules().next().unwrap();
+        assert_eq!(schedule.last_run, Some(due_at));
+        assert!(schedule.next_run.unwrap() > due_at);
+        
+        assert!(coordinator.set_schedule_enabled("rescan", false).unwrap());
+        assert!(!store.get("rescan").unwrap().unwrap().enabled);
+        assert!(coordinator.remove_schedule("rescan").unwrap());
+        assert!(store.schedules().unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_compatibility_report_flags_mismatched_workers() {
+        let mut coordinator = SwarmCoordinator::new();
+        let mut current = create_test_config("current");
+        BuildInfo::current().insert_into(&mut current.metadata);
+        let mut outdated = create_test_config("outdated");
+        let mut build = BuildInfo::current();
+        build.schema_version = "0".to_string();
+        build.insert_into(&mut outdated.metadata);
+        let _current_tasks = coordinator.register_worker(Uuid::new_v4(), current);
+        let _outdated_tasks = coordinator.register_worker(Uuid::new_v4(), outdated);
+        
+        let report = coordinator.compatibility_report();
+        assert_eq!(report.components.len(), 2);
+        let incompatible: Vec<&str> = report.incompatible().map(|component| component.component.as_str()).collect();
+        assert_eq!(incompatible.len(), 1);
+        assert!(incompatible[0].starts_with("outdated ("));
+    }
+    
+    #[test]
+    fn test_priority_rules_escalate_submitted_documents() {
+        use crate::priority_rules::{PriorityCondition, PriorityRule, PRIORITY_RULE_KEY};
+        use crate::types::{Document, DocumentContent, DocumentProcessingOptions, DocumentType};
+        
+        let rules = PriorityRules::default().with_rule(PriorityRule::new("urgent", PriorityCondition::TextContains {
+            value: "URGENT".to_string(),
+        }, TaskPriority::Critical));
+        let mut coordinator = SwarmCoordinator::new().with_priority_rules(rules);
+        let document = Document {
+            id: Uuid::new_v4(),
+            filename: "note.txt".to_string(),
+            document_type: DocumentType::Text,
+            content: DocumentContent::Text("Urgent: server room flooding".to_string()),
+            metadata: HashMap::new(),
+            created_at: Utc::now(),
+            size_bytes: 28,
+        };
+        let task = Task {
+            payload: TaskPayload::Document { document, processing_options: DocumentProcessingOptions::default() },
+            ..create_test_task()
+        };
+        coordinator.submit_task(task);
+        coordinator.submit_task(create_test_task());
+        
+        let pending = &coordinator.state().pending;
+        assert_eq!((pending[0].priority.clone(), &pending[0].metadata[PRIORITY_RULE_KEY]), (TaskPriority::Critical, &serde_json::json!("urgent")));
+        assert_eq!(pending[1].priority, TaskPriority::Normal);
+    }
+    
+    #[tokio::test]
+    async fn test_tasks_go_to_the_best_matching_worker() {
+        use crate::types::{DocumentProcessingType, DocumentType, TextAnalysisType};
+        
+        let mut coordinator = SwarmCoordinator::new();
+        let (pdf_worker, generalist) = (Uuid::new_v4(), Uuid::new_v4());
+        let pdf_config = WorkerConfig {
+            worker_type: WorkerType::DocumentProcessor { supported_types: vec![DocumentType::Pdf] },
+            capabilities: vec![crate::types::WorkerCapability {
+                name: "pdf".to_string(),
+                version: "1".to_string(),
+                supported_task_types: vec![TaskType::DocumentProcessing {
+                    document_type: DocumentType::Pdf,
+                    processing_type: DocumentProcessingType::TextExtraction,
+                }],
+                max_concurrent_tasks: 4,
+                performance_profile: create_test_config("pdf").performance_profile,
+                metadata: HashMap::new(),
+            }],
+            ..create_test_config("pdf")
+        };
+        let mut pdf_tasks = coordinator.register_worker(pdf_worker, pdf_config);
+        let mut generalist_tasks = coordinator.register_worker(generalist, create_test_config("generalist"));
+        
+        let task = |task_type: TaskType| Task { task_type, ..create_test_task() };
+        let pdf = task(TaskType::DocumentProcessing { document_type: DocumentType::Pdf, processing_type: DocumentProcessingType::TextExtraction });
+        let docx = task(TaskType::DocumentProcessing { document_type: DocumentType::Word, processing_type: DocumentProcessingType::TextExtraction });
+        coordinator.submit_task(pdf.clone());
+        coordinator.submit_task(docx.clone());
+        coordinator.distribute_pending_tasks().await;
+        coordinator.distribute_pending_tasks().await;
+        assert_eq!(pdf_tasks.try_recv().unwrap().id, pdf.id);
+        assert_eq!(generalist_tasks.try_recv().unwrap().id, docx.id);
+        
+        // Only workers without capabilities take anything; the specialist does not
+        coordinator.unregister_worker(generalist);
+        let sentiment = task(TaskType::TextAnalysis { analysis_type: TextAnalysisType::SentimentAnalysis });
+        coordinator.submit_task(sentiment.clone());
+        coordinator.distribute_pending_tasks().await;
+        assert!(pdf_tasks.try_recv().is_err());
+        assert_eq!(coordinator.state().pending, vec![sentiment]);
+    }
+    
+    #[tokio::test]
+    async fn test_spread_group_avoids_used_failure_domains() {
+        use crate::scheduling::{FAILURE_DOMAIN_ZONE_KEY, SPREAD_GROUP_KEY};
+        
+        let mut coordinator = SwarmCoordinator::new().with_failure_domain_spread(SpreadLevel::Zone);
+        let mut workers = Vec::new();
+        // Two fast workers in zone a, a slower one in zone b
+        for (zone, avg_processing_time_ms) in [("a", 10), ("a", 10), ("b", 5000)] {
+            let mut config = create_test_config(zone);
+            config.max_concurrent_tasks = 4;
+            config.performance_profile.avg_processing_time_ms = avg_processing_time_ms;
+            config.metadata.insert(FAILURE_DOMAIN_ZONE_KEY.to_string(), serde_json::json!(zone));
+            let worker_id = Uuid::new_v4();
+            workers.push((zone, coordinator.register_worker(worker_id, config)));
+        }
+        
+        let replica = || {
+            let mut task = create_test_task();
+            task.metadata.insert(SPREAD_GROUP_KEY.to_string(), serde_json::json!("doc-1"));
+            task
+        };
+        for _ in 0..3 {
+            coordinator.submit_task(replica());
+            coordinator.distribute_pending_tasks().await;
+        }
+        let mut zones: Vec<&str> = workers.iter_mut()
+            .flat_map(|(zone, tasks)| std::iter::from_fn(|| tasks.try_recv().ok()).map(|_| *zone).collect::<Vec<_>>())
+            .collect();
+        zones.sort();
+        // The first two replicas land in distinct zones; the third has no unused zone left
+        assert_eq!(zones, vec!["a", "a", "b"]);
+        assert_eq!(coordinator.state().in_flight.len(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_plan_capacity_uses_learned_processing_times() {
+        let mut coordinator = SwarmCoordinator::new();
+        let _tasks = coordinator.register_worker(Uuid::new_v4(), create_test_config("w1"));
+        let tasks: Vec<Task> = (0..4).map(|_| create_test_task()).collect();
+        for task in &tasks {
+            coordinator.submit_task(task.clone());
+        }
+        coordinator.record_result(&TaskResult {
+            task_id: tasks[0].id,
+            status: TaskStatus::Completed,
+            result: None,
+            error: None,
+            processing_time_ms: 60_000,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        });
+        assert_eq!(coordinator.processing_times().learned_ms(&tasks[0].task_type), Some(60_000.0));
+        
+        let mut fleet = coordinator.current_fleet();
+        assert_eq!(fleet.len(), 1);
+        let current = coordinator.plan_capacity(&fleet);
+        assert_eq!(current.tasks, 4);
+        assert!(current.max_queue_depth >= 3);
+        
+        fleet.push(PlannedWorkers::like(&create_test_config("extra"), 3));
+        let expanded = coordinator.plan_capacity(&fleet);
+        assert_eq!(expanded.max_queue_depth, 0);
+        assert!(expanded.makespan_ms < current.makespan_ms);
+    }
+}
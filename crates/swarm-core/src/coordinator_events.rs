@@ -0,0 +1,419 @@
+//! Coordinator Event Log
+//!
+//! Coordinator state is derived from an append-only log of events (task
+//! submitted, assigned, completed, worker registered, ...). Projections fold
+//! the log into in-memory views; the audit log, snapshots and dashboards all
+//! consume the same records, and replaying a log always yields the same state.
+//! Logs and snapshots are persisted as JSON, zstd-compressed if configured.
+
+use crate::compression::{self, Compressor};
+use crate::retry::{FailedAttempt, ScheduledRetry};
+use crate::types::{Task, TaskResult, TaskStatus, WorkerConfig};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Something that happened to coordinator state
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CoordinatorEvent {
+    WorkerRegistered { worker_id: Uuid, config: WorkerConfig },
+    WorkerUnregistered { worker_id: Uuid },
+    /// A worker missed too many heartbeats
+    WorkerUnavailable { worker_id: Uuid, missed_heartbeats: u32 },
+    /// An unavailable worker sent a heartbeat again
+    WorkerAvailable { worker_id: Uuid },
+    TaskSubmitted { task: Task },
+    TaskAssigned { task_id: Uuid, worker_id: Uuid },
+    /// An assigned, retrying or failed task went back to the queue
+    TaskRequeued { task_id: Uuid, reason: String },
+    /// A task was removed without being processed (or its result is ignored)
+    TaskDropped { task_id: Uuid, reason: String },
+    TaskFinished { task_id: Uuid, status: TaskStatus, processing_time_ms: u64 },
+    /// A failed task will be retried after a backoff
+    TaskRetryScheduled { task_id: Uuid, error: String, retry_at: DateTime<Utc> },
+    /// A retry's backoff passed and the task went back to the queue
+    TaskRetryDue { task_id: Uuid },
+    /// A task failed permanently and was handed to the dead-letter queue
+    TaskDeadLettered { task_id: Uuid, attempts: u32 },
+}
+
+impl CoordinatorEvent {
+    /// Event recording a task result
+    pub fn task_finished(result: &TaskResult) -> Self {
+        CoordinatorEvent::TaskFinished {
+            task_id: result.task_id,
+            status: result.status.clone(),
+            processing_time_ms: result.processing_time_ms,
+        }
+    }
+}
+
+/// An event with its position in the log
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EventRecord {
+    /// Position in the log, starting at 1
+    pub sequence: u64,
+    pub recorded_at: DateTime<Utc>,
+    pub event: CoordinatorEvent,
+}
+
+/// Append-only coordinator event log
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoordinatorEventLog {
+    records: Vec<EventRecord>,
+    last_sequence: u64,
+}
+
+impl CoordinatorEventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Empty log continuing after a snapshot
+    pub fn starting_after(sequence: u64) -> Self {
+        Self {
+            records: Vec::new(),
+            last_sequence: sequence,
+        }
+    }
+    
+    /// Append an event, assigning the next sequence number
+    pub fn append(&mut self, event: CoordinatorEvent) -> &EventRecord {
+        self.last_sequence += 1;
+        self.records.push(EventRecord {
+            sequence: self.last_sequence,
+            recorded_at: Utc::now(),
+            event,
+        });
+        self.records.last().expect("record was just appended")
+    }
+    
+    /// All retained records
+    pub fn records(&self) -> &[EventRecord] {
+        &self.records
+    }
+    
+    /// Records after a sequence number
+    pub fn since(&self, sequence: u64) -> &[EventRecord] {
+        let start = self.records.partition_point(|record| record.sequence <= sequence);
+        &self.records[start..]
+    }
+    
+    /// Drop records up to and including a sequence number (e.g. after a snapshot)
+    pub fn truncate_through(&mut self, sequence: u64) {
+        self.records.retain(|record| record.sequence > sequence);
+    }
+    
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+    
+    /// Bytes to persist the log as
+    pub fn encode(&self, compressor: &Compressor) -> anyhow::Result<Vec<u8>> {
+        compressor.to_vec(self)
+    }
+    
+    /// Read a log persisted by `encode`, compressed or not
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(serde_json::from_slice(&compression::decompress(bytes)?)?)
+    }
+}
+
+/// A view folded from coordinator events
+pub trait Projection {
+    fn apply(&mut self, record: &EventRecord);
+    
+    /// Apply a sequence of records in order
+    fn replay<'a>(&mut self, records: impl IntoIterator<Item = &'a EventRecord>) where Self: Sized {
+        for record in records {
+            self.apply(record);
+        }
+    }
+}
+
+/// A task assigned to a worker and not yet finished
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Assignment {
+    pub task: Task,
+    pub worker_id: Uuid,
+    /// Sequence of the assignment event
+    pub sequence: u64,
+}
+
+/// Coordinator state: registered workers, queued and in-flight tasks
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CoordinatorState {
+    pub workers: HashMap<Uuid, WorkerConfig>,
+    
+    /// Registered workers that stopped sending heartbeats
+    #[serde(default)]
+    pub unavailable_workers: HashSet<Uuid>,
+    
+    /// Queued tasks in submission order
+    pub pending: Vec<Task>,
+    
+    pub in_flight: HashMap<Uuid, Assignment>,
+    
+    /// Failed tasks waiting for their retry backoff
+    #[serde(default)]
+    pub retrying: HashMap<Uuid, ScheduledRetry>,
+    
+    /// Errors of earlier attempts of tasks that are still being retried
+    #[serde(default)]
+    pub failed_attempts: HashMap<Uuid, Vec<FailedAttempt>>,
+    
+    /// Tasks that failed permanently, kept so they can be requeued
+    #[serde(default)]
+    pub failed: HashMap<Uuid, Task>,
+    
+    pub tasks_dispatched: u64,
+    pub tasks_completed: u64,
+    pub tasks_failed: u64,
+    
+    #[serde(default)]
+    pub tasks_dead_lettered: u64,
+    
+    /// Sequence of the last applied event
+    pub last_sequence: u64,
+}
+
+impl CoordinatorState {
+    /// Rebuild state from a complete log
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a EventRecord>) -> Self {
+        let mut state = Self::default();
+        state.replay(records);
+        state
+    }
+    
+    /// In-flight assignments in the order they were made
+    pub fn assignments(&self) -> Vec<&Assignment> {
+        let mut assignments: Vec<&Assignment> = self.in_flight.values().collect();
+        assignments.sort_by_key(|assignment| assignment.sequence);
+        assignments
+    }
+}
+
+impl Projection for CoordinatorState {
+    fn apply(&mut self, record: &EventRecord) {
+        if record.sequence <= self.last_sequence {
+            return;
+        }
+        self.last_sequence = record.sequence;
+        
+        match &record.event {
+            CoordinatorEvent::WorkerRegistered { worker_id, config } => {
+                self.workers.insert(*worker_id, config.clone());
+            }
+            CoordinatorEvent::WorkerUnregistered { worker_id } => {
+                self.workers.remove(worker_id);
+                self.unavailable_workers.remove(worker_id);
+            }
+            CoordinatorEvent::WorkerUnavailable { worker_id, .. } => {
+                if self.workers.contains_key(worker_id) {
+                    self.unavailable_workers.insert(*worker_id);
+                }
+            }
+            CoordinatorEvent::WorkerAvailable { worker_id } => {
+                self.unavailable_workers.remove(worker_id);
+            }
+            CoordinatorEvent::TaskSubmitted { task } => {
+                self.pending.push(task.clone());
+            }
+            CoordinatorEvent::TaskAssigned { task_id, worker_id } => {
+                if let Some(index) = self.pending.iter().position(|task| task.id == *task_id) {
+                    let task = self.pending.remove(index);
+                    self.tasks_dispatched += 1;
+                    self.in_flight.insert(*task_id, Assignment {
+                        task,
+                        worker_id: *worker_id,
+                        sequence: record.sequence,
+                    });
+                }
+            }
+            CoordinatorEvent::TaskRequeued { task_id, .. } => {
+                if let Some(assignment) = self.in_flight.remove(task_id) {
+                    self.pending.push(assignment.task);
+                } else if let Some(ScheduledRetry { mut task, .. }) = self.retrying.remove(task_id) {
+                    task.status = TaskStatus::Pending;
+                    self.pending.push(task);
+                } else if let Some(mut task) = self.failed.remove(task_id) {
+                    task.status = TaskStatus::Pending;
+                    task.retry_count = 0;
+                    self.pending.push(task);
+                }
+            }
+            CoordinatorEvent::TaskDropped { task_id, .. } => {
+                self.pending.retain(|task| task.id != *task_id);
+                self.in_flight.remove(task_id);
+                self.retrying.remove(task_id);
+                self.failed.remove(task_id);
+                self.failed_attempts.remove(task_id);
+            }
+            CoordinatorEvent::TaskFinished { task_id, status, .. } => {
+                let assignment = self.in_flight.remove(task_id);
+                if assignment.is_none() {
+                    // Answered without being assigned, e.g. from cached results
+                    self.pending.retain(|task| task.id != *task_id);
+                }
+                match status {
+                    TaskStatus::Completed => {
+                        self.tasks_completed += 1;
+                        self.failed_attempts.remove(task_id);
+                    }
+                    TaskStatus::Failed => {
+                        self.tasks_failed += 1;
+                        if let Some(Assignment { mut task, .. }) = assignment {
+                            task.status = TaskStatus::Failed;
+                            self.failed.insert(*task_id, task);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            CoordinatorEvent::TaskRetryScheduled { task_id, error, retry_at } => {
+                if let Some(Assignment { mut task, worker_id, .. }) = self.in_flight.remove(task_id) {
+                    self.failed_attempts.entry(*task_id).or_default().push(FailedAttempt {
+                        attempt: task.retry_count + 1,
+                        worker_id: Some(worker_id),
+                        error: error.clone(),
+                        failed_at: record.recorded_at,
+                    });
+                    task.retry_count += 1;
+                    task.status = TaskStatus::Retrying;
+                    self.retrying.insert(*task_id, ScheduledRetry { task, retry_at: *retry_at });
+                }
+            }
+            CoordinatorEvent::TaskRetryDue { task_id } => {
+                if let Some(ScheduledRetry { mut task, .. }) = self.retrying.remove(task_id) {
+                    task.status = TaskStatus::Pending;
+                    self.pending.push(task);
+                }
+            }
+            CoordinatorEvent::TaskDeadLettered { task_id, .. } => {
+                self.failed_attempts.remove(task_id);
+                self.tasks_dead_lettered += 1;
+            }
+        }
+    }
+}
+
+/// Point-in-time coordinator state; replay later events on top to restore
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CoordinatorSnapshot {
+    pub taken_at: DateTime<Utc>,
+    pub state: CoordinatorState,
+}
+
+impl CoordinatorSnapshot {
+    pub fn of(state: &CoordinatorState) -> Self {
+        Self {
+            taken_at: Utc::now(),
+            state: state.clone(),
+        }
+    }
+    
+    /// Bytes to persist the snapshot as
+    pub fn encode(&self, compressor: &Compressor) -> anyhow::Result<Vec<u8>> {
+        compressor.to_vec(self)
+    }
+    
+    /// Read a snapshot persisted by `encode`, compressed or not
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(serde_json::from_slice(&compression::decompress(bytes)?)?)
+    }
+    
+    /// State after replaying records on top of the snapshot
+    pub fn restore<'a>(&self, records: impl IntoIterator<Item = &'a EventRecord>) -> CoordinatorState {
+        let mut state = self.state.clone();
+        state.replay(records);
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_builder::TaskBuilder;
+    use crate::types::{TaskPayload, TaskType};
+    
+    fn create_test_task(name: &str) -> Task {
+        let task_type = TaskType::Custom { name: name.to_string(), version: "1".to_string() };
+        TaskBuilder::new(task_type, TaskPayload::Custom { data: Vec::new(), format: "raw".to_string() }).build()
+    }
+    
+    fn create_test_log(tasks: &[Task], worker_id: Uuid) -> CoordinatorEventLog {
+        let mut log = CoordinatorEventLog::new();
+        for task in tasks {
+            log.append(CoordinatorEvent::TaskSubmitted { task: task.clone() });
+        }
+        log.append(CoordinatorEvent::TaskAssigned { task_id: tasks[0].id, worker_id });
+        log.append(CoordinatorEvent::TaskAssigned { task_id: tasks[1].id, worker_id });
+        log.append(CoordinatorEvent::TaskFinished { task_id: tasks[0].id, status: TaskStatus::Completed, processing_time_ms: 5 });
+        log
+    }
+    
+    #[test]
+    fn test_state_projection() {
+        let tasks = [create_test_task("a"), create_test_task("b"), create_test_task("c")];
+        let worker_id = Uuid::new_v4();
+        let log = create_test_log(&tasks, worker_id);
+        
+        let state = CoordinatorState::from_records(log.records());
+        assert_eq!(state.pending, vec![tasks[2].clone()]);
+        assert_eq!(state.in_flight[&tasks[1].id].worker_id, worker_id);
+        assert_eq!(state.tasks_dispatched, 2);
+        assert_eq!(state.tasks_completed, 1);
+        assert_eq!(state.last_sequence, 6);
+        
+        let mut requeued = state.clone();
+        requeued.apply(&EventRecord {
+            sequence: 7,
+            recorded_at: Utc::now(),
+            event: CoordinatorEvent::TaskRequeued { task_id: tasks[1].id, reason: "worker lost".to_string() },
+        });
+        assert_eq!(requeued.pending, vec![tasks[2].clone(), tasks[1].clone()]);
+        assert!(requeued.in_flight.is_empty());
+    }
+    
+    #[test]
+    fn test_snapshot_and_replay_match_full_replay() {
+        let tasks = [create_test_task("a"), create_test_task("b"), create_test_task("c")];
+        let mut log = create_test_log(&tasks, Uuid::new_v4());
+        
+        let snapshot = CoordinatorSnapshot::of(&CoordinatorState::from_records(log.records()));
+        log.append(CoordinatorEvent::TaskDropped { task_id: tasks[2].id, reason: "cancelled".to_string() });
+        log.append(CoordinatorEvent::TaskFinished { task_id: tasks[1].id, status: TaskStatus::Failed, processing_time_ms: 1 });
+        
+        let restored = snapshot.restore(log.since(snapshot.state.last_sequence));
+        assert_eq!(restored, CoordinatorState::from_records(log.records()));
+        assert_eq!(restored.tasks_failed, 1);
+        assert!(restored.pending.is_empty());
+        
+        // Re-applying already included events is a no-op
+        assert_eq!(snapshot.restore(log.records()), restored);
+        
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(serde_json::from_str::<CoordinatorSnapshot>(&json).unwrap(), snapshot);
+        
+        let compressor = Compressor::new(crate::compression::CompressionConfig::zstd(3));
+        let encoded = snapshot.encode(&compressor).unwrap();
+        assert!(compression::is_compressed(&encoded));
+        assert_eq!(CoordinatorSnapshot::decode(&encoded).unwrap(), snapshot);
+        assert_eq!(CoordinatorSnapshot::decode(json.as_bytes()).unwrap(), snapshot);
+        let log_bytes = log.encode(&compressor).unwrap();
+        assert_eq!(CoordinatorEventLog::decode(&log_bytes).unwrap().records(), log.records());
+    }
+    
+    #[test]
+    fn test_log_truncation_keeps_sequence() {
+        let tasks = [create_test_task("a"), create_test_task("b")];
+        let mut log = create_test_log(&tasks, Uuid::new_v4());
+        
+        log.truncate_through(3);
+        assert_eq!(log.records().first().map(|record| record.sequence), Some(4));
+        assert_eq!(log.since(4).len(), 1);
+        assert_eq!(log.append(CoordinatorEvent::WorkerUnregistered { worker_id: Uuid::new_v4() }).sequence, 6);
+    }
+}
@@ -0,0 +1,196 @@
+//! Document Builder
+//!
+//! Builds `Document` values and assigns their IDs. IDs are either random
+//! (UUIDv4) or deterministic (UUIDv5 over the document source and a hash of
+//! its content), so re-submitting identical content yields the same ID.
+
+use crate::types::{Document, DocumentContent, DocumentType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Namespace for deterministic document IDs
+pub const DOCUMENT_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2a4e_9b7d_4c3a_8e51_d0a9_3f6b_7c21);
+
+/// How document IDs are assigned
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+pub enum DocumentIdStrategy {
+    /// Random UUIDv4 per document
+    #[default]
+    Random,
+    /// UUIDv5 derived from source and content hash
+    Deterministic,
+}
+
+/// SHA-256 hash of raw bytes, hex encoded
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// SHA-256 hash of document content, hex encoded
+pub fn content_hash(content: &DocumentContent) -> String {
+    let mut hasher = Sha256::new();
+    match content {
+        DocumentContent::Text(text) => hasher.update(text.as_bytes()),
+        DocumentContent::Binary(bytes) => hasher.update(bytes),
+        DocumentContent::Reference { storage_id, path, .. } => {
+            hasher.update(storage_id.as_bytes());
+            hasher.update(b"\0");
+            hasher.update(path.as_bytes());
+        }
+        DocumentContent::Stream { source } => hasher.update(source.as_bytes()),
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Deterministic document ID for a source (path or URL) and its content
+pub fn deterministic_document_id(source: &str, content: &DocumentContent) -> Uuid {
+    let name = format!("{}\n{}", source, content_hash(content));
+    Uuid::new_v5(&DOCUMENT_ID_NAMESPACE, name.as_bytes())
+}
+
+/// Builder for `Document`
+#[derive(Debug, Clone)]
+pub struct DocumentBuilder {
+    filename: String,
+    document_type: DocumentType,
+    content: DocumentContent,
+    metadata: HashMap<String, serde_json::Value>,
+    source: Option<String>,
+    id_strategy: DocumentIdStrategy,
+    created_at: Option<DateTime<Utc>>,
+}
+
+impl DocumentBuilder {
+    /// Start building a document with the given filename and content
+    pub fn new(filename: impl Into<String>, content: DocumentContent) -> Self {
+        Self {
+            filename: filename.into(),
+            document_type: DocumentType::Unknown,
+            content,
+            metadata: HashMap::new(),
+            source: None,
+            id_strategy: DocumentIdStrategy::default(),
+            created_at: None,
+        }
+    }
+
+    /// Set the document type
+    pub fn document_type(mut self, document_type: DocumentType) -> Self {
+        self.document_type = document_type;
+        self
+    }
+
+    /// Add a metadata entry
+    pub fn metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.metadata.insert(key.into(), value);
+        self
+    }
+
+    /// Replace all metadata
+    pub fn with_metadata(mut self, metadata: HashMap<String, serde_json::Value>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Set the source (path or URL) used for deterministic IDs
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Set the ID strategy
+    pub fn id_strategy(mut self, id_strategy: DocumentIdStrategy) -> Self {
+        self.id_strategy = id_strategy;
+        self
+    }
+
+    /// Set the creation timestamp (defaults to now)
+    pub fn created_at(mut self, created_at: DateTime<Utc>) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// Build the document
+    pub fn build(self) -> Document {
+        let id = match self.id_strategy {
+            DocumentIdStrategy::Random => Uuid::new_v4(),
+            DocumentIdStrategy::Deterministic => {
+                let source = self.source.as_deref().unwrap_or(&self.filename);
+                deterministic_document_id(source, &self.content)
+            }
+        };
+
+        let size_bytes = match &self.content {
+            DocumentContent::Text(text) => text.len(),
+            DocumentContent::Binary(bytes) => bytes.len(),
+            DocumentContent::Reference { .. } | DocumentContent::Stream { .. } => 0,
+        };
+
+        Document {
+            id,
+            filename: self.filename,
+            document_type: self.document_type,
+            content: self.content,
+            metadata: self.metadata,
+            created_at: self.created_at.unwrap_or_else(Utc::now),
+            size_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_ids_differ() {
+        let a = DocumentBuilder::new("a.txt", DocumentContent::Text("same".to_string())).build();
+        let b = DocumentBuilder::new("a.txt", DocumentContent::Text("same".to_string())).build();
+        assert_ne!(a.id, b.id);
+    }
+
+    #[test]
+    fn test_deterministic_ids_are_stable() {
+        let build = || {
+            DocumentBuilder::new("a.txt", DocumentContent::Text("same".to_string()))
+                .source("/data/a.txt")
+                .id_strategy(DocumentIdStrategy::Deterministic)
+                .build()
+        };
+        assert_eq!(build().id, build().id);
+        assert_eq!(build().id.get_version_num(), 5);
+    }
+
+    #[test]
+    fn test_deterministic_ids_depend_on_source_and_content() {
+        let content = DocumentContent::Text("same".to_string());
+        let id = deterministic_document_id("/data/a.txt", &content);
+
+        assert_ne!(id, deterministic_document_id("/data/b.txt", &content));
+        assert_ne!(id, deterministic_document_id("/data/a.txt", &DocumentContent::Text("other".to_string())));
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let text = content_hash(&DocumentContent::Text("abc".to_string()));
+        let binary = content_hash(&DocumentContent::Binary(b"abc".to_vec()));
+        assert_eq!(text, binary);
+        assert_eq!(text, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+
+    #[test]
+    fn test_builder_fields() {
+        let document = DocumentBuilder::new("report.md", DocumentContent::Text("# Report".to_string()))
+            .document_type(DocumentType::Markdown)
+            .metadata("author", serde_json::json!("Jane"))
+            .build();
+
+        assert_eq!(document.filename, "report.md");
+        assert_eq!(document.document_type, DocumentType::Markdown);
+        assert_eq!(document.size_bytes, 8);
+        assert_eq!(document.metadata.get("author"), Some(&serde_json::json!("Jane")));
+    }
+}
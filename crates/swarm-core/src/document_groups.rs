@@ -0,0 +1,549 @@
+//! Document Groups
+//!
+//! Some business events span several files that must be processed together
+//! or not at all, like an invoice and its attachments. Members of a group
+//! carry the group's ID and size in their metadata. At ingestion a
+//! `GroupAssembler` holds members back until the whole group has arrived and
+//! gives up on groups that stay incomplete. In the coordinator a
+//! `GroupTracker` links the members' tasks: the group result is produced
+//! once every member completed, while the first member to fail for good
+//! fails the group and cancels the members that have not finished. A task
+//! split into several child tasks fans them out as a group of its own; the
+//! group result then names the parent and combines the children's result
+//! metadata, so the split can be joined again.
+
+use crate::coordinator_events::{CoordinatorEvent, EventRecord, Projection};
+use crate::message_headers::{MessageHeaders, CONTENT_TYPE_HEADER};
+use crate::types::{Document, Message, TaskResult, TaskStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Document and task metadata key holding the ID of the group
+pub const DOCUMENT_GROUP_KEY: &str = "document_group";
+
+/// Document and task metadata key holding the number of members of the group
+pub const GROUP_SIZE_KEY: &str = "group_size";
+
+/// Task metadata key holding the task a group was fanned out from
+pub const GROUP_PARENT_KEY: &str = "group_parent";
+
+/// Subject finished groups are announced on
+pub const GROUP_RESULT_SUBJECT: &str = "swarm.groups.finished";
+
+/// Membership of a document or task in a group
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupMembership {
+    pub group_id: String,
+    pub size: usize,
+}
+
+impl GroupMembership {
+    pub fn new(group_id: impl Into<String>, size: usize) -> Self {
+        Self { group_id: group_id.into(), size }
+    }
+    
+    /// Membership recorded in metadata; both the group ID and a non-zero size are required
+    pub fn from_metadata(metadata: &HashMap<String, serde_json::Value>) -> Option<Self> {
+        let group_id = metadata.get(DOCUMENT_GROUP_KEY)?.as_str()?;
+        let size = metadata.get(GROUP_SIZE_KEY)?.as_u64().filter(|size| *size > 0)?;
+        Some(Self::new(group_id, size as usize))
+    }
+    
+    pub fn insert_into(&self, metadata: &mut HashMap<String, serde_json::Value>) {
+        metadata.insert(DOCUMENT_GROUP_KEY.to_string(), serde_json::json!(self.group_id));
+        metadata.insert(GROUP_SIZE_KEY.to_string(), serde_json::json!(self.size));
+    }
+}
+
+/// A submitted group of tasks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskGroup {
+    pub group_id: String,
+    
+    /// Task the group was fanned out from, if any
+    pub parent_task_id: Option<Uuid>,
+    
+    /// Member tasks in submission order
+    pub task_ids: Vec<Uuid>,
+}
+
+impl TaskGroup {
+    pub fn len(&self) -> usize {
+        self.task_ids.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.task_ids.is_empty()
+    }
+    
+    pub fn contains(&self, task_id: Uuid) -> bool {
+        self.task_ids.contains(&task_id)
+    }
+}
+
+/// How far an open group has got
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupProgress {
+    pub group_id: String,
+    pub size: usize,
+    pub submitted: usize,
+    pub completed: usize,
+}
+
+/// A group that did not arrive in full before the assembly timeout
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncompleteGroup {
+    pub membership: GroupMembership,
+    
+    /// Members that did arrive
+    pub documents: Vec<Document>,
+}
+
+/// Holds group members back at ingestion until their group is complete
+#[derive(Debug)]
+pub struct GroupAssembler {
+    timeout: Duration,
+    pending: HashMap<String, PendingGroup>,
+}
+
+#[derive(Debug)]
+struct PendingGroup {
+    size: usize,
+    documents: Vec<Document>,
+    first_seen: DateTime<Utc>,
+}
+
+impl GroupAssembler {
+    /// Assembler giving up on groups still incomplete `timeout` after their first member
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout, pending: HashMap::new() }
+    }
+    
+    /// Add an ingested document, returning the documents now ready for processing
+    ///
+    /// Documents outside a group are returned at once; members only when the
+    /// last member of their group arrives, all together. A member read again
+    /// replaces its earlier version.
+    pub fn add(&mut self, document: Document, now: DateTime<Utc>) -> Vec<Document> {
+        let Some(membership) = GroupMembership::from_metadata(&document.metadata) else {
+            return vec![document];
+        };
+        let group = self.pending.entry(membership.group_id.clone()).or_insert_with(|| PendingGroup {
+            size: membership.size,
+            documents: Vec::new(),
+            first_seen: now,
+        });
+        group.documents.retain(|member| member.filename != document.filename);
+        group.documents.push(document);
+        
+        if group.documents.len() < group.size {
+            return Vec::new();
+        }
+        self.pending.remove(&membership.group_id).map(|group| group.documents).unwrap_or_default()
+    }
+    
+    /// Remove and return the groups that stayed incomplete past the timeout
+    pub fn expire(&mut self, now: DateTime<Utc>) -> Vec<IncompleteGroup> {
+        let timeout = chrono::Duration::from_std(self.timeout).unwrap_or(chrono::Duration::MAX);
+        let mut expired: Vec<String> = self.pending.iter()
+            .filter(|(_, group)| now - group.first_seen > timeout)
+            .map(|(group_id, _)| group_id.clone())
+            .collect();
+        expired.sort();
+        expired.into_iter()
+            .filter_map(|group_id| self.pending.remove(&group_id).map(|group| IncompleteGroup {
+                membership: GroupMembership::new(group_id, group.size),
+                documents: group.documents,
+            }))
+            .collect()
+    }
+    
+    /// Groups waiting for members
+    pub fn pending_groups(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// Outcome of a group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupStatus {
+    /// Every member completed
+    Completed,
+    /// A member failed or was cancelled
+    Failed,
+}
+
+/// Final status of a member task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupMember {
+    pub task_id: Uuid,
+    
+    /// `Cancelled` for members that had not finished when the group failed
+    pub status: TaskStatus,
+    pub error: Option<String>,
+}
+
+/// A finished group, published on `GROUP_RESULT_SUBJECT`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupResult {
+    pub group_id: String,
+    pub status: GroupStatus,
+    
+    /// Members in submission order
+    pub members: Vec<GroupMember>,
+    
+    /// Results of the completed members this coordinator received
+    pub results: Vec<TaskResult>,
+    
+    /// Task the group was fanned out from, if any
+    #[serde(default)]
+    pub parent_task_id: Option<Uuid>,
+    
+    /// Result metadata of the completed members, see `combine_metadata`
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+    
+    /// Processing time of the completed members added up
+    #[serde(default)]
+    pub processing_time_ms: u64,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// Combine the metadata of several results
+///
+/// A key every result agrees on keeps its value; a key with differing values
+/// holds them all as an array, in result order.
+pub fn combine_metadata(results: &[TaskResult]) -> HashMap<String, serde_json::Value> {
+    let mut values: HashMap<String, Vec<serde_json::Value>> = HashMap::new();
+    for result in results {
+        for (key, value) in &result.metadata {
+            values.entry(key.clone()).or_default().push(value.clone());
+        }
+    }
+    values.into_iter()
+        .map(|(key, mut values)| {
+            let combined = if values.iter().all(|value| *value == values[0]) {
+                values.swap_remove(0)
+            } else {
+                serde_json::Value::Array(values)
+            };
+            (key, combined)
+        })
+        .collect()
+}
+
+impl GroupResult {
+    /// Members cancelled because the group failed
+    pub fn cancelled_members(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.members.iter()
+            .filter(|member| member.status == TaskStatus::Cancelled)
+            .map(|member| member.task_id)
+    }
+    
+    /// The result as a message on `GROUP_RESULT_SUBJECT`
+    pub fn to_message(&self) -> anyhow::Result<Message> {
+        let mut headers = MessageHeaders::new();
+        headers.insert(CONTENT_TYPE_HEADER, "application/json");
+        Ok(Message {
+            id: Uuid::new_v4(),
+            subject: GROUP_RESULT_SUBJECT.to_string(),
+            payload: serde_json::to_vec(self)?,
+            headers,
+            timestamp: self.finished_at,
+            ttl_ms: None,
+        })
+    }
+}
+
+/// Links the tasks of each group and decides when the group is finished
+///
+/// Membership and outcomes are folded from coordinator events; results of
+/// member tasks are kept through `record_result`.
+#[derive(Debug, Default)]
+pub struct GroupTracker {
+    groups: HashMap<String, TrackedGroup>,
+    group_of: HashMap<Uuid, String>,
+    finished: Vec<GroupResult>,
+}
+
+#[derive(Debug)]
+struct TrackedGroup {
+    size: usize,
+    parent_task_id: Option<Uuid>,
+    members: Vec<GroupMember>,
+    results: HashMap<Uuid, TaskResult>,
+}
+
+impl GroupTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Group a task belongs to, while the group is open
+    pub fn group_of(&self, task_id: Uuid) -> Option<&str> {
+        self.group_of.get(&task_id).map(String::as_str)
+    }
+    
+    /// Groups with members still running
+    pub fn open_groups(&self) -> usize {
+        self.groups.len()
+    }
+    
+    /// Progress of an open group
+    pub fn progress(&self, group_id: &str) -> Option<GroupProgress> {
+        let group = self.groups.get(group_id)?;
+        Some(GroupProgress {
+            group_id: group_id.to_string(),
+            size: group.size,
+            submitted: group.members.len(),
+            completed: group.members.iter().filter(|member| member.status == TaskStatus::Completed).count(),
+        })
+    }
+    
+    /// Keep the result of a member task for its group result
+    pub fn record_result(&mut self, result: &TaskResult) {
+        let Some(group) = self.group_of.get(&result.task_id).and_then(|group_id| self.groups.get_mut(group_id)) else {
+            return;
+        };
+        if let Some(member) = group.members.iter_mut().find(|member| member.task_id == result.task_id) {
+            member.error = result.error.clone();
+        }
+        group.results.insert(result.task_id, result.clone());
+    }
+    
+    /// Groups finished since the last call
+    pub fn take_finished(&mut self) -> Vec<GroupResult> {
+        std::mem::take(&mut self.finished)
+    }
+    
+    fn finish_member(&mut self, task_id: Uuid, status: TaskStatus) {
+        let Some(group_id) = self.group_of.remove(&task_id) else { return };
+        let Some(group) = self.groups.get_mut(&group_id) else { return };
+        if let Some(member) = group.members.iter_mut().find(|member| member.task_id == task_id) {
+            member.status = status.clone();
+        }
+        
+        if status != TaskStatus::Completed {
+            self.finish_group(&group_id, GroupStatus::Failed);
+        } else if group.members.len() >= group.size && group.members.iter().all(|member| member.status == TaskStatus::Completed) {
+            self.finish_group(&group_id, GroupStatus::Completed);
+        }
+    }
+    
+    fn finish_group(&mut self, group_id: &str, status: GroupStatus) {
+        let Some(mut group) = self.groups.remove(group_id) else { return };
+        for member in &mut group.members {
+            if self.group_of.remove(&member.task_id).is_some() {
+                member.status = TaskStatus::Cancelled;
+            }
+        }
+        let results: Vec<TaskResult> = group.members.iter()
+            .filter_map(|member| group.results.remove(&member.task_id))
+            .filter(|result| result.status == TaskStatus::Completed)
+            .collect();
+        self.finished.push(GroupResult {
+            group_id: group_id.to_string(),
+            status,
+            members: group.members,
+            parent_task_id: group.parent_task_id,
+            metadata: combine_metadata(&results),
+            processing_time_ms: results.iter().map(|result| result.processing_time_ms).sum(),
+            results,
+            finished_at: Utc::now(),
+        });
+    }
+}
+
+impl Projection for GroupTracker {
+    fn apply(&mut self, record: &EventRecord) {
+        match &record.event {
+            CoordinatorEvent::TaskSubmitted { task } => {
+                let Some(membership) = GroupMembership::from_metadata(&task.metadata) else { return };
+                let group = self.groups.entry(membership.group_id.clone()).or_insert_with(|| TrackedGroup {
+                    size: membership.size,
+                    parent_task_id: task.metadata.get(GROUP_PARENT_KEY)
+                        .and_then(|parent| parent.as_str())
+                        .and_then(|parent| Uuid::parse_str(parent).ok()),
+                    members: Vec::new(),
+                    results: HashMap::new(),
+                });
+                group.members.push(GroupMember { task_id: task.id, status: TaskStatus::Pending, error: None });
+                self.group_of.insert(task.id, membership.group_id);
+            }
+            CoordinatorEvent::TaskFinished { task_id, status, .. } => self.finish_member(*task_id, status.clone()),
+            CoordinatorEvent::TaskDropped { task_id, .. } => self.finish_member(*task_id, TaskStatus::Cancelled),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinator_events::CoordinatorEventLog;
+    use crate::document_builder::DocumentBuilder;
+    use crate::types::{DocumentContent, Task, TaskPayload, TaskPriority, TaskType};
+    
+    fn member(filename: &str, membership: &GroupMembership) -> Document {
+        let mut document = DocumentBuilder::new(filename, DocumentContent::Text(String::new())).build();
+        membership.insert_into(&mut document.metadata);
+        document
+    }
+    
+    fn task(membership: &GroupMembership) -> Task {
+        let mut metadata = HashMap::new();
+        membership.insert_into(&mut metadata);
+        Task {
+            id: Uuid::new_v4(),
+            task_type: TaskType::Custom { name: "test".to_string(), version: "1".to_string() },
+            priority: TaskPriority::Normal,
+            status: TaskStatus::Pending,
+            payload: TaskPayload::Custom { data: Vec::new(), format: "raw".to_string() },
+            created_at: Utc::now(),
+            deadline: None,
+            retry_count: 0,
+            max_retries: 0,
+            metadata,
+        }
+    }
+    
+    fn completed(task_id: Uuid) -> TaskResult {
+        TaskResult {
+            task_id,
+            status: TaskStatus::Completed,
+            result: None,
+            error: None,
+            processing_time_ms: 1,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        }
+    }
+    
+    fn apply(tracker: &mut GroupTracker, log: &mut CoordinatorEventLog, event: CoordinatorEvent) {
+        let record = log.append(event).clone();
+        tracker.apply(&record);
+    }
+    
+    #[test]
+    fn test_members_are_released_together() {
+        let invoice = GroupMembership::new("invoice-17", 2);
+        let mut assembler = GroupAssembler::new(Duration::from_secs(60));
+        let now = Utc::now();
+        
+        let loose = DocumentBuilder::new("memo.txt", DocumentContent::Text(String::new())).build();
+        assert_eq!(assembler.add(loose, now).len(), 1);
+        
+        assert!(assembler.add(member("invoice.pdf", &invoice), now).is_empty());
+        assert!(assembler.add(member("invoice.pdf", &invoice), now).is_empty());
+        assert_eq!(assembler.pending_groups(), 1);
+        let released = assembler.add(member("receipt.png", &invoice), now);
+        assert_eq!(released.iter().map(|document| document.filename.as_str()).collect::<Vec<_>>(), vec!["invoice.pdf", "receipt.png"]);
+        assert_eq!(assembler.pending_groups(), 0);
+    }
+    
+    #[test]
+    fn test_incomplete_groups_expire() {
+        let mut assembler = GroupAssembler::new(Duration::from_secs(60));
+        let now = Utc::now();
+        assembler.add(member("invoice.pdf", &GroupMembership::new("invoice-17", 3)), now);
+        
+        assert!(assembler.expire(now + chrono::Duration::seconds(30)).is_empty());
+        let expired = assembler.expire(now + chrono::Duration::seconds(61));
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].membership, GroupMembership::new("invoice-17", 3));
+        assert_eq!(expired[0].documents.len(), 1);
+        assert_eq!(assembler.pending_groups(), 0);
+    }
+    
+    #[test]
+    fn test_group_completes_when_every_member_completes() {
+        let membership = GroupMembership::new("invoice-17", 2);
+        let (first, second) = (task(&membership), task(&membership));
+        let mut tracker = GroupTracker::new();
+        let mut log = CoordinatorEventLog::new();
+        apply(&mut tracker, &mut log, CoordinatorEvent::TaskSubmitted { task: first.clone() });
+        apply(&mut tracker, &mut log, CoordinatorEvent::TaskSubmitted { task: second.clone() });
+        
+        tracker.record_result(&completed(first.id));
+        apply(&mut tracker, &mut log, CoordinatorEvent::task_finished(&completed(first.id)));
+        assert!(tracker.take_finished().is_empty());
+        
+        tracker.record_result(&completed(second.id));
+        apply(&mut tracker, &mut log, CoordinatorEvent::task_finished(&completed(second.id)));
+        let finished = tracker.take_finished();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].status, GroupStatus::Completed);
+        assert_eq!(finished[0].results.len(), 2);
+        assert_eq!(tracker.open_groups(), 0);
+        
+        let message = finished[0].to_message().unwrap();
+        assert_eq!(message.subject, GROUP_RESULT_SUBJECT);
+        assert_eq!(serde_json::from_slice::<GroupResult>(&message.payload).unwrap(), finished[0]);
+    }
+    
+    #[test]
+    fn test_fanned_out_results_are_combined() {
+        let membership = GroupMembership::new("chunks", 2);
+        let parent = Uuid::new_v4();
+        let chunks: Vec<Task> = (0..2).map(|_| {
+            let mut chunk = task(&membership);
+            chunk.metadata.insert(GROUP_PARENT_KEY.to_string(), serde_json::json!(parent.to_string()));
+            chunk
+        }).collect();
+        let mut tracker = GroupTracker::new();
+        let mut log = CoordinatorEventLog::new();
+        for chunk in &chunks {
+            apply(&mut tracker, &mut log, CoordinatorEvent::TaskSubmitted { task: chunk.clone() });
+        }
+        
+        for (index, chunk) in chunks.iter().enumerate() {
+            let mut result = completed(chunk.id);
+            result.metadata.insert("language".to_string(), serde_json::json!("en"));
+            result.metadata.insert("chunk".to_string(), serde_json::json!(index));
+            tracker.record_result(&result);
+            apply(&mut tracker, &mut log, CoordinatorEvent::task_finished(&result));
+            if index == 0 {
+                assert_eq!(tracker.progress("chunks").unwrap(), GroupProgress { group_id: "chunks".to_string(), size: 2, submitted: 2, completed: 1 });
+            }
+        }
+        
+        let result = tracker.take_finished().remove(0);
+        assert_eq!(result.parent_task_id, Some(parent));
+        assert_eq!(result.processing_time_ms, 2);
+        assert_eq!(result.metadata["language"], "en");
+        assert_eq!(result.metadata["chunk"], serde_json::json!([0, 1]));
+        assert!(tracker.progress("chunks").is_none());
+    }
+    
+    #[test]
+    fn test_failed_member_fails_the_group() {
+        let membership = GroupMembership::new("invoice-17", 3);
+        let tasks: Vec<Task> = (0..3).map(|_| task(&membership)).collect();
+        let mut tracker = GroupTracker::new();
+        let mut log = CoordinatorEventLog::new();
+        for task in &tasks {
+            apply(&mut tracker, &mut log, CoordinatorEvent::TaskSubmitted { task: task.clone() });
+        }
+        apply(&mut tracker, &mut log, CoordinatorEvent::task_finished(&completed(tasks[0].id)));
+        
+        let failed = TaskResult { status: TaskStatus::Failed, error: Some("unreadable".to_string()), ..completed(tasks[1].id) };
+        tracker.record_result(&failed);
+        apply(&mut tracker, &mut log, CoordinatorEvent::task_finished(&failed));
+        
+        let result = tracker.take_finished().remove(0);
+        assert_eq!(result.status, GroupStatus::Failed);
+        let statuses: Vec<TaskStatus> = result.members.iter().map(|member| member.status.clone()).collect();
+        assert_eq!(statuses, vec![TaskStatus::Completed, TaskStatus::Failed, TaskStatus::Cancelled]);
+        assert_eq!(result.members[1].error.as_deref(), Some("unreadable"));
+        assert_eq!(result.cancelled_members().collect::<Vec<_>>(), vec![tasks[2].id]);
+        
+        // The cancelled member finishing later does not reopen the group
+        apply(&mut tracker, &mut log, CoordinatorEvent::task_finished(&completed(tasks[2].id)));
+        assert!(tracker.take_finished().is_empty());
+        assert_eq!(tracker.group_of(tasks[2].id), None);
+    }
+}
@@ -3,8 +3,12 @@
 //! Reads documents from directories and publishes them to NATS for processing.
 //! This is the entry point of the document processing pipeline.
 
+use crate::document_builder::{DocumentBuilder, DocumentIdStrategy};
 use crate::document_worker::{Document, DocumentType};
+use crate::service::{cancellable_sleep, CancellationToken, Service};
+use crate::types::DocumentContent;
 use anyhow::Result;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -21,6 +25,9 @@ pub struct DocumentReaderConfig {
     pub batch_size: usize,
     pub scan_interval_ms: u64,
     pub nats_subject: String,
+    /// How document IDs are assigned (deterministic IDs derive from path and content)
+    #[serde(default)]
+    pub id_strategy: DocumentIdStrategy,
 }
 
 impl Default for DocumentReaderConfig {
@@ -37,6 +44,7 @@ impl Default for DocumentReaderConfig {
             batch_size: 10,
             scan_interval_ms: 1000,
             nats_subject: "swarm.documents.incoming".to_string(),
+            id_strategy: DocumentIdStrategy::Random,
         }
     }
 }
@@ -57,6 +65,11 @@ impl DocumentReader {
 
     /// Start the document reader - scans directories and publishes documents
     pub async fn start(&mut self) -> Result<()> {
+        self.run(CancellationToken::new()).await
+    }
+
+    /// Scan every `scan_interval_ms` until cancelled
+    pub async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
         println!("📁 Document Reader starting...");
         println!("📂 Watching directories: {:?}", self.config.watch_directories);
         println!("📋 Supported extensions: {:?}", self.config.supported_extensions);
@@ -65,10 +78,12 @@ impl DocumentReader {
         println!("📡 NATS subject: {}", self.config.nats_subject);
         println!();
 
-        loop {
+        while !cancel.is_cancelled() {
             self.scan_directories().await?;
-            sleep(Duration::from_millis(self.config.scan_interval_ms)).await;
+            cancellable_sleep(&cancel, Duration::from_millis(self.config.scan_interval_ms)).await;
         }
+        println!("📁 Document Reader stopped");
+        Ok(())
     }
 
     /// Scan all configured directories for new documents
@@ -92,18 +107,14 @@ impl DocumentReader {
         while let Some(entry) = entries.next_entry().await? {
             let path = entry.path();
             
-            if path.is_file() {
-                if self.is_supported_file(&path) {
-                    if self.is_new_or_modified_file(&path).await? {
-                        match self.read_document(&path).await {
-                            Ok(document) => {
-                                documents.push(document);
-                                println!("📄 Found document: {}", path.display());
-                            }
-                            Err(e) => {
-                                println!("❌ Failed to read document {}: {}", path.display(), e);
-                            }
-                        }
+            if path.is_file() && self.is_supported_file(&path) && self.is_new_or_modified_file(&path).await? {
+                match self.read_document(&path).await {
+                    Ok(document) => {
+                        documents.push(document);
+                        println!("📄 Found document: {}", path.display());
+                    }
+                    Err(e) => {
+                        println!("❌ Failed to read document {}: {}", path.display(), e);
                     }
                 }
             }
@@ -156,10 +167,6 @@ impl DocumentReader {
         let modified_time = metadata.modified()?;
         let modified_datetime: DateTime<Utc> = modified_time.into();
 
-        // Create document
-        let mut document = Document::new(filename, content);
-        document.size_bytes = size_bytes;
-
         // Add file system metadata
         let mut file_metadata = HashMap::new();
         file_metadata.insert("file_path".to_string(), json!(path.to_string_lossy()));
@@ -168,8 +175,15 @@ impl DocumentReader {
         file_metadata.insert("file_extension".to_string(), json!(
             path.extension().and_then(|ext| ext.to_str()).unwrap_or("unknown")
         ));
-        
-        document = document.with_metadata(file_metadata);
+
+        // Create document, with its ID assigned like every other reader's
+        let mut document: Document = DocumentBuilder::new(filename, DocumentContent::Text(content))
+            .source(path.to_string_lossy())
+            .id_strategy(self.config.id_strategy)
+            .with_metadata(file_metadata)
+            .build()
+            .into();
+        document.size_bytes = size_bytes;
 
         // Update processed files tracking
         self.processed_files.insert(path.to_path_buf(), modified_datetime);
@@ -292,6 +306,17 @@ impl DocumentReader {
     }
 }
 
+#[async_trait]
+impl Service for DocumentReader {
+    fn name(&self) -> &str {
+        "document-reader"
+    }
+
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        DocumentReader::run(self, cancel).await
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessingTask {
     pub id: Uuid,
@@ -350,8 +375,10 @@ mod tests {
         let test_file = temp_dir.path().join("test.txt");
         fs::write(&test_file, "This is a test document").unwrap();
 
-        let mut config = DocumentReaderConfig::default();
-        config.watch_directories = vec![temp_dir.path().to_path_buf()];
+        let config = DocumentReaderConfig {
+            watch_directories: vec![temp_dir.path().to_path_buf()],
+            ..Default::default()
+        };
         
         let mut reader = DocumentReader::new(config);
         
@@ -363,6 +390,23 @@ mod tests {
         assert!(document.metadata.contains_key("file_size"));
     }
 
+    #[tokio::test]
+    async fn test_deterministic_ids_survive_rereading() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("test.txt");
+        fs::write(&test_file, "This is a test document").unwrap();
+        let config = DocumentReaderConfig {
+            id_strategy: DocumentIdStrategy::Deterministic,
+            ..Default::default()
+        };
+        
+        let first = DocumentReader::new(config.clone()).read_document(&test_file).await.unwrap();
+        let second = DocumentReader::new(config).read_document(&test_file).await.unwrap();
+        assert_eq!(first.id, second.id);
+        assert_eq!(first.id.get_version_num(), 5);
+        assert_eq!(first.size_bytes, 23);
+    }
+
     #[tokio::test]
     async fn test_task_generation() {
         let config = DocumentReaderConfig::default();
@@ -382,4 +426,26 @@ mod tests {
         // PDF should have page counting
         assert!(task_types.contains(&&"page_counting".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_run_stops_when_cancelled() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "Some notes").unwrap();
+        let mut reader = DocumentReader::new(DocumentReaderConfig {
+            watch_directories: vec![temp_dir.path().to_path_buf()],
+            scan_interval_ms: 60_000,
+            ..Default::default()
+        });
+
+        let cancel = CancellationToken::new();
+        let token = cancel.clone();
+        let run = tokio::spawn(async move {
+            reader.run(token).await.map(|_| reader)
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancel.cancel();
+
+        let reader = tokio::time::timeout(Duration::from_secs(1), run).await.unwrap().unwrap().unwrap();
+        assert_eq!(reader.get_stats().total_files_processed, 1);
+    }
 }
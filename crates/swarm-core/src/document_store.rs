@@ -0,0 +1,320 @@
+//! Claim-Check Document Storage
+//!
+//! Brokers cap the size of a message, so a document whose message would be
+//! larger cannot be published at all. A `ClaimCheck` uploads the content of
+//! such a document to a shared `DocumentStore` and replaces it with a
+//! checksummed `DocumentContent::Reference`; consumers check the content back
+//! out of the same store before processing. Stores can also be listed, so
+//! documents dropped into one can be picked up as they arrive.
+
+use crate::document_builder::sha256_hex;
+use crate::types::{Document, DocumentContent};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Document metadata key marking checked-in content and its original form
+pub const CLAIM_CHECK_KEY: &str = "claim_check";
+
+/// An object found by listing a store
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StoredObject {
+    pub path: String,
+    pub size_bytes: u64,
+    pub last_modified: Option<DateTime<Utc>>,
+    
+    /// Entity tag the store reports, if it has one
+    pub etag: Option<String>,
+}
+
+/// Shared storage that oversized document content is offloaded to
+#[async_trait]
+pub trait DocumentStore: Send + Sync {
+    /// Storage ID recorded in references to this store
+    fn storage_id(&self) -> &str;
+    
+    async fn put(&self, path: &str, bytes: Vec<u8>) -> Result<()>;
+    
+    async fn get(&self, path: &str) -> Result<Vec<u8>>;
+    
+    /// Remove an object; removing a missing object is not an error
+    async fn delete(&self, path: &str) -> Result<()>;
+    
+    /// Up to `limit` objects whose path starts with `prefix`, ordered by path,
+    /// starting after the path `start_after` if given
+    async fn list(&self, prefix: &str, start_after: Option<&str>, limit: usize) -> Result<Vec<StoredObject>>;
+}
+
+/// Order listed objects by path and keep those of a page
+fn page(mut objects: Vec<StoredObject>, prefix: &str, start_after: Option<&str>, limit: usize) -> Vec<StoredObject> {
+    objects.retain(|object| object.path.starts_with(prefix) && start_after.is_none_or(|after| object.path.as_str() > after));
+    objects.sort_by(|a, b| a.path.cmp(&b.path));
+    objects.truncate(limit);
+    objects
+}
+
+/// Document store in a directory on local (or shared network) disk
+#[derive(Debug, Clone)]
+pub struct LocalDiskStore {
+    storage_id: String,
+    root: PathBuf,
+}
+
+impl LocalDiskStore {
+    pub fn new(storage_id: impl Into<String>, root: impl Into<PathBuf>) -> Self {
+        Self { storage_id: storage_id.into(), root: root.into() }
+    }
+    
+    /// Location of a store path, which must stay below the root
+    fn full_path(&self, path: &str) -> Result<PathBuf> {
+        let relative = Path::new(path);
+        if !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+            anyhow::bail!("invalid store path: {}", path);
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl DocumentStore for LocalDiskStore {
+    fn storage_id(&self) -> &str {
+        &self.storage_id
+    }
+    
+    async fn put(&self, path: &str, bytes: Vec<u8>) -> Result<()> {
+        let full_path = self.full_path(path)?;
+        if let Some(parent) = full_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Readers never see a partially written object
+        let partial = full_path.with_extension("partial");
+        tokio::fs::write(&partial, bytes).await?;
+        tokio::fs::rename(&partial, &full_path).await?;
+        Ok(())
+    }
+    
+    async fn get(&self, path: &str) -> Result<Vec<u8>> {
+        Ok(tokio::fs::read(self.full_path(path)?).await?)
+    }
+    
+    async fn delete(&self, path: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.full_path(path)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+    
+    async fn list(&self, prefix: &str, start_after: Option<&str>, limit: usize) -> Result<Vec<StoredObject>> {
+        let mut objects = Vec::new();
+        let mut directories = vec![(self.root.clone(), String::new())];
+        while let Some((directory, relative)) = directories.pop() {
+            let mut entries = match tokio::fs::read_dir(&directory).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let path = if relative.is_empty() { name } else { format!("{}/{}", relative, name) };
+                let metadata = entry.metadata().await?;
+                if metadata.is_dir() {
+                    directories.push((entry.path(), path));
+                } else if !path.ends_with(".partial") {
+                    objects.push(StoredObject {
+                        path,
+                        size_bytes: metadata.len(),
+                        last_modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+                        etag: None,
+                    });
+                }
+            }
+        }
+        Ok(page(objects, prefix, start_after, limit))
+    }
+}
+
+/// Document store held in memory, shared between clones
+#[derive(Debug, Clone)]
+pub struct MemoryDocumentStore {
+    storage_id: String,
+    objects: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+}
+
+impl MemoryDocumentStore {
+    pub fn new(storage_id: impl Into<String>) -> Self {
+        Self { storage_id: storage_id.into(), objects: Arc::default() }
+    }
+}
+
+#[async_trait]
+impl DocumentStore for MemoryDocumentStore {
+    fn storage_id(&self) -> &str {
+        &self.storage_id
+    }
+    
+    async fn put(&self, path: &str, bytes: Vec<u8>) -> Result<()> {
+        self.objects.lock().expect("document store lock poisoned").insert(path.to_string(), bytes);
+        Ok(())
+    }
+    
+    async fn get(&self, path: &str) -> Result<Vec<u8>> {
+        self.objects.lock().expect("document store lock poisoned")
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no document stored at {}", path))
+    }
+    
+    async fn delete(&self, path: &str) -> Result<()> {
+        self.objects.lock().expect("document store lock poisoned").remove(path);
+        Ok(())
+    }
+    
+    async fn list(&self, prefix: &str, start_after: Option<&str>, limit: usize) -> Result<Vec<StoredObject>> {
+        let objects = self.objects.lock().expect("document store lock poisoned").iter()
+            .map(|(path, bytes)| StoredObject { path: path.clone(), size_bytes: bytes.len() as u64, last_modified: None, etag: None })
+            .collect();
+        Ok(page(objects, prefix, start_after, limit))
+    }
+}
+
+/// Offloads document content to a store and fetches it back
+#[derive(Clone)]
+pub struct ClaimCheck {
+    store: Arc<dyn DocumentStore>,
+}
+
+impl ClaimCheck {
+    pub fn new(store: Arc<dyn DocumentStore>) -> Self {
+        Self { store }
+    }
+    
+    pub fn store(&self) -> &Arc<dyn DocumentStore> {
+        &self.store
+    }
+    
+    /// Upload the document's content and reference it instead
+    ///
+    /// Content is stored under its checksum, so re-sending a document does
+    /// not store it twice. References and streams are returned unchanged.
+    pub async fn check_in(&self, document: &Document) -> Result<Document> {
+        let (bytes, form) = match &document.content {
+            DocumentContent::Text(text) => (text.as_bytes().to_vec(), "text"),
+            DocumentContent::Binary(bytes) => (bytes.clone(), "binary"),
+            DocumentContent::Reference { .. } | DocumentContent::Stream { .. } => return Ok(document.clone()),
+        };
+        
+        let digest = sha256_hex(&bytes);
+        let path = format!("documents/{}", digest);
+        self.store.put(&path, bytes).await?;
+        
+        let mut metadata = document.metadata.clone();
+        metadata.insert(CLAIM_CHECK_KEY.to_string(), serde_json::json!({ "content": form }));
+        Ok(Document {
+            content: DocumentContent::Reference {
+                storage_id: self.store.storage_id().to_string(),
+                path,
+                access_token: None,
+                checksum: Some(format!("sha256:{}", digest)),
+                etag: None,
+            },
+            metadata,
+            ..document.clone()
+        })
+    }
+    
+    /// Fetch checked-in content back into the document, verifying its checksum
+    ///
+    /// Documents that were not checked in to this store are returned unchanged.
+    pub async fn check_out(&self, document: Document) -> Result<Document> {
+        let DocumentContent::Reference { storage_id, path, checksum, .. } = &document.content else {
+            return Ok(document);
+        };
+        let Some(form) = document.metadata.get(CLAIM_CHECK_KEY).and_then(|check| check["content"].as_str()) else {
+            return Ok(document);
+        };
+        if storage_id != self.store.storage_id() {
+            return Ok(document);
+        }
+        
+        let bytes = self.store.get(path).await?;
+        if let Some(expected) = checksum {
+            let actual = format!("sha256:{}", sha256_hex(&bytes));
+            if &actual != expected {
+                anyhow::bail!("document at {}/{} changed: checksum {} (expected {})", storage_id, path, actual, expected);
+            }
+        }
+        let content = match form {
+            "text" => DocumentContent::Text(String::from_utf8(bytes)?),
+            _ => DocumentContent::Binary(bytes),
+        };
+        
+        let mut document = document;
+        document.metadata.remove(CLAIM_CHECK_KEY);
+        Ok(Document { content, ..document })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DocumentType;
+    use chrono::Utc;
+    use uuid::Uuid;
+    
+    fn create_test_document(content: DocumentContent) -> Document {
+        Document {
+            id: Uuid::new_v4(),
+            filename: "scan.pdf".to_string(),
+            document_type: DocumentType::Pdf,
+            content,
+            metadata: HashMap::from([("tenant".to_string(), serde_json::json!("acme"))]),
+            created_at: Utc::now(),
+            size_bytes: 4,
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_check_in_and_out_on_local_disk() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let claim_check = ClaimCheck::new(Arc::new(LocalDiskStore::new("shared", temp_dir.path())));
+        
+        for content in [DocumentContent::Binary(vec![0, 159, 146, 150]), DocumentContent::Text("résumé".to_string())] {
+            let document = create_test_document(content);
+            let checked_in = claim_check.check_in(&document).await.unwrap();
+            let DocumentContent::Reference { storage_id, path, .. } = &checked_in.content else {
+                panic!("expected a reference");
+            };
+            assert_eq!(storage_id, "shared");
+            assert!(temp_dir.path().join(path).exists());
+            assert_eq!(claim_check.check_out(checked_in).await.unwrap(), document);
+        }
+        
+        let store = LocalDiskStore::new("shared", temp_dir.path());
+        assert!(store.put("../outside", vec![1]).await.is_err());
+        store.put("a/b", vec![1]).await.unwrap();
+        store.put("a/c", vec![1, 2]).await.unwrap();
+        let listed = store.list("a/", Some("a/b"), 10).await.unwrap();
+        assert_eq!(listed.iter().map(|object| (object.path.as_str(), object.size_bytes)).collect::<Vec<_>>(), vec![("a/c", 2)]);
+        assert_eq!(store.list("a/", None, 1).await.unwrap()[0].path, "a/b");
+        store.delete("a/b").await.unwrap();
+        store.delete("a/b").await.unwrap();
+        assert!(store.get("a/b").await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_check_out_detects_changed_content_and_ignores_other_stores() {
+        let store = Arc::new(MemoryDocumentStore::new("shared"));
+        let claim_check = ClaimCheck::new(store.clone());
+        let checked_in = claim_check.check_in(&create_test_document(DocumentContent::Binary(vec![1, 2, 3, 4]))).await.unwrap();
+        
+        let other = ClaimCheck::new(Arc::new(MemoryDocumentStore::new("other")));
+        assert_eq!(other.check_out(checked_in.clone()).await.unwrap(), checked_in);
+        
+        let DocumentContent::Reference { path, .. } = &checked_in.content else { unreachable!() };
+        store.put(path, vec![9]).await.unwrap();
+        assert!(claim_check.check_out(checked_in).await.unwrap_err().to_string().contains("changed"));
+    }
+}
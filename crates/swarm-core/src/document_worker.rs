@@ -23,7 +23,7 @@ impl DocumentType {
     pub fn from_filename(filename: &str) -> Self {
         let extension = filename
             .split('.')
-            .last()
+            .next_back()
             .unwrap_or("")
             .to_lowercase();
         
@@ -95,6 +95,26 @@ impl Document {
     }
 }
 
+/// Narrow a built document to this text-only form; binary content is read lossily
+impl From<crate::types::Document> for Document {
+    fn from(document: crate::types::Document) -> Self {
+        let content = match document.content {
+            crate::types::DocumentContent::Text(text) => text,
+            crate::types::DocumentContent::Binary(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            crate::types::DocumentContent::Reference { .. } | crate::types::DocumentContent::Stream { .. } => String::new(),
+        };
+        Self {
+            id: document.id,
+            document_type: DocumentType::from_filename(&document.filename),
+            filename: document.filename,
+            size_bytes: content.len(),
+            content,
+            metadata: document.metadata,
+            created_at: document.created_at,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentProcessingResult {
     pub document_id: Uuid,
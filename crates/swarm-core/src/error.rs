@@ -16,6 +16,15 @@ pub enum SwarmError {
     #[error("Coordination error: {0}")]
     Coordination(String),
 
+    #[error("Task queue full: {pending} tasks pending (capacity {capacity})")]
+    QueueFull { pending: usize, capacity: usize },
+
+    #[error("Schedule error: {0}")]
+    Schedule(String),
+
+    #[error("Workflow error: {0}")]
+    Workflow(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -0,0 +1,222 @@
+//! Queue Fairness and Starvation Detection
+//!
+//! Tracks how long tasks wait in the coordinator queue, per priority and per
+//! task type. Tasks waiting longer than their priority's threshold are
+//! reported once as starved, and dispatched waits feed a p95 fairness report.
+
+use crate::types::{FairnessReport, Task, TaskPriority};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use uuid::Uuid;
+
+/// Starvation detection configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StarvationConfig {
+    /// Maximum acceptable queue wait per priority before a task counts as starved
+    pub max_wait_ms_by_priority: HashMap<TaskPriority, u64>,
+    
+    /// Number of recent dispatch waits kept per priority for percentiles
+    pub sample_window: usize,
+}
+
+impl Default for StarvationConfig {
+    fn default() -> Self {
+        Self {
+            max_wait_ms_by_priority: HashMap::from([
+                (TaskPriority::Critical, 1_000),
+                (TaskPriority::High, 5_000),
+                (TaskPriority::Normal, 30_000),
+                (TaskPriority::Low, 120_000),
+            ]),
+            sample_window: 1000,
+        }
+    }
+}
+
+/// A queued task that has exceeded its wait threshold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StarvationWarning {
+    pub task_id: Uuid,
+    pub priority: TaskPriority,
+    pub task_type: String,
+    pub waited_ms: u64,
+    pub threshold_ms: u64,
+}
+
+/// Tracks queue waits and detects starving tasks
+#[derive(Debug, Clone, Default)]
+pub struct StarvationDetector {
+    config: StarvationConfig,
+    dispatch_waits: HashMap<TaskPriority, VecDeque<u64>>,
+    max_wait_by_priority: HashMap<TaskPriority, u64>,
+    max_wait_by_task_type: HashMap<String, u64>,
+    warned: HashSet<Uuid>,
+    starved_tasks: usize,
+}
+
+impl StarvationDetector {
+    pub fn new(config: StarvationConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+    
+    /// Record that a task left the queue
+    pub fn record_dispatch(&mut self, task: &Task, now: DateTime<Utc>) {
+        let waited_ms = wait_ms(task, now);
+        self.observe_wait(task, waited_ms);
+        self.warned.remove(&task.id);
+        
+        let window = self.dispatch_waits.entry(task.priority.clone()).or_default();
+        window.push_back(waited_ms);
+        while window.len() > self.config.sample_window.max(1) {
+            window.pop_front();
+        }
+    }
+    
+    /// Inspect queued tasks, logging and returning newly starved ones
+    pub fn check_queue<'a>(&mut self, pending: impl IntoIterator<Item = &'a Task>, now: DateTime<Utc>) -> Vec<StarvationWarning> {
+        let mut warnings = Vec::new();
+        let mut starved_tasks = 0;
+        
+        for task in pending {
+            let waited_ms = wait_ms(task, now);
+            self.observe_wait(task, waited_ms);
+            
+            let Some(&threshold_ms) = self.config.max_wait_ms_by_priority.get(&task.priority) else {
+                continue;
+            };
+            if waited_ms <= threshold_ms {
+                continue;
+            }
+            
+            starved_tasks += 1;
+            if self.warned.insert(task.id) {
+                tracing::warn!("Task {} ({}, {}) has waited {}ms in queue (threshold {}ms)",
+                    task.id, task.priority, task.task_type, waited_ms, threshold_ms);
+                warnings.push(StarvationWarning {
+                    task_id: task.id,
+                    priority: task.priority.clone(),
+                    task_type: task.task_type.to_string(),
+                    waited_ms,
+                    threshold_ms,
+                });
+            }
+        }
+        
+        self.starved_tasks = starved_tasks;
+        warnings
+    }
+    
+    /// Forget a task that left the queue without being dispatched
+    pub fn forget(&mut self, task_id: Uuid) {
+        self.warned.remove(&task_id);
+    }
+    
+    /// Current fairness report
+    pub fn report(&self) -> FairnessReport {
+        FairnessReport {
+            p95_wait_ms_by_priority: self.dispatch_waits.iter()
+                .filter_map(|(priority, waits)| percentile(waits, 0.95).map(|p95| (priority.clone(), p95)))
+                .collect(),
+            max_wait_ms_by_priority: self.max_wait_by_priority.clone(),
+            max_wait_ms_by_task_type: self.max_wait_by_task_type.clone(),
+            starved_tasks: self.starved_tasks,
+        }
+    }
+    
+    fn observe_wait(&mut self, task: &Task, waited_ms: u64) {
+        let max = self.max_wait_by_priority.entry(task.priority.clone()).or_default();
+        *max = (*max).max(waited_ms);
+        
+        let max = self.max_wait_by_task_type.entry(task.task_type.to_string()).or_default();
+        *max = (*max).max(waited_ms);
+    }
+}
+
+fn wait_ms(task: &Task, now: DateTime<Utc>) -> u64 {
+    (now - task.created_at).num_milliseconds().max(0) as u64
+}
+
+/// Nearest-rank percentile of a set of samples
+pub(crate) fn percentile(samples: &VecDeque<u64>, quantile: f64) -> Option<u64> {
+    if samples.is_empty() {
+        return None;
+    }
+    
+    let mut sorted: Vec<u64> = samples.iter().copied().collect();
+    sorted.sort_unstable();
+    let rank = ((quantile * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    Some(sorted[rank - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_builder::TaskBuilder;
+    use crate::types::{TaskPayload, TaskType};
+    
+    fn create_test_task(priority: TaskPriority, age_ms: i64, now: DateTime<Utc>) -> Task {
+        let task_type = TaskType::Custom { name: "test".to_string(), version: "1".to_string() };
+        TaskBuilder::new(task_type, TaskPayload::Custom { data: Vec::new(), format: "raw".to_string() })
+            .priority(priority)
+            .created_at(now - chrono::Duration::milliseconds(age_ms))
+            .build()
+    }
+    
+    #[test]
+    fn test_p95_wait_by_priority() {
+        let now = Utc::now();
+        let mut detector = StarvationDetector::default();
+        
+        for age_ms in 1..=100 {
+            detector.record_dispatch(&create_test_task(TaskPriority::Normal, age_ms * 10, now), now);
+        }
+        detector.record_dispatch(&create_test_task(TaskPriority::High, 7, now), now);
+        
+        let report = detector.report();
+        assert_eq!(report.p95_wait_ms_by_priority[&TaskPriority::Normal], 950);
+        assert_eq!(report.p95_wait_ms_by_priority[&TaskPriority::High], 7);
+        assert_eq!(report.max_wait_ms_by_priority[&TaskPriority::Normal], 1000);
+        assert_eq!(report.max_wait_ms_by_task_type["Custom(test, 1)"], 1000);
+        assert!(!report.p95_wait_ms_by_priority.contains_key(&TaskPriority::Low));
+    }
+    
+    #[test]
+    fn test_starved_tasks_warn_once() {
+        let now = Utc::now();
+        let mut detector = StarvationDetector::default();
+        let queue = vec![
+            create_test_task(TaskPriority::Low, 200_000, now),
+            create_test_task(TaskPriority::Low, 10, now),
+            create_test_task(TaskPriority::Critical, 2_000, now),
+        ];
+        
+        let warnings = detector.check_queue(&queue, now);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].priority, TaskPriority::Low);
+        assert_eq!(warnings[0].threshold_ms, 120_000);
+        assert_eq!(detector.report().starved_tasks, 2);
+        
+        // Still starving, but already reported
+        assert!(detector.check_queue(&queue, now).is_empty());
+        assert_eq!(detector.report().starved_tasks, 2);
+        assert_eq!(detector.report().max_wait_ms_by_priority[&TaskPriority::Low], 200_000);
+    }
+    
+    #[test]
+    fn test_sample_window_is_bounded() {
+        let now = Utc::now();
+        let mut detector = StarvationDetector::new(StarvationConfig { sample_window: 2, ..Default::default() });
+        
+        for age_ms in [5_000, 10, 20] {
+            detector.record_dispatch(&create_test_task(TaskPriority::Low, age_ms, now), now);
+        }
+        
+        let report = detector.report();
+        assert_eq!(report.p95_wait_ms_by_priority[&TaskPriority::Low], 20);
+        assert_eq!(report.max_wait_ms_by_priority[&TaskPriority::Low], 5_000);
+    }
+}
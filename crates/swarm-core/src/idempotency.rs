@@ -0,0 +1,201 @@
+//! Idempotent Task Handling
+//!
+//! A task redelivered after a lost acknowledgement would otherwise be
+//! processed, and its result published, a second time. Every task has an
+//! idempotency key: a hash of its type and payload, unless the submitter sets
+//! one explicitly under `IDEMPOTENCY_KEY`. Workers and the coordinator keep
+//! the completed results of recent keys in a `ResultCache`; a task whose key
+//! is cached is answered with the cached result instead of being processed,
+//! and a second result for a task that already finished is ignored. Only
+//! completed results are cached, so failed tasks are still retried.
+
+use crate::determinism::canonical_json;
+use crate::document_builder::sha256_hex;
+use crate::types::{Task, TaskResult, TaskStatus};
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+/// Task metadata key overriding the derived idempotency key
+pub const IDEMPOTENCY_KEY: &str = "idempotency_key";
+
+/// Result metadata key naming the task whose cached result was replayed
+pub const REPLAYED_FROM_KEY: &str = "replayed_from";
+
+/// Default number of results a cache keeps
+pub const DEFAULT_RESULT_CACHE_CAPACITY: usize = 1024;
+
+impl Task {
+    /// Key identifying repeated submissions of the same work
+    ///
+    /// The explicit `IDEMPOTENCY_KEY` metadata value if set, otherwise the
+    /// SHA-256 of the task type and payload.
+    pub fn idempotency_key(&self) -> String {
+        if let Some(key) = self.metadata.get(IDEMPOTENCY_KEY).and_then(|value| value.as_str()) {
+            return key.to_string();
+        }
+        let bytes = canonical_json(&(&self.task_type, &self.payload)).unwrap_or_default();
+        sha256_hex(&bytes)
+    }
+}
+
+/// Counters of a result cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ResultCacheStats {
+    pub entries: usize,
+    
+    /// Tasks answered from the cache
+    pub hits: u64,
+    
+    /// Results ignored because their task had finished already
+    pub duplicates: u64,
+}
+
+/// Completed results of recent tasks by idempotency key, oldest evicted first
+#[derive(Debug, Clone)]
+pub struct ResultCache {
+    capacity: usize,
+    results: HashMap<String, TaskResult>,
+    order: VecDeque<String>,
+    tasks: HashMap<Uuid, String>,
+    hits: u64,
+    duplicates: u64,
+}
+
+impl Default for ResultCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_RESULT_CACHE_CAPACITY)
+    }
+}
+
+impl ResultCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            results: HashMap::new(),
+            order: VecDeque::new(),
+            tasks: HashMap::new(),
+            hits: 0,
+            duplicates: 0,
+        }
+    }
+    
+    /// Cache the result of `task`; results that did not complete are not cached
+    pub fn insert(&mut self, task: &Task, result: &TaskResult) {
+        if result.status != TaskStatus::Completed {
+            return;
+        }
+        let key = task.idempotency_key();
+        match self.results.insert(key.clone(), result.clone()) {
+            Some(previous) => {
+                self.tasks.remove(&previous.task_id);
+            }
+            None => self.order.push_back(key.clone()),
+        }
+        self.tasks.insert(result.task_id, key);
+        
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front().and_then(|key| self.results.remove(&key)) {
+                self.tasks.remove(&evicted.task_id);
+            }
+        }
+    }
+    
+    /// Cached result for an idempotency key
+    pub fn get(&self, key: &str) -> Option<&TaskResult> {
+        self.results.get(key)
+    }
+    
+    /// The cached result answering `task`, addressed to it, if its key is cached
+    pub fn replay(&mut self, task: &Task) -> Option<TaskResult> {
+        let cached = self.results.get(&task.idempotency_key())?;
+        let mut result = cached.clone();
+        if result.task_id != task.id {
+            result.metadata.insert(REPLAYED_FROM_KEY.to_string(), serde_json::json!(cached.task_id.to_string()));
+            result.task_id = task.id;
+        }
+        self.hits += 1;
+        Some(result)
+    }
+    
+    /// Whether a completed result of this task is cached
+    pub fn has_finished(&self, task_id: Uuid) -> bool {
+        self.tasks.contains_key(&task_id)
+    }
+    
+    /// Count a result ignored as a duplicate
+    pub fn record_duplicate(&mut self) {
+        self.duplicates += 1;
+    }
+    
+    pub fn stats(&self) -> ResultCacheStats {
+        ResultCacheStats {
+            entries: self.results.len(),
+            hits: self.hits,
+            duplicates: self.duplicates,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_builder::TaskBuilder;
+    use crate::types::{TaskPayload, TaskType, TextAnalysisType};
+    use chrono::Utc;
+    
+    fn create_test_task(text: &str) -> Task {
+        let task_type = TaskType::TextAnalysis { analysis_type: TextAnalysisType::KeywordExtraction };
+        TaskBuilder::new(task_type, TaskPayload::Custom { data: text.as_bytes().to_vec(), format: "text".to_string() })
+            .max_retries(0)
+            .build()
+    }
+    
+    fn result_for(task: &Task, status: TaskStatus) -> TaskResult {
+        TaskResult {
+            task_id: task.id,
+            status,
+            result: None,
+            error: None,
+            processing_time_ms: 5,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        }
+    }
+    
+    #[test]
+    fn test_key_follows_type_and_payload() {
+        let task = create_test_task("hello");
+        assert_eq!(task.idempotency_key(), create_test_task("hello").idempotency_key());
+        assert_ne!(task.idempotency_key(), create_test_task("world").idempotency_key());
+        
+        let mut explicit = create_test_task("hello");
+        explicit.metadata.insert(IDEMPOTENCY_KEY.to_string(), serde_json::json!("order-17"));
+        assert_eq!(explicit.idempotency_key(), "order-17");
+    }
+    
+    #[test]
+    fn test_redelivered_tasks_replay_cached_results() {
+        let mut cache = ResultCache::new(2);
+        let first = create_test_task("hello");
+        cache.insert(&first, &result_for(&first, TaskStatus::Completed));
+        assert!(cache.has_finished(first.id));
+        
+        let same = cache.replay(&first).unwrap();
+        assert_eq!((same.task_id, same.metadata.get(REPLAYED_FROM_KEY)), (first.id, None));
+        let again = create_test_task("hello");
+        let replayed = cache.replay(&again).unwrap();
+        assert_eq!(replayed.task_id, again.id);
+        assert_eq!(replayed.metadata[REPLAYED_FROM_KEY], first.id.to_string());
+        
+        let failed = create_test_task("broken");
+        cache.insert(&failed, &result_for(&failed, TaskStatus::Failed));
+        assert!(cache.replay(&failed).is_none());
+        
+        for text in ["a", "b"] {
+            let task = create_test_task(text);
+            cache.insert(&task, &result_for(&task, TaskStatus::Completed));
+        }
+        assert!(!cache.has_finished(first.id));
+        assert_eq!(cache.stats(), ResultCacheStats { entries: 2, hits: 2, duplicates: 0 });
+    }
+}
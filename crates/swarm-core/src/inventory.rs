@@ -0,0 +1,106 @@
+//! Discovery Inventory Reports
+//!
+//! Files found by file discovery move through discovered, queued and
+//! processed. The inventory itself lives with file discovery; these are the
+//! queries and reports exchanged with it, e.g. over the
+//! `ADMIN_INVENTORY_SUBJECT` administration subject.
+
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Subject answering an `InventoryQuery` with an `InventoryReport`
+pub const ADMIN_INVENTORY_SUBJECT: &str = "swarm.admin.inventory";
+
+/// Where a discovered file is in the processing lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileState {
+    /// Found (or changed) and not handed to processing yet
+    Discovered,
+    /// Submitted for processing
+    Queued,
+    /// Processing finished
+    Processed,
+}
+
+/// Time a file has spent in its current state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgeBucket {
+    UnderOneMinute,
+    UnderOneHour,
+    UnderOneDay,
+    OneDayOrMore,
+}
+
+impl AgeBucket {
+    pub fn of(age: chrono::Duration) -> Self {
+        if age < chrono::Duration::minutes(1) {
+            AgeBucket::UnderOneMinute
+        } else if age < chrono::Duration::hours(1) {
+            AgeBucket::UnderOneHour
+        } else if age < chrono::Duration::days(1) {
+            AgeBucket::UnderOneDay
+        } else {
+            AgeBucket::OneDayOrMore
+        }
+    }
+}
+
+/// One file of the inventory
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct InventoryEntry {
+    pub path: PathBuf,
+    pub state: FileState,
+    pub size_bytes: u64,
+    
+    /// When the current version of the file was discovered
+    pub discovered_at: DateTime<Utc>,
+    
+    /// When the file entered its current state
+    pub state_since: DateTime<Utc>,
+}
+
+/// Files to list in an inventory report
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct InventoryQuery {
+    /// Only files in this state; all files if `None`
+    #[serde(default)]
+    pub state: Option<FileState>,
+    
+    /// Only files that have been in their state at least this long
+    #[serde(default)]
+    pub min_age_secs: Option<u64>,
+    
+    /// Maximum number of files listed; counts always cover every file
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl InventoryQuery {
+    /// Files discovered but not yet queued or processed
+    pub fn unprocessed() -> Self {
+        Self { state: Some(FileState::Discovered), ..Default::default() }
+    }
+    
+    /// Whether a file is listed, with its age measured at `now`
+    pub fn matches(&self, entry: &InventoryEntry, now: DateTime<Utc>) -> bool {
+        self.state.is_none_or(|state| entry.state == state)
+            && self.min_age_secs.is_none_or(|min_age| (now - entry.state_since).num_seconds() >= min_age as i64)
+    }
+}
+
+/// Counts and listing of the inventory at one point in time
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct InventoryReport {
+    pub generated_at: DateTime<Utc>,
+    pub total: u64,
+    pub counts: BTreeMap<FileState, u64>,
+    
+    /// Files per state by time spent in that state
+    pub age_buckets: BTreeMap<FileState, BTreeMap<AgeBucket, u64>>,
+    
+    /// Files matching the query, longest in their state first
+    pub files: Vec<InventoryEntry>,
+}
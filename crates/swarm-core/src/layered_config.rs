@@ -0,0 +1,446 @@
+//! Layered Configuration
+//!
+//! `LayeredConfig` implements `ConfigProvider` from layers that each
+//! override the one before: the defaults of each configuration type, a
+//! TOML, YAML or JSON file, and environment variables. Components read
+//! their section with `section`, e.g. `config.section::<NatsConfig>("nats")`.
+//! Environment variables name a key below a prefix, with `__` between
+//! levels: `SWARM_DISCOVERY__SCAN_INTERVAL_MS=500` sets
+//! `discovery.scan_interval_ms`. A `ConfigWatcher` rereads the file when it
+//! changes, and running components pick up tunables such as the scan
+//! interval through `ConfigUpdates`.
+
+use crate::service::{cancellable_sleep, CancellationToken, Service};
+use crate::traits::ConfigProvider;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Section holding the `NatsConfig`
+pub const NATS_SECTION: &str = "nats";
+
+/// Section holding the `BrokerConfig`, which selects the message broker backend
+pub const BROKER_SECTION: &str = "broker";
+
+/// Section holding the `DocumentProcessingConfig`
+pub const DOCUMENTS_SECTION: &str = "documents";
+
+/// Section holding the `WorkerConfig`
+pub const WORKER_SECTION: &str = "worker";
+
+/// Section holding the `FileDiscoveryConfig`
+pub const DISCOVERY_SECTION: &str = "discovery";
+
+/// Configuration merged from a file, the environment and runtime overrides
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    file: Option<PathBuf>,
+    file_values: Value,
+    env_values: Value,
+    /// Values set at runtime, which override every other layer
+    overrides: Value,
+    merged: Value,
+}
+
+impl Default for LayeredConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            file_values: Value::Object(Map::new()),
+            env_values: Value::Object(Map::new()),
+            overrides: Value::Object(Map::new()),
+            merged: Value::Object(Map::new()),
+        }
+    }
+}
+
+impl LayeredConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Configuration read from a file
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self> {
+        Self::new().with_file(path)
+    }
+    
+    /// Read values from a TOML, YAML or JSON file, chosen by its extension
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        self.file_values = read_file(&path)?;
+        self.file = Some(path);
+        self.merge_layers();
+        Ok(self)
+    }
+    
+    /// Override values with the process environment variables starting with `prefix`
+    pub fn with_env(self, prefix: &str) -> Self {
+        self.with_env_vars(prefix, std::env::vars())
+    }
+    
+    /// Override values with those of `vars` starting with `prefix`
+    pub fn with_env_vars(mut self, prefix: &str, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let prefix = format!("{}_", prefix.trim_end_matches('_'));
+        let mut env_values = Value::Object(Map::new());
+        for (name, value) in vars {
+            let Some(key) = name.strip_prefix(&prefix) else { continue };
+            let path: Vec<String> = key.split("__").map(str::to_lowercase).collect();
+            if path.iter().any(String::is_empty) {
+                tracing::warn!("Ignoring environment variable {} with an empty key", name);
+                continue;
+            }
+            // Numbers, booleans and JSON arrays keep their type; anything else is a string
+            let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+            insert_path(&mut env_values, &path, value);
+        }
+        self.env_values = env_values;
+        self.merge_layers();
+        self
+    }
+    
+    /// File the configuration was read from
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+    
+    /// Read the file again, keeping the environment and runtime overrides
+    pub fn reload(&mut self) -> Result<()> {
+        if let Some(path) = &self.file {
+            self.file_values = read_file(path)?;
+            self.merge_layers();
+        }
+        Ok(())
+    }
+    
+    /// A section over the defaults of its type, so only changed values need configuring
+    pub fn section<T: Serialize + DeserializeOwned + Default>(&self, name: &str) -> Result<T> {
+        self.section_or(name, T::default())
+    }
+    
+    /// A section over the given defaults, for types without a `Default`
+    /// such as `WorkerConfig`
+    pub fn section_or<T: Serialize + DeserializeOwned>(&self, name: &str, defaults: T) -> Result<T> {
+        let mut values = serde_json::to_value(defaults)?;
+        if let Some(configured) = lookup(&self.merged, name) {
+            merge(&mut values, configured.clone());
+        }
+        serde_json::from_value(values).with_context(|| format!("invalid configuration section {}", name))
+    }
+    
+    fn merge_layers(&mut self) {
+        let mut merged = self.file_values.clone();
+        merge(&mut merged, self.env_values.clone());
+        merge(&mut merged, self.overrides.clone());
+        self.merged = merged;
+    }
+}
+
+impl ConfigProvider for LayeredConfig {
+    fn get<T>(&self, key: &str) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let value = lookup(&self.merged, key).with_context(|| format!("configuration key {} is not set", key))?;
+        serde_json::from_value(value.clone()).with_context(|| format!("invalid value for configuration key {}", key))
+    }
+    
+    fn set<T>(&mut self, key: &str, value: T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let path: Vec<String> = key.split('.').map(str::to_string).collect();
+        insert_path(&mut self.overrides, &path, serde_json::to_value(value)?);
+        self.merge_layers();
+        Ok(())
+    }
+    
+    fn has(&self, key: &str) -> bool {
+        lookup(&self.merged, key).is_some()
+    }
+    
+    fn keys(&self) -> Vec<String> {
+        let mut keys = Vec::new();
+        collect_keys(&self.merged, String::new(), &mut keys);
+        keys.sort();
+        keys
+    }
+}
+
+fn read_file(path: &Path) -> Result<Value> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read configuration file {}", path.display()))?;
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_lowercase();
+    let values: Value = match extension.as_str() {
+        "toml" => toml::from_str(&contents)?,
+        "yaml" | "yml" => serde_yaml::from_str(&contents)?,
+        "json" => serde_json::from_str(&contents)?,
+        _ => anyhow::bail!("unsupported configuration file format: {}", path.display()),
+    };
+    match values {
+        Value::Object(_) => Ok(values),
+        Value::Null => Ok(Value::Object(Map::new())),
+        _ => anyhow::bail!("configuration file {} does not hold a table of settings", path.display()),
+    }
+}
+
+/// Value at a dotted key
+fn lookup<'a>(values: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.').try_fold(values, |value, name| value.as_object()?.get(name))
+}
+
+fn insert_path(values: &mut Value, path: &[String], value: Value) {
+    let Some((last, parents)) = path.split_last() else { return };
+    let mut current = values;
+    for name in parents {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        current = current.as_object_mut().expect("just made an object")
+            .entry(name.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+    if !current.is_object() {
+        *current = Value::Object(Map::new());
+    }
+    current.as_object_mut().expect("just made an object").insert(last.clone(), value);
+}
+
+/// Merge `overlay` into `base`: tables merge key by key, anything else replaces
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (name, value) in overlay {
+                match base.get_mut(&name) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(name, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn collect_keys(value: &Value, prefix: String, keys: &mut Vec<String>) {
+    match value {
+        Value::Object(values) => {
+            for (name, value) in values {
+                let key = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+                collect_keys(value, key, keys);
+            }
+        }
+        _ => keys.push(prefix),
+    }
+}
+
+/// Rereads a configuration file when it changes
+///
+/// Subscribers see each successfully reloaded configuration. A file that
+/// fails to parse is reported and the previous configuration stays.
+pub struct ConfigWatcher {
+    config: LayeredConfig,
+    sender: watch::Sender<LayeredConfig>,
+    contents: Option<String>,
+    poll_interval: Duration,
+}
+
+impl ConfigWatcher {
+    pub fn new(config: LayeredConfig) -> Self {
+        let contents = config.file().and_then(|path| std::fs::read_to_string(path).ok());
+        let (sender, _) = watch::channel(config.clone());
+        Self { config, sender, contents, poll_interval: Duration::from_secs(1) }
+    }
+    
+    /// Check the file at a custom interval
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+    
+    /// Current configuration, updated on every reload
+    pub fn subscribe(&self) -> watch::Receiver<LayeredConfig> {
+        self.sender.subscribe()
+    }
+    
+    /// Updates of one section, for a component to apply its tunables
+    pub fn updates<T>(&self, section: &str) -> ConfigUpdates<T> {
+        ConfigUpdates::new(self.subscribe(), section)
+    }
+    
+    /// Reload if the file changed since the last check; returns whether it did
+    pub fn check(&mut self) -> Result<bool> {
+        let Some(path) = self.config.file() else { return Ok(false) };
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read configuration file {}", path.display()))?;
+        if self.contents.as_ref() == Some(&contents) {
+            return Ok(false);
+        }
+        
+        let mut config = self.config.clone();
+        self.contents = Some(contents);
+        config.reload()?;
+        tracing::info!("Reloaded configuration from {}", path.display());
+        self.config = config;
+        self.sender.send_replace(self.config.clone());
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl Service for ConfigWatcher {
+    fn name(&self) -> &str {
+        "config-watcher"
+    }
+    
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        while !cancellable_sleep(&cancel, self.poll_interval).await {
+            if let Err(e) = self.check() {
+                tracing::warn!("Keeping the previous configuration: {:#}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reloaded values of one configuration section
+pub struct ConfigUpdates<T> {
+    receiver: watch::Receiver<LayeredConfig>,
+    section: String,
+    _section_type: PhantomData<fn() -> T>,
+}
+
+impl<T> ConfigUpdates<T> {
+    pub fn new(receiver: watch::Receiver<LayeredConfig>, section: &str) -> Self {
+        Self { receiver, section: section.to_string(), _section_type: PhantomData }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Default> ConfigUpdates<T> {
+    /// The section as last reloaded, if there was a reload since the previous call
+    pub fn changed(&mut self) -> Option<T> {
+        if !self.receiver.has_changed().unwrap_or(false) {
+            return None;
+        }
+        let section = self.receiver.borrow_and_update().section(&self.section);
+        match section {
+            Ok(section) => Some(section),
+            Err(e) => {
+                tracing::warn!("Ignoring reloaded configuration: {:#}", e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PerformanceProfile, WorkerConfig, WorkerType};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+    use serde::Deserialize;
+    use tempfile::tempdir;
+    
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Discovery {
+        scan_interval_ms: u64,
+        recursive_scan: bool,
+        include_patterns: Vec<String>,
+    }
+    
+    impl Default for Discovery {
+        fn default() -> Self {
+            Self { scan_interval_ms: 1000, recursive_scan: true, include_patterns: vec!["*".to_string()] }
+        }
+    }
+    
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+    
+    #[test]
+    fn test_layers_override_defaults_in_order() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("swarm.toml");
+        std::fs::write(&path, "[discovery]\nscan_interval_ms = 500\nrecursive_scan = false\n\n[worker]\nname = \"from-file\"\n").unwrap();
+        
+        let config = LayeredConfig::from_file(&path).unwrap().with_env_vars("SWARM", env(&[
+            ("SWARM_DISCOVERY__SCAN_INTERVAL_MS", "250"),
+            ("SWARM_DISCOVERY__INCLUDE_PATTERNS", r#"["*.md"]"#),
+            ("OTHER_DISCOVERY__SCAN_INTERVAL_MS", "1"),
+        ]));
+        let discovery: Discovery = config.section(DISCOVERY_SECTION).unwrap();
+        assert_eq!(discovery, Discovery { scan_interval_ms: 250, recursive_scan: false, include_patterns: vec!["*.md".to_string()] });
+        
+        let defaults = WorkerConfig {
+            id: Uuid::new_v4(),
+            name: "worker".to_string(),
+            worker_type: WorkerType::Custom { name: "test".to_string(), version: "1".to_string() },
+            max_concurrent_tasks: 4,
+            capabilities: Vec::new(),
+            performance_profile: PerformanceProfile {
+                avg_processing_time_ms: 100,
+                memory_usage_mb: 64,
+                cpu_intensity: 0.5,
+                throughput_per_second: 10.0,
+            },
+            health_check_interval_ms: 5000,
+            shutdown_timeout_ms: 1000,
+            metadata: HashMap::new(),
+        };
+        let worker: WorkerConfig = config.section_or(WORKER_SECTION, defaults.clone()).unwrap();
+        assert_eq!(worker, WorkerConfig { name: "from-file".to_string(), ..defaults });
+        
+        // Sections without any configured values are their defaults
+        assert_eq!(config.section::<Discovery>("missing").unwrap(), Discovery::default());
+    }
+    
+    #[test]
+    fn test_config_provider_keys() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("swarm.yaml");
+        std::fs::write(&path, "nats:\n  url: nats://broker:4222\n  max_reconnect_attempts: 3\n").unwrap();
+        let mut config = LayeredConfig::from_file(&path).unwrap();
+        
+        assert_eq!(config.get::<String>("nats.url").unwrap(), "nats://broker:4222");
+        assert!(config.get::<String>("nats.user").is_err());
+        config.set("nats.user", "swarm").unwrap();
+        assert!(config.has("nats.user"));
+        assert_eq!(config.keys(), vec!["nats.max_reconnect_attempts", "nats.url", "nats.user"]);
+        
+        // Runtime overrides survive a reload
+        config.reload().unwrap();
+        assert_eq!(config.get::<String>("nats.user").unwrap(), "swarm");
+        assert!(LayeredConfig::from_file(dir.path().join("swarm.ini")).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_watcher_reloads_changed_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("swarm.json");
+        std::fs::write(&path, r#"{"discovery": {"scan_interval_ms": 500}}"#).unwrap();
+        let mut watcher = ConfigWatcher::new(LayeredConfig::from_file(&path).unwrap());
+        let mut updates = watcher.updates::<Discovery>(DISCOVERY_SECTION);
+        
+        assert!(!watcher.check().unwrap());
+        assert_eq!(updates.changed(), None);
+        
+        std::fs::write(&path, r#"{"discovery": {"scan_interval_ms": 100}}"#).unwrap();
+        assert!(watcher.check().unwrap());
+        assert_eq!(updates.changed().unwrap().scan_interval_ms, 100);
+        assert_eq!(updates.changed(), None);
+        
+        // A broken file keeps the previous configuration
+        std::fs::write(&path, r#"{"discovery": "#).unwrap();
+        assert!(watcher.check().is_err());
+        assert_eq!(updates.changed(), None);
+        assert_eq!(watcher.subscribe().borrow().section::<Discovery>(DISCOVERY_SECTION).unwrap().scan_interval_ms, 100);
+    }
+}
@@ -11,6 +11,45 @@
 pub mod traits;
 pub mod types;
 pub mod error;
+pub mod document_builder;
+pub mod task_builder;
+pub mod backpressure;
+pub mod fairness;
+pub mod concurrency;
+pub mod coordinator_events;
+pub mod liveness;
+pub mod retry;
+pub mod sampling;
+pub mod schema;
+pub mod worker_registry;
+pub mod capacity;
+pub mod benchmark;
+pub mod bulk;
+pub mod budget;
+pub mod compression;
+pub mod service;
+pub mod memory_broker;
+pub mod supervisor;
+pub mod rejection;
+pub mod compatibility;
+pub mod vector_payload;
+pub mod priority_rules;
+pub mod processing_profiles;
+pub mod scheduling;
+pub mod task_schedules;
+pub mod document_store;
+pub mod document_groups;
+pub mod workflow;
+pub mod idempotency;
+pub mod message_headers;
+pub mod registration;
+pub mod sli;
+pub mod subject;
+pub mod determinism;
+pub mod trace_context;
+pub mod layered_config;
+pub mod inventory;
+pub(crate) mod base64_bytes;
 
 // Legacy modules (to be refactored)
 pub mod coordinator;
@@ -24,6 +63,46 @@ pub mod worker_manager;
 pub use traits::*;
 pub use types::*;
 pub use error::{SwarmError, SwarmResult};
+pub use document_builder::{DocumentBuilder, DocumentIdStrategy, content_hash, deterministic_document_id, sha256_hex};
+pub use task_builder::{TaskBuilder, DEFAULT_MAX_RETRIES};
+pub use backpressure::{BackpressureConfig, DEFAULT_RESULT_QUEUE_CAPACITY, DEFAULT_TASK_QUEUE_CAPACITY};
+pub use concurrency::{AdaptiveConcurrencyConfig, AdaptiveConcurrencyController, ConcurrencyPermit, ConcurrencyStats};
+pub use fairness::{StarvationConfig, StarvationDetector, StarvationWarning};
+pub use liveness::{Heartbeat, LivenessConfig, LivenessTracker};
+pub use rejection::{DocumentRejection, RejectionLimit, RejectionReason, CORRELATION_ID_KEY, REJECTION_SUBJECT};
+pub use retry::{DeadLetter, FailedAttempt, RetryPolicy, ScheduledRetry, DEAD_LETTER_SUBJECT};
+pub use sampling::{SamplingConfig, SamplingDecision, TraceSampler, TRACE_SAMPLED_HEADER, TRACE_SAMPLED_KEY};
+pub use schema::{SchemaKind, SCHEMA_HEADER, SCHEMA_VERSION, SCHEMA_VERSION_HEADER};
+pub use worker_registry::{FleetComposition, MembershipChange, MembershipEvent, WorkerRecord, WorkerRegistry, WORKER_VERSION_KEY};
+pub use benchmark::{BenchmarkReport, TaskTypeBenchmark, BENCHMARK_METADATA_KEY};
+pub use compatibility::{BuildInfo, CompatibilityIssue, CompatibilityReport, CompatibilitySeverity, ComponentCompatibility, BUILD_INFO_KEY};
+pub use capacity::{ArrivalStatistics, CapacityConfig, CapacityPlanner, CapacityProjection, PlannedWorkers, ProcessingTimeEstimator, TaskArrival, TaskTypeProjection, WorkerGroupProjection};
+pub use budget::{AnalysisBudgets, BudgetedText, WorkBudget, DEFAULT_SAMPLE_WINDOWS};
+pub use bulk::{BulkAction, BulkMatch, BulkOperation, BulkProgress, BulkReport, TaskFilter, TENANT_KEY};
+pub use compression::{CompressionConfig, CompressionStats, Compressor};
+pub use service::{cancellable_sleep, CancellationToken, Service};
+pub use memory_broker::{MemoryBroker, MemorySubscription};
+pub use document_groups::{combine_metadata, GroupAssembler, GroupMember, GroupMembership, GroupProgress, GroupResult, GroupStatus, GroupTracker, IncompleteGroup, TaskGroup, DOCUMENT_GROUP_KEY, GROUP_PARENT_KEY, GROUP_RESULT_SUBJECT, GROUP_SIZE_KEY};
+pub use workflow::{StageOutcome, StageStatus, Workflow, WorkflowResult, WorkflowStage, WorkflowStatus, WorkflowTracker, WORKFLOW_INPUTS_KEY, WORKFLOW_KEY, WORKFLOW_STAGE_KEY};
+pub use idempotency::{ResultCache, ResultCacheStats, DEFAULT_RESULT_CACHE_CAPACITY, IDEMPOTENCY_KEY, REPLAYED_FROM_KEY};
+pub use document_store::{ClaimCheck, DocumentStore, LocalDiskStore, MemoryDocumentStore, StoredObject, CLAIM_CHECK_KEY};
+pub use message_headers::{MessageHeaders, CONTENT_TYPE_HEADER};
+pub use registration::{assignment_subject, RegistrationAck, RegistrationRequest, RegistrationService, WorkerDeregistration, WorkerRegistration, TASK_RESULTS_SUBJECT, WORKER_DEREGISTRATION_SUBJECT, WORKER_HEALTH_SUBJECT, WORKER_REGISTRATION_SUBJECT};
+pub use sli::{MemoryMetricsCollector, Sli, SliRecorder, DOCUMENTS_INGESTED_COUNTER, MESSAGES_PUBLISHED_COUNTER, TASKS_DISPATCHED_COUNTER, TASKS_FINISHED_COUNTER, TASKS_PROCESSED_COUNTER};
+pub use subject::{subject_matches, Subject, SubjectError};
+pub use layered_config::{ConfigUpdates, ConfigWatcher, LayeredConfig, BROKER_SECTION, DISCOVERY_SECTION, DOCUMENTS_SECTION, NATS_SECTION, WORKER_SECTION};
+pub use inventory::{AgeBucket, FileState, InventoryEntry, InventoryQuery, InventoryReport, ADMIN_INVENTORY_SUBJECT};
+pub use trace_context::{TraceContext, TRACEPARENT_HEADER, TRACE_CONTEXT_KEY};
+#[cfg(feature = "otlp")]
+pub use trace_context::otlp_layer;
+pub use determinism::{canonical_json, Clock, Determinism, DeterminismConfig, FrozenClock, SeededIds, SystemClock};
+pub use scheduling::{supports_task_type, worker_type_name, CapabilityMatch, FailureDomain, SchedulingWeights, SpreadLevel, WorkerScore, FAILURE_DOMAIN_HOST_KEY, FAILURE_DOMAIN_ZONE_KEY, PREFERRED_WORKER_TYPE_KEY, SPREAD_GROUP_KEY};
+pub use task_schedules::{parse_cron, CronScheduler, ScheduleStore, TaskSchedule, TaskTemplate, SCHEDULE_KEY};
+pub use processing_profiles::{effective_options, ProcessingProfiles, PROCESSING_OPTIONS_KEY, PROCESSING_PROFILE_KEY, PROVENANCE_KEY};
+pub use priority_rules::{PreScan, PriorityCondition, PriorityRule, PriorityRules, PRIORITY_RULE_KEY};
+pub use vector_payload::{MemoryVectorStore, PackedVectors, VectorData, VectorPayloadConfig, VectorStore};
+pub use supervisor::{BackoffConfig, ComponentConfig, ComponentContext, ComponentFactory, ComponentState, ComponentStatus, Supervisor, SupervisorConfig};
+pub use coordinator_events::{Assignment, CoordinatorEvent, CoordinatorEventLog, CoordinatorSnapshot, CoordinatorState, EventRecord, Projection};
 
 // Legacy re-exports (for backward compatibility)
 pub use coordinator::SwarmCoordinator;
@@ -38,18 +117,22 @@ pub mod prelude {
     // Core traits
     pub use crate::traits::{
         Worker, TaskProcessor, DocumentProcessor, WorkerCoordinator,
-        TaskScheduler, DocumentReader, MessageBroker, MetricsCollector, ConfigProvider,
+        TaskScheduler, DocumentReader, MessageBroker, ResultSink, MetricsCollector, ConfigProvider,
+        EmbeddingProvider,
     };
     
     // Core types
     pub use crate::types::{
         Task, TaskResult, TaskStatus, TaskPriority, TaskType, TaskPayload,
         WorkerConfig, WorkerType, WorkerStatus, WorkerCapability, WorkerHealth,
-        Document, DocumentType, DocumentContent, DocumentProcessingResult,
-        Message, MessageBrokerStats, CoordinatorStats, TaskQueueStats,
+        Document, DocumentType, DocumentContent, DocumentProcessingResult, ProcessingWarning,
+        Message, MessageBrokerStats, CoordinatorStats, FairnessReport, QueueSaturation, TaskQueueStats,
         DocumentReaderStats, MetricValue, PerformanceProfile,
     };
     
+    // Document construction
+    pub use crate::document_builder::{DocumentBuilder, DocumentIdStrategy};
+    
     // Error types
     pub use crate::error::{SwarmError, SwarmResult};
     
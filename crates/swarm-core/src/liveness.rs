@@ -0,0 +1,159 @@
+//! Worker Liveness
+//!
+//! Workers send periodic heartbeats to the coordinator. A worker that misses
+//! a configured number of consecutive heartbeats is considered unavailable
+//! until it is heard from again.
+
+use crate::types::WorkerStatus;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Heartbeat configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LivenessConfig {
+    /// Interval at which workers send heartbeats
+    pub heartbeat_interval_ms: u64,
+    
+    /// Consecutive missed heartbeats after which a worker is unavailable
+    pub missed_heartbeats: u32,
+}
+
+impl Default for LivenessConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval_ms: 1000,
+            missed_heartbeats: 3,
+        }
+    }
+}
+
+impl LivenessConfig {
+    /// Silence after which a worker is considered unavailable
+    pub fn timeout(&self) -> chrono::Duration {
+        chrono::Duration::milliseconds((self.heartbeat_interval_ms * u64::from(self.missed_heartbeats.max(1))) as i64)
+    }
+}
+
+/// Periodic signal that a worker is alive
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Heartbeat {
+    pub worker_id: Uuid,
+    pub status: WorkerStatus,
+    pub current_load: usize,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// Tracks when each worker was last heard from
+///
+/// Times are taken from the receiving side, so worker clock skew does not
+/// affect liveness.
+#[derive(Debug, Clone, Default)]
+pub struct LivenessTracker {
+    config: LivenessConfig,
+    last_seen: HashMap<Uuid, DateTime<Utc>>,
+    unavailable: HashSet<Uuid>,
+}
+
+impl LivenessTracker {
+    pub fn new(config: LivenessConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+    
+    pub fn config(&self) -> &LivenessConfig {
+        &self.config
+    }
+    
+    /// Start tracking a newly registered worker
+    pub fn track(&mut self, worker_id: Uuid, now: DateTime<Utc>) {
+        self.last_seen.insert(worker_id, now);
+        self.unavailable.remove(&worker_id);
+    }
+    
+    /// Stop tracking a worker
+    pub fn forget(&mut self, worker_id: Uuid) {
+        self.last_seen.remove(&worker_id);
+        self.unavailable.remove(&worker_id);
+    }
+    
+    /// Record a heartbeat; returns true if the worker was unavailable and has recovered
+    pub fn record(&mut self, heartbeat: &Heartbeat, now: DateTime<Utc>) -> bool {
+        match self.last_seen.get_mut(&heartbeat.worker_id) {
+            Some(last_seen) => {
+                *last_seen = now;
+                self.unavailable.remove(&heartbeat.worker_id)
+            }
+            None => false,
+        }
+    }
+    
+    /// Workers that became unavailable since the last check, in ID order
+    pub fn check(&mut self, now: DateTime<Utc>) -> Vec<Uuid> {
+        let timeout = self.config.timeout();
+        let mut expired: Vec<Uuid> = self.last_seen.iter()
+            .filter(|(worker_id, last_seen)| now - **last_seen >= timeout && !self.unavailable.contains(worker_id))
+            .map(|(worker_id, _)| *worker_id)
+            .collect();
+        expired.sort();
+        self.unavailable.extend(expired.iter().copied());
+        expired
+    }
+    
+    pub fn is_available(&self, worker_id: &Uuid) -> bool {
+        self.last_seen.contains_key(worker_id) && !self.unavailable.contains(worker_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn heartbeat(worker_id: Uuid) -> Heartbeat {
+        Heartbeat {
+            worker_id,
+            status: WorkerStatus::Idle,
+            current_load: 0,
+            sent_at: Utc::now(),
+        }
+    }
+    
+    #[test]
+    fn test_missed_heartbeats_mark_worker_unavailable() {
+        let mut tracker = LivenessTracker::new(LivenessConfig { heartbeat_interval_ms: 100, missed_heartbeats: 3 });
+        let (alive, silent) = (Uuid::new_v4(), Uuid::new_v4());
+        let start = Utc::now();
+        tracker.track(alive, start);
+        tracker.track(silent, start);
+        
+        let later = |ms| start + chrono::Duration::milliseconds(ms);
+        tracker.record(&heartbeat(alive), later(200));
+        assert!(tracker.check(later(250)).is_empty());
+        
+        assert_eq!(tracker.check(later(300)), vec![silent]);
+        assert!(!tracker.is_available(&silent));
+        assert!(tracker.is_available(&alive));
+        
+        // Reported once only
+        assert!(tracker.check(later(400)).is_empty());
+        assert_eq!(tracker.check(later(500)), vec![alive]);
+    }
+    
+    #[test]
+    fn test_heartbeat_recovers_worker() {
+        let mut tracker = LivenessTracker::new(LivenessConfig { heartbeat_interval_ms: 100, missed_heartbeats: 1 });
+        let worker_id = Uuid::new_v4();
+        let start = Utc::now();
+        tracker.track(worker_id, start);
+        
+        assert_eq!(tracker.check(start + chrono::Duration::milliseconds(100)), vec![worker_id]);
+        assert!(tracker.record(&heartbeat(worker_id), start + chrono::Duration::milliseconds(150)));
+        assert!(tracker.is_available(&worker_id));
+        
+        // Heartbeats from unknown workers are ignored
+        assert!(!tracker.record(&heartbeat(Uuid::new_v4()), start));
+    }
+}
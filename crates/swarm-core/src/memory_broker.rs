@@ -0,0 +1,287 @@
+//! In-Memory Broker
+//!
+//! Message broker for components running in the same process, so a
+//! pipeline can run end-to-end without a NATS server. Every subscriber of a
+//! subject receives its own copy of each message published to that subject
+//! after it subscribed; nothing is persisted or shared with other
+//! processes. Subscriptions may use NATS wildcards (`swarm.documents.*`,
+//! `swarm.>`), and subscribers in a queue group share the group's messages
+//! round-robin, one member receiving each. Requests carry a private inbox
+//! subject in their `reply-to` header, and the first message published to it
+//! is the reply.
+
+use crate::traits::{MessageBroker, MessageSubscription};
+use crate::message_headers::MessageHeaders;
+use crate::subject::Subject;
+use crate::types::{Message, MessageBrokerStats, REPLY_TO_HEADER};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+struct Subscriber {
+    id: u64,
+    pattern: Subject,
+    queue_group: Option<String>,
+    sender: mpsc::UnboundedSender<Message>,
+}
+
+#[derive(Default)]
+struct BrokerState {
+    subscribers: Vec<Subscriber>,
+    next_subscription: u64,
+    /// Deliveries per queue group, picking the group's next member
+    queue_turns: HashMap<String, usize>,
+    stats: MessageBrokerStats,
+}
+
+type SharedState = Arc<Mutex<BrokerState>>;
+
+/// Broker delivering messages between tasks of one process
+#[derive(Clone, Default)]
+pub struct MemoryBroker {
+    state: SharedState,
+}
+
+impl MemoryBroker {
+    /// Create an empty broker
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Subscribe to `subject` as a member of the queue group `group`
+    ///
+    /// Each message is delivered to exactly one member of `group`, so a pool
+    /// of workers subscribed with the same group shares the load.
+    pub async fn subscribe_queue(&self, subject: &str, group: &str) -> Result<Box<dyn MessageSubscription>> {
+        Ok(Box::new(self.add_subscriber(subject, Some(group.to_string()))?))
+    }
+    
+    fn add_subscriber(&self, subject: &str, queue_group: Option<String>) -> Result<MemorySubscription> {
+        let pattern = Subject::parse(subject)?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut state = self.state.lock().expect("broker state lock poisoned");
+        let id = state.next_subscription;
+        state.next_subscription += 1;
+        state.subscribers.push(Subscriber { id, pattern, queue_group, sender });
+        state.stats.active_subscriptions += 1;
+        Ok(MemorySubscription {
+            id,
+            subject: subject.to_string(),
+            receiver,
+            state: self.state.clone(),
+        })
+    }
+    
+    /// Deliver a message to the subscribers of its subject, returning how many received it
+    fn deliver(&self, message: Message) -> usize {
+        let mut state = self.state.lock().expect("broker state lock poisoned");
+        let state = &mut *state;
+        let mut delivered = 0;
+        let mut groups: HashMap<&str, Vec<&Subscriber>> = HashMap::new();
+        for subscriber in state.subscribers.iter().filter(|subscriber| subscriber.pattern.matches(&message.subject)) {
+            match &subscriber.queue_group {
+                Some(group) => groups.entry(group.as_str()).or_default().push(subscriber),
+                None => delivered += usize::from(subscriber.sender.send(message.clone()).is_ok()),
+            }
+        }
+        for (group, members) in groups {
+            let turn = state.queue_turns.entry(group.to_string()).or_default();
+            let member = members[*turn % members.len()];
+            *turn += 1;
+            delivered += usize::from(member.sender.send(message.clone()).is_ok());
+        }
+        state.stats.total_messages_sent += 1;
+        state.stats.queue_depth += delivered;
+        delivered
+    }
+}
+
+fn new_message(subject: &str, payload: &[u8]) -> Message {
+    Message {
+        id: Uuid::new_v4(),
+        subject: subject.to_string(),
+        payload: payload.to_vec(),
+        headers: MessageHeaders::new(),
+        timestamp: Utc::now(),
+        ttl_ms: None,
+    }
+}
+
+#[async_trait]
+impl MessageBroker for MemoryBroker {
+    async fn publish(&self, subject: &str, message: &[u8]) -> Result<()> {
+        self.deliver(new_message(subject, message));
+        Ok(())
+    }
+    
+    async fn request(&self, subject: &str, message: &[u8], timeout: Duration) -> Result<Message> {
+        let inbox = format!("_INBOX.{}", Uuid::new_v4().simple());
+        let mut replies = self.subscribe(&inbox).await?;
+        let mut request = new_message(subject, message);
+        request.headers.insert(REPLY_TO_HEADER, inbox);
+        if self.deliver(request) == 0 {
+            anyhow::bail!("no responders on {}", subject);
+        }
+        
+        tokio::time::timeout(timeout, async {
+            loop {
+                if let Some(reply) = replies.next_message()? {
+                    return Ok(reply);
+                }
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .map_err(|_| anyhow::anyhow!("request on {} timed out after {:?}", subject, timeout))?
+    }
+    
+    async fn subscribe(&self, subject: &str) -> Result<Box<dyn MessageSubscription>> {
+        Ok(Box::new(self.add_subscriber(subject, None)?))
+    }
+    
+    async fn get_stats(&self) -> MessageBrokerStats {
+        self.state.lock().expect("broker state lock poisoned").stats.clone()
+    }
+}
+
+/// Subscription to one subject of a [`MemoryBroker`]
+pub struct MemorySubscription {
+    id: u64,
+    subject: String,
+    receiver: mpsc::UnboundedReceiver<Message>,
+    state: SharedState,
+}
+
+impl MemorySubscription {
+    /// Subject, or wildcard pattern, this subscription is bound to
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+}
+
+impl MessageSubscription for MemorySubscription {
+    fn next_message(&mut self) -> Result<Option<Message>> {
+        let Ok(message) = self.receiver.try_recv() else {
+            return Ok(None);
+        };
+        let mut state = self.state.lock().expect("broker state lock poisoned");
+        state.stats.total_messages_received += 1;
+        state.stats.queue_depth -= 1;
+        Ok(Some(message))
+    }
+    
+    fn unsubscribe(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for MemorySubscription {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().expect("broker state lock poisoned");
+        state.subscribers.retain(|subscriber| subscriber.id != self.id);
+        state.stats.active_subscriptions -= 1;
+        state.stats.queue_depth -= self.receiver.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_every_subscriber_receives_published_messages() {
+        let broker = MemoryBroker::new();
+        let mut first = broker.subscribe("documents").await.unwrap();
+        let mut second = broker.subscribe("documents").await.unwrap();
+        let mut other = broker.subscribe("results").await.unwrap();
+        
+        broker.publish("documents", b"report.pdf").await.unwrap();
+        
+        assert_eq!(first.next_message().unwrap().unwrap().payload, b"report.pdf");
+        assert_eq!(second.next_message().unwrap().unwrap().subject, "documents");
+        assert!(first.next_message().unwrap().is_none());
+        assert!(other.next_message().unwrap().is_none());
+        
+        let stats = broker.get_stats().await;
+        assert_eq!(stats.total_messages_sent, 1);
+        assert_eq!(stats.total_messages_received, 2);
+        assert_eq!(stats.active_subscriptions, 3);
+    }
+    
+    #[tokio::test]
+    async fn test_wildcards_and_queue_groups() {
+        let broker = MemoryBroker::new();
+        let mut documents = broker.subscribe("swarm.documents.*").await.unwrap();
+        let mut everything = broker.subscribe("swarm.>").await.unwrap();
+        let mut workers = vec![
+            broker.subscribe_queue("swarm.tasks.assignments", "workers").await.unwrap(),
+            broker.subscribe_queue("swarm.tasks.assignments", "workers").await.unwrap(),
+        ];
+        assert!(broker.subscribe("swarm.>.tasks").await.is_err());
+        
+        broker.publish("swarm.documents.incoming", b"report.pdf").await.unwrap();
+        broker.publish("swarm.documents.incoming.eu", b"nested").await.unwrap();
+        for task in [b"task-1", b"task-2", b"task-3"] {
+            broker.publish("swarm.tasks.assignments", task).await.unwrap();
+        }
+        
+        assert_eq!(documents.next_message().unwrap().unwrap().subject, "swarm.documents.incoming");
+        assert!(documents.next_message().unwrap().is_none());
+        let mut received = 0;
+        while everything.next_message().unwrap().is_some() {
+            received += 1;
+        }
+        assert_eq!(received, 5);
+        
+        let mut shares = Vec::new();
+        for worker in &mut workers {
+            let mut tasks = Vec::new();
+            while let Some(message) = worker.next_message().unwrap() {
+                tasks.push(message.payload);
+            }
+            shares.push(tasks);
+        }
+        assert_eq!(shares, vec![vec![b"task-1".to_vec(), b"task-3".to_vec()], vec![b"task-2".to_vec()]]);
+    }
+    
+    #[tokio::test]
+    async fn test_dropped_subscription_stops_receiving() {
+        let broker = MemoryBroker::new();
+        let subscription = broker.subscribe("documents").await.unwrap();
+        broker.publish("documents", b"queued").await.unwrap();
+        assert_eq!(broker.get_stats().await.queue_depth, 1);
+        
+        drop(subscription);
+        broker.publish("documents", b"dropped").await.unwrap();
+        
+        let stats = broker.get_stats().await;
+        assert_eq!(stats.active_subscriptions, 0);
+        assert_eq!(stats.queue_depth, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_request_receives_reply_on_inbox() {
+        let broker = MemoryBroker::new();
+        let mut health = broker.subscribe("workers.health").await.unwrap();
+        let responder = broker.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Some(request) = health.next_message().unwrap() {
+                    let reply_to = request.reply_to().unwrap().to_string();
+                    responder.publish(&reply_to, b"healthy").await.unwrap();
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        });
+        
+        let reply = broker.request("workers.health", b"ping", Duration::from_secs(1)).await.unwrap();
+        assert_eq!(reply.payload, b"healthy");
+        assert!(broker.request("workers.capabilities", b"ping", Duration::from_secs(1)).await.is_err());
+    }
+}
@@ -0,0 +1,317 @@
+//! Message Headers
+//!
+//! Every message carries a handful of headers, almost all with the same few
+//! names. `MessageHeaders` keeps them inline in a small vector instead of a
+//! hash map, and interns well-known names so neither building nor decoding
+//! a message allocates for them. Static values such as content types are
+//! borrowed too. Headers serialize as a plain string map and convert to and
+//! from `HashMap<String, String>` at the edges.
+
+use serde::de::{MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Message header naming the encoding of the payload
+pub const CONTENT_TYPE_HEADER: &str = "content-type";
+
+/// Headers kept inline before spilling to the heap
+const INLINE_HEADERS: usize = 8;
+
+/// Header names stored without allocating
+const WELL_KNOWN_HEADERS: &[&str] = &[
+    CONTENT_TYPE_HEADER,
+    crate::types::REPLY_TO_HEADER,
+    crate::sampling::TRACE_SAMPLED_HEADER,
+    crate::trace_context::TRACEPARENT_HEADER,
+    crate::schema::SCHEMA_HEADER,
+    crate::schema::SCHEMA_VERSION_HEADER,
+    "document-id",
+    "document-type",
+    "task-id",
+    "task-type",
+    "priority",
+    "status",
+    "attempts",
+    "worker-id",
+    "component-id",
+    "component-type",
+    "error-type",
+    "expired-at",
+];
+
+/// Interned name if `name` is well known
+fn intern(name: &str) -> Option<&'static str> {
+    WELL_KNOWN_HEADERS.iter().copied().find(|known| *known == name)
+}
+
+fn interned(name: Cow<'static, str>) -> Cow<'static, str> {
+    match name {
+        Cow::Owned(owned) => match intern(&owned) {
+            Some(known) => Cow::Borrowed(known),
+            None => Cow::Owned(owned),
+        },
+        borrowed => borrowed,
+    }
+}
+
+/// Headers of a message
+#[derive(Debug, Clone, Default)]
+pub struct MessageHeaders {
+    entries: SmallVec<[(Cow<'static, str>, Cow<'static, str>); INLINE_HEADERS]>,
+}
+
+impl MessageHeaders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_ref())
+    }
+    
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+    
+    /// Set a header, returning the value it replaces
+    pub fn insert(&mut self, name: impl Into<Cow<'static, str>>, value: impl Into<Cow<'static, str>>) -> Option<String> {
+        let name = name.into();
+        let value = value.into();
+        match self.entries.iter_mut().find(|(key, _)| *key == name) {
+            Some((_, existing)) => Some(std::mem::replace(existing, value).into_owned()),
+            None => {
+                self.entries.push((interned(name), value));
+                None
+            }
+        }
+    }
+    
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let index = self.entries.iter().position(|(key, _)| key == name)?;
+        Some(self.entries.remove(index).1.into_owned())
+    }
+    
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(name, value)| (name.as_ref(), value.as_ref()))
+    }
+    
+    pub fn values(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(_, value)| value.as_ref())
+    }
+    
+    /// Whether the headers are still stored inline
+    pub fn is_inline(&self) -> bool {
+        !self.entries.spilled()
+    }
+    
+    /// Copy into a map, for APIs that take one
+    pub fn to_map(&self) -> HashMap<String, String> {
+        self.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+    
+    pub fn content_type(&self) -> Option<&str> {
+        self.get(CONTENT_TYPE_HEADER)
+    }
+    
+    pub fn reply_to(&self) -> Option<&str> {
+        self.get(crate::types::REPLY_TO_HEADER)
+    }
+    
+    pub fn trace_sampled(&self) -> Option<&str> {
+        self.get(crate::sampling::TRACE_SAMPLED_HEADER)
+    }
+    
+    pub fn traceparent(&self) -> Option<&str> {
+        self.get(crate::trace_context::TRACEPARENT_HEADER)
+    }
+    
+    pub fn schema(&self) -> Option<&str> {
+        self.get(crate::schema::SCHEMA_HEADER)
+    }
+    
+    pub fn schema_version(&self) -> Option<&str> {
+        self.get(crate::schema::SCHEMA_VERSION_HEADER)
+    }
+}
+
+/// Equal when they hold the same headers, in any order
+impl PartialEq for MessageHeaders {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(name, value)| other.get(name) == Some(value))
+    }
+}
+
+impl Eq for MessageHeaders {}
+
+impl std::ops::Index<&str> for MessageHeaders {
+    type Output = str;
+    
+    fn index(&self, name: &str) -> &str {
+        self.get(name).unwrap_or_else(|| panic!("no header {}", name))
+    }
+}
+
+impl<K, V> Extend<(K, V)> for MessageHeaders
+where
+    K: Into<Cow<'static, str>>,
+    V: Into<Cow<'static, str>>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, headers: I) {
+        for (name, value) in headers {
+            self.insert(name, value);
+        }
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for MessageHeaders
+where
+    K: Into<Cow<'static, str>>,
+    V: Into<Cow<'static, str>>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(headers: I) -> Self {
+        let mut collected = Self::new();
+        collected.extend(headers);
+        collected
+    }
+}
+
+impl From<HashMap<String, String>> for MessageHeaders {
+    fn from(map: HashMap<String, String>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl From<MessageHeaders> for HashMap<String, String> {
+    fn from(headers: MessageHeaders) -> Self {
+        headers.entries.into_iter().map(|(name, value)| (name.into_owned(), value.into_owned())).collect()
+    }
+}
+
+impl Serialize for MessageHeaders {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (name, value) in self.iter() {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Header name decoded without allocating when it is well known
+struct HeaderName(Cow<'static, str>);
+
+impl<'de> Deserialize<'de> for HeaderName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NameVisitor;
+        
+        impl Visitor<'_> for NameVisitor {
+            type Value = HeaderName;
+            
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a header name")
+            }
+            
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<HeaderName, E> {
+                Ok(HeaderName(intern(name).map_or_else(|| Cow::Owned(name.to_string()), Cow::Borrowed)))
+            }
+            
+            fn visit_string<E: serde::de::Error>(self, name: String) -> Result<HeaderName, E> {
+                Ok(HeaderName(interned(Cow::Owned(name))))
+            }
+        }
+        
+        deserializer.deserialize_str(NameVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for MessageHeaders {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HeadersVisitor;
+        
+        impl<'de> Visitor<'de> for HeadersVisitor {
+            type Value = MessageHeaders;
+            
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of header names to values")
+            }
+            
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<MessageHeaders, A::Error> {
+                let mut headers = MessageHeaders::new();
+                while let Some((HeaderName(name), value)) = map.next_entry::<HeaderName, String>()? {
+                    headers.insert(name, value);
+                }
+                Ok(headers)
+            }
+        }
+        
+        deserializer.deserialize_map(HeadersVisitor)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for MessageHeaders {
+    fn schema_name() -> String {
+        "MessageHeaders".to_string()
+    }
+    
+    // Inlined, so the published schema stays a plain string map
+    fn is_referenceable() -> bool {
+        false
+    }
+    
+    fn json_schema(generator: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        <HashMap<String, String>>::json_schema(generator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_headers_behave_like_a_map() {
+        let mut headers = MessageHeaders::new();
+        headers.insert(CONTENT_TYPE_HEADER, "application/json");
+        headers.insert("task-id".to_string(), "42".to_string());
+        headers.insert("x-custom", "a");
+        assert_eq!(headers.insert("x-custom", "b"), Some("a".to_string()));
+        
+        assert_eq!(headers.len(), 3);
+        assert_eq!(headers.content_type(), Some("application/json"));
+        assert_eq!(&headers["task-id"], "42");
+        assert_eq!(headers.remove("x-custom"), Some("b".to_string()));
+        assert!(!headers.contains_key("x-custom"));
+        assert!(headers.is_inline());
+        
+        // Well-known names are interned even when passed as owned strings
+        assert!(headers.entries.iter().all(|(name, _)| matches!(name, Cow::Borrowed(_))));
+    }
+    
+    #[test]
+    fn test_headers_round_trip_as_a_map() {
+        let headers: MessageHeaders = [("schema", "s"), ("reply-to", "inbox.1"), ("x-custom", "c")].into_iter().collect();
+        let json = serde_json::to_value(&headers).unwrap();
+        assert_eq!(json, serde_json::json!({"schema": "s", "reply-to": "inbox.1", "x-custom": "c"}));
+        
+        let decoded: MessageHeaders = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, headers);
+        assert_eq!(decoded.reply_to(), Some("inbox.1"));
+        assert!(matches!(decoded.entries.iter().find(|(name, _)| name == "schema").unwrap().0, Cow::Borrowed(_)));
+        
+        let map: HashMap<String, String> = decoded.clone().into();
+        assert_eq!(map, headers.to_map());
+        assert_eq!(MessageHeaders::from(map), decoded);
+    }
+}
@@ -0,0 +1,296 @@
+//! Content Priority Rules
+//!
+//! Some documents should jump the queue: a subject containing "URGENT", an
+//! invoice above a threshold. Before a document task is scheduled, a cheap
+//! pre-scan reads the document's metadata, filename and the first few
+//! kilobytes of its text, and `PriorityRules` raise the task's priority when
+//! a rule matches. Rules only escalate; a task submitted as `Critical` stays
+//! `Critical`. The rule that decided is recorded in the task metadata.
+
+use crate::types::{Document, DocumentContent, Task, TaskPayload, TaskPriority};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Task metadata key naming the rule that escalated the task's priority
+pub const PRIORITY_RULE_KEY: &str = "priority_rule";
+
+/// Words marking a line that states an amount, e.g. "Total due: 12,400.00"
+const AMOUNT_MARKERS: [&str; 6] = ["total", "amount", "due", "summa", "belopp", "att betala"];
+
+/// Fields of a document that are cheap to read before processing
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PreScan {
+    /// Document metadata plus the derived `filename`, `document_type`,
+    /// `subject` and (when found) `amount` fields
+    pub fields: HashMap<String, serde_json::Value>,
+    
+    /// Start of the document text; empty for content that is not text
+    pub text_prefix: String,
+}
+
+impl PreScan {
+    /// Scan at most `max_bytes` of the document's text
+    pub fn of(document: &Document, max_bytes: usize) -> Self {
+        let text_prefix = match &document.content {
+            DocumentContent::Text(text) => prefix(text, max_bytes).to_string(),
+            // Plain text stored as bytes still yields a usable prefix
+            DocumentContent::Binary(bytes) => match std::str::from_utf8(&bytes[..bytes.len().min(max_bytes)]) {
+                Ok(text) => text.to_string(),
+                Err(e) => String::from_utf8_lossy(&bytes[..e.valid_up_to()]).into_owned(),
+            },
+            DocumentContent::Reference { .. } | DocumentContent::Stream { .. } => String::new(),
+        };
+        
+        let mut fields = document.metadata.clone();
+        fields.insert("filename".to_string(), serde_json::json!(document.filename));
+        fields.insert("document_type".to_string(), serde_json::json!(format!("{:?}", document.document_type)));
+        if !fields.contains_key("subject") {
+            if let Some(subject) = text_prefix.lines().map(str::trim).find(|line| !line.is_empty()) {
+                fields.insert("subject".to_string(), serde_json::json!(subject));
+            }
+        }
+        if !fields.contains_key("amount") {
+            if let Some(amount) = stated_amount(&text_prefix) {
+                fields.insert("amount".to_string(), serde_json::json!(amount));
+            }
+        }
+        Self { fields, text_prefix }
+    }
+    
+    /// Field as text; numbers and booleans are formatted
+    fn text(&self, field: &str) -> Option<String> {
+        match self.fields.get(field)? {
+            serde_json::Value::String(text) => Some(text.clone()),
+            serde_json::Value::Null => None,
+            value => Some(value.to_string()),
+        }
+    }
+    
+    /// Field as a number; numeric strings are parsed
+    fn number(&self, field: &str) -> Option<f64> {
+        match self.fields.get(field)? {
+            serde_json::Value::Number(number) => number.as_f64(),
+            serde_json::Value::String(text) => parse_amount(text),
+            _ => None,
+        }
+    }
+}
+
+/// Longest prefix of `text` within `max_bytes` that ends on a character boundary
+fn prefix(text: &str, max_bytes: usize) -> &str {
+    let mut end = text.len().min(max_bytes);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Largest amount on a line marked as stating one
+fn stated_amount(text: &str) -> Option<f64> {
+    text.lines()
+        .filter(|line| {
+            let line = line.to_lowercase();
+            AMOUNT_MARKERS.iter().any(|marker| line.contains(marker))
+        })
+        .flat_map(|line| line.split(|c: char| !(c.is_ascii_digit() || c == '.' || c == ',')))
+        .filter_map(parse_amount)
+        .max_by(f64::total_cmp)
+}
+
+/// Parse "12,400.50" or "12400"; commas are taken as thousands separators
+fn parse_amount(text: &str) -> Option<f64> {
+    let digits: String = text.trim().chars().filter(|c| *c != ',').collect();
+    let digits = digits.trim_end_matches('.');
+    if digits.is_empty() || !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Condition on a document's pre-scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PriorityCondition {
+    /// A field contains `value`, ignoring case
+    FieldContains { field: String, value: String },
+    /// The text prefix contains `value`, ignoring case
+    TextContains { value: String },
+    /// A numeric field is above `threshold`
+    FieldAbove { field: String, threshold: f64 },
+    /// Every condition holds
+    All { conditions: Vec<PriorityCondition> },
+    /// At least one condition holds
+    Any { conditions: Vec<PriorityCondition> },
+}
+
+impl PriorityCondition {
+    pub fn matches(&self, scan: &PreScan) -> bool {
+        match self {
+            PriorityCondition::FieldContains { field, value } => scan.text(field)
+                .is_some_and(|text| text.to_lowercase().contains(&value.to_lowercase())),
+            PriorityCondition::TextContains { value } => scan.text_prefix.to_lowercase().contains(&value.to_lowercase()),
+            PriorityCondition::FieldAbove { field, threshold } => scan.number(field).is_some_and(|number| number > *threshold),
+            PriorityCondition::All { conditions } => conditions.iter().all(|condition| condition.matches(scan)),
+            PriorityCondition::Any { conditions } => conditions.iter().any(|condition| condition.matches(scan)),
+        }
+    }
+}
+
+/// Priority a document task gets when its condition holds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriorityRule {
+    pub name: String,
+    pub condition: PriorityCondition,
+    pub priority: TaskPriority,
+}
+
+impl PriorityRule {
+    pub fn new(name: impl Into<String>, condition: PriorityCondition, priority: TaskPriority) -> Self {
+        Self { name: name.into(), condition, priority }
+    }
+}
+
+/// Rules escalating the priority of document tasks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriorityRules {
+    pub rules: Vec<PriorityRule>,
+    
+    /// Bytes of document text read by the pre-scan
+    #[serde(default = "default_pre_scan_bytes")]
+    pub pre_scan_bytes: usize,
+}
+
+fn default_pre_scan_bytes() -> usize {
+    4096
+}
+
+impl Default for PriorityRules {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            pre_scan_bytes: default_pre_scan_bytes(),
+        }
+    }
+}
+
+impl PriorityRules {
+    pub fn with_rule(mut self, rule: PriorityRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+    
+    /// Highest priority rule matching the pre-scan; the first one on ties
+    pub fn evaluate(&self, scan: &PreScan) -> Option<&PriorityRule> {
+        self.rules.iter()
+            .filter(|rule| rule.condition.matches(scan))
+            .fold(None, |best: Option<&PriorityRule>, rule| match best {
+                Some(best) if best.priority >= rule.priority => Some(best),
+                _ => Some(rule),
+            })
+    }
+    
+    /// Raise the priority of a document task if a rule calls for more
+    ///
+    /// Returns the rule that escalated the task, if any.
+    pub fn apply(&self, task: &mut Task) -> Option<String> {
+        if self.rules.is_empty() {
+            return None;
+        }
+        let TaskPayload::Document { document, .. } = &task.payload else {
+            return None;
+        };
+        let rule = self.evaluate(&PreScan::of(document, self.pre_scan_bytes))?;
+        if rule.priority <= task.priority {
+            return None;
+        }
+        
+        task.priority = rule.priority.clone();
+        task.metadata.insert(PRIORITY_RULE_KEY.to_string(), serde_json::json!(rule.name));
+        Some(rule.name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DocumentProcessingOptions, DocumentProcessingType, DocumentType, TaskStatus, TaskType};
+    use chrono::Utc;
+    use uuid::Uuid;
+    
+    fn document_task(text: &str, metadata: HashMap<String, serde_json::Value>) -> Task {
+        let document = Document {
+            id: Uuid::new_v4(),
+            filename: "mail.txt".to_string(),
+            document_type: DocumentType::Text,
+            content: DocumentContent::Text(text.to_string()),
+            metadata,
+            created_at: Utc::now(),
+            size_bytes: text.len(),
+        };
+        Task {
+            id: Uuid::new_v4(),
+            task_type: TaskType::DocumentProcessing {
+                document_type: DocumentType::Text,
+                processing_type: DocumentProcessingType::TextExtraction,
+            },
+            priority: TaskPriority::Normal,
+            status: TaskStatus::Pending,
+            payload: TaskPayload::Document { document, processing_options: DocumentProcessingOptions::default() },
+            created_at: Utc::now(),
+            deadline: None,
+            retry_count: 0,
+            max_retries: 3,
+            metadata: HashMap::new(),
+        }
+    }
+    
+    fn rules() -> PriorityRules {
+        PriorityRules::default()
+            .with_rule(PriorityRule::new("urgent-subject", PriorityCondition::FieldContains {
+                field: "subject".to_string(),
+                value: "urgent".to_string(),
+            }, TaskPriority::Critical))
+            .with_rule(PriorityRule::new("large-invoice", PriorityCondition::All { conditions: vec![
+                PriorityCondition::TextContains { value: "invoice".to_string() },
+                PriorityCondition::FieldAbove { field: "amount".to_string(), threshold: 10_000.0 },
+            ] }, TaskPriority::High))
+    }
+    
+    #[test]
+    fn test_pre_scan_derives_subject_and_amount() {
+        let task = document_task("\n  Invoice 2024-117\nItems: 3 x 1,000.00\nTotal due: 12,400.50 EUR\n", HashMap::new());
+        let TaskPayload::Document { document, .. } = &task.payload else { unreachable!() };
+        let scan = PreScan::of(document, 4096);
+        assert_eq!(scan.fields["subject"], "Invoice 2024-117");
+        assert_eq!(scan.fields["amount"], 12_400.5);
+        assert_eq!(scan.fields["document_type"], "Text");
+        
+        // Multi-byte characters are not split
+        assert_eq!(PreScan::of(document, 3).text_prefix, "\n  ");
+        assert_eq!(prefix("åäö", 3), "å");
+    }
+    
+    #[test]
+    fn test_rules_escalate_but_never_lower_priority() {
+        let rules = rules();
+        
+        let mut invoice = document_task("Invoice 17\nTotal: 12,400.00", HashMap::new());
+        assert_eq!(rules.apply(&mut invoice).as_deref(), Some("large-invoice"));
+        assert_eq!((invoice.priority.clone(), &invoice.metadata[PRIORITY_RULE_KEY]), (TaskPriority::High, &serde_json::json!("large-invoice")));
+        
+        let mut small = document_task("Invoice 18\nTotal: 99.00", HashMap::new());
+        assert_eq!(rules.apply(&mut small), None);
+        assert_eq!(small.priority, TaskPriority::Normal);
+        
+        // Both rules match; the higher priority wins, and a metadata subject beats the first line
+        let metadata = HashMap::from([("subject".to_string(), serde_json::json!("URGENT: overdue"))]);
+        let mut urgent = document_task("Invoice 19\nTotal: 50,000", metadata);
+        assert_eq!(rules.apply(&mut urgent).as_deref(), Some("urgent-subject"));
+        assert_eq!(urgent.priority, TaskPriority::Critical);
+        
+        let mut critical = document_task("Invoice 20\nTotal: 20,000", HashMap::new());
+        critical.priority = TaskPriority::Critical;
+        assert_eq!(rules.apply(&mut critical), None);
+        assert_eq!(critical.priority, TaskPriority::Critical);
+    }
+}
@@ -0,0 +1,148 @@
+//! Processing Option Profiles
+//!
+//! Tenants need different processing: one wants embeddings, another only
+//! text. Named profiles of processing options are configured once and
+//! tenants are mapped to them. At ingestion the profile of a document's
+//! tenant is resolved, and the effective options travel with the document
+//! in its metadata. Processors then honour them instead of their own
+//! defaults. The resolution is recorded in the document's provenance.
+
+use crate::bulk::TENANT_KEY;
+use crate::types::{Document, DocumentProcessingOptions};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Document metadata key holding the name of the resolved profile
+pub const PROCESSING_PROFILE_KEY: &str = "processing_profile";
+
+/// Document metadata key holding the effective processing options
+pub const PROCESSING_OPTIONS_KEY: &str = "processing_options";
+
+/// Document metadata key of the provenance record
+pub const PROVENANCE_KEY: &str = "provenance";
+
+/// Named option profiles and the profile of each tenant
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProcessingProfiles {
+    /// Option profiles by name
+    #[serde(default)]
+    pub profiles: HashMap<String, DocumentProcessingOptions>,
+    
+    /// Profile name by tenant
+    #[serde(default)]
+    pub tenants: HashMap<String, String>,
+    
+    /// Profile of documents whose tenant has no mapping; without one they keep the processor's options
+    #[serde(default)]
+    pub default_profile: Option<String>,
+}
+
+impl ProcessingProfiles {
+    /// Tenants and the default mapped to profiles that do not exist
+    pub fn validate(&self) -> Result<(), String> {
+        let unknown: Vec<String> = self.tenants.iter()
+            .map(|(tenant, profile)| (tenant.as_str(), profile))
+            .chain(self.default_profile.iter().map(|profile| ("default", profile)))
+            .filter(|(_, profile)| !self.profiles.contains_key(*profile))
+            .map(|(tenant, profile)| format!("{} -> {}", tenant, profile))
+            .collect();
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("unknown processing profiles: {}", unknown.join(", ")))
+        }
+    }
+    
+    /// Profile name and options for a tenant
+    pub fn resolve(&self, tenant: Option<&str>) -> Option<(&str, &DocumentProcessingOptions)> {
+        let name = tenant.and_then(|tenant| self.tenants.get(tenant)).or(self.default_profile.as_ref())?;
+        self.profiles.get_key_value(name).map(|(name, options)| (name.as_str(), options))
+    }
+    
+    /// Resolve the profile of a document's tenant and record the effective options on the document
+    ///
+    /// Returns the name of the applied profile.
+    pub fn apply(&self, document: &mut Document) -> Option<String> {
+        let tenant = document.metadata.get(TENANT_KEY).and_then(|value| value.as_str()).map(str::to_string);
+        let (profile, options) = self.resolve(tenant.as_deref())?;
+        let profile = profile.to_string();
+        document.metadata.insert(PROCESSING_PROFILE_KEY.to_string(), serde_json::json!(profile));
+        document.metadata.insert(PROCESSING_OPTIONS_KEY.to_string(), serde_json::json!(options));
+        
+        let provenance = document.metadata.entry(PROVENANCE_KEY.to_string()).or_insert_with(|| serde_json::json!({}));
+        if let Some(provenance) = provenance.as_object_mut() {
+            provenance.insert("processing_profile".to_string(), serde_json::json!({
+                "profile": profile,
+                "tenant": tenant,
+                "resolved_at": Utc::now(),
+            }));
+        }
+        Some(profile)
+    }
+}
+
+/// Processing options resolved for a document at ingestion, if any
+pub fn effective_options(document: &Document) -> Option<DocumentProcessingOptions> {
+    document.metadata.get(PROCESSING_OPTIONS_KEY).and_then(|options| serde_json::from_value(options.clone()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_builder::DocumentBuilder;
+    use crate::types::DocumentContent;
+    
+    fn profiles() -> ProcessingProfiles {
+        ProcessingProfiles {
+            profiles: HashMap::from([
+                ("text_only".to_string(), DocumentProcessingOptions {
+                    detect_language: false,
+                    extract_keywords: false,
+                    ..DocumentProcessingOptions::default()
+                }),
+                ("semantic".to_string(), DocumentProcessingOptions { generate_embeddings: true, ..DocumentProcessingOptions::default() }),
+            ]),
+            tenants: HashMap::from([("acme".to_string(), "semantic".to_string())]),
+            default_profile: Some("text_only".to_string()),
+        }
+    }
+    
+    fn document(tenant: Option<&str>) -> Document {
+        let builder = DocumentBuilder::new("a.txt", DocumentContent::Text("text".to_string()));
+        match tenant {
+            Some(tenant) => builder.metadata(TENANT_KEY, serde_json::json!(tenant)),
+            None => builder,
+        }.build()
+    }
+    
+    #[test]
+    fn test_tenant_profile_is_recorded_on_the_document() {
+        let profiles = profiles();
+        assert_eq!(profiles.validate(), Ok(()));
+        
+        let mut acme = document(Some("acme"));
+        assert_eq!(profiles.apply(&mut acme).as_deref(), Some("semantic"));
+        assert!(effective_options(&acme).unwrap().generate_embeddings);
+        assert_eq!(acme.metadata[PROVENANCE_KEY]["processing_profile"]["tenant"], "acme");
+        
+        // Unmapped tenants and documents without a tenant get the default profile
+        for tenant in [Some("globex"), None] {
+            let mut other = document(tenant);
+            assert_eq!(profiles.apply(&mut other).as_deref(), Some("text_only"));
+            assert!(!effective_options(&other).unwrap().extract_keywords);
+        }
+        
+        let without_default = ProcessingProfiles { default_profile: None, ..profiles };
+        let mut other = document(Some("globex"));
+        assert_eq!(without_default.apply(&mut other), None);
+        assert_eq!(effective_options(&other), None);
+    }
+    
+    #[test]
+    fn test_validate_reports_unknown_profiles() {
+        let mut profiles = profiles();
+        profiles.tenants.insert("initech".to_string(), "full".to_string());
+        assert_eq!(profiles.validate(), Err("unknown processing profiles: initech -> full".to_string()));
+    }
+}
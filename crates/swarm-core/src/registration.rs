@@ -0,0 +1,225 @@
+//! Worker Registration
+//!
+//! Workers on other machines join the swarm through the message broker
+//! instead of in-process channels. On startup a worker sends its
+//! configuration as a request on `swarm.workers.registration`. The
+//! `RegistrationService` registers it with the coordinator and acknowledges
+//! with the subject the worker's tasks will be published on. Registered
+//! workers publish their results on `swarm.tasks.results` and heartbeats on
+//! `swarm.workers.health`. A worker that shuts down announces it on
+//! `swarm.workers.deregistration`, and the coordinator requeues whatever it
+//! was still processing.
+
+use crate::service::{cancellable_sleep, CancellationToken, Service};
+use crate::traits::MessageBroker;
+use crate::types::{Message, Task, WorkerConfig};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Subject workers send their registration requests to
+pub const WORKER_REGISTRATION_SUBJECT: &str = "swarm.workers.registration";
+
+/// Subject workers announce their shutdown on
+pub const WORKER_DEREGISTRATION_SUBJECT: &str = "swarm.workers.deregistration";
+
+/// Subject registered workers publish their task results on
+pub const TASK_RESULTS_SUBJECT: &str = "swarm.tasks.results";
+
+/// Subject registered workers publish their heartbeats on
+pub const WORKER_HEALTH_SUBJECT: &str = "swarm.workers.health";
+
+/// Subject the tasks assigned to a worker are published on
+pub fn assignment_subject(worker_id: Uuid) -> String {
+    format!("swarm.workers.{}.tasks", worker_id)
+}
+
+/// A worker asking to join the swarm
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkerRegistration {
+    pub worker_id: Uuid,
+    pub config: WorkerConfig,
+}
+
+impl WorkerRegistration {
+    pub fn new(config: WorkerConfig) -> Self {
+        Self { worker_id: config.id, config }
+    }
+}
+
+/// The coordinator's answer to a registration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistrationAck {
+    pub worker_id: Uuid,
+    pub accepted: bool,
+    
+    /// Subject the worker receives its tasks on, once accepted
+    pub assignment_subject: Option<String>,
+    
+    /// Why the registration was refused
+    pub reason: Option<String>,
+}
+
+impl RegistrationAck {
+    pub fn accepted(worker_id: Uuid) -> Self {
+        Self { worker_id, accepted: true, assignment_subject: Some(assignment_subject(worker_id)), reason: None }
+    }
+    
+    pub fn refused(worker_id: Uuid, reason: impl Into<String>) -> Self {
+        Self { worker_id, accepted: false, assignment_subject: None, reason: Some(reason.into()) }
+    }
+}
+
+/// A worker leaving the swarm
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkerDeregistration {
+    pub worker_id: Uuid,
+    pub reason: String,
+}
+
+/// Membership changes handed to a running coordinator
+#[derive(Debug)]
+pub enum RegistrationRequest {
+    /// Register a worker; its task channel is sent back through `tasks`
+    Register {
+        registration: Box<WorkerRegistration>,
+        tasks: oneshot::Sender<mpsc::Receiver<Task>>,
+    },
+    /// Unregister a worker and requeue its in-flight tasks
+    Deregister { worker_id: Uuid },
+}
+
+/// Serves worker registrations from the broker for a coordinator
+///
+/// Tasks the coordinator assigns to a registered worker are published on
+/// the worker's assignment subject until it deregisters.
+pub struct RegistrationService {
+    broker: Arc<dyn MessageBroker>,
+    requests: mpsc::UnboundedSender<RegistrationRequest>,
+    poll_interval: Duration,
+    forwarders: HashMap<Uuid, JoinHandle<()>>,
+}
+
+impl RegistrationService {
+    /// Service handing registrations to the coordinator behind `requests`
+    ///
+    /// See `SwarmCoordinator::accept_registrations`.
+    pub fn new(broker: Arc<dyn MessageBroker>, requests: mpsc::UnboundedSender<RegistrationRequest>) -> Self {
+        Self { broker, requests, poll_interval: Duration::from_millis(10), forwarders: HashMap::new() }
+    }
+    
+    /// Check for registrations at a custom interval
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+    
+    /// Workers currently registered through this service
+    pub fn registered_workers(&self) -> usize {
+        self.forwarders.len()
+    }
+    
+    async fn register(&mut self, message: &Message) -> RegistrationAck {
+        let registration: WorkerRegistration = match serde_json::from_slice(&message.payload) {
+            Ok(registration) => registration,
+            Err(e) => return RegistrationAck::refused(Uuid::nil(), format!("malformed registration: {}", e)),
+        };
+        let worker_id = registration.worker_id;
+        // A worker registering again after a restart replaces its earlier registration
+        if let Some(forwarder) = self.forwarders.remove(&worker_id) {
+            forwarder.abort();
+            let _ = self.requests.send(RegistrationRequest::Deregister { worker_id });
+        }
+        
+        let (tasks, receiver) = oneshot::channel();
+        let registered = self.requests.send(RegistrationRequest::Register { registration: Box::new(registration), tasks }).is_ok();
+        let Some(mut assigned) = (if registered { receiver.await.ok() } else { None }) else {
+            return RegistrationAck::refused(worker_id, "coordinator is not running");
+        };
+        
+        let broker = self.broker.clone();
+        let subject = assignment_subject(worker_id);
+        self.forwarders.insert(worker_id, tokio::spawn(async move {
+            while let Some(task) = assigned.recv().await {
+                let published = match serde_json::to_vec(&task) {
+                    Ok(payload) => broker.publish(&subject, &payload).await,
+                    Err(e) => Err(e.into()),
+                };
+                if let Err(e) = published {
+                    tracing::error!("Failed to publish task {} to {}: {}", task.id, subject, e);
+                }
+            }
+        }));
+        tracing::info!("Worker {} registered over the broker", worker_id);
+        RegistrationAck::accepted(worker_id)
+    }
+    
+    fn deregister(&mut self, message: &Message) {
+        let deregistration: WorkerDeregistration = match serde_json::from_slice(&message.payload) {
+            Ok(deregistration) => deregistration,
+            Err(e) => return tracing::warn!("Ignoring malformed deregistration: {}", e),
+        };
+        let Some(forwarder) = self.forwarders.remove(&deregistration.worker_id) else {
+            return tracing::debug!("Ignoring deregistration of unknown worker {}", deregistration.worker_id);
+        };
+        forwarder.abort();
+        tracing::info!("Worker {} deregistered: {}", deregistration.worker_id, deregistration.reason);
+        let _ = self.requests.send(RegistrationRequest::Deregister { worker_id: deregistration.worker_id });
+    }
+}
+
+#[async_trait]
+impl Service for RegistrationService {
+    fn name(&self) -> &str {
+        "worker-registration"
+    }
+    
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        let mut registrations = self.broker.subscribe(WORKER_REGISTRATION_SUBJECT).await?;
+        let mut deregistrations = self.broker.subscribe(WORKER_DEREGISTRATION_SUBJECT).await?;
+        
+        loop {
+            while let Some(message) = registrations.next_message()? {
+                let ack = self.register(&message).await;
+                match message.reply_to() {
+                    Some(reply_to) => self.broker.publish(reply_to, &serde_json::to_vec(&ack)?).await?,
+                    None => tracing::warn!("Registration of worker {} has no reply subject", ack.worker_id),
+                }
+            }
+            while let Some(message) = deregistrations.next_message()? {
+                self.deregister(&message);
+            }
+            if cancellable_sleep(&cancel, self.poll_interval).await {
+                break;
+            }
+        }
+        
+        for (_, forwarder) in self.forwarders.drain() {
+            forwarder.abort();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_acks_carry_the_assignment_subject() {
+        let worker_id = Uuid::new_v4();
+        let ack = RegistrationAck::accepted(worker_id);
+        assert_eq!(ack.assignment_subject, Some(format!("swarm.workers.{}.tasks", worker_id)));
+        
+        let refused = RegistrationAck::refused(worker_id, "coordinator is not running");
+        assert!(!refused.accepted && refused.assignment_subject.is_none());
+        let json = serde_json::to_value(&refused).unwrap();
+        assert_eq!(serde_json::from_value::<RegistrationAck>(json).unwrap(), refused);
+    }
+}
@@ -0,0 +1,218 @@
+//! Document Rejections
+//!
+//! A document refused at ingestion is reported with a stable reason code,
+//! the limit it exceeded, a hint on how to get it accepted and the
+//! correlation ID of the submission, instead of a free-form error message.
+//! Rejections are published on `swarm.documents.rejected`.
+
+use crate::types::{Document, DocumentType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+/// Subject rejected documents are reported on
+pub const REJECTION_SUBJECT: &str = "swarm.documents.rejected";
+
+/// Document metadata key producers can set to correlate their submissions
+pub const CORRELATION_ID_KEY: &str = "correlation_id";
+
+/// Why a document was rejected
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum RejectionReason {
+    /// Larger than the configured size limit
+    TooLarge,
+    /// Document type the swarm is not configured to process
+    UnsupportedType,
+    /// The producer or tenant exceeded its quota
+    QuotaExceeded,
+    /// Other members of the document's group did not arrive in time
+    IncompleteGroup,
+}
+
+impl RejectionReason {
+    /// Stable code for producers to match on
+    pub fn code(&self) -> &'static str {
+        match self {
+            RejectionReason::TooLarge => "too_large",
+            RejectionReason::UnsupportedType => "unsupported_type",
+            RejectionReason::QuotaExceeded => "quota_exceeded",
+            RejectionReason::IncompleteGroup => "incomplete_group",
+        }
+    }
+}
+
+/// The limit a rejected document exceeded
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RejectionLimit {
+    pub limit: u64,
+    pub actual: u64,
+    /// What `limit` and `actual` count, e.g. `bytes` or `documents`
+    pub unit: String,
+}
+
+/// A document refused at ingestion
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DocumentRejection {
+    /// The producer's correlation ID, or the document ID when none was given
+    pub correlation_id: Uuid,
+    pub document_id: Option<Uuid>,
+    pub filename: String,
+    pub reason: RejectionReason,
+    pub message: String,
+    pub limit: Option<RejectionLimit>,
+    /// What the producer can do to get the document accepted
+    pub remediation: String,
+    pub rejected_at: DateTime<Utc>,
+}
+
+impl DocumentRejection {
+    fn new(filename: &str, reason: RejectionReason, message: String, remediation: String) -> Self {
+        Self {
+            correlation_id: Uuid::new_v4(),
+            document_id: None,
+            filename: filename.to_string(),
+            reason,
+            message,
+            limit: None,
+            remediation,
+            rejected_at: Utc::now(),
+        }
+    }
+    
+    /// A file over the size limit, rejected before it was read
+    pub fn file_too_large(filename: &str, size_bytes: usize, max_bytes: usize) -> Self {
+        let mut rejection = Self::new(
+            filename,
+            RejectionReason::TooLarge,
+            format!("Document too large: {} bytes (max: {} bytes)", size_bytes, max_bytes),
+            format!("Split the document into parts of at most {} bytes, or submit it as a stream or storage reference", max_bytes),
+        );
+        rejection.limit = Some(RejectionLimit {
+            limit: max_bytes as u64,
+            actual: size_bytes as u64,
+            unit: "bytes".to_string(),
+        });
+        rejection
+    }
+    
+    /// A document over the size limit
+    pub fn too_large(document: &Document, max_bytes: usize) -> Self {
+        Self::file_too_large(&document.filename, document.size_bytes, max_bytes).for_document(document)
+    }
+    
+    /// A document of a type that is not processed
+    pub fn unsupported_type(document: &Document, supported: &[DocumentType]) -> Self {
+        let supported = supported.iter().map(|document_type| format!("{:?}", document_type)).collect::<Vec<_>>();
+        Self::new(
+            &document.filename,
+            RejectionReason::UnsupportedType,
+            format!("Unsupported document type: {:?}", document.document_type),
+            format!("Convert the document to one of the supported types: {}", supported.join(", ")),
+        ).for_document(document)
+    }
+    
+    /// A submission over the producer's quota
+    pub fn quota_exceeded(filename: &str, quota: &str, limit: u64, actual: u64) -> Self {
+        let mut rejection = Self::new(
+            filename,
+            RejectionReason::QuotaExceeded,
+            format!("Quota {} exceeded: {} of {} documents", quota, actual, limit),
+            format!("Wait for submitted documents to finish before sending more, or ask for a higher {} quota", quota),
+        );
+        rejection.limit = Some(RejectionLimit {
+            limit,
+            actual,
+            unit: "documents".to_string(),
+        });
+        rejection
+    }
+    
+    /// A member of a document group whose other members did not all arrive
+    pub fn incomplete_group(document: &Document, group_id: &str, size: usize, received: usize) -> Self {
+        let mut rejection = Self::new(
+            &document.filename,
+            RejectionReason::IncompleteGroup,
+            format!("Document group {} incomplete: {} of {} documents arrived", group_id, received, size),
+            format!("Submit all {} documents of group {} together, or correct its group size", size, group_id),
+        ).for_document(document);
+        rejection.limit = Some(RejectionLimit {
+            limit: size as u64,
+            actual: received as u64,
+            unit: "documents".to_string(),
+        });
+        rejection
+    }
+    
+    /// Attach the document's ID and the correlation ID from its metadata
+    pub fn for_document(mut self, document: &Document) -> Self {
+        self.document_id = Some(document.id);
+        self.correlation_id = document.metadata.get(CORRELATION_ID_KEY)
+            .and_then(|value| value.as_str())
+            .and_then(|value| Uuid::parse_str(value).ok())
+            .unwrap_or(document.id);
+        self
+    }
+    
+    /// Use the given correlation ID
+    pub fn with_correlation_id(mut self, correlation_id: Uuid) -> Self {
+        self.correlation_id = correlation_id;
+        self
+    }
+}
+
+impl fmt::Display for DocumentRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} rejected ({}): {}", self.filename, self.reason.code(), self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DocumentContent;
+    use std::collections::HashMap;
+    
+    fn document(metadata: HashMap<String, serde_json::Value>) -> Document {
+        Document {
+            id: Uuid::new_v4(),
+            filename: "scan.tiff".to_string(),
+            document_type: DocumentType::Image,
+            content: DocumentContent::Binary(vec![0; 2048]),
+            metadata,
+            created_at: Utc::now(),
+            size_bytes: 2048,
+        }
+    }
+    
+    #[test]
+    fn test_too_large_reports_limit_and_correlation_id() {
+        let correlation_id = Uuid::new_v4();
+        let document = document(HashMap::from([(CORRELATION_ID_KEY.to_string(), serde_json::json!(correlation_id.to_string()))]));
+        let rejection = DocumentRejection::too_large(&document, 1024);
+        
+        assert_eq!(rejection.correlation_id, correlation_id);
+        assert_eq!(rejection.document_id, Some(document.id));
+        assert_eq!(rejection.limit, Some(RejectionLimit { limit: 1024, actual: 2048, unit: "bytes".to_string() }));
+        assert!(rejection.remediation.contains("1024 bytes"));
+        
+        let json = serde_json::to_value(&rejection).unwrap();
+        assert_eq!(json["reason"], "too_large");
+        assert_eq!(rejection.to_string(), "scan.tiff rejected (too_large): Document too large: 2048 bytes (max: 1024 bytes)");
+    }
+    
+    #[test]
+    fn test_unsupported_type_lists_supported_types() {
+        let document = document(HashMap::new());
+        let rejection = DocumentRejection::unsupported_type(&document, &[DocumentType::Pdf, DocumentType::Text]);
+        
+        assert_eq!(rejection.reason, RejectionReason::UnsupportedType);
+        assert_eq!(rejection.correlation_id, document.id);
+        assert!(rejection.remediation.ends_with("Pdf, Text"));
+        assert!(rejection.limit.is_none());
+    }
+}
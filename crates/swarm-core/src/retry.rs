@@ -0,0 +1,92 @@
+//! Task Retries and Dead Letters
+//!
+//! Failed tasks are retried until `max_retries` is exhausted, waiting an
+//! exponentially growing backoff between attempts. Tasks that fail
+//! permanently become dead letters carrying the original task and the error
+//! of every attempt.
+
+use crate::types::Task;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Subject permanently failed tasks are published to
+pub const DEAD_LETTER_SUBJECT: &str = "swarm.tasks.deadletter";
+
+/// Backoff between retries of a failed task
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryPolicy {
+    /// Delay before the first retry
+    pub initial_backoff_ms: u64,
+    
+    /// Factor applied to the delay for each further retry
+    pub multiplier: f64,
+    
+    /// Upper bound for the delay
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: 1000,
+            multiplier: 2.0,
+            max_backoff_ms: 60_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (starting at 1)
+    pub fn backoff(&self, retry: u32) -> chrono::Duration {
+        let exponent = retry.saturating_sub(1).min(63) as i32;
+        let delay_ms = (self.initial_backoff_ms as f64 * self.multiplier.max(1.0).powi(exponent))
+            .min(self.max_backoff_ms as f64);
+        chrono::Duration::milliseconds(delay_ms as i64)
+    }
+}
+
+/// One failed processing attempt
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FailedAttempt {
+    /// Attempt number, starting at 1
+    pub attempt: u32,
+    pub worker_id: Option<Uuid>,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// A failed task waiting for its backoff to pass
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScheduledRetry {
+    pub task: Task,
+    pub retry_at: DateTime<Utc>,
+}
+
+/// A task that failed on every attempt
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DeadLetter {
+    /// The task with its original payload
+    pub task: Task,
+    
+    /// Errors of all attempts, oldest first
+    pub errors: Vec<FailedAttempt>,
+    
+    pub dead_lettered_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_backoff_grows_exponentially_up_to_max() {
+        let policy = RetryPolicy {
+            initial_backoff_ms: 100,
+            multiplier: 3.0,
+            max_backoff_ms: 1000,
+        };
+        let delays: Vec<i64> = (1..=4).map(|retry| policy.backoff(retry).num_milliseconds()).collect();
+        assert_eq!(delays, vec![100, 300, 900, 1000]);
+    }
+}
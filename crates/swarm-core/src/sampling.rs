@@ -0,0 +1,240 @@
+//! Trace Sampling
+//!
+//! Head-based sampling of per-document tracing. The first component to see a
+//! document (usually the reader) makes the decision from a percentage, and
+//! downstream components honour it: it travels in document and task metadata
+//! and in the `trace-sampled` message header. Decisions derive from the
+//! document, task or message ID, so components without a propagated decision
+//! still agree. Critical tasks and failures are always traced.
+
+use crate::message_headers::MessageHeaders;
+use crate::types::{Document, Message, Task, TaskPayload, TaskPriority, TaskResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Message header carrying the sampling decision (`1` or `0`)
+pub const TRACE_SAMPLED_HEADER: &str = "trace-sampled";
+
+/// Document, task and result metadata key carrying the sampling decision
+pub const TRACE_SAMPLED_KEY: &str = "trace_sampled";
+
+/// Sampling buckets, giving a resolution of 0.01%
+const BUCKETS: u64 = 10_000;
+
+/// Tracing sampling configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    /// Percentage of documents traced, from 0.0 to 100.0
+    pub sample_percent: f64,
+    
+    /// Trace failures even when their document was not sampled
+    pub always_sample_errors: bool,
+    
+    /// Task priorities that are always traced
+    pub always_sample_priorities: Vec<TaskPriority>,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            sample_percent: 1.0,
+            always_sample_errors: true,
+            always_sample_priorities: vec![TaskPriority::Critical],
+        }
+    }
+}
+
+/// Whether a document's journey through the pipeline is traced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SamplingDecision {
+    Sampled,
+    NotSampled,
+}
+
+impl SamplingDecision {
+    pub fn is_sampled(&self) -> bool {
+        *self == SamplingDecision::Sampled
+    }
+    
+    /// Header (and metadata) representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SamplingDecision::Sampled => "1",
+            SamplingDecision::NotSampled => "0",
+        }
+    }
+    
+    /// Parse a propagated decision, accepting `1`/`0` and `true`/`false`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "1" | "true" => Some(SamplingDecision::Sampled),
+            "0" | "false" => Some(SamplingDecision::NotSampled),
+            _ => None,
+        }
+    }
+    
+    /// Decision recorded in metadata, if any
+    pub fn from_metadata(metadata: &HashMap<String, serde_json::Value>) -> Option<Self> {
+        match metadata.get(TRACE_SAMPLED_KEY)? {
+            serde_json::Value::String(value) => Self::parse(value),
+            serde_json::Value::Bool(sampled) => Some(Self::from(*sampled)),
+            _ => None,
+        }
+    }
+    
+    /// Decision carried in message headers, if any
+    pub fn from_headers(headers: &MessageHeaders) -> Option<Self> {
+        headers.trace_sampled().and_then(Self::parse)
+    }
+    
+    /// Record the decision in metadata
+    pub fn record(&self, metadata: &mut HashMap<String, serde_json::Value>) {
+        metadata.insert(TRACE_SAMPLED_KEY.to_string(), serde_json::Value::String(self.as_str().to_string()));
+    }
+}
+
+impl From<bool> for SamplingDecision {
+    fn from(sampled: bool) -> Self {
+        if sampled { SamplingDecision::Sampled } else { SamplingDecision::NotSampled }
+    }
+}
+
+/// Makes and propagates sampling decisions
+#[derive(Debug, Clone, Default)]
+pub struct TraceSampler {
+    config: SamplingConfig,
+}
+
+impl TraceSampler {
+    pub fn new(config: SamplingConfig) -> Self {
+        Self { config }
+    }
+    
+    pub fn config(&self) -> &SamplingConfig {
+        &self.config
+    }
+    
+    /// Head decision for an ID; the same ID always gets the same decision
+    pub fn head_decision(&self, id: Uuid) -> SamplingDecision {
+        let (high, low) = id.as_u64_pair();
+        let bucket = (high ^ low) % BUCKETS;
+        let threshold = (self.config.sample_percent.clamp(0.0, 100.0) * (BUCKETS as f64 / 100.0)).round() as u64;
+        SamplingDecision::from(bucket < threshold)
+    }
+    
+    /// Decision for a document: propagated, or made from its ID
+    pub fn sample_document(&self, document: &Document) -> SamplingDecision {
+        SamplingDecision::from_metadata(&document.metadata).unwrap_or_else(|| self.head_decision(document.id))
+    }
+    
+    /// Decision for a task: always sampled for configured priorities, otherwise
+    /// propagated from the task or its document, or made from the document or task ID
+    pub fn sample_task(&self, task: &Task) -> SamplingDecision {
+        if self.config.always_sample_priorities.contains(&task.priority) {
+            return SamplingDecision::Sampled;
+        }
+        if let Some(decision) = SamplingDecision::from_metadata(&task.metadata) {
+            return decision;
+        }
+        match &task.payload {
+            TaskPayload::Document { document, .. } => self.sample_document(document),
+            _ => self.head_decision(task.id),
+        }
+    }
+    
+    /// Decision for a message: propagated in its headers, or made from its ID
+    pub fn sample_message(&self, message: &Message) -> SamplingDecision {
+        SamplingDecision::from_headers(&message.headers).unwrap_or_else(|| self.head_decision(message.id))
+    }
+    
+    /// Decision once processing has failed
+    pub fn on_error(&self, decision: SamplingDecision) -> SamplingDecision {
+        if self.config.always_sample_errors { SamplingDecision::Sampled } else { decision }
+    }
+    
+    /// Record the decision for a task on its result, upgraded if the task failed
+    pub fn record_result(&self, decision: SamplingDecision, result: &mut TaskResult) {
+        let decision = match result.error {
+            Some(_) => self.on_error(decision),
+            None => decision,
+        };
+        decision.record(&mut result.metadata);
+    }
+    
+    /// Span for one pipeline stage of an item, disabled unless sampled
+    pub fn span(&self, decision: SamplingDecision, stage: &'static str, id: Uuid) -> tracing::Span {
+        match decision {
+            SamplingDecision::Sampled => tracing::info_span!("swarm.trace", stage, %id),
+            SamplingDecision::NotSampled => tracing::Span::none(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_builder::TaskBuilder;
+    use crate::types::{DocumentContent, DocumentType, TaskType, TextAnalysisOptions, TextAnalysisType};
+    use chrono::Utc;
+    
+    fn create_test_task(priority: TaskPriority) -> Task {
+        let task_type = TaskType::TextAnalysis { analysis_type: TextAnalysisType::SentimentAnalysis };
+        TaskBuilder::new(task_type, TaskPayload::Text { content: "text".to_string(), analysis_options: TextAnalysisOptions::default() })
+            .priority(priority)
+            .build()
+    }
+    
+    #[test]
+    fn test_head_sampling_rate_is_deterministic() {
+        let sampler = TraceSampler::new(SamplingConfig { sample_percent: 10.0, ..Default::default() });
+        let ids: Vec<Uuid> = (0..20_000).map(|_| Uuid::new_v4()).collect();
+        let sampled = ids.iter().filter(|id| sampler.head_decision(**id).is_sampled()).count();
+        assert!((1_600..2_400).contains(&sampled), "sampled {} of 20000", sampled);
+        assert!(ids.iter().all(|id| sampler.head_decision(*id) == sampler.head_decision(*id)));
+        
+        let never = TraceSampler::new(SamplingConfig { sample_percent: 0.0, ..Default::default() });
+        let always = TraceSampler::new(SamplingConfig { sample_percent: 100.0, ..Default::default() });
+        assert!(ids.iter().all(|id| !never.head_decision(*id).is_sampled() && always.head_decision(*id).is_sampled()));
+    }
+    
+    #[test]
+    fn test_decisions_propagate_and_are_overridden() {
+        let never = TraceSampler::new(SamplingConfig { sample_percent: 0.0, ..Default::default() });
+        
+        // A document sampled upstream stays sampled, and so does its task
+        let mut document = Document {
+            id: Uuid::new_v4(),
+            filename: "a.txt".to_string(),
+            document_type: DocumentType::Text,
+            content: DocumentContent::Text("text".to_string()),
+            metadata: HashMap::new(),
+            created_at: Utc::now(),
+            size_bytes: 4,
+        };
+        SamplingDecision::Sampled.record(&mut document.metadata);
+        assert!(never.sample_document(&document).is_sampled());
+        let mut task = create_test_task(TaskPriority::Normal);
+        task.payload = TaskPayload::Document { document, processing_options: Default::default() };
+        assert!(never.sample_task(&task).is_sampled());
+        
+        // Critical tasks and failures are always sampled
+        assert!(!never.sample_task(&create_test_task(TaskPriority::Normal)).is_sampled());
+        assert!(never.sample_task(&create_test_task(TaskPriority::Critical)).is_sampled());
+        assert!(never.on_error(SamplingDecision::NotSampled).is_sampled());
+        let no_errors = TraceSampler::new(SamplingConfig { sample_percent: 0.0, always_sample_errors: false, ..Default::default() });
+        assert!(!no_errors.on_error(SamplingDecision::NotSampled).is_sampled());
+        
+        let mut message = Message {
+            id: Uuid::new_v4(),
+            subject: "swarm.tasks".to_string(),
+            payload: Vec::new(),
+            headers: MessageHeaders::new(),
+            timestamp: Utc::now(),
+            ttl_ms: None,
+        };
+        assert!(!never.sample_message(&message).is_sampled());
+        message.headers.insert(TRACE_SAMPLED_HEADER, "1");
+        assert!(never.sample_message(&message).is_sampled());
+    }
+}
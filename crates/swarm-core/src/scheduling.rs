@@ -0,0 +1,265 @@
+//! Capability-Based Scheduling
+//!
+//! The coordinator hands each task to the available worker that scores
+//! highest for it. A worker qualifies through a capability listing the exact
+//! task type, through its worker type (a document processor supporting the
+//! document type, say), or as a generalist that declares no capabilities.
+//! Among qualifying workers, a task's preferred worker type, a faster
+//! performance profile and a lighter load raise the score.
+//!
+//! Workers can be labelled with the host and zone they run in. Tasks that
+//! share a `spread_group` (replicas or speculative copies of the same work)
+//! are then kept out of failure domains already running one of them, so a
+//! single host or zone failure cannot take out every copy.
+
+use crate::types::{Task, TaskType, WorkerConfig, WorkerType};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Task metadata key naming the worker type the task should preferably run on
+pub const PREFERRED_WORKER_TYPE_KEY: &str = "preferred_worker_type";
+
+/// Worker metadata key naming the host (or VM) the worker runs on
+pub const FAILURE_DOMAIN_HOST_KEY: &str = "host";
+
+/// Worker metadata key naming the zone the worker runs in
+pub const FAILURE_DOMAIN_ZONE_KEY: &str = "zone";
+
+/// Task metadata key grouping tasks to spread over distinct failure domains
+pub const SPREAD_GROUP_KEY: &str = "spread_group";
+
+/// Host and zone a worker runs in, from its registration metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FailureDomain {
+    #[serde(default)]
+    pub host: Option<String>,
+    #[serde(default)]
+    pub zone: Option<String>,
+}
+
+impl FailureDomain {
+    pub fn of(config: &WorkerConfig) -> Self {
+        let label = |key: &str| config.metadata.get(key).and_then(|value| value.as_str()).map(str::to_string);
+        Self {
+            host: label(FAILURE_DOMAIN_HOST_KEY),
+            zone: label(FAILURE_DOMAIN_ZONE_KEY),
+        }
+    }
+    
+    pub fn is_labelled(&self) -> bool {
+        self.host.is_some() || self.zone.is_some()
+    }
+    
+    /// Domain at `level`; a worker without that label is a domain of its own
+    pub fn key(&self, level: SpreadLevel, worker_id: Uuid) -> String {
+        let label = match level {
+            SpreadLevel::Host => &self.host,
+            SpreadLevel::Zone => &self.zone,
+        };
+        label.clone().unwrap_or_else(|| format!("worker:{}", worker_id))
+    }
+}
+
+/// Failure domain that tasks of a spread group are kept apart at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpreadLevel {
+    Host,
+    Zone,
+}
+
+/// How a worker qualifies for a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapabilityMatch {
+    /// The worker declares no capabilities and takes any task
+    Generalist,
+    /// The worker's type supports the task
+    WorkerType,
+    /// A capability of the worker lists the task type
+    Exact,
+}
+
+impl CapabilityMatch {
+    fn strength(&self) -> f64 {
+        match self {
+            CapabilityMatch::Generalist => 0.2,
+            CapabilityMatch::WorkerType => 0.6,
+            CapabilityMatch::Exact => 1.0,
+        }
+    }
+}
+
+/// Weights of the parts of a worker's score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchedulingWeights {
+    /// Weight of the capability match (1.0 exact, 0.6 worker type, 0.2 generalist)
+    pub capability: f64,
+    
+    /// Bonus for the worker type named in the task's `preferred_worker_type`
+    pub preferred_type: f64,
+    
+    /// Weight of processing speed, from the matching performance profile
+    pub performance: f64,
+    
+    /// Penalty per unit of load (in-flight tasks over `max_concurrent_tasks`)
+    pub load: f64,
+}
+
+impl Default for SchedulingWeights {
+    fn default() -> Self {
+        Self {
+            capability: 1.0,
+            preferred_type: 1.0,
+            performance: 0.3,
+            load: 1.0,
+        }
+    }
+}
+
+/// Score of a worker for a task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkerScore {
+    pub capability: CapabilityMatch,
+    pub preferred: bool,
+    pub score: f64,
+}
+
+/// Name of a worker type as used in `preferred_worker_type`, e.g. `document_processor`
+///
+/// Custom workers go by their own name.
+pub fn worker_type_name(worker_type: &WorkerType) -> &str {
+    match worker_type {
+        WorkerType::DocumentProcessor { .. } => "document_processor",
+        WorkerType::TextAnalyzer { .. } => "text_analyzer",
+        WorkerType::VectorIndexer { .. } => "vector_indexer",
+        WorkerType::Custom { name, .. } => name,
+    }
+}
+
+/// Whether a worker type supports a task type
+fn worker_type_supports(worker_type: &WorkerType, task_type: &TaskType) -> bool {
+    match (worker_type, task_type) {
+        (WorkerType::DocumentProcessor { supported_types }, TaskType::DocumentProcessing { document_type, .. }) => {
+            supported_types.contains(document_type)
+        }
+        (WorkerType::TextAnalyzer { supported_analyses }, TaskType::TextAnalysis { analysis_type }) => {
+            supported_analyses.contains(analysis_type)
+        }
+        (WorkerType::VectorIndexer { supported_indexes }, TaskType::VectorIndexing { index_type }) => {
+            supported_indexes.contains(index_type)
+        }
+        (WorkerType::Custom { name, version }, TaskType::Custom { name: task_name, version: task_version }) => {
+            name == task_name && version == task_version
+        }
+        _ => false,
+    }
+}
+
+/// Whether a worker can run a task type, by capability, by worker type or as a generalist
+pub fn supports_task_type(worker: &WorkerConfig, task_type: &TaskType) -> bool {
+    worker.capabilities.iter().any(|capability| capability.supported_task_types.contains(task_type))
+        || worker_type_supports(&worker.worker_type, task_type)
+        || worker.capabilities.is_empty()
+}
+
+impl SchedulingWeights {
+    /// Score a worker with `in_flight` assigned tasks; `None` if it cannot run the task
+    pub fn score(&self, worker: &WorkerConfig, task: &Task, in_flight: usize) -> Option<WorkerScore> {
+        let exact = worker.capabilities.iter()
+            .find(|capability| capability.supported_task_types.contains(&task.task_type));
+        let (capability, profile) = match exact {
+            Some(capability) => (CapabilityMatch::Exact, &capability.performance_profile),
+            None if worker_type_supports(&worker.worker_type, &task.task_type) => (CapabilityMatch::WorkerType, &worker.performance_profile),
+            None if worker.capabilities.is_empty() => (CapabilityMatch::Generalist, &worker.performance_profile),
+            None => return None,
+        };
+        
+        let preferred = task.metadata.get(PREFERRED_WORKER_TYPE_KEY)
+            .and_then(|value| value.as_str())
+            .is_some_and(|name| name == worker_type_name(&worker.worker_type));
+        // 1.0 for instant processing, 0.5 at one second per task
+        let speed = 1.0 / (1.0 + profile.avg_processing_time_ms as f64 / 1000.0);
+        let load = in_flight as f64 / worker.max_concurrent_tasks.max(1) as f64;
+        
+        let score = self.capability * capability.strength()
+            + if preferred { self.preferred_type } else { 0.0 }
+            + self.performance * speed
+            - self.load * load;
+        Some(WorkerScore { capability, preferred, score })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DocumentProcessingType, DocumentType, PerformanceProfile, TaskPayload, TaskPriority, TaskStatus, WorkerCapability};
+    use chrono::Utc;
+    use std::collections::HashMap;
+    
+    fn profile(avg_processing_time_ms: u64) -> PerformanceProfile {
+        PerformanceProfile { avg_processing_time_ms, memory_usage_mb: 64, cpu_intensity: 0.5, throughput_per_second: 1.0 }
+    }
+    
+    fn worker(worker_type: WorkerType, capabilities: Vec<WorkerCapability>) -> WorkerConfig {
+        WorkerConfig {
+            id: Uuid::new_v4(),
+            name: "w".to_string(),
+            worker_type,
+            max_concurrent_tasks: 2,
+            capabilities,
+            performance_profile: profile(1000),
+            health_check_interval_ms: 1000,
+            shutdown_timeout_ms: 1000,
+            metadata: HashMap::new(),
+        }
+    }
+    
+    fn pdf_task() -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            task_type: TaskType::DocumentProcessing { document_type: DocumentType::Pdf, processing_type: DocumentProcessingType::TextExtraction },
+            priority: TaskPriority::Normal,
+            status: TaskStatus::Pending,
+            payload: TaskPayload::Custom { data: Vec::new(), format: "raw".to_string() },
+            created_at: Utc::now(),
+            deadline: None,
+            retry_count: 0,
+            max_retries: 3,
+            metadata: HashMap::new(),
+        }
+    }
+    
+    #[test]
+    fn test_capability_match_ranks_workers() {
+        let weights = SchedulingWeights::default();
+        let task = pdf_task();
+        let capability = WorkerCapability {
+            name: "pdf".to_string(),
+            version: "1".to_string(),
+            supported_task_types: vec![task.task_type.clone()],
+            max_concurrent_tasks: 2,
+            performance_profile: profile(200),
+            metadata: HashMap::new(),
+        };
+        let specialist = worker(WorkerType::Custom { name: "ocr".to_string(), version: "1".to_string() }, vec![capability.clone()]);
+        let processor = worker(WorkerType::DocumentProcessor { supported_types: vec![DocumentType::Pdf] }, vec![]);
+        let generalist = worker(WorkerType::Custom { name: "any".to_string(), version: "1".to_string() }, vec![]);
+        let unrelated = worker(WorkerType::TextAnalyzer { supported_analyses: vec![] }, vec![WorkerCapability { supported_task_types: vec![], ..capability }]);
+        
+        let scores: Vec<_> = [&specialist, &processor, &generalist].iter()
+            .map(|worker| weights.score(worker, &task, 0).unwrap())
+            .collect();
+        assert_eq!(scores.iter().map(|score| score.capability).collect::<Vec<_>>(),
+            vec![CapabilityMatch::Exact, CapabilityMatch::WorkerType, CapabilityMatch::Generalist]);
+        assert!(scores[0].score > scores[1].score && scores[1].score > scores[2].score);
+        assert_eq!(weights.score(&unrelated, &task, 0), None);
+        
+        // Preference and load can outweigh a better match
+        let mut preferring = pdf_task();
+        preferring.metadata.insert(PREFERRED_WORKER_TYPE_KEY.to_string(), serde_json::json!("document_processor"));
+        let preferred = weights.score(&processor, &preferring, 0).unwrap();
+        assert!(preferred.preferred && preferred.score > weights.score(&specialist, &preferring, 0).unwrap().score);
+        assert!(weights.score(&specialist, &task, 2).unwrap().score < scores[1].score);
+    }
+}
@@ -0,0 +1,168 @@
+//! Message and Result Schemas
+//!
+//! Published JSON Schemas for the payloads other teams consume. Every
+//! serialized document, task and task result carries the URL and version of
+//! its schema in the `schema` and `schema-version` message headers. With the
+//! `schema` feature enabled the schemas are generated from the types
+//! themselves (see the `swarm-schema` binary); the generated files are kept
+//! in the repository's `schemas/` directory, which the URLs point to.
+
+/// Version of the published schemas, bumped on incompatible changes
+pub const SCHEMA_VERSION: &str = "1";
+
+/// Location the `schemas/` directory is published at
+pub const SCHEMA_BASE_URL: &str = "https://raw.githubusercontent.com/kpernyer/aprio-swarm/main/schemas";
+
+/// Message header holding the payload's schema URL
+pub const SCHEMA_HEADER: &str = "schema";
+
+/// Message header holding the schema version
+pub const SCHEMA_VERSION_HEADER: &str = "schema-version";
+
+/// Types with a published schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum SchemaKind {
+    Document,
+    Task,
+    TaskResult,
+    DocumentProcessingResult,
+    Message,
+    DocumentRejection,
+}
+
+impl SchemaKind {
+    pub const ALL: [SchemaKind; 6] = [
+        SchemaKind::Document,
+        SchemaKind::Task,
+        SchemaKind::TaskResult,
+        SchemaKind::DocumentProcessingResult,
+        SchemaKind::Message,
+        SchemaKind::DocumentRejection,
+    ];
+    
+    /// File name of the schema, e.g. `task-result.schema.json`
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            SchemaKind::Document => "document.schema.json",
+            SchemaKind::Task => "task.schema.json",
+            SchemaKind::TaskResult => "task-result.schema.json",
+            SchemaKind::DocumentProcessingResult => "document-processing-result.schema.json",
+            SchemaKind::Message => "message.schema.json",
+            SchemaKind::DocumentRejection => "document-rejection.schema.json",
+        }
+    }
+    
+    /// Published URL of the current schema version
+    pub fn url(&self) -> String {
+        format!("{}/v{}/{}", SCHEMA_BASE_URL, SCHEMA_VERSION, self.file_name())
+    }
+    
+    /// Published schema this build was compiled against
+    pub fn published(&self) -> &'static str {
+        match self {
+            SchemaKind::Document => include_str!("../../../schemas/v1/document.schema.json"),
+            SchemaKind::Task => include_str!("../../../schemas/v1/task.schema.json"),
+            SchemaKind::TaskResult => include_str!("../../../schemas/v1/task-result.schema.json"),
+            SchemaKind::DocumentProcessingResult => include_str!("../../../schemas/v1/document-processing-result.schema.json"),
+            SchemaKind::Message => include_str!("../../../schemas/v1/message.schema.json"),
+            SchemaKind::DocumentRejection => include_str!("../../../schemas/v1/document-rejection.schema.json"),
+        }
+    }
+    
+    /// Short hash of the published schema, equal across builds with the same message shape
+    pub fn fingerprint(&self) -> String {
+        crate::document_builder::sha256_hex(self.published().as_bytes())[..16].to_string()
+    }
+    
+    /// `schema` and `schema-version` headers for a payload of this kind
+    pub fn headers(&self) -> [(&'static str, String); 2] {
+        [
+            (SCHEMA_HEADER, self.url()),
+            (SCHEMA_VERSION_HEADER, SCHEMA_VERSION.to_string()),
+        ]
+    }
+}
+
+#[cfg(feature = "schema")]
+mod generate {
+    use super::*;
+    use crate::rejection::DocumentRejection;
+    use crate::types::{Document, DocumentProcessingResult, Message, Task, TaskResult};
+    use schemars::schema::RootSchema;
+    use std::path::{Path, PathBuf};
+    
+    impl SchemaKind {
+        /// JSON Schema of this kind, with `$id` set to its URL
+        pub fn schema(&self) -> RootSchema {
+            let mut schema = match self {
+                SchemaKind::Document => schemars::schema_for!(Document),
+                SchemaKind::Task => schemars::schema_for!(Task),
+                SchemaKind::TaskResult => schemars::schema_for!(TaskResult),
+                SchemaKind::DocumentProcessingResult => schemars::schema_for!(DocumentProcessingResult),
+                SchemaKind::Message => schemars::schema_for!(Message),
+                SchemaKind::DocumentRejection => schemars::schema_for!(DocumentRejection),
+            };
+            schema.schema.metadata().id = Some(self.url());
+            schema
+        }
+    }
+    
+    /// Write all schemas to `<directory>/v<version>/`, returning the written files
+    pub fn write_schemas(directory: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let directory = directory.join(format!("v{}", SCHEMA_VERSION));
+        std::fs::create_dir_all(&directory)?;
+        
+        let mut written = Vec::new();
+        for kind in SchemaKind::ALL {
+            let path = directory.join(kind.file_name());
+            let mut json = serde_json::to_string_pretty(&kind.schema())?;
+            json.push('\n');
+            std::fs::write(&path, json)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(feature = "schema")]
+pub use generate::write_schemas;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_schema_urls_and_headers() {
+        assert_eq!(
+            SchemaKind::TaskResult.url(),
+            "https://raw.githubusercontent.com/kpernyer/aprio-swarm/main/schemas/v1/task-result.schema.json"
+        );
+        let headers = SchemaKind::Task.headers();
+        assert_eq!(headers[0], ("schema", SchemaKind::Task.url()));
+        assert_eq!(headers[1], ("schema-version", "1".to_string()));
+    }
+    
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_published_schemas_are_current() {
+        let published = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../schemas");
+        for kind in SchemaKind::ALL {
+            let path = published.join(format!("v{}", SCHEMA_VERSION)).join(kind.file_name());
+            let committed: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            assert_eq!(committed, serde_json::to_value(kind.schema()).unwrap(),
+                "{} is out of date; regenerate with `cargo run -p swarm-core --features schema --bin swarm-schema`", path.display());
+        }
+    }
+    
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_task_result_schema_describes_fields() {
+        let schema = serde_json::to_value(SchemaKind::TaskResult.schema()).unwrap();
+        assert_eq!(schema["$id"], SchemaKind::TaskResult.url());
+        let properties = schema["properties"].as_object().unwrap();
+        for field in ["task_id", "status", "result", "error", "processing_time_ms", "completed_at", "metadata"] {
+            assert!(properties.contains_key(field), "missing {}", field);
+        }
+        assert!(schema["definitions"].get("DocumentProcessingResult").is_some());
+    }
+}
@@ -0,0 +1,78 @@
+//! Service Run Loops
+//!
+//! Long-running components (readers, discovery, the coordinator, periodic
+//! sweeps) share one entrypoint: `run(cancel)` runs until the token is
+//! cancelled and then returns. Loops wait in `tokio::select!` on the token
+//! and their timer or channel, so cancellation only ever interrupts a wait;
+//! a scan or state update that has started runs to completion first.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::time::Duration;
+
+pub use tokio_util::sync::CancellationToken;
+
+/// A component driven by a run loop
+#[async_trait]
+pub trait Service: Send {
+    /// Name used in logs
+    fn name(&self) -> &str;
+    
+    /// Run until `cancel` is cancelled or the work is done
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()>;
+}
+
+/// Sleep for `duration` unless cancelled first; returns whether it was cancelled
+pub async fn cancellable_sleep(cancel: &CancellationToken, duration: Duration) -> bool {
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => true,
+        _ = tokio::time::sleep(duration) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    struct Ticker {
+        ticks: usize,
+    }
+    
+    #[async_trait]
+    impl Service for Ticker {
+        fn name(&self) -> &str {
+            "ticker"
+        }
+        
+        async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+            while !cancellable_sleep(&cancel, Duration::from_millis(5)).await {
+                self.ticks += 1;
+            }
+            Ok(())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_run_returns_after_cancellation() {
+        let cancel = CancellationToken::new();
+        let mut ticker = Ticker { ticks: 0 };
+        let stopper = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            stopper.cancel();
+        });
+        
+        tokio::time::timeout(Duration::from_secs(1), ticker.run(cancel)).await.unwrap().unwrap();
+        assert!(ticker.ticks > 0);
+    }
+    
+    #[tokio::test]
+    async fn test_cancelled_sleep_returns_immediately() {
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let started = tokio::time::Instant::now();
+        assert!(cancellable_sleep(&cancel, Duration::from_secs(60)).await);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,205 @@
+//! Service Level Indicators
+//!
+//! A canonical set of duration indicators is recorded under fixed names by
+//! every component, so dashboards and SLOs do not depend on which part of
+//! the swarm measured what. Durations are recorded as histograms in
+//! milliseconds; each recording also counts towards the indicator's
+//! throughput counter. Components record through an `SliRecorder`, which
+//! does nothing until a `MetricsCollector` is attached.
+
+use crate::traits::MetricsCollector;
+use crate::types::MetricValue;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Documents ingested per time unit, counted by the reader
+pub const DOCUMENTS_INGESTED_COUNTER: &str = "sli.documents_ingested";
+
+/// Tasks taken from the queue by a worker, counted by the coordinator
+pub const TASKS_DISPATCHED_COUNTER: &str = "sli.tasks_dispatched";
+
+/// Tasks processed by workers, whatever the outcome
+pub const TASKS_PROCESSED_COUNTER: &str = "sli.tasks_processed";
+
+/// Messages published to the broker
+pub const MESSAGES_PUBLISHED_COUNTER: &str = "sli.messages_published";
+
+/// Tasks that reached a final outcome, counted by the coordinator
+pub const TASKS_FINISHED_COUNTER: &str = "sli.tasks_finished";
+
+/// Canonical duration indicators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sli {
+    /// From a document's last modification to it being read
+    IngestLatency,
+    /// From a task's creation to its dispatch to a worker
+    QueueWait,
+    /// Time a worker spent processing a task
+    ProcessingDuration,
+    /// Time the broker took to accept a published message
+    PublishLatency,
+    /// From a task's creation to its final outcome
+    EndToEndLatency,
+}
+
+impl Sli {
+    pub const ALL: [Sli; 5] = [Sli::IngestLatency, Sli::QueueWait, Sli::ProcessingDuration, Sli::PublishLatency, Sli::EndToEndLatency];
+    
+    /// Histogram name of the indicator
+    pub fn name(self) -> &'static str {
+        match self {
+            Sli::IngestLatency => "sli.ingest_latency_ms",
+            Sli::QueueWait => "sli.queue_wait_ms",
+            Sli::ProcessingDuration => "sli.processing_duration_ms",
+            Sli::PublishLatency => "sli.publish_latency_ms",
+            Sli::EndToEndLatency => "sli.end_to_end_latency_ms",
+        }
+    }
+    
+    /// Counter incremented with every recording
+    pub fn throughput_counter(self) -> &'static str {
+        match self {
+            Sli::IngestLatency => DOCUMENTS_INGESTED_COUNTER,
+            Sli::QueueWait => TASKS_DISPATCHED_COUNTER,
+            Sli::ProcessingDuration => TASKS_PROCESSED_COUNTER,
+            Sli::PublishLatency => MESSAGES_PUBLISHED_COUNTER,
+            Sli::EndToEndLatency => TASKS_FINISHED_COUNTER,
+        }
+    }
+}
+
+/// Records indicators to an optional metrics collector
+#[derive(Clone, Default)]
+pub struct SliRecorder {
+    metrics: Option<Arc<dyn MetricsCollector>>,
+}
+
+impl SliRecorder {
+    pub fn new(metrics: Arc<dyn MetricsCollector>) -> Self {
+        Self { metrics: Some(metrics) }
+    }
+    
+    /// Recorder for an optional collector
+    pub fn from_option(metrics: Option<Arc<dyn MetricsCollector>>) -> Self {
+        Self { metrics }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.metrics.is_some()
+    }
+    
+    /// Record a duration in milliseconds and count it towards throughput
+    pub fn observe(&self, sli: Sli, duration_ms: f64, tags: &[(&str, &str)]) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_histogram(sli.name(), duration_ms.max(0.0), tags);
+            metrics.increment_counter(sli.throughput_counter(), tags);
+        }
+    }
+    
+    /// Record the time since a timestamp; timestamps in the future count as zero
+    pub fn observe_since(&self, sli: Sli, since: DateTime<Utc>, tags: &[(&str, &str)]) {
+        if self.is_enabled() {
+            self.observe(sli, (Utc::now() - since).num_milliseconds() as f64, tags);
+        }
+    }
+    
+    /// Record the time elapsed since `start`
+    pub fn observe_elapsed(&self, sli: Sli, start: Instant, tags: &[(&str, &str)]) {
+        self.observe(sli, start.elapsed().as_secs_f64() * 1000.0, tags);
+    }
+}
+
+impl std::fmt::Debug for SliRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SliRecorder").field("enabled", &self.is_enabled()).finish()
+    }
+}
+
+/// Collector keeping metrics in memory, for tests and single-process deployments
+///
+/// Metrics are kept by name; tags are not told apart.
+#[derive(Debug, Default)]
+pub struct MemoryMetricsCollector {
+    metrics: Mutex<HashMap<String, MetricValue>>,
+}
+
+impl MemoryMetricsCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Values recorded to a histogram so far
+    pub fn histogram(&self, name: &str) -> Vec<f64> {
+        match self.metrics.lock().unwrap().get(name) {
+            Some(MetricValue::Histogram(values)) => values.clone(),
+            _ => Vec::new(),
+        }
+    }
+    
+    /// Current value of a counter
+    pub fn counter(&self, name: &str) -> u64 {
+        match self.metrics.lock().unwrap().get(name) {
+            Some(MetricValue::Counter(count)) => *count,
+            _ => 0,
+        }
+    }
+}
+
+impl MetricsCollector for MemoryMetricsCollector {
+    fn record_metric(&self, name: &str, value: f64, _tags: &[(&str, &str)]) {
+        self.metrics.lock().unwrap().insert(name.to_string(), MetricValue::Gauge(value));
+    }
+    
+    fn increment_counter(&self, name: &str, _tags: &[(&str, &str)]) {
+        let mut metrics = self.metrics.lock().unwrap();
+        match metrics.entry(name.to_string()).or_insert(MetricValue::Counter(0)) {
+            MetricValue::Counter(count) => *count += 1,
+            other => *other = MetricValue::Counter(1),
+        }
+    }
+    
+    fn record_histogram(&self, name: &str, value: f64, _tags: &[(&str, &str)]) {
+        let mut metrics = self.metrics.lock().unwrap();
+        match metrics.entry(name.to_string()).or_insert_with(|| MetricValue::Histogram(Vec::new())) {
+            MetricValue::Histogram(values) => values.push(value),
+            other => *other = MetricValue::Histogram(vec![value]),
+        }
+    }
+    
+    fn get_metrics(&self) -> HashMap<String, MetricValue> {
+        self.metrics.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_observations_feed_histogram_and_throughput() {
+        let metrics = Arc::new(MemoryMetricsCollector::new());
+        let recorder = SliRecorder::new(metrics.clone());
+        
+        recorder.observe(Sli::ProcessingDuration, 120.0, &[("task_type", "text_extraction")]);
+        recorder.observe(Sli::ProcessingDuration, 80.0, &[]);
+        recorder.observe_since(Sli::QueueWait, Utc::now() + chrono::Duration::seconds(5), &[]);
+        
+        assert_eq!(metrics.histogram("sli.processing_duration_ms"), vec![120.0, 80.0]);
+        assert_eq!(metrics.counter(TASKS_PROCESSED_COUNTER), 2);
+        assert_eq!(metrics.histogram(Sli::QueueWait.name()), vec![0.0]);
+        assert_eq!(metrics.counter(TASKS_DISPATCHED_COUNTER), 1);
+        assert!(metrics.histogram(Sli::PublishLatency.name()).is_empty());
+    }
+    
+    #[test]
+    fn test_disabled_recorder_records_nothing() {
+        let recorder = SliRecorder::default();
+        assert!(!recorder.is_enabled());
+        recorder.observe_elapsed(Sli::PublishLatency, Instant::now(), &[]);
+        
+        let names: std::collections::HashSet<_> = Sli::ALL.iter().map(|sli| sli.name()).collect();
+        assert_eq!(names.len(), Sli::ALL.len());
+    }
+}
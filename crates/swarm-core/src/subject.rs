@@ -0,0 +1,209 @@
+//! Message Subjects
+//!
+//! `Subject` is a validated broker subject. It can only be created by
+//! parsing, so a malformed subject is rejected where it is configured
+//! rather than when a message is first published. Components get their
+//! subjects from the routing configuration instead of string literals, so
+//! a misspelt subject does not compile.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Why a string is not a valid subject
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SubjectError {
+    #[error("Subject is empty")]
+    Empty,
+    
+    #[error("Subject {subject:?} contains an empty token")]
+    EmptyToken { subject: String },
+    
+    #[error("Subject {subject:?} contains whitespace")]
+    Whitespace { subject: String },
+    
+    #[error("Subject {subject:?} uses a wildcard inside token {token:?}")]
+    MisplacedWildcard { subject: String, token: String },
+}
+
+/// A validated, dot-separated broker subject
+///
+/// Tokens are non-empty and free of whitespace. `*` may stand for a whole
+/// token and `>` for the trailing tokens, making the subject a pattern for
+/// subscriptions.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Subject(String);
+
+impl Subject {
+    /// Parse and validate a subject
+    pub fn parse(subject: impl Into<String>) -> Result<Self, SubjectError> {
+        let subject = subject.into();
+        if subject.is_empty() {
+            return Err(SubjectError::Empty);
+        }
+        if subject.chars().any(char::is_whitespace) {
+            return Err(SubjectError::Whitespace { subject });
+        }
+        let token_count = subject.split('.').count();
+        let invalid = subject.split('.').enumerate().find(|&(index, token)| match token {
+            "" => true,
+            "*" => false,
+            ">" => index + 1 != token_count,
+            token => token.contains(['*', '>']),
+        });
+        match invalid.map(|(_, token)| token.to_string()) {
+            None => Ok(Self(subject)),
+            Some(token) if token.is_empty() => Err(SubjectError::EmptyToken { subject }),
+            Some(token) => Err(SubjectError::MisplacedWildcard { subject, token }),
+        }
+    }
+    
+    /// Subject made of `tokens` joined with dots
+    pub fn from_tokens(tokens: &[&str]) -> Result<Self, SubjectError> {
+        Self::parse(tokens.join("."))
+    }
+    
+    /// This subject extended by further tokens
+    pub fn child(&self, tokens: &[&str]) -> Result<Self, SubjectError> {
+        let mut subject = self.0.clone();
+        for token in tokens {
+            subject.push('.');
+            subject.push_str(token);
+        }
+        Self::parse(subject)
+    }
+    
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+    
+    pub fn tokens(&self) -> impl Iterator<Item = &str> {
+        self.0.split('.')
+    }
+    
+    /// Whether the subject contains wildcards and can only be subscribed to
+    pub fn is_wildcard(&self) -> bool {
+        self.tokens().any(|token| token == "*" || token == ">")
+    }
+    
+    /// Whether this subject, as a subscription pattern, covers `subject`
+    pub fn matches(&self, subject: &str) -> bool {
+        subject_matches(&self.0, subject)
+    }
+    
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+/// Whether `pattern` covers `subject`
+///
+/// `*` matches one token and `>` one or more trailing tokens. Wildcards in
+/// `subject` (a wildcard subscription) are only covered by wildcards in
+/// `pattern` that are at least as broad.
+pub fn subject_matches(pattern: &str, subject: &str) -> bool {
+    let mut pattern_tokens = pattern.split('.');
+    let mut subject_tokens = subject.split('.');
+    loop {
+        match (pattern_tokens.next(), subject_tokens.next()) {
+            (Some(">"), Some(_)) => return true,
+            (Some("*"), Some(token)) if token != ">" => {}
+            (Some(expected), Some(token)) if expected == token => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Subject {
+    type Err = SubjectError;
+    
+    fn from_str(subject: &str) -> Result<Self, Self::Err> {
+        Self::parse(subject)
+    }
+}
+
+impl TryFrom<String> for Subject {
+    type Error = SubjectError;
+    
+    fn try_from(subject: String) -> Result<Self, Self::Error> {
+        Self::parse(subject)
+    }
+}
+
+impl TryFrom<&str> for Subject {
+    type Error = SubjectError;
+    
+    fn try_from(subject: &str) -> Result<Self, Self::Error> {
+        Self::parse(subject)
+    }
+}
+
+impl From<Subject> for String {
+    fn from(subject: Subject) -> Self {
+        subject.0
+    }
+}
+
+impl AsRef<str> for Subject {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for Subject {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Subject {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_valid_subjects() {
+        let subject: Subject = "swarm.documents.incoming".parse().unwrap();
+        assert_eq!(subject, "swarm.documents.incoming");
+        assert_eq!(subject.to_string(), "swarm.documents.incoming");
+        assert_eq!(subject.tokens().count(), 3);
+        assert!(!subject.is_wildcard());
+        
+        assert!(Subject::parse("swarm.tasks.*").unwrap().is_wildcard());
+        assert!(Subject::parse("swarm.>").unwrap().is_wildcard());
+        assert_eq!(Subject::from_tokens(&["swarm", "tasks"]).unwrap(), "swarm.tasks");
+        assert_eq!(subject.child(&["eu", "west"]).unwrap(), "swarm.documents.incoming.eu.west");
+    }
+    
+    #[test]
+    fn test_invalid_subjects() {
+        assert_eq!(Subject::parse(""), Err(SubjectError::Empty));
+        assert!(matches!(Subject::parse("swarm..results"), Err(SubjectError::EmptyToken { .. })));
+        assert!(matches!(Subject::parse("swarm.tasks."), Err(SubjectError::EmptyToken { .. })));
+        assert!(matches!(Subject::parse("swarm.task results"), Err(SubjectError::Whitespace { .. })));
+        assert!(matches!(Subject::parse("swarm.>.results"), Err(SubjectError::MisplacedWildcard { .. })));
+        assert!(matches!(Subject::parse("swarm.tasks*"), Err(SubjectError::MisplacedWildcard { .. })));
+        assert!(Subject::parse("swarm").unwrap().child(&[""]).is_err());
+    }
+    
+    #[test]
+    fn test_subjects_are_validated_when_deserialized() {
+        let subject: Subject = serde_json::from_str(r#""swarm.tasks.results""#).unwrap();
+        assert_eq!(serde_json::to_string(&subject).unwrap(), r#""swarm.tasks.results""#);
+        assert!(serde_json::from_str::<Subject>(r#""swarm..results""#).is_err());
+    }
+}
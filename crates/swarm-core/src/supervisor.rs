@@ -0,0 +1,518 @@
+//! Component Supervision
+//!
+//! Runs several services in one process, configured from one file. Enabled
+//! components start in dependency order and stop in reverse order. A
+//! component whose run loop panics or fails is recreated from its factory
+//! after an exponential backoff. All components share one in-memory broker,
+//! so a small deployment can host the reader, workers and coordinator in a
+//! single binary.
+
+use crate::memory_broker::MemoryBroker;
+use crate::service::{cancellable_sleep, CancellationToken, Service};
+use crate::traits::MessageBroker;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// Builds a fresh instance of a component, on start and on every restart
+pub type ComponentFactory = Arc<dyn Fn(&ComponentContext) -> Result<Box<dyn Service>> + Send + Sync>;
+
+/// What a component factory is given
+#[derive(Clone)]
+pub struct ComponentContext {
+    /// Component name in the configuration
+    pub name: String,
+    
+    /// The component's own `settings` section
+    pub settings: serde_json::Value,
+    
+    /// Broker shared by all components of the supervisor
+    pub broker: Arc<dyn MessageBroker>,
+}
+
+impl ComponentContext {
+    /// Deserialize the settings section, using the default when it is absent
+    pub fn settings<T: DeserializeOwned + Default>(&self) -> Result<T> {
+        if self.settings.is_null() {
+            return Ok(T::default());
+        }
+        serde_json::from_value(self.settings.clone())
+            .with_context(|| format!("invalid settings for component {}", self.name))
+    }
+}
+
+/// Delay before restarting a failed component
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackoffConfig {
+    /// Delay before the first restart
+    pub initial_backoff_ms: u64,
+    
+    /// Upper bound for the doubled delays
+    pub max_backoff_ms: u64,
+    
+    /// Restarts before the component is given up on (unlimited when unset)
+    pub max_restarts: Option<u32>,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30000,
+            max_restarts: Some(10),
+        }
+    }
+}
+
+impl BackoffConfig {
+    /// Delay before restart number `restart` (starting at 1)
+    pub fn delay(&self, restart: u32) -> Duration {
+        let factor = 1u64.checked_shl(restart.saturating_sub(1)).unwrap_or(u64::MAX);
+        Duration::from_millis(self.initial_backoff_ms.saturating_mul(factor).min(self.max_backoff_ms))
+    }
+}
+
+/// One component of the supervision tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentConfig {
+    /// Whether the component is started
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    
+    /// Components that must be started before this one and stopped after it
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    
+    /// Overrides the supervisor's backoff for this component
+    #[serde(default)]
+    pub backoff: Option<BackoffConfig>,
+    
+    /// Component-specific configuration handed to its factory
+    #[serde(default)]
+    pub settings: serde_json::Value,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for ComponentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            depends_on: Vec::new(),
+            backoff: None,
+            settings: serde_json::Value::Null,
+        }
+    }
+}
+
+/// Supervisor configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupervisorConfig {
+    /// Restart backoff for components without their own
+    #[serde(default)]
+    pub backoff: BackoffConfig,
+    
+    /// Time each component gets to stop once cancelled
+    #[serde(default = "default_shutdown_timeout_ms")]
+    pub shutdown_timeout_ms: u64,
+    
+    /// Components by name
+    #[serde(default)]
+    pub components: BTreeMap<String, ComponentConfig>,
+}
+
+fn default_shutdown_timeout_ms() -> u64 {
+    30000
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            backoff: BackoffConfig::default(),
+            shutdown_timeout_ms: default_shutdown_timeout_ms(),
+            components: BTreeMap::new(),
+        }
+    }
+}
+
+impl SupervisorConfig {
+    /// Load the configuration from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read supervisor config {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("invalid supervisor config {}", path.display()))
+    }
+    
+    /// Add a component
+    pub fn with_component(mut self, name: impl Into<String>, component: ComponentConfig) -> Self {
+        self.components.insert(name.into(), component);
+        self
+    }
+    
+    /// Enabled components, each after the components it depends on
+    pub fn start_order(&self) -> Result<Vec<String>> {
+        let mut order = Vec::new();
+        let mut visiting = Vec::new();
+        for (name, component) in &self.components {
+            if component.enabled {
+                self.visit(name, &mut visiting, &mut order)?;
+            }
+        }
+        Ok(order)
+    }
+    
+    fn visit(&self, name: &str, visiting: &mut Vec<String>, order: &mut Vec<String>) -> Result<()> {
+        if order.iter().any(|started| started == name) {
+            return Ok(());
+        }
+        if visiting.iter().any(|pending| pending == name) {
+            anyhow::bail!("dependency cycle: {} -> {}", visiting.join(" -> "), name);
+        }
+        
+        let component = self.components.get(name)
+            .with_context(|| format!("{} depends on unknown component {}", visiting.last().map(String::as_str).unwrap_or("?"), name))?;
+        if !component.enabled {
+            anyhow::bail!("{} depends on disabled component {}", visiting.last().map(String::as_str).unwrap_or("?"), name);
+        }
+        
+        visiting.push(name.to_string());
+        for dependency in &component.depends_on {
+            self.visit(dependency, visiting, order)?;
+        }
+        visiting.pop();
+        order.push(name.to_string());
+        Ok(())
+    }
+}
+
+/// Lifecycle state of a supervised component
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ComponentState {
+    Running,
+    /// Waiting out the backoff after a failure
+    Restarting,
+    /// Returned or cancelled
+    Stopped,
+    /// Out of restarts
+    Failed,
+}
+
+/// Status of a supervised component
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ComponentStatus {
+    pub name: String,
+    pub state: ComponentState,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+}
+
+type SharedStatuses = Arc<RwLock<BTreeMap<String, ComponentStatus>>>;
+
+/// Starts, restarts and stops the configured components
+pub struct Supervisor {
+    config: SupervisorConfig,
+    factories: HashMap<String, ComponentFactory>,
+    broker: Arc<dyn MessageBroker>,
+    statuses: SharedStatuses,
+}
+
+impl Supervisor {
+    /// Create a supervisor with a fresh in-memory broker
+    pub fn new(config: SupervisorConfig) -> Self {
+        Self {
+            config,
+            factories: HashMap::new(),
+            broker: Arc::new(MemoryBroker::new()),
+            statuses: Arc::new(RwLock::new(BTreeMap::new())),
+        }
+    }
+    
+    /// Share another broker between the components
+    pub fn with_broker(mut self, broker: Arc<dyn MessageBroker>) -> Self {
+        self.broker = broker;
+        self
+    }
+    
+    /// Register the factory for a component name
+    ///
+    /// Factories of components that are not configured, or are disabled, are
+    /// never called.
+    pub fn with_factory<F>(mut self, name: impl Into<String>, factory: F) -> Self
+    where
+        F: Fn(&ComponentContext) -> Result<Box<dyn Service>> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.into(), Arc::new(factory));
+        self
+    }
+    
+    /// Broker shared by the components
+    pub fn broker(&self) -> Arc<dyn MessageBroker> {
+        self.broker.clone()
+    }
+    
+    /// Status of every started component
+    pub async fn statuses(&self) -> Vec<ComponentStatus> {
+        self.statuses.read().await.values().cloned().collect()
+    }
+    
+    fn supervised(&self, name: &str) -> Result<SupervisedComponent> {
+        let factory = self.factories.get(name)
+            .with_context(|| format!("no factory registered for component {}", name))?
+            .clone();
+        let component = &self.config.components[name];
+        Ok(SupervisedComponent {
+            context: ComponentContext {
+                name: name.to_string(),
+                settings: component.settings.clone(),
+                broker: self.broker.clone(),
+            },
+            factory,
+            backoff: component.backoff.clone().unwrap_or_else(|| self.config.backoff.clone()),
+            statuses: self.statuses.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl Service for Supervisor {
+    fn name(&self) -> &str {
+        "supervisor"
+    }
+    
+    /// Start the components, and stop them in reverse order once cancelled
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        let components = self.config.start_order()?
+            .iter()
+            .map(|name| self.supervised(name))
+            .collect::<Result<Vec<_>>>()?;
+        
+        let mut running = Vec::new();
+        for component in components {
+            let name = component.context.name.clone();
+            tracing::info!("Starting component {}", name);
+            let token = CancellationToken::new();
+            let handle = tokio::spawn(component.run(token.clone()));
+            running.push((name, token, handle));
+        }
+        
+        cancel.cancelled().await;
+        
+        let timeout = Duration::from_millis(self.config.shutdown_timeout_ms);
+        for (name, token, handle) in running.into_iter().rev() {
+            tracing::info!("Stopping component {}", name);
+            token.cancel();
+            if tokio::time::timeout(timeout, handle).await.is_err() {
+                tracing::warn!("Component {} did not stop within {:?}", name, timeout);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keeps one component running
+struct SupervisedComponent {
+    context: ComponentContext,
+    factory: ComponentFactory,
+    backoff: BackoffConfig,
+    statuses: SharedStatuses,
+}
+
+impl SupervisedComponent {
+    async fn run(self, cancel: CancellationToken) {
+        let mut restarts = 0;
+        
+        loop {
+            self.set_status(ComponentState::Running, restarts, None).await;
+            let outcome = match (self.factory)(&self.context) {
+                Ok(mut service) => {
+                    let token = cancel.clone();
+                    match tokio::spawn(async move { service.run(token).await }).await {
+                        Ok(result) => result,
+                        Err(e) => Err(anyhow::anyhow!(e)),
+                    }
+                }
+                Err(e) => Err(e),
+            };
+            
+            let error = match outcome {
+                Ok(()) => {
+                    self.set_status(ComponentState::Stopped, restarts, None).await;
+                    return;
+                }
+                Err(e) => e.to_string(),
+            };
+            
+            if cancel.is_cancelled() {
+                self.set_status(ComponentState::Stopped, restarts, Some(error)).await;
+                return;
+            }
+            
+            tracing::error!("Component {} failed: {}", self.context.name, error);
+            if self.backoff.max_restarts.is_some_and(|max_restarts| restarts >= max_restarts) {
+                tracing::error!("Component {} will not be restarted", self.context.name);
+                self.set_status(ComponentState::Failed, restarts, Some(error)).await;
+                return;
+            }
+            
+            restarts += 1;
+            self.set_status(ComponentState::Restarting, restarts, Some(error)).await;
+            if cancellable_sleep(&cancel, self.backoff.delay(restarts)).await {
+                self.set_status(ComponentState::Stopped, restarts, None).await;
+                return;
+            }
+            
+            tracing::info!("Restarting component {} (restart {})", self.context.name, restarts);
+        }
+    }
+    
+    async fn set_status(&self, state: ComponentState, restarts: u32, error: Option<String>) {
+        let mut statuses = self.statuses.write().await;
+        let status = statuses.entry(self.context.name.clone()).or_insert_with(|| ComponentStatus {
+            name: self.context.name.clone(),
+            state,
+            restarts,
+            last_error: None,
+        });
+        status.state = state;
+        status.restarts = restarts;
+        if error.is_some() {
+            status.last_error = error;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+    
+    /// Records when it starts and stops, and panics on its first `panics` runs
+    struct Probe {
+        name: String,
+        events: Arc<Mutex<Vec<String>>>,
+        runs: Arc<AtomicU32>,
+        panics: u32,
+    }
+    
+    #[async_trait]
+    impl Service for Probe {
+        fn name(&self) -> &str {
+            &self.name
+        }
+        
+        async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+            if self.runs.fetch_add(1, Ordering::SeqCst) < self.panics {
+                panic!("{} crashed", self.name);
+            }
+            self.events.lock().unwrap().push(format!("start {}", self.name));
+            cancel.cancelled().await;
+            self.events.lock().unwrap().push(format!("stop {}", self.name));
+            Ok(())
+        }
+    }
+    
+    fn probe_factory(events: &Arc<Mutex<Vec<String>>>, panics: u32) -> impl Fn(&ComponentContext) -> Result<Box<dyn Service>> {
+        let events = events.clone();
+        let runs = Arc::new(AtomicU32::new(0));
+        move |context| Ok(Box::new(Probe {
+            name: context.name.clone(),
+            events: events.clone(),
+            runs: runs.clone(),
+            panics,
+        }) as Box<dyn Service>)
+    }
+    
+    fn depends_on(names: &[&str]) -> ComponentConfig {
+        ComponentConfig {
+            depends_on: names.iter().map(|name| name.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn test_start_order_follows_dependencies() {
+        let config = SupervisorConfig::default()
+            .with_component("workers", depends_on(&["coordinator"]))
+            .with_component("reader", depends_on(&["workers", "coordinator"]))
+            .with_component("coordinator", depends_on(&[]))
+            .with_component("metrics", ComponentConfig { enabled: false, ..Default::default() });
+        assert_eq!(config.start_order().unwrap(), vec!["coordinator", "workers", "reader"]);
+        
+        let cyclic = SupervisorConfig::default()
+            .with_component("a", depends_on(&["b"]))
+            .with_component("b", depends_on(&["a"]));
+        assert!(cyclic.start_order().unwrap_err().to_string().contains("cycle"));
+        
+        let disabled = config.with_component("exporter", depends_on(&["metrics"]));
+        assert!(disabled.start_order().unwrap_err().to_string().contains("disabled component metrics"));
+    }
+    
+    #[tokio::test]
+    async fn test_components_start_in_order_and_stop_in_reverse() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let config = SupervisorConfig::default()
+            .with_component("coordinator", depends_on(&[]))
+            .with_component("workers", depends_on(&["coordinator"]));
+        let mut supervisor = Supervisor::new(config)
+            .with_factory("coordinator", probe_factory(&events, 0))
+            .with_factory("workers", probe_factory(&events, 0));
+        
+        let cancel = CancellationToken::new();
+        let stopper = cancel.clone();
+        let watched = events.clone();
+        tokio::spawn(async move {
+            while watched.lock().unwrap().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            stopper.cancel();
+        });
+        tokio::time::timeout(Duration::from_secs(5), supervisor.run(cancel)).await.unwrap().unwrap();
+        
+        assert_eq!(*events.lock().unwrap(), vec!["start coordinator", "start workers", "stop workers", "stop coordinator"]);
+        assert!(supervisor.statuses().await.iter().all(|status| status.state == ComponentState::Stopped));
+    }
+    
+    #[tokio::test]
+    async fn test_panicking_component_is_restarted_with_backoff() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut config = SupervisorConfig::default().with_component("flaky", depends_on(&[]));
+        config.backoff = BackoffConfig {
+            initial_backoff_ms: 10,
+            max_backoff_ms: 20,
+            max_restarts: Some(3),
+        };
+        assert_eq!(config.backoff.delay(1), Duration::from_millis(10));
+        assert_eq!(config.backoff.delay(3), Duration::from_millis(20));
+        
+        let supervisor = Supervisor::new(config).with_factory("flaky", probe_factory(&events, 2));
+        let statuses = supervisor.statuses.clone();
+        let cancel = CancellationToken::new();
+        let mut running = supervisor;
+        let handle = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { running.run(cancel).await }
+        });
+        
+        while events.lock().unwrap().is_empty() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let status = statuses.read().await["flaky"].clone();
+        assert_eq!(status.state, ComponentState::Running);
+        assert_eq!(status.restarts, 2);
+        assert!(status.last_error.unwrap().contains("panicked"));
+        
+        cancel.cancel();
+        handle.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+//! Task Builder
+//!
+//! Builds `Task` values with the defaults a fresh submission has: a random
+//! ID, normal priority, pending status, created now and three retries.
+
+use crate::types::{Document, DocumentProcessingType, Task, TaskPayload, TaskPriority, TaskStatus, TaskType};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Retries a task gets unless set otherwise
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Builder for `Task`
+#[derive(Debug, Clone)]
+pub struct TaskBuilder {
+    task_type: TaskType,
+    payload: TaskPayload,
+    priority: TaskPriority,
+    created_at: Option<DateTime<Utc>>,
+    deadline: Option<DateTime<Utc>>,
+    max_retries: u32,
+    metadata: HashMap<String, serde_json::Value>,
+}
+
+impl TaskBuilder {
+    /// Start building a task of the given type and payload
+    pub fn new(task_type: TaskType, payload: TaskPayload) -> Self {
+        Self {
+            task_type,
+            payload,
+            priority: TaskPriority::Normal,
+            created_at: None,
+            deadline: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Start building a task processing `document` with default options
+    pub fn document(document: Document, processing_type: DocumentProcessingType) -> Self {
+        let task_type = TaskType::DocumentProcessing { document_type: document.document_type.clone(), processing_type };
+        Self::new(task_type, TaskPayload::Document { document, processing_options: Default::default() })
+    }
+
+    /// Set the priority
+    pub fn priority(mut self, priority: TaskPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Set the creation timestamp (defaults to now)
+    pub fn created_at(mut self, created_at: DateTime<Utc>) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// Set the deadline
+    pub fn deadline(mut self, deadline: DateTime<Utc>) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Set how often a failed task is retried
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Add a metadata entry
+    pub fn metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.metadata.insert(key.into(), value);
+        self
+    }
+
+    /// Build the task
+    pub fn build(self) -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            task_type: self.task_type,
+            priority: self.priority,
+            status: TaskStatus::Pending,
+            payload: self.payload,
+            created_at: self.created_at.unwrap_or_else(Utc::now),
+            deadline: self.deadline,
+            retry_count: 0,
+            max_retries: self.max_retries,
+            metadata: self.metadata,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_builder::DocumentBuilder;
+    use crate::types::{DocumentContent, DocumentType};
+
+    #[test]
+    fn test_document_tasks_take_their_type_from_the_document() {
+        let document = DocumentBuilder::new("doc.txt", DocumentContent::Text("text".to_string()))
+            .document_type(DocumentType::Text)
+            .build();
+        let task = TaskBuilder::document(document.clone(), DocumentProcessingType::TextExtraction)
+            .priority(TaskPriority::High)
+            .metadata("tenant", serde_json::json!("acme"))
+            .build();
+
+        assert_eq!(task.task_type, TaskType::DocumentProcessing {
+            document_type: DocumentType::Text,
+            processing_type: DocumentProcessingType::TextExtraction,
+        });
+        assert_eq!(task.document_id(), Some(document.id));
+        assert_eq!((task.priority, task.status, task.max_retries), (TaskPriority::High, TaskStatus::Pending, DEFAULT_MAX_RETRIES));
+        assert_eq!(task.metadata.get("tenant"), Some(&serde_json::json!("acme")));
+    }
+}
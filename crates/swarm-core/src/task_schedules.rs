@@ -0,0 +1,350 @@
+//! Scheduled Tasks
+//!
+//! Recurring work such as re-scanning a directory nightly or re-indexing
+//! embeddings weekly is described by a `TaskSchedule`: a cron expression and
+//! a template the coordinator instantiates into a fresh task every time the
+//! expression fires. Expressions take the usual five fields (minute, hour,
+//! day of month, month, day of week) or six with leading seconds, in UTC.
+//! Runs missed while the coordinator was down are caught up with a single
+//! run, not one per missed occurrence. Every run gets an idempotency key of
+//! its own, so the result cache does not answer it with an earlier run's
+//! result. Schedules are kept in sled, one JSON value per schedule, so they
+//! survive restarts.
+
+use crate::error::{SwarmError, SwarmResult};
+use crate::idempotency::IDEMPOTENCY_KEY;
+use crate::types::{Task, TaskPayload, TaskPriority, TaskStatus, TaskType};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Task metadata key holding the schedule a task was created by
+pub const SCHEDULE_KEY: &str = "schedule";
+
+/// Parse a cron expression, accepting five fields or six with seconds
+pub fn parse_cron(expression: &str) -> SwarmResult<cron::Schedule> {
+    let expression = expression.trim();
+    let normalized = match expression.split_whitespace().count() {
+        5 => format!("0 {}", expression),
+        _ => expression.to_string(),
+    };
+    cron::Schedule::from_str(&normalized)
+        .map_err(|e| SwarmError::Schedule(format!("invalid cron expression '{}': {}", expression, e)))
+}
+
+/// What a scheduled task looks like, apart from its id and creation time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskTemplate {
+    pub task_type: TaskType,
+    pub priority: TaskPriority,
+    pub payload: TaskPayload,
+    #[serde(default)]
+    pub max_retries: u32,
+    
+    /// Deadline relative to the task's creation (none when unset)
+    #[serde(default)]
+    pub deadline_ms: Option<u64>,
+    
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl TaskTemplate {
+    pub fn new(task_type: TaskType, priority: TaskPriority, payload: TaskPayload) -> Self {
+        Self { task_type, priority, payload, max_retries: 0, deadline_ms: None, metadata: HashMap::new() }
+    }
+    
+    /// A new pending task created at `now`
+    pub fn instantiate(&self, now: DateTime<Utc>) -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            task_type: self.task_type.clone(),
+            priority: self.priority.clone(),
+            status: TaskStatus::Pending,
+            payload: self.payload.clone(),
+            created_at: now,
+            deadline: self.deadline_ms.map(|ms| now + Duration::milliseconds(ms as i64)),
+            retry_count: 0,
+            max_retries: self.max_retries,
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+/// A recurring task definition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskSchedule {
+    pub id: String,
+    
+    /// Cron expression in UTC
+    pub cron: String,
+    
+    pub template: TaskTemplate,
+    
+    /// Disabled schedules are kept but submit nothing
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    
+    pub last_run: Option<DateTime<Utc>>,
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl TaskSchedule {
+    /// An enabled schedule, failing if the cron expression is invalid
+    pub fn new(id: impl Into<String>, cron: impl Into<String>, template: TaskTemplate) -> SwarmResult<Self> {
+        let schedule = Self {
+            id: id.into(),
+            cron: cron.into(),
+            template,
+            enabled: true,
+            last_run: None,
+            next_run: None,
+        };
+        parse_cron(&schedule.cron)?;
+        Ok(schedule)
+    }
+    
+    pub fn disabled(mut self) -> Self {
+        self.enabled = false;
+        self
+    }
+}
+
+struct ScheduleEntry {
+    schedule: TaskSchedule,
+    cron: cron::Schedule,
+}
+
+/// Schedules known to the coordinator, with their parsed expressions
+#[derive(Default)]
+pub struct CronScheduler {
+    entries: BTreeMap<String, ScheduleEntry>,
+}
+
+impl CronScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add or replace a schedule; its next run is computed from `now` unless already set
+    pub fn insert(&mut self, mut schedule: TaskSchedule, now: DateTime<Utc>) -> SwarmResult<&TaskSchedule> {
+        let cron = parse_cron(&schedule.cron)?;
+        if schedule.next_run.is_none() {
+            schedule.next_run = cron.after(&now).next();
+        }
+        let id = schedule.id.clone();
+        self.entries.insert(id.clone(), ScheduleEntry { schedule, cron });
+        Ok(&self.entries[&id].schedule)
+    }
+    
+    pub fn remove(&mut self, id: &str) -> Option<TaskSchedule> {
+        self.entries.remove(id).map(|entry| entry.schedule)
+    }
+    
+    pub fn get(&self, id: &str) -> Option<&TaskSchedule> {
+        self.entries.get(id).map(|entry| &entry.schedule)
+    }
+    
+    /// Enable or disable a schedule, returning it if it exists
+    pub fn set_enabled(&mut self, id: &str, enabled: bool, now: DateTime<Utc>) -> Option<&TaskSchedule> {
+        let entry = self.entries.get_mut(id)?;
+        entry.schedule.enabled = enabled;
+        if enabled {
+            entry.schedule.next_run = entry.cron.after(&now).next();
+        }
+        Some(&entry.schedule)
+    }
+    
+    /// Schedules ordered by id
+    pub fn schedules(&self) -> impl Iterator<Item = &TaskSchedule> {
+        self.entries.values().map(|entry| &entry.schedule)
+    }
+    
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    
+    /// Instantiate the task of every enabled schedule due at `now`
+    ///
+    /// Each due schedule runs once, however many occurrences it missed, and
+    /// moves on to its next occurrence after `now`. Unless the template sets
+    /// one, a task's idempotency key is the schedule id and run time. Returns
+    /// the tasks with the updated schedules.
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Vec<(Task, TaskSchedule)> {
+        let mut due = Vec::new();
+        for entry in self.entries.values_mut() {
+            let schedule = &mut entry.schedule;
+            if !schedule.enabled || schedule.next_run.is_none_or(|next_run| next_run > now) {
+                continue;
+            }
+            
+            let mut task = schedule.template.instantiate(now);
+            task.metadata.insert(SCHEDULE_KEY.to_string(), serde_json::Value::String(schedule.id.clone()));
+            task.metadata.entry(IDEMPOTENCY_KEY.to_string())
+                .or_insert_with(|| serde_json::Value::String(format!("{}@{}", schedule.id, now.to_rfc3339())));
+            schedule.last_run = Some(now);
+            schedule.next_run = entry.cron.after(&now).next();
+            due.push((task, schedule.clone()));
+        }
+        due
+    }
+}
+
+/// sled-backed store of task schedules
+///
+/// Lets a coordinator's schedules survive restarts (see `with_schedule_store`).
+#[derive(Debug, Clone)]
+pub struct ScheduleStore {
+    db: sled::Db,
+    schedules: sled::Tree,
+}
+
+impl ScheduleStore {
+    /// Open (or create) a store at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let db = sled::open(path)?;
+        Self::from_db(&db)
+    }
+    
+    /// Store that lives only as long as the process
+    pub fn temporary() -> Result<Self> {
+        let db = sled::Config::new().temporary(true).open()?;
+        Self::from_db(&db)
+    }
+    
+    fn from_db(db: &sled::Db) -> Result<Self> {
+        Ok(Self {
+            db: db.clone(),
+            schedules: db.open_tree("schedules")?,
+        })
+    }
+    
+    pub fn put(&self, schedule: &TaskSchedule) -> Result<()> {
+        self.schedules.insert(schedule.id.as_bytes(), serde_json::to_vec(schedule)?)?;
+        Ok(())
+    }
+    
+    pub fn remove(&self, id: &str) -> Result<bool> {
+        Ok(self.schedules.remove(id.as_bytes())?.is_some())
+    }
+    
+    pub fn get(&self, id: &str) -> Result<Option<TaskSchedule>> {
+        match self.schedules.get(id.as_bytes())? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Every stored schedule, ordered by id
+    pub fn schedules(&self) -> Result<Vec<TaskSchedule>> {
+        self.schedules.iter()
+            .map(|entry| Ok(serde_json::from_slice(&entry?.1)?))
+            .collect()
+    }
+    
+    /// Wait until everything written so far is on disk
+    pub fn flush(&self) -> Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::idempotency::ResultCache;
+    use crate::types::{TaskResult, TextAnalysisType, VectorIndexType};
+    use chrono::TimeZone;
+    
+    fn create_test_template() -> TaskTemplate {
+        TaskTemplate::new(
+            TaskType::VectorIndexing { index_type: VectorIndexType::DenseEmbedding },
+            TaskPriority::Low,
+            TaskPayload::Custom { data: b"reindex".to_vec(), format: "text".to_string() },
+        )
+    }
+    
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 10, hour, minute, 0).unwrap()
+    }
+    
+    #[test]
+    fn test_five_and_six_field_expressions() {
+        assert!(parse_cron("0 2 * * *").is_ok());
+        assert!(parse_cron("30 0 2 * * *").is_ok());
+        assert!(matches!(parse_cron("every night"), Err(SwarmError::Schedule(_))));
+        assert!(TaskSchedule::new("bad", "61 * * * *", create_test_template()).is_err());
+    }
+    
+    #[test]
+    fn test_due_schedules_run_once_and_move_on() {
+        let mut scheduler = CronScheduler::new();
+        let nightly = TaskSchedule::new("nightly", "0 2 * * *", create_test_template()).unwrap();
+        assert_eq!(scheduler.insert(nightly, at(1, 0)).unwrap().next_run, Some(at(2, 0)));
+        let mut paused = create_test_template();
+        paused.task_type = TaskType::TextAnalysis { analysis_type: TextAnalysisType::KeywordExtraction };
+        scheduler.insert(TaskSchedule::new("paused", "* * * * *", paused).unwrap().disabled(), at(1, 0)).unwrap();
+        
+        assert!(scheduler.take_due(at(1, 59)).is_empty());
+        
+        // Coming back long after the run was due catches up with a single run
+        let due = scheduler.take_due(at(5, 30));
+        assert_eq!(due.len(), 1);
+        let (task, schedule) = &due[0];
+        assert_eq!(task.metadata[SCHEDULE_KEY], "nightly");
+        assert_eq!((task.created_at, task.status.clone()), (at(5, 30), TaskStatus::Pending));
+        assert_eq!(schedule.last_run, Some(at(5, 30)));
+        assert_eq!(schedule.next_run, Some(Utc.with_ymd_and_hms(2026, 3, 11, 2, 0, 0).unwrap()));
+        assert!(scheduler.take_due(at(6, 0)).is_empty());
+    }
+    
+    #[test]
+    fn test_each_run_misses_the_result_cache() {
+        let mut scheduler = CronScheduler::new();
+        scheduler.insert(TaskSchedule::new("hourly", "0 * * * *", create_test_template()).unwrap(), at(1, 30)).unwrap();
+        let mut cache = ResultCache::default();
+        
+        let (first, _) = scheduler.take_due(at(2, 0)).remove(0);
+        assert_eq!(first.idempotency_key(), format!("hourly@{}", at(2, 0).to_rfc3339()));
+        let result = TaskResult {
+            task_id: first.id,
+            status: TaskStatus::Completed,
+            result: None,
+            error: None,
+            processing_time_ms: 5,
+            completed_at: at(2, 1),
+            metadata: HashMap::new(),
+        };
+        cache.insert(&first, &result);
+        
+        // A redelivery of the same run is answered, the next run is processed
+        assert!(cache.replay(&Task { id: Uuid::new_v4(), ..first.clone() }).is_some());
+        let (second, _) = scheduler.take_due(at(3, 0)).remove(0);
+        assert_eq!(second.payload, first.payload);
+        assert!(cache.replay(&second).is_none());
+    }
+    
+    #[test]
+    fn test_store_keeps_schedules() {
+        let store = ScheduleStore::temporary().unwrap();
+        let schedule = TaskSchedule::new("weekly", "0 3 * * SUN", create_test_template()).unwrap();
+        store.put(&schedule).unwrap();
+        
+        assert_eq!(store.get("weekly").unwrap(), Some(schedule.clone()));
+        assert_eq!(store.schedules().unwrap(), vec![schedule]);
+        assert!(store.remove("weekly").unwrap());
+        assert!(store.schedules().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+//! Trace Context
+//!
+//! W3C trace-context propagation, so a single document's journey through the
+//! swarm (scan, publish, assign, process, result) shows up as one trace in
+//! Jaeger or Tempo. The reader starts a trace per document, with the
+//! document ID as trace ID. The context of the latest stage travels in the
+//! `traceparent` message header and in document, task and result metadata,
+//! read and written by OpenTelemetry's W3C propagator. Each stage continues
+//! it with a span whose OpenTelemetry parent is the propagated context, so
+//! with a `tracing-opentelemetry` layer installed the stages are exported as
+//! one trace. Spans are only recorded for sampled traces. With the `otlp`
+//! feature, `otlp_layer` exports them to a collector.
+
+use crate::message_headers::MessageHeaders;
+use crate::sampling::SamplingDecision;
+use crate::types::{Task, TaskPayload};
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use std::collections::HashMap;
+use std::fmt;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+/// Message header carrying the W3C trace context
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Document, task and result metadata key carrying the trace context
+pub const TRACE_CONTEXT_KEY: &str = "traceparent";
+
+/// Position of a stage in a trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    /// Known only where the span was started, it does not propagate
+    parent_span_id: Option<u64>,
+    sampled: bool,
+}
+
+fn new_span_id() -> u64 {
+    loop {
+        let (high, _) = Uuid::new_v4().as_u64_pair();
+        if high != 0 {
+            return high;
+        }
+    }
+}
+
+/// Message headers as a propagator carrier to inject into
+struct HeaderInjector<'a>(&'a mut MessageHeaders);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.insert(key.to_string(), value);
+    }
+}
+
+/// Message headers as a propagator carrier to extract from
+struct HeaderExtractor<'a>(&'a MessageHeaders);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key)
+    }
+    
+    fn keys(&self) -> Vec<&str> {
+        self.0.iter().map(|(name, _)| name).collect()
+    }
+}
+
+/// A lone `traceparent` value as a propagator carrier
+struct TraceparentExtractor<'a>(&'a str);
+
+impl Extractor for TraceparentExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        (key == TRACEPARENT_HEADER).then_some(self.0)
+    }
+    
+    fn keys(&self) -> Vec<&str> {
+        vec![TRACEPARENT_HEADER]
+    }
+}
+
+impl TraceContext {
+    /// Start the trace of an item, e.g. with a document ID
+    pub fn root(trace_id: Uuid, decision: SamplingDecision) -> Self {
+        Self { trace_id: trace_id.as_u128(), span_id: new_span_id(), parent_span_id: None, sampled: decision.is_sampled() }
+    }
+    
+    /// Context of a stage following this one
+    pub fn child(&self) -> Self {
+        Self { span_id: new_span_id(), parent_span_id: Some(self.span_id), ..*self }
+    }
+    
+    pub fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+    
+    pub fn span_id(&self) -> u64 {
+        self.span_id
+    }
+    
+    pub fn parent_span_id(&self) -> Option<u64> {
+        self.parent_span_id
+    }
+    
+    pub fn is_sampled(&self) -> bool {
+        self.sampled
+    }
+    
+    /// The same context with a sampling decision made by a `TraceSampler`
+    pub fn with_decision(mut self, decision: SamplingDecision) -> Self {
+        self.sampled = decision.is_sampled();
+        self
+    }
+    
+    /// The context as an OpenTelemetry span context received from another process
+    pub fn span_context(&self) -> SpanContext {
+        let flags = if self.sampled { TraceFlags::SAMPLED } else { TraceFlags::default() };
+        SpanContext::new(TraceId::from(self.trace_id), SpanId::from(self.span_id), flags, true, TraceState::default())
+    }
+    
+    /// Context of an OpenTelemetry span, if it is valid
+    pub fn from_span_context(span_context: &SpanContext) -> Option<Self> {
+        if !span_context.is_valid() {
+            return None;
+        }
+        Some(Self {
+            trace_id: u128::from_be_bytes(span_context.trace_id().to_bytes()),
+            span_id: u64::from_be_bytes(span_context.span_id().to_bytes()),
+            parent_span_id: None,
+            sampled: span_context.is_sampled(),
+        })
+    }
+    
+    /// Context of a span exported by a `tracing-opentelemetry` layer, if one is installed
+    pub fn from_span(span: &tracing::Span) -> Option<Self> {
+        Self::from_span_context(span.context().span().span_context())
+    }
+    
+    /// Parse a `traceparent` value (`00-<trace id>-<span id>-<flags>`)
+    pub fn parse(traceparent: &str) -> Option<Self> {
+        let context = TraceContextPropagator::new().extract(&TraceparentExtractor(traceparent));
+        Self::from_span_context(context.span().span_context())
+    }
+    
+    /// Context carried in message headers, if any
+    pub fn from_headers(headers: &MessageHeaders) -> Option<Self> {
+        let context = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+        Self::from_span_context(context.span().span_context())
+    }
+    
+    /// Carry the context in message headers
+    pub fn inject(&self, headers: &mut MessageHeaders) {
+        let context = opentelemetry::Context::new().with_remote_span_context(self.span_context());
+        TraceContextPropagator::new().inject_context(&context, &mut HeaderInjector(headers));
+    }
+    
+    /// Context recorded in metadata, if any
+    pub fn from_metadata(metadata: &HashMap<String, serde_json::Value>) -> Option<Self> {
+        metadata.get(TRACE_CONTEXT_KEY)?.as_str().and_then(Self::parse)
+    }
+    
+    /// Record the context in metadata
+    pub fn record(&self, metadata: &mut HashMap<String, serde_json::Value>) {
+        metadata.insert(TRACE_CONTEXT_KEY.to_string(), serde_json::Value::String(self.to_string()));
+    }
+    
+    /// Context of a task, or else of the document it carries
+    pub fn from_task(task: &Task) -> Option<Self> {
+        Self::from_metadata(&task.metadata).or_else(|| match &task.payload {
+            TaskPayload::Document { document, .. } => Self::from_metadata(&document.metadata),
+            _ => None,
+        })
+    }
+    
+    /// Continue the trace recorded in metadata with a span for `stage`,
+    /// recording the new span as the latest stage
+    pub fn continue_in(metadata: &mut HashMap<String, serde_json::Value>, stage: &'static str, id: Uuid) -> tracing::Span {
+        match Self::from_metadata(metadata) {
+            Some(parent) => {
+                let (context, span) = parent.continue_span(stage, id);
+                context.record(metadata);
+                span
+            }
+            None => tracing::Span::none(),
+        }
+    }
+    
+    /// Continue the trace of a task with a span for `stage`, recorded on the task
+    pub fn continue_task(task: &mut Task, stage: &'static str) -> tracing::Span {
+        match Self::from_task(task) {
+            Some(parent) => {
+                let (context, span) = parent.continue_span(stage, task.id);
+                context.record(&mut task.metadata);
+                span
+            }
+            None => tracing::Span::none(),
+        }
+    }
+    
+    /// Span for one pipeline stage of an item following this one, disabled
+    /// unless sampled, and the context later stages continue from
+    ///
+    /// The context is the exported span's when an OpenTelemetry layer is
+    /// installed, so later stages point at a span the collector received.
+    pub fn continue_span(&self, stage: &'static str, id: Uuid) -> (Self, tracing::Span) {
+        let child = self.child();
+        if !self.sampled {
+            return (child, tracing::Span::none());
+        }
+        let span = tracing::info_span!("swarm.trace", stage, %id);
+        let parent = opentelemetry::Context::new().with_remote_span_context(self.span_context());
+        if span.set_parent(parent).is_err() {
+            return (child, span);
+        }
+        let context = Self::from_span(&span).map_or(child, |exported| Self { parent_span_id: Some(self.span_id), ..exported });
+        (context, span)
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut headers = MessageHeaders::new();
+        self.inject(&mut headers);
+        f.write_str(headers.traceparent().unwrap_or_default())
+    }
+}
+
+/// Layer exporting sampled spans over OTLP/HTTP, to the collector named by
+/// the standard `OTEL_EXPORTER_OTLP_ENDPOINT` variable
+///
+/// Spans are exported in batches; shut the returned provider down before
+/// exiting so the last batch is not lost.
+#[cfg(feature = "otlp")]
+pub fn otlp_layer<S>(service_name: &str) -> anyhow::Result<(tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry_sdk::trace::SdkTracer>, opentelemetry_sdk::trace::SdkTracerProvider)>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    use opentelemetry::trace::TracerProvider;
+    let exporter = opentelemetry_otlp::SpanExporter::builder().with_http().build()?;
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(opentelemetry_sdk::Resource::builder().with_service_name(service_name.to_string()).build())
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("swarm"));
+    Ok((layer, provider))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DocumentContent, DocumentProcessingType, DocumentType};
+    use crate::{DocumentBuilder, TaskBuilder};
+    
+    #[test]
+    fn test_traceparent_round_trip() {
+        let root = TraceContext::root(Uuid::new_v4(), SamplingDecision::Sampled);
+        let child = root.child();
+        assert_eq!(child.trace_id(), root.trace_id());
+        assert_ne!(child.span_id(), root.span_id());
+        assert_eq!(child.parent_span_id(), Some(root.span_id()));
+        
+        let traceparent = child.to_string();
+        assert_eq!(traceparent.len(), 55);
+        assert!(traceparent.starts_with("00-") && traceparent.ends_with("-01"));
+        let parsed = TraceContext::parse(&traceparent).unwrap();
+        assert_eq!((parsed.trace_id(), parsed.span_id(), parsed.parent_span_id()), (child.trace_id(), child.span_id(), None));
+        assert!(!TraceContext::parse(&TraceContext::root(Uuid::new_v4(), SamplingDecision::NotSampled).to_string()).unwrap().is_sampled());
+    }
+    
+    #[test]
+    fn test_malformed_traceparents_are_ignored() {
+        for traceparent in [
+            "",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-00",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e473g-00f067aa0ba902b7-01",
+        ] {
+            assert_eq!(TraceContext::parse(traceparent), None, "{}", traceparent);
+        }
+    }
+    
+    #[test]
+    fn test_tasks_continue_their_documents_trace() {
+        let mut document = DocumentBuilder::new("doc.txt", DocumentContent::Text("text".to_string()))
+            .document_type(DocumentType::Text)
+            .build();
+        let scan = TraceContext::root(document.id, SamplingDecision::Sampled);
+        scan.record(&mut document.metadata);
+        let mut task = TaskBuilder::document(document, DocumentProcessingType::TextExtraction).build();
+        
+        let _span = TraceContext::continue_task(&mut task, "assign");
+        let assign = TraceContext::from_task(&task).unwrap();
+        assert_eq!(assign.trace_id(), scan.trace_id());
+        assert_ne!(assign.span_id(), scan.span_id());
+        
+        let mut headers = MessageHeaders::new();
+        assign.inject(&mut headers);
+        assert_eq!(TraceContext::from_headers(&headers).unwrap().span_id(), assign.span_id());
+    }
+    
+    #[test]
+    fn test_stages_are_exported_as_one_trace() {
+        use opentelemetry::trace::TracerProvider;
+        use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+        use tracing_subscriber::layer::SubscriberExt;
+        
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        let subscriber = tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(provider.tracer("swarm")));
+        let scan = TraceContext::root(Uuid::new_v4(), SamplingDecision::Sampled);
+        let (publish, process) = tracing::subscriber::with_default(subscriber, || {
+            let mut metadata = HashMap::new();
+            scan.record(&mut metadata);
+            drop(TraceContext::continue_in(&mut metadata, "publish", Uuid::new_v4()));
+            let publish = TraceContext::from_metadata(&metadata).unwrap();
+            let (process, span) = publish.continue_span("process", Uuid::new_v4());
+            drop(span);
+            assert!(publish.with_decision(SamplingDecision::NotSampled).continue_span("skipped", Uuid::new_v4()).1.is_none());
+            (publish, process)
+        });
+        
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 2);
+        assert!(spans.iter().all(|span| span.span_context.trace_id() == TraceId::from(scan.trace_id())));
+        assert_eq!((spans[0].parent_span_id, spans[0].span_context.span_id()), (SpanId::from(scan.span_id()), SpanId::from(publish.span_id())));
+        assert_eq!((spans[1].parent_span_id, spans[1].span_context.span_id()), (SpanId::from(publish.span_id()), SpanId::from(process.span_id())));
+        assert_eq!(process.parent_span_id(), Some(publish.span_id()));
+    }
+}
@@ -3,10 +3,11 @@
 //! This module defines the fundamental traits that all swarm components must implement.
 //! These traits provide clean abstractions and enable dependency injection and testing.
 
-use crate::types::*;
 use anyhow::Result;
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use uuid::Uuid;
 
 /// Core trait for all workers in the swarm system
@@ -70,6 +71,21 @@ pub trait DocumentProcessor: Send + Sync {
     /// Process a document
     async fn process_document(&self, document: &Document) -> Result<DocumentProcessingResult>;
     
+    /// Process a document whose content is read incrementally from `reader`
+    ///
+    /// The default implementation buffers the whole stream and calls
+    /// `process_document`; override it to keep memory bounded.
+    async fn process_document_stream(&self, document: &Document, reader: &mut (dyn AsyncRead + Unpin + Send)) -> Result<DocumentProcessingResult> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let content = match String::from_utf8(bytes) {
+            Ok(text) => DocumentContent::Text(text),
+            Err(e) => DocumentContent::Binary(e.into_bytes()),
+        };
+        let buffered = Document { content, ..document.clone() };
+        self.process_document(&buffered).await
+    }
+    
     /// Get supported document types
     fn supported_document_types(&self) -> &[DocumentType];
     
@@ -125,6 +141,7 @@ pub trait DocumentReader: Send + Sync {
     async fn stop(&mut self) -> Result<()>;
     
     /// Get next available document
+    #[deprecated(note = "poll loops over this are wasteful; use the reader's document stream instead")]
     async fn get_next_document(&mut self) -> Result<Option<Document>>;
     
     /// Get reader statistics
@@ -140,6 +157,12 @@ pub trait MessageBroker: Send + Sync {
     /// Subscribe to a subject
     async fn subscribe(&self, subject: &str) -> Result<Box<dyn MessageSubscription>>;
     
+    /// Publish a request and wait up to `timeout` for the first reply
+    ///
+    /// Responders publish their reply to the request's [`Message::reply_to`]
+    /// subject.
+    async fn request(&self, subject: &str, message: &[u8], timeout: Duration) -> Result<Message>;
+    
     /// Get broker statistics
     async fn get_stats(&self) -> MessageBrokerStats;
 }
@@ -153,6 +176,26 @@ pub trait MessageSubscription: Send + Sync {
     fn unsubscribe(self) -> Result<()>;
 }
 
+/// Turns text into embedding vectors (local hashing, ONNX runtimes, remote model APIs)
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Model name, recorded with the results it produced
+    fn model(&self) -> &str;
+    
+    /// Length of the produced vectors
+    fn dimensions(&self) -> usize;
+    
+    /// Embed a batch of texts, returning one vector per text
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Destination for complete task results (storage, archives, analytics)
+#[async_trait]
+pub trait ResultSink: Send + Sync {
+    /// Persist a task result
+    async fn write(&self, result: &TaskResult) -> Result<()>;
+}
+
 /// Trait for monitoring and metrics
 pub trait MetricsCollector: Send + Sync {
     /// Record a metric value
@@ -191,7 +234,7 @@ pub trait ConfigProvider: Send + Sync {
 pub use crate::types::{
     Task, TaskResult, TaskStatus, TaskPriority, TaskType,
     WorkerConfig, WorkerStatus, WorkerType, WorkerCapability,
-    Document, DocumentType, DocumentProcessingResult,
+    Document, DocumentType, DocumentContent, DocumentProcessingResult,
     Message, MessageBrokerStats, CoordinatorStats, TaskQueueStats,
     DocumentReaderStats, WorkerHealth, MetricValue,
 };
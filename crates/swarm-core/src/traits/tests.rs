@@ -141,6 +141,7 @@ impl DocumentProcessor for MockDocumentProcessor {
             embeddings: None,
             processing_time_ms: 75,
             processed_at: Utc::now(),
+            warnings: Vec::new(),
         })
     }
     
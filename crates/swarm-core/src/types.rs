@@ -3,6 +3,9 @@
 //! This module defines all the fundamental types used throughout the swarm system.
 //! These types are designed to be serializable, type-safe, and efficient.
 
+use crate::budget::AnalysisBudgets;
+use crate::message_headers::MessageHeaders;
+use crate::vector_payload::PackedVectors;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -14,6 +17,7 @@ use chrono::{DateTime, Utc};
 
 /// Represents a unit of work in the swarm system
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Task {
     pub id: Uuid,
     pub task_type: TaskType,
@@ -29,6 +33,7 @@ pub struct Task {
 
 /// Different types of tasks that can be processed
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum TaskType {
     /// Document processing tasks
     DocumentProcessing {
@@ -43,6 +48,10 @@ pub enum TaskType {
     VectorIndexing {
         index_type: VectorIndexType,
     },
+    /// Export of processing results for offline review
+    Export {
+        format: ExportFormat,
+    },
     /// Custom task types
     Custom {
         name: String,
@@ -52,6 +61,7 @@ pub enum TaskType {
 
 /// Types of document processing
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum DocumentProcessingType {
     TextExtraction,
     MetadataExtraction,
@@ -64,6 +74,7 @@ pub enum DocumentProcessingType {
 
 /// Types of text analysis
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum TextAnalysisType {
     LanguageDetection,
     KeywordExtraction,
@@ -75,6 +86,7 @@ pub enum TextAnalysisType {
 
 /// Types of vector indexing
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum VectorIndexType {
     DenseEmbedding,
     SparseEmbedding,
@@ -82,8 +94,17 @@ pub enum VectorIndexType {
     SemanticSearch,
 }
 
+/// Formats of result exports
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ExportFormat {
+    /// Zip bundle with the original file, extracted text and result JSON per document
+    Zip,
+}
+
 /// Task priority levels
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum TaskPriority {
     Low = 1,
     Normal = 2,
@@ -93,6 +114,7 @@ pub enum TaskPriority {
 
 /// Task status
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum TaskStatus {
     Pending,
     Assigned,
@@ -105,6 +127,7 @@ pub enum TaskStatus {
 
 /// Task payload containing the actual work data
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum TaskPayload {
     /// Document processing payload
     Document {
@@ -121,6 +144,10 @@ pub enum TaskPayload {
         vectors: Vec<Vec<f32>>,
         metadata: HashMap<String, serde_json::Value>,
     },
+    /// Vector indexing payload in binary form, see `TaskPayload::pack_vectors`
+    PackedVectors(PackedVectors),
+    /// Result export payload
+    Export(ExportRequest),
     /// Custom payload
     Custom {
         data: Vec<u8>,
@@ -128,8 +155,31 @@ pub enum TaskPayload {
     },
 }
 
+/// Selection of results to export; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ExportRequest {
+    pub batch_id: Option<String>,
+    pub tenant: Option<String>,
+    
+    /// Only results processed at or after this time
+    pub from: Option<DateTime<Utc>>,
+    
+    /// Only results processed before this time
+    pub to: Option<DateTime<Utc>>,
+    
+    /// Only results with (`true`) or without (`false`) processing warnings
+    #[serde(default)]
+    pub has_warnings: Option<bool>,
+    
+    /// Only results with a processing warning of this code
+    #[serde(default)]
+    pub warning_code: Option<String>,
+}
+
 /// Document processing options
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DocumentProcessingOptions {
     pub extract_text: bool,
     pub extract_metadata: bool,
@@ -154,11 +204,16 @@ impl Default for DocumentProcessingOptions {
 
 /// Text analysis options
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TextAnalysisOptions {
     pub language: Option<String>,
     pub max_keywords: usize,
     pub min_keyword_length: usize,
     pub sentiment_threshold: f32,
+    
+    /// Work limits of the analysis stages
+    #[serde(default)]
+    pub budgets: AnalysisBudgets,
 }
 
 impl Default for TextAnalysisOptions {
@@ -168,12 +223,14 @@ impl Default for TextAnalysisOptions {
             max_keywords: 10,
             min_keyword_length: 3,
             sentiment_threshold: 0.1,
+            budgets: AnalysisBudgets::default(),
         }
     }
 }
 
 /// Result of task processing
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TaskResult {
     pub task_id: Uuid,
     pub status: TaskStatus,
@@ -184,8 +241,43 @@ pub struct TaskResult {
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
+/// Result metadata key holding the id of the document the task processed
+pub const DOCUMENT_ID_KEY: &str = "document_id";
+
+impl Task {
+    /// Id of the document carried by a document payload
+    pub fn document_id(&self) -> Option<Uuid> {
+        match &self.payload {
+            TaskPayload::Document { document, .. } => Some(document.id),
+            _ => None,
+        }
+    }
+}
+
+impl TaskResult {
+    /// Record the processed document under `DOCUMENT_ID_KEY`
+    pub fn record_document_id(&mut self, document_id: Uuid) {
+        self.metadata.insert(DOCUMENT_ID_KEY.to_string(), serde_json::Value::String(document_id.to_string()));
+    }
+    
+    /// Document the result belongs to, failed results included
+    ///
+    /// The id recorded under `DOCUMENT_ID_KEY`, otherwise the document id of
+    /// a document processing result.
+    pub fn document_id(&self) -> Option<Uuid> {
+        let recorded = self.metadata.get(DOCUMENT_ID_KEY)
+            .and_then(|value| value.as_str())
+            .and_then(|value| value.parse().ok());
+        recorded.or(match &self.result {
+            Some(TaskResultData::DocumentProcessing(processed)) => Some(processed.document_id),
+            _ => None,
+        })
+    }
+}
+
 /// Task result data
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum TaskResultData {
     /// Document processing result
     DocumentProcessing(DocumentProcessingResult),
@@ -193,6 +285,8 @@ pub enum TaskResultData {
     TextAnalysis(TextAnalysisResult),
     /// Vector indexing result
     VectorIndexing(VectorIndexingResult),
+    /// Result export
+    Export(ExportSummary),
     /// Custom result
     Custom {
         data: Vec<u8>,
@@ -269,6 +363,8 @@ pub enum WorkerStatus {
     Idle,
     Error(String),
     Shutdown,
+    /// Missed too many heartbeats
+    Unavailable,
 }
 
 /// Worker health information
@@ -291,6 +387,7 @@ pub struct WorkerHealth {
 
 /// Document representation
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Document {
     pub id: Uuid,
     pub filename: String,
@@ -303,6 +400,7 @@ pub struct Document {
 
 /// Document content
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum DocumentContent {
     /// Text content
     Text(String),
@@ -313,11 +411,23 @@ pub enum DocumentContent {
         storage_id: String,
         path: String,
         access_token: Option<String>,
+        /// Expected content checksum (`sha256:<hex>`), verified on fetch
+        #[serde(default)]
+        checksum: Option<String>,
+        /// Expected storage ETag, verified on fetch
+        #[serde(default)]
+        etag: Option<String>,
+    },
+    /// Content too large to hold in memory, read incrementally from `source`
+    /// with `DocumentProcessor::process_document_stream`
+    Stream {
+        source: String,
     },
 }
 
 /// Document types
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum DocumentType {
     Pdf,
     Word,
@@ -334,21 +444,60 @@ pub enum DocumentType {
 
 /// Document processing result
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DocumentProcessingResult {
     pub document_id: Uuid,
     pub extracted_text: Option<String>,
+    #[serde(default)]
     pub metadata: HashMap<String, serde_json::Value>,
     pub language: Option<String>,
+    #[serde(default)]
     pub keywords: Vec<String>,
     pub sentiment: Option<f32>,
     pub classification: Option<String>,
     pub embeddings: Option<Vec<f32>>,
     pub processing_time_ms: u64,
     pub processed_at: DateTime<Utc>,
+    
+    /// Non-fatal issues met while processing, in the order they occurred
+    #[serde(default)]
+    pub warnings: Vec<ProcessingWarning>,
+}
+
+impl DocumentProcessingResult {
+    /// Record a non-fatal issue
+    pub fn warn(&mut self, stage: &str, code: &str, message: impl Into<String>) {
+        self.warnings.push(ProcessingWarning::new(stage, code, message));
+    }
+    
+    /// Whether a warning with this code was recorded
+    pub fn has_warning(&self, code: &str) -> bool {
+        self.warnings.iter().any(|warning| warning.code == code)
+    }
+}
+
+/// Non-fatal issue met while processing a document, e.g. a skipped page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProcessingWarning {
+    /// Stage that raised the warning, e.g. `pdf` or `embeddings`
+    pub stage: String,
+    
+    /// Stable snake_case code to filter on, e.g. `page_unreadable`
+    pub code: String,
+    
+    pub message: String,
+}
+
+impl ProcessingWarning {
+    pub fn new(stage: &str, code: &str, message: impl Into<String>) -> Self {
+        Self { stage: stage.to_string(), code: code.to_string(), message: message.into() }
+    }
 }
 
 /// Text analysis result
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TextAnalysisResult {
     pub text_id: Uuid,
     pub language: Option<String>,
@@ -363,6 +512,7 @@ pub struct TextAnalysisResult {
 
 /// Named entity
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct NamedEntity {
     pub text: String,
     pub entity_type: String,
@@ -373,6 +523,7 @@ pub struct NamedEntity {
 
 /// Vector indexing result
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct VectorIndexingResult {
     pub index_id: Uuid,
     pub vector_count: usize,
@@ -382,23 +533,49 @@ pub struct VectorIndexingResult {
     pub processed_at: DateTime<Utc>,
 }
 
+/// Result export summary
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ExportSummary {
+    pub bundle_path: String,
+    pub document_count: usize,
+    
+    /// Documents exported without their original file (not in the content cache)
+    pub missing_originals: usize,
+    pub size_bytes: u64,
+}
+
 // ============================================================================
 // MESSAGING TYPES
 // ============================================================================
 
 /// Message for inter-component communication
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Message {
     pub id: Uuid,
     pub subject: String,
+    /// Base64 in JSON, so binary payloads stay compact on the wire
+    #[serde(with = "crate::base64_bytes")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub payload: Vec<u8>,
-    pub headers: HashMap<String, String>,
+    pub headers: MessageHeaders,
     pub timestamp: DateTime<Utc>,
     pub ttl_ms: Option<u64>,
 }
 
+/// Message header holding the subject a request's reply goes to
+pub const REPLY_TO_HEADER: &str = "reply-to";
+
+impl Message {
+    /// Subject to publish the reply to, if the message is a request
+    pub fn reply_to(&self) -> Option<&str> {
+        self.headers.reply_to()
+    }
+}
+
 /// Message broker statistics
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct MessageBrokerStats {
     pub total_messages_sent: u64,
     pub total_messages_received: u64,
@@ -420,6 +597,47 @@ pub struct CoordinatorStats {
     pub tasks_per_second: f32,
     pub average_processing_time_ms: f32,
     pub error_rate: f32,
+    #[serde(default)]
+    pub fairness: FairnessReport,
+    #[serde(default)]
+    pub saturation: QueueSaturation,
+}
+
+/// How close the coordinator's queue and channels are to their capacity
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QueueSaturation {
+    pub pending_tasks: usize,
+    /// Queued tasks above which submissions are refused, if capped
+    pub max_pending_tasks: Option<usize>,
+    /// Submissions refused because the queue was full
+    pub rejected_submissions: u64,
+    /// Workers whose task channel is full, passed over until they catch up
+    pub full_worker_queues: usize,
+    /// Results waiting to be recorded
+    pub result_queue_depth: usize,
+    pub result_queue_capacity: usize,
+}
+
+impl QueueSaturation {
+    /// Whether the queue or any channel is at capacity
+    pub fn is_saturated(&self) -> bool {
+        self.max_pending_tasks.is_some_and(|max| self.pending_tasks >= max)
+            || self.full_worker_queues > 0
+            || (self.result_queue_capacity > 0 && self.result_queue_depth >= self.result_queue_capacity)
+    }
+}
+
+/// Queue wait fairness across priorities and task types
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FairnessReport {
+    /// 95th percentile queue wait of dispatched tasks, per priority
+    pub p95_wait_ms_by_priority: HashMap<TaskPriority, u64>,
+    /// Longest observed queue wait (dispatched or still queued), per priority
+    pub max_wait_ms_by_priority: HashMap<TaskPriority, u64>,
+    /// Longest observed queue wait, keyed by task type display name
+    pub max_wait_ms_by_task_type: HashMap<String, u64>,
+    /// Queued tasks currently waiting longer than their priority threshold
+    pub starved_tasks: usize,
 }
 
 /// Task queue statistics
@@ -471,6 +689,9 @@ impl std::fmt::Display for TaskType {
             TaskType::VectorIndexing { index_type } => {
                 write!(f, "VectorIndexing({:?})", index_type)
             }
+            TaskType::Export { format } => {
+                write!(f, "Export({:?})", format)
+            }
             TaskType::Custom { name, version } => {
                 write!(f, "Custom({}, {})", name, version)
             }
@@ -531,6 +752,7 @@ impl std::fmt::Display for WorkerStatus {
             WorkerStatus::Idle => write!(f, "Idle"),
             WorkerStatus::Error(msg) => write!(f, "Error({})", msg),
             WorkerStatus::Shutdown => write!(f, "Shutdown"),
+            WorkerStatus::Unavailable => write!(f, "Unavailable"),
         }
     }
 }
@@ -0,0 +1,298 @@
+//! Vector Payload Encoding
+//!
+//! `TaskPayload::Vector` serializes every f32 as a JSON number, so a batch of
+//! embeddings turns into a message several times larger than its data.
+//! `TaskPayload::PackedVectors` carries the same values as little-endian f32s
+//! (base64 in JSON), and payloads above a configured size can be moved to a
+//! shared `VectorStore` so the broker message only holds a checksummed
+//! reference to them.
+
+use crate::document_builder::sha256_hex;
+use crate::types::TaskPayload;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const F32_BYTES: usize = std::mem::size_of::<f32>();
+
+/// Where the packed vector values are
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum VectorData {
+    /// Little-endian f32 values, base64 encoded in JSON
+    Inline(
+        #[serde(with = "crate::base64_bytes")]
+        #[cfg_attr(feature = "schema", schemars(with = "String"))]
+        Vec<u8>,
+    ),
+    /// Values moved to shared storage
+    Reference {
+        storage_id: String,
+        path: String,
+        /// Checksum of the stored values (`sha256:<hex>`), verified on resolve
+        checksum: String,
+    },
+}
+
+/// Vectors of equal dimension packed into a single buffer
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PackedVectors {
+    pub dimensions: usize,
+    pub count: usize,
+    pub data: VectorData,
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl PackedVectors {
+    /// Pack vectors, which must all have the same dimension
+    pub fn pack(vectors: &[Vec<f32>], metadata: HashMap<String, serde_json::Value>) -> Result<Self> {
+        let dimensions = vectors.first().map_or(0, Vec::len);
+        if dimensions == 0 && !vectors.is_empty() {
+            anyhow::bail!("vectors must have at least one dimension");
+        }
+        if let Some(index) = vectors.iter().position(|vector| vector.len() != dimensions) {
+            anyhow::bail!("vector {} has {} dimensions, expected {}", index, vectors[index].len(), dimensions);
+        }
+        
+        let mut bytes = Vec::with_capacity(vectors.len() * dimensions * F32_BYTES);
+        for value in vectors.iter().flatten() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Ok(Self {
+            dimensions,
+            count: vectors.len(),
+            data: VectorData::Inline(bytes),
+            metadata,
+        })
+    }
+    
+    /// Size of the packed values in bytes
+    ///
+    /// `count` and `dimensions` come from the wire, so a header whose size
+    /// overflows, or that claims vectors without dimensions, is an error.
+    pub fn byte_len(&self) -> Result<usize> {
+        if self.dimensions == 0 && self.count > 0 {
+            anyhow::bail!("{} packed vectors have no dimensions", self.count);
+        }
+        self.count.checked_mul(self.dimensions)
+            .and_then(|values| values.checked_mul(F32_BYTES))
+            .ok_or_else(|| anyhow::anyhow!("packed vectors of {}x{} overflow", self.count, self.dimensions))
+    }
+    
+    /// Whether the values travel with the payload
+    pub fn is_inline(&self) -> bool {
+        matches!(self.data, VectorData::Inline(_))
+    }
+    
+    /// Unpack inline values; externalized vectors must be resolved first
+    pub fn unpack(&self) -> Result<Vec<Vec<f32>>> {
+        let VectorData::Inline(bytes) = &self.data else {
+            anyhow::bail!("vectors are stored externally, resolve them first");
+        };
+        self.check_len(bytes)?;
+        if self.count == 0 {
+            return Ok(Vec::new());
+        }
+        
+        Ok(bytes
+            .chunks_exact(self.dimensions * F32_BYTES)
+            .map(|vector| {
+                vector.chunks_exact(F32_BYTES)
+                    .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+                    .collect()
+            })
+            .collect())
+    }
+    
+    /// Move inline values to `store`, keyed by their checksum
+    pub async fn externalize(self, storage_id: &str, store: &dyn VectorStore) -> Result<Self> {
+        let VectorData::Inline(bytes) = self.data else {
+            return Ok(self);
+        };
+        let digest = sha256_hex(&bytes);
+        let path = format!("vectors/{}", digest);
+        store.put(&path, bytes).await?;
+        Ok(Self {
+            data: VectorData::Reference {
+                storage_id: storage_id.to_string(),
+                path,
+                checksum: format!("sha256:{}", digest),
+            },
+            ..self
+        })
+    }
+    
+    /// Externalize values larger than the configured inline limit
+    pub async fn externalize_if_larger(self, config: &VectorPayloadConfig, store: &dyn VectorStore) -> Result<Self> {
+        if self.byte_len()? > config.inline_limit_bytes {
+            self.externalize(&config.storage_id, store).await
+        } else {
+            Ok(self)
+        }
+    }
+    
+    /// Fetch externalized values back into the payload, verifying their checksum
+    pub async fn resolve(self, store: &dyn VectorStore) -> Result<Self> {
+        let VectorData::Reference { storage_id, path, checksum } = &self.data else {
+            return Ok(self);
+        };
+        let bytes = store.get(path).await?;
+        let actual = format!("sha256:{}", sha256_hex(&bytes));
+        if &actual != checksum {
+            anyhow::bail!("vectors at {}/{} changed: checksum {} (expected {})", storage_id, path, actual, checksum);
+        }
+        self.check_len(&bytes)?;
+        Ok(Self { data: VectorData::Inline(bytes), ..self })
+    }
+    
+    fn check_len(&self, bytes: &[u8]) -> Result<()> {
+        let expected = self.byte_len()?;
+        if bytes.len() != expected {
+            anyhow::bail!("packed vectors hold {} bytes, expected {} for {}x{}",
+                bytes.len(), expected, self.count, self.dimensions);
+        }
+        Ok(())
+    }
+}
+
+impl TaskPayload {
+    /// Pack a `Vector` payload; other payloads are returned unchanged
+    pub fn pack_vectors(self) -> Result<Self> {
+        match self {
+            TaskPayload::Vector { vectors, metadata } => {
+                Ok(TaskPayload::PackedVectors(PackedVectors::pack(&vectors, metadata)?))
+            }
+            payload => Ok(payload),
+        }
+    }
+}
+
+/// Settings for externalizing packed vectors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorPayloadConfig {
+    /// Largest packed size kept inline in the message
+    pub inline_limit_bytes: usize,
+    
+    /// Storage ID recorded in references to externalized vectors
+    pub storage_id: String,
+}
+
+impl Default for VectorPayloadConfig {
+    fn default() -> Self {
+        Self {
+            inline_limit_bytes: 256 * 1024,
+            storage_id: "vectors".to_string(),
+        }
+    }
+}
+
+/// Shared storage that externalized vectors are written to
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    async fn put(&self, path: &str, bytes: Vec<u8>) -> Result<()>;
+    
+    async fn get(&self, path: &str) -> Result<Vec<u8>>;
+}
+
+/// Vector store held in memory, shared between clones
+#[derive(Debug, Clone, Default)]
+pub struct MemoryVectorStore {
+    values: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+}
+
+impl MemoryVectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl VectorStore for MemoryVectorStore {
+    async fn put(&self, path: &str, bytes: Vec<u8>) -> Result<()> {
+        self.values.lock().expect("vector store lock poisoned").insert(path.to_string(), bytes);
+        Ok(())
+    }
+    
+    async fn get(&self, path: &str) -> Result<Vec<u8>> {
+        self.values.lock().expect("vector store lock poisoned")
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no vectors stored at {}", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn embeddings(count: usize, dimensions: usize) -> Vec<Vec<f32>> {
+        (0..count).map(|i| (0..dimensions).map(|d| ((i * dimensions + d) as f32 * 0.37).sin()).collect()).collect()
+    }
+    
+    #[test]
+    fn test_packed_vectors_roundtrip_smaller_than_json() {
+        let vectors = embeddings(64, 384);
+        let plain = serde_json::to_vec(&TaskPayload::Vector { vectors: vectors.clone(), metadata: HashMap::new() }).unwrap();
+        let packed = TaskPayload::Vector { vectors: vectors.clone(), metadata: HashMap::new() }.pack_vectors().unwrap();
+        let encoded = serde_json::to_vec(&packed).unwrap();
+        assert!(encoded.len() * 2 < plain.len());
+        
+        let TaskPayload::PackedVectors(decoded) = serde_json::from_slice(&encoded).unwrap() else {
+            panic!("expected packed vectors");
+        };
+        assert_eq!(decoded.count, 64);
+        assert_eq!(decoded.unpack().unwrap(), vectors);
+    }
+    
+    #[test]
+    fn test_pack_rejects_ragged_vectors() {
+        let error = PackedVectors::pack(&[vec![1.0, 2.0], vec![3.0]], HashMap::new()).unwrap_err();
+        assert!(error.to_string().contains("vector 1 has 1 dimensions"));
+        assert!(PackedVectors::pack(&[Vec::new()], HashMap::new()).is_err());
+        assert!(PackedVectors::pack(&[], HashMap::new()).unwrap().unpack().unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_hostile_headers_are_rejected() {
+        let decode = |count: u64, dimensions: u64| -> PackedVectors {
+            serde_json::from_value(serde_json::json!({
+                "dimensions": dimensions,
+                "count": count,
+                "data": {"Inline": ""},
+                "metadata": {},
+            })).unwrap()
+        };
+        let overflowing = decode(1 << 60, 1 << 10);
+        assert!(overflowing.byte_len().unwrap_err().to_string().contains("overflow"));
+        assert!(overflowing.unpack().is_err());
+        
+        let dimensionless = decode(1 << 60, 0);
+        assert!(dimensionless.byte_len().is_err());
+        assert!(dimensionless.unpack().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_large_vectors_are_externalized_and_resolved() {
+        let store = MemoryVectorStore::new();
+        let config = VectorPayloadConfig { inline_limit_bytes: 1024, ..Default::default() };
+        let small = PackedVectors::pack(&embeddings(2, 8), HashMap::new()).unwrap();
+        assert!(small.externalize_if_larger(&config, &store).await.unwrap().is_inline());
+        
+        let vectors = embeddings(32, 64);
+        let large = PackedVectors::pack(&vectors, HashMap::new()).unwrap()
+            .externalize_if_larger(&config, &store).await.unwrap();
+        assert!(!large.is_inline());
+        assert!(serde_json::to_vec(&large).unwrap().len() < 512);
+        assert!(large.unpack().is_err());
+        
+        let resolved = large.clone().resolve(&store).await.unwrap();
+        assert_eq!(resolved.unpack().unwrap(), vectors);
+        
+        let VectorData::Reference { path, .. } = &large.data else { unreachable!() };
+        store.put(path, vec![0; 32 * 64 * 4]).await.unwrap();
+        assert!(large.resolve(&store).await.unwrap_err().to_string().contains("changed"));
+    }
+}
@@ -0,0 +1,418 @@
+//! Worker Registry
+//!
+//! Persistent record of every worker the coordinator has seen, including
+//! ones that have since disconnected. Each worker keeps its first and last
+//! sighting and a history of membership changes, with the version and
+//! capabilities (and failure domain) it reported on every registration, so
+//! fleet composition can be reconstructed for any point in time. Records are stored in sled, one
+//! JSON value per worker, optionally zstd-compressed.
+
+use crate::compression::{CompressionConfig, CompressionStats, Compressor};
+use crate::scheduling::FailureDomain;
+use crate::coordinator_events::{CoordinatorEvent, EventRecord};
+use crate::types::{WorkerCapability, WorkerConfig};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Worker metadata key holding the worker's software version
+pub const WORKER_VERSION_KEY: &str = "version";
+
+/// A change in a worker's fleet membership
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MembershipChange {
+    /// Registered with the coordinator, reporting its version and capabilities
+    Registered {
+        version: Option<String>,
+        capabilities: Vec<String>,
+        #[serde(default)]
+        failure_domain: FailureDomain,
+    },
+    Unregistered,
+    /// Stopped sending heartbeats
+    Unavailable,
+    /// Resumed sending heartbeats
+    Available,
+}
+
+impl MembershipChange {
+    /// Whether the worker is part of the fleet after this change
+    pub fn is_active(&self) -> bool {
+        matches!(self, MembershipChange::Registered { .. } | MembershipChange::Available)
+    }
+}
+
+/// One entry of a worker's membership history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MembershipEvent {
+    pub at: DateTime<Utc>,
+    pub change: MembershipChange,
+}
+
+/// Everything known about one worker, past and present
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkerRecord {
+    pub worker_id: Uuid,
+    pub name: String,
+    pub worker_type: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub registrations: u32,
+    
+    /// Membership changes, oldest first
+    pub history: Vec<MembershipEvent>,
+}
+
+impl WorkerRecord {
+    /// Whether the worker is currently part of the fleet
+    pub fn is_active(&self) -> bool {
+        self.history.last().is_some_and(|event| event.change.is_active())
+    }
+    
+    /// Whether the worker was part of the fleet at `at`
+    pub fn was_active_at(&self, at: DateTime<Utc>) -> bool {
+        self.history.iter()
+            .take_while(|event| event.at <= at)
+            .last()
+            .is_some_and(|event| event.change.is_active())
+    }
+    
+    /// Version and capabilities of the latest registration at or before `at`
+    pub fn registration_at(&self, at: DateTime<Utc>) -> Option<(Option<&str>, &[String])> {
+        self.history.iter()
+            .take_while(|event| event.at <= at)
+            .filter_map(|event| match &event.change {
+                MembershipChange::Registered { version, capabilities, .. } => Some((version.as_deref(), capabilities.as_slice())),
+                _ => None,
+            })
+            .last()
+    }
+    
+    /// Failure domain of the latest registration at or before `at`
+    pub fn failure_domain_at(&self, at: DateTime<Utc>) -> Option<&FailureDomain> {
+        self.history.iter()
+            .take_while(|event| event.at <= at)
+            .filter_map(|event| match &event.change {
+                MembershipChange::Registered { failure_domain, .. } => Some(failure_domain),
+                _ => None,
+            })
+            .last()
+    }
+    
+    /// Distinct versions the worker has registered with, in order
+    pub fn versions(&self) -> Vec<&str> {
+        let mut versions: Vec<&str> = Vec::new();
+        for event in &self.history {
+            if let MembershipChange::Registered { version: Some(version), .. } = &event.change {
+                if versions.last() != Some(&version.as_str()) {
+                    versions.push(version);
+                }
+            }
+        }
+        versions
+    }
+}
+
+/// Fleet make-up at one point in time
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FleetComposition {
+    pub at: DateTime<Utc>,
+    pub active_workers: usize,
+    pub by_worker_type: BTreeMap<String, usize>,
+    pub by_version: BTreeMap<String, usize>,
+    pub by_capability: BTreeMap<String, usize>,
+    
+    /// Active workers per host; unlabelled workers count as `unknown`
+    #[serde(default)]
+    pub by_host: BTreeMap<String, usize>,
+    
+    /// Active workers per zone; unlabelled workers count as `unknown`
+    #[serde(default)]
+    pub by_zone: BTreeMap<String, usize>,
+}
+
+/// sled-backed registry of every worker seen
+#[derive(Debug, Clone)]
+pub struct WorkerRegistry {
+    db: sled::Db,
+    workers: sled::Tree,
+    compressor: Compressor,
+}
+
+impl WorkerRegistry {
+    /// Open (or create) a registry at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let db = sled::open(path)?;
+        Self::from_db(&db)
+    }
+    
+    /// Registry that lives only as long as the process
+    pub fn temporary() -> Result<Self> {
+        let db = sled::Config::new().temporary(true).open()?;
+        Self::from_db(&db)
+    }
+    
+    fn from_db(db: &sled::Db) -> Result<Self> {
+        Ok(Self {
+            db: db.clone(),
+            workers: db.open_tree("workers")?,
+            compressor: Compressor::default(),
+        })
+    }
+    
+    /// Compress records written from now on; existing records stay readable
+    pub fn with_compression(mut self, config: CompressionConfig) -> Self {
+        self.compressor = Compressor::new(config);
+        self
+    }
+    
+    /// Record a worker registering with its current configuration
+    pub fn record_registration(&self, worker_id: Uuid, config: &WorkerConfig, at: DateTime<Utc>) -> Result<WorkerRecord> {
+        let version = config.metadata.get(WORKER_VERSION_KEY).map(|value| match value {
+            serde_json::Value::String(version) => version.clone(),
+            other => other.to_string(),
+        });
+        let change = MembershipChange::Registered {
+            version,
+            capabilities: config.capabilities.iter().map(capability_label).collect(),
+            failure_domain: FailureDomain::of(config),
+        };
+        
+        let mut record = self.get(worker_id)?.unwrap_or_else(|| WorkerRecord {
+            worker_id,
+            name: config.name.clone(),
+            worker_type: config.worker_type.to_string(),
+            first_seen: at,
+            last_seen: at,
+            registrations: 0,
+            history: Vec::new(),
+        });
+        record.name = config.name.clone();
+        record.worker_type = config.worker_type.to_string();
+        record.registrations += 1;
+        record.last_seen = record.last_seen.max(at);
+        record.history.push(MembershipEvent { at, change });
+        self.store(&record)?;
+        Ok(record)
+    }
+    
+    /// Record a membership change of a known worker; unknown workers are ignored
+    pub fn record_change(&self, worker_id: Uuid, change: MembershipChange, at: DateTime<Utc>) -> Result<()> {
+        if let Some(mut record) = self.get(worker_id)? {
+            record.last_seen = record.last_seen.max(at);
+            record.history.push(MembershipEvent { at, change });
+            self.store(&record)?;
+        }
+        Ok(())
+    }
+    
+    /// Record a sign of life (e.g. a heartbeat) from a known worker
+    pub fn record_seen(&self, worker_id: Uuid, at: DateTime<Utc>) -> Result<()> {
+        if let Some(mut record) = self.get(worker_id)? {
+            if at > record.last_seen {
+                record.last_seen = at;
+                self.store(&record)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Apply the worker membership events of the coordinator event log
+    pub fn apply(&self, record: &EventRecord) -> Result<()> {
+        let at = record.recorded_at;
+        match &record.event {
+            CoordinatorEvent::WorkerRegistered { worker_id, config } => self.record_registration(*worker_id, config, at).map(|_| ()),
+            CoordinatorEvent::WorkerUnregistered { worker_id } => self.record_change(*worker_id, MembershipChange::Unregistered, at),
+            CoordinatorEvent::WorkerUnavailable { worker_id, .. } => self.record_change(*worker_id, MembershipChange::Unavailable, at),
+            CoordinatorEvent::WorkerAvailable { worker_id } => self.record_change(*worker_id, MembershipChange::Available, at),
+            _ => Ok(()),
+        }
+    }
+    
+    pub fn get(&self, worker_id: Uuid) -> Result<Option<WorkerRecord>> {
+        match self.workers.get(worker_id.as_bytes())? {
+            Some(value) => Ok(Some(self.compressor.from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Every worker ever seen, active or not
+    pub fn workers(&self) -> Result<Vec<WorkerRecord>> {
+        self.workers.iter()
+            .map(|entry| self.compressor.from_slice(&entry?.1))
+            .collect()
+    }
+    
+    /// Fleet composition at `at`
+    pub fn fleet_at(&self, at: DateTime<Utc>) -> Result<FleetComposition> {
+        let mut composition = FleetComposition { at, ..Default::default() };
+        for record in self.workers()? {
+            if !record.was_active_at(at) {
+                continue;
+            }
+            composition.active_workers += 1;
+            *composition.by_worker_type.entry(record.worker_type.clone()).or_insert(0) += 1;
+            if let Some((version, capabilities)) = record.registration_at(at) {
+                *composition.by_version.entry(version.unwrap_or("unknown").to_string()).or_insert(0) += 1;
+                for capability in capabilities {
+                    *composition.by_capability.entry(capability.clone()).or_insert(0) += 1;
+                }
+            }
+            let domain = record.failure_domain_at(at).cloned().unwrap_or_default();
+            *composition.by_host.entry(domain.host.unwrap_or_else(|| "unknown".to_string())).or_insert(0) += 1;
+            *composition.by_zone.entry(domain.zone.unwrap_or_else(|| "unknown".to_string())).or_insert(0) += 1;
+        }
+        Ok(composition)
+    }
+    
+    /// Fleet composition from `from` to `to`, sampled every `step`
+    pub fn fleet_history(&self, from: DateTime<Utc>, to: DateTime<Utc>, step: Duration) -> Result<Vec<FleetComposition>> {
+        anyhow::ensure!(step > Duration::zero(), "fleet history step must be positive");
+        let mut history = Vec::new();
+        let mut at = from;
+        while at <= to {
+            history.push(self.fleet_at(at)?);
+            at += step;
+        }
+        Ok(history)
+    }
+    
+    /// Flush pending writes to disk
+    pub fn flush(&self) -> Result<()> {
+        self.workers.flush()?;
+        Ok(())
+    }
+    
+    /// Size of the database on disk, in bytes
+    pub fn size_on_disk(&self) -> Result<u64> {
+        Ok(self.db.size_on_disk()?)
+    }
+    
+    /// Bytes of records written by this registry, before and after compression
+    pub fn compression_stats(&self) -> CompressionStats {
+        self.compressor.stats()
+    }
+    
+    fn store(&self, record: &WorkerRecord) -> Result<()> {
+        self.workers.insert(record.worker_id.as_bytes(), self.compressor.to_vec(record)?)?;
+        Ok(())
+    }
+}
+
+/// Capability label as stored in the history, e.g. `pdf-extraction@1.2`
+fn capability_label(capability: &WorkerCapability) -> String {
+    format!("{}@{}", capability.name, capability.version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PerformanceProfile, WorkerType};
+    use std::collections::HashMap;
+    
+    fn create_test_config(name: &str, version: &str, capabilities: &[&str]) -> WorkerConfig {
+        let profile = PerformanceProfile {
+            avg_processing_time_ms: 10,
+            memory_usage_mb: 16,
+            cpu_intensity: 0.1,
+            throughput_per_second: 100.0,
+        };
+        WorkerConfig {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            worker_type: WorkerType::Custom { name: "test".to_string(), version: "1".to_string() },
+            max_concurrent_tasks: 1,
+            capabilities: capabilities.iter().map(|name| WorkerCapability {
+                name: name.to_string(),
+                version: "1".to_string(),
+                supported_task_types: Vec::new(),
+                max_concurrent_tasks: 1,
+                performance_profile: profile.clone(),
+                metadata: HashMap::new(),
+            }).collect(),
+            performance_profile: profile,
+            health_check_interval_ms: 1000,
+            shutdown_timeout_ms: 1000,
+            metadata: HashMap::from([(WORKER_VERSION_KEY.to_string(), serde_json::json!(version))]),
+        }
+    }
+    
+    #[test]
+    fn test_departed_workers_are_remembered() {
+        let registry = WorkerRegistry::temporary().unwrap();
+        let start = Utc::now();
+        let mut config = create_test_config("pdf-1", "0.1.0", &["pdf"]);
+        
+        registry.record_registration(config.id, &config, start).unwrap();
+        registry.record_seen(config.id, start + Duration::seconds(30)).unwrap();
+        registry.record_change(config.id, MembershipChange::Unregistered, start + Duration::seconds(60)).unwrap();
+        config.metadata.insert(WORKER_VERSION_KEY.to_string(), serde_json::json!("0.2.0"));
+        registry.record_registration(config.id, &config, start + Duration::seconds(90)).unwrap();
+        registry.record_change(config.id, MembershipChange::Unregistered, start + Duration::seconds(120)).unwrap();
+        
+        let record = registry.get(config.id).unwrap().unwrap();
+        assert!(!record.is_active());
+        assert_eq!(record.first_seen, start);
+        assert_eq!(record.last_seen, start + Duration::seconds(120));
+        assert_eq!(record.registrations, 2);
+        assert_eq!(record.versions(), vec!["0.1.0", "0.2.0"]);
+        assert_eq!(registry.workers().unwrap().len(), 1);
+        
+        // Unknown workers are not created by heartbeats or departures
+        registry.record_seen(Uuid::new_v4(), start).unwrap();
+        registry.record_change(Uuid::new_v4(), MembershipChange::Unregistered, start).unwrap();
+        assert_eq!(registry.workers().unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_fleet_composition_over_time() {
+        let registry = WorkerRegistry::temporary().unwrap();
+        let start = Utc::now();
+        let mut pdf = create_test_config("pdf-1", "0.1.0", &["pdf", "ocr"]);
+        pdf.metadata.insert(crate::scheduling::FAILURE_DOMAIN_HOST_KEY.to_string(), serde_json::json!("node-a"));
+        pdf.metadata.insert(crate::scheduling::FAILURE_DOMAIN_ZONE_KEY.to_string(), serde_json::json!("eu-north-1a"));
+        let text = create_test_config("text-1", "0.2.0", &["text"]);
+        
+        registry.record_registration(pdf.id, &pdf, start).unwrap();
+        registry.record_registration(text.id, &text, start + Duration::seconds(10)).unwrap();
+        registry.record_change(pdf.id, MembershipChange::Unavailable, start + Duration::seconds(20)).unwrap();
+        registry.record_change(pdf.id, MembershipChange::Available, start + Duration::seconds(30)).unwrap();
+        
+        let fleet = registry.fleet_at(start + Duration::seconds(15)).unwrap();
+        assert_eq!(fleet.active_workers, 2);
+        assert_eq!(fleet.by_version.get("0.1.0"), Some(&1));
+        assert_eq!(fleet.by_capability.get("ocr@1"), Some(&1));
+        assert_eq!(fleet.by_worker_type.values().sum::<usize>(), 2);
+        assert_eq!(fleet.by_zone, BTreeMap::from([("eu-north-1a".to_string(), 1), ("unknown".to_string(), 1)]));
+        assert_eq!(fleet.by_host.get("node-a"), Some(&1));
+        
+        let history = registry.fleet_history(start - Duration::seconds(5), start + Duration::seconds(35), Duration::seconds(10)).unwrap();
+        let active: Vec<usize> = history.iter().map(|fleet| fleet.active_workers).collect();
+        assert_eq!(active, vec![0, 1, 2, 1, 2]);
+        assert!(registry.fleet_history(start, start, Duration::zero()).is_err());
+    }
+    
+    #[test]
+    fn test_registry_persists_across_reopen() {
+        let directory = tempfile::tempdir().unwrap();
+        let config = create_test_config("pdf-1", "0.1.0", &["pdf"]);
+        {
+            let registry = WorkerRegistry::open(directory.path()).unwrap();
+            registry.record_registration(config.id, &config, Utc::now()).unwrap();
+            registry.flush().unwrap();
+        }
+        
+        // Records written before compression was enabled stay readable
+        let registry = WorkerRegistry::open(directory.path()).unwrap().with_compression(CompressionConfig::zstd(3));
+        assert_eq!(registry.get(config.id).unwrap().unwrap().name, "pdf-1");
+        let compressed = create_test_config("pdf-2", "0.1.0", &["pdf"]);
+        registry.record_registration(compressed.id, &compressed, Utc::now()).unwrap();
+        assert_eq!(registry.workers().unwrap().len(), 2);
+        assert_eq!(registry.compression_stats().values_written, 1);
+        assert!(registry.compression_stats().ratio() > 1.0);
+        assert!(registry.size_on_disk().unwrap() > 0);
+    }
+}
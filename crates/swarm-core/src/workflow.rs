@@ -0,0 +1,456 @@
+//! Workflows
+//!
+//! Processing a document often takes several dependent steps, such as
+//! extract → chunk → embed → index. A `Workflow` names its stages, each with
+//! the task to run and the stages it depends on; the stages form a directed
+//! acyclic graph. In the coordinator a `WorkflowTracker` holds each stage
+//! back until all of its parents completed, then releases its task with the
+//! parents' results in its metadata. The workflow completes once every stage
+//! did; the first stage to fail for good fails the workflow, skips the
+//! stages not yet released and cancels the released ones still running.
+
+use crate::coordinator_events::{CoordinatorEvent, EventRecord, Projection};
+use crate::error::{SwarmError, SwarmResult};
+use crate::types::{Task, TaskResult, TaskStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Task metadata key holding the ID of the workflow a task belongs to
+pub const WORKFLOW_KEY: &str = "workflow";
+
+/// Task metadata key holding the name of the task's stage
+pub const WORKFLOW_STAGE_KEY: &str = "workflow_stage";
+
+/// Task metadata key holding the results of the stage's parents, by stage name
+pub const WORKFLOW_INPUTS_KEY: &str = "workflow_inputs";
+
+/// One step of a workflow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowStage {
+    pub name: String,
+    pub task: Task,
+    
+    /// Stages that have to complete before this one is released
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+/// Stages of dependent tasks, submitted together
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Workflow {
+    pub id: String,
+    
+    /// Stages in declaration order
+    pub stages: Vec<WorkflowStage>,
+}
+
+impl Workflow {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self { id: id.into(), stages: Vec::new() }
+    }
+    
+    /// Add a stage running `task` once the stages in `depends_on` completed
+    pub fn stage(mut self, name: impl Into<String>, task: Task, depends_on: &[&str]) -> Self {
+        self.stages.push(WorkflowStage {
+            name: name.into(),
+            task,
+            depends_on: depends_on.iter().map(|parent| parent.to_string()).collect(),
+        });
+        self
+    }
+    
+    /// Check that the workflow has stages, unique stage names, known parents and no cycles
+    pub fn validate(&self) -> SwarmResult<()> {
+        let invalid = |reason: String| Err(SwarmError::Workflow(format!("workflow {}: {}", self.id, reason)));
+        if self.stages.is_empty() {
+            return invalid("has no stages".to_string());
+        }
+        
+        let mut names = HashSet::new();
+        for stage in &self.stages {
+            if !names.insert(stage.name.as_str()) {
+                return invalid(format!("stage {} is declared twice", stage.name));
+            }
+        }
+        for stage in &self.stages {
+            if let Some(parent) = stage.depends_on.iter().find(|parent| !names.contains(parent.as_str())) {
+                return invalid(format!("stage {} depends on unknown stage {}", stage.name, parent));
+            }
+        }
+        
+        // Peel off stages whose parents are all placed; whatever is left is on a cycle
+        let mut placed: HashSet<&str> = HashSet::new();
+        while placed.len() < self.stages.len() {
+            let ready: Vec<&str> = self.stages.iter()
+                .filter(|stage| !placed.contains(stage.name.as_str()))
+                .filter(|stage| stage.depends_on.iter().all(|parent| placed.contains(parent.as_str())))
+                .map(|stage| stage.name.as_str())
+                .collect();
+            if ready.is_empty() {
+                let mut cyclic: Vec<&str> = self.stages.iter()
+                    .map(|stage| stage.name.as_str())
+                    .filter(|name| !placed.contains(name))
+                    .collect();
+                cyclic.sort();
+                return invalid(format!("stages {} depend on each other", cyclic.join(", ")));
+            }
+            placed.extend(ready);
+        }
+        Ok(())
+    }
+}
+
+/// Progress of a stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StageStatus {
+    /// Waiting for its parents
+    Waiting,
+    /// Released to the queue
+    Released,
+    Completed,
+    Failed,
+    /// Released, but cancelled because the workflow failed
+    Cancelled,
+    /// Never released because the workflow failed
+    Skipped,
+}
+
+/// Outcome of a workflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowStatus {
+    /// Every stage completed
+    Completed,
+    /// A stage failed or was cancelled
+    Failed,
+}
+
+/// Final status of a stage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageOutcome {
+    pub name: String,
+    pub task_id: Uuid,
+    pub status: StageStatus,
+    pub error: Option<String>,
+}
+
+/// A finished workflow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowResult {
+    pub workflow_id: String,
+    pub status: WorkflowStatus,
+    
+    /// Stages in declaration order
+    pub stages: Vec<StageOutcome>,
+    
+    /// Results of the completed stages, by stage name
+    pub results: HashMap<String, TaskResult>,
+    pub finished_at: DateTime<Utc>,
+}
+
+impl WorkflowResult {
+    /// Tasks of released stages cancelled because the workflow failed
+    pub fn cancelled_tasks(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.stages.iter()
+            .filter(|stage| stage.status == StageStatus::Cancelled)
+            .map(|stage| stage.task_id)
+    }
+}
+
+/// Releases the stages of each workflow as their parents complete
+///
+/// Stage outcomes are folded from coordinator events; results of stage tasks
+/// are kept through `record_result` and handed to the stages depending on them.
+#[derive(Debug, Default)]
+pub struct WorkflowTracker {
+    workflows: HashMap<String, TrackedWorkflow>,
+    stage_of: HashMap<Uuid, (String, usize)>,
+    ready: Vec<Task>,
+    finished: Vec<WorkflowResult>,
+}
+
+#[derive(Debug)]
+struct TrackedWorkflow {
+    stages: Vec<TrackedStage>,
+    results: HashMap<String, TaskResult>,
+}
+
+#[derive(Debug)]
+struct TrackedStage {
+    stage: WorkflowStage,
+    status: StageStatus,
+    error: Option<String>,
+}
+
+impl WorkflowTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Start tracking a workflow, releasing the stages without parents
+    pub fn add(&mut self, workflow: Workflow) -> SwarmResult<()> {
+        workflow.validate()?;
+        if self.workflows.contains_key(&workflow.id) {
+            return Err(SwarmError::Workflow(format!("workflow {} is already running", workflow.id)));
+        }
+        
+        let stages = workflow.stages.into_iter()
+            .map(|mut stage| {
+                stage.task.metadata.insert(WORKFLOW_KEY.to_string(), serde_json::json!(workflow.id));
+                stage.task.metadata.insert(WORKFLOW_STAGE_KEY.to_string(), serde_json::json!(stage.name));
+                TrackedStage { stage, status: StageStatus::Waiting, error: None }
+            })
+            .collect();
+        self.workflows.insert(workflow.id.clone(), TrackedWorkflow { stages, results: HashMap::new() });
+        self.release_ready(&workflow.id);
+        Ok(())
+    }
+    
+    /// Workflow and stage a task belongs to, while its workflow is running
+    pub fn stage_of(&self, task_id: Uuid) -> Option<(&str, &str)> {
+        let (workflow_id, index) = self.stage_of.get(&task_id)?;
+        let stage = &self.workflows.get(workflow_id)?.stages[*index];
+        Some((workflow_id.as_str(), stage.stage.name.as_str()))
+    }
+    
+    /// Status of every stage of a running workflow, in declaration order
+    pub fn progress(&self, workflow_id: &str) -> Option<Vec<(&str, StageStatus)>> {
+        let workflow = self.workflows.get(workflow_id)?;
+        Some(workflow.stages.iter().map(|stage| (stage.stage.name.as_str(), stage.status)).collect())
+    }
+    
+    /// Workflows with stages still to run
+    pub fn running_workflows(&self) -> usize {
+        self.workflows.len()
+    }
+    
+    /// Keep the result of a stage task for the stages depending on it
+    pub fn record_result(&mut self, result: &TaskResult) {
+        let Some((workflow_id, index)) = self.stage_of.get(&result.task_id) else { return };
+        let Some(workflow) = self.workflows.get_mut(workflow_id) else { return };
+        let stage = &mut workflow.stages[*index];
+        stage.error = result.error.clone();
+        workflow.results.insert(stage.stage.name.clone(), result.clone());
+    }
+    
+    /// Stage tasks released since the last call, to be submitted
+    pub fn take_ready(&mut self) -> Vec<Task> {
+        std::mem::take(&mut self.ready)
+    }
+    
+    /// Workflows finished since the last call
+    pub fn take_finished(&mut self) -> Vec<WorkflowResult> {
+        std::mem::take(&mut self.finished)
+    }
+    
+    /// Release every waiting stage whose parents all completed
+    fn release_ready(&mut self, workflow_id: &str) {
+        let Some(workflow) = self.workflows.get_mut(workflow_id) else { return };
+        let completed: HashSet<String> = workflow.stages.iter()
+            .filter(|stage| stage.status == StageStatus::Completed)
+            .map(|stage| stage.stage.name.clone())
+            .collect();
+        
+        for (index, stage) in workflow.stages.iter_mut().enumerate() {
+            if stage.status != StageStatus::Waiting || !stage.stage.depends_on.iter().all(|parent| completed.contains(parent)) {
+                continue;
+            }
+            let inputs: serde_json::Map<String, serde_json::Value> = stage.stage.depends_on.iter()
+                .filter_map(|parent| {
+                    let output = workflow.results.get(parent)?.result.as_ref()
+                        .and_then(|data| serde_json::to_value(data).ok())
+                        .unwrap_or(serde_json::Value::Null);
+                    Some((parent.clone(), output))
+                })
+                .collect();
+            let mut task = stage.stage.task.clone();
+            if !inputs.is_empty() {
+                task.metadata.insert(WORKFLOW_INPUTS_KEY.to_string(), serde_json::Value::Object(inputs));
+            }
+            
+            stage.status = StageStatus::Released;
+            self.stage_of.insert(task.id, (workflow_id.to_string(), index));
+            self.ready.push(task);
+        }
+    }
+    
+    fn finish_stage(&mut self, task_id: Uuid, status: TaskStatus) {
+        let Some((workflow_id, index)) = self.stage_of.remove(&task_id) else { return };
+        let Some(workflow) = self.workflows.get_mut(&workflow_id) else { return };
+        let stage = &mut workflow.stages[index];
+        
+        if status != TaskStatus::Completed {
+            stage.status = StageStatus::Failed;
+            return self.finish_workflow(&workflow_id, WorkflowStatus::Failed);
+        }
+        stage.status = StageStatus::Completed;
+        if workflow.stages.iter().all(|stage| stage.status == StageStatus::Completed) {
+            self.finish_workflow(&workflow_id, WorkflowStatus::Completed);
+        } else {
+            self.release_ready(&workflow_id);
+        }
+    }
+    
+    fn finish_workflow(&mut self, workflow_id: &str, status: WorkflowStatus) {
+        let Some(mut workflow) = self.workflows.remove(workflow_id) else { return };
+        let stages = workflow.stages.iter_mut()
+            .map(|stage| {
+                let task_id = stage.stage.task.id;
+                stage.status = match stage.status {
+                    StageStatus::Waiting => StageStatus::Skipped,
+                    StageStatus::Released if self.stage_of.remove(&task_id).is_some() => StageStatus::Cancelled,
+                    status => status,
+                };
+                StageOutcome {
+                    name: stage.stage.name.clone(),
+                    task_id,
+                    status: stage.status,
+                    error: stage.error.take(),
+                }
+            })
+            .collect();
+        workflow.results.retain(|_, result| result.status == TaskStatus::Completed);
+        self.finished.push(WorkflowResult {
+            workflow_id: workflow_id.to_string(),
+            status,
+            stages,
+            results: workflow.results,
+            finished_at: Utc::now(),
+        });
+    }
+}
+
+impl Projection for WorkflowTracker {
+    fn apply(&mut self, record: &EventRecord) {
+        match &record.event {
+            CoordinatorEvent::TaskFinished { task_id, status, .. } => self.finish_stage(*task_id, status.clone()),
+            CoordinatorEvent::TaskDropped { task_id, .. } => self.finish_stage(*task_id, TaskStatus::Cancelled),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinator_events::CoordinatorEventLog;
+    use crate::types::{TaskPayload, TaskPriority, TaskResultData, TaskType};
+    
+    fn task(name: &str) -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            task_type: TaskType::Custom { name: name.to_string(), version: "1".to_string() },
+            priority: TaskPriority::Normal,
+            status: TaskStatus::Pending,
+            payload: TaskPayload::Custom { data: Vec::new(), format: "raw".to_string() },
+            created_at: Utc::now(),
+            deadline: None,
+            retry_count: 0,
+            max_retries: 0,
+            metadata: HashMap::new(),
+        }
+    }
+    
+    fn finished(task_id: Uuid, status: TaskStatus, output: &str) -> TaskResult {
+        TaskResult {
+            task_id,
+            status,
+            result: Some(TaskResultData::Custom { data: output.as_bytes().to_vec(), format: "text".to_string() }),
+            error: None,
+            processing_time_ms: 1,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        }
+    }
+    
+    /// Record a task result and its event, as the coordinator does
+    fn finish(tracker: &mut WorkflowTracker, log: &mut CoordinatorEventLog, result: TaskResult) {
+        tracker.record_result(&result);
+        tracker.apply(log.append(CoordinatorEvent::task_finished(&result)));
+    }
+    
+    fn pipeline() -> Workflow {
+        Workflow::new("doc-1")
+            .stage("extract", task("extract"), &[])
+            .stage("chunk", task("chunk"), &["extract"])
+            .stage("embed", task("embed"), &["chunk"])
+            .stage("keywords", task("keywords"), &["extract"])
+            .stage("index", task("index"), &["embed", "keywords"])
+    }
+    
+    #[test]
+    fn test_invalid_workflows_are_rejected() {
+        assert!(Workflow::new("empty").validate().is_err());
+        let unknown = Workflow::new("w").stage("a", task("a"), &["missing"]);
+        assert!(matches!(unknown.validate(), Err(SwarmError::Workflow(_))));
+        let twice = Workflow::new("w").stage("a", task("a"), &[]).stage("a", task("a"), &[]);
+        assert!(twice.validate().is_err());
+        let cycle = Workflow::new("w")
+            .stage("a", task("a"), &[])
+            .stage("b", task("b"), &["a", "c"])
+            .stage("c", task("c"), &["b"]);
+        assert!(cycle.validate().unwrap_err().to_string().contains("stages b, c depend on each other"));
+        assert!(pipeline().validate().is_ok());
+    }
+    
+    #[test]
+    fn test_stages_run_once_their_parents_completed() {
+        let mut tracker = WorkflowTracker::new();
+        let mut log = CoordinatorEventLog::new();
+        tracker.add(pipeline()).unwrap();
+        assert!(tracker.add(pipeline()).is_err());
+        
+        let extract = tracker.take_ready();
+        assert_eq!(extract.len(), 1);
+        assert_eq!(extract[0].metadata[WORKFLOW_STAGE_KEY], "extract");
+        finish(&mut tracker, &mut log, finished(extract[0].id, TaskStatus::Completed, "text"));
+        
+        // Both children of extract are released, with its output as their input
+        let released = tracker.take_ready();
+        let names: Vec<&str> = released.iter().map(|task| task.metadata[WORKFLOW_STAGE_KEY].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["chunk", "keywords"]);
+        let extracted = serde_json::to_value(TaskResultData::Custom { data: b"text".to_vec(), format: "text".to_string() }).unwrap();
+        assert_eq!(released[0].metadata[WORKFLOW_INPUTS_KEY], serde_json::json!({ "extract": extracted }));
+        for task in &released {
+            finish(&mut tracker, &mut log, finished(task.id, TaskStatus::Completed, ""));
+        }
+        
+        let embed = tracker.take_ready();
+        assert_eq!(embed.len(), 1);
+        assert!(tracker.progress("doc-1").unwrap().contains(&("index", StageStatus::Waiting)));
+        finish(&mut tracker, &mut log, finished(embed[0].id, TaskStatus::Completed, "0.5"));
+        
+        let index = tracker.take_ready();
+        let inputs = index[0].metadata[WORKFLOW_INPUTS_KEY].as_object().unwrap();
+        assert_eq!(inputs.keys().collect::<Vec<_>>(), vec!["embed", "keywords"]);
+        finish(&mut tracker, &mut log, finished(index[0].id, TaskStatus::Completed, ""));
+        
+        let result = tracker.take_finished().pop().unwrap();
+        assert_eq!(result.status, WorkflowStatus::Completed);
+        assert_eq!(result.results.len(), 5);
+        assert_eq!(tracker.running_workflows(), 0);
+    }
+    
+    #[test]
+    fn test_failed_stage_skips_and_cancels_the_rest() {
+        let mut tracker = WorkflowTracker::new();
+        let mut log = CoordinatorEventLog::new();
+        tracker.add(pipeline()).unwrap();
+        let extract = tracker.take_ready().remove(0);
+        finish(&mut tracker, &mut log, finished(extract.id, TaskStatus::Completed, ""));
+        let released = tracker.take_ready();
+        
+        finish(&mut tracker, &mut log, finished(released[0].id, TaskStatus::Failed, ""));
+        let result = tracker.take_finished().pop().unwrap();
+        assert_eq!(result.status, WorkflowStatus::Failed);
+        let statuses: Vec<StageStatus> = result.stages.iter().map(|stage| stage.status).collect();
+        assert_eq!(statuses, vec![
+            StageStatus::Completed, StageStatus::Failed, StageStatus::Skipped, StageStatus::Cancelled, StageStatus::Skipped,
+        ]);
+        assert_eq!(result.cancelled_tasks().collect::<Vec<_>>(), vec![released[1].id]);
+        assert!(tracker.stage_of(released[1].id).is_none());
+    }
+}
@@ -0,0 +1,435 @@
+//! Canary Validation
+//!
+//! Runs a candidate pipeline next to the current one on a fraction of the
+//! documents and compares their results: extracted text similarity, keyword
+//! overlap, and language and classification changes. Callers always get the
+//! baseline result; comparisons accumulate in a divergence report that is
+//! published to subscribers (and as a message on `CANARY_REPORT_SUBJECT`)
+//! for review before the candidate is rolled out.
+
+use super::*;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use swarm_core::{MessageHeaders, CONTENT_TYPE_HEADER};
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncRead;
+use tokio::sync::mpsc;
+
+/// Subject divergence reports are published on
+pub const CANARY_REPORT_SUBJECT: &str = "swarm.canary.reports";
+
+/// Canary configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanaryConfig {
+    /// Version label of the current pipeline
+    pub baseline_version: String,
+    
+    /// Version label of the pipeline under evaluation
+    pub candidate_version: String,
+    
+    /// Percentage of documents processed by both pipelines, from 0.0 to 100.0
+    pub sample_percent: f64,
+    
+    /// Extracted text similarity below which results diverge, from 0.0 to 1.0
+    pub min_text_similarity: f32,
+    
+    /// Keyword overlap (Jaccard) below which results diverge, from 0.0 to 1.0
+    pub min_keyword_overlap: f32,
+    
+    /// Diverging comparisons kept in a report as examples
+    pub max_reported_divergences: usize,
+}
+
+impl Default for CanaryConfig {
+    fn default() -> Self {
+        Self {
+            baseline_version: "baseline".to_string(),
+            candidate_version: "candidate".to_string(),
+            sample_percent: 5.0,
+            min_text_similarity: 0.95,
+            min_keyword_overlap: 0.6,
+            max_reported_divergences: 50,
+        }
+    }
+}
+
+/// Comparison of the two pipelines' results for one document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResultDiff {
+    pub document_id: Uuid,
+    pub filename: String,
+    
+    /// Cosine similarity of the extracted texts' word counts
+    pub text_similarity: f32,
+    
+    /// Jaccard overlap of the keyword sets
+    pub keyword_overlap: f32,
+    
+    /// Keywords only the baseline or only the candidate found
+    pub keywords_removed: Vec<String>,
+    pub keywords_added: Vec<String>,
+    
+    /// `(baseline, candidate)` when they differ
+    pub language_change: Option<(Option<String>, Option<String>)>,
+    pub classification_change: Option<(Option<String>, Option<String>)>,
+    
+    pub baseline_error: Option<String>,
+    pub candidate_error: Option<String>,
+    
+    /// Whether the difference exceeds the configured thresholds
+    pub diverged: bool,
+}
+
+/// Aggregate comparison of the two pipelines
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DivergenceReport {
+    pub baseline_version: String,
+    pub candidate_version: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+    pub documents_compared: u64,
+    pub documents_diverged: u64,
+    pub mean_text_similarity: f32,
+    pub mean_keyword_overlap: f32,
+    pub language_changes: u64,
+    pub classification_changes: u64,
+    pub baseline_errors: u64,
+    pub candidate_errors: u64,
+    
+    /// Examples of diverging documents, oldest first
+    pub divergences: Vec<ResultDiff>,
+}
+
+impl DivergenceReport {
+    fn new(config: &CanaryConfig) -> Self {
+        let now = Utc::now();
+        Self {
+            baseline_version: config.baseline_version.clone(),
+            candidate_version: config.candidate_version.clone(),
+            started_at: now,
+            generated_at: now,
+            documents_compared: 0,
+            documents_diverged: 0,
+            mean_text_similarity: 1.0,
+            mean_keyword_overlap: 1.0,
+            language_changes: 0,
+            classification_changes: 0,
+            baseline_errors: 0,
+            candidate_errors: 0,
+            divergences: Vec::new(),
+        }
+    }
+    
+    fn record(&mut self, diff: ResultDiff, max_reported_divergences: usize) {
+        let previous = self.documents_compared as f32;
+        self.documents_compared += 1;
+        let compared = self.documents_compared as f32;
+        self.mean_text_similarity = (self.mean_text_similarity * previous + diff.text_similarity) / compared;
+        self.mean_keyword_overlap = (self.mean_keyword_overlap * previous + diff.keyword_overlap) / compared;
+        self.language_changes += diff.language_change.is_some() as u64;
+        self.classification_changes += diff.classification_change.is_some() as u64;
+        self.baseline_errors += diff.baseline_error.is_some() as u64;
+        self.candidate_errors += diff.candidate_error.is_some() as u64;
+        
+        if diff.diverged {
+            self.documents_diverged += 1;
+            if self.divergences.len() < max_reported_divergences {
+                self.divergences.push(diff);
+            }
+        }
+    }
+    
+    /// Share of compared documents that diverged
+    pub fn divergence_rate(&self) -> f32 {
+        if self.documents_compared == 0 {
+            0.0
+        } else {
+            self.documents_diverged as f32 / self.documents_compared as f32
+        }
+    }
+    
+    /// The report as a message on `CANARY_REPORT_SUBJECT`
+    pub fn to_message(&self) -> Result<Message> {
+        let mut headers = MessageHeaders::new();
+        headers.insert(CONTENT_TYPE_HEADER, "application/json");
+        headers.insert("baseline-version", self.baseline_version.clone());
+        headers.insert("candidate-version", self.candidate_version.clone());
+        Ok(Message {
+            id: Uuid::new_v4(),
+            subject: CANARY_REPORT_SUBJECT.to_string(),
+            payload: serde_json::to_vec(self)?,
+            headers,
+            timestamp: Utc::now(),
+            ttl_ms: None,
+        })
+    }
+}
+
+/// Compare a baseline and a candidate result for the same document
+pub fn diff_results(
+    document: &Document,
+    baseline: &Result<DocumentProcessingResult>,
+    candidate: &Result<DocumentProcessingResult>,
+    config: &CanaryConfig,
+) -> ResultDiff {
+    let mut diff = ResultDiff {
+        document_id: document.id,
+        filename: document.filename.clone(),
+        text_similarity: 1.0,
+        keyword_overlap: 1.0,
+        keywords_removed: Vec::new(),
+        keywords_added: Vec::new(),
+        language_change: None,
+        classification_change: None,
+        baseline_error: baseline.as_ref().err().map(|e| e.to_string()),
+        candidate_error: candidate.as_ref().err().map(|e| e.to_string()),
+        diverged: false,
+    };
+    
+    match (baseline, candidate) {
+        (Ok(baseline), Ok(candidate)) => {
+            diff.text_similarity = text_similarity(baseline.extracted_text.as_deref(), candidate.extracted_text.as_deref());
+            
+            let baseline_keywords: HashSet<&String> = baseline.keywords.iter().collect();
+            let candidate_keywords: HashSet<&String> = candidate.keywords.iter().collect();
+            let union = baseline_keywords.union(&candidate_keywords).count();
+            if union > 0 {
+                diff.keyword_overlap = baseline_keywords.intersection(&candidate_keywords).count() as f32 / union as f32;
+            }
+            diff.keywords_removed = sorted(baseline_keywords.difference(&candidate_keywords));
+            diff.keywords_added = sorted(candidate_keywords.difference(&baseline_keywords));
+            
+            if baseline.language != candidate.language {
+                diff.language_change = Some((baseline.language.clone(), candidate.language.clone()));
+            }
+            if baseline.classification != candidate.classification {
+                diff.classification_change = Some((baseline.classification.clone(), candidate.classification.clone()));
+            }
+            diff.diverged = diff.text_similarity < config.min_text_similarity
+                || diff.keyword_overlap < config.min_keyword_overlap
+                || diff.language_change.is_some()
+                || diff.classification_change.is_some();
+        }
+        // Both failing is agreement; only one failing is a divergence
+        (Err(_), Err(_)) => {}
+        _ => {
+            diff.text_similarity = 0.0;
+            diff.keyword_overlap = 0.0;
+            diff.diverged = true;
+        }
+    }
+    diff
+}
+
+/// Cosine similarity of lowercased word counts; 1.0 when neither has text
+fn text_similarity(baseline: Option<&str>, candidate: Option<&str>) -> f32 {
+    let word_counts = |text: &str| {
+        let mut counts: HashMap<String, f32> = HashMap::new();
+        for word in segmenter_for_language(detect_cjk_language(text)).segment(text) {
+            *counts.entry(word.to_lowercase()).or_insert(0.0) += 1.0;
+        }
+        counts
+    };
+    
+    match (baseline, candidate) {
+        (None, None) => 1.0,
+        (Some(baseline), Some(candidate)) => {
+            let (baseline, candidate) = (word_counts(baseline), word_counts(candidate));
+            if baseline.is_empty() && candidate.is_empty() {
+                return 1.0;
+            }
+            let dot: f32 = baseline.iter().map(|(word, count)| count * candidate.get(word).unwrap_or(&0.0)).sum();
+            let norm = |counts: &HashMap<String, f32>| counts.values().map(|count| count * count).sum::<f32>().sqrt();
+            let norms = norm(&baseline) * norm(&candidate);
+            if norms == 0.0 { 0.0 } else { dot / norms }
+        }
+        _ => 0.0,
+    }
+}
+
+fn sorted<'a>(words: impl Iterator<Item = &'a &'a String>) -> Vec<String> {
+    let mut words: Vec<String> = words.map(|word| word.to_string()).collect();
+    words.sort();
+    words
+}
+
+/// Document processor that shadows a fraction of documents with a candidate pipeline
+pub struct CanaryProcessor {
+    baseline: Arc<dyn DocumentProcessor>,
+    candidate: Arc<dyn DocumentProcessor>,
+    config: CanaryConfig,
+    report: Mutex<DivergenceReport>,
+    subscribers: Mutex<Vec<mpsc::UnboundedSender<DivergenceReport>>>,
+}
+
+impl CanaryProcessor {
+    pub fn new(baseline: Arc<dyn DocumentProcessor>, candidate: Arc<dyn DocumentProcessor>, config: CanaryConfig) -> Self {
+        Self {
+            report: Mutex::new(DivergenceReport::new(&config)),
+            baseline,
+            candidate,
+            config,
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+    
+    /// Receive published divergence reports
+    pub fn subscribe_reports(&self) -> mpsc::UnboundedReceiver<DivergenceReport> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+    
+    /// Report of the comparisons since the last publish
+    pub fn report(&self) -> DivergenceReport {
+        let mut report = self.report.lock().unwrap().clone();
+        report.generated_at = Utc::now();
+        report
+    }
+    
+    /// Publish the current report to subscribers and start a new one
+    pub fn publish_report(&self) -> DivergenceReport {
+        let report = {
+            let mut current = self.report.lock().unwrap();
+            let mut report = std::mem::replace(&mut *current, DivergenceReport::new(&self.config));
+            report.generated_at = Utc::now();
+            report
+        };
+        tracing::info!("Canary {} vs {}: {} of {} documents diverged",
+            report.baseline_version, report.candidate_version, report.documents_diverged, report.documents_compared);
+        self.subscribers.lock().unwrap().retain(|subscriber| subscriber.send(report.clone()).is_ok());
+        report
+    }
+    
+    /// Whether a document is shadowed; stable per document ID
+    fn in_canary(&self, document: &Document) -> bool {
+        let (high, low) = document.id.as_u64_pair();
+        // Salted so the canary fraction is independent of trace sampling
+        let bucket = (high.rotate_left(17) ^ low ^ 0x9e37_79b9_7f4a_7c15) % 10_000;
+        (bucket as f64) < self.config.sample_percent.clamp(0.0, 100.0) * 100.0
+    }
+    
+    fn record(&self, document: &Document, baseline: &Result<DocumentProcessingResult>, candidate: &Result<DocumentProcessingResult>) {
+        let diff = diff_results(document, baseline, candidate, &self.config);
+        if diff.diverged {
+            tracing::debug!("Canary divergence on {}: text similarity {:.2}, keyword overlap {:.2}",
+                document.filename, diff.text_similarity, diff.keyword_overlap);
+        }
+        self.report.lock().unwrap().record(diff, self.config.max_reported_divergences);
+    }
+}
+
+#[async_trait]
+impl DocumentProcessor for CanaryProcessor {
+    async fn process_document(&self, document: &Document) -> Result<DocumentProcessingResult> {
+        if !self.in_canary(document) {
+            return self.baseline.process_document(document).await;
+        }
+        let (baseline, candidate) = tokio::join!(
+            self.baseline.process_document(document),
+            self.candidate.process_document(document),
+        );
+        self.record(document, &baseline, &candidate);
+        baseline
+    }
+    
+    async fn process_document_stream(&self, document: &Document, reader: &mut (dyn AsyncRead + Unpin + Send)) -> Result<DocumentProcessingResult> {
+        // A stream can only be read once, so streamed documents are not shadowed
+        self.baseline.process_document_stream(document, reader).await
+    }
+    
+    fn supported_document_types(&self) -> &[DocumentType] {
+        self.baseline.supported_document_types()
+    }
+    
+    fn can_process(&self, document_type: &DocumentType) -> bool {
+        self.baseline.can_process(document_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn create_test_document(text: &str) -> Document {
+        DocumentBuilder::new("canary.txt", DocumentContent::Text(text.to_string()))
+            .document_type(DocumentType::Text)
+            .build()
+    }
+    
+    fn create_test_result(text: &str, keywords: &[&str], classification: Option<&str>) -> Result<DocumentProcessingResult> {
+        Ok(DocumentProcessingResult {
+            document_id: Uuid::new_v4(),
+            extracted_text: Some(text.to_string()),
+            metadata: HashMap::new(),
+            language: Some("en".to_string()),
+            keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+            sentiment: None,
+            classification: classification.map(str::to_string),
+            embeddings: None,
+            processing_time_ms: 1,
+            processed_at: Utc::now(),
+            warnings: Vec::new(),
+        })
+    }
+    
+    #[test]
+    fn test_diff_results() {
+        let document = create_test_document("text");
+        let config = CanaryConfig::default();
+        
+        let baseline = create_test_result("The budget report for Q3", &["budget", "report"], Some("finance"));
+        let same = diff_results(&document, &baseline, &create_test_result("the budget report for q3", &["report", "budget"], Some("finance")), &config);
+        assert_eq!((same.text_similarity, same.keyword_overlap, same.diverged), (1.0, 1.0, false));
+        
+        let changed = diff_results(&document, &baseline, &create_test_result("The budget for Q3", &["budget", "quarter"], Some("legal")), &config);
+        assert!(changed.text_similarity < 0.95 && changed.text_similarity > 0.5);
+        assert!((changed.keyword_overlap - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!((changed.keywords_removed.clone(), changed.keywords_added.clone()), (vec!["report".to_string()], vec!["quarter".to_string()]));
+        assert_eq!(changed.classification_change, Some((Some("finance".to_string()), Some("legal".to_string()))));
+        assert!(changed.diverged);
+        
+        let failed = diff_results(&document, &baseline, &Err(anyhow::anyhow!("extractor crashed")), &config);
+        assert_eq!(failed.candidate_error.as_deref(), Some("extractor crashed"));
+        assert!(failed.diverged);
+    }
+    
+    #[tokio::test]
+    async fn test_canary_processor_reports_divergence() {
+        let baseline = Arc::new(SwarmDocumentProcessor::new(DocumentProcessingConfig::default()));
+        let mut candidate_config = DocumentProcessingConfig::default();
+        candidate_config.processing_options.extract_keywords = false;
+        let candidate = Arc::new(SwarmDocumentProcessor::new(candidate_config));
+        
+        let canary = CanaryProcessor::new(baseline, candidate, CanaryConfig {
+            baseline_version: "v1".to_string(),
+            candidate_version: "v2".to_string(),
+            sample_percent: 100.0,
+            ..Default::default()
+        });
+        let mut reports = canary.subscribe_reports();
+        
+        let document = create_test_document("Quarterly budget review: revenue increased across every region.");
+        let result = canary.process_document(&document).await.unwrap();
+        assert!(!result.keywords.is_empty(), "callers get the baseline result");
+        
+        let report = canary.publish_report();
+        assert_eq!((report.documents_compared, report.documents_diverged), (1, 1));
+        assert_eq!(report.divergences[0].keyword_overlap, 0.0);
+        assert_eq!(reports.recv().await.unwrap(), report);
+        assert_eq!(canary.report().documents_compared, 0);
+        
+        let message = report.to_message().unwrap();
+        assert_eq!(message.subject, CANARY_REPORT_SUBJECT);
+        assert_eq!(&message.headers["candidate-version"], "v2");
+        
+        let never = CanaryProcessor::new(
+            Arc::new(SwarmDocumentProcessor::new(DocumentProcessingConfig::default())),
+            Arc::new(SwarmDocumentProcessor::new(DocumentProcessingConfig::default())),
+            CanaryConfig { sample_percent: 0.0, ..Default::default() },
+        );
+        never.process_document(&document).await.unwrap();
+        assert_eq!(never.report().documents_compared, 0);
+    }
+}
@@ -0,0 +1,236 @@
+//! Content Sniffing
+//!
+//! Detects a document's type from its first bytes (magic numbers, container
+//! markers, text heuristics) and reconciles the result with the type implied
+//! by the file extension.
+
+use super::*;
+
+/// Number of leading bytes inspected
+pub const SNIFF_LENGTH: usize = 8192;
+
+/// Outcome of content-based type detection
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TypeDetection {
+    /// Type to process the document as
+    pub document_type: DocumentType,
+    
+    pub mime_type: String,
+    
+    /// Confidence in `document_type`, from 0.0 to 1.0
+    pub confidence: f32,
+    
+    /// Type implied by the file extension
+    pub extension_type: DocumentType,
+    
+    /// Type implied by the content, if it gave a signal
+    pub content_type: Option<DocumentType>,
+}
+
+impl TypeDetection {
+    /// Whether content and extension disagree
+    pub fn is_mismatch(&self) -> bool {
+        self.content_type.as_ref().is_some_and(|content_type| *content_type != self.extension_type)
+    }
+}
+
+/// What the leading bytes say about a document
+#[derive(Debug, Clone, PartialEq)]
+enum Signal {
+    /// A signature identifying the format
+    Signature(DocumentType, &'static str),
+    /// A container shared by several formats (zip, OLE), with a best guess
+    Container(Vec<DocumentType>, Option<DocumentType>),
+    /// Readable text; `true` if it looks like HTML
+    Text { html: bool },
+    /// Empty or unrecognised binary content
+    None,
+}
+
+/// Detect the type of a document from its path and leading bytes
+pub fn sniff_document_type(path: &Path, head: &[u8]) -> TypeDetection {
+    let registry = MimeRegistry::global();
+    let extension_type = registry.document_type_for_path(path);
+    let extension_mime = registry.mime_type_for_path(path);
+    
+    let (document_type, mime_type, confidence, content_type) = match sniff(head) {
+        Signal::Signature(content_type, mime_type) => {
+            let confidence = if content_type == extension_type { 1.0 } else { 0.9 };
+            let mime_type = if content_type == extension_type { extension_mime } else { mime_type.to_string() };
+            (content_type.clone(), mime_type, confidence, Some(content_type))
+        }
+        Signal::Container(family, guess) => {
+            if family.contains(&extension_type) {
+                (extension_type.clone(), extension_mime, 0.95, Some(extension_type.clone()))
+            } else {
+                let content_type = guess.unwrap_or(DocumentType::Unknown);
+                let mime_type = match content_type {
+                    DocumentType::Unknown => "application/octet-stream".to_string(),
+                    _ => registry.mime_type_for_extension(default_extension(&content_type)),
+                };
+                (content_type.clone(), mime_type, 0.7, Some(content_type))
+            }
+        }
+        Signal::Text { html } => match extension_type {
+            DocumentType::Html if html => (DocumentType::Html, extension_mime, 1.0, Some(DocumentType::Html)),
+            DocumentType::Text | DocumentType::Markdown | DocumentType::Html => {
+                (extension_type.clone(), extension_mime, 0.9, Some(extension_type.clone()))
+            }
+            _ if html => (DocumentType::Html, "text/html".to_string(), 0.8, Some(DocumentType::Html)),
+            DocumentType::Unknown => (DocumentType::Text, "text/plain".to_string(), 0.7, Some(DocumentType::Text)),
+            _ => (DocumentType::Text, "text/plain".to_string(), 0.6, Some(DocumentType::Text)),
+        },
+        Signal::None => {
+            let confidence = match extension_type {
+                DocumentType::Unknown => 0.0,
+                _ if head.is_empty() => 0.3,
+                _ => 0.5,
+            };
+            (extension_type.clone(), extension_mime, confidence, None)
+        }
+    };
+    
+    if content_type.as_ref().is_some_and(|content_type| *content_type != extension_type) {
+        tracing::debug!("{} looks like {:?} despite its extension ({:?})", path.display(), document_type, extension_type);
+    }
+    TypeDetection { document_type, mime_type, confidence, extension_type, content_type }
+}
+
+fn sniff(head: &[u8]) -> Signal {
+    const SIGNATURES: &[(&[u8], DocumentType, &str)] = &[
+        (b"%PDF-", DocumentType::Pdf, "application/pdf"),
+        (b"\x89PNG\r\n\x1a\n", DocumentType::Image, "image/png"),
+        (b"\xFF\xD8\xFF", DocumentType::Image, "image/jpeg"),
+        (b"GIF87a", DocumentType::Image, "image/gif"),
+        (b"GIF89a", DocumentType::Image, "image/gif"),
+        (b"II*\x00", DocumentType::Image, "image/tiff"),
+        (b"MM\x00*", DocumentType::Image, "image/tiff"),
+        (b"ID3", DocumentType::Audio, "audio/mpeg"),
+        (b"fLaC", DocumentType::Audio, "audio/flac"),
+        (b"OggS", DocumentType::Audio, "audio/ogg"),
+        (b"\x1A\x45\xDF\xA3", DocumentType::Video, "video/webm"),
+    ];
+    
+    if head.is_empty() {
+        return Signal::None;
+    }
+    if let Some((_, document_type, mime_type)) = SIGNATURES.iter().find(|(magic, _, _)| head.starts_with(magic)) {
+        return Signal::Signature(document_type.clone(), mime_type);
+    }
+    
+    if head.len() >= 12 && head.starts_with(b"RIFF") {
+        match &head[8..12] {
+            b"WEBP" => return Signal::Signature(DocumentType::Image, "image/webp"),
+            b"WAVE" => return Signal::Signature(DocumentType::Audio, "audio/wav"),
+            b"AVI " => return Signal::Signature(DocumentType::Video, "video/x-msvideo"),
+            _ => {}
+        }
+    }
+    if head.len() >= 12 && &head[4..8] == b"ftyp" {
+        return match &head[8..11] {
+            b"M4A" => Signal::Signature(DocumentType::Audio, "audio/mp4"),
+            b"qt " => Signal::Signature(DocumentType::Video, "video/quicktime"),
+            _ => Signal::Signature(DocumentType::Video, "video/mp4"),
+        };
+    }
+    
+    if head.starts_with(b"PK\x03\x04") {
+        // OOXML part names usually appear in the first local file headers
+        let guess = [(&b"word/"[..], DocumentType::Word), (b"xl/", DocumentType::Excel), (b"ppt/", DocumentType::PowerPoint)]
+            .into_iter()
+            .find(|(marker, _)| contains(head, marker))
+            .map(|(_, document_type)| document_type);
+        return Signal::Container(vec![DocumentType::Word, DocumentType::Excel, DocumentType::PowerPoint], guess);
+    }
+    if head.starts_with(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1") {
+        return Signal::Container(vec![DocumentType::Word, DocumentType::Excel, DocumentType::PowerPoint], None);
+    }
+    
+    if looks_like_text(head) {
+        let text = String::from_utf8_lossy(head);
+        let start = text.trim_start_matches('\u{feff}').trim_start().to_lowercase();
+        let html = start.starts_with("<!doctype html") || start.starts_with("<html");
+        return Signal::Text { html };
+    }
+    Signal::None
+}
+
+/// UTF-8 without NUL bytes, allowing a character cut off at the end
+fn looks_like_text(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+fn default_extension(document_type: &DocumentType) -> &'static str {
+    match document_type {
+        DocumentType::Word => "docx",
+        DocumentType::Excel => "xlsx",
+        DocumentType::PowerPoint => "pptx",
+        _ => "bin",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_signature_overrides_extension() {
+        let detection = sniff_document_type(Path::new("invoice.txt"), b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n");
+        assert_eq!(detection.document_type, DocumentType::Pdf);
+        assert_eq!(detection.mime_type, "application/pdf");
+        assert_eq!(detection.confidence, 0.9);
+        assert_eq!(detection.extension_type, DocumentType::Text);
+        assert!(detection.is_mismatch());
+        
+        let detection = sniff_document_type(Path::new("invoice.pdf"), b"%PDF-1.4");
+        assert_eq!((&detection.document_type, detection.confidence), (&DocumentType::Pdf, 1.0));
+        assert!(!detection.is_mismatch());
+        
+        let detection = sniff_document_type(Path::new("photo.dat"), b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR");
+        assert_eq!((detection.document_type, detection.mime_type.as_str()), (DocumentType::Image, "image/png"));
+    }
+    
+    #[test]
+    fn test_containers_and_text() {
+        let docx_head = b"PK\x03\x04\x14\x00\x06\x00[Content_Types].xml...word/document.xml";
+        assert_eq!(sniff_document_type(Path::new("report.docx"), docx_head).confidence, 0.95);
+        let renamed = sniff_document_type(Path::new("report.bin"), docx_head);
+        assert_eq!((renamed.document_type, renamed.confidence), (DocumentType::Word, 0.7));
+        
+        let notes = sniff_document_type(Path::new("notes.md"), "# Notes — ünïcode".as_bytes());
+        assert_eq!((notes.document_type, notes.confidence), (DocumentType::Markdown, 0.9));
+        let page = sniff_document_type(Path::new("page"), b"\xEF\xBB\xBF  <!DOCTYPE html><html></html>");
+        assert_eq!((page.document_type, page.mime_type.as_str()), (DocumentType::Html, "text/html"));
+        
+        // A multi-byte character cut off by the sniff length is still text
+        let truncated = &"ééé".as_bytes()[..5];
+        assert_eq!(sniff_document_type(Path::new("a.txt"), truncated).content_type, Some(DocumentType::Text));
+        
+        let binary = sniff_document_type(Path::new("data.txt"), b"\x00\x01\x02\x03");
+        assert_eq!((binary.document_type, binary.content_type, binary.confidence), (DocumentType::Text, None, 0.5));
+        assert_eq!(sniff_document_type(Path::new("blob"), b"\x00\x01").confidence, 0.0);
+    }
+    
+    #[tokio::test]
+    async fn test_document_from_mislabelled_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("scan.txt");
+        std::fs::write(&path, b"%PDF-1.5\n\xFF\xFE binary body").unwrap();
+        
+        let document = utils::create_document_from_path(&path).await.unwrap();
+        assert_eq!(document.document_type, DocumentType::Pdf);
+        assert!(matches!(document.content, DocumentContent::Binary(_)));
+        assert_eq!(document.metadata["mime_type"], "application/pdf");
+        assert_eq!(document.metadata["type_detection"]["extension_type"], "Text");
+    }
+}
@@ -0,0 +1,364 @@
+//! Content Tiering
+//!
+//! Original document content is kept in two tiers: a hot one on local disk
+//! for content that was processed recently and may be re-processed, and a
+//! cold one in cheaper object storage. A tiering pass moves content that has
+//! been idle too long (or overflows the hot tier's capacity) to the cold tier,
+//! and reading cold content moves it back. References issued by the cache
+//! name the cache rather than a tier, so they stay valid across migrations.
+
+use super::*;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use swarm_core::{sha256_hex, CancellationToken, DocumentStore, Service};
+
+/// Storage tier holding a document's content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageTier {
+    Hot,
+    Cold,
+}
+
+/// When content moves between tiers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TieringPolicy {
+    /// Content not read for this long moves to the cold tier
+    pub hot_max_idle_secs: u64,
+    
+    /// Bytes kept in the hot tier; the least recently read content moves first
+    #[serde(default)]
+    pub hot_capacity_bytes: Option<u64>,
+    
+    /// Documents larger than this are stored cold right away
+    #[serde(default)]
+    pub hot_max_document_bytes: Option<u64>,
+    
+    /// Move cold content back to the hot tier when it is read
+    pub promote_on_access: bool,
+    
+    /// Interval between tiering passes
+    pub interval_secs: u64,
+    
+    /// Storage ID of the references the cache issues
+    pub storage_id: String,
+}
+
+impl Default for TieringPolicy {
+    fn default() -> Self {
+        Self {
+            hot_max_idle_secs: 86_400,
+            hot_capacity_bytes: None,
+            hot_max_document_bytes: None,
+            promote_on_access: true,
+            interval_secs: 300,
+            storage_id: "tiered".to_string(),
+        }
+    }
+}
+
+/// Outcome of a tiering pass
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TieringReport {
+    /// Documents moved to the cold tier
+    pub demoted: u64,
+    pub bytes_demoted: u64,
+    pub hot_bytes: u64,
+    pub cold_bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+struct TieredEntry {
+    size_bytes: u64,
+    checksum: String,
+    tier: StorageTier,
+    
+    /// The cold tier holds a copy, so demoting only removes the hot one
+    cold_copy: bool,
+    last_accessed: DateTime<Utc>,
+}
+
+/// Content cache spread over a hot and a cold document store
+#[derive(Clone)]
+pub struct TieredContentCache {
+    policy: TieringPolicy,
+    hot: Arc<dyn DocumentStore>,
+    cold: Arc<dyn DocumentStore>,
+    entries: Arc<RwLock<HashMap<Uuid, TieredEntry>>>,
+}
+
+/// Store path of a document's content, the same in both tiers
+fn content_path(document_id: Uuid) -> String {
+    format!("content/{}", document_id)
+}
+
+impl TieredContentCache {
+    pub fn new(policy: TieringPolicy, hot: Arc<dyn DocumentStore>, cold: Arc<dyn DocumentStore>) -> Self {
+        Self { policy, hot, cold, entries: Arc::default() }
+    }
+    
+    pub fn policy(&self) -> &TieringPolicy {
+        &self.policy
+    }
+    
+    fn store_for(&self, tier: StorageTier) -> &Arc<dyn DocumentStore> {
+        match tier {
+            StorageTier::Hot => &self.hot,
+            StorageTier::Cold => &self.cold,
+        }
+    }
+    
+    /// Cache a document's inline content, returning the reference to use instead
+    ///
+    /// References and streams are not cached and yield `None`.
+    pub async fn store(&self, document: &Document) -> Result<Option<DocumentContent>> {
+        let bytes = match &document.content {
+            DocumentContent::Text(text) => text.as_bytes().to_vec(),
+            DocumentContent::Binary(bytes) => bytes.clone(),
+            DocumentContent::Reference { .. } | DocumentContent::Stream { .. } => return Ok(None),
+        };
+        let size_bytes = bytes.len() as u64;
+        let tier = match self.policy.hot_max_document_bytes {
+            Some(max_bytes) if size_bytes > max_bytes => StorageTier::Cold,
+            _ => StorageTier::Hot,
+        };
+        let checksum = format!("sha256:{}", sha256_hex(&bytes));
+        let path = content_path(document.id);
+        self.store_for(tier).put(&path, bytes).await?;
+        
+        self.entries.write().unwrap().insert(document.id, TieredEntry {
+            size_bytes,
+            checksum: checksum.clone(),
+            tier,
+            cold_copy: tier == StorageTier::Cold,
+            last_accessed: Utc::now(),
+        });
+        Ok(Some(DocumentContent::Reference {
+            storage_id: self.policy.storage_id.clone(),
+            path,
+            access_token: None,
+            checksum: Some(checksum),
+            etag: None,
+        }))
+    }
+    
+    /// Tier currently holding a document's content
+    pub fn tier(&self, document_id: Uuid) -> Option<StorageTier> {
+        self.entries.read().unwrap().get(&document_id).map(|entry| entry.tier)
+    }
+    
+    /// Read a document's content, promoting it to the hot tier if configured
+    pub async fn read(&self, document_id: Uuid) -> Result<Option<Vec<u8>>> {
+        let Some(entry) = self.entries.read().unwrap().get(&document_id).cloned() else {
+            return Ok(None);
+        };
+        let path = content_path(document_id);
+        let bytes = match self.store_for(entry.tier).get(&path).await {
+            Ok(bytes) => bytes,
+            // Demoted while we were reading
+            Err(_) if entry.tier == StorageTier::Hot && entry.cold_copy => self.cold.get(&path).await?,
+            Err(e) => return Err(e),
+        };
+        let actual = format!("sha256:{}", sha256_hex(&bytes));
+        if actual != entry.checksum {
+            anyhow::bail!("cached content of {} changed: checksum {} (expected {})", document_id, actual, entry.checksum);
+        }
+        
+        let promote = entry.tier == StorageTier::Cold && self.policy.promote_on_access;
+        if promote {
+            self.hot.put(&path, bytes.clone()).await?;
+        }
+        if let Some(entry) = self.entries.write().unwrap().get_mut(&document_id) {
+            entry.last_accessed = Utc::now();
+            if promote {
+                entry.tier = StorageTier::Hot;
+            }
+        }
+        Ok(Some(bytes))
+    }
+    
+    /// Remove a document's content from both tiers
+    pub async fn remove(&self, document_id: Uuid) -> Result<bool> {
+        let Some(entry) = self.entries.write().unwrap().remove(&document_id) else {
+            return Ok(false);
+        };
+        let path = content_path(document_id);
+        if entry.tier == StorageTier::Hot {
+            self.hot.delete(&path).await?;
+        }
+        if entry.cold_copy {
+            self.cold.delete(&path).await?;
+        }
+        Ok(true)
+    }
+    
+    pub async fn apply_policy(&self) -> Result<TieringReport> {
+        self.apply_policy_at(Utc::now()).await
+    }
+    
+    /// Demote idle content, then the least recently read until the hot tier fits its capacity
+    pub async fn apply_policy_at(&self, now: DateTime<Utc>) -> Result<TieringReport> {
+        let mut hot: Vec<(Uuid, TieredEntry)> = self.entries.read().unwrap().iter()
+            .filter(|(_, entry)| entry.tier == StorageTier::Hot)
+            .map(|(id, entry)| (*id, entry.clone()))
+            .collect();
+        hot.sort_by_key(|(_, entry)| entry.last_accessed);
+        
+        let max_idle = chrono::Duration::seconds(self.policy.hot_max_idle_secs as i64);
+        let mut hot_bytes: u64 = hot.iter().map(|(_, entry)| entry.size_bytes).sum();
+        let mut report = TieringReport::default();
+        for (document_id, entry) in hot {
+            let idle = now - entry.last_accessed >= max_idle;
+            let over_capacity = self.policy.hot_capacity_bytes.is_some_and(|capacity| hot_bytes > capacity);
+            if !idle && !over_capacity {
+                break;
+            }
+            self.demote(document_id, &entry).await?;
+            hot_bytes -= entry.size_bytes;
+            report.demoted += 1;
+            report.bytes_demoted += entry.size_bytes;
+        }
+        
+        for entry in self.entries.read().unwrap().values() {
+            match entry.tier {
+                StorageTier::Hot => report.hot_bytes += entry.size_bytes,
+                StorageTier::Cold => report.cold_bytes += entry.size_bytes,
+            }
+        }
+        if report.demoted > 0 {
+            tracing::info!("Moved {} documents ({} bytes) to cold storage", report.demoted, report.bytes_demoted);
+        }
+        Ok(report)
+    }
+    
+    async fn demote(&self, document_id: Uuid, entry: &TieredEntry) -> Result<()> {
+        let path = content_path(document_id);
+        if !entry.cold_copy {
+            self.cold.put(&path, self.hot.get(&path).await?).await?;
+        }
+        {
+            let mut entries = self.entries.write().unwrap();
+            // Read (and so kept hot) since the pass started
+            match entries.get_mut(&document_id) {
+                Some(current) if current.last_accessed == entry.last_accessed => {
+                    current.tier = StorageTier::Cold;
+                    current.cold_copy = true;
+                }
+                Some(current) => {
+                    current.cold_copy = true;
+                    return Ok(());
+                }
+                None => return Ok(()),
+            }
+        }
+        self.hot.delete(&path).await
+    }
+    
+    /// Apply the policy every `interval_secs` until cancelled
+    pub async fn run(&self, cancel: CancellationToken) {
+        let interval = std::time::Duration::from_secs(self.policy.interval_secs);
+        while !swarm_core::cancellable_sleep(&cancel, interval).await {
+            if let Err(e) = self.apply_policy().await {
+                tracing::warn!("Content tiering pass failed: {}", e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ContentCache for TieredContentCache {
+    async fn get(&self, document_id: Uuid) -> Result<Option<Vec<u8>>> {
+        self.read(document_id).await
+    }
+}
+
+#[async_trait]
+impl ReferenceStore for TieredContentCache {
+    async fn fetch(&self, path: &str, _access_token: Option<&str>) -> Result<FetchedContent> {
+        let document_id = path.strip_prefix("content/")
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .ok_or_else(|| anyhow::anyhow!("not a tiered content path: {}", path))?;
+        let bytes = self.read(document_id).await?
+            .ok_or_else(|| anyhow::anyhow!("no cached content for {}", document_id))?;
+        Ok(FetchedContent { bytes, etag: None })
+    }
+}
+
+#[async_trait]
+impl Service for TieredContentCache {
+    fn name(&self) -> &str {
+        "content-tiering"
+    }
+    
+    async fn run(&mut self, cancel: CancellationToken) -> anyhow::Result<()> {
+        TieredContentCache::run(self, cancel).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swarm_core::MemoryDocumentStore;
+    
+    fn document(size: usize) -> Document {
+        Document {
+            id: Uuid::new_v4(),
+            filename: "scan.bin".to_string(),
+            document_type: DocumentType::Unknown,
+            content: DocumentContent::Binary(vec![size as u8; size]),
+            metadata: HashMap::new(),
+            created_at: Utc::now(),
+            size_bytes: size,
+        }
+    }
+    
+    fn cache(policy: TieringPolicy) -> (TieredContentCache, MemoryDocumentStore, MemoryDocumentStore) {
+        let hot = MemoryDocumentStore::new("local");
+        let cold = MemoryDocumentStore::new("s3");
+        (TieredContentCache::new(policy, Arc::new(hot.clone()), Arc::new(cold.clone())), hot, cold)
+    }
+    
+    #[tokio::test]
+    async fn test_idle_content_moves_cold_and_back_on_read() {
+        let (cache, hot, cold) = cache(TieringPolicy { hot_max_document_bytes: Some(100), ..Default::default() });
+        let small = document(10);
+        let large = document(200);
+        let reference = cache.store(&small).await.unwrap().unwrap();
+        cache.store(&large).await.unwrap();
+        assert_eq!((cache.tier(small.id), cache.tier(large.id)), (Some(StorageTier::Hot), Some(StorageTier::Cold)));
+        
+        let report = cache.apply_policy_at(Utc::now() + chrono::Duration::days(2)).await.unwrap();
+        assert_eq!(report, TieringReport { demoted: 1, bytes_demoted: 10, hot_bytes: 0, cold_bytes: 210 });
+        let path = content_path(small.id);
+        assert!(hot.get(&path).await.is_err() && cold.get(&path).await.is_ok());
+        
+        // The reference issued before the move still resolves, and the read promotes
+        let fetcher = ReferenceFetcher::new().with_store("tiered", Arc::new(cache.clone()));
+        assert_eq!(fetcher.fetch(&reference).await.unwrap(), vec![10; 10]);
+        assert_eq!(cache.tier(small.id), Some(StorageTier::Hot));
+        assert!(hot.get(&path).await.is_ok());
+        
+        assert!(cache.remove(small.id).await.unwrap());
+        assert!(cold.get(&path).await.is_err());
+        assert_eq!(ContentCache::get(&cache, small.id).await.unwrap(), None);
+    }
+    
+    #[tokio::test]
+    async fn test_hot_capacity_demotes_least_recently_read() {
+        let (cache, _, _) = cache(TieringPolicy { hot_capacity_bytes: Some(25), ..Default::default() });
+        let documents: Vec<Document> = (0..3).map(|_| document(10)).collect();
+        for document in &documents {
+            cache.store(document).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        }
+        cache.read(documents[0].id).await.unwrap();
+        
+        let report = cache.apply_policy().await.unwrap();
+        assert_eq!((report.demoted, report.hot_bytes), (1, 20));
+        let tiers: Vec<_> = documents.iter().map(|document| cache.tier(document.id).unwrap()).collect();
+        assert_eq!(tiers, vec![StorageTier::Hot, StorageTier::Cold, StorageTier::Hot]);
+    }
+}
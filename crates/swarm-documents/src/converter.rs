@@ -0,0 +1,275 @@
+//! External Document Converters
+//!
+//! Converts documents whose format the pipeline cannot read (e.g. `.odt`,
+//! `.rtf`) into a supported format by running an external command or calling
+//! an HTTP conversion service, configured per file extension.
+
+use super::*;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use crate::temp_files::UNTRACKED_CONVERTER_PREFIX;
+
+/// How an external converter is invoked
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConverterKind {
+    /// Run a program. `{input}` and `{output}` in `args` are replaced with file
+    /// paths; without `{output}` the program's stdout is the converted document.
+    Command { program: String, args: Vec<String> },
+    /// POST the file to a conversion service; the response body is the converted document
+    Http { url: String },
+}
+
+/// External converter configuration for one source extension
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ExternalConverterConfig {
+    /// Converter invocation
+    pub kind: ConverterKind,
+    
+    /// Extension of the converted output (e.g. "txt", "html", "pdf")
+    pub output_extension: String,
+    
+    /// Conversion timeout in milliseconds
+    pub timeout_ms: u64,
+}
+
+/// Output of a successful conversion
+#[derive(Debug, Clone)]
+pub struct Conversion {
+    /// Converted document bytes
+    pub bytes: Vec<u8>,
+    
+    /// Extension of the converted document
+    pub output_extension: String,
+    
+    /// Provenance record describing the conversion
+    pub provenance: serde_json::Value,
+}
+
+/// Converts unsupported documents using configured external converters
+#[derive(Debug, Clone, Default)]
+pub struct DocumentConverter {
+    converters: HashMap<String, ExternalConverterConfig>,
+    temp_files: Option<Arc<TempFileCollector>>,
+}
+
+impl DocumentConverter {
+    /// Create a converter from per-extension configuration
+    pub fn new(converters: HashMap<String, ExternalConverterConfig>) -> Self {
+        let converters = converters.into_iter()
+            .map(|(extension, config)| (extension.to_lowercase(), config))
+            .collect();
+        Self { converters, temp_files: None }
+    }
+    
+    /// Allocate converter output files through a temp file collector, so
+    /// output left behind by a crash or timeout is swept later
+    pub fn with_temp_files(mut self, temp_files: Arc<TempFileCollector>) -> Self {
+        self.temp_files = Some(temp_files);
+        self
+    }
+    
+    /// Check if a converter is configured for an extension
+    pub fn supports(&self, extension: &str) -> bool {
+        self.converters.contains_key(&extension.to_lowercase())
+    }
+    
+    /// Convert a file if a converter is configured for its extension
+    pub async fn convert(&self, path: &Path) -> DocumentResult<Option<Conversion>> {
+        let Some(extension) = path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_lowercase()) else {
+            return Ok(None);
+        };
+        let Some(config) = self.converters.get(&extension) else {
+            return Ok(None);
+        };
+        
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let (converter, bytes) = match &config.kind {
+            ConverterKind::Command { program, args } => {
+                let bytes = tokio::time::timeout(timeout, run_command(program, args, path, &config.output_extension, self.temp_files.as_deref()))
+                    .await
+                    .map_err(|_| conversion_error(&extension, format!("timed out after {}ms", config.timeout_ms)))?
+                    .map_err(|reason| conversion_error(&extension, reason))?;
+                (format!("command:{}", program), bytes)
+            }
+            ConverterKind::Http { url } => {
+                let bytes = post_to_service(url, path, timeout).await
+                    .map_err(|reason| conversion_error(&extension, reason))?;
+                (format!("http:{}", url), bytes)
+            }
+        };
+        
+        tracing::info!("Converted {} from {} to {} using {}",
+            path.display(), extension, config.output_extension, converter);
+        
+        Ok(Some(Conversion {
+            provenance: serde_json::json!({
+                "from_extension": extension,
+                "to_extension": config.output_extension,
+                "converter": converter,
+                "original_size": fs::metadata(path).await?.len(),
+                "converted_size": bytes.len(),
+                "converted_at": Utc::now().to_rfc3339(),
+            }),
+            output_extension: config.output_extension.clone(),
+            bytes,
+        }))
+    }
+}
+
+fn conversion_error(extension: &str, reason: impl ToString) -> DocumentError {
+    DocumentError::ConversionFailed {
+        extension: extension.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Run a converter command and collect its output
+async fn run_command(
+    program: &str,
+    args: &[String],
+    input: &Path,
+    output_extension: &str,
+    temp_files: Option<&TempFileCollector>,
+) -> Result<Vec<u8>, String> {
+    let output_path = match temp_files {
+        Some(temp_files) => temp_files.allocate(TempArtifactKind::Conversion, output_extension).await
+            .map_err(|e| format!("failed to allocate converter output: {}", e))?,
+        None => std::env::temp_dir().join(format!("{}{}.{}", UNTRACKED_CONVERTER_PREFIX, Uuid::new_v4(), output_extension)),
+    };
+    let remove_output = || async {
+        match temp_files {
+            Some(temp_files) => temp_files.release(&output_path).await.map_err(|e| e.to_string()),
+            None => fs::remove_file(&output_path).await.map_err(|e| e.to_string()),
+        }
+    };
+    let writes_file = args.iter().any(|arg| arg.contains("{output}"));
+    let args: Vec<String> = args.iter()
+        .map(|arg| arg
+            .replace("{input}", &input.to_string_lossy())
+            .replace("{output}", &output_path.to_string_lossy()))
+        .collect();
+    
+    let output = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("failed to run {}: {}", program, e))?;
+    
+    if !output.status.success() {
+        let _ = remove_output().await;
+        return Err(format!("{} exited with {}: {}", program, output.status,
+            String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    
+    if writes_file {
+        let bytes = fs::read(&output_path).await
+            .map_err(|e| format!("failed to read converter output: {}", e));
+        let _ = remove_output().await;
+        bytes
+    } else {
+        Ok(output.stdout)
+    }
+}
+
+/// Send a file to an HTTP conversion service
+async fn post_to_service(url: &str, input: &Path, timeout: Duration) -> Result<Vec<u8>, String> {
+    let body = fs::read(input).await.map_err(|e| e.to_string())?;
+    let filename = input.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    
+    let response = reqwest::Client::new()
+        .post(url)
+        .timeout(timeout)
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .header("X-Filename", filename)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("request to {} failed: {}", url, e))?;
+    
+    if !response.status().is_success() {
+        return Err(format!("{} returned {}", url, response.status()));
+    }
+    
+    response.bytes().await
+        .map(|bytes| bytes.to_vec())
+        .map_err(|e| format!("failed to read response from {}: {}", url, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    
+    fn command_converter(program: &str, args: &[&str]) -> DocumentConverter {
+        DocumentConverter::new(HashMap::from([(
+            "RTF".to_string(),
+            ExternalConverterConfig {
+                kind: ConverterKind::Command {
+                    program: program.to_string(),
+                    args: args.iter().map(|arg| arg.to_string()).collect(),
+                },
+                output_extension: "txt".to_string(),
+                timeout_ms: 5000,
+            },
+        )]))
+    }
+    
+    #[tokio::test]
+    async fn test_command_converter_stdout() {
+        let temp_dir = tempdir().unwrap();
+        let input = temp_dir.path().join("letter.rtf");
+        std::fs::write(&input, "plain letter").unwrap();
+        
+        let converter = command_converter("cat", &["{input}"]);
+        assert!(converter.supports("rtf"));
+        
+        let conversion = converter.convert(&input).await.unwrap().unwrap();
+        assert_eq!(conversion.bytes, b"plain letter");
+        assert_eq!(conversion.output_extension, "txt");
+        assert_eq!(conversion.provenance["from_extension"], "rtf");
+        assert_eq!(conversion.provenance["converter"], "command:cat");
+    }
+    
+    #[tokio::test]
+    async fn test_command_converter_output_file() {
+        let temp_dir = tempdir().unwrap();
+        let input = temp_dir.path().join("letter.rtf");
+        std::fs::write(&input, "copied letter").unwrap();
+        
+        let converter = command_converter("cp", &["{input}", "{output}"]);
+        let conversion = converter.convert(&input).await.unwrap().unwrap();
+        assert_eq!(conversion.bytes, b"copied letter");
+        
+        // Output allocated through a collector is released after reading
+        let temp_files = Arc::new(TempFileCollector::open(TempFileConfig {
+            directory: temp_dir.path().join("tmp"),
+            ..Default::default()
+        }).await.unwrap());
+        let converter = converter.with_temp_files(temp_files);
+        let conversion = converter.convert(&input).await.unwrap().unwrap();
+        assert_eq!(conversion.bytes, b"copied letter");
+        let remaining: Vec<_> = std::fs::read_dir(temp_dir.path().join("tmp")).unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(remaining, vec![std::ffi::OsString::from(TEMP_MANIFEST_FILENAME)]);
+    }
+    
+    #[tokio::test]
+    async fn test_converter_failure_and_unconfigured() {
+        let temp_dir = tempdir().unwrap();
+        let input = temp_dir.path().join("letter.rtf");
+        std::fs::write(&input, "letter").unwrap();
+        
+        let converter = command_converter("false", &[]);
+        assert!(matches!(converter.convert(&input).await, Err(DocumentError::ConversionFailed { .. })));
+        
+        let other = temp_dir.path().join("notes.odt");
+        std::fs::write(&other, "notes").unwrap();
+        assert!(converter.convert(&other).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,441 @@
+//! Credentials for Protected Documents
+//!
+//! Password-protected PDFs and Office documents are unlocked during extraction
+//! using passwords from a credential provider, selected by tenant and path
+//! pattern. Passwords are wrapped in `Secret` so they never end up in logs,
+//! debug output or result metadata; only credential IDs are recorded. With
+//! the `pdf` feature, `DocumentUnlocker` decrypts PDFs with `PdfDecryptor`
+//! out of the box; decryptors for other formats are registered explicitly.
+
+use super::*;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// A password that is redacted in debug and display output
+#[derive(Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+    
+    /// Access the secret value; never log the result
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl std::fmt::Display for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+/// A candidate password for a document
+#[derive(Debug, Clone)]
+pub struct Credential {
+    /// Identifier recorded in place of the password
+    pub id: String,
+    
+    /// The password
+    pub password: Secret,
+}
+
+/// Document a credential is requested for
+#[derive(Debug, Clone)]
+pub struct CredentialRequest {
+    /// Tenant the document belongs to, if any
+    pub tenant: Option<String>,
+    
+    /// Source path (or filename) of the document
+    pub path: String,
+    
+    pub document_type: DocumentType,
+}
+
+impl CredentialRequest {
+    /// Build a request from a document's `tenant` and `file_path` metadata
+    pub fn for_document(document: &Document) -> Self {
+        let metadata_str = |key: &str| document.metadata.get(key)
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string());
+        
+        Self {
+            tenant: metadata_str("tenant"),
+            path: metadata_str("file_path").unwrap_or_else(|| document.filename.clone()),
+            document_type: document.document_type.clone(),
+        }
+    }
+}
+
+/// Supplies candidate passwords for protected documents
+#[async_trait]
+pub trait CredentialProvider: Send + Sync {
+    /// Candidate credentials for a document, in the order they should be tried
+    async fn credentials_for(&self, request: &CredentialRequest) -> Result<Vec<Credential>>;
+}
+
+/// A vault entry: a password scoped to a tenant and/or path pattern
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct CredentialRule {
+    /// Credential identifier
+    pub id: String,
+    
+    /// Only applies to this tenant (any tenant when unset)
+    #[serde(default)]
+    pub tenant: Option<String>,
+    
+    /// Only applies to paths matching this pattern; `*` matches any characters
+    #[serde(default)]
+    pub path_pattern: Option<String>,
+    
+    /// The password
+    pub password: Secret,
+}
+
+impl CredentialRule {
+    fn matches(&self, request: &CredentialRequest) -> bool {
+        let tenant_matches = match &self.tenant {
+            Some(tenant) => request.tenant.as_deref() == Some(tenant.as_str()),
+            None => true,
+        };
+        let path_matches = match &self.path_pattern {
+            Some(pattern) => wildcard_match(pattern, &request.path),
+            None => true,
+        };
+        tenant_matches && path_matches
+    }
+}
+
+/// In-memory credential provider
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct CredentialVault {
+    #[serde(default)]
+    rules: Vec<CredentialRule>,
+}
+
+impl CredentialVault {
+    pub fn new(rules: Vec<CredentialRule>) -> Self {
+        Self { rules }
+    }
+    
+    /// Add a password scoped to a tenant and/or path pattern
+    pub fn with_password(
+        mut self,
+        id: impl Into<String>,
+        tenant: Option<&str>,
+        path_pattern: Option<&str>,
+        password: Secret,
+    ) -> Self {
+        self.rules.push(CredentialRule {
+            id: id.into(),
+            tenant: tenant.map(|tenant| tenant.to_string()),
+            path_pattern: path_pattern.map(|pattern| pattern.to_string()),
+            password,
+        });
+        self
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for CredentialVault {
+    async fn credentials_for(&self, request: &CredentialRequest) -> Result<Vec<Credential>> {
+        Ok(self.rules.iter()
+            .filter(|rule| rule.matches(request))
+            .map(|rule| Credential {
+                id: rule.id.clone(),
+                password: rule.password.clone(),
+            })
+            .collect())
+    }
+}
+
+/// Decrypts protected documents of one format
+pub trait DocumentDecryptor: Send + Sync {
+    /// Decrypt with a password; `Ok(None)` means the password was wrong
+    fn decrypt(&self, bytes: &[u8], password: &Secret) -> Result<Option<Vec<u8>>>;
+}
+
+/// Decrypts PDFs protected by the standard security handler
+///
+/// Supports the RC4 encryption `lopdf` implements (`/V` 1 and 2, revisions
+/// 2 and 3); documents using AES fail with an error rather than `Ok(None)`.
+#[cfg(feature = "pdf")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PdfDecryptor;
+
+#[cfg(feature = "pdf")]
+impl DocumentDecryptor for PdfDecryptor {
+    fn decrypt(&self, bytes: &[u8], password: &Secret) -> Result<Option<Vec<u8>>> {
+        let mut document = lopdf::Document::load_mem(bytes)?;
+        match document.decrypt(password.expose()) {
+            Ok(()) => {}
+            Err(lopdf::Error::Decryption(lopdf::encryption::DecryptionError::IncorrectPassword)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let mut decrypted = Vec::with_capacity(bytes.len());
+        document.save_to(&mut decrypted)?;
+        Ok(Some(decrypted))
+    }
+}
+
+/// Check whether document bytes are password protected
+///
+/// PDFs carry an `/Encrypt` dictionary; protected OOXML files are stored as
+/// OLE compound files with an `EncryptionInfo` stream instead of a ZIP.
+pub fn is_encrypted(document_type: &DocumentType, bytes: &[u8]) -> bool {
+    const OLE_MAGIC: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+    
+    match document_type {
+        DocumentType::Pdf => bytes.starts_with(b"%PDF") && contains(bytes, b"/Encrypt"),
+        DocumentType::Word | DocumentType::Excel | DocumentType::PowerPoint => {
+            let stream_name: Vec<u8> = "EncryptionInfo".encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect();
+            bytes.starts_with(&OLE_MAGIC) && contains(bytes, &stream_name)
+        }
+        _ => false,
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+/// Outcome of unlocking a protected document
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DecryptionRecord {
+    /// Number of credentials tried
+    pub attempts: usize,
+    
+    /// ID of the credential that unlocked the document
+    pub credential_id: Option<String>,
+}
+
+/// Counters for decryption outcomes
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DecryptionStats {
+    pub documents_unlocked: u64,
+    pub documents_failed: u64,
+    pub attempts: u64,
+}
+
+/// Unlocks protected documents using a credential provider and per-format decryptors
+pub struct DocumentUnlocker {
+    provider: Arc<dyn CredentialProvider>,
+    decryptors: HashMap<DocumentType, Arc<dyn DocumentDecryptor>>,
+    unlocked: AtomicU64,
+    failed: AtomicU64,
+    attempts: AtomicU64,
+}
+
+impl DocumentUnlocker {
+    /// Unlocker with the built-in decryptors registered
+    pub fn new(provider: Arc<dyn CredentialProvider>) -> Self {
+        let unlocker = Self {
+            provider,
+            decryptors: HashMap::new(),
+            unlocked: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            attempts: AtomicU64::new(0),
+        };
+        #[cfg(feature = "pdf")]
+        let unlocker = unlocker.with_decryptor(DocumentType::Pdf, Arc::new(PdfDecryptor));
+        unlocker
+    }
+    
+    /// Register the decryptor for a document type
+    pub fn with_decryptor(mut self, document_type: DocumentType, decryptor: Arc<dyn DocumentDecryptor>) -> Self {
+        self.decryptors.insert(document_type, decryptor);
+        self
+    }
+    
+    /// Decrypt a protected document, trying each candidate credential in turn
+    pub async fn unlock(&self, request: &CredentialRequest, bytes: &[u8]) -> DocumentResult<(Vec<u8>, DecryptionRecord)> {
+        let Some(decryptor) = self.decryptors.get(&request.document_type) else {
+            return Err(self.failure(request, 0, "no decryptor for this document type"));
+        };
+        
+        let credentials = self.provider.credentials_for(request).await
+            .map_err(|e| self.failure(request, 0, &format!("credential lookup failed: {}", e)))?;
+        
+        for (index, credential) in credentials.iter().enumerate() {
+            self.attempts.fetch_add(1, Ordering::Relaxed);
+            match decryptor.decrypt(bytes, &credential.password) {
+                Ok(Some(decrypted)) => {
+                    self.unlocked.fetch_add(1, Ordering::Relaxed);
+                    tracing::info!("Unlocked {} with credential {} after {} attempt(s)",
+                        request.path, credential.id, index + 1);
+                    return Ok((decrypted, DecryptionRecord {
+                        attempts: index + 1,
+                        credential_id: Some(credential.id.clone()),
+                    }));
+                }
+                Ok(None) => {
+                    tracing::debug!("Credential {} did not unlock {}", credential.id, request.path);
+                }
+                Err(e) => {
+                    tracing::warn!("Decrypting {} with credential {} failed: {}", request.path, credential.id, e);
+                }
+            }
+        }
+        
+        let reason = if credentials.is_empty() { "no matching credentials" } else { "no credential matched" };
+        Err(self.failure(request, credentials.len(), reason))
+    }
+    
+    /// Current decryption counters
+    pub fn stats(&self) -> DecryptionStats {
+        DecryptionStats {
+            documents_unlocked: self.unlocked.load(Ordering::Relaxed),
+            documents_failed: self.failed.load(Ordering::Relaxed),
+            attempts: self.attempts.load(Ordering::Relaxed),
+        }
+    }
+    
+    fn failure(&self, request: &CredentialRequest, attempts: usize, reason: &str) -> DocumentError {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!("Could not unlock {}: {} ({} attempt(s))", request.path, reason, attempts);
+        DocumentError::DecryptionFailed {
+            path: request.path.clone(),
+            attempts,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || !text[first.len()..].ends_with(last) {
+        return false;
+    }
+    
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    
+    /// Test "encryption": the password followed by the plaintext
+    pub(crate) struct PrefixDecryptor;
+    
+    impl DocumentDecryptor for PrefixDecryptor {
+        fn decrypt(&self, bytes: &[u8], password: &Secret) -> Result<Option<Vec<u8>>> {
+            let body = &bytes[bytes.len().min(b"%PDF /Encrypt ".len())..];
+            Ok(body.strip_prefix(password.expose().as_bytes()).map(|plain| plain.to_vec()))
+        }
+    }
+    
+    pub(crate) fn encrypted_pdf(password: &str, plain: &[u8]) -> Vec<u8> {
+        [format!("%PDF /Encrypt {}", password).as_bytes(), plain].concat()
+    }
+    
+    fn create_test_request(tenant: Option<&str>, path: &str) -> CredentialRequest {
+        CredentialRequest {
+            tenant: tenant.map(|tenant| tenant.to_string()),
+            path: path.to_string(),
+            document_type: DocumentType::Pdf,
+        }
+    }
+    
+    #[test]
+    fn test_secret_is_redacted() {
+        let credential = Credential { id: "hr".to_string(), password: Secret::new("hunter2") };
+        
+        assert!(!format!("{:?}", credential).contains("hunter2"));
+        assert_eq!(credential.password.to_string(), "[REDACTED]");
+        assert_eq!(credential.password.expose(), "hunter2");
+    }
+    
+    #[tokio::test]
+    async fn test_vault_scopes_by_tenant_and_path() {
+        let vault = CredentialVault::default()
+            .with_password("acme-hr", Some("acme"), Some("/data/acme/hr/*.pdf"), Secret::new("a"))
+            .with_password("acme-any", Some("acme"), None, Secret::new("b"))
+            .with_password("global", None, Some("*/shared/*"), Secret::new("c"));
+        
+        let ids = |request: CredentialRequest| {
+            let vault = vault.clone();
+            async move {
+                vault.credentials_for(&request).await.unwrap()
+                    .into_iter().map(|credential| credential.id).collect::<Vec<_>>()
+            }
+        };
+        
+        assert_eq!(ids(create_test_request(Some("acme"), "/data/acme/hr/salaries.pdf")).await, vec!["acme-hr", "acme-any"]);
+        assert_eq!(ids(create_test_request(Some("acme"), "/data/acme/hr/notes.docx")).await, vec!["acme-any"]);
+        assert_eq!(ids(create_test_request(Some("other"), "/data/other/shared/x.pdf")).await, vec!["global"]);
+        assert!(ids(create_test_request(None, "/data/private.pdf")).await.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_unlocker_tries_candidates_and_records_outcome() {
+        let vault = CredentialVault::default()
+            .with_password("wrong", None, None, Secret::new("nope"))
+            .with_password("right", None, Some("*.pdf"), Secret::new("s3cret"));
+        let unlocker = DocumentUnlocker::new(Arc::new(vault))
+            .with_decryptor(DocumentType::Pdf, Arc::new(PrefixDecryptor));
+        
+        let bytes = encrypted_pdf("s3cret", b"quarterly report");
+        assert!(is_encrypted(&DocumentType::Pdf, &bytes));
+        
+        let (plain, record) = unlocker.unlock(&create_test_request(None, "report.pdf"), &bytes).await.unwrap();
+        assert_eq!(plain, b"quarterly report");
+        assert_eq!(record, DecryptionRecord { attempts: 2, credential_id: Some("right".to_string()) });
+        
+        let error = unlocker.unlock(&create_test_request(None, "report.txt"), &bytes).await.unwrap_err();
+        assert!(matches!(error, DocumentError::DecryptionFailed { attempts: 1, .. }));
+        assert!(!error.to_string().contains("nope"));
+        
+        assert_eq!(unlocker.stats(), DecryptionStats { documents_unlocked: 1, documents_failed: 1, attempts: 3 });
+    }
+    
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn test_pdf_decryptor_checks_the_password() {
+        let bytes = include_bytes!("../../../test-data/protected_report.pdf");
+        assert!(is_encrypted(&DocumentType::Pdf, bytes));
+        assert_eq!(PdfDecryptor.decrypt(bytes, &Secret::new("wrong")).unwrap(), None);
+        
+        let decrypted = PdfDecryptor.decrypt(bytes, &Secret::new("s3cret")).unwrap().unwrap();
+        assert!(!is_encrypted(&DocumentType::Pdf, &decrypted));
+        let extraction = crate::pdf::extract_pdf(&decrypted).unwrap();
+        assert!(extraction.text.contains("Quarterly revenue grew by twelve percent"));
+        assert!(PdfDecryptor.decrypt(b"%PDF-1.4 truncated", &Secret::new("s3cret")).is_err());
+    }
+    
+    #[test]
+    fn test_encryption_detection() {
+        assert!(!is_encrypted(&DocumentType::Pdf, b"%PDF-1.7 plain"));
+        assert!(!is_encrypted(&DocumentType::Word, b"PK\x03\x04 docx zip"));
+        
+        let mut protected_docx = vec![0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+        protected_docx.extend("EncryptionInfo".encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+        assert!(is_encrypted(&DocumentType::Word, &protected_docx));
+        
+        assert!(wildcard_match("*.pdf", "a/b.pdf"));
+        assert!(wildcard_match("/in/*/x*", "/in/t/xy"));
+        assert!(!wildcard_match("/in/*.pdf", "/out/a.pdf"));
+    }
+}
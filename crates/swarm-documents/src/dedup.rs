@@ -0,0 +1,152 @@
+//! Duplicate Detection
+//!
+//! The same content is often ingested under different filenames: copies in
+//! several folders, attachments saved twice, re-exports. The reader stamps
+//! each document with a SHA-256 hash of its content, and the processor
+//! remembers the hashes it has seen. A later document with a known hash is
+//! a duplicate: it is either skipped, or linked to the first document with
+//! a light result instead of being processed again.
+
+use super::*;
+use std::sync::Mutex;
+
+/// Document metadata key holding the content hash (`sha256:<hex>`)
+pub const CONTENT_HASH_KEY: &str = "content_hash";
+
+/// Result metadata key holding the ID of the document a duplicate is linked to
+pub const DUPLICATE_OF_KEY: &str = "duplicate_of";
+
+/// What happens to a document whose content was seen before
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateAction {
+    /// Fail with `DocumentError::Duplicate`, without a result
+    #[default]
+    Skip,
+    /// Return a result without analysis, naming the original in `duplicate_of`
+    Link,
+}
+
+/// Duplicate detection configuration
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DedupConfig {
+    /// Detect documents with the content of an earlier document
+    #[serde(default)]
+    pub enabled: bool,
+    
+    #[serde(default)]
+    pub action: DuplicateAction,
+}
+
+/// Duplicate counts of a processor
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DedupStats {
+    /// Documents with content not seen before
+    pub unique_documents: u64,
+    pub duplicates_skipped: u64,
+    pub duplicates_linked: u64,
+}
+
+impl DedupStats {
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates_skipped + self.duplicates_linked
+    }
+}
+
+/// The first document seen with some content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginalDocument {
+    pub document_id: Uuid,
+    pub filename: String,
+}
+
+/// Content hash of a document, as stamped by the reader or computed from its content
+///
+/// Streamed content cannot be hashed up front and has no hash.
+pub fn document_content_hash(document: &Document) -> Option<String> {
+    if let Some(hash) = document.metadata.get(CONTENT_HASH_KEY).and_then(|hash| hash.as_str()) {
+        return Some(hash.to_string());
+    }
+    match document.content {
+        DocumentContent::Stream { .. } => None,
+        ref content => Some(format!("sha256:{}", swarm_core::content_hash(content))),
+    }
+}
+
+/// Remembers the content hashes of documents seen so far
+#[derive(Debug, Default)]
+pub struct DuplicateDetector {
+    seen: Mutex<HashMap<String, OriginalDocument>>,
+}
+
+impl DuplicateDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// The original of a document with already seen content, or `None` when
+    /// the content is new (and now remembered as this document's)
+    pub fn check(&self, document: &Document) -> Option<OriginalDocument> {
+        let hash = document_content_hash(document)?;
+        let mut seen = self.seen.lock().unwrap();
+        match seen.get(&hash) {
+            Some(original) if original.document_id != document.id => Some(original.clone()),
+            Some(_) => None,
+            None => {
+                seen.insert(hash, OriginalDocument { document_id: document.id, filename: document.filename.clone() });
+                None
+            }
+        }
+    }
+    
+    /// Number of distinct contents seen
+    pub fn len(&self) -> usize {
+        self.seen.lock().unwrap().len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Forget every content seen
+    pub fn clear(&self) {
+        self.seen.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn document(filename: &str, text: &str) -> Document {
+        DocumentBuilder::new(filename, DocumentContent::Text(text.to_string()))
+            .document_type(DocumentType::Text)
+            .build()
+    }
+    
+    #[test]
+    fn test_same_content_under_another_name_is_a_duplicate() {
+        let detector = DuplicateDetector::new();
+        let original = document("report.txt", "quarterly numbers");
+        assert_eq!(detector.check(&original), None);
+        
+        // Retries of the same document are not duplicates of themselves
+        assert_eq!(detector.check(&original), None);
+        assert_eq!(detector.check(&document("other.txt", "other numbers")), None);
+        
+        let copy = document("copy of report.txt", "quarterly numbers");
+        let found = detector.check(&copy).unwrap();
+        assert_eq!((found.document_id, found.filename.as_str()), (original.id, "report.txt"));
+        assert_eq!(detector.len(), 2);
+    }
+    
+    #[test]
+    fn test_stamped_hash_is_preferred() {
+        let mut stamped = document("a.txt", "one");
+        stamped.metadata.insert(CONTENT_HASH_KEY.to_string(), serde_json::json!("sha256:abc"));
+        assert_eq!(document_content_hash(&stamped).as_deref(), Some("sha256:abc"));
+        
+        let computed = document_content_hash(&document("b.txt", "one")).unwrap();
+        assert_eq!(computed, format!("sha256:{}", swarm_core::sha256_hex(b"one")));
+    }
+}
@@ -0,0 +1,128 @@
+//! Directory Traversal
+//!
+//! Depth-limited recursive listing of files, shared by the file discovery
+//! service and the document reader. Symlinked directories are followed, but
+//! each directory is visited at most once (by canonical path), so symlink
+//! loops terminate.
+
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Default for how many directory levels below a watched directory are scanned
+pub const DEFAULT_MAX_SCAN_DEPTH: usize = 16;
+
+pub(crate) fn default_max_scan_depth() -> usize {
+    DEFAULT_MAX_SCAN_DEPTH
+}
+
+/// Files found below a root directory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DirectoryListing {
+    pub files: Vec<PathBuf>,
+    pub directories_scanned: u64,
+    
+    /// Subdirectories that could not be read
+    pub errors: u64,
+}
+
+/// List the files in `root` and, up to `max_depth` levels down, its subdirectories
+///
+/// A `max_depth` of 0 lists only `root` itself. Directories for which
+/// `skip_dir` returns true are not entered. Errors reading `root` are
+/// returned; errors reading subdirectories are logged and counted.
+pub async fn walk_directory(root: &Path, max_depth: usize, skip_dir: impl Fn(&Path) -> bool) -> Result<DirectoryListing> {
+    let mut listing = DirectoryListing::default();
+    let mut visited = HashSet::new();
+    visited.insert(fs::canonicalize(root).await?);
+    
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((directory, depth)) = pending.pop() {
+        let mut entries = match fs::read_dir(&directory).await {
+            Ok(entries) => entries,
+            Err(e) if depth == 0 => return Err(e.into()),
+            Err(e) => {
+                tracing::warn!("Failed to read directory {}: {}", directory.display(), e);
+                listing.errors += 1;
+                continue;
+            }
+        };
+        listing.directories_scanned += 1;
+        
+        let mut subdirectories = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            // Follows symlinks; broken links are skipped
+            let Ok(metadata) = fs::metadata(&path).await else { continue };
+            
+            if metadata.is_file() {
+                listing.files.push(path);
+            } else if metadata.is_dir() && depth < max_depth && !skip_dir(&path) {
+                let Ok(canonical) = fs::canonicalize(&path).await else { continue };
+                if visited.insert(canonical) {
+                    subdirectories.push(path);
+                } else {
+                    tracing::debug!("Skipping already visited directory {}", path.display());
+                }
+            }
+        }
+        
+        // Visit subdirectories in the order they were listed
+        pending.extend(subdirectories.into_iter().rev().map(|path| (path, depth + 1)));
+    }
+    
+    Ok(listing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    
+    fn create_test_tree() -> tempfile::TempDir {
+        let root = tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("a/b/c")).unwrap();
+        std::fs::create_dir_all(root.path().join(".git")).unwrap();
+        for file in ["top.txt", "a/one.txt", "a/b/two.txt", "a/b/c/three.txt", ".git/config"] {
+            std::fs::write(root.path().join(file), "content").unwrap();
+        }
+        root
+    }
+    
+    fn filenames(listing: &DirectoryListing) -> Vec<String> {
+        let mut names: Vec<String> = listing.files.iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+    
+    #[tokio::test]
+    async fn test_walk_respects_max_depth() {
+        let root = create_test_tree();
+        let hidden = |path: &Path| path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        
+        let listing = walk_directory(root.path(), 0, hidden).await.unwrap();
+        assert_eq!(filenames(&listing), vec!["top.txt"]);
+        
+        let listing = walk_directory(root.path(), 2, hidden).await.unwrap();
+        assert_eq!(filenames(&listing), vec!["one.txt", "top.txt", "two.txt"]);
+        assert_eq!(listing.directories_scanned, 3);
+        
+        let listing = walk_directory(root.path(), DEFAULT_MAX_SCAN_DEPTH, |_| false).await.unwrap();
+        assert_eq!(filenames(&listing), vec!["config", "one.txt", "three.txt", "top.txt", "two.txt"]);
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_walk_terminates_on_symlink_loops() {
+        let root = create_test_tree();
+        std::os::unix::fs::symlink(root.path(), root.path().join("a/b/loop")).unwrap();
+        std::os::unix::fs::symlink(root.path().join("a/b"), root.path().join("a/b/c/back")).unwrap();
+        
+        let listing = walk_directory(root.path(), 100, |_| false).await.unwrap();
+        assert_eq!(filenames(&listing), vec!["config", "one.txt", "three.txt", "top.txt", "two.txt"]);
+        assert_eq!(listing.directories_scanned, 5);
+    }
+}
@@ -4,41 +4,433 @@
 //! capabilities for the Aprio Swarm system.
 
 use super::*;
-use swarm_core::prelude::*;
-use swarm_core::{DocumentProcessingOptions, TextAnalysisOptions};
+use swarm_core::{AnalysisBudgets, DocumentProcessingOptions, DocumentRejection};
 use anyhow::Result;
 use async_trait::async_trait;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use swarm_core::{AdaptiveConcurrencyController, ConcurrencyPermit, ConcurrencyStats, Clock, Determinism};
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::task::JoinSet;
 use uuid::Uuid;
-use chrono::Utc;
 
 /// Concrete implementation of DocumentProcessor trait
+///
+/// Clones share statistics, so a clone can be handed to a spawned task.
+#[derive(Clone)]
 pub struct SwarmDocumentProcessor {
     config: DocumentProcessingConfig,
-    stats: DocumentProcessingStats,
+    stats: Arc<RwLock<DocumentProcessingStats>>,
     supported_types: Vec<DocumentType>,
+    unlocker: Option<Arc<DocumentUnlocker>>,
+    reference_fetcher: Option<Arc<ReferenceFetcher>>,
+    concurrency: Option<Arc<AdaptiveConcurrencyController>>,
+    embedding_provider: Arc<dyn EmbeddingProvider>,
+    ocr_engine: Option<Arc<dyn OcrEngine>>,
+    keyword_extractor: KeywordExtractor,
+    term_normalizer: TermNormalizer,
+    quality_scorer: QualityScorer,
+    html_sanitizer: HtmlSanitizer,
+    link_crawler: LinkCrawler,
+    corpus: Arc<std::sync::RwLock<CorpusStatistics>>,
+    metrics: Option<Arc<dyn MetricsCollector>>,
+    determinism: Determinism,
+    duplicates: Arc<DuplicateDetector>,
 }
 
 impl SwarmDocumentProcessor {
     /// Create a new document processor
     pub fn new(config: DocumentProcessingConfig) -> Self {
         let supported_types = config.supported_types.clone();
+        let concurrency = config.adaptive_concurrency.clone().map(|adaptive| {
+            Arc::new(AdaptiveConcurrencyController::new(adaptive, config.max_concurrent_documents))
+        });
+        let embedding_provider = Arc::new(HashEmbeddingProvider::new(config.embeddings.clone()));
+        let keyword_extractor = KeywordExtractor::new(config.keywords.clone());
+        let term_normalizer = TermNormalizer::new(config.normalization.clone());
+        let quality_scorer = QualityScorer::new(config.quality.clone());
+        let html_sanitizer = HtmlSanitizer::new(config.sanitization.clone());
+        let link_crawler = LinkCrawler::new(config.crawling.clone());
+        let determinism = Determinism::from_config(&config.determinism);
         Self {
             config,
-            stats: DocumentProcessingStats::default(),
+            stats: Arc::new(RwLock::new(DocumentProcessingStats::default())),
             supported_types,
+            unlocker: None,
+            reference_fetcher: None,
+            concurrency,
+            embedding_provider,
+            ocr_engine: None,
+            keyword_extractor,
+            term_normalizer,
+            quality_scorer,
+            html_sanitizer,
+            link_crawler,
+            corpus: Arc::new(std::sync::RwLock::new(CorpusStatistics::default())),
+            metrics: None,
+            determinism,
+            duplicates: Arc::new(DuplicateDetector::new()),
         }
     }
     
+    /// Start keyword TF-IDF weighting from previously collected corpus statistics
+    pub fn with_corpus_statistics(self, corpus: CorpusStatistics) -> Self {
+        *self.corpus.write().unwrap() = corpus;
+        self
+    }
+    
+    /// Corpus statistics accumulated so far, e.g. to persist across restarts
+    pub fn corpus_statistics(&self) -> CorpusStatistics {
+        self.corpus.read().unwrap().clone()
+    }
+    
+    /// Count processing warnings in a metrics collector, tagged by stage and code
+    pub fn with_metrics(mut self, metrics: Arc<dyn MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    /// Take `processed_at` timestamps from a custom clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.determinism = self.determinism.with_clock(clock);
+        self
+    }
+    
+    /// Detect duplicates among the documents of several processors
+    pub fn with_duplicate_detector(mut self, detector: Arc<DuplicateDetector>) -> Self {
+        self.duplicates = detector;
+        self
+    }
+    
+    /// Content hashes seen so far, with `dedup` enabled
+    pub fn duplicate_detector(&self) -> &DuplicateDetector {
+        &self.duplicates
+    }
+    
+    /// Generate embeddings with another model (ONNX runtime, remote API, ...)
+    pub fn with_embedding_provider(mut self, provider: Arc<dyn EmbeddingProvider>) -> Self {
+        self.embedding_provider = provider;
+        self
+    }
+    
+    /// Translate keywords and classification labels for `TermNormalization::Translate`
+    pub fn with_translator(mut self, translator: Arc<dyn Translator>) -> Self {
+        self.term_normalizer = self.term_normalizer.with_translator(translator);
+        self
+    }
+    
+    /// Read image documents with an OCR engine; images become processable
+    pub fn with_ocr_engine(mut self, engine: Arc<dyn OcrEngine>) -> Self {
+        if !self.supported_types.contains(&DocumentType::Image) {
+            self.supported_types.push(DocumentType::Image);
+            self.config.supported_types.push(DocumentType::Image);
+        }
+        self.ocr_engine = Some(engine);
+        self
+    }
+    
+    /// Options for a document: those of its processing profile, else the configured ones
+    fn options_for(&self, document: &Document) -> Cow<'_, DocumentProcessingOptions> {
+        match swarm_core::effective_options(document) {
+            Some(options) => Cow::Owned(options),
+            None => Cow::Borrowed(&self.config.processing_options),
+        }
+    }
+    
+    /// Embed the document's text, if embeddings are enabled and there is text
+    async fn add_embeddings(&self, document: &Document, result: &mut DocumentProcessingResult) {
+        if !self.options_for(document).generate_embeddings {
+            return;
+        }
+        let text = match (&result.extracted_text, &document.content) {
+            (Some(text), _) | (None, DocumentContent::Text(text)) => text.clone(),
+            _ => return,
+        };
+        
+        match self.embedding_provider.embed(&[text]).await {
+            Ok(mut vectors) => {
+                result.embeddings = vectors.pop();
+                result.metadata.insert("embedding_model".to_string(), serde_json::json!(self.embedding_provider.model()));
+            }
+            Err(e) => {
+                result.metadata.insert("embedding_error".to_string(), serde_json::json!(e.to_string()));
+                result.warn("embeddings", "embedding_failed", e.to_string());
+            }
+        }
+    }
+    
+    /// Record keywords and classification normalized as configured
+    async fn normalize_terms(&self, document: &Document, result: &mut DocumentProcessingResult) {
+        if self.term_normalizer.config().keywords == TermNormalization::None {
+            return;
+        }
+        let language = result.language.clone().or_else(|| match (&result.extracted_text, &document.content) {
+            (Some(text), _) | (None, DocumentContent::Text(text)) => self.detect_language(text),
+            _ => None,
+        });
+        
+        match self.term_normalizer.normalize(&result.keywords, result.classification.as_deref(), language.as_deref()).await {
+            Ok(Some(normalized)) => normalized.insert_into(&mut result.metadata),
+            Ok(None) => {}
+            Err(e) => result.warn("normalization", "normalization_failed", e.to_string()),
+        }
+    }
+    
+    fn budgets(&self) -> &AnalysisBudgets {
+        &self.config.text_analysis_options.budgets
+    }
+    
+    /// Processing time reported in results; always zero in deterministic mode
+    fn elapsed_ms(&self, start_time: Instant) -> u64 {
+        if self.determinism.is_enabled() { 0 } else { elapsed_ms(start_time) }
+    }
+    
+    /// Record the outbound links of a crawled HTML or Markdown document as URL candidates
+    fn add_crawl_candidates(&self, document: &Document, result: &mut DocumentProcessingResult) {
+        let DocumentContent::Text(text) = &document.content else { return };
+        if !self.link_crawler.config().enabled {
+            return;
+        }
+        let candidates = match document.document_type {
+            DocumentType::Html => {
+                let links = crate::html::extract_html(text).links;
+                self.link_crawler.candidates(document, links.iter().map(|link| (link.href.as_str(), link.text.as_str())))
+            }
+            DocumentType::Markdown => {
+                let links = crate::markdown::extract_markdown(text).links;
+                self.link_crawler.candidates(document, links.iter().map(|link| (link.url.as_str(), link.text.as_str())))
+            }
+            _ => return,
+        };
+        if !candidates.is_empty() {
+            result.metadata.insert(CRAWL_CANDIDATES_KEY.to_string(), serde_json::json!(candidates));
+        }
+    }
+    
+    /// Note in the result which analysis stages saw only part of the text
+    fn note_analysis_budgets(&self, document: &Document, result: &mut DocumentProcessingResult) {
+        let text = match (&result.extracted_text, &document.content) {
+            (Some(text), _) | (None, DocumentContent::Text(text)) => text,
+            _ => return,
+        };
+        let options = self.options_for(document);
+        let stages = [
+            ("language", &self.budgets().language, options.detect_language),
+            ("keywords", &self.budgets().keywords, options.extract_keywords),
+            ("sentiment", &self.budgets().sentiment, options.extract_text),
+        ];
+        
+        let mut limited = serde_json::Map::new();
+        let mut warnings = Vec::new();
+        for (stage, budget, enabled) in stages {
+            let budgeted = budget.apply(text);
+            if enabled && budgeted.truncated {
+                warnings.push(ProcessingWarning::new("analysis", "budget_exceeded", format!("{} analyzed {} of {} bytes",
+                    stage, budgeted.analyzed_bytes(), budgeted.total_bytes)));
+                limited.insert(stage.to_string(), serde_json::json!({
+                    "total_bytes": budgeted.total_bytes,
+                    "analyzed_bytes": budgeted.analyzed_bytes(),
+                    "sampled": budgeted.sampled,
+                }));
+            }
+        }
+        if !limited.is_empty() {
+            tracing::debug!("Analysis of {} was limited by its work budget", document.filename);
+            result.metadata.insert("analysis_budget".to_string(), serde_json::Value::Object(limited));
+        }
+        result.warnings.extend(warnings);
+    }
+    
+    /// Score the extracted text, flagging the result if it looks like garbage
+    fn assess_quality(&self, document: &Document, result: &mut DocumentProcessingResult) {
+        if let Some(quality) = self.quality_scorer.assess(result) {
+            if quality.score < self.quality_scorer.config().min_score {
+                tracing::warn!("Low extraction quality for {} ({:.2})", document.filename, quality.score);
+                result.warn("quality", "low_quality", format!("extraction quality {:.2} below {:.2}",
+                    quality.score, self.quality_scorer.config().min_score));
+            }
+        }
+    }
+    
+    /// Fetch (and verify) referenced content before processing
+    pub fn with_reference_fetcher(mut self, fetcher: Arc<ReferenceFetcher>) -> Self {
+        self.reference_fetcher = Some(fetcher);
+        self
+    }
+    
+    /// Replace verified referenced content with the fetched bytes
+    async fn resolve_reference<'a>(&self, document: &'a Document) -> DocumentResult<Cow<'a, Document>> {
+        let (Some(fetcher), DocumentContent::Reference { .. }) = (&self.reference_fetcher, &document.content) else {
+            return Ok(Cow::Borrowed(document));
+        };
+        
+        let bytes = fetcher.fetch(&document.content).await?;
+        let content = match document.document_type {
+            DocumentType::Text | DocumentType::Html | DocumentType::Markdown => {
+                DocumentContent::Text(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            _ => DocumentContent::Binary(bytes),
+        };
+        Ok(Cow::Owned(Document {
+            content,
+            ..document.clone()
+        }))
+    }
+    
+    /// Unlock password-protected documents before extraction
+    pub fn with_unlocker(mut self, unlocker: Arc<DocumentUnlocker>) -> Self {
+        self.unlocker = Some(unlocker);
+        self
+    }
+    
+    /// Decrypt the document if it is password protected
+    async fn unlock_document<'a>(&self, document: &'a Document) -> DocumentResult<(Cow<'a, Document>, Option<DecryptionRecord>)> {
+        let DocumentContent::Binary(bytes) = &document.content else {
+            return Ok((Cow::Borrowed(document), None));
+        };
+        if !is_encrypted(&document.document_type, bytes) {
+            return Ok((Cow::Borrowed(document), None));
+        }
+        
+        let request = CredentialRequest::for_document(document);
+        let Some(unlocker) = &self.unlocker else {
+            return Err(DocumentError::DecryptionFailed {
+                path: request.path,
+                attempts: 0,
+                reason: "document is password protected and no credential provider is configured".to_string(),
+            });
+        };
+        
+        let (decrypted, record) = unlocker.unlock(&request, bytes).await?;
+        let unlocked = Document {
+            size_bytes: decrypted.len(),
+            content: DocumentContent::Binary(decrypted),
+            ..document.clone()
+        };
+        Ok((Cow::Owned(unlocked), Some(record)))
+    }
+    
     /// Get current statistics
-    pub fn get_stats(&self) -> &DocumentProcessingStats {
-        &self.stats
+    pub async fn get_stats(&self) -> DocumentProcessingStats {
+        let mut stats = self.stats.read().await.clone();
+        stats.concurrency = match &self.concurrency {
+            Some(controller) => controller.stats(),
+            None => ConcurrencyStats { limit: self.config.max_concurrent_documents, ..Default::default() },
+        };
+        stats
     }
     
     /// Reset statistics
-    pub fn reset_stats(&mut self) {
-        self.stats = DocumentProcessingStats::default();
+    pub async fn reset_stats(&self) {
+        *self.stats.write().await = DocumentProcessingStats::default();
+    }
+    
+    /// Outcome for a document with the content of an earlier document, if it is one
+    async fn check_duplicate(&self, document: &Document) -> Option<Result<DocumentProcessingResult>> {
+        if !self.config.dedup.enabled {
+            return None;
+        }
+        let Some(original) = self.duplicates.check(document) else {
+            self.stats.write().await.dedup.unique_documents += 1;
+            return None;
+        };
+        
+        tracing::info!("{} duplicates {} ({})", document.filename, original.filename, original.document_id);
+        match self.config.dedup.action {
+            DuplicateAction::Skip => {
+                self.stats.write().await.dedup.duplicates_skipped += 1;
+                Some(Err(DocumentError::Duplicate {
+                    filename: document.filename.clone(),
+                    original_id: original.document_id,
+                    original_filename: original.filename,
+                }.into()))
+            }
+            DuplicateAction::Link => {
+                self.stats.write().await.dedup.duplicates_linked += 1;
+                let metadata = HashMap::from([
+                    (DUPLICATE_OF_KEY.to_string(), serde_json::Value::String(original.document_id.to_string())),
+                ]);
+                Some(Ok(DocumentProcessingResult {
+                    document_id: document.id,
+                    extracted_text: None,
+                    metadata,
+                    language: None,
+                    keywords: Vec::new(),
+                    sentiment: None,
+                    classification: None,
+                    embeddings: None,
+                    processing_time_ms: 0,
+                    processed_at: self.determinism.now(),
+                    warnings: Vec::new(),
+                }))
+            }
+        }
+    }
+    
+    /// Record the outcome of processing one document, reporting its warnings
+    async fn record(&self, document: &Document, start_time: Instant, result: &Result<DocumentProcessingResult>) {
+        let mut stats = self.stats.write().await;
+        stats.record(&document.document_type, elapsed_ms(start_time), result.is_ok());
+        let Ok(result) = result else { return };
+        
+        stats.record_warnings(&result.warnings);
+        for warning in &result.warnings {
+            tracing::warn!("{} [{}/{}]: {}", document.filename, warning.stage, warning.code, warning.message);
+            if let Some(metrics) = &self.metrics {
+                metrics.increment_counter("document.warnings", &[("stage", &warning.stage), ("code", &warning.code)]);
+            }
+        }
+    }
+    
+    /// Process a batch of documents, returning results in input order
+    ///
+    /// With `enable_parallel_processing`, documents are processed on spawned
+    /// tasks, at most `max_concurrent_documents` at a time (or the adaptive
+    /// limit, with `adaptive_concurrency`); otherwise one by one. In
+    /// deterministic mode documents are always processed one by one, so the
+    /// corpus statistics weighting keywords grow in input order.
+    pub async fn process_documents(&self, documents: &[Document]) -> Vec<Result<DocumentProcessingResult>> {
+        let fixed_single = self.concurrency.is_none() && self.config.max_concurrent_documents <= 1;
+        if !self.config.enable_parallel_processing || fixed_single || self.determinism.is_enabled() {
+            let mut results = Vec::with_capacity(documents.len());
+            for document in documents {
+                results.push(self.process_document(document).await);
+            }
+            return results;
+        }
+        
+        let semaphore = Arc::new(Semaphore::new(self.config.max_concurrent_documents));
+        let mut tasks = JoinSet::new();
+        for (index, document) in documents.iter().enumerate() {
+            // Waiting for a permit before spawning also bounds the number of tasks
+            let permit = match &self.concurrency {
+                Some(controller) => BatchPermit::Adaptive(controller.acquire().await),
+                None => BatchPermit::Fixed(semaphore.clone().acquire_owned().await.expect("semaphore is never closed")),
+            };
+            let processor = self.clone();
+            let document = document.clone();
+            tasks.spawn(async move {
+                let result = processor.process_document(&document).await;
+                permit.complete();
+                (index, result)
+            });
+        }
+        
+        let mut results: Vec<Option<Result<DocumentProcessingResult>>> = documents.iter().map(|_| None).collect();
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok((index, result)) => results[index] = Some(result),
+                Err(e) => tracing::error!("Document processing task failed: {}", e),
+            }
+        }
+        results.into_iter()
+            .map(|result| result.unwrap_or_else(|| Err(DocumentError::ProcessingFailed {
+                reason: "processing task panicked or was cancelled".to_string(),
+            }.into())))
+            .collect()
     }
     
     /// Process text content for analysis
@@ -55,7 +447,8 @@ impl SwarmDocumentProcessor {
             classification: None,
             embeddings: None,
             processing_time_ms: 0,
-            processed_at: Utc::now(),
+            processed_at: self.determinism.now(),
+            warnings: Vec::new(),
         };
         
         // Language detection
@@ -73,12 +466,18 @@ impl SwarmDocumentProcessor {
             result.sentiment = self.analyze_sentiment(content);
         }
         
-        result.processing_time_ms = start_time.elapsed().as_millis() as u64;
+        result.processing_time_ms = self.elapsed_ms(start_time);
         result
     }
     
     /// Detect language from text content
     fn detect_language(&self, text: &str) -> Option<String> {
+        let budgeted = self.budgets().language.apply(text);
+        let text = budgeted.text.as_ref();
+        if let Some(language) = detect_cjk_language(text) {
+            return Some(language.to_string());
+        }
+        
         // Simplified language detection based on common words
         let text_lower = text.to_lowercase();
         
@@ -104,35 +503,26 @@ impl SwarmDocumentProcessor {
     }
     
     /// Extract keywords from text
+    ///
+    /// Each document also updates the corpus statistics that later documents
+    /// are weighted against.
     fn extract_keywords(&self, text: &str) -> Vec<String> {
-        // Simple keyword extraction based on word frequency
-        let words: Vec<&str> = text
-            .split_whitespace()
-            .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
-            .filter(|word| word.len() > 3)
-            .collect();
-        
-        let mut word_count: HashMap<String, usize> = HashMap::new();
-        for word in words {
-            if word.len() > 3 {
-                *word_count.entry(word.to_lowercase()).or_insert(0) += 1;
-            }
+        let budgeted = self.budgets().keywords.apply(text);
+        let text = budgeted.text.as_ref();
+        let language = self.detect_language(text);
+        let terms = self.keyword_extractor.terms(text, language.as_deref());
+        if !self.keyword_extractor.config().tf_idf {
+            return self.keyword_extractor.rank(&terms, &CorpusStatistics::default());
         }
         
-        // Get top 10 most frequent words
-        let mut sorted_words: Vec<(String, usize)> = word_count.into_iter().collect();
-        sorted_words.sort_by(|a, b| b.1.cmp(&a.1));
-        
-        sorted_words
-            .into_iter()
-            .take(10)
-            .map(|(word, _)| word)
-            .collect()
+        let mut corpus = self.corpus.write().unwrap();
+        corpus.add_document(terms.iter().map(|term| &term.stem));
+        self.keyword_extractor.rank(&terms, &corpus)
     }
     
     /// Analyze sentiment (simplified)
     fn analyze_sentiment(&self, text: &str) -> Option<f32> {
-        let text_lower = text.to_lowercase();
+        let text_lower = self.budgets().sentiment.apply(text).text.to_lowercase();
         
         // Positive words
         let positive_words = ["good", "great", "excellent", "amazing", "wonderful", "fantastic", "love", "like"];
@@ -155,52 +545,46 @@ impl SwarmDocumentProcessor {
         Some(sentiment.clamp(-1.0, 1.0))
     }
     
-    /// Simulate PDF processing
-    async fn process_pdf(&self, content: &DocumentContent, options: &DocumentProcessingOptions) -> DocumentProcessingResult {
+    /// Process a PDF document
+    ///
+    /// Binary PDFs are parsed when the `pdf` feature is enabled; other content is simulated.
+    async fn process_pdf(&self, content: &DocumentContent, options: &DocumentProcessingOptions) -> DocumentResult<DocumentProcessingResult> {
         let start_time = Instant::now();
+        let mut metadata = HashMap::new();
+        #[allow(unused_mut)]
+        let mut warnings = Vec::new();
         
-        // Simulate PDF text extraction
+        #[cfg(feature = "pdf")]
         let extracted_text = match content {
-            DocumentContent::Text(text) => {
-                if options.extract_text {
-                    Some(format!("[PDF EXTRACTED] {}", text))
-                } else {
-                    None
-                }
-            }
-            DocumentContent::Binary(_) => {
-                if options.extract_text {
-                    Some("[PDF EXTRACTED] Binary PDF content processed".to_string())
-                } else {
-                    None
-                }
-            }
-            DocumentContent::Reference { .. } => {
-                if options.extract_text {
-                    Some("[PDF EXTRACTED] Referenced PDF content processed".to_string())
-                } else {
-                    None
+            DocumentContent::Binary(bytes) => {
+                let extraction = crate::pdf::extract_pdf(bytes)?;
+                warnings = extraction.warnings;
+                metadata.insert("page_count".to_string(), serde_json::Value::from(extraction.page_count));
+                metadata.insert("pdf_version".to_string(), serde_json::Value::String(extraction.pdf_version));
+                if !extraction.info.is_empty() {
+                    metadata.insert("pdf_info".to_string(), serde_json::Value::Object(extraction.info.into_iter().collect()));
                 }
+                options.extract_text.then_some(extraction.text)
             }
+            _ => self.simulate_pdf_text(content, options, &mut metadata),
         };
+        #[cfg(not(feature = "pdf"))]
+        let extracted_text = self.simulate_pdf_text(content, options, &mut metadata);
         
         let mut result = DocumentProcessingResult {
             document_id: Uuid::new_v4(),
             extracted_text,
-            metadata: HashMap::new(),
+            metadata,
             language: None,
             keywords: Vec::new(),
             sentiment: None,
             classification: Some("PDF Document".to_string()),
             embeddings: None,
             processing_time_ms: 0,
-            processed_at: Utc::now(),
+            processed_at: self.determinism.now(),
+            warnings,
         };
         
-        // Add PDF-specific metadata
-        result.metadata.insert("page_count".to_string(), serde_json::Value::Number(serde_json::Number::from(1)));
-        result.metadata.insert("pdf_version".to_string(), serde_json::Value::String("1.4".to_string()));
-        
         // Process extracted text if available
         if let Some(ref text) = result.extracted_text {
             if options.detect_language {
@@ -214,8 +598,38 @@ impl SwarmDocumentProcessor {
             }
         }
         
-        result.processing_time_ms = start_time.elapsed().as_millis() as u64;
-        result
+        result.processing_time_ms = self.elapsed_ms(start_time);
+        Ok(result)
+    }
+    
+    /// Simulate PDF text extraction for content that is not parsed
+    fn simulate_pdf_text(&self, content: &DocumentContent, options: &DocumentProcessingOptions, metadata: &mut HashMap<String, serde_json::Value>) -> Option<String> {
+        metadata.insert("page_count".to_string(), serde_json::Value::Number(serde_json::Number::from(1)));
+        metadata.insert("pdf_version".to_string(), serde_json::Value::String("1.4".to_string()));
+        
+        match content {
+            DocumentContent::Text(text) => {
+                if options.extract_text {
+                    Some(format!("[PDF EXTRACTED] {}", text))
+                } else {
+                    None
+                }
+            }
+            DocumentContent::Binary(_) => {
+                if options.extract_text {
+                    Some("[PDF EXTRACTED] Binary PDF content processed".to_string())
+                } else {
+                    None
+                }
+            }
+            DocumentContent::Reference { .. } | DocumentContent::Stream { .. } => {
+                if options.extract_text {
+                    Some("[PDF EXTRACTED] Referenced PDF content processed".to_string())
+                } else {
+                    None
+                }
+            }
+        }
     }
     
     /// Simulate Word document processing
@@ -238,7 +652,7 @@ impl SwarmDocumentProcessor {
                     None
                 }
             }
-            DocumentContent::Reference { .. } => {
+            DocumentContent::Reference { .. } | DocumentContent::Stream { .. } => {
                 if options.extract_text {
                     Some("[WORD EXTRACTED] Referenced Word document processed".to_string())
                 } else {
@@ -257,7 +671,8 @@ impl SwarmDocumentProcessor {
             classification: Some("Word Document".to_string()),
             embeddings: None,
             processing_time_ms: 0,
-            processed_at: Utc::now(),
+            processed_at: self.determinism.now(),
+            warnings: Vec::new(),
         };
         
         // Add Word-specific metadata
@@ -277,31 +692,189 @@ impl SwarmDocumentProcessor {
             }
         }
         
-        result.processing_time_ms = start_time.elapsed().as_millis() as u64;
+        result.processing_time_ms = self.elapsed_ms(start_time);
         result
     }
     
+    /// Process an xlsx workbook: sheet names and cell text
+    async fn process_excel(&self, content: &DocumentContent, options: &DocumentProcessingOptions) -> DocumentResult<DocumentProcessingResult> {
+        let start_time = Instant::now();
+        let DocumentContent::Binary(bytes) = content else {
+            return Err(DocumentError::ProcessingFailed {
+                reason: "Excel document must have binary content".to_string(),
+            });
+        };
+        let extraction = crate::xlsx::extract_xlsx(bytes)?;
+        
+        let mut metadata = HashMap::new();
+        metadata.insert("document_format".to_string(), serde_json::Value::String("xlsx".to_string()));
+        metadata.insert("sheet_count".to_string(), serde_json::Value::from(extraction.sheets.len()));
+        metadata.insert("sheets".to_string(), serde_json::to_value(&extraction.sheets)?);
+        metadata.insert("cell_count".to_string(), serde_json::Value::from(extraction.sheets.iter().map(|sheet| sheet.cell_count).sum::<usize>()));
+        
+        let mut result = DocumentProcessingResult {
+            document_id: Uuid::new_v4(),
+            extracted_text: options.extract_text.then_some(extraction.text),
+            metadata,
+            language: None,
+            keywords: Vec::new(),
+            sentiment: None,
+            classification: Some("Spreadsheet".to_string()),
+            embeddings: None,
+            processing_time_ms: 0,
+            processed_at: self.determinism.now(),
+            warnings: Vec::new(),
+        };
+        
+        // Process extracted text if available
+        if let Some(ref text) = result.extracted_text {
+            if options.detect_language {
+                result.language = self.detect_language(text);
+            }
+            if options.extract_keywords {
+                result.keywords = self.extract_keywords(text);
+            }
+            if options.extract_text {
+                result.sentiment = self.analyze_sentiment(text);
+            }
+        }
+        
+        result.processing_time_ms = self.elapsed_ms(start_time);
+        Ok(result)
+    }
+    
+    /// Process a pptx presentation: slide titles and body text, one section per slide
+    async fn process_powerpoint(&self, content: &DocumentContent, options: &DocumentProcessingOptions) -> DocumentResult<DocumentProcessingResult> {
+        let start_time = Instant::now();
+        let DocumentContent::Binary(bytes) = content else {
+            return Err(DocumentError::ProcessingFailed {
+                reason: "PowerPoint document must have binary content".to_string(),
+            });
+        };
+        let extraction = crate::pptx::extract_pptx(bytes)?;
+        
+        let mut metadata = HashMap::new();
+        metadata.insert("document_format".to_string(), serde_json::Value::String("pptx".to_string()));
+        metadata.insert("slide_count".to_string(), serde_json::Value::from(extraction.slides.len()));
+        metadata.insert("slides".to_string(), serde_json::to_value(&extraction.slides)?);
+        
+        let mut result = DocumentProcessingResult {
+            document_id: Uuid::new_v4(),
+            extracted_text: options.extract_text.then_some(extraction.text),
+            metadata,
+            language: None,
+            keywords: Vec::new(),
+            sentiment: None,
+            classification: Some("Presentation".to_string()),
+            embeddings: None,
+            processing_time_ms: 0,
+            processed_at: self.determinism.now(),
+            warnings: Vec::new(),
+        };
+        
+        // Process extracted text if available
+        if let Some(ref text) = result.extracted_text {
+            if options.detect_language {
+                result.language = self.detect_language(text);
+            }
+            if options.extract_keywords {
+                result.keywords = self.extract_keywords(text);
+            }
+            if options.extract_text {
+                result.sentiment = self.analyze_sentiment(text);
+            }
+        }
+        
+        result.processing_time_ms = self.elapsed_ms(start_time);
+        Ok(result)
+    }
+    
+    /// Process an image: OCR text, word bounding boxes and confidence
+    async fn process_image(&self, content: &DocumentContent, options: &DocumentProcessingOptions) -> DocumentResult<DocumentProcessingResult> {
+        let start_time = Instant::now();
+        let Some(engine) = &self.ocr_engine else {
+            return Err(DocumentError::UnsupportedDocumentType {
+                document_type: "Image (no OCR engine configured)".to_string(),
+            });
+        };
+        let DocumentContent::Binary(bytes) = content else {
+            return Err(DocumentError::ProcessingFailed {
+                reason: "Image document must have binary content".to_string(),
+            });
+        };
+        let output = engine.recognize(bytes).await?;
+        
+        let mut metadata = HashMap::new();
+        metadata.insert("ocr_engine".to_string(), serde_json::Value::String(engine.name().to_string()));
+        metadata.insert("word_count".to_string(), serde_json::Value::from(output.words.len()));
+        metadata.insert("ocr_words".to_string(), serde_json::to_value(&output.words)?);
+        if let Some(confidence) = output.mean_confidence() {
+            metadata.insert("ocr_confidence".to_string(), serde_json::json!(confidence));
+        }
+        
+        let mut result = DocumentProcessingResult {
+            document_id: Uuid::new_v4(),
+            extracted_text: options.extract_text.then_some(output.text),
+            metadata,
+            language: None,
+            keywords: Vec::new(),
+            sentiment: None,
+            classification: Some("Image".to_string()),
+            embeddings: None,
+            processing_time_ms: 0,
+            processed_at: self.determinism.now(),
+            warnings: Vec::new(),
+        };
+        
+        // Process extracted text if available
+        if let Some(ref text) = result.extracted_text {
+            if options.detect_language {
+                result.language = self.detect_language(text).or(output.language);
+            }
+            if options.extract_keywords {
+                result.keywords = self.extract_keywords(text);
+            }
+            if options.extract_text {
+                result.sentiment = self.analyze_sentiment(text);
+            }
+        }
+        
+        result.processing_time_ms = self.elapsed_ms(start_time);
+        Ok(result)
+    }
+    
     /// Process HTML content
     async fn process_html(&self, content: &DocumentContent, options: &DocumentProcessingOptions) -> DocumentProcessingResult {
         let start_time = Instant::now();
         
-        // Extract text from HTML (simplified)
+        let mut metadata = HashMap::new();
+        
+        // Extract text from HTML
         let extracted_text = match content {
             DocumentContent::Text(text) => {
-                if options.extract_text {
-                    // Simple HTML tag removal
-                    let clean_text = text
-                        .replace("<[^>]*>", "")
-                        .replace("&nbsp;", " ")
-                        .replace("&amp;", "&")
-                        .replace("&lt;", "<")
-                        .replace("&gt;", ">")
-                        .replace("&quot;", "\"")
-                        .replace("&#39;", "'");
-                    Some(format!("[HTML EXTRACTED] {}", clean_text.trim()))
-                } else {
-                    None
+                let extraction = crate::html::extract_html(text);
+                if let Some(title) = extraction.title {
+                    metadata.insert("title".to_string(), serde_json::Value::String(title));
+                }
+                if !extraction.meta.is_empty() {
+                    metadata.insert("meta".to_string(), serde_json::json!(extraction.meta));
+                }
+                if let Some(language) = extraction.language {
+                    metadata.insert("declared_language".to_string(), serde_json::Value::String(language));
                 }
+                metadata.insert("headings".to_string(), serde_json::json!(extraction.headings));
+                metadata.insert("link_count".to_string(), serde_json::Value::from(extraction.links.len()));
+                
+                if self.html_sanitizer.config().enabled {
+                    let sanitized = self.html_sanitizer.sanitize(text);
+                    metadata.insert("preview_html".to_string(), serde_json::Value::String(sanitized.html));
+                    metadata.insert("sanitization".to_string(), serde_json::json!({
+                        "removed_elements": sanitized.removed_elements,
+                        "removed_attributes": sanitized.removed_attributes,
+                        "preview_truncated": sanitized.truncated,
+                    }));
+                }
+                options.extract_text.then(|| format!("[HTML EXTRACTED] {}", extraction.text))
             }
             DocumentContent::Binary(_) => {
                 if options.extract_text {
@@ -310,7 +883,7 @@ impl SwarmDocumentProcessor {
                     None
                 }
             }
-            DocumentContent::Reference { .. } => {
+            DocumentContent::Reference { .. } | DocumentContent::Stream { .. } => {
                 if options.extract_text {
                     Some("[HTML EXTRACTED] Referenced HTML content processed".to_string())
                 } else {
@@ -322,14 +895,15 @@ impl SwarmDocumentProcessor {
         let mut result = DocumentProcessingResult {
             document_id: Uuid::new_v4(),
             extracted_text,
-            metadata: HashMap::new(),
+            metadata,
             language: None,
             keywords: Vec::new(),
             sentiment: None,
             classification: Some("HTML Document".to_string()),
             embeddings: None,
             processing_time_ms: 0,
-            processed_at: Utc::now(),
+            processed_at: self.determinism.now(),
+            warnings: Vec::new(),
         };
         
         // Add HTML-specific metadata
@@ -348,7 +922,7 @@ impl SwarmDocumentProcessor {
             }
         }
         
-        result.processing_time_ms = start_time.elapsed().as_millis() as u64;
+        result.processing_time_ms = self.elapsed_ms(start_time);
         result
     }
     
@@ -356,21 +930,20 @@ impl SwarmDocumentProcessor {
     async fn process_markdown(&self, content: &DocumentContent, options: &DocumentProcessingOptions) -> DocumentProcessingResult {
         let start_time = Instant::now();
         
-        // Extract text from Markdown (simplified)
+        let mut metadata = HashMap::new();
         let extracted_text = match content {
             DocumentContent::Text(text) => {
                 if options.extract_text {
-                    // Simple Markdown processing
-                    let clean_text = text
-                        .replace("#", "")
-                        .replace("*", "")
-                        .replace("_", "")
-                        .replace("`", "")
-                        .replace("[", "")
-                        .replace("]", "")
-                        .replace("(", "")
-                        .replace(")", "");
-                    Some(format!("[MARKDOWN EXTRACTED] {}", clean_text.trim()))
+                    const PREFIX: &str = "[MARKDOWN EXTRACTED] ";
+                    let mut extraction = crate::markdown::extract_markdown(text);
+                    // Outline offsets point into the extracted text as returned
+                    for heading in &mut extraction.headings {
+                        heading.offset += PREFIX.len();
+                    }
+                    metadata.insert("headings".to_string(), serde_json::json!(extraction.headings));
+                    metadata.insert("links".to_string(), serde_json::json!(extraction.links));
+                    metadata.insert("code_blocks".to_string(), serde_json::json!(extraction.code_blocks));
+                    Some(format!("{}{}", PREFIX, extraction.text))
                 } else {
                     None
                 }
@@ -382,7 +955,7 @@ impl SwarmDocumentProcessor {
                     None
                 }
             }
-            DocumentContent::Reference { .. } => {
+            DocumentContent::Reference { .. } | DocumentContent::Stream { .. } => {
                 if options.extract_text {
                     Some("[MARKDOWN EXTRACTED] Referenced Markdown content processed".to_string())
                 } else {
@@ -394,14 +967,15 @@ impl SwarmDocumentProcessor {
         let mut result = DocumentProcessingResult {
             document_id: Uuid::new_v4(),
             extracted_text,
-            metadata: HashMap::new(),
+            metadata,
             language: None,
             keywords: Vec::new(),
             sentiment: None,
             classification: Some("Markdown Document".to_string()),
             embeddings: None,
             processing_time_ms: 0,
-            processed_at: Utc::now(),
+            processed_at: self.determinism.now(),
+            warnings: Vec::new(),
         };
         
         // Add Markdown-specific metadata
@@ -420,29 +994,37 @@ impl SwarmDocumentProcessor {
             }
         }
         
-        result.processing_time_ms = start_time.elapsed().as_millis() as u64;
+        result.processing_time_ms = self.elapsed_ms(start_time);
         result
     }
-}
-
-#[async_trait]
-impl DocumentProcessor for SwarmDocumentProcessor {
-    async fn process_document(&self, document: &Document) -> Result<DocumentProcessingResult> {
+    
+    /// Process a document without recording statistics
+    async fn process(&self, document: &Document) -> Result<DocumentProcessingResult> {
         // Validate document
         utils::validate_document(document, &self.config)?;
+        if let DocumentContent::Stream { source } = &document.content {
+            return Err(DocumentError::ProcessingFailed {
+                reason: format!("{} is streamed content; use process_document_stream", source),
+            }.into());
+        }
         
-        // Process based on document type
-        let result = match document.document_type {
+        let document = self.resolve_reference(document).await?;
+        let (unlocked, decryption) = self.unlock_document(&document).await?;
+        let document = unlocked.as_ref();
+        
+        // Process based on document type, with the options resolved for it at ingestion
+        let options = self.options_for(document);
+        let mut result = match document.document_type {
             DocumentType::Pdf => {
-                self.process_pdf(&document.content, &self.config.processing_options).await
+                self.process_pdf(&document.content, &options).await?
             }
             DocumentType::Word => {
-                self.process_word(&document.content, &self.config.processing_options).await
+                self.process_word(&document.content, &options).await
             }
             DocumentType::Text => {
                 match &document.content {
                     DocumentContent::Text(text) => {
-                        self.process_text_content(text, &self.config.processing_options).await
+                        self.process_text_content(text, &options).await
                     }
                     _ => {
                         return Err(DocumentError::ProcessingFailed {
@@ -452,10 +1034,19 @@ impl DocumentProcessor for SwarmDocumentProcessor {
                 }
             }
             DocumentType::Html => {
-                self.process_html(&document.content, &self.config.processing_options).await
+                self.process_html(&document.content, &options).await
             }
             DocumentType::Markdown => {
-                self.process_markdown(&document.content, &self.config.processing_options).await
+                self.process_markdown(&document.content, &options).await
+            }
+            DocumentType::Excel => {
+                self.process_excel(&document.content, &options).await?
+            }
+            DocumentType::PowerPoint => {
+                self.process_powerpoint(&document.content, &options).await?
+            }
+            DocumentType::Image => {
+                self.process_image(&document.content, &options).await?
             }
             _ => {
                 return Err(DocumentError::UnsupportedDocumentType {
@@ -464,7 +1055,105 @@ impl DocumentProcessor for SwarmDocumentProcessor {
             }
         };
         
-        Ok(result)
+        if let Some(record) = decryption {
+            result.metadata.insert("decryption".to_string(), serde_json::to_value(&record)?);
+        }
+        if let Some(profile) = document.metadata.get(swarm_core::PROCESSING_PROFILE_KEY) {
+            result.metadata.insert(swarm_core::PROCESSING_PROFILE_KEY.to_string(), profile.clone());
+        }
+        self.note_analysis_budgets(document, &mut result);
+        self.add_crawl_candidates(document, &mut result);
+        self.add_embeddings(document, &mut result).await;
+        self.normalize_terms(document, &mut result).await;
+        self.assess_quality(document, &mut result);
+        
+        // Results carry the ID of the document they were produced from
+        Ok(DocumentProcessingResult {
+            document_id: document.id,
+            ..result
+        })
+    }
+    
+    /// Process a streamed document without recording statistics
+    async fn process_stream(&self, document: &Document, reader: &mut (dyn AsyncRead + Unpin + Send)) -> Result<DocumentProcessingResult> {
+        // The size limit does not apply to text streamed with bounded memory
+        if !self.can_process(&document.document_type) {
+            return Err(DocumentError::Rejected(Box::new(DocumentRejection::unsupported_type(document, &self.supported_types))).into());
+        }
+        
+        let start_time = Instant::now();
+        let options = self.options_for(document);
+        let analyzer = StreamingTextAnalyzer::new(self.config.streaming.clone());
+        let mut result = match document.document_type {
+            DocumentType::Text | DocumentType::Markdown => analyzer.analyze(reader, &options).await?,
+            DocumentType::Html => analyzer.with_markup_stripping().analyze(reader, &options).await?,
+            _ => {
+                // Formats that need random access are buffered, within the size limit
+                let mut bytes = Vec::new();
+                (&mut *reader).take(self.config.max_file_size as u64 + 1).read_to_end(&mut bytes).await?;
+                let buffered = Document {
+                    size_bytes: bytes.len(),
+                    content: DocumentContent::Binary(bytes),
+                    ..document.clone()
+                };
+                return self.process(&buffered).await;
+            }
+        };
+        
+        // Streamed text is only embedded when it was retained as extracted text
+        self.add_embeddings(document, &mut result).await;
+        self.normalize_terms(document, &mut result).await;
+        self.assess_quality(document, &mut result);
+        Ok(DocumentProcessingResult {
+            document_id: document.id,
+            processing_time_ms: self.elapsed_ms(start_time),
+            processed_at: self.determinism.now(),
+            ..result
+        })
+    }
+}
+
+/// Permit for one document of a batch
+enum BatchPermit {
+    Fixed(OwnedSemaphorePermit),
+    Adaptive(ConcurrencyPermit),
+}
+
+impl BatchPermit {
+    /// Release the permit, reporting the document's latency to an adaptive limit
+    fn complete(self) {
+        match self {
+            BatchPermit::Fixed(permit) => drop(permit),
+            BatchPermit::Adaptive(permit) => permit.complete(),
+        }
+    }
+}
+
+/// Elapsed wall time in whole milliseconds, rounded up so that any work is visible
+fn elapsed_ms(start_time: Instant) -> u64 {
+    start_time.elapsed().as_micros().div_ceil(1000) as u64
+}
+
+#[async_trait]
+impl DocumentProcessor for SwarmDocumentProcessor {
+    async fn process_document(&self, document: &Document) -> Result<DocumentProcessingResult> {
+        if let Some(outcome) = self.check_duplicate(document).await {
+            return outcome;
+        }
+        let start_time = Instant::now();
+        let result = self.process(document).await;
+        self.record(document, start_time, &result).await;
+        result
+    }
+    
+    async fn process_document_stream(&self, document: &Document, reader: &mut (dyn AsyncRead + Unpin + Send)) -> Result<DocumentProcessingResult> {
+        if let Some(outcome) = self.check_duplicate(document).await {
+            return outcome;
+        }
+        let start_time = Instant::now();
+        let result = self.process_stream(document, reader).await;
+        self.record(document, start_time, &result).await;
+        result
     }
     
     fn supported_document_types(&self) -> &[DocumentType] {
@@ -479,8 +1168,8 @@ impl DocumentProcessor for SwarmDocumentProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::tempdir;
-    use std::path::PathBuf;
+    use chrono::Utc;
+    use swarm_core::TextAnalysisOptions;
     
     fn create_test_config() -> DocumentProcessingConfig {
         DocumentProcessingConfig {
@@ -496,6 +1185,16 @@ mod tests {
             text_analysis_options: TextAnalysisOptions::default(),
             enable_parallel_processing: true,
             max_concurrent_documents: 5,
+            adaptive_concurrency: None,
+            embeddings: HashEmbeddingConfig::default(),
+            keywords: KeywordConfig::default(),
+            normalization: NormalizationConfig::default(),
+            quality: QualityConfig::default(),
+            sanitization: SanitizationConfig::default(),
+            streaming: StreamingConfig::default(),
+            crawling: CrawlConfig::default(),
+            determinism: swarm_core::DeterminismConfig::default(),
+            dedup: DedupConfig::default(),
         }
     }
     
@@ -538,6 +1237,31 @@ mod tests {
         assert!(!result.keywords.is_empty());
         assert!(result.sentiment.is_some());
         assert!(result.processing_time_ms > 0);
+        assert_eq!(result.document_id, document.id);
+    }
+    
+    #[tokio::test]
+    async fn test_analysis_budget_limits_large_text() {
+        let mut config = create_test_config();
+        config.text_analysis_options.budgets.keywords = swarm_core::WorkBudget::bytes(2048);
+        let processor = SwarmDocumentProcessor::new(config);
+        let text = format!("{} {}", "budget ".repeat(2000), "elephant ".repeat(300));
+        let result = processor.process_document(&create_test_document(DocumentType::Text, &text)).await.unwrap();
+        
+        let keywords = &result.metadata["analysis_budget"]["keywords"];
+        assert_eq!(keywords["total_bytes"], text.len());
+        assert!(keywords["analyzed_bytes"].as_u64().unwrap() <= 2048);
+        assert_eq!(keywords["sampled"], true);
+        // Sampling reaches the end of the text
+        assert!(result.keywords.iter().any(|keyword| keyword == "elephant"));
+        assert!(result.metadata["analysis_budget"].get("sentiment").is_none());
+        assert_eq!(result.warnings, vec![ProcessingWarning::new("analysis", "budget_exceeded",
+            format!("keywords analyzed {} of {} bytes", keywords["analyzed_bytes"], text.len()))]);
+        
+        let result = processor.process_document(&create_test_document(DocumentType::Text, "Short text")).await.unwrap();
+        assert!(!result.metadata.contains_key("analysis_budget"));
+        assert!(result.warnings.is_empty());
+        assert_eq!(processor.get_stats().await.warnings_by_code["budget_exceeded"], 1);
     }
     
     #[tokio::test]
@@ -557,6 +1281,65 @@ mod tests {
         assert!(result.metadata.contains_key("page_count"));
     }
     
+    #[cfg(feature = "pdf")]
+    #[tokio::test]
+    async fn test_binary_pdf_is_parsed() {
+        let processor = SwarmDocumentProcessor::new(create_test_config());
+        let bytes = crate::pdf::tests::create_test_pdf(&["The annual report", "Second page"], "Annual Report");
+        let mut document = create_test_document(DocumentType::Pdf, "");
+        document.size_bytes = bytes.len();
+        document.content = DocumentContent::Binary(bytes);
+        
+        let result = processor.process_document(&document).await.unwrap();
+        let text = result.extracted_text.unwrap();
+        assert!(text.contains("The annual report") && text.contains("Second page"));
+        assert!(!text.contains("[PDF EXTRACTED]"));
+        assert_eq!(result.metadata["page_count"], 2);
+        assert_eq!(result.metadata["pdf_info"]["title"], "Annual Report");
+        
+        document.content = DocumentContent::Binary(b"%PDF-1.4 truncated".to_vec());
+        assert!(processor.process_document(&document).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_xlsx_sheets_are_extracted() {
+        let mut config = create_test_config();
+        config.supported_types.push(DocumentType::Excel);
+        let processor = SwarmDocumentProcessor::new(config);
+        let bytes = crate::xlsx::tests::create_test_workbook();
+        let mut document = create_test_document(DocumentType::Excel, "");
+        document.size_bytes = bytes.len();
+        document.content = DocumentContent::Binary(bytes);
+        
+        let result = processor.process_document(&document).await.unwrap();
+        assert!(result.extracted_text.unwrap().contains("North & East\t1250.5"));
+        assert_eq!(result.metadata["sheet_count"], 2);
+        assert_eq!(result.metadata["sheets"][0]["name"], "Sales");
+        assert_eq!(result.metadata["sheets"][0]["row_count"], 4);
+        assert_eq!(result.metadata["sheets"][0]["column_count"], 4);
+        assert_eq!(result.classification.as_deref(), Some("Spreadsheet"));
+        
+        document.content = DocumentContent::Text("Region,Revenue".to_string());
+        assert!(processor.process_document(&document).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_pptx_slides_are_extracted() {
+        let mut config = create_test_config();
+        config.supported_types.push(DocumentType::PowerPoint);
+        let processor = SwarmDocumentProcessor::new(config);
+        let bytes = crate::pptx::tests::create_test_presentation();
+        let mut document = create_test_document(DocumentType::PowerPoint, "");
+        document.size_bytes = bytes.len();
+        document.content = DocumentContent::Binary(bytes);
+        
+        let result = processor.process_document(&document).await.unwrap();
+        assert!(result.extracted_text.unwrap().starts_with("Slide 1: Quarterly Review\nRevenue grew 12%"));
+        assert_eq!(result.metadata["slide_count"], 2);
+        assert_eq!(result.metadata["slides"][0]["title"], "Quarterly Review");
+        assert_eq!(result.classification.as_deref(), Some("Presentation"));
+    }
+    
     #[tokio::test]
     async fn test_html_document_processing() {
         let config = create_test_config();
@@ -574,6 +1357,28 @@ mod tests {
         assert!(!extracted.contains("<h1>"));
         assert!(!extracted.contains("<strong>"));
         assert_eq!(result.classification, Some("HTML Document".to_string()));
+        assert_eq!(result.metadata["headings"][0]["text"], "Title");
+        
+        let document = create_test_document(
+            DocumentType::Html,
+            "<html lang=\"sv\"><head><title>Rapport</title><meta name=\"author\" content=\"Ada\"></head>\
+             <body><h2>Del 1</h2><p>Se <a href=\"https://example.com\">länken</a></p></body></html>"
+        );
+        let result = processor.process_document(&document).await.unwrap();
+        assert_eq!(result.extracted_text.as_deref(), Some("[HTML EXTRACTED] Del 1\nSe länken"));
+        assert_eq!(result.metadata["title"], "Rapport");
+        assert_eq!(result.metadata["meta"]["author"], "Ada");
+        assert_eq!(result.metadata["declared_language"], "sv");
+        assert_eq!(result.metadata["link_count"], 1);
+        
+        let document = create_test_document(
+            DocumentType::Html,
+            "<p onmouseover=\"x()\">Safe <b>text</b></p><script>alert('xss')</script><style>p {}</style>"
+        );
+        let result = processor.process_document(&document).await.unwrap();
+        assert_eq!(result.extracted_text.as_deref(), Some("[HTML EXTRACTED] Safe text"));
+        assert_eq!(result.metadata["preview_html"], "<p>Safe <b>text</b></p>");
+        assert_eq!(result.metadata["sanitization"]["removed_elements"], 2);
     }
     
     #[tokio::test]
@@ -582,7 +1387,8 @@ mod tests {
         let processor = SwarmDocumentProcessor::new(config);
         let document = create_test_document(
             DocumentType::Markdown,
-            "# Title\n\nThis is **markdown** content with *formatting* and `code`."
+            "# Title\n\nThis is **markdown** content with *formatting* and `code`.\n\n\
+             ## Links\n\nSee [the docs](https://example.com/docs).\n\n```python\nprint(a[0])\n```"
         );
         
         let result = processor.process_document(&document).await.unwrap();
@@ -592,7 +1398,294 @@ mod tests {
         assert!(extracted.contains("[MARKDOWN EXTRACTED]"));
         assert!(!extracted.contains("#"));
         assert!(!extracted.contains("**"));
+        assert!(extracted.contains("the docs"));
+        assert!(extracted.contains("print(a[0])"));
         assert_eq!(result.classification, Some("Markdown Document".to_string()));
+        
+        let headings = result.metadata["headings"].as_array().unwrap();
+        assert_eq!(headings.len(), 2);
+        let offset = headings[1]["offset"].as_u64().unwrap() as usize;
+        assert!(extracted[offset..].starts_with("Links"));
+        assert_eq!(result.metadata["links"][0]["url"], "https://example.com/docs");
+        assert_eq!(result.metadata["code_blocks"][0]["language"], "python");
+    }
+    
+    #[tokio::test]
+    async fn test_documents_are_processed_with_their_profile_options() {
+        let processor = SwarmDocumentProcessor::new(create_test_config());
+        let mut document = create_test_document(DocumentType::Text, "Quarterly revenue grew strongly in every region.");
+        assert!(!processor.process_document(&document).await.unwrap().keywords.is_empty());
+        
+        let profiles = swarm_core::ProcessingProfiles {
+            profiles: HashMap::from([("text_only".to_string(), DocumentProcessingOptions {
+                extract_keywords: false,
+                ..DocumentProcessingOptions::default()
+            })]),
+            tenants: HashMap::new(),
+            default_profile: Some("text_only".to_string()),
+        };
+        profiles.apply(&mut document);
+        let result = processor.process_document(&document).await.unwrap();
+        assert!(result.keywords.is_empty());
+        assert_eq!(result.metadata[swarm_core::PROCESSING_PROFILE_KEY], "text_only");
+    }
+    
+    #[tokio::test]
+    async fn test_crawled_documents_emit_url_candidates() {
+        let processor = SwarmDocumentProcessor::new(DocumentProcessingConfig {
+            crawling: CrawlConfig { enabled: true, ..CrawlConfig::default() },
+            ..create_test_config()
+        });
+        let mut document = create_test_document(
+            DocumentType::Html,
+            "<p><a href=\"/about\">About</a> <a href=\"https://other.org/\">Other</a></p>"
+        );
+        document.metadata.insert(SOURCE_URL_KEY.to_string(), serde_json::json!("https://example.com/index.html"));
+        
+        let result = processor.process_document(&document).await.unwrap();
+        let candidates = crawl_candidates(&result);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!((candidates[0].url.as_str(), candidates[0].depth), ("https://example.com/about", 1));
+        
+        // Documents not fetched from a URL are not crawled
+        document.metadata.clear();
+        let result = processor.process_document(&document).await.unwrap();
+        assert!(!result.metadata.contains_key(CRAWL_CANDIDATES_KEY));
+    }
+    
+    #[tokio::test]
+    async fn test_batch_processing() {
+        let mut documents: Vec<Document> = (0..12)
+            .map(|i| create_test_document(DocumentType::Text, &format!("Document number {} is good.", i)))
+            .collect();
+        documents[7] = create_test_document(DocumentType::Image, "not supported");
+        
+        for enable_parallel_processing in [true, false] {
+            let processor = SwarmDocumentProcessor::new(DocumentProcessingConfig {
+                enable_parallel_processing,
+                max_concurrent_documents: 3,
+                ..create_test_config()
+            });
+            let results = processor.process_documents(&documents).await;
+            
+            assert_eq!(results.len(), documents.len());
+            for (document, result) in documents.iter().zip(&results) {
+                match document.document_type {
+                    DocumentType::Image => assert!(result.is_err()),
+                    _ => assert_eq!(result.as_ref().unwrap().document_id, document.id),
+                }
+            }
+            
+            let stats = processor.get_stats().await;
+            assert_eq!((stats.total_processed, stats.error_count), (12, 1));
+            assert_eq!(stats.documents_by_type[&DocumentType::Text], 11);
+            assert!((stats.success_rate - 11.0 / 12.0).abs() < f32::EPSILON);
+            assert!(stats.last_processed_at.is_some());
+            
+            processor.reset_stats().await;
+            assert_eq!(processor.get_stats().await.total_processed, 0);
+        }
+    }
+    
+    struct ConstantEmbeddings;
+    
+    #[async_trait]
+    impl EmbeddingProvider for ConstantEmbeddings {
+        fn model(&self) -> &str {
+            "constant"
+        }
+        
+        fn dimensions(&self) -> usize {
+            2
+        }
+        
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            Ok(texts.iter().map(|_| vec![1.0, 0.0]).collect())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_embeddings_generation() {
+        let document = create_test_document(DocumentType::Text, "Quarterly budget report for the finance team.");
+        let result = SwarmDocumentProcessor::new(create_test_config()).process_document(&document).await.unwrap();
+        assert_eq!(result.embeddings, None);
+        
+        let mut config = create_test_config();
+        config.processing_options.generate_embeddings = true;
+        config.processing_options.extract_text = false;
+        let result = SwarmDocumentProcessor::new(config.clone()).process_document(&document).await.unwrap();
+        assert_eq!(result.embeddings.unwrap().len(), 256);
+        assert_eq!(result.metadata["embedding_model"], "hash-tfidf-256");
+        
+        let processor = SwarmDocumentProcessor::new(config).with_embedding_provider(Arc::new(ConstantEmbeddings));
+        let result = processor.process_document(&document).await.unwrap();
+        assert_eq!(result.embeddings, Some(vec![1.0, 0.0]));
+        assert_eq!(result.metadata["embedding_model"], "constant");
+    }
+    
+    #[tokio::test]
+    async fn test_adaptive_batch_concurrency() {
+        let documents: Vec<Document> = (0..12)
+            .map(|i| create_test_document(DocumentType::Text, &format!("Document number {}.", i)))
+            .collect();
+        let static_processor = SwarmDocumentProcessor::new(create_test_config());
+        assert_eq!(static_processor.get_stats().await.concurrency.limit, 5);
+        
+        // Fast documents stay under the target, so the limit grows once per window
+        let processor = SwarmDocumentProcessor::new(DocumentProcessingConfig {
+            max_concurrent_documents: 2,
+            adaptive_concurrency: Some(AdaptiveConcurrencyConfig {
+                target_p95_ms: 10_000,
+                window_size: 4,
+                ..Default::default()
+            }),
+            ..create_test_config()
+        });
+        let results = processor.process_documents(&documents).await;
+        assert!(results.iter().all(|result| result.is_ok()));
+        
+        let concurrency = processor.get_stats().await.concurrency;
+        assert_eq!((concurrency.limit, concurrency.increases, concurrency.in_flight), (5, 3, 0));
+        assert!(concurrency.last_p95_ms.is_some());
+    }
+    
+    #[tokio::test]
+    async fn test_protected_pdf_is_unlocked() {
+        use crate::credentials::tests::{encrypted_pdf, PrefixDecryptor};
+        
+        let vault = CredentialVault::default()
+            .with_password("finance", Some("acme"), Some("*/finance/*"), Secret::new("s3cret"));
+        let unlocker = Arc::new(DocumentUnlocker::new(Arc::new(vault))
+            .with_decryptor(DocumentType::Pdf, Arc::new(PrefixDecryptor)));
+        let processor = SwarmDocumentProcessor::new(create_test_config()).with_unlocker(unlocker.clone());
+        
+        #[cfg(feature = "pdf")]
+        let plain = crate::pdf::tests::create_test_pdf(&["Budget for next year"], "Budget");
+        #[cfg(not(feature = "pdf"))]
+        let plain = b"Budget for next year".to_vec();
+        let bytes = encrypted_pdf("s3cret", &plain);
+        let mut document = create_test_document(DocumentType::Pdf, "");
+        document.content = DocumentContent::Binary(bytes.clone());
+        document.metadata.insert("tenant".to_string(), serde_json::json!("acme"));
+        document.metadata.insert("file_path".to_string(), serde_json::json!("/in/finance/q3.pdf"));
+        
+        let result = processor.process_document(&document).await.unwrap();
+        #[cfg(feature = "pdf")]
+        assert!(result.extracted_text.unwrap().contains("Budget for next year"));
+        assert_eq!(result.metadata["decryption"], serde_json::json!({ "attempts": 1, "credential_id": "finance" }));
+        
+        document.metadata.insert("tenant".to_string(), serde_json::json!("other"));
+        let error = processor.process_document(&document).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<DocumentError>(), Some(DocumentError::DecryptionFailed { attempts: 0, .. })));
+        assert_eq!(unlocker.stats().documents_failed, 1);
+        
+        // Without a credential provider protected documents are rejected explicitly
+        let plain_processor = SwarmDocumentProcessor::new(create_test_config());
+        assert!(plain_processor.process_document(&document).await.is_err());
+    }
+    
+    #[cfg(feature = "pdf")]
+    #[tokio::test]
+    async fn test_encrypted_pdf_fixture_is_extracted() {
+        let vault = CredentialVault::default()
+            .with_password("old", None, None, Secret::new("hunter2"))
+            .with_password("reports", None, Some("*/reports/*"), Secret::new("s3cret"));
+        let unlocker = Arc::new(DocumentUnlocker::new(Arc::new(vault)));
+        let processor = SwarmDocumentProcessor::new(create_test_config()).with_unlocker(unlocker.clone());
+        
+        let mut document = create_test_document(DocumentType::Pdf, "");
+        document.content = DocumentContent::Binary(include_bytes!("../../../test-data/protected_report.pdf").to_vec());
+        document.metadata.insert("file_path".to_string(), serde_json::json!("/in/reports/q3.pdf"));
+        
+        let result = processor.process_document(&document).await.unwrap();
+        assert!(result.extracted_text.unwrap().contains("Quarterly revenue grew by twelve percent"));
+        assert_eq!(result.metadata["decryption"], serde_json::json!({ "attempts": 2, "credential_id": "reports" }));
+        assert_eq!(unlocker.stats(), DecryptionStats { documents_unlocked: 1, documents_failed: 0, attempts: 2 });
+    }
+    
+    #[tokio::test]
+    async fn test_reference_content_is_fetched_and_verified() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), "The reference content is here").unwrap();
+        let store = Arc::new(FileSystemStore::new(temp_dir.path()));
+        let processor = SwarmDocumentProcessor::new(create_test_config())
+            .with_reference_fetcher(Arc::new(ReferenceFetcher::new().with_store("local", store.clone())));
+        
+        let mut document = create_test_document(DocumentType::Text, "");
+        document.content = store.reference("local", "notes.txt").await.unwrap();
+        let result = processor.process_document(&document).await.unwrap();
+        assert_eq!(result.extracted_text.as_deref(), Some("The reference content is here"));
+        
+        std::fs::write(temp_dir.path().join("notes.txt"), "Changed after discovery").unwrap();
+        let error = processor.process_document(&document).await.unwrap_err();
+        assert!(error.downcast_ref::<DocumentError>().is_some_and(DocumentError::is_requeueable));
+    }
+    
+    #[tokio::test]
+    async fn test_streamed_document_processing() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("large.txt");
+        let line = "The streaming processor reads this document incrementally.\n";
+        tokio::fs::write(&path, line.repeat(200)).await.unwrap();
+        
+        let mut config = create_test_config();
+        config.max_file_size = 1024;
+        config.streaming.max_text_bytes = 256;
+        let processor = SwarmDocumentProcessor::new(config);
+        let document = stream_document_from_path(&path).await.unwrap();
+        assert!(processor.process_document(&document).await.is_err());
+        
+        let mut file = tokio::fs::File::open(&path).await.unwrap();
+        let result = processor.process_document_stream(&document, &mut file).await.unwrap();
+        assert_eq!(result.document_id, document.id);
+        assert_eq!(result.language, Some("en".to_string()));
+        assert!(result.keywords.contains(&"streaming".to_string()));
+        assert!(result.extracted_text.unwrap().len() <= 256);
+        assert_eq!(result.metadata["bytes_read"], line.len() as u64 * 200);
+    }
+    
+    struct FixedOcr;
+    
+    #[async_trait]
+    impl OcrEngine for FixedOcr {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+        
+        async fn recognize(&self, _image: &[u8]) -> DocumentResult<OcrOutput> {
+            let word = |text: &str, left| OcrWord {
+                text: text.to_string(),
+                confidence: 90.0,
+                bounding_box: BoundingBox { left, top: 10, width: 40, height: 12 },
+            };
+            Ok(OcrOutput {
+                text: "The invoice is due and the payment is overdue".to_string(),
+                words: vec![word("The", 0), word("invoice", 50)],
+                language: Some("eng".to_string()),
+            })
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_image_ocr_processing() {
+        let document = Document {
+            content: DocumentContent::Binary(b"\x89PNG\r\n\x1a\n".to_vec()),
+            ..create_test_document(DocumentType::Image, "")
+        };
+        assert!(SwarmDocumentProcessor::new(create_test_config()).process_document(&document).await.is_err());
+        
+        let processor = SwarmDocumentProcessor::new(create_test_config()).with_ocr_engine(Arc::new(FixedOcr));
+        assert!(processor.can_process(&DocumentType::Image));
+        let result = processor.process_document(&document).await.unwrap();
+        assert_eq!(result.extracted_text.as_deref(), Some("The invoice is due and the payment is overdue"));
+        assert_eq!(result.language, Some("en".to_string()));
+        assert_eq!(result.classification, Some("Image".to_string()));
+        assert_eq!(result.metadata["ocr_engine"], "fixed");
+        assert_eq!(result.metadata["ocr_confidence"], 90.0);
+        assert_eq!(result.metadata["ocr_words"][1]["bounding_box"]["left"], 50);
+        
+        let text_only = Document { content: DocumentContent::Text("pixels".to_string()), ..document };
+        assert!(processor.process_document(&text_only).await.is_err());
     }
     
     #[tokio::test]
@@ -606,6 +1699,12 @@ mod tests {
         
         let result = processor.process_document(&document).await;
         assert!(result.is_err());
+        
+        let error = result.unwrap_err();
+        let rejection = error.downcast_ref::<DocumentError>().and_then(DocumentError::rejection).unwrap();
+        assert_eq!(rejection.reason, swarm_core::RejectionReason::UnsupportedType);
+        assert_eq!(rejection.document_id, Some(document.id));
+        assert!(rejection.remediation.contains("Pdf"));
     }
     
     #[tokio::test]
@@ -644,6 +1743,58 @@ mod tests {
         assert!(!result.keywords.is_empty());
         assert!(result.keywords.len() <= 10); // Should not exceed max keywords
         assert!(result.keywords.iter().all(|kw| kw.len() > 3)); // All keywords should be longer than 3 chars
+        assert!(!result.keywords.iter().any(|kw| kw == "this" || kw == "with" || kw == "that" || kw == "should"));
+        assert_eq!(result.keywords.iter().filter(|kw| kw.starts_with("keyword")).count(), 1);
+        assert_eq!(processor.corpus_statistics().documents, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_chinese_keyword_extraction() {
+        let processor = SwarmDocumentProcessor::new(create_test_config());
+        let document = create_test_document(
+            DocumentType::Text,
+            "机器学习是人工智能的分支。机器学习需要大量数据，深度学习也是机器学习。"
+        );
+        
+        let result = processor.process_document(&document).await.unwrap();
+        
+        assert_eq!(result.language, Some("zh".to_string()));
+        assert!(result.keywords.iter().any(|keyword| keyword == "机器" || keyword == "机器学习"));
+        assert!(result.keywords.iter().all(|keyword| keyword.chars().count() >= 2));
+    }
+    
+    #[tokio::test]
+    async fn test_duplicates_are_skipped_or_linked() {
+        let documents = [
+            create_test_document(DocumentType::Text, "Quarterly revenue grew strongly."),
+            create_test_document(DocumentType::Text, "Quarterly revenue grew strongly."),
+            create_test_document(DocumentType::Text, "Costs stayed flat."),
+        ];
+        
+        let skipping = SwarmDocumentProcessor::new(DocumentProcessingConfig {
+            dedup: DedupConfig { enabled: true, action: DuplicateAction::Skip },
+            ..create_test_config()
+        });
+        let results = skipping.process_documents(&documents).await;
+        assert!(results[0].is_ok() && results[2].is_ok());
+        let error = results[1].as_ref().unwrap_err().downcast_ref::<DocumentError>().unwrap();
+        assert!(matches!(error, DocumentError::Duplicate { original_id, .. } if *original_id == documents[0].id));
+        assert_eq!(error.task_status(), TaskStatus::Cancelled);
+        let stats = skipping.get_stats().await;
+        assert_eq!((stats.total_processed, stats.error_count), (2, 0));
+        assert_eq!((stats.dedup.unique_documents, stats.dedup.duplicates_skipped), (2, 1));
+        
+        let linking = SwarmDocumentProcessor::new(DocumentProcessingConfig {
+            dedup: DedupConfig { enabled: true, action: DuplicateAction::Link },
+            enable_parallel_processing: false,
+            ..create_test_config()
+        });
+        let results = linking.process_documents(&documents).await;
+        let linked = results[1].as_ref().unwrap();
+        assert_eq!(linked.document_id, documents[1].id);
+        assert_eq!(linked.metadata[DUPLICATE_OF_KEY], documents[0].id.to_string());
+        assert!(linked.extracted_text.is_none());
+        assert_eq!(linking.get_stats().await.dedup.duplicates(), 1);
     }
     
     #[tokio::test]
@@ -667,4 +1818,35 @@ mod tests {
         let result = processor.process_document(&negative_doc).await.unwrap();
         assert!(result.sentiment.unwrap() < 0.0);
     }
+    
+    #[tokio::test]
+    async fn test_deterministic_mode_reproduces_results() {
+        let config = DocumentProcessingConfig {
+            determinism: swarm_core::DeterminismConfig::seeded(42),
+            ..create_test_config()
+        };
+        let texts = [
+            "Distributed systems coordinate workers across machines. Workers process documents.",
+            "<html><body><p>Keyword extraction ranks terms by frequency and rarity.</p></body></html>",
+            "Research pipelines need reproducible results across runs and machines.",
+        ];
+        let documents: Vec<Document> = texts.iter().enumerate().map(|(index, text)| {
+            swarm_core::DocumentBuilder::new(format!("doc{}.txt", index), DocumentContent::Text(text.to_string()))
+                .document_type(if index == 1 { DocumentType::Html } else { DocumentType::Text })
+                .id_strategy(swarm_core::DocumentIdStrategy::Deterministic)
+                .created_at(config.determinism.frozen_time)
+                .build()
+        }).collect();
+        
+        let mut runs = Vec::new();
+        for _ in 0..2 {
+            let processor = SwarmDocumentProcessor::new(config.clone());
+            let results: Vec<DocumentProcessingResult> = processor.process_documents(&documents).await
+                .into_iter().map(Result::unwrap).collect();
+            assert!(results.iter().all(|result| result.processing_time_ms == 0));
+            assert!(results.iter().all(|result| result.processed_at == config.determinism.frozen_time));
+            runs.push(swarm_core::canonical_json(&results).unwrap());
+        }
+        assert_eq!(runs[0], runs[1]);
+    }
 }
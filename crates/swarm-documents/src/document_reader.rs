@@ -1,18 +1,26 @@
 //! Document Reader Implementation
 //!
 //! This module provides concrete implementations of document reading
-//! capabilities for the Aprio Swarm system.
+//! capabilities for the Aprio Swarm system. The ingest latency of every
+//! document read, from its last change on disk, is recorded as an SLI.
+//! Members of a document group are held back until the whole group has been
+//! read, and rejected if it stays incomplete.
 
 use super::*;
-use swarm_core::prelude::*;
+use crate::directory_walker::default_max_scan_depth;
+use crate::processing_state::{MemoryStateStore, ProcessedFile, ProcessingStateStore, SledStateStore};
 use anyhow::Result;
 use async_trait::async_trait;
-use std::collections::HashMap;
+use futures::stream::{self, Stream};
+use std::borrow::BorrowMut;
+use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs;
 use tokio::time::{sleep, Duration};
-use uuid::Uuid;
 use chrono::Utc;
+use swarm_core::{cancellable_sleep, CancellationToken, Clock, ConfigUpdates, DocumentRejection, GroupAssembler, ProcessingProfiles, SamplingConfig, Service, Sli, SliRecorder, SystemClock, TraceContext, TraceSampler, REJECTION_SUBJECT};
+use tracing::Instrument;
 
 /// Configuration for document reader
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -35,11 +43,49 @@ pub struct DocumentReaderConfig {
     /// Enable recursive directory scanning
     pub recursive_scan: bool,
     
-    /// File patterns to include (glob patterns)
+    /// Maximum directory levels scanned below each watched directory when
+    /// scanning recursively; directories matching an exclude pattern are skipped
+    #[serde(default = "default_max_scan_depth")]
+    pub max_scan_depth: usize,
+    
+    /// File patterns to include (glob patterns, see [`crate::path_patterns`])
     pub include_patterns: Vec<String>,
     
-    /// File patterns to exclude (glob patterns)
+    /// File patterns to exclude (glob patterns, see [`crate::path_patterns`])
     pub exclude_patterns: Vec<String>,
+    
+    /// How document IDs are assigned (deterministic IDs derive from path and content)
+    #[serde(default)]
+    pub id_strategy: DocumentIdStrategy,
+    
+    /// External converters for otherwise unsupported formats, keyed by extension
+    #[serde(default)]
+    pub converters: HashMap<String, ExternalConverterConfig>,
+    
+    /// Tracing sampling; the reader makes the decision for each document it reads
+    #[serde(default)]
+    pub tracing_sampling: SamplingConfig,
+    
+    /// Metadata sidecar files (`report.pdf.meta.json`), merged into document metadata
+    #[serde(default)]
+    pub sidecars: SidecarConfig,
+    
+    /// Processing option profiles, resolved per document from its tenant
+    #[serde(default)]
+    pub processing_profiles: ProcessingProfiles,
+    
+    /// Time the members of a document group have to arrive, from the first one read
+    #[serde(default = "default_group_assembly_timeout_ms")]
+    pub group_assembly_timeout_ms: u64,
+    
+    /// Database remembering the files read, so restarts do not read them again;
+    /// kept in memory when unset
+    #[serde(default)]
+    pub state_db_path: Option<PathBuf>,
+}
+
+fn default_group_assembly_timeout_ms() -> u64 {
+    5 * 60 * 1000
 }
 
 impl Default for DocumentReaderConfig {
@@ -57,8 +103,16 @@ impl Default for DocumentReaderConfig {
             scan_interval_ms: 1000,
             batch_size: 10,
             recursive_scan: true,
+            max_scan_depth: default_max_scan_depth(),
             include_patterns: vec!["*".to_string()],
             exclude_patterns: vec![".*".to_string()], // Exclude hidden files
+            id_strategy: DocumentIdStrategy::Random,
+            converters: HashMap::new(),
+            tracing_sampling: SamplingConfig::default(),
+            sidecars: SidecarConfig::default(),
+            processing_profiles: ProcessingProfiles::default(),
+            group_assembly_timeout_ms: default_group_assembly_timeout_ms(),
+            state_db_path: None,
         }
     }
 }
@@ -66,18 +120,51 @@ impl Default for DocumentReaderConfig {
 /// Concrete implementation of DocumentReader trait
 pub struct SwarmDocumentReader {
     config: DocumentReaderConfig,
-    processed_files: HashMap<PathBuf, chrono::DateTime<chrono::Utc>>,
+    converter: DocumentConverter,
+    include_patterns: PathPatterns,
+    exclude_patterns: PathPatterns,
+    state: Arc<dyn ProcessingStateStore>,
     is_running: bool,
+    shutdown: CancellationToken,
     stats: DocumentReaderStats,
+    sampler: TraceSampler,
+    publisher: Option<(Arc<dyn MessageBroker>, String)>,
+    sli: SliRecorder,
+    groups: GroupAssembler,
+    clock: Arc<dyn Clock>,
+    config_updates: Option<ConfigUpdates<DocumentReaderConfig>>,
 }
 
 impl SwarmDocumentReader {
     /// Create a new document reader
     pub fn new(config: DocumentReaderConfig) -> Self {
+        if let Err(e) = config.processing_profiles.validate() {
+            tracing::warn!("Documents of some tenants keep the processor's options: {}", e);
+        }
+        let state: Arc<dyn ProcessingStateStore> = match &config.state_db_path {
+            Some(path) => match SledStateStore::open(path) {
+                Ok(store) => Arc::new(store),
+                Err(e) => {
+                    tracing::error!("Failed to open processing state {}, keeping it in memory: {}", path.display(), e);
+                    Arc::new(MemoryStateStore::new())
+                }
+            },
+            None => Arc::new(MemoryStateStore::new()),
+        };
         Self {
+            converter: DocumentConverter::new(config.converters.clone()),
+            include_patterns: PathPatterns::compile_valid(&config.include_patterns),
+            exclude_patterns: PathPatterns::compile_valid(&config.exclude_patterns),
+            sampler: TraceSampler::new(config.tracing_sampling.clone()),
+            publisher: None,
+            sli: SliRecorder::default(),
+            groups: GroupAssembler::new(Duration::from_millis(config.group_assembly_timeout_ms)),
+            clock: Arc::new(SystemClock),
+            config_updates: None,
             config,
-            processed_files: HashMap::new(),
+            state,
             is_running: false,
+            shutdown: CancellationToken::new(),
             stats: DocumentReaderStats {
                 total_documents_read: 0,
                 documents_per_second: 0.0,
@@ -87,6 +174,54 @@ impl SwarmDocumentReader {
         }
     }
     
+    /// Publish every document found by the run loop to `subject`, as JSON
+    ///
+    /// Files that are rejected are reported on [`REJECTION_SUBJECT`].
+    pub fn with_broker(mut self, broker: Arc<dyn MessageBroker>, subject: impl Into<String>) -> Self {
+        self.publisher = Some((broker, subject.into()));
+        self
+    }
+    
+    /// Record the ingest latency of every document read to `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<dyn MetricsCollector>) -> Self {
+        self.sli = SliRecorder::new(metrics);
+        self
+    }
+    
+    /// Take documents' `created_at` from a custom clock, e.g. a `FrozenClock`
+    /// for reproducible runs together with `DocumentIdStrategy::Deterministic`
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Apply reloaded tunables (scan interval and maximum file size) between scans
+    pub fn with_config_updates(mut self, updates: ConfigUpdates<DocumentReaderConfig>) -> Self {
+        self.config_updates = Some(updates);
+        self
+    }
+    
+    /// Take the tunables of a reloaded configuration, if there was a reload
+    fn apply_config_updates(&mut self) {
+        let Some(config) = self.config_updates.as_mut().and_then(ConfigUpdates::changed) else { return };
+        if config.scan_interval_ms != self.config.scan_interval_ms {
+            tracing::info!("Scan interval changed to {}ms", config.scan_interval_ms);
+        }
+        self.config.scan_interval_ms = config.scan_interval_ms;
+        self.config.max_file_size = config.max_file_size;
+    }
+    
+    /// Remember the files read in another store, e.g. one shared with other readers
+    pub fn with_state_store(mut self, store: Arc<dyn ProcessingStateStore>) -> Self {
+        self.state = store;
+        self
+    }
+    
+    /// Files read so far, to query or reset
+    pub fn processing_state(&self) -> &dyn ProcessingStateStore {
+        self.state.as_ref()
+    }
+    
     /// Get current configuration
     pub fn config(&self) -> &DocumentReaderConfig {
         &self.config
@@ -97,11 +232,47 @@ impl SwarmDocumentReader {
         &self.stats
     }
     
+    /// Token that stops `start` from another task
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+    
+    /// Hand a document to the broker, if one is configured
+    ///
+    /// Downstream stages continue the document's trace from the publish span.
+    async fn publish(&mut self, mut document: Document) {
+        if let Some((_, subject)) = &self.publisher {
+            let subject = subject.clone();
+            let span = TraceContext::continue_in(&mut document.metadata, "publish", document.id);
+            self.publish_json(&subject, &document, &document.filename).instrument(span).await;
+        }
+    }
+    
+    /// Report a rejected file, if a broker is configured
+    async fn publish_rejection(&mut self, rejection: &DocumentRejection) {
+        self.publish_json(REJECTION_SUBJECT, rejection, &rejection.filename).await;
+    }
+    
+    async fn publish_json(&mut self, subject: &str, payload: &impl serde::Serialize, filename: &str) {
+        let Some((broker, _)) = &self.publisher else {
+            return;
+        };
+        let published = match serde_json::to_vec(payload) {
+            Ok(payload) => broker.publish(subject, &payload).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = published {
+            tracing::error!("Failed to publish {} to {}: {}", filename, subject, e);
+            self.stats.error_count += 1;
+        }
+    }
+    
     /// Check if file has a supported extension
     fn is_supported_file(&self, path: &Path) -> bool {
         if let Some(extension) = path.extension() {
             if let Some(ext_str) = extension.to_str() {
-                return self.config.supported_extensions.contains(&ext_str.to_lowercase());
+                let ext_str = ext_str.to_lowercase();
+                return self.config.supported_extensions.contains(&ext_str) || self.converter.supports(&ext_str);
             }
         }
         false
@@ -109,49 +280,82 @@ impl SwarmDocumentReader {
     
     /// Check if file matches include patterns
     fn matches_include_patterns(&self, path: &Path) -> bool {
-        // For now, simple implementation - check if any include pattern matches
-        // In a real implementation, you'd use glob matching
-        self.config.include_patterns.iter().any(|pattern| {
-            if pattern == "*" {
-                return true;
-            }
-            // Simple pattern matching (could be enhanced with proper glob)
-            path.to_string_lossy().contains(pattern.trim_start_matches('*').trim_end_matches('*'))
-        })
+        self.include_patterns.matches(relative_to_roots(path, &self.config.watch_directories))
     }
     
     /// Check if file matches exclude patterns
     fn matches_exclude_patterns(&self, path: &Path) -> bool {
-        // For now, simple implementation - check if any exclude pattern matches
-        self.config.exclude_patterns.iter().any(|pattern| {
-            if pattern == ".*" {
-                return path.file_name()
-                    .and_then(|name| name.to_str())
-                    .map(|name| name.starts_with('.'))
-                    .unwrap_or(false);
-            }
-            // Simple pattern matching (could be enhanced with proper glob)
-            path.to_string_lossy().contains(pattern.trim_start_matches('*').trim_end_matches('*'))
-        })
+        self.exclude_patterns.matches(relative_to_roots(path, &self.config.watch_directories))
     }
     
     /// Check if file should be processed
     fn should_process_file(&self, path: &Path) -> bool {
-        self.is_supported_file(path) 
+        !self.config.sidecars.is_sidecar(path)
+            && self.is_supported_file(path) 
             && self.matches_include_patterns(path) 
             && !self.matches_exclude_patterns(path)
     }
     
     /// Check if file is new or has been modified since last scan
     async fn is_new_or_modified_file(&self, path: &Path) -> Result<bool> {
-        let metadata = fs::metadata(path).await?;
-        let modified_time = metadata.modified()?;
-        let modified_datetime: chrono::DateTime<chrono::Utc> = modified_time.into();
+        let modified_at = self.modified_time(path).await?;
+        let Some(processed) = self.state.get(path)? else {
+            return Ok(true); // New file
+        };
+        if modified_at <= processed.modified_at {
+            return Ok(false);
+        }
         
-        if let Some(last_processed) = self.processed_files.get(path) {
-            Ok(modified_datetime > *last_processed)
-        } else {
-            Ok(true) // New file
+        // A file touched without changing is not read again
+        if self.content_hash(path).await? == processed.content_hash {
+            self.state.record(&ProcessedFile { modified_at, ..processed })?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+    
+    /// Hash of a file's content and its sidecar, if any
+    async fn content_hash(&self, path: &Path) -> Result<String> {
+        let mut content = fs::read(path).await?;
+        if self.config.sidecars.enabled {
+            if let Ok(sidecar) = fs::read(self.config.sidecars.sidecar_path(path)).await {
+                content.extend(sidecar);
+            }
+        }
+        Ok(swarm_core::sha256_hex(&content))
+    }
+    
+    /// Modification time of a file, or of its sidecar if that changed later
+    async fn modified_time(&self, path: &Path) -> Result<chrono::DateTime<chrono::Utc>> {
+        let modified: chrono::DateTime<chrono::Utc> = fs::metadata(path).await?.modified()?.into();
+        if !self.config.sidecars.enabled {
+            return Ok(modified);
+        }
+        match fs::metadata(self.config.sidecars.sidecar_path(path)).await {
+            Ok(sidecar) => Ok(modified.max(sidecar.modified()?.into())),
+            Err(_) => Ok(modified),
+        }
+    }
+    
+    /// Merge the document's sidecar, if any, into its metadata
+    ///
+    /// A malformed sidecar does not fail the document; the error is recorded
+    /// under `sidecar_error` instead.
+    async fn merge_sidecar(&self, path: &Path, document: &mut Document) {
+        match self.config.sidecars.read(path).await {
+            Ok(Some(fields)) => {
+                let conflicts = merge_sidecar(&mut document.metadata, fields, self.config.sidecars.conflict_policy);
+                if !conflicts.is_empty() {
+                    tracing::debug!("Sidecar of {} conflicts on {:?} ({:?})", path.display(), conflicts, self.config.sidecars.conflict_policy);
+                }
+                let sidecar_path = self.config.sidecars.sidecar_path(path);
+                document.metadata.insert(SIDECAR_PATH_KEY.to_string(), serde_json::json!(sidecar_path.to_string_lossy()));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!("Ignoring sidecar of {}: {}", path.display(), e);
+                document.metadata.insert("sidecar_error".to_string(), serde_json::json!(e.to_string()));
+            }
         }
     }
     
@@ -164,44 +368,61 @@ impl SwarmDocumentReader {
         
         // Get file metadata
         let metadata = fs::metadata(path).await?;
-        let size_bytes = metadata.len() as usize;
+        let mut size_bytes = metadata.len() as usize;
         
         // Check file size
         if size_bytes > self.config.max_file_size {
-            return Err(DocumentError::ProcessingFailed {
-                reason: format!("File too large: {} bytes (max: {} bytes)", 
-                               size_bytes, self.config.max_file_size),
-            }.into());
+            let rejection = DocumentRejection::file_too_large(&filename, size_bytes, self.config.max_file_size);
+            return Err(DocumentError::Rejected(Box::new(rejection)).into());
         }
         
         let modified_time = metadata.modified()?;
         let modified_datetime: chrono::DateTime<chrono::Utc> = modified_time.into();
+        // A changed sidecar also makes the document count as modified
+        let tracked_time = self.modified_time(path).await?;
+        
+        // Convert formats we cannot read through an external converter
+        let conversion = self.converter.convert(path).await?;
         
         // Detect document type
-        let document_type = utils::detect_document_type_from_path(path);
+        let typed_path = match &conversion {
+            Some(conversion) => path.with_extension(&conversion.output_extension),
+            None => path.to_path_buf(),
+        };
+        let document_type = utils::detect_document_type_from_path(&typed_path);
         
         // Read file content based on type
-        let content = match document_type {
-            DocumentType::Text | DocumentType::Html | DocumentType::Markdown => {
+        let mut provenance = None;
+        let content = match (conversion, &document_type) {
+            (Some(conversion), DocumentType::Text | DocumentType::Html | DocumentType::Markdown) => {
+                size_bytes = conversion.bytes.len();
+                provenance = Some(conversion.provenance);
+                DocumentContent::Text(String::from_utf8_lossy(&conversion.bytes).into_owned())
+            }
+            (Some(conversion), _) => {
+                size_bytes = conversion.bytes.len();
+                provenance = Some(conversion.provenance);
+                DocumentContent::Binary(conversion.bytes)
+            }
+            (None, DocumentType::Text | DocumentType::Html | DocumentType::Markdown) => {
                 let text = fs::read_to_string(path).await?;
                 DocumentContent::Text(text)
             }
-            _ => {
+            (None, _) => {
                 let bytes = fs::read(path).await?;
                 DocumentContent::Binary(bytes)
             }
         };
         
-        // Create document
-        let mut document = Document {
-            id: Uuid::new_v4(),
-            filename,
-            document_type: document_type.clone(),
-            content,
-            metadata: HashMap::new(),
-            created_at: Utc::now(),
-            size_bytes,
-        };
+        // Create document, keyed by canonical path for deterministic IDs
+        let source = fs::canonicalize(path).await.unwrap_or_else(|_| path.to_path_buf());
+        let mut document = DocumentBuilder::new(filename, content)
+            .document_type(document_type.clone())
+            .source(source.to_string_lossy())
+            .id_strategy(self.config.id_strategy)
+            .created_at(self.clock.now())
+            .build();
+        document.size_bytes = size_bytes;
         
         // Add file system metadata
         document.metadata.insert("file_path".to_string(), serde_json::Value::String(path.to_string_lossy().to_string()));
@@ -211,20 +432,44 @@ impl SwarmDocumentReader {
             path.extension().and_then(|ext| ext.to_str()).unwrap_or("unknown").to_string()
         ));
         document.metadata.insert("mime_type".to_string(), serde_json::Value::String(
-            utils::get_mime_type(&document_type).to_string()
+            MimeRegistry::global().mime_type_for_path(&typed_path)
+        ));
+        document.metadata.insert(CONTENT_HASH_KEY.to_string(), serde_json::Value::String(
+            format!("sha256:{}", swarm_core::content_hash(&document.content))
         ));
+        if let Some(conversion) = provenance {
+            document.metadata.insert("provenance".to_string(), serde_json::json!({ "conversions": [conversion] }));
+        }
+        self.merge_sidecar(path, &mut document).await;
+        // Sidecars may name the tenant, so profiles are resolved after merging them
+        self.config.processing_profiles.apply(&mut document);
+        let decision = self.sampler.sample_document(&document);
+        decision.record(&mut document.metadata);
+        if TraceContext::from_metadata(&document.metadata).is_none() {
+            TraceContext::root(document.id, decision).record(&mut document.metadata);
+        }
         
         // Update processed files tracking
-        self.processed_files.insert(path.to_path_buf(), modified_datetime);
+        let processed = ProcessedFile {
+            path: path.to_path_buf(),
+            content_hash: self.content_hash(path).await?,
+            modified_at: tracked_time,
+            processed_at: Utc::now(),
+        };
+        if let Err(e) = self.state.record(&processed) {
+            tracing::warn!("Failed to record {} as read: {}", path.display(), e);
+        }
         
         // Update statistics
         self.stats.total_documents_read += 1;
         self.stats.last_read_time = Some(Utc::now());
+        let document_type = format!("{:?}", document.document_type).to_lowercase();
+        self.sli.observe_since(Sli::IngestLatency, tracked_time, &[("document_type", &document_type)]);
         
         Ok(document)
     }
     
-    /// Scan a single directory for documents
+    /// Scan a directory (and its subdirectories, if recursive) for documents
     async fn scan_directory(&mut self, directory: &Path) -> Result<Vec<Document>> {
         let mut documents = Vec::new();
         
@@ -233,50 +478,147 @@ impl SwarmDocumentReader {
             return Ok(documents);
         }
         
-        let mut entries = fs::read_dir(directory).await?;
+        let max_depth = if self.config.recursive_scan { self.config.max_scan_depth } else { 0 };
+        let listing = walk_directory(directory, max_depth, |path| self.matches_exclude_patterns(path)).await?;
+        self.stats.error_count += listing.errors;
         
-        while let Some(entry) = entries.next_entry().await? {
-            let path = entry.path();
-            
-            if path.is_file() {
-                if self.should_process_file(&path) {
-                    if self.is_new_or_modified_file(&path).await? {
-                        match self.read_document(&path).await {
-                            Ok(document) => {
-                                tracing::info!("Read document: {}", path.display());
-                                documents.push(document);
-                            }
-                            Err(e) => {
-                                tracing::error!("Failed to read document {}: {}", path.display(), e);
-                                self.stats.error_count += 1;
-                            }
+        for path in listing.files {
+            if self.should_process_file(&path) && self.is_new_or_modified_file(&path).await? {
+                match self.read_document(&path).await {
+                    Ok(mut document) => {
+                        tracing::info!("Read document: {}", path.display());
+                        let scan = TraceContext::continue_in(&mut document.metadata, "scan", document.id);
+                        scan.in_scope(|| {
+                            tracing::debug!(document_type = ?document.document_type, size_bytes = document.size_bytes, "Read {}", path.display());
+                        });
+                        documents.push(document);
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to read document {}: {}", path.display(), e);
+                        self.stats.error_count += 1;
+                        if let Some(rejection) = e.downcast_ref::<DocumentError>().and_then(DocumentError::rejection) {
+                            self.publish_rejection(rejection).await;
                         }
                     }
                 }
             }
-            // Note: Recursive scanning removed for simplicity
         }
         
         Ok(documents)
     }
     
+    /// Turn the reader into an endless stream of new and modified documents
+    ///
+    /// Directories are scanned immediately and then every `scan_interval_ms`
+    /// while no documents are buffered. Scan failures are yielded as errors
+    /// and scanning continues.
+    pub fn into_stream(self) -> impl Stream<Item = Result<Document>> + Send {
+        document_stream(self)
+    }
+    
+    /// Stream documents without giving up the reader (see `into_stream`)
+    pub fn stream(&mut self) -> impl Stream<Item = Result<Document>> + Send + '_ {
+        document_stream(self)
+    }
+    
     /// Scan all configured directories for new documents
+    ///
+    /// Members of a document group are only returned once the whole group
+    /// has been read, together.
     async fn scan_directories(&mut self) -> Result<Vec<Document>> {
         let mut all_documents = Vec::new();
         let directories = self.config.watch_directories.clone();
         
         for directory in directories {
-            let documents = self.scan_directory(&directory).await?;
-            all_documents.extend(documents);
+            for document in self.scan_directory(&directory).await? {
+                all_documents.extend(self.groups.add(document, Utc::now()));
+            }
+        }
+        
+        match self.state.prune_missing() {
+            Ok(pruned) => for path in pruned {
+                tracing::debug!("Forgot deleted file {}", path.display());
+            },
+            Err(e) => tracing::warn!("Failed to prune processing state: {}", e),
+        }
+        
+        for group in self.groups.expire(Utc::now()) {
+            let membership = &group.membership;
+            tracing::warn!("Document group {} incomplete: {} of {} documents arrived",
+                membership.group_id, group.documents.len(), membership.size);
+            for document in &group.documents {
+                self.stats.error_count += 1;
+                let rejection = DocumentRejection::incomplete_group(document, &membership.group_id, membership.size, group.documents.len());
+                self.publish_rejection(&rejection).await;
+            }
         }
         
         Ok(all_documents)
     }
 }
 
+fn document_stream<R>(reader: R) -> impl Stream<Item = Result<Document>> + Send
+where
+    R: BorrowMut<SwarmDocumentReader> + Send,
+{
+    let state = (reader, VecDeque::new(), true);
+    stream::unfold(state, |(mut reader, mut buffered, first_scan)| async move {
+        let mut first_scan = first_scan;
+        loop {
+            if let Some(document) = buffered.pop_front() {
+                return Some((Ok(document), (reader, buffered, first_scan)));
+            }
+            
+            if !first_scan {
+                sleep(Duration::from_millis(reader.borrow().config.scan_interval_ms)).await;
+            }
+            first_scan = false;
+            
+            match reader.borrow_mut().scan_directories().await {
+                Ok(documents) => buffered.extend(documents),
+                Err(e) => {
+                    reader.borrow_mut().stats.error_count += 1;
+                    return Some((Err(e), (reader, buffered, first_scan)));
+                }
+            }
+        }
+    })
+}
+
 #[async_trait]
 impl DocumentReader for SwarmDocumentReader {
     async fn start(&mut self) -> Result<()> {
+        if self.shutdown.is_cancelled() {
+            self.shutdown = CancellationToken::new();
+        }
+        let shutdown = self.shutdown.clone();
+        Service::run(self, shutdown).await
+    }
+    
+    async fn stop(&mut self) -> Result<()> {
+        tracing::info!("Stopping document reader...");
+        self.shutdown.cancel();
+        Ok(())
+    }
+    
+    async fn get_next_document(&mut self) -> Result<Option<Document>> {
+        let documents = self.scan_directories().await?;
+        Ok(documents.into_iter().next())
+    }
+    
+    async fn get_stats(&self) -> DocumentReaderStats {
+        self.stats.clone()
+    }
+}
+
+#[async_trait]
+impl Service for SwarmDocumentReader {
+    fn name(&self) -> &str {
+        "document-reader"
+    }
+    
+    /// Scan every `scan_interval_ms` until cancelled; a scan in progress finishes first
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
         tracing::info!("Starting document reader...");
         tracing::info!("Watching directories: {:?}", self.config.watch_directories);
         tracing::info!("Supported extensions: {:?}", self.config.supported_extensions);
@@ -284,16 +626,15 @@ impl DocumentReader for SwarmDocumentReader {
         
         self.is_running = true;
         
-        while self.is_running {
+        while !cancel.is_cancelled() {
             match self.scan_directories().await {
                 Ok(documents) => {
                     if !documents.is_empty() {
                         tracing::info!("Found {} new documents", documents.len());
-                        // In a real implementation, these documents would be sent to a queue or processor
-                        // For now, we just log them
                         for doc in documents {
                             tracing::info!("Document: {} ({:?}, {} bytes)", 
                                          doc.filename, doc.document_type, doc.size_bytes);
+                            self.publish(doc).await;
                         }
                     }
                 }
@@ -303,26 +644,14 @@ impl DocumentReader for SwarmDocumentReader {
                 }
             }
             
-            sleep(Duration::from_millis(self.config.scan_interval_ms)).await;
+            self.apply_config_updates();
+            cancellable_sleep(&cancel, Duration::from_millis(self.config.scan_interval_ms)).await;
         }
         
-        Ok(())
-    }
-    
-    async fn stop(&mut self) -> Result<()> {
-        tracing::info!("Stopping document reader...");
         self.is_running = false;
+        tracing::info!("Document reader stopped");
         Ok(())
     }
-    
-    async fn get_next_document(&mut self) -> Result<Option<Document>> {
-        let documents = self.scan_directories().await?;
-        Ok(documents.into_iter().next())
-    }
-    
-    async fn get_stats(&self) -> DocumentReaderStats {
-        self.stats.clone()
-    }
 }
 
 #[cfg(test)]
@@ -339,8 +668,16 @@ mod tests {
             scan_interval_ms: 100,
             batch_size: 5,
             recursive_scan: true,
+            max_scan_depth: 2,
             include_patterns: vec!["*".to_string()],
             exclude_patterns: vec![".*".to_string()],
+            id_strategy: DocumentIdStrategy::Random,
+            converters: HashMap::new(),
+            tracing_sampling: SamplingConfig::default(),
+            sidecars: SidecarConfig::default(),
+            processing_profiles: ProcessingProfiles::default(),
+            group_assembly_timeout_ms: 60_000,
+            state_db_path: None,
         }
     }
     
@@ -349,11 +686,68 @@ mod tests {
         let config = create_test_config();
         let reader = SwarmDocumentReader::new(config);
         
-        assert_eq!(reader.processed_files.len(), 0);
+        assert_eq!(reader.processing_state().entries().unwrap().len(), 0);
         assert!(!reader.is_running);
         assert_eq!(reader.stats().total_documents_read, 0);
     }
     
+    #[tokio::test]
+    async fn test_start_returns_when_shut_down() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = create_test_config();
+        config.watch_directories = vec![temp_dir.path().to_path_buf()];
+        config.scan_interval_ms = 60_000;
+        let mut reader = SwarmDocumentReader::new(config);
+        let shutdown = reader.shutdown_token();
+        
+        let run = tokio::spawn(async move {
+            reader.start().await.map(|_| reader)
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        shutdown.cancel();
+        
+        let reader = tokio::time::timeout(Duration::from_secs(1), run).await.unwrap().unwrap().unwrap();
+        assert!(!reader.is_running);
+        assert!(reader.shutdown_token().is_cancelled());
+    }
+    
+    #[tokio::test]
+    async fn test_run_publishes_documents_to_broker() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "Published content").unwrap();
+        fs::write(temp_dir.path().join("huge.txt"), "x".repeat(128)).unwrap();
+        let mut config = create_test_config();
+        config.watch_directories = vec![temp_dir.path().to_path_buf()];
+        config.max_file_size = 64;
+        
+        let broker = Arc::new(swarm_core::MemoryBroker::new());
+        let mut subscription = broker.subscribe("documents.discovered").await.unwrap();
+        let mut rejections = broker.subscribe(REJECTION_SUBJECT).await.unwrap();
+        let mut reader = SwarmDocumentReader::new(config).with_broker(broker, "documents.discovered");
+        let cancel = CancellationToken::new();
+        let run = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { Service::run(&mut reader, cancel).await }
+        });
+        
+        let message = loop {
+            if let Some(message) = subscription.next_message().unwrap() {
+                break message;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        cancel.cancel();
+        run.await.unwrap().unwrap();
+        
+        let document: Document = serde_json::from_slice(&message.payload).unwrap();
+        assert_eq!(document.filename, "notes.txt");
+        
+        let rejection: DocumentRejection = serde_json::from_slice(&rejections.next_message().unwrap().unwrap().payload).unwrap();
+        assert_eq!(rejection.filename, "huge.txt");
+        assert_eq!(rejection.reason, swarm_core::RejectionReason::TooLarge);
+        assert_eq!(rejection.limit.unwrap().actual, 128);
+    }
+    
     #[test]
     fn test_supported_file_detection() {
         let config = create_test_config();
@@ -409,10 +803,29 @@ mod tests {
         let document = reader.read_document(&test_file).await.unwrap();
         assert_eq!(document.filename, "test.txt");
         assert_eq!(document.document_type, DocumentType::Text);
-        assert_eq!(document.size_bytes, 24);
+        assert_eq!(document.size_bytes, 23);
         assert!(document.metadata.contains_key("file_path"));
         assert!(document.metadata.contains_key("file_size"));
         assert!(document.metadata.contains_key("mime_type"));
+        assert_eq!(document.metadata[CONTENT_HASH_KEY], format!("sha256:{}", swarm_core::sha256_hex(b"This is a test document")));
+    }
+    
+    #[tokio::test]
+    async fn test_deterministic_document_ids() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("test.txt");
+        fs::write(&test_file, "Same content every time").unwrap();
+        
+        let mut config = create_test_config();
+        config.id_strategy = DocumentIdStrategy::Deterministic;
+        
+        let first = SwarmDocumentReader::new(config.clone()).read_document(&test_file).await.unwrap();
+        let second = SwarmDocumentReader::new(config.clone()).read_document(&test_file).await.unwrap();
+        assert_eq!(first.id, second.id);
+        
+        fs::write(&test_file, "Different content").unwrap();
+        let changed = SwarmDocumentReader::new(config).read_document(&test_file).await.unwrap();
+        assert_ne!(first.id, changed.id);
     }
     
     #[tokio::test]
@@ -441,6 +854,80 @@ mod tests {
     }
     
     #[tokio::test]
+    async fn test_processing_state_survives_restart() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("test.txt");
+        fs::write(&test_file, "Test content").unwrap();
+        
+        let mut config = create_test_config();
+        config.watch_directories = vec![temp_dir.path().to_path_buf()];
+        let state = Arc::new(SledStateStore::temporary().unwrap());
+        
+        {
+            let mut reader = SwarmDocumentReader::new(config.clone()).with_state_store(state.clone());
+            assert_eq!(reader.scan_directories().await.unwrap().len(), 1);
+        }
+        
+        // A restarted reader skips the file, also when it is touched without changing
+        let mut reader = SwarmDocumentReader::new(config).with_state_store(state);
+        assert!(reader.scan_directories().await.unwrap().is_empty());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        fs::write(&test_file, "Test content").unwrap();
+        assert!(reader.scan_directories().await.unwrap().is_empty());
+        
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        fs::write(&test_file, "Changed content").unwrap();
+        assert_eq!(reader.scan_directories().await.unwrap().len(), 1);
+        
+        fs::remove_file(&test_file).unwrap();
+        assert!(reader.scan_directories().await.unwrap().is_empty());
+        assert!(reader.processing_state().entries().unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_recursive_directory_scanning() {
+        let temp_dir = tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join("2024/q1/drafts")).unwrap();
+        fs::create_dir_all(temp_dir.path().join(".git")).unwrap();
+        fs::write(temp_dir.path().join("index.md"), "# Index").unwrap();
+        fs::write(temp_dir.path().join("2024/summary.txt"), "Summary").unwrap();
+        fs::write(temp_dir.path().join("2024/q1/report.txt"), "Report").unwrap();
+        fs::write(temp_dir.path().join("2024/q1/drafts/draft.txt"), "Below max depth").unwrap();
+        fs::write(temp_dir.path().join(".git/notes.txt"), "Excluded directory").unwrap();
+        
+        let mut reader = SwarmDocumentReader::new(create_test_config());
+        let documents = reader.scan_directory(temp_dir.path()).await.unwrap();
+        let mut filenames: Vec<&str> = documents.iter().map(|d| d.filename.as_str()).collect();
+        filenames.sort();
+        assert_eq!(filenames, vec!["index.md", "report.txt", "summary.txt"]);
+        
+        // Files found in subdirectories are not read again
+        assert!(reader.scan_directory(temp_dir.path()).await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_glob_patterns_on_nested_paths() {
+        let temp_dir = tempdir().unwrap();
+        for file in ["notes/a.md", "notes/b.txt", "reports/2024-06.txt", "reports/2024-6.txt", "archive/old.md", "archive/keep.md"] {
+            let path = temp_dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "Content").unwrap();
+        }
+        
+        let mut config = create_test_config();
+        config.watch_directories = vec![temp_dir.path().to_path_buf()];
+        config.include_patterns = vec!["**/*.md".to_string(), "reports/2024-??.txt".to_string()];
+        config.exclude_patterns = vec![".*".to_string(), "archive/**".to_string(), "!archive/keep.md".to_string()];
+        
+        let mut reader = SwarmDocumentReader::new(config);
+        let documents = reader.scan_directory(temp_dir.path()).await.unwrap();
+        let mut filenames: Vec<&str> = documents.iter().map(|d| d.filename.as_str()).collect();
+        filenames.sort();
+        assert_eq!(filenames, vec!["2024-06.txt", "a.md", "keep.md"]);
+    }
+    
+    #[tokio::test]
+    #[allow(deprecated)]
     async fn test_get_next_document() {
         // Create a temporary directory with a test file
         let temp_dir = tempdir().unwrap();
@@ -462,6 +949,141 @@ mod tests {
         assert!(document.is_none());
     }
     
+    #[tokio::test]
+    async fn test_document_stream_picks_up_new_files() {
+        use futures::StreamExt;
+        
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("first.txt"), "First").unwrap();
+        
+        let mut config = create_test_config();
+        config.watch_directories = vec![temp_dir.path().to_path_buf()];
+        config.scan_interval_ms = 10;
+        let mut documents = Box::pin(SwarmDocumentReader::new(config).into_stream());
+        
+        assert_eq!(documents.next().await.unwrap().unwrap().filename, "first.txt");
+        
+        fs::write(temp_dir.path().join("second.md"), "# Second").unwrap();
+        let next = tokio::time::timeout(Duration::from_secs(5), documents.next()).await.unwrap();
+        assert_eq!(next.unwrap().unwrap().filename, "second.md");
+    }
+    
+    #[tokio::test]
+    async fn test_document_stream_into_processor() {
+        use futures::StreamExt;
+        
+        let temp_dir = tempdir().unwrap();
+        for i in 0..4 {
+            fs::write(temp_dir.path().join(format!("doc{}.txt", i)), format!("Document number {}", i)).unwrap();
+        }
+        
+        let mut config = create_test_config();
+        config.watch_directories = vec![temp_dir.path().to_path_buf()];
+        let mut reader = SwarmDocumentReader::new(config);
+        let processor = SwarmDocumentProcessor::new(DocumentProcessingConfig::default());
+        
+        let results: Vec<_> = reader.stream()
+            .take(4)
+            .map(|document| async { processor.process_document(&document?).await })
+            .buffer_unordered(2)
+            .collect()
+            .await;
+        
+        assert_eq!(results.len(), 4);
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(reader.stats().total_documents_read, 4);
+    }
+    
+    #[tokio::test]
+    async fn test_sidecar_metadata_is_merged_and_not_ingested() {
+        let temp_dir = tempdir().unwrap();
+        let report = temp_dir.path().join("report.txt");
+        fs::write(&report, "Quarterly report").unwrap();
+        fs::write(temp_dir.path().join("report.txt.meta.json"), r#"{"customer": "Acme", "file_size": 1}"#).unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "Notes").unwrap();
+        fs::write(temp_dir.path().join("notes.txt.meta.json"), "[1, 2]").unwrap();
+        
+        let mut config = create_test_config();
+        config.watch_directories = vec![temp_dir.path().to_path_buf()];
+        // Sidecars are skipped even when their extension is supported
+        config.supported_extensions.push("json".to_string());
+        let mut reader = SwarmDocumentReader::new(config);
+        
+        let mut documents = reader.scan_directory(temp_dir.path()).await.unwrap();
+        documents.sort_by(|a, b| a.filename.cmp(&b.filename));
+        let filenames: Vec<&str> = documents.iter().map(|d| d.filename.as_str()).collect();
+        assert_eq!(filenames, vec!["notes.txt", "report.txt"]);
+        
+        let report_document = &documents[1];
+        assert_eq!(report_document.metadata["customer"], "Acme");
+        assert_eq!(report_document.metadata["file_size"], 16, "the reader's own fields win by default");
+        assert!(report_document.metadata.contains_key(SIDECAR_PATH_KEY));
+        assert!(documents[0].metadata["sidecar_error"].as_str().unwrap().contains("not a JSON object"));
+        
+        // Changing only the sidecar makes the document count as modified
+        assert!(reader.scan_directory(temp_dir.path()).await.unwrap().is_empty());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        fs::write(temp_dir.path().join("report.txt.meta.json"), r#"{"customer": "Globex"}"#).unwrap();
+        let rescanned = reader.scan_directory(temp_dir.path()).await.unwrap();
+        assert_eq!(rescanned.len(), 1);
+        assert_eq!(rescanned[0].metadata["customer"], "Globex");
+    }
+    
+    #[tokio::test]
+    async fn test_tenant_profile_is_resolved_at_ingestion() {
+        let temp_dir = tempdir().unwrap();
+        let report = temp_dir.path().join("report.txt");
+        fs::write(&report, "Quarterly report").unwrap();
+        fs::write(temp_dir.path().join("report.txt.meta.json"), r#"{"tenant": "acme"}"#).unwrap();
+        
+        let mut config = create_test_config();
+        config.processing_profiles = ProcessingProfiles {
+            profiles: HashMap::from([("semantic".to_string(), DocumentProcessingOptions {
+                generate_embeddings: true,
+                ..DocumentProcessingOptions::default()
+            })]),
+            tenants: HashMap::from([("acme".to_string(), "semantic".to_string())]),
+            default_profile: None,
+        };
+        let mut reader = SwarmDocumentReader::new(config);
+        
+        let document = reader.read_document(&report).await.unwrap();
+        assert_eq!(document.metadata[swarm_core::PROCESSING_PROFILE_KEY], "semantic");
+        assert!(swarm_core::effective_options(&document).unwrap().generate_embeddings);
+        assert_eq!(document.metadata["provenance"]["processing_profile"]["tenant"], "acme");
+    }
+    
+    #[tokio::test]
+    async fn test_document_group_is_released_when_complete() {
+        let temp_dir = tempdir().unwrap();
+        let group = r#"{"document_group": "invoice-17", "group_size": 2}"#;
+        fs::write(temp_dir.path().join("invoice.txt"), "Invoice").unwrap();
+        fs::write(temp_dir.path().join("invoice.txt.meta.json"), group).unwrap();
+        
+        let mut config = create_test_config();
+        config.watch_directories = vec![temp_dir.path().to_path_buf()];
+        let mut reader = SwarmDocumentReader::new(config.clone());
+        assert!(reader.scan_directories().await.unwrap().is_empty());
+        
+        fs::write(temp_dir.path().join("receipt.txt"), "Receipt").unwrap();
+        fs::write(temp_dir.path().join("receipt.txt.meta.json"), group).unwrap();
+        let mut documents = reader.scan_directories().await.unwrap();
+        documents.sort_by(|a, b| a.filename.cmp(&b.filename));
+        assert_eq!(documents.iter().map(|d| d.filename.as_str()).collect::<Vec<_>>(), vec!["invoice.txt", "receipt.txt"]);
+        
+        // A group whose other members never arrive is rejected
+        let incomplete_dir = tempdir().unwrap();
+        fs::write(incomplete_dir.path().join("invoice.txt"), "Invoice").unwrap();
+        fs::write(incomplete_dir.path().join("invoice.txt.meta.json"), r#"{"document_group": "invoice-18", "group_size": 3}"#).unwrap();
+        config.watch_directories = vec![incomplete_dir.path().to_path_buf()];
+        config.group_assembly_timeout_ms = 0;
+        let mut reader = SwarmDocumentReader::new(config);
+        assert!(reader.scan_directories().await.unwrap().is_empty());
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(reader.scan_directories().await.unwrap().is_empty());
+        assert_eq!(reader.stats().error_count, 1);
+    }
+    
     #[tokio::test]
     async fn test_statistics_tracking() {
         // Create a temporary directory with test files
@@ -475,7 +1097,8 @@ mod tests {
         let mut config = create_test_config();
         config.watch_directories = vec![temp_dir.path().to_path_buf()];
         
-        let mut reader = SwarmDocumentReader::new(config);
+        let metrics = Arc::new(swarm_core::MemoryMetricsCollector::new());
+        let mut reader = SwarmDocumentReader::new(config).with_metrics(metrics.clone());
         
         // Read documents
         let _doc1 = reader.read_document(&test_file1).await.unwrap();
@@ -485,5 +1108,29 @@ mod tests {
         assert_eq!(stats.total_documents_read, 2);
         assert!(stats.last_read_time.is_some());
         assert_eq!(stats.error_count, 0);
+        assert_eq!(metrics.histogram(Sli::IngestLatency.name()).len(), 2);
+        assert_eq!(metrics.counter(swarm_core::DOCUMENTS_INGESTED_COUNTER), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_unsupported_extension_is_converted() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("letter.rtf");
+        fs::write(&test_file, "Converted letter").unwrap();
+        
+        let mut config = create_test_config();
+        config.converters.insert("rtf".to_string(), ExternalConverterConfig {
+            kind: ConverterKind::Command { program: "cat".to_string(), args: vec!["{input}".to_string()] },
+            output_extension: "txt".to_string(),
+            timeout_ms: 5000,
+        });
+        let mut reader = SwarmDocumentReader::new(config);
+        assert!(reader.should_process_file(&test_file));
+        
+        let document = reader.read_document(&test_file).await.unwrap();
+        assert_eq!(document.document_type, DocumentType::Text);
+        assert_eq!(document.content, DocumentContent::Text("Converted letter".to_string()));
+        assert_eq!(document.metadata["mime_type"], "text/plain");
+        assert_eq!(document.metadata["provenance"]["conversions"][0]["from_extension"], "rtf");
     }
 }
@@ -0,0 +1,630 @@
+//! Email Document Source
+//!
+//! Ingests the messages of an IMAP mailbox. Every poll logs in, selects the
+//! mailbox and fetches the messages that do not carry the processed flag
+//! yet (`\Seen` by default, i.e. unread mail). The body of a message becomes
+//! an Html or Text document and each attachment a document of its own that
+//! names the message document as its parent. A message with attachments is
+//! published as a document group, so the message and its attachments are
+//! processed together. Once its documents are published, a message is
+//! flagged as processed; messages that cannot be parsed are flagged too, so
+//! they are not fetched again and again. The documents of a message that
+//! could not all be published, or not be flagged, are kept, and the next
+//! poll delivers just what is missing instead of reading it again. A message
+//! that fails to fetch is skipped for this poll.
+//!
+//! The IMAP client speaks just the commands the source needs (`LOGIN`,
+//! `SELECT`, `UID SEARCH`, `UID FETCH`, `UID STORE`, `LOGOUT`), over TLS or,
+//! for local test servers, plain TCP.
+
+use super::*;
+use crate::credentials::Secret;
+use async_trait::async_trait;
+use mail_parser::{MessageParser, MimeHeaders};
+use std::sync::Arc;
+use std::time::Duration;
+use swarm_core::{cancellable_sleep, CancellationToken, GroupMembership, Service, REJECTION_SUBJECT};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Document metadata key holding the `Message-ID` of the email a document came from
+pub const EMAIL_MESSAGE_ID_KEY: &str = "email_message_id";
+
+/// Document metadata key holding the subject of the email a document came from
+pub const EMAIL_SUBJECT_KEY: &str = "email_subject";
+
+/// Document metadata key holding the sender of the email a document came from
+pub const EMAIL_FROM_KEY: &str = "email_from";
+
+/// Attachment metadata key holding the ID of the document of the message body
+pub const EMAIL_PARENT_KEY: &str = "email_parent";
+
+/// Attachment metadata key holding the position of the attachment in its message
+pub const ATTACHMENT_INDEX_KEY: &str = "attachment_index";
+
+/// Mailbox to ingest and how to reach it
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct EmailSourceConfig {
+    pub host: String,
+    
+    #[serde(default = "default_port")]
+    pub port: u16,
+    
+    /// Connect with TLS (IMAPS); plain TCP is only meant for local servers
+    #[serde(default = "default_tls")]
+    pub tls: bool,
+    
+    pub username: String,
+    pub password: Secret,
+    
+    #[serde(default = "default_mailbox")]
+    pub mailbox: String,
+    
+    /// Flag set on processed messages; messages without it are fetched
+    #[serde(default = "default_processed_flag")]
+    pub processed_flag: String,
+    
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    
+    /// Messages fetched per poll at most
+    #[serde(default = "default_max_messages_per_poll")]
+    pub max_messages_per_poll: usize,
+    
+    /// Larger attachments are rejected instead of ingested
+    #[serde(default = "default_max_attachment_size")]
+    pub max_attachment_size: usize,
+    
+    #[serde(default)]
+    pub id_strategy: DocumentIdStrategy,
+}
+
+fn default_port() -> u16 {
+    993
+}
+
+fn default_tls() -> bool {
+    true
+}
+
+fn default_mailbox() -> String {
+    "INBOX".to_string()
+}
+
+fn default_processed_flag() -> String {
+    "\\Seen".to_string()
+}
+
+fn default_poll_interval_ms() -> u64 {
+    60_000
+}
+
+fn default_max_messages_per_poll() -> usize {
+    50
+}
+
+fn default_max_attachment_size() -> usize {
+    50 * 1024 * 1024
+}
+
+impl EmailSourceConfig {
+    /// `UID SEARCH` criteria matching messages that were not processed yet
+    fn unprocessed_criteria(&self) -> String {
+        match self.processed_flag.strip_prefix('\\') {
+            Some(system_flag) => format!("UN{}", system_flag.to_uppercase()),
+            None => format!("UNKEYWORD {}", self.processed_flag),
+        }
+    }
+}
+
+/// The documents of an email: the message body first, then its attachments
+///
+/// Attachments larger than `max_attachment_size` are returned as rejections.
+pub fn email_documents(raw: &[u8], source: &str, max_attachment_size: usize, id_strategy: DocumentIdStrategy) -> Result<(Vec<Document>, Vec<DocumentRejection>)> {
+    let message = MessageParser::default().parse(raw).ok_or_else(|| anyhow::anyhow!("{} is not a parseable email", source))?;
+    let message_id = message.message_id().map(str::to_string);
+    let subject = message.subject().unwrap_or_default().to_string();
+    let from = message.from().and_then(|from| from.first()).and_then(|from| from.address.as_deref()).map(str::to_string);
+    
+    let mut headers = HashMap::new();
+    if let Some(message_id) = &message_id {
+        headers.insert(EMAIL_MESSAGE_ID_KEY.to_string(), serde_json::json!(message_id));
+    }
+    headers.insert(EMAIL_SUBJECT_KEY.to_string(), serde_json::json!(subject));
+    if let Some(from) = &from {
+        headers.insert(EMAIL_FROM_KEY.to_string(), serde_json::json!(from));
+    }
+    if let Some(date) = message.date() {
+        headers.insert("email_date".to_string(), serde_json::json!(date.to_rfc3339()));
+    }
+    
+    // mail-parser renders text-only mail as HTML too, so only a real HTML part counts
+    let html = message.html_body.iter().any(|part| message.parts.get(*part).is_some_and(|part| part.is_text_html()));
+    let (body, document_type, extension) = match (html, message.body_html(0), message.body_text(0)) {
+        (true, Some(html), _) => (html.into_owned(), DocumentType::Html, "html"),
+        (_, _, Some(text)) => (text.into_owned(), DocumentType::Text, "txt"),
+        _ => (String::new(), DocumentType::Text, "txt"),
+    };
+    let stem = if subject.trim().is_empty() { "message".to_string() } else { subject.replace(['/', '\\'], "_") };
+    let source = message_id.as_ref().map_or_else(|| source.to_string(), |message_id| format!("email:{}", message_id));
+    let parent = swarm_core::DocumentBuilder::new(format!("{}.{}", stem, extension), DocumentContent::Text(body))
+        .document_type(document_type)
+        .source(source.clone())
+        .id_strategy(id_strategy)
+        .with_metadata(headers.clone())
+        .metadata("mime_type", serde_json::json!(if html { "text/html" } else { "text/plain" }))
+        .build();
+    
+    let mut documents = vec![parent];
+    let mut rejections = Vec::new();
+    for (index, attachment) in message.attachments().enumerate() {
+        let filename = attachment.attachment_name().map_or_else(|| format!("attachment-{}", index + 1), str::to_string);
+        let bytes = attachment.contents();
+        if bytes.len() > max_attachment_size {
+            rejections.push(DocumentRejection::file_too_large(&filename, bytes.len(), max_attachment_size));
+            continue;
+        }
+        let detection = sniff_document_type(Path::new(&filename), &bytes[..bytes.len().min(SNIFF_LENGTH)]);
+        let content = match detection.document_type {
+            DocumentType::Text | DocumentType::Html | DocumentType::Markdown => {
+                DocumentContent::Text(String::from_utf8_lossy(bytes).into_owned())
+            }
+            _ => DocumentContent::Binary(bytes.to_vec()),
+        };
+        let hash = format!("sha256:{}", swarm_core::content_hash(&content));
+        documents.push(swarm_core::DocumentBuilder::new(filename, content)
+            .document_type(detection.document_type)
+            .source(format!("{}#{}", source, index))
+            .id_strategy(id_strategy)
+            .with_metadata(headers.clone())
+            .metadata(EMAIL_PARENT_KEY, serde_json::json!(documents[0].id.to_string()))
+            .metadata(ATTACHMENT_INDEX_KEY, serde_json::json!(index))
+            .metadata("mime_type", serde_json::json!(detection.mime_type))
+            .metadata(CONTENT_HASH_KEY, serde_json::json!(hash))
+            .build());
+    }
+    
+    if documents.len() > 1 {
+        let membership = GroupMembership::new(format!("email-{}", documents[0].id), documents.len());
+        for document in &mut documents {
+            membership.insert_into(&mut document.metadata);
+        }
+    }
+    Ok((documents, rejections))
+}
+
+trait ImapStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> ImapStream for S {}
+
+/// A response line with the literals it carried
+struct ImapLine {
+    text: String,
+    literals: Vec<Vec<u8>>,
+}
+
+/// The few IMAP commands the source needs
+struct ImapSession {
+    stream: BufReader<Box<dyn ImapStream>>,
+    next_tag: u32,
+}
+
+impl ImapSession {
+    async fn connect(config: &EmailSourceConfig) -> Result<Self> {
+        let tcp = TcpStream::connect((config.host.as_str(), config.port)).await?;
+        let stream: Box<dyn ImapStream> = if config.tls {
+            let connector = tokio_native_tls::TlsConnector::from(tokio_native_tls::native_tls::TlsConnector::new()?);
+            Box::new(connector.connect(&config.host, tcp).await?)
+        } else {
+            Box::new(tcp)
+        };
+        let mut session = Self { stream: BufReader::new(stream), next_tag: 1 };
+        let greeting = session.read_line().await?;
+        if !greeting.text.starts_with("* OK") && !greeting.text.starts_with("* PREAUTH") {
+            anyhow::bail!("unexpected IMAP greeting: {}", greeting.text);
+        }
+        Ok(session)
+    }
+    
+    /// Read a response line, including any literals (`{n}`) it contains
+    async fn read_line(&mut self) -> Result<ImapLine> {
+        let mut line = ImapLine { text: String::new(), literals: Vec::new() };
+        loop {
+            let mut chunk = Vec::new();
+            if self.stream.read_until(b'\n', &mut chunk).await? == 0 {
+                anyhow::bail!("IMAP server closed the connection");
+            }
+            let chunk = String::from_utf8_lossy(&chunk).trim_end_matches(['\r', '\n']).to_string();
+            let literal = chunk.strip_suffix('}')
+                .and_then(|head| head.rsplit_once('{'))
+                .and_then(|(_, length)| length.parse::<usize>().ok());
+            line.text.push_str(&chunk);
+            let Some(length) = literal else {
+                return Ok(line);
+            };
+            let mut bytes = vec![0; length];
+            self.stream.read_exact(&mut bytes).await?;
+            line.literals.push(bytes);
+        }
+    }
+    
+    /// Send a command, returning its untagged responses once it completed
+    async fn command(&mut self, command: &str) -> Result<Vec<ImapLine>> {
+        let tag = format!("a{}", self.next_tag);
+        self.next_tag += 1;
+        self.stream.get_mut().write_all(format!("{} {}\r\n", tag, command).as_bytes()).await?;
+        self.stream.get_mut().flush().await?;
+        
+        let mut untagged = Vec::new();
+        loop {
+            let line = self.read_line().await?;
+            let Some(status) = line.text.strip_prefix(&format!("{} ", tag)) else {
+                untagged.push(line);
+                continue;
+            };
+            if !status.starts_with("OK") {
+                let verb = command.split_whitespace().take(2).collect::<Vec<_>>().join(" ");
+                anyhow::bail!("IMAP {} failed: {}", verb, status);
+            }
+            return Ok(untagged);
+        }
+    }
+    
+    async fn login(&mut self, username: &str, password: &Secret) -> Result<()> {
+        self.command(&format!("LOGIN {} {}", quote(username), quote(password.expose()))).await?;
+        Ok(())
+    }
+    
+    async fn select(&mut self, mailbox: &str) -> Result<()> {
+        self.command(&format!("SELECT {}", quote(mailbox))).await?;
+        Ok(())
+    }
+    
+    async fn search(&mut self, criteria: &str) -> Result<Vec<u32>> {
+        let mut uids: Vec<u32> = self.command(&format!("UID SEARCH {}", criteria)).await?.iter()
+            .filter_map(|line| line.text.strip_prefix("* SEARCH"))
+            .flat_map(|uids| uids.split_whitespace().filter_map(|uid| uid.parse().ok()).collect::<Vec<_>>())
+            .collect();
+        uids.sort_unstable();
+        Ok(uids)
+    }
+    
+    /// The raw message, fetched without setting `\Seen`
+    async fn fetch(&mut self, uid: u32) -> Result<Vec<u8>> {
+        self.command(&format!("UID FETCH {} BODY.PEEK[]", uid)).await?
+            .into_iter()
+            .find_map(|line| line.literals.into_iter().next())
+            .ok_or_else(|| anyhow::anyhow!("message {} was not returned", uid))
+    }
+    
+    async fn add_flag(&mut self, uid: u32, flag: &str) -> Result<()> {
+        self.command(&format!("UID STORE {} +FLAGS.SILENT ({})", uid, flag)).await?;
+        Ok(())
+    }
+    
+    async fn logout(&mut self) -> Result<()> {
+        self.command("LOGOUT").await?;
+        Ok(())
+    }
+}
+
+/// Quote a string argument
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Polls a mailbox and publishes its messages as documents
+pub struct EmailDocumentSource {
+    config: EmailSourceConfig,
+    publisher: Option<(Arc<dyn MessageBroker>, String)>,
+    stats: DocumentReaderStats,
+    /// Messages read but not fully delivered, by UID: the documents still
+    /// to publish, or none when only the processed flag is missing
+    undelivered: HashMap<u32, Vec<Document>>,
+}
+
+impl EmailDocumentSource {
+    pub fn new(config: EmailSourceConfig) -> Self {
+        Self {
+            config,
+            publisher: None,
+            stats: DocumentReaderStats {
+                total_documents_read: 0,
+                documents_per_second: 0.0,
+                error_count: 0,
+                last_read_time: None,
+            },
+            undelivered: HashMap::new(),
+        }
+    }
+    
+    /// Publish every document read to `subject`, as JSON
+    ///
+    /// Attachments that are rejected are reported on [`REJECTION_SUBJECT`].
+    pub fn with_broker(mut self, broker: Arc<dyn MessageBroker>, subject: impl Into<String>) -> Self {
+        self.publisher = Some((broker, subject.into()));
+        self
+    }
+    
+    pub fn config(&self) -> &EmailSourceConfig {
+        &self.config
+    }
+    
+    pub fn stats(&self) -> &DocumentReaderStats {
+        &self.stats
+    }
+    
+    /// Read the unprocessed messages, publish their documents and flag them as processed
+    ///
+    /// Returns the documents published by this poll. The session is logged
+    /// out whether or not the poll succeeded.
+    pub async fn poll(&mut self) -> Result<Vec<Document>> {
+        let mut session = ImapSession::connect(&self.config).await?;
+        let read = self.poll_session(&mut session).await;
+        if let Err(e) = session.logout().await {
+            tracing::warn!("Failed to log out of {}: {}", self.config.host, e);
+        }
+        read
+    }
+    
+    async fn poll_session(&mut self, session: &mut ImapSession) -> Result<Vec<Document>> {
+        session.login(&self.config.username, &self.config.password).await?;
+        session.select(&self.config.mailbox).await?;
+        let uids = session.search(&self.config.unprocessed_criteria()).await?;
+        
+        let mut read = Vec::new();
+        for uid in uids.into_iter().take(self.config.max_messages_per_poll) {
+            let source = format!("imap://{}/{}/{}", self.config.host, self.config.mailbox, uid);
+            let documents = match self.undelivered.remove(&uid) {
+                Some(documents) => documents,
+                None => match session.fetch(uid).await {
+                    Ok(raw) => self.read_message(&raw, &source).await,
+                    Err(e) => {
+                        tracing::error!("Failed to fetch email {}: {}", source, e);
+                        self.stats.error_count += 1;
+                        continue;
+                    }
+                },
+            };
+            
+            // Documents are published in order; the first that fails and
+            // those after it are kept for the next poll
+            let mut unpublished = Vec::new();
+            for document in documents {
+                if unpublished.is_empty() && self.publish_document(&document).await {
+                    self.stats.total_documents_read += 1;
+                    self.stats.last_read_time = Some(Utc::now());
+                    read.push(document);
+                } else {
+                    unpublished.push(document);
+                }
+            }
+            if !unpublished.is_empty() {
+                self.undelivered.insert(uid, unpublished);
+                continue;
+            }
+            if let Err(e) = session.add_flag(uid, &self.config.processed_flag).await {
+                tracing::error!("Failed to flag email {} as processed: {}", source, e);
+                self.stats.error_count += 1;
+                self.undelivered.insert(uid, Vec::new());
+            }
+        }
+        Ok(read)
+    }
+    
+    /// The documents of a fetched message, reporting its rejected attachments;
+    /// none when it cannot be parsed, so it is flagged and not read again
+    async fn read_message(&mut self, raw: &[u8], source: &str) -> Vec<Document> {
+        match email_documents(raw, source, self.config.max_attachment_size, self.config.id_strategy) {
+            Ok((documents, rejections)) => {
+                tracing::info!("Read email {} with {} document(s)", source, documents.len());
+                for rejection in &rejections {
+                    self.stats.error_count += 1;
+                    self.publish_json(REJECTION_SUBJECT, rejection).await;
+                }
+                documents
+            }
+            Err(e) => {
+                tracing::error!("Skipping email {}: {}", source, e);
+                self.stats.error_count += 1;
+                Vec::new()
+            }
+        }
+    }
+    
+    /// Publish a document to the documents subject; without a broker there
+    /// is nothing to deliver to
+    async fn publish_document(&mut self, document: &Document) -> bool {
+        let Some((_, subject)) = &self.publisher else {
+            return true;
+        };
+        let subject = subject.clone();
+        self.publish_json(&subject, document).await
+    }
+    
+    async fn publish_json(&mut self, subject: &str, payload: &impl serde::Serialize) -> bool {
+        let Some((broker, _)) = &self.publisher else {
+            return false;
+        };
+        let published = match serde_json::to_vec(payload) {
+            Ok(payload) => broker.publish(subject, &payload).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = published {
+            tracing::error!("Failed to publish to {}: {}", subject, e);
+            self.stats.error_count += 1;
+            return false;
+        }
+        true
+    }
+}
+
+#[async_trait]
+impl Service for EmailDocumentSource {
+    fn name(&self) -> &str {
+        "email-document-source"
+    }
+    
+    /// Poll every `poll_interval_ms` until cancelled
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        tracing::info!("Ingesting {} on {} every {}ms", self.config.mailbox, self.config.host, self.config.poll_interval_ms);
+        while !cancel.is_cancelled() {
+            if let Err(e) = self.poll().await {
+                tracing::error!("Failed to poll {} on {}: {}", self.config.mailbox, self.config.host, e);
+                self.stats.error_count += 1;
+            }
+            cancellable_sleep(&cancel, Duration::from_millis(self.config.poll_interval_ms)).await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use swarm_core::MemoryBroker;
+    
+    const EMAIL: &str = concat!(
+        "From: Alice <alice@example.com>\r\n",
+        "To: inbox@example.com\r\n",
+        "Subject: Invoice 42\r\n",
+        "Message-ID: <invoice-42@example.com>\r\n",
+        "Date: Fri, 1 Mar 2024 12:00:00 +0000\r\n",
+        "MIME-Version: 1.0\r\n",
+        "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+        "\r\n",
+        "--b\r\n",
+        "Content-Type: text/plain; charset=utf-8\r\n",
+        "\r\n",
+        "Please find the invoice attached.\r\n",
+        "--b\r\n",
+        "Content-Type: text/csv; name=\"lines.csv\"\r\n",
+        "Content-Disposition: attachment; filename=\"lines.csv\"\r\n",
+        "\r\n",
+        "item,amount\r\nwidget,42\r\n",
+        "--b\r\n",
+        "Content-Type: application/pdf\r\n",
+        "Content-Disposition: attachment; filename=\"invoice.pdf\"\r\n",
+        "Content-Transfer-Encoding: base64\r\n",
+        "\r\n",
+        "JVBERi0xLjQgaW52b2ljZQ==\r\n",
+        "--b--\r\n",
+    );
+    
+    #[test]
+    fn test_body_and_attachments_become_a_document_group() {
+        let (documents, rejections) = email_documents(EMAIL.as_bytes(), "imap://test/INBOX/1", 1024, DocumentIdStrategy::Deterministic).unwrap();
+        assert!(rejections.is_empty());
+        assert_eq!(documents.iter().map(|document| document.filename.as_str()).collect::<Vec<_>>(), vec!["Invoice 42.txt", "lines.csv", "invoice.pdf"]);
+        
+        let body = &documents[0];
+        assert_eq!(body.content, DocumentContent::Text("Please find the invoice attached.".to_string()));
+        assert_eq!(body.metadata[EMAIL_FROM_KEY], "alice@example.com");
+        assert_eq!(body.metadata[EMAIL_MESSAGE_ID_KEY], "invoice-42@example.com");
+        let pdf = &documents[2];
+        assert_eq!(pdf.document_type, DocumentType::Pdf);
+        assert_eq!(pdf.content, DocumentContent::Binary(b"%PDF-1.4 invoice".to_vec()));
+        assert_eq!(pdf.metadata[EMAIL_PARENT_KEY], body.id.to_string());
+        assert_eq!(pdf.metadata[ATTACHMENT_INDEX_KEY], 1);
+        for document in &documents {
+            assert_eq!(GroupMembership::from_metadata(&document.metadata).unwrap().size, 3);
+        }
+        
+        let (documents, rejections) = email_documents(EMAIL.as_bytes(), "imap://test/INBOX/1", 20, DocumentIdStrategy::Deterministic).unwrap();
+        assert_eq!((documents.len(), rejections.len()), (2, 1));
+        assert_eq!(rejections[0].filename, "lines.csv");
+    }
+    
+    /// Answer the commands of every session; returns the commands received
+    ///
+    /// A flaky mailbox also lists message 5, which cannot be fetched, and
+    /// fails the first attempt to flag a message.
+    async fn serve_mailbox(message: &'static str, flaky: bool) -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = Arc::new(Mutex::new(Vec::<String>::new()));
+        let commands = received.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (reader, mut writer) = tokio::io::split(stream);
+                let mut lines = BufReader::new(reader).lines();
+                writer.write_all(b"* OK test server ready\r\n").await.unwrap();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let (tag, command) = line.split_once(' ').unwrap();
+                    let first_store = command.starts_with("UID STORE")
+                        && !received.lock().unwrap().iter().any(|received| received.starts_with("UID STORE"));
+                    received.lock().unwrap().push(command.to_string());
+                    let (untagged, status) = match command {
+                        command if command.starts_with("UID SEARCH UNSEEN") && flaky => ("* SEARCH 5 7\r\n".to_string(), "OK"),
+                        command if command.starts_with("UID SEARCH UNSEEN") => ("* SEARCH 7\r\n".to_string(), "OK"),
+                        command if command.starts_with("UID FETCH 7") => {
+                            (format!("* 1 FETCH (UID 7 BODY[] {{{}}}\r\n{})\r\n", message.len(), message), "OK")
+                        }
+                        command if command.starts_with("UID FETCH") => (String::new(), "NO no such message"),
+                        _ if first_store && flaky => (String::new(), "NO try again"),
+                        "LOGOUT" => ("* BYE\r\n".to_string(), "OK"),
+                        _ => (String::new(), "OK"),
+                    };
+                    writer.write_all(format!("{}{} {}\r\n", untagged, tag, status).as_bytes()).await.unwrap();
+                }
+            }
+        });
+        (port, commands)
+    }
+    
+    fn create_test_config(port: u16) -> EmailSourceConfig {
+        serde_json::from_value(serde_json::json!({
+            "host": "127.0.0.1",
+            "port": port,
+            "tls": false,
+            "username": "inbox",
+            "password": "secret",
+        })).unwrap()
+    }
+    
+    fn stores(commands: &Mutex<Vec<String>>) -> Vec<String> {
+        commands.lock().unwrap().iter().filter(|command| command.starts_with("UID STORE")).cloned().collect()
+    }
+    
+    #[tokio::test]
+    async fn test_messages_are_published_and_flagged() {
+        let (port, commands) = serve_mailbox(EMAIL, false).await;
+        let config = create_test_config(port);
+        let broker = Arc::new(MemoryBroker::new());
+        let mut incoming = broker.subscribe("swarm.documents.incoming").await.unwrap();
+        let mut source = EmailDocumentSource::new(config).with_broker(broker, "swarm.documents.incoming");
+        
+        let documents = source.poll().await.unwrap();
+        assert_eq!(documents.len(), 3);
+        assert_eq!(stores(&commands), vec!["UID STORE 7 +FLAGS.SILENT (\\Seen)"]);
+        let mut published = 0;
+        while incoming.next_message().unwrap().is_some() {
+            published += 1;
+        }
+        assert_eq!(published, 3);
+    }
+    
+    #[tokio::test]
+    async fn test_failures_skip_one_message_and_are_not_delivered_twice() {
+        let (port, commands) = serve_mailbox(EMAIL, true).await;
+        let broker = Arc::new(MemoryBroker::new());
+        let mut incoming = broker.subscribe("swarm.documents.incoming").await.unwrap();
+        let mut source = EmailDocumentSource::new(create_test_config(port)).with_broker(broker, "swarm.documents.incoming");
+        
+        // Message 5 cannot be fetched and message 7 cannot be flagged
+        assert_eq!(source.poll().await.unwrap().len(), 3);
+        assert_eq!(source.stats().error_count, 2);
+        assert_eq!(commands.lock().unwrap().last().unwrap(), "LOGOUT");
+        
+        // The next poll only flags message 7 instead of publishing it again
+        assert!(source.poll().await.unwrap().is_empty());
+        assert_eq!(stores(&commands).len(), 2);
+        assert_eq!(commands.lock().unwrap().iter().filter(|command| command.starts_with("UID FETCH 7")).count(), 1);
+        let mut published = 0;
+        while incoming.next_message().unwrap().is_some() {
+            published += 1;
+        }
+        assert_eq!(published, 3);
+    }
+}
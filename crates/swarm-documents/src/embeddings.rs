@@ -0,0 +1,178 @@
+//! Embeddings
+//!
+//! Default `EmbeddingProvider`: TF-IDF weighted feature hashing. Words (and
+//! optionally adjacent word pairs) are hashed into a fixed number of signed
+//! buckets, weighted by sublinear term frequency and, once fitted on a corpus,
+//! inverse document frequency, then L2-normalised. It needs no model files, so
+//! it is always available; ONNX or remote models plug in through
+//! `SwarmDocumentProcessor::with_embedding_provider`.
+
+use super::*;
+use async_trait::async_trait;
+use std::collections::HashSet;
+
+/// Hash embedding configuration
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HashEmbeddingConfig {
+    /// Vector length
+    pub dimensions: usize,
+    
+    /// Also hash adjacent word pairs, capturing some word order
+    pub use_bigrams: bool,
+    
+    /// Weight terms by `1 + ln(tf)` instead of raw counts
+    pub sublinear_tf: bool,
+}
+
+impl Default for HashEmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            dimensions: 256,
+            use_bigrams: true,
+            sublinear_tf: true,
+        }
+    }
+}
+
+/// Feature-hashing embedding provider with optional IDF weights
+#[derive(Debug, Clone, Default)]
+pub struct HashEmbeddingProvider {
+    config: HashEmbeddingConfig,
+    model: String,
+    document_frequencies: HashMap<String, u32>,
+    corpus_size: u32,
+}
+
+impl HashEmbeddingProvider {
+    pub fn new(config: HashEmbeddingConfig) -> Self {
+        let model = format!("hash-tfidf-{}", config.dimensions);
+        Self { config, model, ..Default::default() }
+    }
+    
+    /// Learn IDF weights from a representative corpus
+    ///
+    /// Without fitting every term has the same IDF, i.e. plain TF weighting.
+    pub fn fit(mut self, corpus: &[String]) -> Self {
+        for text in corpus {
+            let terms: HashSet<String> = self.terms(text).into_iter().collect();
+            for term in terms {
+                *self.document_frequencies.entry(term).or_insert(0) += 1;
+            }
+        }
+        self.corpus_size += corpus.len() as u32;
+        self
+    }
+    
+    /// Embed one text
+    pub fn embed_text(&self, text: &str) -> Vec<f32> {
+        let mut term_counts: HashMap<String, u32> = HashMap::new();
+        for term in self.terms(text) {
+            *term_counts.entry(term).or_insert(0) += 1;
+        }
+        
+        let mut vector = vec![0.0f32; self.config.dimensions.max(1)];
+        for (term, count) in term_counts {
+            let tf = if self.config.sublinear_tf { 1.0 + (count as f32).ln() } else { count as f32 };
+            let hash = fnv1a(term.as_bytes());
+            let bucket = (hash % vector.len() as u64) as usize;
+            // The top hash bit picks the sign, so colliding terms tend to cancel out
+            let sign = if (hash >> 63) == 0 { 1.0 } else { -1.0 };
+            vector[bucket] += sign * tf * self.idf(&term);
+        }
+        
+        let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|value| *value /= norm);
+        }
+        vector
+    }
+    
+    /// Smoothed inverse document frequency, 1.0 for every term when unfitted
+    fn idf(&self, term: &str) -> f32 {
+        if self.corpus_size == 0 {
+            return 1.0;
+        }
+        let document_frequency = self.document_frequencies.get(term).copied().unwrap_or(0);
+        ((1.0 + self.corpus_size as f32) / (1.0 + document_frequency as f32)).ln() + 1.0
+    }
+    
+    /// Lowercased words, segmented by language, plus adjacent pairs if enabled
+    fn terms(&self, text: &str) -> Vec<String> {
+        let words: Vec<String> = segmenter_for_language(detect_cjk_language(text))
+            .segment(text)
+            .into_iter()
+            .map(|word| word.to_lowercase())
+            .collect();
+        
+        let mut terms = words.clone();
+        if self.config.use_bigrams {
+            terms.extend(words.windows(2).map(|pair| format!("{} {}", pair[0], pair[1])));
+        }
+        terms
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for HashEmbeddingProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+    
+    fn dimensions(&self) -> usize {
+        self.config.dimensions.max(1)
+    }
+    
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.embed_text(text)).collect())
+    }
+}
+
+/// 64-bit FNV-1a, stable across builds and platforms (unlike `DefaultHasher`)
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Cosine similarity of two vectors of equal length
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 { 0.0 } else { dot / (norm_a * norm_b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_hash_embeddings_are_normalised_and_stable() {
+        let provider = HashEmbeddingProvider::new(HashEmbeddingConfig { dimensions: 64, ..Default::default() });
+        let texts = vec!["The quarterly budget report".to_string(), String::new()];
+        let vectors = provider.embed(&texts).await.unwrap();
+        
+        assert_eq!(provider.model(), "hash-tfidf-64");
+        assert_eq!(vectors[0].len(), 64);
+        let norm = vectors[0].iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-5);
+        assert!(vectors[1].iter().all(|x| *x == 0.0));
+        assert_eq!(vectors[0], provider.embed_text("the QUARTERLY budget report"));
+    }
+    
+    #[test]
+    fn test_similar_texts_are_closer() {
+        let corpus: Vec<String> = [
+            "the budget report for the finance team",
+            "the holiday schedule for the office",
+            "the annual budget and finance review",
+        ].iter().map(|text| text.to_string()).collect();
+        let provider = HashEmbeddingProvider::new(HashEmbeddingConfig::default()).fit(&corpus);
+        
+        let budget = provider.embed_text("finance budget report");
+        let related = provider.embed_text("budget review for finance");
+        let unrelated = provider.embed_text("office holiday schedule");
+        assert!(cosine_similarity(&budget, &related) > cosine_similarity(&budget, &unrelated));
+        
+        // Common words carry less weight once IDF is fitted
+        assert!(provider.idf("the") < provider.idf("budget"));
+    }
+}
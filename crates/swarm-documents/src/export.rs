@@ -0,0 +1,401 @@
+//! Result Export
+//!
+//! Assembles zip bundles of processing results for offline review. Each
+//! document gets its original file (from the content cache), its extracted
+//! text and its result JSON; results are selected by batch, tenant and time
+//! range from the result store.
+
+use super::*;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use swarm_core::{ExportFormat, ExportRequest, ExportSummary, TaskResultData};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// A processing result with the document details used to select and export it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StoredResult {
+    pub filename: String,
+    pub tenant: Option<String>,
+    pub batch_id: Option<String>,
+    pub result: DocumentProcessingResult,
+}
+
+impl StoredResult {
+    /// Stored form of a result; tenant and batch come from the document metadata
+    pub fn new(document: &Document, result: DocumentProcessingResult) -> Self {
+        let metadata_string = |key: &str| document.metadata.get(key).and_then(|value| value.as_str()).map(str::to_string);
+        Self {
+            filename: document.filename.clone(),
+            tenant: metadata_string("tenant"),
+            batch_id: metadata_string("batch_id"),
+            result,
+        }
+    }
+    
+    /// Whether this result is selected by an export request
+    pub fn matches(&self, request: &ExportRequest) -> bool {
+        let processed_at = self.result.processed_at;
+        let has_warnings = !self.result.warnings.is_empty();
+        (request.batch_id.is_none() || request.batch_id == self.batch_id)
+            && (request.tenant.is_none() || request.tenant == self.tenant)
+            && request.from.is_none_or(|from| processed_at >= from)
+            && request.to.is_none_or(|to| processed_at < to)
+            && request.has_warnings.is_none_or(|wanted| wanted == has_warnings)
+            && request.warning_code.as_deref().is_none_or(|code| self.result.has_warning(code))
+    }
+}
+
+/// Store of processing results
+#[async_trait]
+pub trait ResultStore: Send + Sync {
+    /// Results selected by an export request
+    async fn query(&self, request: &ExportRequest) -> Result<Vec<StoredResult>>;
+}
+
+/// Cache of original document content
+#[async_trait]
+pub trait ContentCache: Send + Sync {
+    /// Original bytes of a document, if still cached
+    async fn get(&self, document_id: Uuid) -> Result<Option<Vec<u8>>>;
+}
+
+/// In-memory result store and content cache
+#[derive(Debug, Default)]
+pub struct InMemoryResultStore {
+    results: RwLock<Vec<StoredResult>>,
+    contents: RwLock<HashMap<Uuid, Vec<u8>>>,
+}
+
+impl InMemoryResultStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Record a result and cache the document's inline content
+    pub fn record(&self, document: &Document, result: DocumentProcessingResult) {
+        let bytes = match &document.content {
+            DocumentContent::Text(text) => Some(text.as_bytes().to_vec()),
+            DocumentContent::Binary(bytes) => Some(bytes.clone()),
+            DocumentContent::Reference { .. } | DocumentContent::Stream { .. } => None,
+        };
+        if let Some(bytes) = bytes {
+            self.contents.write().unwrap().insert(result.document_id, bytes);
+        }
+        self.results.write().unwrap().push(StoredResult::new(document, result));
+    }
+}
+
+#[async_trait]
+impl ResultStore for InMemoryResultStore {
+    async fn query(&self, request: &ExportRequest) -> Result<Vec<StoredResult>> {
+        Ok(self.results.read().unwrap().iter().filter(|stored| stored.matches(request)).cloned().collect())
+    }
+}
+
+#[async_trait]
+impl ContentCache for InMemoryResultStore {
+    async fn get(&self, document_id: Uuid) -> Result<Option<Vec<u8>>> {
+        Ok(self.contents.read().unwrap().get(&document_id).cloned())
+    }
+}
+
+/// Export configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportConfig {
+    /// Directory that bundles are written to
+    pub output_directory: PathBuf,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            output_directory: PathBuf::from("./exports"),
+        }
+    }
+}
+
+/// Manifest entry for an exported document
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExportedDocument {
+    pub document_id: Uuid,
+    pub filename: String,
+    pub tenant: Option<String>,
+    pub batch_id: Option<String>,
+    pub has_original: bool,
+}
+
+/// Top-level `manifest.json` of a bundle
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExportManifest {
+    pub request: ExportRequest,
+    pub created_at: chrono::DateTime<Utc>,
+    pub documents: Vec<ExportedDocument>,
+}
+
+/// Builds export bundles and handles export tasks
+pub struct ResultExporter {
+    config: ExportConfig,
+    results: Arc<dyn ResultStore>,
+    contents: Arc<dyn ContentCache>,
+    task_types: Vec<TaskType>,
+}
+
+impl ResultExporter {
+    pub fn new(config: ExportConfig, results: Arc<dyn ResultStore>, contents: Arc<dyn ContentCache>) -> Self {
+        Self {
+            config,
+            results,
+            contents,
+            task_types: vec![TaskType::Export { format: ExportFormat::Zip }],
+        }
+    }
+    
+    /// Write a zip bundle of the selected results to the output directory
+    pub async fn export(&self, request: &ExportRequest) -> DocumentResult<ExportSummary> {
+        let mut results = self.results.query(request).await.map_err(|e| DocumentError::ProcessingFailed {
+            reason: format!("result store query failed: {}", e),
+        })?;
+        results.sort_by_key(|stored| stored.result.processed_at);
+        
+        let mut documents = Vec::with_capacity(results.len());
+        let mut entries = Vec::new();
+        for stored in results {
+            let document_id = stored.result.document_id;
+            let original = match self.contents.get(document_id).await {
+                Ok(original) => original,
+                Err(e) => {
+                    tracing::warn!("Content cache lookup for {} failed: {}", document_id, e);
+                    None
+                }
+            };
+            
+            documents.push(ExportedDocument {
+                document_id,
+                filename: stored.filename.clone(),
+                tenant: stored.tenant.clone(),
+                batch_id: stored.batch_id.clone(),
+                has_original: original.is_some(),
+            });
+            if let Some(original) = original {
+                entries.push((format!("{}/original/{}", document_id, entry_filename(&stored.filename)), original));
+            }
+            if let Some(text) = &stored.result.extracted_text {
+                entries.push((format!("{}/extracted.txt", document_id), text.clone().into_bytes()));
+            }
+            entries.push((format!("{}/result.json", document_id), serde_json::to_vec_pretty(&stored.result)?));
+        }
+        
+        let manifest = ExportManifest {
+            request: request.clone(),
+            created_at: Utc::now(),
+            documents,
+        };
+        let document_count = manifest.documents.len();
+        let missing_originals = manifest.documents.iter().filter(|document| !document.has_original).count();
+        entries.insert(0, ("manifest.json".to_string(), serde_json::to_vec_pretty(&manifest)?));
+        
+        fs::create_dir_all(&self.config.output_directory).await?;
+        let bundle_path = self.config.output_directory.join(format!(
+            "export-{}-{}.zip",
+            manifest.created_at.format("%Y%m%dT%H%M%SZ"),
+            Uuid::new_v4().simple()
+        ));
+        let path = bundle_path.clone();
+        let size_bytes = tokio::task::spawn_blocking(move || write_bundle(&path, entries))
+            .await
+            .map_err(|e| DocumentError::ProcessingFailed { reason: format!("export task failed: {}", e) })??;
+        
+        tracing::info!("Exported {} document(s) to {}", document_count, bundle_path.display());
+        Ok(ExportSummary {
+            bundle_path: bundle_path.to_string_lossy().into_owned(),
+            document_count,
+            missing_originals,
+            size_bytes,
+        })
+    }
+}
+
+#[async_trait]
+impl TaskProcessor for ResultExporter {
+    async fn process(&self, task: &Task) -> Result<TaskResult> {
+        let TaskPayload::Export(request) = &task.payload else {
+            anyhow::bail!("export task {} has no export payload", task.id);
+        };
+        
+        let start_time = std::time::Instant::now();
+        let summary = self.export(request).await?;
+        Ok(TaskResult {
+            task_id: task.id,
+            status: TaskStatus::Completed,
+            result: Some(TaskResultData::Export(summary)),
+            error: None,
+            processing_time_ms: start_time.elapsed().as_millis() as u64,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        })
+    }
+    
+    fn supported_task_types(&self) -> &[TaskType] {
+        &self.task_types
+    }
+    
+    fn estimate_processing_time(&self, _task: &Task) -> std::time::Duration {
+        std::time::Duration::from_secs(5)
+    }
+}
+
+/// File name of an original inside the bundle, without any directories
+fn entry_filename(filename: &str) -> String {
+    Path::new(filename)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "original".to_string())
+}
+
+/// Write entries to a new zip file, returning its size
+fn write_bundle(path: &Path, entries: Vec<(String, Vec<u8>)>) -> DocumentResult<u64> {
+    let file = std::fs::File::create(path)?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, bytes) in entries {
+        zip.start_file(name, options).map_err(zip_error)?;
+        zip.write_all(&bytes)?;
+    }
+    let file = zip.finish().map_err(zip_error)?;
+    Ok(file.metadata()?.len())
+}
+
+fn zip_error(error: zip::result::ZipError) -> DocumentError {
+    DocumentError::ProcessingFailed {
+        reason: format!("writing export bundle failed: {}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use zip::ZipArchive;
+    
+    fn create_test_document(filename: &str, tenant: &str, batch_id: &str) -> Document {
+        let content = format!("Contents of {}", filename);
+        Document {
+            id: Uuid::new_v4(),
+            filename: filename.to_string(),
+            document_type: DocumentType::Text,
+            size_bytes: content.len(),
+            content: DocumentContent::Text(content),
+            metadata: HashMap::from([
+                ("tenant".to_string(), serde_json::json!(tenant)),
+                ("batch_id".to_string(), serde_json::json!(batch_id)),
+            ]),
+            created_at: Utc::now(),
+        }
+    }
+    
+    fn create_test_result(document: &Document, processed_at: chrono::DateTime<Utc>) -> DocumentProcessingResult {
+        DocumentProcessingResult {
+            document_id: document.id,
+            extracted_text: Some(format!("text of {}", document.filename)),
+            metadata: HashMap::new(),
+            language: Some("en".to_string()),
+            keywords: Vec::new(),
+            sentiment: None,
+            classification: None,
+            embeddings: None,
+            processing_time_ms: 3,
+            processed_at,
+            warnings: Vec::new(),
+        }
+    }
+    
+    fn read_entry(archive: &mut ZipArchive<std::fs::File>, name: &str) -> String {
+        let mut contents = String::new();
+        archive.by_name(name).unwrap().read_to_string(&mut contents).unwrap();
+        contents
+    }
+    
+    #[test]
+    fn test_result_selection() {
+        let now = Utc::now();
+        let document = create_test_document("a.txt", "acme", "batch-1");
+        let stored = StoredResult::new(&document, create_test_result(&document, now));
+        
+        assert!(stored.matches(&ExportRequest::default()));
+        assert!(stored.matches(&ExportRequest { tenant: Some("acme".to_string()), ..Default::default() }));
+        assert!(!stored.matches(&ExportRequest { tenant: Some("globex".to_string()), ..Default::default() }));
+        assert!(!stored.matches(&ExportRequest { batch_id: Some("batch-2".to_string()), ..Default::default() }));
+        assert!(stored.matches(&ExportRequest { from: Some(now), to: Some(now + chrono::Duration::seconds(1)), ..Default::default() }));
+        assert!(!stored.matches(&ExportRequest { to: Some(now), ..Default::default() }));
+        assert!(stored.matches(&ExportRequest { has_warnings: Some(false), ..Default::default() }));
+        
+        let mut warned = stored.clone();
+        warned.result.warn("pdf", "page_unreadable", "no text extracted from page 2");
+        assert!(warned.matches(&ExportRequest { has_warnings: Some(true), ..Default::default() }));
+        assert!(warned.matches(&ExportRequest { warning_code: Some("page_unreadable".to_string()), ..Default::default() }));
+        assert!(!stored.matches(&ExportRequest { warning_code: Some("page_unreadable".to_string()), ..Default::default() }));
+    }
+    
+    #[tokio::test]
+    async fn test_export_writes_bundle() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(InMemoryResultStore::new());
+        let report = create_test_document("reports/q3.txt", "acme", "batch-1");
+        let other = create_test_document("other.txt", "globex", "batch-1");
+        store.record(&report, create_test_result(&report, Utc::now()));
+        store.record(&other, create_test_result(&other, Utc::now()));
+        
+        let config = ExportConfig { output_directory: temp_dir.path().join("exports") };
+        let exporter = ResultExporter::new(config, store.clone(), store);
+        let summary = exporter.export(&ExportRequest { tenant: Some("acme".to_string()), ..Default::default() }).await.unwrap();
+        assert_eq!(summary.document_count, 1);
+        assert_eq!(summary.missing_originals, 0);
+        
+        let file = std::fs::File::open(&summary.bundle_path).unwrap();
+        assert_eq!(file.metadata().unwrap().len(), summary.size_bytes);
+        let mut archive = ZipArchive::new(file).unwrap();
+        assert_eq!(archive.len(), 4);
+        assert_eq!(read_entry(&mut archive, &format!("{}/original/q3.txt", report.id)), "Contents of reports/q3.txt");
+        assert_eq!(read_entry(&mut archive, &format!("{}/extracted.txt", report.id)), "text of reports/q3.txt");
+        
+        let result: DocumentProcessingResult = serde_json::from_str(&read_entry(&mut archive, &format!("{}/result.json", report.id))).unwrap();
+        assert_eq!(result.document_id, report.id);
+        let manifest: ExportManifest = serde_json::from_str(&read_entry(&mut archive, "manifest.json")).unwrap();
+        assert_eq!(manifest.documents[0].filename, "reports/q3.txt");
+    }
+    
+    #[tokio::test]
+    async fn test_export_task_without_cached_originals() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let results = Arc::new(InMemoryResultStore::new());
+        let document = create_test_document("a.txt", "acme", "batch-7");
+        results.record(&document, create_test_result(&document, Utc::now()));
+        
+        let config = ExportConfig { output_directory: temp_dir.path().to_path_buf() };
+        let exporter = ResultExporter::new(config, results, Arc::new(InMemoryResultStore::new()));
+        let task = Task {
+            id: Uuid::new_v4(),
+            task_type: TaskType::Export { format: ExportFormat::Zip },
+            priority: TaskPriority::Low,
+            status: TaskStatus::Pending,
+            payload: TaskPayload::Export(ExportRequest { batch_id: Some("batch-7".to_string()), ..Default::default() }),
+            created_at: Utc::now(),
+            deadline: None,
+            retry_count: 0,
+            max_retries: 1,
+            metadata: HashMap::new(),
+        };
+        
+        let result = exporter.process(&task).await.unwrap();
+        let Some(TaskResultData::Export(summary)) = result.result else {
+            panic!("expected an export summary");
+        };
+        assert_eq!(summary.document_count, 1);
+        assert_eq!(summary.missing_originals, 1);
+        assert_eq!(ZipArchive::new(std::fs::File::open(&summary.bundle_path).unwrap()).unwrap().len(), 3);
+    }
+}
@@ -1,16 +1,25 @@
 //! File Discovery Module
 //!
 //! This module provides utilities for discovering and monitoring files
-//! in the file system for document processing.
+//! in the file system for document processing. Changes are detected by
+//! native file system events when watching is enabled and supported, and
+//! by periodic scans otherwise. Discovered files are tracked in a
+//! `DiscoveryInventory` until they are processed.
 
-use super::*;
+use crate::directory_walker::{default_max_scan_depth, walk_directory};
+use crate::inventory::DiscoveryInventory;
+use crate::mime_registry::MimeRegistry;
+use crate::path_patterns::{relative_to_roots, PathPatterns};
 use anyhow::Result;
-use std::collections::HashMap;
+use async_trait::async_trait;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use tokio::fs;
-use tokio::time::{sleep, Duration};
-use uuid::Uuid;
+use tokio::sync::mpsc;
+use tokio::time::{sleep_until, Duration, Instant};
 use chrono::Utc;
+use swarm_core::{cancellable_sleep, CancellationToken, ConfigUpdates, Service};
 
 /// File discovery configuration
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -18,10 +27,10 @@ pub struct FileDiscoveryConfig {
     /// Directories to monitor
     pub watch_directories: Vec<PathBuf>,
     
-    /// File patterns to include (glob patterns)
+    /// File patterns to include (glob patterns, see [`crate::path_patterns`])
     pub include_patterns: Vec<String>,
     
-    /// File patterns to exclude (glob patterns)
+    /// File patterns to exclude (glob patterns, see [`crate::path_patterns`])
     pub exclude_patterns: Vec<String>,
     
     /// Maximum file size to process (in bytes)
@@ -36,9 +45,19 @@ pub struct FileDiscoveryConfig {
     /// Enable recursive directory scanning
     pub recursive_scan: bool,
     
-    /// Enable file system watching (inotify/fsevents)
+    /// Maximum directory levels scanned below each watched directory when
+    /// scanning recursively; directories matching an exclude pattern are skipped
+    #[serde(default = "default_max_scan_depth")]
+    pub max_scan_depth: usize,
+    
+    /// Enable file system watching (inotify/fsevents); falls back to
+    /// polling when the platform does not support it
     pub enable_fs_watching: bool,
     
+    /// Quiet period after the last event for a path before it is reported
+    #[serde(default = "default_watch_debounce_ms")]
+    pub watch_debounce_ms: u64,
+    
     /// File age threshold (ignore files older than this)
     pub max_file_age_hours: Option<u64>,
 }
@@ -53,12 +72,18 @@ impl Default for FileDiscoveryConfig {
             min_file_size: 1, // 1 byte
             scan_interval_ms: 1000,
             recursive_scan: true,
+            max_scan_depth: default_max_scan_depth(),
             enable_fs_watching: false,
+            watch_debounce_ms: default_watch_debounce_ms(),
             max_file_age_hours: Some(24 * 7), // 1 week
         }
     }
 }
 
+fn default_watch_debounce_ms() -> u64 {
+    250
+}
+
 /// File discovery statistics
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct FileDiscoveryStats {
@@ -112,25 +137,78 @@ pub struct FileInfo {
     pub mime_type: Option<String>,
 }
 
+/// Change to a discovered file
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum FileEvent {
+    Created(FileInfo),
+    Modified(FileInfo),
+    /// Last known information of a file that no longer exists
+    Removed(FileInfo),
+}
+
+impl FileEvent {
+    pub fn file(&self) -> &FileInfo {
+        match self {
+            FileEvent::Created(file) | FileEvent::Modified(file) | FileEvent::Removed(file) => file,
+        }
+    }
+}
+
 /// File discovery service
 pub struct FileDiscoveryService {
     config: FileDiscoveryConfig,
+    include_patterns: PathPatterns,
+    exclude_patterns: PathPatterns,
     stats: FileDiscoveryStats,
     discovered_files: HashMap<PathBuf, FileInfo>,
+    event_subscribers: Vec<mpsc::UnboundedSender<FileEvent>>,
+    inventory: DiscoveryInventory,
     is_running: bool,
+    shutdown: CancellationToken,
+    config_updates: Option<ConfigUpdates<FileDiscoveryConfig>>,
 }
 
 impl FileDiscoveryService {
     /// Create a new file discovery service
     pub fn new(config: FileDiscoveryConfig) -> Self {
         Self {
+            include_patterns: PathPatterns::compile_valid(&config.include_patterns),
+            exclude_patterns: PathPatterns::compile_valid(&config.exclude_patterns),
             config,
             stats: FileDiscoveryStats::default(),
             discovered_files: HashMap::new(),
+            event_subscribers: Vec::new(),
+            inventory: DiscoveryInventory::new(),
             is_running: false,
+            shutdown: CancellationToken::new(),
+            config_updates: None,
         }
     }
     
+    /// Apply reloaded tunables (scan interval and file size limits) between scans
+    pub fn with_config_updates(mut self, updates: ConfigUpdates<FileDiscoveryConfig>) -> Self {
+        self.config_updates = Some(updates);
+        self
+    }
+    
+    /// Take the tunables of a reloaded configuration, if there was a reload
+    fn apply_config_updates(&mut self) {
+        let Some(config) = self.config_updates.as_mut().and_then(ConfigUpdates::changed) else { return };
+        if config.scan_interval_ms != self.config.scan_interval_ms {
+            tracing::info!("Scan interval changed to {}ms", config.scan_interval_ms);
+        }
+        self.config.scan_interval_ms = config.scan_interval_ms;
+        self.config.max_file_size = config.max_file_size;
+        self.config.min_file_size = config.min_file_size;
+    }
+    
+    /// Subscribe to file created/modified/removed events
+    pub fn subscribe_events(&mut self) -> mpsc::UnboundedReceiver<FileEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.event_subscribers.push(sender);
+        receiver
+    }
+    
     /// Get current configuration
     pub fn config(&self) -> &FileDiscoveryConfig {
         &self.config
@@ -141,6 +219,16 @@ impl FileDiscoveryService {
         &self.stats
     }
     
+    /// Token that stops `start` from another task
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+    
+    /// Lifecycle of the discovered files; processing marks files queued and processed
+    pub fn inventory(&self) -> DiscoveryInventory {
+        self.inventory.clone()
+    }
+    
     /// Get discovered files
     pub fn discovered_files(&self) -> &HashMap<PathBuf, FileInfo> {
         &self.discovered_files
@@ -148,27 +236,12 @@ impl FileDiscoveryService {
     
     /// Check if file matches include patterns
     fn matches_include_patterns(&self, path: &Path) -> bool {
-        self.config.include_patterns.iter().any(|pattern| {
-            if pattern == "*" {
-                return true;
-            }
-            // Simple pattern matching (could be enhanced with proper glob)
-            path.to_string_lossy().contains(pattern.trim_start_matches('*').trim_end_matches('*'))
-        })
+        self.include_patterns.matches(relative_to_roots(path, &self.config.watch_directories))
     }
     
     /// Check if file matches exclude patterns
     fn matches_exclude_patterns(&self, path: &Path) -> bool {
-        self.config.exclude_patterns.iter().any(|pattern| {
-            if pattern == ".*" {
-                return path.file_name()
-                    .and_then(|name| name.to_str())
-                    .map(|name| name.starts_with('.'))
-                    .unwrap_or(false);
-            }
-            // Simple pattern matching (could be enhanced with proper glob)
-            path.to_string_lossy().contains(pattern.trim_start_matches('*').trim_end_matches('*'))
-        })
+        self.exclude_patterns.matches(relative_to_roots(path, &self.config.watch_directories))
     }
     
     /// Check if file should be discovered
@@ -222,25 +295,7 @@ impl FileDiscoveryService {
         let is_symlink = metadata.file_type().is_symlink();
         
         // Determine MIME type based on extension
-        let mime_type = extension.as_ref().map(|ext| {
-            match ext.as_str() {
-                "txt" => "text/plain",
-                "md" => "text/markdown",
-                "html" | "htm" => "text/html",
-                "pdf" => "application/pdf",
-                "doc" | "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
-                "xls" | "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
-                "ppt" | "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
-                "jpg" | "jpeg" => "image/jpeg",
-                "png" => "image/png",
-                "gif" => "image/gif",
-                "mp3" => "audio/mpeg",
-                "wav" => "audio/wav",
-                "mp4" => "video/mp4",
-                "avi" => "video/x-msvideo",
-                _ => "application/octet-stream",
-            }.to_string()
-        });
+        let mime_type = extension.as_ref().map(|ext| MimeRegistry::global().mime_type_for_extension(ext));
         
         Ok(FileInfo {
             path: path.to_path_buf(),
@@ -255,7 +310,7 @@ impl FileDiscoveryService {
         })
     }
     
-    /// Scan a single directory for files
+    /// Scan a directory (and its subdirectories, if recursive) for files
     async fn scan_directory(&mut self, directory: &Path) -> Result<Vec<FileInfo>> {
         let mut files = Vec::new();
         
@@ -264,26 +319,23 @@ impl FileDiscoveryService {
             return Ok(files);
         }
         
-        let mut entries = fs::read_dir(directory).await?;
-        self.stats.total_directories_scanned += 1;
+        let max_depth = if self.config.recursive_scan { self.config.max_scan_depth } else { 0 };
+        let listing = walk_directory(directory, max_depth, |path| self.matches_exclude_patterns(path)).await?;
+        self.stats.total_directories_scanned += listing.directories_scanned;
+        self.stats.error_count += listing.errors;
         
-        while let Some(entry) = entries.next_entry().await? {
-            let path = entry.path();
-            
-            if path.is_file() {
-                match self.get_file_info(&path).await {
-                    Ok(file_info) => {
-                        if self.should_discover_file(&path, file_info.size_bytes) {
-                            files.push(file_info);
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!("Failed to get file info for {}: {}", path.display(), e);
-                        self.stats.error_count += 1;
+        for path in listing.files {
+            match self.get_file_info(&path).await {
+                Ok(file_info) => {
+                    if self.should_discover_file(&path, file_info.size_bytes) {
+                        files.push(file_info);
                     }
                 }
+                Err(e) => {
+                    tracing::error!("Failed to get file info for {}: {}", path.display(), e);
+                    self.stats.error_count += 1;
+                }
             }
-            // Note: Recursive scanning removed for simplicity
         }
         
         Ok(files)
@@ -334,53 +386,256 @@ impl FileDiscoveryService {
         }
     }
     
-    /// Start the file discovery service
+    /// Start the file discovery service; runs until `stop` or the shutdown token
     pub async fn start(&mut self) -> Result<()> {
+        if self.shutdown.is_cancelled() {
+            self.shutdown = CancellationToken::new();
+        }
+        let shutdown = self.shutdown.clone();
+        self.run(shutdown).await
+    }
+    
+    /// Discover files until cancelled; a scan or reconciliation in progress finishes first
+    pub async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
         tracing::info!("Starting file discovery service...");
         tracing::info!("Watching directories: {:?}", self.config.watch_directories);
-        tracing::info!("Scan interval: {}ms", self.config.scan_interval_ms);
         
         self.is_running = true;
+        let result = self.discover(&cancel).await;
+        self.is_running = false;
+        tracing::info!("File discovery service stopped");
+        result
+    }
+    
+    async fn discover(&mut self, cancel: &CancellationToken) -> Result<()> {
+        if self.config.enable_fs_watching {
+            match self.create_watcher() {
+                Ok((watcher, raw_events)) => return self.run_watcher(watcher, raw_events, cancel).await,
+                Err(e) => tracing::warn!("File system watching unavailable, falling back to polling: {}", e),
+            }
+        }
         
-        while self.is_running {
-            match self.scan_all_directories().await {
-                Ok(new_files) => {
-                    if !new_files.is_empty() {
-                        tracing::info!("Discovered {} new files", new_files.len());
-                        
-                        // Update discovered files
-                        for file in &new_files {
-                            self.discovered_files.insert(file.path.clone(), file.clone());
+        tracing::info!("Scan interval: {}ms", self.config.scan_interval_ms);
+        while !cancel.is_cancelled() {
+            self.poll_once().await;
+            self.apply_config_updates();
+            cancellable_sleep(cancel, Duration::from_millis(self.config.scan_interval_ms)).await;
+        }
+        
+        Ok(())
+    }
+    
+    /// Scan all directories once and report changes since the previous scan
+    async fn poll_once(&mut self) {
+        match self.scan_all_directories().await {
+            Ok(new_files) => {
+                self.emit_scan_changes(&new_files);
+                
+                if !new_files.is_empty() {
+                    tracing::info!("Discovered {} new files", new_files.len());
+                    
+                    // Update discovered files
+                    for file in &new_files {
+                        self.discovered_files.insert(file.path.clone(), file.clone());
+                    }
+                    
+                    // Update statistics
+                    self.update_stats(&new_files);
+                    
+                    // Log some file details
+                    for file in new_files.iter().take(5) {
+                        tracing::info!("File: {} ({}, {} bytes)", 
+                                     file.filename, 
+                                     file.extension.as_deref().unwrap_or("no ext"),
+                                     file.size_bytes);
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::error!("Error during file discovery: {}", e);
+                self.stats.error_count += 1;
+            }
+        }
+    }
+    
+    /// Compare a full scan with the discovered files and emit the differences
+    fn emit_scan_changes(&mut self, files: &[FileInfo]) {
+        let scanned: HashSet<&Path> = files.iter().map(|file| file.path.as_path()).collect();
+        let removed: Vec<PathBuf> = self.discovered_files.keys()
+            .filter(|path| !scanned.contains(path.as_path()))
+            .cloned()
+            .collect();
+        for path in removed {
+            if let Some(file) = self.discovered_files.remove(&path) {
+                self.emit(FileEvent::Removed(file));
+            }
+        }
+        
+        for file in files {
+            let event = match self.discovered_files.get(&file.path) {
+                None => FileEvent::Created(file.clone()),
+                Some(known) if has_changed(known, file) => FileEvent::Modified(file.clone()),
+                Some(_) => continue,
+            };
+            self.emit(event);
+        }
+    }
+    
+    fn emit(&mut self, event: FileEvent) {
+        match &event {
+            FileEvent::Created(file) | FileEvent::Modified(file) => self.inventory.record_discovered(file),
+            FileEvent::Removed(file) => {
+                self.inventory.remove(&file.path);
+            }
+        }
+        self.event_subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+    
+    /// Watch the configured directories for native file system events
+    fn create_watcher(&self) -> notify::Result<(RecommendedWatcher, mpsc::UnboundedReceiver<notify::Result<notify::Event>>)> {
+        let (sender, raw_events) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })?;
+        
+        let mode = if self.config.recursive_scan { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+        for directory in &self.config.watch_directories {
+            if directory.exists() {
+                watcher.watch(directory, mode)?;
+            } else {
+                tracing::warn!("Directory does not exist: {:?}", directory);
+            }
+        }
+        Ok((watcher, raw_events))
+    }
+    
+    /// Report debounced changes from the watcher until cancelled
+    async fn run_watcher(
+        &mut self,
+        _watcher: RecommendedWatcher,
+        mut raw_events: mpsc::UnboundedReceiver<notify::Result<notify::Event>>,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        tracing::info!("Watching for file system events (debounce {}ms)", self.config.watch_debounce_ms);
+        
+        // Files that existed before the watch started
+        self.poll_once().await;
+        
+        let debounce = Duration::from_millis(self.config.watch_debounce_ms);
+        let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+        loop {
+            let next_flush = pending.values().min().map(|last_event| *last_event + debounce);
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => break,
+                event = raw_events.recv() => match event {
+                    Some(Ok(event)) => {
+                        let now = Instant::now();
+                        for path in event.paths {
+                            pending.insert(path, now);
                         }
-                        
-                        // Update statistics
-                        self.update_stats(&new_files);
-                        
-                        // Log some file details
-                        for file in new_files.iter().take(5) {
-                            tracing::info!("File: {} ({}, {} bytes)", 
-                                         file.filename, 
-                                         file.extension.as_deref().unwrap_or("no ext"),
-                                         file.size_bytes);
+                    }
+                    Some(Err(e)) => {
+                        tracing::warn!("File system watch error: {}", e);
+                        self.stats.error_count += 1;
+                    }
+                    None => break,
+                },
+                _ = sleep_until(next_flush.unwrap_or_else(Instant::now)), if next_flush.is_some() => {
+                    let now = Instant::now();
+                    let settled: Vec<PathBuf> = pending.iter()
+                        .filter(|(_, last_event)| **last_event + debounce <= now)
+                        .map(|(path, _)| path.clone())
+                        .collect();
+                    for path in settled {
+                        pending.remove(&path);
+                        self.reconcile_path(&path).await;
+                    }
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Bring the discovered files up to date with a path reported by the watcher
+    async fn reconcile_path(&mut self, path: &Path) {
+        let max_depth = if self.config.recursive_scan { self.config.max_scan_depth } else { 0 };
+        match fs::metadata(path).await {
+            Ok(metadata) if metadata.is_dir() => {
+                // Files can be created in a new directory before it is watched
+                let Some(depth) = self.watched_depth(path) else { return };
+                if depth > max_depth || self.matches_exclude_patterns(path) {
+                    return;
+                }
+                match walk_directory(path, max_depth - depth, |path| self.matches_exclude_patterns(path)).await {
+                    Ok(listing) => {
+                        for file in listing.files {
+                            self.reconcile_file(&file).await;
                         }
                     }
+                    Err(e) => tracing::warn!("Failed to scan new directory {}: {}", path.display(), e),
                 }
-                Err(e) => {
-                    tracing::error!("Error during file discovery: {}", e);
-                    self.stats.error_count += 1;
+            }
+            Ok(_) => {
+                if self.watched_depth(path).is_some_and(|depth| depth > 0 && depth - 1 <= max_depth) {
+                    self.reconcile_file(path).await;
                 }
             }
-            
-            sleep(Duration::from_millis(self.config.scan_interval_ms)).await;
+            Err(_) => {
+                // A removed directory takes the files below it along
+                let removed: Vec<PathBuf> = self.discovered_files.keys()
+                    .filter(|known| known.starts_with(path))
+                    .cloned()
+                    .collect();
+                for known in removed {
+                    if let Some(file) = self.discovered_files.remove(&known) {
+                        self.emit(FileEvent::Removed(file));
+                    }
+                }
+            }
+        }
+    }
+    
+    async fn reconcile_file(&mut self, path: &Path) {
+        // The file may be gone again by the time it is reconciled
+        let Ok(file) = self.get_file_info(path).await else { return };
+        if !self.should_discover_file(path, file.size_bytes) {
+            return;
         }
         
-        Ok(())
+        let event = match self.discovered_files.get(path) {
+            None => FileEvent::Created(file.clone()),
+            Some(known) if has_changed(known, &file) => FileEvent::Modified(file.clone()),
+            Some(_) => return,
+        };
+        if matches!(event, FileEvent::Created(_)) {
+            self.update_stats(std::slice::from_ref(&file));
+        }
+        self.discovered_files.insert(path.to_path_buf(), file);
+        self.emit(event);
+    }
+    
+    /// Number of path components below the watched directory containing `path`,
+    /// or `None` if it is outside the watched directories or below an excluded directory
+    fn watched_depth(&self, path: &Path) -> Option<usize> {
+        let relative = self.config.watch_directories.iter()
+            .filter_map(|directory| path.strip_prefix(directory).ok()
+                .or_else(|| path.strip_prefix(std::fs::canonicalize(directory).ok()?).ok()))
+            .next()?;
+        let components: Vec<_> = relative.components().collect();
+        let excluded_parent = (1..components.len())
+            .any(|depth| self.matches_exclude_patterns(&components[..depth].iter().collect::<PathBuf>()));
+        if excluded_parent {
+            return None;
+        }
+        Some(components.len())
     }
     
     /// Stop the file discovery service
     pub async fn stop(&mut self) -> Result<()> {
         tracing::info!("Stopping file discovery service...");
-        self.is_running = false;
+        self.shutdown.cancel();
         Ok(())
     }
     
@@ -407,9 +662,25 @@ impl FileDiscoveryService {
     }
 }
 
+fn has_changed(known: &FileInfo, current: &FileInfo) -> bool {
+    known.size_bytes != current.size_bytes || known.modified_time != current.modified_time
+}
+
+#[async_trait]
+impl Service for FileDiscoveryService {
+    fn name(&self) -> &str {
+        "file-discovery"
+    }
+    
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        FileDiscoveryService::run(self, cancel).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::inventory::{FileState, InventoryQuery};
     use tempfile::tempdir;
     use std::fs;
     
@@ -422,7 +693,9 @@ mod tests {
             min_file_size: 1,
             scan_interval_ms: 100,
             recursive_scan: true,
+            max_scan_depth: 2,
             enable_fs_watching: false,
+            watch_debounce_ms: 50,
             max_file_age_hours: Some(24),
         }
     }
@@ -436,6 +709,29 @@ mod tests {
         assert_eq!(discovery.stats().total_files_discovered, 0);
     }
     
+    #[test]
+    fn test_reloaded_tunables_are_applied() {
+        use swarm_core::{ConfigWatcher, LayeredConfig, DISCOVERY_SECTION};
+        
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("swarm.toml");
+        fs::write(&path, "[discovery]\nscan_interval_ms = 100\n").unwrap();
+        let config = LayeredConfig::from_file(&path).unwrap();
+        let mut watcher = ConfigWatcher::new(config.clone());
+        let mut discovery = FileDiscoveryService::new(config.section(DISCOVERY_SECTION).unwrap())
+            .with_config_updates(watcher.updates(DISCOVERY_SECTION));
+        assert_eq!(discovery.config().scan_interval_ms, 100);
+        assert_eq!(discovery.config().max_file_size, FileDiscoveryConfig::default().max_file_size);
+        
+        fs::write(&path, "[discovery]\nscan_interval_ms = 2500\nmax_file_size = 1024\nrecursive_scan = false\n").unwrap();
+        assert!(watcher.check().unwrap());
+        discovery.apply_config_updates();
+        assert_eq!(discovery.config().scan_interval_ms, 2500);
+        assert_eq!(discovery.config().max_file_size, 1024);
+        // Only tunables change while running
+        assert!(discovery.config().recursive_scan);
+    }
+    
     #[test]
     fn test_include_pattern_matching() {
         let config = create_test_config();
@@ -538,4 +834,113 @@ mod tests {
         assert_eq!(stats.files_by_extension.get("txt"), Some(&1));
         assert_eq!(stats.files_by_extension.get("md"), Some(&1));
     }
+    
+    #[tokio::test]
+    async fn test_recursive_scanning() {
+        let temp_dir = tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join("a/b/c")).unwrap();
+        fs::create_dir_all(temp_dir.path().join(".cache")).unwrap();
+        for file in ["top.txt", "a/one.txt", "a/b/two.md", "a/b/c/too-deep.txt", ".cache/hidden-dir.txt"] {
+            fs::write(temp_dir.path().join(file), "Test content").unwrap();
+        }
+        
+        let mut discovery = FileDiscoveryService::new(create_test_config());
+        let files = discovery.scan_directory(temp_dir.path()).await.unwrap();
+        let mut filenames: Vec<&str> = files.iter().map(|f| f.filename.as_str()).collect();
+        filenames.sort();
+        assert_eq!(filenames, vec!["one.txt", "top.txt", "two.md"]);
+        assert_eq!(discovery.stats().total_directories_scanned, 3);
+        
+        let mut config = create_test_config();
+        config.recursive_scan = false;
+        let mut discovery = FileDiscoveryService::new(config);
+        assert_eq!(discovery.scan_directory(temp_dir.path()).await.unwrap().len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_glob_patterns_on_nested_paths() {
+        let temp_dir = tempdir().unwrap();
+        for file in ["a/b/two.md", "a/cache.tmp", "build/out.txt", "build/report.txt", "top.txt"] {
+            let path = temp_dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "Test content").unwrap();
+        }
+        
+        let mut config = create_test_config();
+        config.watch_directories = vec![temp_dir.path().to_path_buf()];
+        config.exclude_patterns = vec![".*".to_string(), "*.tmp".to_string(), "build/**".to_string(), "!build/report.txt".to_string()];
+        let mut discovery = FileDiscoveryService::new(config.clone());
+        let mut filenames: Vec<String> = discovery.scan_directory(temp_dir.path()).await.unwrap()
+            .into_iter().map(|f| f.filename).collect();
+        filenames.sort();
+        assert_eq!(filenames, vec!["report.txt", "top.txt", "two.md"]);
+        
+        config.include_patterns = vec!["a/**/*.md".to_string()];
+        let mut discovery = FileDiscoveryService::new(config);
+        let files = discovery.scan_directory(temp_dir.path()).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].filename, "two.md");
+    }
+    
+    /// Write through an excluded temp file so scans never see partial content
+    fn write_atomically(path: &Path, content: &str) {
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, content).unwrap();
+        fs::rename(&temporary, path).unwrap();
+    }
+    
+    async fn next_event(events: &mut mpsc::UnboundedReceiver<FileEvent>) -> FileEvent {
+        tokio::time::timeout(Duration::from_secs(5), events.recv()).await
+            .expect("timed out waiting for a file event")
+            .expect("event channel closed")
+    }
+    
+    async fn assert_create_modify_remove(enable_fs_watching: bool) {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("existing.txt"), "Already there").unwrap();
+        
+        let mut config = create_test_config();
+        config.watch_directories = vec![temp_dir.path().to_path_buf()];
+        config.enable_fs_watching = enable_fs_watching;
+        let mut discovery = FileDiscoveryService::new(config);
+        let mut events = discovery.subscribe_events();
+        let inventory = discovery.inventory();
+        let shutdown = discovery.shutdown_token();
+        let service = tokio::spawn(async move { discovery.start().await });
+        
+        assert!(matches!(next_event(&mut events).await, FileEvent::Created(file) if file.filename == "existing.txt"));
+        let existing = temp_dir.path().join("existing.txt");
+        assert_eq!(inventory.state(&existing), Some(FileState::Discovered));
+        inventory.mark_queued(&existing);
+        
+        let path = temp_dir.path().join("a/new.md");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        write_atomically(&path, "# New");
+        fs::write(temp_dir.path().join("ignored.tmp"), "Excluded").unwrap();
+        assert!(matches!(next_event(&mut events).await, FileEvent::Created(file) if file.path == path));
+        
+        write_atomically(&path, "# New, with more content");
+        assert!(matches!(next_event(&mut events).await, FileEvent::Modified(file) if file.size_bytes == 24));
+        
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(next_event(&mut events).await, FileEvent::Removed(file) if file.path == path));
+        let report = inventory.query(&InventoryQuery::default());
+        assert_eq!((report.total, report.counts[&FileState::Queued]), (1, 1));
+        
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(1), service).await
+            .expect("discovery did not stop after cancellation")
+            .unwrap()
+            .unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_fs_watching_emits_debounced_events() {
+        assert_create_modify_remove(true).await;
+    }
+    
+    #[tokio::test]
+    async fn test_polling_emits_events() {
+        assert_create_modify_remove(false).await;
+    }
 }
@@ -0,0 +1,175 @@
+//! HTML Text Extraction
+//!
+//! HTML documents are parsed into a DOM by html5ever, which recovers from
+//! malformed markup the way browsers do. Text is collected in document
+//! order: script, style and other non-content elements are dropped with
+//! their content, headings and other block elements start a new line, and
+//! link text stays in place. The title, meta tags, declared language and
+//! heading outline are returned alongside the text.
+
+use crate::sanitization::{BLOCK_ELEMENTS, DEFAULT_STRIPPED_ELEMENTS};
+use ego_tree::iter::Edge;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::BTreeMap;
+
+/// A heading of the document outline
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HtmlHeading {
+    /// 1 for `h1` through 6 for `h6`
+    pub level: u8,
+    pub text: String,
+}
+
+/// A link with its text
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HtmlLink {
+    pub text: String,
+    pub href: String,
+}
+
+/// Text and metadata extracted from an HTML document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HtmlExtraction {
+    /// Visible text, one line per heading, paragraph or other block
+    pub text: String,
+    pub title: Option<String>,
+    
+    /// `content` of meta tags by their `name`, `property` or `http-equiv`, lowercased
+    pub meta: BTreeMap<String, String>,
+    
+    /// `lang` of the root element
+    pub language: Option<String>,
+    pub headings: Vec<HtmlHeading>,
+    pub links: Vec<HtmlLink>,
+}
+
+/// Parse HTML and extract its text, title, meta tags, headings and links
+pub fn extract_html(html: &str) -> HtmlExtraction {
+    let document = Html::parse_document(html);
+    let mut extraction = HtmlExtraction {
+        title: document.select(&selector("title")).next().and_then(|title| non_empty(&collapse(title.text()))),
+        language: document.root_element().attr("lang").and_then(|lang| non_empty(lang.trim())),
+        ..Default::default()
+    };
+    for meta in document.select(&selector("meta[content]")) {
+        let element = meta.value();
+        let key = element.attr("name").or_else(|| element.attr("property")).or_else(|| element.attr("http-equiv"));
+        if let (Some(key), Some(content)) = (key, element.attr("content")) {
+            extraction.meta.entry(key.trim().to_lowercase()).or_insert_with(|| content.trim().to_string());
+        }
+    }
+    
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    // Stripped element whose content is being skipped
+    let mut skipping = None;
+    
+    for edge in document.tree.root().traverse() {
+        match edge {
+            Edge::Open(node) if skipping.is_none() => match node.value() {
+                Node::Text(text) => line.push_str(text),
+                Node::Element(element) => {
+                    let name = element.name();
+                    let element_ref = ElementRef::wrap(node).expect("element node");
+                    if let ("a", Some(href)) = (name, element.attr("href")) {
+                        extraction.links.push(HtmlLink { text: collapse(element_ref.text()), href: href.trim().to_string() });
+                    }
+                    if let Some(level) = heading_level(name) {
+                        if let Some(text) = non_empty(&collapse(element_ref.text())) {
+                            extraction.headings.push(HtmlHeading { level, text });
+                        }
+                    }
+                    
+                    if DEFAULT_STRIPPED_ELEMENTS.contains(&name) {
+                        skipping = Some(node.id());
+                    } else if BLOCK_ELEMENTS.contains(&name) {
+                        end_line(&mut lines, &mut line);
+                    }
+                }
+                _ => {}
+            },
+            Edge::Close(node) => {
+                if skipping == Some(node.id()) {
+                    skipping = None;
+                } else if skipping.is_none() && node.value().as_element().is_some_and(|element| BLOCK_ELEMENTS.contains(&element.name())) {
+                    end_line(&mut lines, &mut line);
+                }
+            }
+            Edge::Open(_) => {}
+        }
+    }
+    end_line(&mut lines, &mut line);
+    
+    extraction.text = lines.join("\n");
+    extraction
+}
+
+/// Finish the current line, dropping it if it is only whitespace
+fn end_line(lines: &mut Vec<String>, line: &mut String) {
+    if let Some(text) = non_empty(&collapse(std::iter::once(line.as_str()))) {
+        lines.push(text);
+    }
+    line.clear();
+}
+
+fn collapse<'a>(text: impl Iterator<Item = &'a str>) -> String {
+    text.flat_map(str::split_whitespace).collect::<Vec<_>>().join(" ")
+}
+
+fn selector(selectors: &str) -> Selector {
+    Selector::parse(selectors).expect("valid selector")
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn heading_level(name: &str) -> Option<u8> {
+    match name.as_bytes() {
+        [b'h', level @ b'1'..=b'6'] => Some(level - b'0'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_text_keeps_headings_and_links_but_not_scripts() {
+        let html = r#"<!DOCTYPE html>
+            <html lang="en"><head>
+              <title>Quarterly   Report</title>
+              <meta name="Description" content="Q3 results">
+              <meta property="og:type" content="article">
+              <script>var secret = "<p>not text</p>";</script>
+              <style>p { color: red }</style>
+            </head><body>
+              <h1>Results</h1>
+              <p>Revenue grew <b>12%</b>, see <a href="/details">the details</a>.</p>
+              <noscript>Enable JavaScript</noscript>
+              <h2>Outlook &amp; risks</h2><ul><li>Supply</li><li>Rates</li></ul>
+            </body></html>"#;
+        let extraction = extract_html(html);
+        
+        assert_eq!(extraction.text, "Results\nRevenue grew 12%, see the details.\nOutlook & risks\nSupply\nRates");
+        assert_eq!(extraction.title.as_deref(), Some("Quarterly Report"));
+        assert_eq!(extraction.meta["description"], "Q3 results");
+        assert_eq!(extraction.meta["og:type"], "article");
+        assert_eq!(extraction.language.as_deref(), Some("en"));
+        assert_eq!(extraction.headings, vec![
+            HtmlHeading { level: 1, text: "Results".to_string() },
+            HtmlHeading { level: 2, text: "Outlook & risks".to_string() },
+        ]);
+        assert_eq!(extraction.links, vec![HtmlLink { text: "the details".to_string(), href: "/details".to_string() }]);
+    }
+    
+    #[test]
+    fn test_malformed_markup_is_recovered() {
+        let extraction = extract_html("<p>Unclosed <i>italic<p>Next paragraph<script>if (a < b) {}");
+        assert_eq!(extraction.text, "Unclosed italic\nNext paragraph");
+        assert_eq!(extraction.title, None);
+        assert!(extraction.meta.is_empty() && extraction.headings.is_empty());
+        assert_eq!(extract_html("").text, "");
+    }
+}
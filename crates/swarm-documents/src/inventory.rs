@@ -0,0 +1,346 @@
+//! Discovery Inventory
+//!
+//! Files found by `FileDiscoveryService` move through discovered, queued and
+//! processed. The service records discoveries, changes and removals; a
+//! `DiscoveryPublisher` submits the files and marks them queued, then
+//! processed once their results arrive. The inventory answers "what has
+//! been found but not processed yet?" with counts per state, age buckets and
+//! a listing of the files themselves, also to `swarm inventory` through the
+//! `ADMIN_INVENTORY_SUBJECT` administration subject.
+
+use crate::content_sniffing::{sniff_document_type, SNIFF_LENGTH};
+use crate::dedup::CONTENT_HASH_KEY;
+use crate::file_discovery::{FileDiscoveryService, FileEvent, FileInfo};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use swarm_core::prelude::*;
+use swarm_core::{cancellable_sleep, CancellationToken, Service};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+pub use swarm_core::inventory::{AgeBucket, FileState, InventoryEntry, InventoryQuery, InventoryReport, ADMIN_INVENTORY_SUBJECT};
+
+/// Lifecycle state of discovered files, shared between clones
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryInventory {
+    entries: Arc<RwLock<HashMap<PathBuf, InventoryEntry>>>,
+}
+
+impl DiscoveryInventory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Record a new or changed file as discovered, restarting its lifecycle
+    pub fn record_discovered(&self, file: &FileInfo) {
+        let now = Utc::now();
+        self.entries.write().unwrap().insert(file.path.clone(), InventoryEntry {
+            path: file.path.clone(),
+            state: FileState::Discovered,
+            size_bytes: file.size_bytes,
+            discovered_at: now,
+            state_since: now,
+        });
+    }
+    
+    /// Mark a file as submitted for processing; false if it is not in the inventory
+    pub fn mark_queued(&self, path: &Path) -> bool {
+        self.transition(path, FileState::Queued)
+    }
+    
+    /// Mark a file as processed; false if it is not in the inventory
+    pub fn mark_processed(&self, path: &Path) -> bool {
+        self.transition(path, FileState::Processed)
+    }
+    
+    /// Forget a file that no longer exists
+    pub fn remove(&self, path: &Path) -> Option<InventoryEntry> {
+        self.entries.write().unwrap().remove(path)
+    }
+    
+    pub fn state(&self, path: &Path) -> Option<FileState> {
+        self.entries.read().unwrap().get(path).map(|entry| entry.state)
+    }
+    
+    fn transition(&self, path: &Path, state: FileState) -> bool {
+        let mut entries = self.entries.write().unwrap();
+        let Some(entry) = entries.get_mut(path) else { return false };
+        if entry.state != state {
+            entry.state = state;
+            entry.state_since = Utc::now();
+        }
+        true
+    }
+    
+    pub fn query(&self, query: &InventoryQuery) -> InventoryReport {
+        self.query_at(query, Utc::now())
+    }
+    
+    /// Report with ages measured at `now`
+    pub fn query_at(&self, query: &InventoryQuery, now: DateTime<Utc>) -> InventoryReport {
+        let entries = self.entries.read().unwrap();
+        let mut counts = BTreeMap::new();
+        let mut age_buckets: BTreeMap<FileState, BTreeMap<AgeBucket, u64>> = BTreeMap::new();
+        let mut files = Vec::new();
+        for entry in entries.values() {
+            *counts.entry(entry.state).or_insert(0) += 1;
+            *age_buckets.entry(entry.state).or_default()
+                .entry(AgeBucket::of(now - entry.state_since)).or_insert(0) += 1;
+            if query.matches(entry, now) {
+                files.push(entry.clone());
+            }
+        }
+        
+        files.sort_by(|a, b| a.state_since.cmp(&b.state_since).then_with(|| a.path.cmp(&b.path)));
+        if let Some(limit) = query.limit {
+            files.truncate(limit);
+        }
+        InventoryReport {
+            generated_at: now,
+            total: entries.len() as u64,
+            counts,
+            age_buckets,
+            files,
+        }
+    }
+}
+
+/// Publishes discovered files as documents and follows them to their results
+///
+/// Created and modified files are published on the documents subject and
+/// marked queued. A completed result naming one of their documents under
+/// `DOCUMENT_ID_KEY` marks the file processed; files whose processing failed
+/// stay queued, so they show up as stuck. Inventory requests on
+/// `ADMIN_INVENTORY_SUBJECT` are answered while the publisher runs.
+pub struct DiscoveryPublisher {
+    events: mpsc::UnboundedReceiver<FileEvent>,
+    inventory: DiscoveryInventory,
+    broker: Arc<dyn MessageBroker>,
+    documents_subject: String,
+    results_subject: String,
+    poll_interval: Duration,
+    queued: HashMap<Uuid, PathBuf>,
+}
+
+impl DiscoveryPublisher {
+    /// Publish the files `discovery` finds to `documents_subject`, reading results from `results_subject`
+    pub fn new(discovery: &mut FileDiscoveryService, broker: Arc<dyn MessageBroker>, documents_subject: impl Into<String>, results_subject: impl Into<String>) -> Self {
+        Self {
+            events: discovery.subscribe_events(),
+            inventory: discovery.inventory(),
+            broker,
+            documents_subject: documents_subject.into(),
+            results_subject: results_subject.into(),
+            poll_interval: Duration::from_millis(50),
+            queued: HashMap::new(),
+        }
+    }
+    
+    /// How often events, results and requests are checked while idle
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+    
+    /// Publish the document of a discovered file and mark the file queued
+    pub async fn publish(&mut self, file: &FileInfo) -> Result<Document> {
+        let bytes = tokio::fs::read(&file.path).await?;
+        let detection = sniff_document_type(&file.path, &bytes[..bytes.len().min(SNIFF_LENGTH)]);
+        let content = match detection.document_type {
+            DocumentType::Text | DocumentType::Html | DocumentType::Markdown => {
+                DocumentContent::Text(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            _ => DocumentContent::Binary(bytes),
+        };
+        let hash = format!("sha256:{}", swarm_core::content_hash(&content));
+        let document = DocumentBuilder::new(file.filename.clone(), content)
+            .document_type(detection.document_type)
+            .metadata("file_path", serde_json::json!(file.path.to_string_lossy()))
+            .metadata("mime_type", serde_json::json!(detection.mime_type))
+            .metadata(CONTENT_HASH_KEY, serde_json::json!(hash))
+            .build();
+        self.broker.publish(&self.documents_subject, &serde_json::to_vec(&document)?).await?;
+        
+        self.queued.retain(|_, path| *path != file.path);
+        self.queued.insert(document.id, file.path.clone());
+        self.inventory.mark_queued(&file.path);
+        tracing::debug!("Published {} as document {}", file.path.display(), document.id);
+        Ok(document)
+    }
+    
+    /// Mark the file of a completed document processed; false if the result is not for one
+    pub fn record_result(&mut self, result: &TaskResult) -> bool {
+        if result.status != TaskStatus::Completed {
+            return false;
+        }
+        let Some(path) = result.document_id().and_then(|document_id| self.queued.remove(&document_id)) else {
+            return false;
+        };
+        self.inventory.mark_processed(&path)
+    }
+    
+    async fn handle_event(&mut self, event: FileEvent) {
+        match event {
+            FileEvent::Created(file) | FileEvent::Modified(file) => {
+                if let Err(e) = self.publish(&file).await {
+                    tracing::error!("Failed to publish {}: {}", file.path.display(), e);
+                }
+            }
+            FileEvent::Removed(file) => self.queued.retain(|_, path| *path != file.path),
+        }
+    }
+    
+    async fn answer(&self, message: &Message) -> Result<()> {
+        let Some(reply_to) = message.reply_to() else {
+            tracing::warn!("Inventory request on {} has no reply subject", message.subject);
+            return Ok(());
+        };
+        let query = if message.payload.is_empty() {
+            InventoryQuery::default()
+        } else {
+            serde_json::from_slice(&message.payload)?
+        };
+        self.broker.publish(reply_to, &serde_json::to_vec(&self.inventory.query(&query))?).await
+    }
+}
+
+#[async_trait]
+impl Service for DiscoveryPublisher {
+    fn name(&self) -> &str {
+        "discovery-publisher"
+    }
+    
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        let mut results = self.broker.subscribe(&self.results_subject).await?;
+        let mut requests = self.broker.subscribe(ADMIN_INVENTORY_SUBJECT).await?;
+        loop {
+            while let Ok(event) = self.events.try_recv() {
+                self.handle_event(event).await;
+            }
+            while let Some(message) = results.next_message()? {
+                match serde_json::from_slice::<TaskResult>(&message.payload) {
+                    Ok(result) => {
+                        self.record_result(&result);
+                    }
+                    Err(e) => tracing::warn!("Skipping undecodable result {}: {}", message.id, e),
+                }
+            }
+            while let Some(message) = requests.next_message()? {
+                if let Err(e) = self.answer(&message).await {
+                    tracing::warn!("Failed to answer inventory request {}: {}", message.id, e);
+                }
+            }
+            if cancellable_sleep(&cancel, self.poll_interval).await {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn file(path: &str) -> FileInfo {
+        FileInfo {
+            path: PathBuf::from(path),
+            filename: path.rsplit('/').next().unwrap().to_string(),
+            extension: Some("txt".to_string()),
+            size_bytes: 10,
+            modified_time: Utc::now(),
+            created_time: Utc::now(),
+            is_hidden: false,
+            is_symlink: false,
+            mime_type: Some("text/plain".to_string()),
+        }
+    }
+    
+    #[test]
+    fn test_inventory_tracks_lifecycle_and_ages() {
+        let inventory = DiscoveryInventory::new();
+        for path in ["/in/a.txt", "/in/b.txt", "/in/c.txt"] {
+            inventory.record_discovered(&file(path));
+        }
+        assert!(inventory.mark_queued(Path::new("/in/b.txt")));
+        assert!(inventory.mark_queued(Path::new("/in/c.txt")) && inventory.mark_processed(Path::new("/in/c.txt")));
+        assert!(!inventory.mark_queued(Path::new("/in/unknown.txt")));
+        
+        let later = Utc::now() + chrono::Duration::minutes(5);
+        let report = inventory.query_at(&InventoryQuery::unprocessed(), later);
+        assert_eq!(report.total, 3);
+        assert_eq!(report.counts.values().copied().collect::<Vec<_>>(), vec![1, 1, 1]);
+        assert_eq!(report.age_buckets[&FileState::Discovered], BTreeMap::from([(AgeBucket::UnderOneHour, 1)]));
+        assert_eq!(report.files.iter().map(|entry| entry.path.to_str().unwrap()).collect::<Vec<_>>(), vec!["/in/a.txt"]);
+        
+        let query = InventoryQuery { state: None, min_age_secs: Some(600), limit: None };
+        assert!(inventory.query_at(&query, later).files.is_empty());
+        
+        // A changed file starts over, a removed one is forgotten
+        inventory.record_discovered(&file("/in/c.txt"));
+        inventory.remove(Path::new("/in/a.txt"));
+        let report = inventory.query(&InventoryQuery { limit: Some(1), ..InventoryQuery::unprocessed() });
+        assert_eq!(report.counts.get(&FileState::Processed), None);
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(inventory.state(Path::new("/in/c.txt")), Some(FileState::Discovered));
+    }
+    
+    #[tokio::test]
+    async fn test_published_files_are_queued_until_processed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "hello swarm").unwrap();
+        let mut discovered = file(path.to_str().unwrap());
+        discovered.size_bytes = 11;
+        
+        let broker = Arc::new(swarm_core::MemoryBroker::new());
+        let mut incoming = broker.subscribe("swarm.documents.incoming").await.unwrap();
+        let mut discovery = FileDiscoveryService::new(Default::default());
+        let inventory = discovery.inventory();
+        let mut publisher = DiscoveryPublisher::new(&mut discovery, broker.clone(), "swarm.documents.incoming", "swarm.documents.results")
+            .with_poll_interval(Duration::from_millis(1));
+        inventory.record_discovered(&discovered);
+        
+        let document = publisher.publish(&discovered).await.unwrap();
+        let published: Document = serde_json::from_slice(&incoming.next_message().unwrap().unwrap().payload).unwrap();
+        assert_eq!((published.id, published.document_type), (document.id, DocumentType::Text));
+        assert_eq!(inventory.state(&path), Some(FileState::Queued));
+        
+        let cancel = CancellationToken::new();
+        let running = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { publisher.run(cancel).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        
+        // A failed attempt leaves the file queued, a completed one processes it
+        let mut result = TaskResult {
+            task_id: Uuid::new_v4(),
+            status: TaskStatus::Failed,
+            result: None,
+            error: Some("extraction failed".to_string()),
+            processing_time_ms: 3,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        };
+        result.record_document_id(document.id);
+        broker.publish("swarm.documents.results", &serde_json::to_vec(&result).unwrap()).await.unwrap();
+        let query = serde_json::to_vec(&InventoryQuery { state: Some(FileState::Queued), ..Default::default() }).unwrap();
+        let reply = broker.request(ADMIN_INVENTORY_SUBJECT, &query, Duration::from_secs(1)).await.unwrap();
+        let report: InventoryReport = serde_json::from_slice(&reply.payload).unwrap();
+        assert_eq!(report.files.iter().map(|entry| entry.path.clone()).collect::<Vec<_>>(), vec![path.clone()]);
+        
+        let completed = TaskResult { status: TaskStatus::Completed, error: None, ..result };
+        broker.publish("swarm.documents.results", &serde_json::to_vec(&completed).unwrap()).await.unwrap();
+        let reply = broker.request(ADMIN_INVENTORY_SUBJECT, &[], Duration::from_secs(1)).await.unwrap();
+        let report: InventoryReport = serde_json::from_slice(&reply.payload).unwrap();
+        assert_eq!(report.counts, BTreeMap::from([(FileState::Processed, 1)]));
+        
+        cancel.cancel();
+        running.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+//! Keyword Extraction
+//!
+//! Keywords are the highest scoring terms of a document after stop words are
+//! removed. Inflected forms are grouped by their stem ("reports" and
+//! "reporting" count as one term) and reported by their most frequent surface
+//! form. Terms are weighted by TF-IDF against `CorpusStatistics`, which
+//! accumulate document frequencies as documents are processed; until the
+//! corpus has grown the ranking is plain term frequency.
+
+use super::*;
+use std::collections::HashSet;
+
+/// Keyword extraction configuration
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct KeywordConfig {
+    /// Number of keywords per document
+    pub max_keywords: usize,
+    
+    /// Drop stop words of the detected language
+    pub remove_stop_words: bool,
+    
+    /// Additional stop words by language code, merged with the built-in lists
+    pub stop_words: HashMap<String, Vec<String>>,
+    
+    /// Group inflected forms by stem (English and Swedish)
+    pub stemming: bool,
+    
+    /// Weight terms by inverse document frequency across processed documents
+    pub tf_idf: bool,
+}
+
+impl Default for KeywordConfig {
+    fn default() -> Self {
+        Self {
+            max_keywords: 10,
+            remove_stop_words: true,
+            stop_words: HashMap::new(),
+            stemming: true,
+            tf_idf: true,
+        }
+    }
+}
+
+/// Document frequencies of terms across processed documents
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CorpusStatistics {
+    /// Documents added
+    pub documents: u64,
+    
+    /// Number of documents containing each term
+    pub document_frequencies: HashMap<String, u64>,
+}
+
+impl CorpusStatistics {
+    /// Count the distinct terms of one document
+    pub fn add_document<'a>(&mut self, terms: impl IntoIterator<Item = &'a String>) {
+        let distinct: HashSet<&String> = terms.into_iter().collect();
+        for term in distinct {
+            *self.document_frequencies.entry(term.clone()).or_insert(0) += 1;
+        }
+        self.documents += 1;
+    }
+    
+    /// Smoothed inverse document frequency, 1.0 for an empty corpus
+    pub fn idf(&self, term: &str) -> f32 {
+        if self.documents == 0 {
+            return 1.0;
+        }
+        let document_frequency = self.document_frequencies.get(term).copied().unwrap_or(0);
+        ((1.0 + self.documents as f32) / (1.0 + document_frequency as f32)).ln() + 1.0
+    }
+}
+
+/// A keyword candidate: its stem and the form it appeared in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordTerm {
+    pub stem: String,
+    pub surface: String,
+}
+
+/// Stop-word and stem aware keyword extractor
+#[derive(Debug, Clone, Default)]
+pub struct KeywordExtractor {
+    config: KeywordConfig,
+}
+
+impl KeywordExtractor {
+    pub fn new(config: KeywordConfig) -> Self {
+        Self { config }
+    }
+    
+    pub fn config(&self) -> &KeywordConfig {
+        &self.config
+    }
+    
+    /// Keyword candidates of a text in `language`, in order of appearance
+    pub fn terms(&self, text: &str, language: Option<&str>) -> Vec<KeywordTerm> {
+        let segmenter = segmenter_for_language(detect_cjk_language(text));
+        segmenter.segment(text)
+            .into_iter()
+            .filter(|word| is_keyword_candidate(word))
+            .map(|word| word.to_lowercase())
+            .filter(|word| !self.is_stop_word(word, language))
+            .map(|surface| {
+                let stem = if self.config.stemming { stem(&surface, language) } else { surface.clone() };
+                KeywordTerm { stem, surface }
+            })
+            .collect()
+    }
+    
+    /// Top keywords of `terms`, scored by TF-IDF against `corpus`
+    pub fn rank(&self, terms: &[KeywordTerm], corpus: &CorpusStatistics) -> Vec<String> {
+        let mut frequencies: HashMap<&str, usize> = HashMap::new();
+        let mut surfaces: HashMap<&str, HashMap<&str, usize>> = HashMap::new();
+        for term in terms {
+            *frequencies.entry(&term.stem).or_insert(0) += 1;
+            *surfaces.entry(&term.stem).or_default().entry(&term.surface).or_insert(0) += 1;
+        }
+        
+        let mut scored: Vec<(&str, f32)> = frequencies.into_iter()
+            .map(|(stem, count)| {
+                let idf = if self.config.tf_idf { corpus.idf(stem) } else { 1.0 };
+                (stem, count as f32 * idf)
+            })
+            .collect();
+        // Ties are broken alphabetically so the ranking is deterministic
+        scored.sort_by(|(a, a_score), (b, b_score)| b_score.total_cmp(a_score).then_with(|| a.cmp(b)));
+        
+        scored.into_iter()
+            .take(self.config.max_keywords)
+            .map(|(stem, _)| most_frequent_surface(&surfaces[stem]).to_string())
+            .collect()
+    }
+    
+    /// Whether `word` (lowercase) is a stop word of `language`
+    pub fn is_stop_word(&self, word: &str, language: Option<&str>) -> bool {
+        if !self.config.remove_stop_words {
+            return false;
+        }
+        let language = language.unwrap_or("en");
+        builtin_stop_words(language).contains(&word)
+            || self.config.stop_words.get(language).is_some_and(|words| words.iter().any(|stop| stop == word))
+    }
+}
+
+/// Most frequent form of a stem, the shortest on ties
+fn most_frequent_surface<'a>(surfaces: &HashMap<&'a str, usize>) -> &'a str {
+    surfaces.iter()
+        .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then_with(|| b.len().cmp(&a.len())).then_with(|| b.cmp(a)))
+        .map(|(surface, _)| *surface)
+        .unwrap_or_default()
+}
+
+/// Built-in stop words by language code; only words that pass
+/// `is_keyword_candidate` need to be listed
+pub fn builtin_stop_words(language: &str) -> &'static [&'static str] {
+    match language {
+        "en" => &[
+            "about", "above", "after", "again", "against", "also", "although", "because", "been", "before",
+            "being", "below", "between", "both", "could", "does", "doing", "down", "during", "each", "either",
+            "even", "every", "from", "further", "have", "having", "here", "hers", "herself", "himself", "into",
+            "itself", "just", "many", "might", "more", "most", "much", "must", "myself", "neither", "never",
+            "once", "only", "other", "ours", "ourselves", "over", "same", "shall", "should", "some", "such",
+            "than", "that", "their", "theirs", "them", "themselves", "then", "there", "these", "they", "this",
+            "those", "though", "through", "under", "until", "upon", "very", "were", "what", "when", "where",
+            "whether", "which", "while", "whom", "whose", "will", "with", "within", "without", "would", "your",
+            "yours", "yourself", "yourselves",
+        ],
+        "sv" => &[
+            "alla", "allt", "andra", "annan", "annat", "blev", "blir", "bara", "denna", "dessa", "deras",
+            "detta", "dina", "ditt", "efter", "eller", "ert", "era", "från", "genom", "hade", "hans", "hennes",
+            "honom", "icke", "inom", "inte", "kunde", "mellan", "men", "mina", "mitt", "måste", "något",
+            "några", "när", "också", "över", "samma", "sedan", "sina", "sitt", "skall", "skulle", "till",
+            "under", "utan", "vara", "varit", "vars", "vart", "vilka", "vilken", "vilket", "våra",
+            "vårt", "ännu", "åt",
+        ],
+        "zh" => &["我们", "你们", "他们", "她们", "这个", "那个", "这些", "那些", "一个", "没有", "因为", "所以", "但是", "如果", "可以", "已经", "还是", "就是"],
+        "ja" => &["これ", "それ", "あれ", "この", "その", "あの", "ここ", "そこ", "こと", "もの", "ため", "よう", "から", "まで", "です", "ます", "した", "して"],
+        _ => &[],
+    }
+}
+
+/// Light suffix-stripping stem of a lowercase word; not a full Porter
+/// stemmer, but enough to group plurals and common verb forms
+pub fn stem(word: &str, language: Option<&str>) -> String {
+    let suffixes: &[(&str, &str)] = match language.unwrap_or("en") {
+        "en" => &[
+            ("ational", "ate"), ("ization", "ize"), ("fulness", "ful"), ("iveness", "ive"),
+            ("sses", "ss"), ("ies", "y"), ("ing", ""), ("edly", ""), ("ed", ""), ("ly", ""), ("s", ""),
+        ],
+        "sv" => &[
+            ("heterna", ""), ("heten", ""), ("arna", ""), ("erna", ""), ("orna", ""), ("ande", ""),
+            ("ende", ""), ("are", ""), ("ar", ""), ("er", ""), ("or", ""), ("en", ""), ("et", ""),
+        ],
+        _ => return word.to_string(),
+    };
+    
+    for (suffix, replacement) in suffixes {
+        if let Some(base) = word.strip_suffix(suffix) {
+            // Keep short words and words like "class" or "status" intact
+            let protected = *suffix == "s" && (base.ends_with('s') || base.ends_with('u') || base.ends_with('i'));
+            if base.chars().count() >= 3 && !protected {
+                return format!("{}{}", base, replacement);
+            }
+        }
+    }
+    word.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_stop_words_and_stems() {
+        let extractor = KeywordExtractor::default();
+        let text = "This report covers reports and reporting. Their budgets were reviewed with these budget owners.";
+        let terms = extractor.terms(text, Some("en"));
+        let surfaces: Vec<&str> = terms.iter().map(|term| term.surface.as_str()).collect();
+        assert!(!surfaces.iter().any(|word| ["this", "their", "were", "with", "these"].contains(word)));
+        assert_eq!(terms.iter().filter(|term| term.stem == "report").count(), 3);
+        
+        assert_eq!(stem("categories", Some("en")), "category");
+        assert_eq!(stem("status", Some("en")), "status");
+        assert_eq!(stem("rapporterna", Some("sv")), "rapport");
+        assert_eq!(stem("機械", Some("ja")), "機械");
+        
+        let custom = KeywordExtractor::new(KeywordConfig {
+            stop_words: HashMap::from([("en".to_string(), vec!["budget".to_string()])]),
+            ..Default::default()
+        });
+        assert!(custom.is_stop_word("budget", Some("en")) && !custom.is_stop_word("budget", Some("sv")));
+    }
+    
+    #[test]
+    fn test_tf_idf_ranks_distinctive_terms_first() {
+        let extractor = KeywordExtractor::default();
+        let mut corpus = CorpusStatistics::default();
+        let corpus_texts = [
+            "quarterly report for finance", "report on the office move", "annual report and review",
+            "report from the board", "report about hiring", "sales report",
+        ];
+        for text in corpus_texts {
+            let terms = extractor.terms(text, Some("en"));
+            corpus.add_document(terms.iter().map(|term| &term.stem));
+        }
+        
+        let terms = extractor.terms("Report report covering invoices", Some("en"));
+        corpus.add_document(terms.iter().map(|term| &term.stem));
+        assert_eq!(extractor.rank(&terms, &CorpusStatistics::default()), vec!["report", "covering", "invoices"]);
+        assert_eq!(extractor.rank(&terms, &corpus), vec!["covering", "invoices", "report"]);
+        assert_eq!(corpus.documents, 7);
+        assert_eq!(corpus.document_frequencies["report"], 7);
+    }
+}
@@ -5,7 +5,7 @@
 //! defined in swarm-core with real document processing capabilities.
 
 use swarm_core::prelude::*;
-use swarm_core::{DocumentProcessingOptions, TextAnalysisOptions};
+use swarm_core::{AdaptiveConcurrencyConfig, ConcurrencyStats, DeterminismConfig, DocumentProcessingOptions, DocumentRejection, TextAnalysisOptions};
 use anyhow::Result;
 use std::collections::HashMap;
 use std::path::Path;
@@ -14,14 +14,91 @@ use uuid::Uuid;
 use chrono::Utc;
 
 // Core modules
+pub mod canary;
+pub mod content_sniffing;
+pub mod content_tiering;
+pub mod converter;
+pub mod credentials;
+pub mod dedup;
+pub mod email_source;
+pub mod directory_walker;
 pub mod document_processor;
 pub mod document_reader;
+pub mod embeddings;
+pub mod export;
 pub mod file_discovery;
+pub mod html;
+pub mod inventory;
+pub mod keywords;
+pub mod link_crawling;
+pub mod markdown;
+pub mod mime_registry;
+pub mod ner;
+pub mod normalization;
+pub mod ocr;
+pub(crate) mod ooxml;
+pub mod path_patterns;
+pub mod quality;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod pptx;
+pub mod processing_state;
+pub mod reference;
+pub mod sanitization;
+pub mod result_sink;
+pub mod s3_source;
+pub mod s3_store;
+pub mod segmentation;
+pub mod sidecar;
+pub mod streaming;
+pub mod temp_files;
+pub mod url_source;
+#[cfg(feature = "tesseract")]
+pub mod tesseract;
+pub mod xlsx;
 
 // Re-export main components
+pub use canary::{diff_results, CanaryConfig, CanaryProcessor, DivergenceReport, ResultDiff, CANARY_REPORT_SUBJECT};
+pub use content_tiering::{StorageTier, TieredContentCache, TieringPolicy, TieringReport};
+pub use content_sniffing::{sniff_document_type, TypeDetection, SNIFF_LENGTH};
+pub use converter::*;
+pub use credentials::*;
+pub use dedup::*;
+pub use email_source::{email_documents, EmailDocumentSource, EmailSourceConfig, ATTACHMENT_INDEX_KEY, EMAIL_FROM_KEY, EMAIL_MESSAGE_ID_KEY, EMAIL_PARENT_KEY, EMAIL_SUBJECT_KEY};
+pub use directory_walker::{walk_directory, DirectoryListing, DEFAULT_MAX_SCAN_DEPTH};
 pub use document_processor::*;
 pub use document_reader::*;
+pub use embeddings::*;
+pub use export::*;
 pub use file_discovery::*;
+pub use html::{extract_html, HtmlExtraction, HtmlHeading, HtmlLink};
+pub use inventory::*;
+pub use keywords::*;
+pub use link_crawling::{crawl_candidates, CrawlConfig, LinkCrawler, UrlCandidate, CRAWL_CANDIDATES_KEY, CRAWL_DEPTH_KEY, SOURCE_URL_KEY};
+pub use markdown::{extract_markdown, CodeBlock, MarkdownExtraction, MarkdownHeading, MarkdownLink};
+pub use mime_registry::*;
+pub use ner::{EntityRecognizer, NerConfig, TextAnalysisProcessor, ENTITY_DATE, ENTITY_EMAIL, ENTITY_ORGANIZATION, ENTITY_PERSON};
+pub use normalization::*;
+pub use path_patterns::{relative_to_roots, PathPatterns};
+pub use ocr::{BoundingBox, OcrEngine, OcrOutput, OcrWord};
+pub use quality::*;
+#[cfg(feature = "pdf")]
+pub use pdf::{extract_pdf, PdfExtraction};
+pub use pptx::{extract_pptx, PptxExtraction, SlideExtraction};
+pub use processing_state::{MemoryStateStore, ProcessedFile, ProcessingStateStore, SledStateStore};
+pub use reference::*;
+pub use result_sink::*;
+pub use s3_source::{S3DocumentSource, S3SourceConfig, S3_BUCKET_KEY, S3_ETAG_KEY, S3_KEY_KEY};
+pub use s3_store::{S3Config, S3DocumentStore};
+pub use sanitization::*;
+pub use segmentation::*;
+pub use sidecar::*;
+pub use streaming::*;
+pub use temp_files::*;
+pub use url_source::{url_candidate, UrlDocumentSource, UrlSourceConfig, CONTENT_TYPE_KEY, URL_SUBJECT};
+#[cfg(feature = "tesseract")]
+pub use tesseract::{TesseractConfig, TesseractEngine};
+pub use xlsx::{extract_xlsx, SheetExtraction, XlsxExtraction};
 
 /// Document processing error types
 #[derive(thiserror::Error, Debug)]
@@ -32,9 +109,24 @@ pub enum DocumentError {
     #[error("Unsupported document type: {document_type}")]
     UnsupportedDocumentType { document_type: String },
     
+    #[error("{0}")]
+    Rejected(Box<DocumentRejection>),
+    
     #[error("Processing failed: {reason}")]
     ProcessingFailed { reason: String },
     
+    #[error("Conversion of .{extension} failed: {reason}")]
+    ConversionFailed { extension: String, reason: String },
+    
+    #[error("Could not decrypt {path} after {attempts} attempt(s): {reason}")]
+    DecryptionFailed { path: String, attempts: usize, reason: String },
+    
+    #[error("Content of {path} changed since discovery: {field} is {actual}, expected {expected}")]
+    IntegrityMismatch { path: String, field: String, expected: String, actual: String },
+    
+    #[error("Content of {filename} duplicates {original_filename} ({original_id})")]
+    Duplicate { filename: String, original_id: Uuid, original_filename: String },
+    
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
     
@@ -42,6 +134,32 @@ pub enum DocumentError {
     SerializationError(#[from] serde_json::Error),
 }
 
+impl DocumentError {
+    /// Whether the task should be requeued (e.g. after re-discovery) rather than failed
+    pub fn is_requeueable(&self) -> bool {
+        matches!(self, DocumentError::IntegrityMismatch { .. })
+    }
+    
+    /// The rejection, if the document was refused rather than failed
+    pub fn rejection(&self) -> Option<&DocumentRejection> {
+        match self {
+            DocumentError::Rejected(rejection) => Some(rejection),
+            _ => None,
+        }
+    }
+    
+    /// Status to report for a task that failed with this error
+    pub fn task_status(&self) -> TaskStatus {
+        if self.is_requeueable() {
+            TaskStatus::Retrying
+        } else if matches!(self, DocumentError::Duplicate { .. }) {
+            TaskStatus::Cancelled
+        } else {
+            TaskStatus::Failed
+        }
+    }
+}
+
 /// Result type for document operations
 pub type DocumentResult<T> = Result<T, DocumentError>;
 
@@ -63,8 +181,48 @@ pub struct DocumentProcessingConfig {
     /// Enable parallel processing
     pub enable_parallel_processing: bool,
     
-    /// Maximum number of concurrent documents
+    /// Maximum number of concurrent documents (the starting limit with adaptive concurrency)
     pub max_concurrent_documents: usize,
+    
+    /// Adjust the concurrency limit to keep p95 processing latency under a target
+    #[serde(default)]
+    pub adaptive_concurrency: Option<AdaptiveConcurrencyConfig>,
+    
+    /// Default embedding model, used when `generate_embeddings` is set
+    #[serde(default)]
+    pub embeddings: HashEmbeddingConfig,
+    
+    /// Stop words, stemming and TF-IDF weighting of extracted keywords
+    #[serde(default)]
+    pub keywords: KeywordConfig,
+    
+    /// Normalization of keywords and classification labels to a target language or stems
+    #[serde(default)]
+    pub normalization: NormalizationConfig,
+    
+    /// Scoring of extracted text, flagging or routing low-quality results
+    #[serde(default)]
+    pub quality: QualityConfig,
+    
+    /// Removal of scripts and unsafe markup from HTML text and previews
+    #[serde(default)]
+    pub sanitization: SanitizationConfig,
+    
+    /// Incremental processing of streamed documents
+    #[serde(default)]
+    pub streaming: StreamingConfig,
+    
+    /// Following links of HTML and Markdown documents fetched from a URL
+    #[serde(default)]
+    pub crawling: CrawlConfig,
+    
+    /// Reproducible results: frozen timestamps, seeded IDs, no timings
+    #[serde(default)]
+    pub determinism: DeterminismConfig,
+    
+    /// Skipping or linking documents with the content of an earlier document
+    #[serde(default)]
+    pub dedup: DedupConfig,
 }
 
 impl Default for DocumentProcessingConfig {
@@ -77,11 +235,23 @@ impl Default for DocumentProcessingConfig {
                 DocumentType::Text,
                 DocumentType::Html,
                 DocumentType::Markdown,
+                DocumentType::Excel,
+                DocumentType::PowerPoint,
             ],
             processing_options: DocumentProcessingOptions::default(),
             text_analysis_options: TextAnalysisOptions::default(),
             enable_parallel_processing: true,
             max_concurrent_documents: 10,
+            adaptive_concurrency: None,
+            embeddings: HashEmbeddingConfig::default(),
+            keywords: KeywordConfig::default(),
+            normalization: NormalizationConfig::default(),
+            quality: QualityConfig::default(),
+            sanitization: SanitizationConfig::default(),
+            streaming: StreamingConfig::default(),
+            crawling: CrawlConfig::default(),
+            determinism: DeterminismConfig::default(),
+            dedup: DedupConfig::default(),
         }
     }
 }
@@ -109,6 +279,22 @@ pub struct DocumentProcessingStats {
     
     /// Last processing time
     pub last_processed_at: Option<chrono::DateTime<chrono::Utc>>,
+    
+    /// First processing time, the start of the throughput window
+    #[serde(default)]
+    pub first_processed_at: Option<chrono::DateTime<chrono::Utc>>,
+    
+    /// Batch concurrency, including the current limit when it adapts
+    #[serde(default)]
+    pub concurrency: ConcurrencyStats,
+    
+    /// Processing warnings raised, by warning code
+    #[serde(default)]
+    pub warnings_by_code: HashMap<String, u64>,
+    
+    /// Unique documents and duplicates skipped or linked
+    #[serde(default)]
+    pub dedup: DedupStats,
 }
 
 impl Default for DocumentProcessingStats {
@@ -121,6 +307,41 @@ impl Default for DocumentProcessingStats {
             success_rate: 1.0,
             documents_by_type: HashMap::new(),
             last_processed_at: None,
+            first_processed_at: None,
+            concurrency: ConcurrencyStats::default(),
+            warnings_by_code: HashMap::new(),
+            dedup: DedupStats::default(),
+        }
+    }
+}
+
+impl DocumentProcessingStats {
+    /// Record one processed document
+    pub fn record(&mut self, document_type: &DocumentType, processing_time_ms: u64, success: bool) {
+        let now = Utc::now();
+        let previous_total = self.total_processed as f32;
+        self.total_processed += 1;
+        if !success {
+            self.error_count += 1;
+        }
+        
+        self.average_processing_time_ms =
+            (self.average_processing_time_ms * previous_total + processing_time_ms as f32) / self.total_processed as f32;
+        self.success_rate = (self.total_processed - self.error_count) as f32 / self.total_processed as f32;
+        *self.documents_by_type.entry(document_type.clone()).or_insert(0) += 1;
+        
+        let first_processed_at = *self.first_processed_at.get_or_insert(now);
+        let window_secs = (now - first_processed_at).num_milliseconds() as f32 / 1000.0;
+        if window_secs > 0.0 {
+            self.throughput_per_second = self.total_processed as f32 / window_secs;
+        }
+        self.last_processed_at = Some(now);
+    }
+    
+    /// Count the warnings raised for one document
+    pub fn record_warnings(&mut self, warnings: &[ProcessingWarning]) {
+        for warning in warnings {
+            *self.warnings_by_code.entry(warning.code.clone()).or_insert(0) += 1;
         }
     }
 }
@@ -129,46 +350,19 @@ impl Default for DocumentProcessingStats {
 pub mod utils {
     use super::*;
     
-    /// Detect document type from file extension
+    /// Detect document type from file extension (see `MimeRegistry`)
     pub fn detect_document_type_from_path(path: &Path) -> DocumentType {
-        if let Some(extension) = path.extension() {
-            if let Some(ext_str) = extension.to_str() {
-                match ext_str.to_lowercase().as_str() {
-                    "pdf" => DocumentType::Pdf,
-                    "doc" | "docx" => DocumentType::Word,
-                    "txt" => DocumentType::Text,
-                    "html" | "htm" => DocumentType::Html,
-                    "md" | "markdown" => DocumentType::Markdown,
-                    "xls" | "xlsx" => DocumentType::Excel,
-                    "ppt" | "pptx" => DocumentType::PowerPoint,
-                    "jpg" | "jpeg" | "png" | "gif" | "bmp" => DocumentType::Image,
-                    "mp3" | "wav" | "flac" => DocumentType::Audio,
-                    "mp4" | "avi" | "mov" => DocumentType::Video,
-                    _ => DocumentType::Unknown,
-                }
-            } else {
-                DocumentType::Unknown
-            }
-        } else {
-            DocumentType::Unknown
-        }
+        MimeRegistry::global().document_type_for_path(path)
     }
     
-    /// Get MIME type for document type
-    pub fn get_mime_type(document_type: &DocumentType) -> &'static str {
-        match document_type {
-            DocumentType::Pdf => "application/pdf",
-            DocumentType::Word => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
-            DocumentType::Text => "text/plain",
-            DocumentType::Html => "text/html",
-            DocumentType::Markdown => "text/markdown",
-            DocumentType::Excel => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
-            DocumentType::PowerPoint => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
-            DocumentType::Image => "image/*",
-            DocumentType::Audio => "audio/*",
-            DocumentType::Video => "video/*",
-            DocumentType::Unknown => "application/octet-stream",
-        }
+    /// Detect document type from the file's leading bytes, reconciled with its extension
+    pub async fn detect_document_type(path: &Path) -> DocumentResult<TypeDetection> {
+        use tokio::io::AsyncReadExt;
+        
+        let file = fs::File::open(path).await?;
+        let mut head = Vec::with_capacity(SNIFF_LENGTH);
+        file.take(SNIFF_LENGTH as u64).read_to_end(&mut head).await?;
+        Ok(sniff_document_type(path, &head))
     }
     
     /// Estimate processing time for document type
@@ -198,17 +392,12 @@ pub mod utils {
     pub fn validate_document(document: &Document, config: &DocumentProcessingConfig) -> DocumentResult<()> {
         // Check file size
         if document.size_bytes > config.max_file_size {
-            return Err(DocumentError::ProcessingFailed {
-                reason: format!("Document too large: {} bytes (max: {} bytes)", 
-                               document.size_bytes, config.max_file_size),
-            });
+            return Err(DocumentError::Rejected(Box::new(DocumentRejection::too_large(document, config.max_file_size))));
         }
         
         // Check supported types
         if !config.supported_types.contains(&document.document_type) {
-            return Err(DocumentError::UnsupportedDocumentType {
-                document_type: format!("{:?}", document.document_type),
-            });
+            return Err(DocumentError::Rejected(Box::new(DocumentRejection::unsupported_type(document, &config.supported_types))));
         }
         
         Ok(())
@@ -224,7 +413,8 @@ pub mod utils {
         
         let metadata = fs::metadata(path).await?;
         let size_bytes = metadata.len() as usize;
-        let document_type = detect_document_type_from_path(path);
+        let detection = detect_document_type(path).await?;
+        let document_type = detection.document_type.clone();
         
         // Read content based on document type
         let content = match document_type {
@@ -241,7 +431,12 @@ pub mod utils {
         let mut doc_metadata = HashMap::new();
         doc_metadata.insert("file_path".to_string(), serde_json::Value::String(path.to_string_lossy().to_string()));
         doc_metadata.insert("file_size".to_string(), serde_json::Value::Number(size_bytes.into()));
-        doc_metadata.insert("mime_type".to_string(), serde_json::Value::String(get_mime_type(&document_type).to_string()));
+        doc_metadata.insert("mime_type".to_string(), serde_json::Value::String(detection.mime_type.clone()));
+        doc_metadata.insert("type_detection".to_string(), serde_json::json!({
+            "confidence": detection.confidence,
+            "extension_type": detection.extension_type,
+            "content_type": detection.content_type,
+        }));
         
         Ok(Document {
             id: Uuid::new_v4(),
@@ -0,0 +1,200 @@
+//! Link Crawling
+//!
+//! HTML and Markdown documents fetched from a URL can feed their outbound
+//! links back into ingestion, turning the discovery and processing pipeline
+//! into a lightweight site crawler. Links are resolved against the URL the
+//! document came from, limited to http(s), stripped of fragments and
+//! filtered by crawl depth and domain. The surviving links are returned as
+//! URL candidates in the result's `crawl_candidates` metadata.
+
+use super::*;
+use reqwest::Url;
+use std::collections::HashSet;
+
+/// Document metadata key holding the URL the document was fetched from
+pub const SOURCE_URL_KEY: &str = "source_url";
+
+/// Document metadata key holding the number of links followed to reach the document
+pub const CRAWL_DEPTH_KEY: &str = "crawl_depth";
+
+/// Result metadata key listing discovered URL candidates
+pub const CRAWL_CANDIDATES_KEY: &str = "crawl_candidates";
+
+/// Which links of crawled documents are followed
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CrawlConfig {
+    pub enabled: bool,
+    
+    /// Links followed from the seed URL at most; links of documents at this depth are not emitted
+    pub max_depth: u32,
+    
+    /// Follow links to the host of the linking document
+    pub same_domain: bool,
+    
+    /// Further domains to follow links to, subdomains included
+    pub allowed_domains: Vec<String>,
+    
+    /// Candidates emitted per document at most
+    pub max_links_per_document: usize,
+}
+
+impl Default for CrawlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_depth: 2,
+            same_domain: true,
+            allowed_domains: Vec::new(),
+            max_links_per_document: 200,
+        }
+    }
+}
+
+/// URL discovered in a document, to be fetched as a new document
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct UrlCandidate {
+    pub url: String,
+    
+    /// Crawl depth of the document fetched from the URL
+    pub depth: u32,
+    
+    /// URL of the document linking to it
+    pub discovered_from: String,
+    pub link_text: String,
+}
+
+impl UrlCandidate {
+    /// Metadata for the document fetched from this candidate, so its links are followed in turn
+    pub fn document_metadata(&self) -> HashMap<String, serde_json::Value> {
+        HashMap::from([
+            (SOURCE_URL_KEY.to_string(), serde_json::Value::String(self.url.clone())),
+            (CRAWL_DEPTH_KEY.to_string(), serde_json::Value::from(self.depth)),
+        ])
+    }
+}
+
+/// Turns the links of crawled documents into URL candidates
+#[derive(Debug, Clone, Default)]
+pub struct LinkCrawler {
+    config: CrawlConfig,
+}
+
+impl LinkCrawler {
+    pub fn new(config: CrawlConfig) -> Self {
+        Self { config }
+    }
+    
+    pub fn config(&self) -> &CrawlConfig {
+        &self.config
+    }
+    
+    /// Candidates among the `(href, text)` links of a document
+    ///
+    /// Documents without a `source_url` are not crawled.
+    pub fn candidates<'a>(&self, document: &Document, links: impl IntoIterator<Item = (&'a str, &'a str)>) -> Vec<UrlCandidate> {
+        if !self.config.enabled {
+            return Vec::new();
+        }
+        let Some(base) = document.metadata.get(SOURCE_URL_KEY)
+            .and_then(|value| value.as_str())
+            .and_then(|url| Url::parse(url).ok()) else {
+            return Vec::new();
+        };
+        let depth = document.metadata.get(CRAWL_DEPTH_KEY).and_then(|value| value.as_u64()).unwrap_or(0) as u32 + 1;
+        if depth > self.config.max_depth {
+            return Vec::new();
+        }
+        
+        // Links back to the document itself are not candidates
+        let mut page = base.clone();
+        page.set_fragment(None);
+        let mut seen = HashSet::from([page.to_string()]);
+        let mut candidates = Vec::new();
+        for (href, text) in links {
+            if candidates.len() >= self.config.max_links_per_document {
+                break;
+            }
+            let Ok(mut url) = base.join(href.trim()) else { continue };
+            url.set_fragment(None);
+            if !matches!(url.scheme(), "http" | "https") || !self.follows(&url, &base) || !seen.insert(url.to_string()) {
+                continue;
+            }
+            candidates.push(UrlCandidate {
+                url: url.to_string(),
+                depth,
+                discovered_from: base.to_string(),
+                link_text: text.to_string(),
+            });
+        }
+        candidates
+    }
+    
+    /// Whether the domain filters let a link from `base` to `url` through
+    fn follows(&self, url: &Url, base: &Url) -> bool {
+        let Some(host) = url.host_str() else { return false };
+        if self.config.same_domain && Some(host) == base.host_str() {
+            return true;
+        }
+        self.config.allowed_domains.iter().any(|domain| {
+            let domain = domain.trim_start_matches('.');
+            host == domain || host.strip_suffix(domain).is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+}
+
+/// URL candidates recorded in a processing result
+pub fn crawl_candidates(result: &DocumentProcessingResult) -> Vec<UrlCandidate> {
+    result.metadata.get(CRAWL_CANDIDATES_KEY)
+        .and_then(|candidates| serde_json::from_value(candidates.clone()).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swarm_core::DocumentBuilder;
+    
+    fn page(url: &str, depth: u32) -> Document {
+        DocumentBuilder::new("page.html", DocumentContent::Text(String::new()))
+            .document_type(DocumentType::Html)
+            .metadata(SOURCE_URL_KEY, serde_json::json!(url))
+            .metadata(CRAWL_DEPTH_KEY, serde_json::json!(depth))
+            .build()
+    }
+    
+    #[test]
+    fn test_links_are_resolved_and_filtered() {
+        let crawler = LinkCrawler::new(CrawlConfig {
+            enabled: true,
+            allowed_domains: vec!["docs.example.org".to_string()],
+            ..CrawlConfig::default()
+        });
+        let links = [
+            ("/guide#install", "Guide"),
+            ("guide", "Guide again"),
+            ("https://api.docs.example.org/v1", "API"),
+            ("https://elsewhere.com/", "Elsewhere"),
+            ("mailto:team@example.com", "Mail"),
+            ("#top", "Top"),
+        ];
+        let candidates = crawler.candidates(&page("https://example.com/start/", 0), links);
+        
+        assert_eq!(candidates.iter().map(|candidate| candidate.url.as_str()).collect::<Vec<_>>(),
+            vec!["https://example.com/guide", "https://example.com/start/guide", "https://api.docs.example.org/v1"]);
+        assert!(candidates.iter().all(|candidate| candidate.depth == 1 && candidate.discovered_from == "https://example.com/start/"));
+        assert_eq!(candidates[0].document_metadata()[CRAWL_DEPTH_KEY], 1);
+    }
+    
+    #[test]
+    fn test_crawling_stops_at_max_depth() {
+        let crawler = LinkCrawler::new(CrawlConfig { enabled: true, max_depth: 2, ..CrawlConfig::default() });
+        let links = [("/next", "Next")];
+        assert_eq!(crawler.candidates(&page("https://example.com/", 1), links).len(), 1);
+        assert!(crawler.candidates(&page("https://example.com/", 2), links).is_empty());
+        
+        // Disabled, or without a source URL, nothing is crawled
+        assert!(LinkCrawler::default().candidates(&page("https://example.com/", 0), links).is_empty());
+        let local = DocumentBuilder::new("page.html", DocumentContent::Text(String::new())).document_type(DocumentType::Html).build();
+        assert!(crawler.candidates(&local, links).is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+//! Markdown Structure Extraction
+//!
+//! Markdown documents are parsed as CommonMark (with tables, strikethrough
+//! and task lists). The plain text keeps one line per heading, paragraph,
+//! list item and table row, keeps link and image text, and keeps code
+//! blocks verbatim. The heading outline, with each heading's position in
+//! the text, is returned for chunking, together with link targets and the
+//! languages of fenced code blocks.
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+
+/// A heading of the document outline
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MarkdownHeading {
+    /// 1 for `#` through 6 for `######`
+    pub level: u8,
+    pub text: String,
+    
+    /// Byte offset of the heading's line in the extracted text
+    pub offset: usize,
+}
+
+/// A link or image with its text
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MarkdownLink {
+    pub text: String,
+    pub url: String,
+}
+
+/// A code block
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CodeBlock {
+    /// Language of a fenced block's info string, e.g. `rust` for ```` ```rust ````
+    pub language: Option<String>,
+    pub line_count: usize,
+}
+
+/// Text and structure extracted from a Markdown document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarkdownExtraction {
+    pub text: String,
+    pub headings: Vec<MarkdownHeading>,
+    pub links: Vec<MarkdownLink>,
+    pub code_blocks: Vec<CodeBlock>,
+}
+
+impl MarkdownExtraction {
+    /// Finish the current line, dropping it if it is only whitespace
+    fn end_line(&mut self, line: &mut String) -> Option<usize> {
+        let text = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        line.clear();
+        (!text.is_empty()).then(|| self.push_line(&text))
+    }
+    
+    /// Append a line, returning its offset
+    fn push_line(&mut self, line: &str) -> usize {
+        if !self.text.is_empty() {
+            self.text.push('\n');
+        }
+        let offset = self.text.len();
+        self.text.push_str(line);
+        offset
+    }
+}
+
+/// Parse Markdown and extract its text, outline, links and code blocks
+pub fn extract_markdown(markdown: &str) -> MarkdownExtraction {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut extraction = MarkdownExtraction::default();
+    let mut line = String::new();
+    // Text of the links and images being read, innermost last
+    let mut open_links: Vec<(String, usize)> = Vec::new();
+    let mut code: Option<(Option<String>, String)> = None;
+    
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Text(text) => match code.as_mut() {
+                Some((_, block)) => block.push_str(&text),
+                None => line.push_str(&text),
+            },
+            Event::Code(text) => line.push_str(&text),
+            Event::SoftBreak => line.push(' '),
+            Event::HardBreak | Event::Rule => {
+                extraction.end_line(&mut line);
+            }
+            Event::TaskListMarker(_) | Event::Html(_) | Event::InlineHtml(_) => {}
+            Event::Start(Tag::CodeBlock(kind)) => {
+                extraction.end_line(&mut line);
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info.split_whitespace().next().map(str::to_string),
+                    CodeBlockKind::Indented => None,
+                };
+                code = Some((language, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((language, block)) = code.take() {
+                    let block = block.trim_end_matches('\n');
+                    for code_line in block.lines() {
+                        extraction.push_line(code_line.trim_end());
+                    }
+                    extraction.code_blocks.push(CodeBlock { language, line_count: block.lines().count() });
+                }
+            }
+            Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. }) => {
+                open_links.push((dest_url.into_string(), line.len()));
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                if let Some((url, start)) = open_links.pop() {
+                    let text = line.get(start..).unwrap_or_default().split_whitespace().collect::<Vec<_>>().join(" ");
+                    extraction.links.push(MarkdownLink { text, url });
+                }
+            }
+            Event::Start(Tag::Paragraph | Tag::Heading { .. } | Tag::Item | Tag::TableRow | Tag::TableHead) => {
+                extraction.end_line(&mut line);
+            }
+            Event::End(TagEnd::Heading(level)) => {
+                let text = line.split_whitespace().collect::<Vec<_>>().join(" ");
+                if let Some(offset) = extraction.end_line(&mut line) {
+                    extraction.headings.push(MarkdownHeading { level: level as u8, text, offset });
+                }
+            }
+            Event::End(TagEnd::TableCell) => line.push_str(" | "),
+            Event::End(TagEnd::TableRow | TagEnd::TableHead) => {
+                let row = line.trim_end().trim_end_matches('|').to_string();
+                line = row;
+                extraction.end_line(&mut line);
+            }
+            Event::End(TagEnd::Paragraph | TagEnd::Item | TagEnd::BlockQuote(_)) => {
+                extraction.end_line(&mut line);
+            }
+            _ => {}
+        }
+    }
+    extraction.end_line(&mut line);
+    extraction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_structure_is_extracted() {
+        let markdown = "# Guide\n\nRead the [setup notes](https://example.com/setup) first,\nthen run `cargo build`.\n\n\
+            ## Install\n\n- [x] Clone the *repo*\n- Build:\n  - debug\n\n```rust title=main.rs\nfn main() {\n    println!(\"#1 * 2\");\n}\n```\n\n\
+            | Key | Value |\n|-----|-------|\n| a_b | 1 |\n";
+        let extraction = extract_markdown(markdown);
+        
+        assert_eq!(extraction.text, "Guide\nRead the setup notes first, then run cargo build.\nInstall\nClone the repo\nBuild:\ndebug\n\
+            fn main() {\n    println!(\"#1 * 2\");\n}\nKey | Value\na_b | 1");
+        assert_eq!(extraction.headings, vec![
+            MarkdownHeading { level: 1, text: "Guide".to_string(), offset: 0 },
+            MarkdownHeading { level: 2, text: "Install".to_string(), offset: 56 },
+        ]);
+        assert_eq!(&extraction.text[56..63], "Install");
+        assert_eq!(extraction.links, vec![MarkdownLink { text: "setup notes".to_string(), url: "https://example.com/setup".to_string() }]);
+        assert_eq!(extraction.code_blocks, vec![CodeBlock { language: Some("rust".to_string()), line_count: 3 }]);
+    }
+    
+    #[test]
+    fn test_indented_code_and_images() {
+        let extraction = extract_markdown("![Logo](logo.png)\n\n    plain code\n\n> Quoted <b>text</b>");
+        assert_eq!(extraction.text, "Logo\nplain code\nQuoted text");
+        assert_eq!(extraction.links[0].url, "logo.png");
+        assert_eq!(extraction.code_blocks, vec![CodeBlock { language: None, line_count: 1 }]);
+        assert!(extraction.headings.is_empty());
+    }
+}
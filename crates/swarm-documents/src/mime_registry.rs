@@ -0,0 +1,202 @@
+//! MIME Type Registry
+//!
+//! Maps file extensions to precise MIME types and document types. The
+//! process-wide registry is pre-populated with common formats and can be
+//! extended at runtime; file discovery, document metadata and type detection
+//! all resolve through it.
+
+use super::*;
+use std::sync::{OnceLock, RwLock};
+
+/// MIME type used when nothing more specific is known
+pub const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+/// A registered file format
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MimeEntry {
+    /// Lower-case file extension without the dot
+    pub extension: String,
+    
+    /// Precise MIME type (no wildcards)
+    pub mime_type: String,
+    
+    /// Document type the format is processed as
+    pub document_type: DocumentType,
+}
+
+/// Extension ↔ MIME type ↔ document type registry
+#[derive(Debug)]
+pub struct MimeRegistry {
+    entries: RwLock<HashMap<String, MimeEntry>>,
+}
+
+impl MimeRegistry {
+    /// Registry containing the built-in formats
+    pub fn new() -> Self {
+        let registry = Self::empty();
+        for (extension, mime_type, document_type) in [
+            ("txt", "text/plain", DocumentType::Text),
+            ("md", "text/markdown", DocumentType::Markdown),
+            ("markdown", "text/markdown", DocumentType::Markdown),
+            ("html", "text/html", DocumentType::Html),
+            ("htm", "text/html", DocumentType::Html),
+            ("pdf", "application/pdf", DocumentType::Pdf),
+            ("doc", "application/msword", DocumentType::Word),
+            ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document", DocumentType::Word),
+            ("xls", "application/vnd.ms-excel", DocumentType::Excel),
+            ("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", DocumentType::Excel),
+            ("ppt", "application/vnd.ms-powerpoint", DocumentType::PowerPoint),
+            ("pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation", DocumentType::PowerPoint),
+            ("jpg", "image/jpeg", DocumentType::Image),
+            ("jpeg", "image/jpeg", DocumentType::Image),
+            ("png", "image/png", DocumentType::Image),
+            ("gif", "image/gif", DocumentType::Image),
+            ("bmp", "image/bmp", DocumentType::Image),
+            ("mp3", "audio/mpeg", DocumentType::Audio),
+            ("wav", "audio/wav", DocumentType::Audio),
+            ("flac", "audio/flac", DocumentType::Audio),
+            ("mp4", "video/mp4", DocumentType::Video),
+            ("avi", "video/x-msvideo", DocumentType::Video),
+            ("mov", "video/quicktime", DocumentType::Video),
+        ] {
+            registry.register(extension, mime_type, document_type);
+        }
+        registry
+    }
+    
+    /// Registry with no formats
+    pub fn empty() -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+    
+    /// Process-wide registry
+    pub fn global() -> &'static MimeRegistry {
+        static GLOBAL: OnceLock<MimeRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(MimeRegistry::new)
+    }
+    
+    /// Register or replace a format
+    pub fn register(&self, extension: &str, mime_type: &str, document_type: DocumentType) {
+        let extension = normalize_extension(extension);
+        let entry = MimeEntry {
+            extension: extension.clone(),
+            mime_type: mime_type.to_lowercase(),
+            document_type,
+        };
+        self.entries.write().unwrap_or_else(|e| e.into_inner()).insert(extension, entry);
+    }
+    
+    /// Look up a format by extension
+    pub fn entry(&self, extension: &str) -> Option<MimeEntry> {
+        self.entries.read().unwrap_or_else(|e| e.into_inner())
+            .get(&normalize_extension(extension))
+            .cloned()
+    }
+    
+    /// MIME type for an extension (`application/octet-stream` if unknown)
+    pub fn mime_type_for_extension(&self, extension: &str) -> String {
+        self.entry(extension)
+            .map(|entry| entry.mime_type)
+            .unwrap_or_else(|| DEFAULT_MIME_TYPE.to_string())
+    }
+    
+    /// MIME type for a path, based on its extension
+    pub fn mime_type_for_path(&self, path: &Path) -> String {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| self.mime_type_for_extension(ext))
+            .unwrap_or_else(|| DEFAULT_MIME_TYPE.to_string())
+    }
+    
+    /// Document type for an extension (`Unknown` if unregistered)
+    pub fn document_type_for_extension(&self, extension: &str) -> DocumentType {
+        self.entry(extension)
+            .map(|entry| entry.document_type)
+            .unwrap_or(DocumentType::Unknown)
+    }
+    
+    /// Document type for a path, based on its extension
+    pub fn document_type_for_path(&self, path: &Path) -> DocumentType {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| self.document_type_for_extension(ext))
+            .unwrap_or(DocumentType::Unknown)
+    }
+    
+    /// Document type for a MIME type (parameters such as `; charset=` are ignored)
+    pub fn document_type_for_mime(&self, mime_type: &str) -> DocumentType {
+        let mime_type = essence(mime_type);
+        self.entries.read().unwrap_or_else(|e| e.into_inner())
+            .values()
+            .find(|entry| entry.mime_type == mime_type)
+            .map(|entry| entry.document_type.clone())
+            .unwrap_or(DocumentType::Unknown)
+    }
+    
+    /// Registered extensions for a MIME type, sorted
+    pub fn extensions_for_mime(&self, mime_type: &str) -> Vec<String> {
+        let mime_type = essence(mime_type);
+        let mut extensions: Vec<String> = self.entries.read().unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|entry| entry.mime_type == mime_type)
+            .map(|entry| entry.extension.clone())
+            .collect();
+        extensions.sort();
+        extensions
+    }
+}
+
+impl Default for MimeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn normalize_extension(extension: &str) -> String {
+    extension.trim_start_matches('.').to_lowercase()
+}
+
+fn essence(mime_type: &str) -> String {
+    mime_type.split(';').next().unwrap_or_default().trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_builtin_formats_are_precise() {
+        let registry = MimeRegistry::new();
+        
+        assert_eq!(registry.mime_type_for_extension("PNG"), "image/png");
+        assert_eq!(registry.mime_type_for_extension(".jpeg"), "image/jpeg");
+        assert_eq!(registry.mime_type_for_extension("doc"), "application/msword");
+        assert_eq!(registry.mime_type_for_extension("xyz"), DEFAULT_MIME_TYPE);
+        assert_eq!(registry.mime_type_for_path(Path::new("clip.mov")), "video/quicktime");
+        assert_eq!(registry.document_type_for_path(Path::new("notes.md")), DocumentType::Markdown);
+    }
+    
+    #[test]
+    fn test_reverse_lookup() {
+        let registry = MimeRegistry::new();
+        
+        assert_eq!(registry.extensions_for_mime("text/html; charset=utf-8"), vec!["htm", "html"]);
+        assert_eq!(registry.document_type_for_mime("application/PDF"), DocumentType::Pdf);
+        assert_eq!(registry.document_type_for_mime("application/x-unknown"), DocumentType::Unknown);
+    }
+    
+    #[test]
+    fn test_runtime_registration() {
+        let registry = MimeRegistry::empty();
+        assert_eq!(registry.document_type_for_extension("rst"), DocumentType::Unknown);
+        
+        registry.register("RST", "text/x-rst", DocumentType::Text);
+        assert_eq!(registry.document_type_for_extension("rst"), DocumentType::Text);
+        assert_eq!(registry.mime_type_for_extension("rst"), "text/x-rst");
+        
+        registry.register("rst", "text/prs.fallenstein.rst", DocumentType::Text);
+        assert_eq!(registry.mime_type_for_extension("rst"), "text/prs.fallenstein.rst");
+    }
+}
@@ -0,0 +1,258 @@
+//! Named Entity Recognition
+//!
+//! Rule and gazetteer based recognition of persons, organizations, dates and
+//! email addresses. Patterns find emails, dates, names after titles or known
+//! first names, and capitalised names ending in a company suffix; gazetteers
+//! add names that no rule catches. Overlapping matches are resolved in favour
+//! of the more confident, then longer, one. `TextAnalysisProcessor` runs the
+//! recognizer for `TextAnalysis { NamedEntityRecognition }` tasks.
+
+use super::*;
+use async_trait::async_trait;
+use regex::Regex;
+use swarm_core::{NamedEntity, TaskResultData, TextAnalysisResult, TextAnalysisType};
+
+pub const ENTITY_PERSON: &str = "PERSON";
+pub const ENTITY_ORGANIZATION: &str = "ORGANIZATION";
+pub const ENTITY_DATE: &str = "DATE";
+pub const ENTITY_EMAIL: &str = "EMAIL";
+
+const MONTHS: &str = "January|February|March|April|May|June|July|August|September|October|November|December|Jan|Feb|Mar|Apr|Jun|Jul|Aug|Sept|Sep|Oct|Nov|Dec";
+
+const ORGANIZATION_SUFFIXES: &str = "Inc|Ltd|LLC|Corp|Corporation|Company|GmbH|AB|AG|PLC|Group|University|Institute|Foundation";
+
+const FIRST_NAMES: &[&str] = &[
+    "Anna", "Daniel", "David", "Elizabeth", "Emma", "Erik", "James", "Jane", "Jennifer", "Johan", "John",
+    "Karl", "Lars", "Linda", "Maria", "Mary", "Michael", "Olivia", "Peter", "Richard", "Robert", "Sarah",
+    "Thomas", "William",
+];
+
+/// Entity recognition configuration
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NerConfig {
+    /// Known person names, matched verbatim
+    pub persons: Vec<String>,
+    
+    /// Known organization names, matched verbatim
+    pub organizations: Vec<String>,
+    
+    /// First names that start a person name, in addition to the built-in list
+    pub first_names: Vec<String>,
+    
+    /// Entities below this confidence are dropped
+    pub min_confidence: f32,
+}
+
+impl Default for NerConfig {
+    fn default() -> Self {
+        Self {
+            persons: Vec::new(),
+            organizations: Vec::new(),
+            first_names: Vec::new(),
+            min_confidence: 0.5,
+        }
+    }
+}
+
+/// One pattern and what it recognizes
+#[derive(Debug, Clone)]
+struct EntityRule {
+    pattern: Regex,
+    entity_type: &'static str,
+    confidence: f32,
+}
+
+/// Rule and gazetteer based entity recognizer
+#[derive(Debug, Clone)]
+pub struct EntityRecognizer {
+    rules: Vec<EntityRule>,
+    min_confidence: f32,
+}
+
+impl EntityRecognizer {
+    pub fn new(config: NerConfig) -> Result<Self> {
+        let mut first_names: Vec<String> = FIRST_NAMES.iter().map(|name| name.to_string()).collect();
+        first_names.extend(config.first_names.iter().cloned());
+        
+        let mut rules = vec![
+            rule(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b", ENTITY_EMAIL, 0.95)?,
+            rule(r"\b\d{4}-\d{2}-\d{2}\b", ENTITY_DATE, 0.9)?,
+            rule(r"\b\d{1,2}[/.]\d{1,2}[/.]\d{4}\b", ENTITY_DATE, 0.8)?,
+            rule(&format!(r"\b(?:{})\.?\s+\d{{1,2}}(?:st|nd|rd|th)?,?\s+\d{{4}}\b", MONTHS), ENTITY_DATE, 0.85)?,
+            rule(&format!(r"\b\d{{1,2}}(?:st|nd|rd|th)?\s+(?:{})\.?,?\s+\d{{4}}\b", MONTHS), ENTITY_DATE, 0.85)?,
+            rule(&format!(r"\b(?:[A-Z][A-Za-z&'-]+\s+){{1,4}}(?:{})\b\.?", ORGANIZATION_SUFFIXES), ENTITY_ORGANIZATION, 0.7)?,
+            rule(r"\b(?:Mr|Mrs|Ms|Dr|Prof)\.?\s+[A-Z][a-z]+(?:\s+[A-Z][a-z]+)?\b", ENTITY_PERSON, 0.75)?,
+            rule(&format!(r"\b(?:{})\s+(?:[A-Z]\.\s+)?[A-Z][a-z]+\b", alternation(&first_names)), ENTITY_PERSON, 0.6)?,
+        ];
+        if !config.persons.is_empty() {
+            rules.push(rule(&format!(r"\b(?:{})\b", alternation(&config.persons)), ENTITY_PERSON, 0.9)?);
+        }
+        if !config.organizations.is_empty() {
+            rules.push(rule(&format!(r"\b(?:{})\b", alternation(&config.organizations)), ENTITY_ORGANIZATION, 0.9)?);
+        }
+        
+        Ok(Self { rules, min_confidence: config.min_confidence })
+    }
+    
+    /// Entities in `text`, in order of appearance, with byte offsets
+    pub fn recognize(&self, text: &str) -> Vec<NamedEntity> {
+        let mut candidates: Vec<NamedEntity> = self.rules.iter()
+            .filter(|rule| rule.confidence >= self.min_confidence)
+            .flat_map(|rule| rule.pattern.find_iter(text).map(move |found| NamedEntity {
+                text: found.as_str().to_string(),
+                entity_type: rule.entity_type.to_string(),
+                confidence: rule.confidence,
+                start_pos: found.start(),
+                end_pos: found.end(),
+            }))
+            .collect();
+        
+        // Keep the most confident, then longest, of overlapping matches
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence)
+            .then_with(|| (b.end_pos - b.start_pos).cmp(&(a.end_pos - a.start_pos)))
+            .then_with(|| a.start_pos.cmp(&b.start_pos)));
+        let mut entities: Vec<NamedEntity> = Vec::new();
+        for candidate in candidates {
+            let overlaps = entities.iter().any(|entity| candidate.start_pos < entity.end_pos && entity.start_pos < candidate.end_pos);
+            if !overlaps {
+                entities.push(candidate);
+            }
+        }
+        entities.sort_by_key(|entity| entity.start_pos);
+        entities
+    }
+}
+
+fn rule(pattern: &str, entity_type: &'static str, confidence: f32) -> Result<EntityRule> {
+    Ok(EntityRule { pattern: Regex::new(pattern)?, entity_type, confidence })
+}
+
+/// Regex alternation of literal names, longest first so prefixes don't win
+fn alternation(names: &[String]) -> String {
+    let mut names: Vec<&String> = names.iter().filter(|name| !name.trim().is_empty()).collect();
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    names.iter().map(|name| regex::escape(name.trim())).collect::<Vec<_>>().join("|")
+}
+
+/// Task processor for named entity recognition tasks
+#[derive(Debug, Clone)]
+pub struct TextAnalysisProcessor {
+    recognizer: EntityRecognizer,
+    task_types: Vec<TaskType>,
+}
+
+impl TextAnalysisProcessor {
+    pub fn new(config: NerConfig) -> Result<Self> {
+        Ok(Self {
+            recognizer: EntityRecognizer::new(config)?,
+            task_types: vec![TaskType::TextAnalysis { analysis_type: TextAnalysisType::NamedEntityRecognition }],
+        })
+    }
+}
+
+#[async_trait]
+impl TaskProcessor for TextAnalysisProcessor {
+    async fn process(&self, task: &Task) -> Result<TaskResult> {
+        if !self.task_types.contains(&task.task_type) {
+            anyhow::bail!("task {} is not a named entity recognition task", task.id);
+        }
+        let (text, language) = match &task.payload {
+            TaskPayload::Text { content, analysis_options } => (content.as_str(), analysis_options.language.clone()),
+            TaskPayload::Document { document: Document { content: DocumentContent::Text(text), .. }, .. } => (text.as_str(), None),
+            _ => anyhow::bail!("named entity recognition task {} has no text payload", task.id),
+        };
+        
+        let start_time = std::time::Instant::now();
+        let entities = self.recognizer.recognize(text);
+        let processing_time_ms = start_time.elapsed().as_millis() as u64;
+        Ok(TaskResult {
+            task_id: task.id,
+            status: TaskStatus::Completed,
+            result: Some(TaskResultData::TextAnalysis(TextAnalysisResult {
+                text_id: task.id,
+                language,
+                keywords: Vec::new(),
+                sentiment: None,
+                entities,
+                topics: Vec::new(),
+                summary: None,
+                processing_time_ms,
+                processed_at: Utc::now(),
+            })),
+            error: None,
+            processing_time_ms,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        })
+    }
+    
+    fn supported_task_types(&self) -> &[TaskType] {
+        &self.task_types
+    }
+    
+    fn estimate_processing_time(&self, task: &Task) -> std::time::Duration {
+        match &task.payload {
+            TaskPayload::Text { content, .. } => std::time::Duration::from_micros(content.len() as u64 / 10 + 100),
+            _ => std::time::Duration::from_millis(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swarm_core::TaskPriority;
+    
+    fn entities_of_type<'a>(entities: &'a [NamedEntity], entity_type: &str) -> Vec<&'a str> {
+        entities.iter().filter(|entity| entity.entity_type == entity_type).map(|entity| entity.text.as_str()).collect()
+    }
+    
+    #[test]
+    fn test_recognizes_persons_organizations_dates_and_emails() {
+        let recognizer = EntityRecognizer::new(NerConfig {
+            organizations: vec!["Aprio".to_string()],
+            persons: vec!["Kenneth Pernyer".to_string()],
+            ..Default::default()
+        }).unwrap();
+        let text = "On 2024-03-15 Dr. Alice Moore of Northwind Traders Inc. emailed jane.doe@example.com. \
+                    Sarah Connor met Kenneth Pernyer at Aprio on March 3, 2024.";
+        let entities = recognizer.recognize(text);
+        
+        assert_eq!(entities_of_type(&entities, ENTITY_DATE), vec!["2024-03-15", "March 3, 2024"]);
+        assert_eq!(entities_of_type(&entities, ENTITY_EMAIL), vec!["jane.doe@example.com"]);
+        assert_eq!(entities_of_type(&entities, ENTITY_PERSON), vec!["Dr. Alice Moore", "Sarah Connor", "Kenneth Pernyer"]);
+        assert_eq!(entities_of_type(&entities, ENTITY_ORGANIZATION), vec!["Northwind Traders Inc.", "Aprio"]);
+        
+        let email = entities.iter().find(|entity| entity.entity_type == ENTITY_EMAIL).unwrap();
+        assert_eq!(&text[email.start_pos..email.end_pos], "jane.doe@example.com");
+        assert!(entities.windows(2).all(|pair| pair[0].end_pos <= pair[1].start_pos));
+    }
+    
+    #[tokio::test]
+    async fn test_ner_task_fills_entities() {
+        let processor = TextAnalysisProcessor::new(NerConfig::default()).unwrap();
+        let mut task = Task {
+            id: Uuid::new_v4(),
+            task_type: TaskType::TextAnalysis { analysis_type: TextAnalysisType::NamedEntityRecognition },
+            priority: TaskPriority::Normal,
+            status: TaskStatus::Pending,
+            payload: TaskPayload::Text {
+                content: "Contact John Smith (john@acme.io) before 12/05/2025.".to_string(),
+                analysis_options: Default::default(),
+            },
+            created_at: Utc::now(),
+            deadline: None,
+            retry_count: 0,
+            max_retries: 3,
+            metadata: HashMap::new(),
+        };
+        
+        let result = processor.process(&task).await.unwrap();
+        let Some(TaskResultData::TextAnalysis(analysis)) = result.result else { panic!("expected a text analysis result") };
+        let types: Vec<&str> = analysis.entities.iter().map(|entity| entity.entity_type.as_str()).collect();
+        assert_eq!(types, vec![ENTITY_PERSON, ENTITY_EMAIL, ENTITY_DATE]);
+        
+        task.task_type = TaskType::TextAnalysis { analysis_type: TextAnalysisType::Summarization };
+        assert!(processor.process(&task).await.is_err());
+    }
+}
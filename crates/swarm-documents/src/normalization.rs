@@ -0,0 +1,200 @@
+//! Keyword Normalization
+//!
+//! Keywords and classification labels come out in the language of the
+//! document, so a Swedish "rapporter" and an English "reports" never meet in
+//! a facet. With normalization enabled, the processor also records the terms
+//! translated to a target language (through a `Translator`) or reduced to
+//! their stems. The original terms are kept as they were; the normalized ones
+//! are stored in the result metadata next to them.
+
+use super::*;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Result metadata key holding the normalized keywords, in keyword order
+pub const NORMALIZED_KEYWORDS_KEY: &str = "normalized_keywords";
+
+/// Result metadata key holding the normalized classification label
+pub const NORMALIZED_CLASSIFICATION_KEY: &str = "normalized_classification";
+
+/// Result metadata key describing how terms were normalized, e.g. `translate:en`
+pub const NORMALIZATION_KEY: &str = "term_normalization";
+
+/// Translates short terms such as keywords and labels (MT services, glossaries, ...)
+#[async_trait]
+pub trait Translator: Send + Sync {
+    /// Translator name, recorded with the terms it translated
+    fn name(&self) -> &str;
+    
+    /// Translate `terms` from `source` (unknown if `None`) to `target`, one output per term
+    async fn translate(&self, terms: &[String], source: Option<&str>, target: &str) -> DocumentResult<Vec<String>>;
+}
+
+/// How keywords and classification labels are normalized
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum TermNormalization {
+    /// Terms are only reported in the source language
+    #[default]
+    None,
+    /// Translate terms to a target language with the processor's `Translator`
+    Translate { target_language: String },
+    /// Reduce terms to their stems in the source language
+    Stem,
+}
+
+/// Keyword and classification normalization configuration
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NormalizationConfig {
+    pub keywords: TermNormalization,
+    
+    /// Normalize the classification label as well
+    pub classification: bool,
+}
+
+impl Default for NormalizationConfig {
+    fn default() -> Self {
+        Self {
+            keywords: TermNormalization::None,
+            classification: true,
+        }
+    }
+}
+
+/// Normalized forms of a result's terms
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedTerms {
+    pub keywords: Vec<String>,
+    pub classification: Option<String>,
+    
+    /// How the terms were normalized, e.g. `stem` or `translate:en`
+    pub method: String,
+}
+
+impl NormalizedTerms {
+    /// Record the normalized terms in result metadata
+    pub fn insert_into(self, metadata: &mut HashMap<String, serde_json::Value>) {
+        metadata.insert(NORMALIZED_KEYWORDS_KEY.to_string(), serde_json::json!(self.keywords));
+        if let Some(classification) = self.classification {
+            metadata.insert(NORMALIZED_CLASSIFICATION_KEY.to_string(), serde_json::json!(classification));
+        }
+        metadata.insert(NORMALIZATION_KEY.to_string(), serde_json::json!(self.method));
+    }
+}
+
+/// Normalizes keywords and classification labels as configured
+#[derive(Clone, Default)]
+pub struct TermNormalizer {
+    config: NormalizationConfig,
+    translator: Option<Arc<dyn Translator>>,
+}
+
+impl TermNormalizer {
+    pub fn new(config: NormalizationConfig) -> Self {
+        Self { config, translator: None }
+    }
+    
+    pub fn with_translator(mut self, translator: Arc<dyn Translator>) -> Self {
+        self.translator = Some(translator);
+        self
+    }
+    
+    pub fn config(&self) -> &NormalizationConfig {
+        &self.config
+    }
+    
+    /// Normalized terms of a result in `language`, `None` if normalization is off
+    pub async fn normalize(&self, keywords: &[String], classification: Option<&str>, language: Option<&str>) -> DocumentResult<Option<NormalizedTerms>> {
+        let classification = classification.filter(|_| self.config.classification);
+        match &self.config.keywords {
+            TermNormalization::None => Ok(None),
+            TermNormalization::Stem => Ok(Some(NormalizedTerms {
+                keywords: keywords.iter().map(|keyword| stem_phrase(keyword, language)).collect(),
+                classification: classification.map(|label| stem_phrase(label, language)),
+                method: "stem".to_string(),
+            })),
+            TermNormalization::Translate { target_language } => {
+                let method = format!("translate:{}", target_language);
+                let mut terms = keywords.to_vec();
+                terms.extend(classification.map(str::to_string));
+                if language == Some(target_language.as_str()) || terms.is_empty() {
+                    let classification = classification.map(str::to_string);
+                    return Ok(Some(NormalizedTerms { keywords: keywords.to_vec(), classification, method }));
+                }
+                
+                let Some(translator) = &self.translator else {
+                    return Err(DocumentError::ProcessingFailed {
+                        reason: format!("no translator configured for {}", method),
+                    });
+                };
+                let mut translated = translator.translate(&terms, language, target_language).await?;
+                if translated.len() != terms.len() {
+                    return Err(DocumentError::ProcessingFailed {
+                        reason: format!("{} returned {} terms for {}", translator.name(), translated.len(), terms.len()),
+                    });
+                }
+                let classification = classification.and_then(|_| translated.pop());
+                Ok(Some(NormalizedTerms {
+                    keywords: translated,
+                    classification,
+                    method: format!("{} via {}", method, translator.name()),
+                }))
+            }
+        }
+    }
+}
+
+/// Lowercase stems of each word of a term
+fn stem_phrase(term: &str, language: Option<&str>) -> String {
+    term.split_whitespace()
+        .map(|word| stem(&word.to_lowercase(), language))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Glossary translator for tests
+    struct Glossary(HashMap<&'static str, &'static str>);
+    
+    #[async_trait]
+    impl Translator for Glossary {
+        fn name(&self) -> &str {
+            "glossary"
+        }
+        
+        async fn translate(&self, terms: &[String], _source: Option<&str>, _target: &str) -> DocumentResult<Vec<String>> {
+            Ok(terms.iter().map(|term| self.0.get(term.as_str()).map_or(term.clone(), |word| word.to_string())).collect())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_stem_and_translate_normalization() {
+        let keywords = vec!["rapporterna".to_string(), "kostnader".to_string()];
+        let stems = TermNormalizer::new(NormalizationConfig { keywords: TermNormalization::Stem, ..Default::default() })
+            .normalize(&keywords, Some("Kvartalsrapporter"), Some("sv")).await.unwrap().unwrap();
+        assert_eq!(stems.keywords, vec!["rapport", "kostnad"]);
+        assert_eq!(stems.classification.as_deref(), Some("kvartalsrapport"));
+        
+        let config = NormalizationConfig {
+            keywords: TermNormalization::Translate { target_language: "en".to_string() },
+            classification: true,
+        };
+        let untranslated = TermNormalizer::new(config.clone());
+        assert!(untranslated.normalize(&keywords, None, Some("sv")).await.is_err());
+        assert_eq!(untranslated.normalize(&keywords, None, Some("en")).await.unwrap().unwrap().keywords, keywords);
+        
+        let glossary = Glossary(HashMap::from([("rapporterna", "reports"), ("kostnader", "costs"), ("Rapport", "Report")]));
+        let translated = TermNormalizer::new(config).with_translator(Arc::new(glossary))
+            .normalize(&keywords, Some("Rapport"), Some("sv")).await.unwrap().unwrap();
+        assert_eq!(translated.keywords, vec!["reports", "costs"]);
+        assert_eq!(translated.classification.as_deref(), Some("Report"));
+        assert_eq!(translated.method, "translate:en via glossary");
+        
+        let mut metadata = HashMap::new();
+        translated.insert_into(&mut metadata);
+        assert_eq!(metadata[NORMALIZED_KEYWORDS_KEY], serde_json::json!(["reports", "costs"]));
+    }
+}
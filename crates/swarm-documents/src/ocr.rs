@@ -0,0 +1,76 @@
+//! Image OCR
+//!
+//! Image documents are read by an `OcrEngine`, which returns the recognized
+//! text together with each word's bounding box and confidence. Engines plug
+//! in through `SwarmDocumentProcessor::with_ocr_engine`; a Tesseract-backed
+//! engine is available with the `tesseract` feature.
+
+use super::*;
+use async_trait::async_trait;
+
+/// Pixel rectangle of recognized text, origin at the top left
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BoundingBox {
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// One recognized word
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OcrWord {
+    pub text: String,
+    
+    /// Recognition confidence, 0 to 100
+    pub confidence: f32,
+    pub bounding_box: BoundingBox,
+}
+
+/// Text recognized in an image
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OcrOutput {
+    /// Words in reading order; lines separated by newlines, blocks by blank lines
+    pub text: String,
+    pub words: Vec<OcrWord>,
+    
+    /// Language reported by the engine, if it knows
+    pub language: Option<String>,
+}
+
+impl OcrOutput {
+    /// Mean word confidence, `None` without words
+    pub fn mean_confidence(&self) -> Option<f32> {
+        if self.words.is_empty() {
+            return None;
+        }
+        Some(self.words.iter().map(|word| word.confidence).sum::<f32>() / self.words.len() as f32)
+    }
+}
+
+/// Recognizes text in images (Tesseract, cloud vision APIs, ...)
+#[async_trait]
+pub trait OcrEngine: Send + Sync {
+    /// Engine name, recorded with the results it produced
+    fn name(&self) -> &str;
+    
+    /// Recognize the text in encoded image bytes (PNG, JPEG, TIFF, ...)
+    async fn recognize(&self, image: &[u8]) -> DocumentResult<OcrOutput>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_mean_confidence() {
+        let word = |confidence| OcrWord {
+            text: "word".to_string(),
+            confidence,
+            bounding_box: BoundingBox { left: 0, top: 0, width: 10, height: 10 },
+        };
+        assert_eq!(OcrOutput::default().mean_confidence(), None);
+        let output = OcrOutput { words: vec![word(90.0), word(70.0)], ..Default::default() };
+        assert_eq!(output.mean_confidence(), Some(80.0));
+    }
+}
@@ -0,0 +1,246 @@
+//! Office Open XML Packages
+//!
+//! xlsx, docx and pptx files are zip packages of XML parts linked by
+//! relationship parts. This module reads parts, resolves relationships and
+//! scans XML with a small pull tokenizer; extracting text only needs element
+//! names, attributes and character data, not a full XML parser.
+
+use super::*;
+use std::borrow::Cow;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+/// An opened Office Open XML package
+pub(crate) struct OoxmlPackage<'a> {
+    archive: ZipArchive<Cursor<&'a [u8]>>,
+}
+
+impl<'a> OoxmlPackage<'a> {
+    pub(crate) fn open(bytes: &'a [u8]) -> DocumentResult<Self> {
+        let archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| DocumentError::ProcessingFailed {
+            reason: format!("invalid Office Open XML package: {}", e),
+        })?;
+        Ok(Self { archive })
+    }
+    
+    /// Text of a part, `None` if the package does not contain it
+    pub(crate) fn read_part(&mut self, name: &str) -> DocumentResult<Option<String>> {
+        let mut file = match self.archive.by_name(name.trim_start_matches('/')) {
+            Ok(file) => file,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(DocumentError::ProcessingFailed { reason: format!("reading part {} failed: {}", name, e) }),
+        };
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+        Ok(Some(text))
+    }
+    
+    /// Relationships of a part: relationship ID to the target part name
+    pub(crate) fn relationships(&mut self, part: &str) -> DocumentResult<HashMap<String, String>> {
+        let (directory, file_name) = part.rsplit_once('/').unwrap_or(("", part));
+        let rels_name = format!("{}/_rels/{}.rels", directory, file_name);
+        let Some(xml) = self.read_part(rels_name.trim_start_matches('/'))? else {
+            return Ok(HashMap::new());
+        };
+        
+        let mut relationships = HashMap::new();
+        for event in XmlReader::new(&xml) {
+            if let XmlEvent::Start { name: "Relationship", attributes, .. } = event {
+                let external = attribute(attributes, "TargetMode").is_some_and(|mode| mode == "External");
+                if let (Some(id), Some(target), false) = (attribute(attributes, "Id"), attribute(attributes, "Target"), external) {
+                    relationships.insert(id.into_owned(), resolve_part(directory, &target));
+                }
+            }
+        }
+        Ok(relationships)
+    }
+}
+
+/// Part name of a relationship target, relative to the source part's directory
+fn resolve_part(directory: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_string();
+    }
+    let mut segments: Vec<&str> = directory.split('/').filter(|segment| !segment.is_empty()).collect();
+    for segment in target.split('/') {
+        match segment {
+            ".." => {
+                segments.pop();
+            }
+            "." | "" => {}
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// One token of an XML document
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum XmlEvent<'a> {
+    /// Start tag, with the namespace prefix stripped from the name
+    Start { name: &'a str, attributes: &'a str, empty: bool },
+    End { name: &'a str },
+    /// Character data, entities decoded
+    Text(Cow<'a, str>),
+}
+
+/// Pull tokenizer over well-formed XML; comments, processing instructions
+/// and declarations are skipped
+pub(crate) struct XmlReader<'a> {
+    xml: &'a str,
+    position: usize,
+}
+
+impl<'a> XmlReader<'a> {
+    pub(crate) fn new(xml: &'a str) -> Self {
+        Self { xml, position: 0 }
+    }
+}
+
+impl<'a> Iterator for XmlReader<'a> {
+    type Item = XmlEvent<'a>;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let rest = &self.xml[self.position..];
+            if rest.is_empty() {
+                return None;
+            }
+            
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.position += end;
+                return Some(XmlEvent::Text(unescape(&rest[..end])));
+            }
+            
+            let (terminator, skip) = if rest.starts_with("<!--") {
+                ("-->", true)
+            } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = cdata.find("]]>").unwrap_or(cdata.len());
+                self.position += "<![CDATA[".len() + end + "]]>".len().min(cdata.len() - end);
+                return Some(XmlEvent::Text(Cow::Borrowed(&cdata[..end])));
+            } else if rest.starts_with("<?") || rest.starts_with("<!") {
+                (">", true)
+            } else {
+                (">", false)
+            };
+            let Some(end) = rest.find(terminator) else {
+                self.position = self.xml.len();
+                return None;
+            };
+            self.position += end + terminator.len();
+            if skip {
+                continue;
+            }
+            
+            let tag = &rest[1..end];
+            if let Some(name) = tag.strip_prefix('/') {
+                return Some(XmlEvent::End { name: local_name(name.trim()) });
+            }
+            let (tag, empty) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let name_end = tag.find(|c: char| c.is_whitespace()).unwrap_or(tag.len());
+            return Some(XmlEvent::Start {
+                name: local_name(&tag[..name_end]),
+                attributes: &tag[name_end..],
+                empty,
+            });
+        }
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit_once(':').map_or(name, |(_, local)| local)
+}
+
+/// Value of an attribute, matched by its name without namespace prefix
+/// unless `name` has a prefix itself
+pub(crate) fn attribute<'a>(attributes: &'a str, name: &str) -> Option<Cow<'a, str>> {
+    let mut rest = attributes;
+    loop {
+        rest = rest.trim_start();
+        let equals = rest.find('=')?;
+        let key = rest[..equals].trim();
+        let value_start = rest[equals + 1..].trim_start();
+        let quote = value_start.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let value_end = value_start[1..].find(quote)?;
+        if key == name || (!name.contains(':') && local_name(key) == name) {
+            return Some(unescape(&value_start[1..1 + value_end]));
+        }
+        rest = &value_start[value_end + 2..];
+    }
+}
+
+/// Decode the predefined and numeric character entities
+pub(crate) fn unescape(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').map(|end| &rest[1..end]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|decimal| decimal.parse::<u32>()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_xml_reader_tokens() {
+        let xml = r#"<?xml version="1.0"?><!-- note --><x:row r="1" spans='1:2'><c t="s"/><v>A &amp; B &#x263A;</v></x:row>"#;
+        let events: Vec<XmlEvent> = XmlReader::new(xml).collect();
+        assert_eq!(events, vec![
+            XmlEvent::Start { name: "row", attributes: r#" r="1" spans='1:2'"#, empty: false },
+            XmlEvent::Start { name: "c", attributes: r#" t="s""#, empty: true },
+            XmlEvent::Start { name: "v", attributes: "", empty: false },
+            XmlEvent::Text(Cow::Owned("A & B \u{263A}".to_string())),
+            XmlEvent::End { name: "v" },
+            XmlEvent::End { name: "row" },
+        ]);
+        
+        assert_eq!(attribute(r#" name="Q1 &lt;draft&gt;" r:id="rId2""#, "id").as_deref(), Some("rId2"));
+        assert_eq!(attribute(r#" name="Q1 &lt;draft&gt;" r:id="rId2""#, "name").as_deref(), Some("Q1 <draft>"));
+        assert_eq!(attribute(r#" name="x""#, "missing"), None);
+        assert_eq!(unescape("a &bogus; b &"), "a &bogus; b &");
+    }
+    
+    #[test]
+    fn test_relationship_targets_resolve_to_part_names() {
+        assert_eq!(resolve_part("xl", "worksheets/sheet1.xml"), "xl/worksheets/sheet1.xml");
+        assert_eq!(resolve_part("ppt/slides", "../media/image1.png"), "ppt/media/image1.png");
+        assert_eq!(resolve_part("xl", "/xl/worksheets/sheet2.xml"), "xl/worksheets/sheet2.xml");
+    }
+}
@@ -0,0 +1,136 @@
+//! Path Patterns
+//!
+//! Glob matching for include/exclude patterns, shared by the file discovery
+//! service and the document reader. Patterns follow gitignore conventions:
+//!
+//! - a pattern without `/` matches the file name at any depth (`*.tmp`, `.*`)
+//! - a pattern with `/` matches the path relative to the watched directory
+//!   (`reports/2024-??.md`, `**/drafts/*.pdf`)
+//! - `*` and `?` do not match `/`; `**` matches any number of directories
+//! - a leading `!` negates a pattern, and the last matching pattern wins, so
+//!   `["*.pdf", "!drafts/*.pdf"]` matches every PDF outside `drafts/`
+
+use globset::{Glob, GlobBuilder, GlobMatcher};
+use std::path::{Path, PathBuf};
+
+/// A compiled, ordered list of glob patterns
+#[derive(Debug, Clone, Default)]
+pub struct PathPatterns {
+    rules: Vec<PatternRule>,
+}
+
+#[derive(Debug, Clone)]
+struct PatternRule {
+    matcher: GlobMatcher,
+    negated: bool,
+    file_name_only: bool,
+}
+
+impl PathPatterns {
+    /// Compile patterns, failing on the first invalid one
+    pub fn new(patterns: &[String]) -> Result<Self, globset::Error> {
+        let rules = patterns.iter()
+            .map(|pattern| compile(pattern))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+    
+    /// Compile patterns, logging and skipping invalid ones
+    pub fn compile_valid(patterns: &[String]) -> Self {
+        let rules = patterns.iter()
+            .filter_map(|pattern| match compile(pattern) {
+                Ok(rule) => Some(rule),
+                Err(e) => {
+                    tracing::error!("Ignoring invalid path pattern {:?}: {}", pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+    
+    /// Whether the last pattern matching `relative_path` is not negated
+    pub fn matches(&self, relative_path: &Path) -> bool {
+        let file_name = relative_path.file_name().map(Path::new);
+        self.rules.iter().rev()
+            .find(|rule| match (rule.file_name_only, file_name) {
+                (true, Some(file_name)) => rule.matcher.is_match(file_name),
+                (true, None) => false,
+                (false, _) => rule.matcher.is_match(relative_path),
+            })
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+fn compile(pattern: &str) -> Result<PatternRule, globset::Error> {
+    let (negated, pattern) = match pattern.strip_prefix('!') {
+        Some(pattern) => (true, pattern),
+        None => (false, pattern),
+    };
+    let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+    let glob: Glob = GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()?;
+    
+    Ok(PatternRule {
+        matcher: glob.compile_matcher(),
+        negated,
+        file_name_only: !pattern.contains('/'),
+    })
+}
+
+/// `path` relative to the first of `roots` containing it, or `path` itself
+pub fn relative_to_roots<'a>(path: &'a Path, roots: &[PathBuf]) -> &'a Path {
+    roots.iter()
+        .find_map(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn patterns(patterns: &[&str]) -> PathPatterns {
+        PathPatterns::new(&patterns.iter().map(|pattern| pattern.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+    
+    #[test]
+    fn test_file_name_and_nested_path_patterns() {
+        let hidden_and_temp = patterns(&[".*", "*.tmp"]);
+        assert!(hidden_and_temp.matches(Path::new(".hidden.txt")));
+        assert!(hidden_and_temp.matches(Path::new("a/b/cache.tmp")));
+        assert!(!hidden_and_temp.matches(Path::new("a/visible.txt")));
+        
+        let pdfs = patterns(&["**/*.pdf"]);
+        assert!(pdfs.matches(Path::new("report.pdf")));
+        assert!(pdfs.matches(Path::new("reports/2024/q1/report.pdf")));
+        assert!(!pdfs.matches(Path::new("reports/report.pdf.txt")));
+        
+        let monthly = patterns(&["reports/2024-??.md"]);
+        assert!(monthly.matches(Path::new("reports/2024-06.md")));
+        assert!(!monthly.matches(Path::new("reports/2024-6.md")));
+        assert!(!monthly.matches(Path::new("reports/archive/2024-06.md")));
+        assert!(!monthly.matches(Path::new("other/reports/2024-06.md")));
+    }
+    
+    #[test]
+    fn test_negation_last_match_wins() {
+        let pdfs_outside_drafts = patterns(&["**/*.pdf", "!drafts/**", "drafts/final.pdf"]);
+        assert!(pdfs_outside_drafts.matches(Path::new("a/b/c.pdf")));
+        assert!(!pdfs_outside_drafts.matches(Path::new("drafts/wip.pdf")));
+        assert!(!pdfs_outside_drafts.matches(Path::new("drafts/nested/wip.pdf")));
+        assert!(pdfs_outside_drafts.matches(Path::new("drafts/final.pdf")));
+        assert!(!patterns(&[]).matches(Path::new("anything.txt")));
+    }
+    
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(PathPatterns::new(&["[unclosed".to_string()]).is_err());
+        let valid = PathPatterns::compile_valid(&["[unclosed".to_string(), "*.md".to_string()]);
+        assert!(valid.matches(Path::new("notes/readme.md")));
+        
+        let roots = vec![PathBuf::from("/data/in")];
+        assert_eq!(relative_to_roots(Path::new("/data/in/a/b.txt"), &roots), Path::new("a/b.txt"));
+        assert_eq!(relative_to_roots(Path::new("/elsewhere/b.txt"), &roots), Path::new("/elsewhere/b.txt"));
+    }
+}
@@ -0,0 +1,166 @@
+//! PDF Text Extraction
+//!
+//! Parses binary PDFs with `lopdf` (enabled by the `pdf` feature) to extract
+//! page text, the page count, the PDF version and the embedded document
+//! information dictionary (title, author, dates, ...).
+
+use super::*;
+use lopdf::Object;
+
+/// Information dictionary keys copied into the extraction metadata
+const INFO_KEYS: [(&str, &str); 8] = [
+    ("Title", "title"),
+    ("Author", "author"),
+    ("Subject", "subject"),
+    ("Keywords", "keywords"),
+    ("Creator", "creator"),
+    ("Producer", "producer"),
+    ("CreationDate", "creation_date"),
+    ("ModDate", "modification_date"),
+];
+
+/// Text and metadata extracted from a PDF
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfExtraction {
+    /// Text of all pages, in page order
+    pub text: String,
+    pub page_count: usize,
+    pub pdf_version: String,
+    
+    /// Document information dictionary entries (title, author, ...)
+    pub info: HashMap<String, serde_json::Value>,
+    
+    /// Pages whose text could not be extracted
+    pub warnings: Vec<ProcessingWarning>,
+}
+
+/// Extract text and metadata from PDF bytes
+pub fn extract_pdf(bytes: &[u8]) -> DocumentResult<PdfExtraction> {
+    let document = lopdf::Document::load_mem(bytes).map_err(|e| DocumentError::ProcessingFailed {
+        reason: format!("invalid PDF: {}", e),
+    })?;
+    
+    let page_numbers: Vec<u32> = document.get_pages().keys().copied().collect();
+    let mut pages = Vec::with_capacity(page_numbers.len());
+    let mut warnings = Vec::new();
+    for page_number in &page_numbers {
+        // A page without extractable text should not fail the whole document
+        match document.extract_text(&[*page_number]) {
+            Ok(text) => pages.push(text.trim().to_string()),
+            Err(e) => warnings.push(ProcessingWarning::new("pdf", "page_unreadable",
+                format!("no text extracted from page {}: {}", page_number, e))),
+        }
+    }
+    
+    let mut info = HashMap::new();
+    if let Ok(dictionary) = document.trailer.get_deref(b"Info", &document).and_then(Object::as_dict) {
+        for (key, name) in INFO_KEYS {
+            if let Ok(value) = dictionary.get_deref(key.as_bytes(), &document).and_then(Object::as_str) {
+                let value = decode_text_string(value);
+                if !value.is_empty() {
+                    info.insert(name.to_string(), serde_json::Value::String(value));
+                }
+            }
+        }
+    }
+    
+    Ok(PdfExtraction {
+        text: pages.into_iter().filter(|page| !page.is_empty()).collect::<Vec<_>>().join("\n\n"),
+        page_count: page_numbers.len(),
+        pdf_version: document.version.clone(),
+        info,
+        warnings,
+    })
+}
+
+/// Decode a PDF text string (UTF-16BE with BOM, otherwise PDFDocEncoding ≈ Latin-1)
+fn decode_text_string(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xFE, 0xFF]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => bytes.iter().map(|&byte| byte as char).collect(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Stream};
+    
+    /// Build a PDF with one page per entry in `pages` and an information dictionary
+    pub(crate) fn create_test_pdf(pages: &[&str], title: &str) -> Vec<u8> {
+        let mut document = lopdf::Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let font_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+        });
+        let resources_id = document.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        
+        let mut kids = Vec::new();
+        for text in pages {
+            let content = Content {
+                operations: vec![
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                    Operation::new("Td", vec![100.into(), 600.into()]),
+                    Operation::new("Tj", vec![Object::string_literal(*text)]),
+                    Operation::new("ET", vec![]),
+                ],
+            };
+            let content_id = document.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            let page_id = document.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            });
+            kids.push(page_id.into());
+        }
+        
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Count" => kids.len() as i64,
+            "Kids" => kids,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        }));
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        let info_id = document.add_object(dictionary! {
+            "Title" => Object::string_literal(title),
+            "Author" => Object::String(vec![0xFE, 0xFF, 0x00, 0xC5, 0x00, b's', 0x00, b'a'], lopdf::StringFormat::Literal),
+        });
+        document.trailer.set("Root", catalog_id);
+        document.trailer.set("Info", info_id);
+        
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).unwrap();
+        bytes
+    }
+    
+    #[test]
+    fn test_extracts_text_pages_and_info() {
+        let bytes = create_test_pdf(&["Quarterly revenue grew", "Outlook remains positive"], "Q3 Report");
+        let extraction = extract_pdf(&bytes).unwrap();
+        
+        assert_eq!(extraction.page_count, 2);
+        assert_eq!(extraction.pdf_version, "1.5");
+        assert!(extraction.text.contains("Quarterly revenue grew"));
+        assert!(extraction.text.contains("Outlook remains positive"));
+        assert_eq!(extraction.info["title"], "Q3 Report");
+        assert_eq!(extraction.info["author"], "Åsa");
+    }
+    
+    #[test]
+    fn test_invalid_pdf_is_an_error() {
+        assert!(matches!(extract_pdf(b"not a pdf"), Err(DocumentError::ProcessingFailed { .. })));
+    }
+}
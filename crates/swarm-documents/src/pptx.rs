@@ -0,0 +1,161 @@
+//! PowerPoint Slide Extraction
+//!
+//! Reads pptx presentations: the slides listed in the presentation, in
+//! order, with the title placeholder's text as the slide title and the
+//! paragraphs of every other shape and table as its body. Each slide
+//! becomes a section of the extracted text headed by its number and title.
+
+use super::*;
+use crate::ooxml::{attribute, OoxmlPackage, XmlEvent, XmlReader};
+
+const PRESENTATION_PART: &str = "ppt/presentation.xml";
+
+/// Text of one slide
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SlideExtraction {
+    /// Position in the presentation, counting from 1
+    pub number: usize,
+    pub title: Option<String>,
+    
+    /// Non-empty paragraphs outside the title, in document order
+    #[serde(skip)]
+    pub body: Vec<String>,
+}
+
+/// Text and slides extracted from a presentation
+#[derive(Debug, Clone, PartialEq)]
+pub struct PptxExtraction {
+    /// One section per slide, separated by blank lines
+    pub text: String,
+    pub slides: Vec<SlideExtraction>,
+}
+
+/// Extract slide titles and body text from pptx bytes
+pub fn extract_pptx(bytes: &[u8]) -> DocumentResult<PptxExtraction> {
+    let mut package = OoxmlPackage::open(bytes)?;
+    let presentation = package.read_part(PRESENTATION_PART)?.ok_or_else(|| DocumentError::ProcessingFailed {
+        reason: "invalid pptx: the package has no presentation".to_string(),
+    })?;
+    let relationships = package.relationships(PRESENTATION_PART)?;
+    
+    let slide_ids: Vec<String> = XmlReader::new(&presentation)
+        .filter_map(|event| match event {
+            XmlEvent::Start { name: "sldId", attributes, .. } => attribute(attributes, "r:id").map(|id| id.into_owned()),
+            _ => None,
+        })
+        .collect();
+    
+    let mut slides = Vec::new();
+    for relationship_id in slide_ids {
+        let Some(part) = relationships.get(&relationship_id) else {
+            tracing::debug!("Slide relationship {} has no target", relationship_id);
+            continue;
+        };
+        if let Some(xml) = package.read_part(part)? {
+            slides.push(parse_slide(slides.len() + 1, &xml));
+        }
+    }
+    
+    let text = slides.iter()
+        .map(|slide| {
+            let mut section = match &slide.title {
+                Some(title) => format!("Slide {}: {}", slide.number, title),
+                None => format!("Slide {}", slide.number),
+            };
+            for paragraph in &slide.body {
+                section.push('\n');
+                section.push_str(paragraph);
+            }
+            section
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Ok(PptxExtraction { text, slides })
+}
+
+fn parse_slide(number: usize, xml: &str) -> SlideExtraction {
+    let mut title: Vec<String> = Vec::new();
+    let mut body = Vec::new();
+    let mut paragraph = String::new();
+    let (mut in_title_shape, mut in_text) = (false, false);
+    
+    for event in XmlReader::new(xml) {
+        match event {
+            XmlEvent::Start { name: "sp", empty: false, .. } => in_title_shape = false,
+            XmlEvent::End { name: "sp" } => in_title_shape = false,
+            XmlEvent::Start { name: "ph", attributes, .. } => {
+                in_title_shape = attribute(attributes, "type").is_some_and(|kind| kind == "title" || kind == "ctrTitle");
+            }
+            XmlEvent::Start { name: "p", empty: false, .. } => paragraph.clear(),
+            XmlEvent::End { name: "p" } => {
+                let text = paragraph.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !text.is_empty() {
+                    if in_title_shape { title.push(text) } else { body.push(text) }
+                }
+            }
+            XmlEvent::Start { name: "br", .. } => paragraph.push(' '),
+            XmlEvent::Start { name: "t", empty: false, .. } => in_text = true,
+            XmlEvent::End { name: "t" } => in_text = false,
+            XmlEvent::Text(text) if in_text => paragraph.push_str(&text),
+            _ => {}
+        }
+    }
+    
+    SlideExtraction {
+        number,
+        title: (!title.is_empty()).then(|| title.join(" ")),
+        body,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    
+    fn slide(title: Option<&str>, paragraphs: &[&str]) -> String {
+        let title = title.map(|title| format!(
+            r#"<p:sp><p:nvSpPr><p:cNvPr id="2" name="Title 1"/><p:nvPr><p:ph type="title"/></p:nvPr></p:nvSpPr><p:txBody><a:p><a:r><a:t>{}</a:t></a:r></a:p></p:txBody></p:sp>"#,
+            title
+        )).unwrap_or_default();
+        let body: String = paragraphs.iter().map(|text| format!("<a:p><a:r><a:rPr lang=\"en-US\"/><a:t>{}</a:t></a:r></a:p>", text)).collect();
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><p:sld xmlns:a="a" xmlns:p="p"><p:cSld><p:spTree>{}<p:sp><p:nvSpPr><p:nvPr><p:ph idx="1"/></p:nvPr></p:nvSpPr><p:txBody>{}<a:p><a:endParaRPr/></a:p></p:txBody></p:sp></p:spTree></p:cSld></p:sld>"#,
+            title, body
+        )
+    }
+    
+    /// Presentation whose slide parts are listed out of order
+    pub(crate) fn create_test_presentation() -> Vec<u8> {
+        let parts = [
+            ("ppt/presentation.xml".to_string(), r#"<p:presentation xmlns:p="p" xmlns:r="r"><p:sldIdLst><p:sldId id="256" r:id="rId3"/><p:sldId id="257" r:id="rId2"/></p:sldIdLst></p:presentation>"#.to_string()),
+            ("ppt/_rels/presentation.xml.rels".to_string(), r#"<Relationships><Relationship Id="rId2" Target="slides/slide2.xml"/><Relationship Id="rId3" Target="slides/slide1.xml"/></Relationships>"#.to_string()),
+            ("ppt/slides/slide1.xml".to_string(), slide(Some("Quarterly Review"), &["Revenue grew 12%", "Costs &amp; margins"])),
+            ("ppt/slides/slide2.xml".to_string(), slide(None, &["Questions?"])),
+        ];
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, xml) in parts {
+            zip.start_file(name, FileOptions::default()).unwrap();
+            zip.write_all(xml.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+    
+    #[test]
+    fn test_extracts_slides_in_presentation_order() {
+        let extraction = extract_pptx(&create_test_presentation()).unwrap();
+        
+        assert_eq!(extraction.slides.len(), 2);
+        assert_eq!(extraction.slides[0].title.as_deref(), Some("Quarterly Review"));
+        assert_eq!(extraction.slides[0].body, vec!["Revenue grew 12%", "Costs & margins"]);
+        assert_eq!(extraction.slides[1].title, None);
+        assert_eq!(extraction.text, "Slide 1: Quarterly Review\nRevenue grew 12%\nCosts & margins\n\nSlide 2\nQuestions?");
+    }
+    
+    #[test]
+    fn test_packages_without_a_presentation_are_rejected() {
+        assert!(extract_pptx(b"PK not really").is_err());
+        assert!(extract_pptx(&crate::xlsx::tests::create_test_workbook()).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Processing State
+//!
+//! The reader remembers which files it has read, so a scan only picks up
+//! new and changed files. Each entry holds the file's path, a hash of its
+//! content and when it was read. With a persistent store (sled) that
+//! survives restarts, files are not read again after a restart, and a file
+//! that was touched without changing is recognised by its hash. Entries of
+//! files that have been deleted are pruned after each scan.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A file the reader has read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessedFile {
+    pub path: PathBuf,
+    
+    /// SHA-256 of the file's content (and sidecar, if any)
+    pub content_hash: String,
+    
+    /// Modification time of the file (or its sidecar) when it was read
+    pub modified_at: DateTime<Utc>,
+    
+    pub processed_at: DateTime<Utc>,
+}
+
+/// Storage of the files a reader has read
+pub trait ProcessingStateStore: Send + Sync {
+    fn get(&self, path: &Path) -> Result<Option<ProcessedFile>>;
+    
+    /// Record a file, replacing any earlier entry for its path
+    fn record(&self, file: &ProcessedFile) -> Result<()>;
+    
+    /// Forget a file, so it is read again; returns whether it was known
+    fn remove(&self, path: &Path) -> Result<bool>;
+    
+    /// Every recorded file, ordered by path
+    fn entries(&self) -> Result<Vec<ProcessedFile>>;
+    
+    /// Forget every file, returning how many there were
+    fn reset(&self) -> Result<usize>;
+    
+    /// Forget the files that no longer exist, returning their paths
+    fn prune_missing(&self) -> Result<Vec<PathBuf>> {
+        let mut pruned = Vec::new();
+        for file in self.entries()? {
+            if !file.path.exists() && self.remove(&file.path)? {
+                pruned.push(file.path);
+            }
+        }
+        Ok(pruned)
+    }
+}
+
+/// Processing state kept in memory, lost on restart
+#[derive(Debug, Default)]
+pub struct MemoryStateStore {
+    files: Mutex<HashMap<PathBuf, ProcessedFile>>,
+}
+
+impl MemoryStateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ProcessingStateStore for MemoryStateStore {
+    fn get(&self, path: &Path) -> Result<Option<ProcessedFile>> {
+        Ok(self.files.lock().unwrap().get(path).cloned())
+    }
+    
+    fn record(&self, file: &ProcessedFile) -> Result<()> {
+        self.files.lock().unwrap().insert(file.path.clone(), file.clone());
+        Ok(())
+    }
+    
+    fn remove(&self, path: &Path) -> Result<bool> {
+        Ok(self.files.lock().unwrap().remove(path).is_some())
+    }
+    
+    fn entries(&self) -> Result<Vec<ProcessedFile>> {
+        let mut entries: Vec<ProcessedFile> = self.files.lock().unwrap().values().cloned().collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+    
+    fn reset(&self) -> Result<usize> {
+        let mut files = self.files.lock().unwrap();
+        let count = files.len();
+        files.clear();
+        Ok(count)
+    }
+}
+
+/// sled-backed processing state that survives restarts
+#[derive(Debug, Clone)]
+pub struct SledStateStore {
+    files: sled::Tree,
+}
+
+impl SledStateStore {
+    /// Open (or create) a state database at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_db(&sled::open(path)?)
+    }
+    
+    /// State database that lives only as long as the process
+    pub fn temporary() -> Result<Self> {
+        Self::from_db(&sled::Config::new().temporary(true).open()?)
+    }
+    
+    fn from_db(db: &sled::Db) -> Result<Self> {
+        Ok(Self { files: db.open_tree("processed_files")? })
+    }
+    
+    fn key(path: &Path) -> Vec<u8> {
+        path.to_string_lossy().into_owned().into_bytes()
+    }
+}
+
+impl ProcessingStateStore for SledStateStore {
+    fn get(&self, path: &Path) -> Result<Option<ProcessedFile>> {
+        match self.files.get(Self::key(path))? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+    
+    fn record(&self, file: &ProcessedFile) -> Result<()> {
+        self.files.insert(Self::key(&file.path), serde_json::to_vec(file)?)?;
+        self.files.flush()?;
+        Ok(())
+    }
+    
+    fn remove(&self, path: &Path) -> Result<bool> {
+        let removed = self.files.remove(Self::key(path))?.is_some();
+        self.files.flush()?;
+        Ok(removed)
+    }
+    
+    fn entries(&self) -> Result<Vec<ProcessedFile>> {
+        self.files.iter()
+            .map(|entry| Ok(serde_json::from_slice(&entry?.1)?))
+            .collect()
+    }
+    
+    fn reset(&self) -> Result<usize> {
+        let count = self.files.len();
+        self.files.clear()?;
+        self.files.flush()?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    
+    fn processed(path: &Path) -> ProcessedFile {
+        ProcessedFile {
+            path: path.to_path_buf(),
+            content_hash: swarm_core::sha256_hex(b"content"),
+            modified_at: Utc::now(),
+            processed_at: Utc::now(),
+        }
+    }
+    
+    #[test]
+    fn test_sled_state_survives_reopening() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("state");
+        let file = processed(&dir.path().join("a.txt"));
+        {
+            let store = SledStateStore::open(&db_path).unwrap();
+            store.record(&file).unwrap();
+        }
+        
+        let store = SledStateStore::open(&db_path).unwrap();
+        assert_eq!(store.get(&file.path).unwrap(), Some(file.clone()));
+        assert_eq!(store.entries().unwrap(), vec![file.clone()]);
+        assert!(store.remove(&file.path).unwrap());
+        assert!(!store.remove(&file.path).unwrap());
+        assert_eq!(store.get(&file.path).unwrap(), None);
+    }
+    
+    #[test]
+    fn test_missing_files_are_pruned() {
+        let dir = tempdir().unwrap();
+        let kept = dir.path().join("kept.txt");
+        std::fs::write(&kept, "kept").unwrap();
+        let deleted = dir.path().join("deleted.txt");
+        
+        let stores: Vec<Box<dyn ProcessingStateStore>> = vec![Box::new(MemoryStateStore::new()), Box::new(SledStateStore::temporary().unwrap())];
+        for store in stores {
+            store.record(&processed(&kept)).unwrap();
+            store.record(&processed(&deleted)).unwrap();
+            assert_eq!(store.prune_missing().unwrap(), vec![deleted.clone()]);
+            assert_eq!(store.entries().unwrap().len(), 1);
+            assert_eq!(store.reset().unwrap(), 1);
+            assert!(store.entries().unwrap().is_empty());
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Extraction Quality
+//!
+//! Scores extracted text before it reaches an index. Text from scanned or
+//! badly encoded documents tends to be short, full of replacement and control
+//! characters, or made of tokens that are not words; each of these lowers the
+//! score. Results below the configured threshold are flagged, and optionally
+//! routed to OCR or manual review, through their metadata.
+
+use super::*;
+
+/// Result metadata key holding the `QualityScore`
+pub const QUALITY_METADATA_KEY: &str = "extraction_quality";
+
+/// Result metadata key set to `true` on results below the threshold
+pub const LOW_QUALITY_KEY: &str = "low_quality";
+
+/// Result metadata key naming the route of a low-quality result
+pub const QUALITY_ROUTE_KEY: &str = "quality_route";
+
+/// Where low-quality results are sent for another attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityRoute {
+    Ocr,
+    ManualReview,
+}
+
+impl QualityRoute {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QualityRoute::Ocr => "ocr",
+            QualityRoute::ManualReview => "manual_review",
+        }
+    }
+    
+    /// Subject low-quality documents are published on
+    pub fn subject(&self) -> &'static str {
+        match self {
+            QualityRoute::Ocr => "swarm.documents.ocr",
+            QualityRoute::ManualReview => "swarm.documents.review",
+        }
+    }
+}
+
+/// Extraction quality configuration
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QualityConfig {
+    /// Score extracted text
+    pub enabled: bool,
+    
+    /// Score below which a result is flagged, from 0.0 to 1.0
+    pub min_score: f32,
+    
+    /// Route for flagged results; `None` only flags them
+    pub low_quality_route: Option<QualityRoute>,
+    
+    /// Characters of text considered long enough to be meaningful
+    pub min_length: usize,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_score: 0.5,
+            low_quality_route: None,
+            min_length: 50,
+        }
+    }
+}
+
+/// Quality of one extracted text
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QualityScore {
+    /// Weighted overall score, from 0.0 to 1.0
+    pub score: f32,
+    
+    /// Share of characters that are letters, digits, whitespace or punctuation
+    pub character_ratio: f32,
+    
+    /// Share of tokens that look like dictionary words
+    pub word_ratio: f32,
+    
+    /// Text length relative to `min_length`, capped at 1.0
+    pub length_score: f32,
+    
+    /// Unicode replacement characters (U+FFFD), a sign of decoding errors
+    pub replacement_chars: usize,
+}
+
+/// Scores extracted text and flags results below the threshold
+#[derive(Debug, Clone, Default)]
+pub struct QualityScorer {
+    config: QualityConfig,
+}
+
+impl QualityScorer {
+    pub fn new(config: QualityConfig) -> Self {
+        Self { config }
+    }
+    
+    pub fn config(&self) -> &QualityConfig {
+        &self.config
+    }
+    
+    /// Score a text
+    pub fn score(&self, text: &str) -> QualityScore {
+        let (mut total, mut clean, mut replacement_chars) = (0usize, 0usize, 0usize);
+        for c in text.chars() {
+            total += 1;
+            if c == char::REPLACEMENT_CHARACTER {
+                replacement_chars += 1;
+            } else if c.is_alphanumeric() || c.is_whitespace() || c.is_ascii_punctuation() || is_cjk_punctuation(c) {
+                clean += 1;
+            }
+        }
+        let character_ratio = if total == 0 { 0.0 } else { clean as f32 / total as f32 };
+        
+        let tokens: Vec<&str> = text.split_whitespace()
+            .map(|token| token.trim_matches(|c: char| c.is_ascii_punctuation()))
+            .filter(|token| !token.is_empty())
+            .collect();
+        let words = tokens.iter().filter(|token| looks_like_word(token)).count();
+        let word_ratio = if tokens.is_empty() { 0.0 } else { words as f32 / tokens.len() as f32 };
+        
+        let length_score = (total as f32 / self.config.min_length.max(1) as f32).min(1.0);
+        
+        QualityScore {
+            score: 0.4 * character_ratio + 0.4 * word_ratio + 0.2 * length_score,
+            character_ratio,
+            word_ratio,
+            length_score,
+            replacement_chars,
+        }
+    }
+    
+    /// Score the extracted text of a result and record the outcome in its metadata
+    ///
+    /// Returns the score, or `None` when scoring is disabled or nothing was extracted.
+    pub fn assess(&self, result: &mut DocumentProcessingResult) -> Option<QualityScore> {
+        if !self.config.enabled {
+            return None;
+        }
+        let score = self.score(result.extracted_text.as_deref()?);
+        result.metadata.insert(QUALITY_METADATA_KEY.to_string(), serde_json::to_value(&score).unwrap_or_default());
+        if score.score < self.config.min_score {
+            result.metadata.insert(LOW_QUALITY_KEY.to_string(), serde_json::Value::Bool(true));
+            if let Some(route) = self.config.low_quality_route {
+                result.metadata.insert(QUALITY_ROUTE_KEY.to_string(), serde_json::json!(route.as_str()));
+            }
+        }
+        Some(score)
+    }
+}
+
+/// Route recorded on a result by `QualityScorer::assess`, if any
+pub fn quality_route(result: &DocumentProcessingResult) -> Option<QualityRoute> {
+    match result.metadata.get(QUALITY_ROUTE_KEY)?.as_str()? {
+        "ocr" => Some(QualityRoute::Ocr),
+        "manual_review" => Some(QualityRoute::ManualReview),
+        _ => None,
+    }
+}
+
+/// Whether a token looks like a word or number: mostly letters, not too
+/// long, and containing a vowel when written in ASCII
+fn looks_like_word(token: &str) -> bool {
+    if token.chars().any(is_cjk) {
+        return token.chars().all(|c| is_cjk(c) || is_cjk_punctuation(c));
+    }
+    let letters = token.chars().filter(|c| c.is_alphabetic()).count();
+    if letters == 0 || token.chars().count() > 24 {
+        // Numbers are fine, other letterless tokens are not
+        return letters == 0 && token.chars().all(|c| c.is_ascii_digit() || ".,-/:%".contains(c));
+    }
+    let mostly_letters = letters * 10 >= token.chars().count() * 8;
+    let has_vowel = token.chars().any(|c| "aeiouyåäöéèüAEIOUYÅÄÖÉÈÜ".contains(c)) || !token.is_ascii();
+    mostly_letters && has_vowel
+}
+
+fn is_cjk_punctuation(c: char) -> bool {
+    matches!(c, '\u{3000}'..='\u{303F}' | '\u{FF00}'..='\u{FF0F}' | '\u{FF1A}'..='\u{FF20}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn create_test_result(text: &str) -> DocumentProcessingResult {
+        DocumentProcessingResult {
+            document_id: Uuid::new_v4(),
+            extracted_text: Some(text.to_string()),
+            metadata: HashMap::new(),
+            language: None,
+            keywords: Vec::new(),
+            sentiment: None,
+            classification: None,
+            embeddings: None,
+            processing_time_ms: 0,
+            processed_at: Utc::now(),
+            warnings: Vec::new(),
+        }
+    }
+    
+    #[test]
+    fn test_clean_text_scores_higher_than_garbage() {
+        let scorer = QualityScorer::default();
+        let clean = scorer.score("The quarterly report shows revenue grew by 12% in the second quarter of 2024.");
+        let garbage = scorer.score("\u{FFFD}\u{FFFD}x7#@ qzxv \u{1}\u{2}\u{FFFD} bcdfg ~~~~ \u{FFFD}");
+        let short = scorer.score("Report");
+        
+        assert!(clean.score > 0.9, "{:?}", clean);
+        assert!(garbage.score < 0.5, "{:?}", garbage);
+        assert_eq!(garbage.replacement_chars, 4);
+        assert!(short.score < clean.score && short.length_score < 0.2);
+        assert_eq!(scorer.score("机器学习是人工智能的一个分支，它使计算机能够从数据中学习。").word_ratio, 1.0);
+        assert_eq!(scorer.score("").score, 0.0);
+    }
+    
+    #[test]
+    fn test_low_quality_results_are_flagged_and_routed() {
+        let scorer = QualityScorer::new(QualityConfig { low_quality_route: Some(QualityRoute::Ocr), ..Default::default() });
+        let mut good = create_test_result("A perfectly readable paragraph of extracted text, long enough to count.");
+        let mut bad = create_test_result("\u{FFFD}\u{FFFD} ## qxz \u{FFFD}");
+        
+        assert!(scorer.assess(&mut good).is_some());
+        assert!(good.metadata.contains_key(QUALITY_METADATA_KEY));
+        assert!(!good.metadata.contains_key(LOW_QUALITY_KEY) && quality_route(&good).is_none());
+        
+        scorer.assess(&mut bad);
+        assert_eq!(bad.metadata[LOW_QUALITY_KEY], serde_json::json!(true));
+        assert_eq!(quality_route(&bad), Some(QualityRoute::Ocr));
+        assert_eq!(QualityRoute::Ocr.subject(), "swarm.documents.ocr");
+        
+        let mut nothing = DocumentProcessingResult { extracted_text: None, ..create_test_result("") };
+        assert!(scorer.assess(&mut nothing).is_none());
+        assert!(QualityScorer::new(QualityConfig { enabled: false, ..Default::default() }).assess(&mut bad.clone()).is_none());
+    }
+}
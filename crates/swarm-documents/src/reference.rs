@@ -0,0 +1,270 @@
+//! Reference Content Fetching
+//!
+//! Resolves `DocumentContent::Reference` from its storage and verifies the
+//! fetched bytes against the checksum and ETag captured at discovery, so a
+//! document modified in between is detected and its task requeued instead of
+//! processing content that no longer matches what was discovered.
+
+use super::*;
+use async_trait::async_trait;
+use std::path::{Component, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use swarm_core::{sha256_hex, DocumentStore};
+
+/// Content fetched from storage
+#[derive(Debug, Clone)]
+pub struct FetchedContent {
+    pub bytes: Vec<u8>,
+    
+    /// Storage ETag of the fetched version, if the store provides one
+    pub etag: Option<String>,
+}
+
+/// Storage backend that referenced content is fetched from
+#[async_trait]
+pub trait ReferenceStore: Send + Sync {
+    /// Fetch the content at a path
+    async fn fetch(&self, path: &str, access_token: Option<&str>) -> Result<FetchedContent>;
+}
+
+/// Store reading files below a root directory
+///
+/// ETags are derived from file size and modification time.
+#[derive(Debug, Clone)]
+pub struct FileSystemStore {
+    root: PathBuf,
+}
+
+impl FileSystemStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+    
+    /// Location of a referenced path, which must stay below the root
+    ///
+    /// Reference paths arrive in messages, so absolute paths and `..` are
+    /// refused rather than read from anywhere on the host.
+    fn full_path(&self, path: &str) -> std::io::Result<PathBuf> {
+        let relative = Path::new(path);
+        if !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid reference path: {}", path)));
+        }
+        Ok(self.root.join(relative))
+    }
+    
+    /// Reference to a file in this store, with its current checksum and ETag
+    pub async fn reference(&self, storage_id: &str, path: &str) -> DocumentResult<DocumentContent> {
+        let full_path = self.full_path(path)?;
+        let bytes = fs::read(&full_path).await?;
+        Ok(DocumentContent::Reference {
+            storage_id: storage_id.to_string(),
+            path: path.to_string(),
+            access_token: None,
+            checksum: Some(format!("sha256:{}", sha256_hex(&bytes))),
+            etag: Some(file_etag(&full_path).await?),
+        })
+    }
+}
+
+#[async_trait]
+impl ReferenceStore for FileSystemStore {
+    async fn fetch(&self, path: &str, _access_token: Option<&str>) -> Result<FetchedContent> {
+        let full_path = self.full_path(path)?;
+        let bytes = fs::read(&full_path).await?;
+        let etag = file_etag(&full_path).await?;
+        Ok(FetchedContent { bytes, etag: Some(etag) })
+    }
+}
+
+async fn file_etag(path: &Path) -> std::io::Result<String> {
+    let metadata = fs::metadata(path).await?;
+    let modified = metadata.modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    Ok(format!("{:x}-{:x}", metadata.len(), modified))
+}
+
+/// Reads claim-checked content from a shared `DocumentStore`
+struct DocumentStoreReferences(Arc<dyn DocumentStore>);
+
+#[async_trait]
+impl ReferenceStore for DocumentStoreReferences {
+    async fn fetch(&self, path: &str, _access_token: Option<&str>) -> Result<FetchedContent> {
+        let bytes = self.0.get(path).await?;
+        Ok(FetchedContent { bytes, etag: None })
+    }
+}
+
+/// Fetches referenced content and verifies its integrity
+#[derive(Default, Clone)]
+pub struct ReferenceFetcher {
+    stores: HashMap<String, Arc<dyn ReferenceStore>>,
+}
+
+impl ReferenceFetcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Register the store for a storage ID
+    pub fn with_store(mut self, storage_id: impl Into<String>, store: Arc<dyn ReferenceStore>) -> Self {
+        self.stores.insert(storage_id.into(), store);
+        self
+    }
+    
+    /// Resolve references to a document store, such as content offloaded by a `ClaimCheck`
+    pub fn with_document_store(self, store: Arc<dyn DocumentStore>) -> Self {
+        let storage_id = store.storage_id().to_string();
+        self.with_store(storage_id, Arc::new(DocumentStoreReferences(store)))
+    }
+    
+    /// Fetch referenced content, failing with `IntegrityMismatch` if it changed since discovery
+    pub async fn fetch(&self, content: &DocumentContent) -> DocumentResult<Vec<u8>> {
+        let DocumentContent::Reference { storage_id, path, access_token, checksum, etag } = content else {
+            return Err(DocumentError::ProcessingFailed {
+                reason: "content is not a reference".to_string(),
+            });
+        };
+        
+        let store = self.stores.get(storage_id).ok_or_else(|| DocumentError::ProcessingFailed {
+            reason: format!("unknown storage: {}", storage_id),
+        })?;
+        let fetched = store.fetch(path, access_token.as_deref()).await
+            .map_err(|e| DocumentError::ProcessingFailed {
+                reason: format!("failed to fetch {}/{}: {}", storage_id, path, e),
+            })?;
+        
+        if let (Some(expected), Some(actual)) = (etag, &fetched.etag) {
+            if expected != actual {
+                return Err(mismatch(storage_id, path, "etag", expected, actual));
+            }
+        }
+        
+        if let Some(expected) = checksum {
+            let (algorithm, expected_hex) = expected.split_once(':').unwrap_or(("sha256", expected));
+            if !algorithm.eq_ignore_ascii_case("sha256") {
+                return Err(DocumentError::ProcessingFailed {
+                    reason: format!("unsupported checksum algorithm: {}", algorithm),
+                });
+            }
+            let actual = sha256_hex(&fetched.bytes);
+            if !expected_hex.eq_ignore_ascii_case(&actual) {
+                return Err(mismatch(storage_id, path, "checksum", expected, &format!("sha256:{}", actual)));
+            }
+        }
+        
+        Ok(fetched.bytes)
+    }
+}
+
+fn mismatch(storage_id: &str, path: &str, field: &str, expected: &str, actual: &str) -> DocumentError {
+    tracing::warn!("Referenced content {}/{} changed since discovery ({} {} != {})",
+        storage_id, path, field, actual, expected);
+    DocumentError::IntegrityMismatch {
+        path: format!("{}/{}", storage_id, path),
+        field: field.to_string(),
+        expected: expected.to_string(),
+        actual: actual.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    
+    fn create_test_fetcher(root: &Path) -> (Arc<FileSystemStore>, ReferenceFetcher) {
+        let store = Arc::new(FileSystemStore::new(root));
+        let fetcher = ReferenceFetcher::new().with_store("local", store.clone());
+        (store, fetcher)
+    }
+    
+    #[tokio::test]
+    async fn test_unchanged_reference_is_verified() {
+        let temp_dir = tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "original").unwrap();
+        let (store, fetcher) = create_test_fetcher(temp_dir.path());
+        
+        let reference = store.reference("local", "a.txt").await.unwrap();
+        assert_eq!(fetcher.fetch(&reference).await.unwrap(), b"original");
+    }
+    
+    #[tokio::test]
+    async fn test_modified_content_is_a_requeueable_mismatch() {
+        let temp_dir = tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "original").unwrap();
+        let (store, fetcher) = create_test_fetcher(temp_dir.path());
+        let reference = store.reference("local", "a.txt").await.unwrap();
+        
+        std::fs::write(temp_dir.path().join("a.txt"), "modified").unwrap();
+        let error = fetcher.fetch(&reference).await.unwrap_err();
+        
+        assert!(matches!(error, DocumentError::IntegrityMismatch { .. }));
+        assert!(error.is_requeueable());
+        assert_eq!(error.task_status(), TaskStatus::Retrying);
+    }
+    
+    #[tokio::test]
+    async fn test_checksum_without_etag() {
+        let temp_dir = tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "abc").unwrap();
+        let (_, fetcher) = create_test_fetcher(temp_dir.path());
+        let reference = |checksum: &str| DocumentContent::Reference {
+            storage_id: "local".to_string(),
+            path: "a.txt".to_string(),
+            access_token: None,
+            checksum: Some(checksum.to_string()),
+            etag: None,
+        };
+        
+        let abc = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert!(fetcher.fetch(&reference(&format!("sha256:{}", abc))).await.is_ok());
+        assert!(fetcher.fetch(&reference(&abc.to_uppercase())).await.is_ok());
+        
+        let error = fetcher.fetch(&reference("sha256:00")).await.unwrap_err();
+        assert!(matches!(error, DocumentError::IntegrityMismatch { ref field, .. } if field == "checksum"));
+        assert!(!DocumentError::ProcessingFailed { reason: String::new() }.is_requeueable());
+    }
+    
+    #[tokio::test]
+    async fn test_paths_outside_the_root_are_refused() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(temp_dir.path().join("outside"), "secret").unwrap();
+        let (store, fetcher) = create_test_fetcher(&root);
+        
+        for path in ["../outside", "/etc/passwd"] {
+            assert!(store.reference("local", path).await.is_err());
+            let reference = DocumentContent::Reference {
+                storage_id: "local".to_string(),
+                path: path.to_string(),
+                access_token: None,
+                checksum: None,
+                etag: None,
+            };
+            let error = fetcher.fetch(&reference).await.unwrap_err();
+            assert!(error.to_string().contains("invalid reference path"), "{}", error);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_claim_checked_content_is_fetched_from_document_store() {
+        let claim_check = swarm_core::ClaimCheck::new(Arc::new(swarm_core::MemoryDocumentStore::new("offload")));
+        let fetcher = ReferenceFetcher::new().with_document_store(claim_check.store().clone());
+        let document = Document {
+            id: uuid::Uuid::new_v4(),
+            filename: "large.bin".to_string(),
+            document_type: DocumentType::Unknown,
+            content: DocumentContent::Binary(vec![7; 64]),
+            metadata: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            size_bytes: 64,
+        };
+        
+        let checked_in = claim_check.check_in(&document).await.unwrap();
+        assert_eq!(fetcher.fetch(&checked_in.content).await.unwrap(), vec![7; 64]);
+    }
+}
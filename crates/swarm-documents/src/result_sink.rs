@@ -0,0 +1,384 @@
+//! Partitioned File Sinks
+//!
+//! Writes task results as JSON lines into partitioned directories such as
+//! `out/2024/06/01/tenant=acme/`. Each file is written under an
+//! `.inprogress` name and renamed once complete, so readers never see a
+//! partial file. Every partition has a `_manifest.json` listing its
+//! finalized files. With compression enabled, files are written as a zstd
+//! stream (`.jsonl.zst`).
+
+use super::*;
+use async_trait::async_trait;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use swarm_core::{CompressionConfig, CompressionStats, ResultSink};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// Name of the manifest file in each partition
+pub const MANIFEST_FILENAME: &str = "_manifest.json";
+
+/// Suffix of files still being written
+const IN_PROGRESS_SUFFIX: &str = ".inprogress";
+
+/// Directory level a result is partitioned by
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PartitionKey {
+    /// `yyyy/mm/dd` of the time the result was produced
+    ProcessedDate,
+    /// `tenant=<tenant>` from the result metadata
+    Tenant,
+    /// `document_type=<type>` from the result metadata
+    DocumentType,
+}
+
+/// File sink configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSinkConfig {
+    /// Root directory of the partitions
+    pub output_directory: PathBuf,
+    
+    /// Partition levels, outermost first
+    pub partition_by: Vec<PartitionKey>,
+    
+    /// Records after which a file is finalized and a new one started
+    pub max_records_per_file: usize,
+    
+    /// zstd compression of new files
+    #[serde(default)]
+    pub compression: CompressionConfig,
+}
+
+impl Default for FileSinkConfig {
+    fn default() -> Self {
+        Self {
+            output_directory: PathBuf::from("./results"),
+            partition_by: vec![PartitionKey::ProcessedDate],
+            max_records_per_file: 10_000,
+            compression: CompressionConfig::default(),
+        }
+    }
+}
+
+/// A finalized file in a partition manifest
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ManifestEntry {
+    pub file: String,
+    pub records: usize,
+    
+    /// Size of the file on disk
+    pub size_bytes: u64,
+    
+    /// Size of the JSON lines before compression
+    #[serde(default)]
+    pub uncompressed_bytes: u64,
+    pub first_completed_at: DateTime<Utc>,
+    pub last_completed_at: DateTime<Utc>,
+    pub finalized_at: DateTime<Utc>,
+}
+
+/// Finalized files of one partition
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PartitionManifest {
+    /// Partition path relative to the output directory
+    pub partition: String,
+    pub files: Vec<ManifestEntry>,
+}
+
+impl PartitionManifest {
+    /// Read the manifest of a partition directory (empty if none was written yet)
+    pub async fn load(partition_directory: &Path) -> DocumentResult<Self> {
+        match fs::read(partition_directory.join(MANIFEST_FILENAME)).await {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// File being written in a partition
+struct OpenFile {
+    file: fs::File,
+    path: PathBuf,
+    encoder: Option<zstd::stream::write::Encoder<'static, Vec<u8>>>,
+    records: usize,
+    size_bytes: u64,
+    uncompressed_bytes: u64,
+    first_completed_at: DateTime<Utc>,
+    last_completed_at: DateTime<Utc>,
+}
+
+/// Result sink writing JSON lines into partitioned directories
+pub struct JsonlResultSink {
+    config: FileSinkConfig,
+    open_files: Mutex<HashMap<String, OpenFile>>,
+    stats: std::sync::Mutex<CompressionStats>,
+}
+
+impl JsonlResultSink {
+    pub fn new(config: FileSinkConfig) -> Self {
+        Self {
+            config,
+            open_files: Mutex::new(HashMap::new()),
+            stats: std::sync::Mutex::new(CompressionStats::default()),
+        }
+    }
+    
+    /// Records and bytes written so far, before and after compression
+    pub fn stats(&self) -> CompressionStats {
+        *self.stats.lock().unwrap()
+    }
+    
+    /// Partition path of a result relative to the output directory
+    pub fn partition_for(&self, result: &TaskResult) -> String {
+        let metadata_value = |key: &str| {
+            let value = result.metadata.get(key).and_then(|value| value.as_str()).unwrap_or("unknown");
+            sanitize_partition_value(value)
+        };
+        
+        self.config.partition_by.iter()
+            .map(|key| match key {
+                PartitionKey::ProcessedDate => result.completed_at.format("%Y/%m/%d").to_string(),
+                PartitionKey::Tenant => format!("tenant={}", metadata_value("tenant")),
+                PartitionKey::DocumentType => format!("document_type={}", metadata_value("document_type")),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+    
+    /// Finalize all open files
+    pub async fn flush(&self) -> DocumentResult<()> {
+        let mut open_files = self.open_files.lock().await;
+        for (partition, open_file) in open_files.drain() {
+            self.finalize(&partition, open_file).await?;
+        }
+        Ok(())
+    }
+    
+    async fn open(&self, partition: &str, completed_at: DateTime<Utc>) -> DocumentResult<OpenFile> {
+        let directory = self.config.output_directory.join(partition);
+        fs::create_dir_all(&directory).await?;
+        
+        let compression = &self.config.compression;
+        let path = directory.join(format!(
+            "part-{}-{}.jsonl{}{}",
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            Uuid::new_v4().simple(),
+            if compression.enabled { ".zst" } else { "" },
+            IN_PROGRESS_SUFFIX
+        ));
+        let encoder = match compression.enabled {
+            true => Some(zstd::stream::write::Encoder::new(Vec::new(), compression.level)?),
+            false => None,
+        };
+        Ok(OpenFile {
+            file: fs::File::create(&path).await?,
+            path,
+            encoder,
+            records: 0,
+            size_bytes: 0,
+            uncompressed_bytes: 0,
+            first_completed_at: completed_at,
+            last_completed_at: completed_at,
+        })
+    }
+    
+    /// Sync and rename a file to its final name, then record it in the manifest
+    async fn finalize(&self, partition: &str, mut open_file: OpenFile) -> DocumentResult<()> {
+        if let Some(encoder) = open_file.encoder.take() {
+            let tail = encoder.finish()?;
+            open_file.file.write_all(&tail).await?;
+            open_file.size_bytes += tail.len() as u64;
+            self.stats.lock().unwrap().stored_bytes += tail.len() as u64;
+        }
+        open_file.file.flush().await?;
+        open_file.file.sync_all().await?;
+        drop(open_file.file);
+        
+        let final_path = open_file.path.with_extension("");
+        fs::rename(&open_file.path, &final_path).await?;
+        
+        let directory = self.config.output_directory.join(partition);
+        let mut manifest = PartitionManifest::load(&directory).await?;
+        manifest.partition = partition.to_string();
+        manifest.files.push(ManifestEntry {
+            file: final_path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+            records: open_file.records,
+            size_bytes: open_file.size_bytes,
+            uncompressed_bytes: open_file.uncompressed_bytes,
+            first_completed_at: open_file.first_completed_at,
+            last_completed_at: open_file.last_completed_at,
+            finalized_at: Utc::now(),
+        });
+        
+        // Replace the manifest atomically as well
+        let manifest_path = directory.join(MANIFEST_FILENAME);
+        let temporary_path = directory.join(format!("{}{}", MANIFEST_FILENAME, IN_PROGRESS_SUFFIX));
+        fs::write(&temporary_path, serde_json::to_vec_pretty(&manifest)?).await?;
+        fs::rename(&temporary_path, &manifest_path).await?;
+        
+        tracing::debug!("Finalized {} ({} records)", final_path.display(), open_file.records);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ResultSink for JsonlResultSink {
+    async fn write(&self, result: &TaskResult) -> Result<()> {
+        let partition = self.partition_for(result);
+        let mut line = serde_json::to_vec(result)?;
+        line.push(b'\n');
+        
+        let mut open_files = self.open_files.lock().await;
+        let mut open_file = match open_files.remove(&partition) {
+            Some(open_file) => open_file,
+            None => self.open(&partition, result.completed_at).await?,
+        };
+        
+        // Compressed output is written as the encoder produces it
+        let stored = match open_file.encoder.as_mut() {
+            Some(encoder) => {
+                encoder.write_all(&line)?;
+                std::mem::take(encoder.get_mut())
+            }
+            None => line.clone(),
+        };
+        open_file.file.write_all(&stored).await?;
+        open_file.records += 1;
+        open_file.size_bytes += stored.len() as u64;
+        open_file.uncompressed_bytes += line.len() as u64;
+        self.stats.lock().unwrap().record(line.len(), stored.len());
+        open_file.first_completed_at = open_file.first_completed_at.min(result.completed_at);
+        open_file.last_completed_at = open_file.last_completed_at.max(result.completed_at);
+        
+        if open_file.records >= self.config.max_records_per_file.max(1) {
+            self.finalize(&partition, open_file).await?;
+        } else {
+            open_files.insert(partition, open_file);
+        }
+        Ok(())
+    }
+}
+
+/// Keep partition values to a single, safe path component
+fn sanitize_partition_value(value: &str) -> String {
+    let sanitized: String = value.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if sanitized.is_empty() { "unknown".to_string() } else { sanitized }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::tempdir;
+    
+    fn create_test_result(tenant: &str, document_type: &str, day: u32) -> TaskResult {
+        TaskResult {
+            task_id: Uuid::new_v4(),
+            status: TaskStatus::Completed,
+            result: None,
+            error: None,
+            processing_time_ms: 5,
+            completed_at: Utc.with_ymd_and_hms(2024, 6, day, 12, 0, 0).unwrap(),
+            metadata: HashMap::from([
+                ("tenant".to_string(), serde_json::json!(tenant)),
+                ("document_type".to_string(), serde_json::json!(document_type)),
+            ]),
+        }
+    }
+    
+    fn create_test_sink(output_directory: &Path, max_records_per_file: usize) -> JsonlResultSink {
+        JsonlResultSink::new(FileSinkConfig {
+            output_directory: output_directory.to_path_buf(),
+            partition_by: vec![PartitionKey::ProcessedDate, PartitionKey::Tenant, PartitionKey::DocumentType],
+            max_records_per_file,
+            compression: CompressionConfig::default(),
+        })
+    }
+    
+    fn files_in(directory: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(directory).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+    
+    #[test]
+    fn test_partition_paths() {
+        let sink = create_test_sink(Path::new("out"), 10);
+        assert_eq!(sink.partition_for(&create_test_result("acme", "Pdf", 1)), "2024/06/01/tenant=acme/document_type=Pdf");
+        assert_eq!(sink.partition_for(&create_test_result("../etc", "Pdf", 1)), "2024/06/01/tenant=___etc/document_type=Pdf");
+        
+        let mut result = create_test_result("acme", "Pdf", 1);
+        result.metadata.clear();
+        assert_eq!(sink.partition_for(&result), "2024/06/01/tenant=unknown/document_type=unknown");
+    }
+    
+    #[tokio::test]
+    async fn test_files_are_finalized_atomically_with_manifest() {
+        let output = tempdir().unwrap();
+        let sink = create_test_sink(output.path(), 2);
+        for _ in 0..3 {
+            sink.write(&create_test_result("acme", "Text", 1)).await.unwrap();
+        }
+        sink.write(&create_test_result("globex", "Text", 2)).await.unwrap();
+        
+        // One full file is finalized; the third record is still in progress
+        let acme = output.path().join("2024/06/01/tenant=acme/document_type=Text");
+        let files = files_in(&acme);
+        assert_eq!(files.len(), 3);
+        assert!(files.iter().any(|name| name.ends_with(".jsonl")));
+        assert!(files.iter().any(|name| name.ends_with(".jsonl.inprogress")));
+        assert_eq!(PartitionManifest::load(&acme).await.unwrap().files.len(), 1);
+        
+        sink.flush().await.unwrap();
+        assert!(files_in(&acme).iter().all(|name| !name.ends_with(IN_PROGRESS_SUFFIX)));
+        
+        let manifest = PartitionManifest::load(&acme).await.unwrap();
+        assert_eq!(manifest.partition, "2024/06/01/tenant=acme/document_type=Text");
+        assert_eq!(manifest.files.iter().map(|entry| entry.records).collect::<Vec<_>>(), vec![2, 1]);
+        
+        let first = std::fs::read_to_string(acme.join(&manifest.files[0].file)).unwrap();
+        assert_eq!(first.lines().count(), 2);
+        assert_eq!(first.len() as u64, manifest.files[0].size_bytes);
+        let parsed: TaskResult = serde_json::from_str(first.lines().next().unwrap()).unwrap();
+        assert_eq!(parsed.metadata["tenant"], "acme");
+        
+        let globex = output.path().join("2024/06/02/tenant=globex/document_type=Text");
+        assert_eq!(PartitionManifest::load(&globex).await.unwrap().files[0].records, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_compressed_files_decode_to_json_lines() {
+        let output = tempdir().unwrap();
+        let sink = JsonlResultSink::new(FileSinkConfig {
+            compression: CompressionConfig::zstd(3),
+            ..create_test_sink(output.path(), 10).config
+        });
+        let mut result = create_test_result("acme", "Text", 1);
+        result.metadata.insert("text".to_string(), serde_json::json!("extracted text ".repeat(100)));
+        for _ in 0..3 {
+            sink.write(&result).await.unwrap();
+        }
+        sink.flush().await.unwrap();
+        
+        let partition = output.path().join("2024/06/01/tenant=acme/document_type=Text");
+        let entry = PartitionManifest::load(&partition).await.unwrap().files.remove(0);
+        assert!(entry.file.ends_with(".jsonl.zst"));
+        let stored = std::fs::read(partition.join(&entry.file)).unwrap();
+        assert_eq!(stored.len() as u64, entry.size_bytes);
+        
+        let lines = zstd::stream::decode_all(stored.as_slice()).unwrap();
+        assert_eq!(lines.len() as u64, entry.uncompressed_bytes);
+        assert_eq!(String::from_utf8(lines).unwrap().lines().count(), 3);
+        let stats = sink.stats();
+        assert_eq!((stats.values_written, stats.stored_bytes), (3, entry.size_bytes));
+        assert!(stats.ratio() > 5.0);
+    }
+}
@@ -0,0 +1,503 @@
+//! S3 Document Source
+//!
+//! Ingests documents dropped into an S3-compatible bucket (AWS S3, MinIO,
+//! ...) below a key prefix. The prefix is listed every poll interval, the
+//! objects after the last one read are downloaded and turned into documents
+//! that carry their bucket and key. Once every document read so far has
+//! been delivered, the key of the last object read is checkpointed as the
+//! marker the next listing starts after. Buckets list
+//! keys in lexicographic order, so keys should grow as objects arrive (e.g.
+//! by starting with a timestamp); an object written below the marker is not
+//! picked up. With a checkpoint database the marker survives restarts.
+
+use super::*;
+use crate::s3_store::{S3Config, S3DocumentStore};
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use swarm_core::{cancellable_sleep, CancellationToken, DocumentStore, Service, StoredObject, REJECTION_SUBJECT};
+
+/// Document metadata key holding the bucket a document was read from
+pub const S3_BUCKET_KEY: &str = "s3_bucket";
+
+/// Document metadata key holding the object key a document was read from
+pub const S3_KEY_KEY: &str = "s3_key";
+
+/// Document metadata key holding the entity tag of the object read
+pub const S3_ETAG_KEY: &str = "s3_etag";
+
+/// What to ingest from a bucket, and how often
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct S3SourceConfig {
+    /// Key prefix to ingest below, e.g. `inbox/`; the whole bucket when empty
+    #[serde(default)]
+    pub prefix: String,
+    
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    
+    /// Objects downloaded per poll at most
+    #[serde(default = "default_max_objects_per_poll")]
+    pub max_objects_per_poll: usize,
+    
+    /// Larger objects are rejected without being downloaded
+    #[serde(default = "default_max_object_size")]
+    pub max_object_size: usize,
+    
+    #[serde(default)]
+    pub id_strategy: DocumentIdStrategy,
+    
+    /// Database the marker is checkpointed to; kept in memory when unset
+    #[serde(default)]
+    pub checkpoint_db_path: Option<PathBuf>,
+}
+
+fn default_poll_interval_ms() -> u64 {
+    10_000
+}
+
+fn default_max_objects_per_poll() -> usize {
+    100
+}
+
+fn default_max_object_size() -> usize {
+    100 * 1024 * 1024
+}
+
+impl Default for S3SourceConfig {
+    fn default() -> Self {
+        Self {
+            prefix: String::new(),
+            poll_interval_ms: default_poll_interval_ms(),
+            max_objects_per_poll: default_max_objects_per_poll(),
+            max_object_size: default_max_object_size(),
+            id_strategy: DocumentIdStrategy::default(),
+            checkpoint_db_path: None,
+        }
+    }
+}
+
+/// Key of the last object delivered, persisted if a database is configured
+struct Checkpoint {
+    tree: Option<sled::Tree>,
+    key: String,
+    marker: Option<String>,
+    /// Key of the last object read, committed once its documents are delivered
+    read: Option<String>,
+}
+
+impl Checkpoint {
+    fn open(path: Option<&PathBuf>, key: String) -> Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self { tree: None, key, marker: None, read: None });
+        };
+        let tree = sled::open(path)?.open_tree("s3_markers")?;
+        let marker = tree.get(&key)?.map(|marker| String::from_utf8_lossy(&marker).into_owned());
+        Ok(Self { tree: Some(tree), key, marker, read: None })
+    }
+    
+    /// Where the next listing starts after
+    fn position(&self) -> Option<&str> {
+        self.read.as_deref().or(self.marker.as_deref())
+    }
+    
+    fn advance(&mut self, read: &str) {
+        self.read = Some(read.to_string());
+    }
+    
+    fn commit(&mut self) -> Result<()> {
+        let Some(read) = self.read.take() else {
+            return Ok(());
+        };
+        if let Some(tree) = &self.tree {
+            tree.insert(&self.key, read.as_bytes())?;
+            tree.flush()?;
+        }
+        self.marker = Some(read);
+        Ok(())
+    }
+}
+
+/// Reads new objects below a bucket prefix as documents
+pub struct S3DocumentSource {
+    store: Arc<dyn DocumentStore>,
+    bucket: String,
+    config: S3SourceConfig,
+    checkpoint: Checkpoint,
+    buffered: VecDeque<Document>,
+    publisher: Option<(Arc<dyn MessageBroker>, String)>,
+    stats: DocumentReaderStats,
+    shutdown: CancellationToken,
+}
+
+impl S3DocumentSource {
+    /// Ingest from the bucket of `s3`
+    pub fn new(s3: S3Config, config: S3SourceConfig) -> Result<Self> {
+        let bucket = s3.bucket.clone();
+        Self::with_store(Arc::new(S3DocumentStore::new(s3)), bucket, config)
+    }
+    
+    /// Ingest from any listable store, recording its objects as in `bucket`
+    pub fn with_store(store: Arc<dyn DocumentStore>, bucket: impl Into<String>, config: S3SourceConfig) -> Result<Self> {
+        let bucket = bucket.into();
+        let checkpoint = Checkpoint::open(config.checkpoint_db_path.as_ref(), format!("{}/{}", bucket, config.prefix))?;
+        Ok(Self {
+            store,
+            bucket,
+            config,
+            checkpoint,
+            buffered: VecDeque::new(),
+            publisher: None,
+            stats: DocumentReaderStats {
+                total_documents_read: 0,
+                documents_per_second: 0.0,
+                error_count: 0,
+                last_read_time: None,
+            },
+            shutdown: CancellationToken::new(),
+        })
+    }
+    
+    /// Publish every document found by the run loop to `subject`, as JSON
+    ///
+    /// Objects that are rejected are reported on [`REJECTION_SUBJECT`].
+    pub fn with_broker(mut self, broker: Arc<dyn MessageBroker>, subject: impl Into<String>) -> Self {
+        self.publisher = Some((broker, subject.into()));
+        self
+    }
+    
+    pub fn config(&self) -> &S3SourceConfig {
+        &self.config
+    }
+    
+    pub fn stats(&self) -> &DocumentReaderStats {
+        &self.stats
+    }
+    
+    /// Key of the last object delivered, which a restarted source lists after
+    pub fn marker(&self) -> Option<&str> {
+        self.checkpoint.marker.as_deref()
+    }
+    
+    /// Checkpoint every object read so far as delivered
+    pub fn commit(&mut self) -> Result<()> {
+        self.checkpoint.commit()
+    }
+    
+    /// Token that stops `start` from another task
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+    
+    /// Read the objects added after the last one read
+    ///
+    /// An object that fails to download ends the poll without reading past
+    /// it, so it is tried again by the next poll. The marker only moves on
+    /// [`commit`](Self::commit), so objects read but never delivered are
+    /// read again after a restart.
+    pub async fn poll(&mut self) -> Result<Vec<Document>> {
+        let objects = self.store.list(&self.config.prefix, self.checkpoint.position(), self.config.max_objects_per_poll).await?;
+        let mut documents = Vec::new();
+        for object in objects {
+            if object.path.ends_with('/') {
+                // Folder placeholders created by consoles
+                self.checkpoint.advance(&object.path);
+                continue;
+            }
+            match self.read_object(&object).await {
+                Ok(document) => {
+                    tracing::info!("Read document s3://{}/{}", self.bucket, object.path);
+                    self.stats.total_documents_read += 1;
+                    self.stats.last_read_time = Some(Utc::now());
+                    documents.push(document);
+                }
+                Err(e) => {
+                    self.stats.error_count += 1;
+                    let Some(rejection) = e.downcast_ref::<DocumentError>().and_then(DocumentError::rejection) else {
+                        tracing::error!("Failed to read s3://{}/{}: {}", self.bucket, object.path, e);
+                        break;
+                    };
+                    tracing::warn!("Rejected s3://{}/{}: {}", self.bucket, object.path, e);
+                    self.publish_json(REJECTION_SUBJECT, rejection).await;
+                }
+            }
+            self.checkpoint.advance(&object.path);
+        }
+        Ok(documents)
+    }
+    
+    async fn read_object(&self, object: &StoredObject) -> Result<Document> {
+        let filename = object.path.rsplit('/').next().unwrap_or(&object.path).to_string();
+        if object.size_bytes > self.config.max_object_size as u64 {
+            let rejection = DocumentRejection::file_too_large(&filename, object.size_bytes as usize, self.config.max_object_size);
+            return Err(DocumentError::Rejected(Box::new(rejection)).into());
+        }
+        
+        let bytes = self.store.get(&object.path).await?;
+        let size_bytes = bytes.len();
+        let detection = sniff_document_type(Path::new(&object.path), &bytes[..size_bytes.min(SNIFF_LENGTH)]);
+        let content = match detection.document_type {
+            DocumentType::Text | DocumentType::Html | DocumentType::Markdown => {
+                DocumentContent::Text(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            _ => DocumentContent::Binary(bytes),
+        };
+        let hash = format!("sha256:{}", swarm_core::content_hash(&content));
+        let mut builder = swarm_core::DocumentBuilder::new(filename, content)
+            .document_type(detection.document_type)
+            .source(format!("s3://{}/{}", self.bucket, object.path))
+            .id_strategy(self.config.id_strategy)
+            .metadata(S3_BUCKET_KEY, serde_json::json!(self.bucket))
+            .metadata(S3_KEY_KEY, serde_json::json!(object.path))
+            .metadata("file_size", serde_json::json!(size_bytes))
+            .metadata("mime_type", serde_json::json!(detection.mime_type))
+            .metadata(CONTENT_HASH_KEY, serde_json::json!(hash));
+        if let Some(etag) = &object.etag {
+            builder = builder.metadata(S3_ETAG_KEY, serde_json::json!(etag));
+        }
+        if let Some(modified) = object.last_modified {
+            builder = builder.metadata("modified_time", serde_json::json!(modified.to_rfc3339()));
+        }
+        Ok(builder.build())
+    }
+    
+    async fn publish_json(&mut self, subject: &str, payload: &impl serde::Serialize) {
+        let Some((broker, _)) = &self.publisher else {
+            return;
+        };
+        let published = match serde_json::to_vec(payload) {
+            Ok(payload) => broker.publish(subject, &payload).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = published {
+            tracing::error!("Failed to publish to {}: {}", subject, e);
+            self.stats.error_count += 1;
+        }
+    }
+    
+    /// Publish the buffered documents in order, keeping the first that fails
+    /// and everything after it buffered; true once the buffer is empty
+    async fn publish_buffered(&mut self) -> bool {
+        let Some((broker, subject)) = self.publisher.clone() else {
+            self.buffered.clear();
+            return true;
+        };
+        while let Some(document) = self.buffered.front() {
+            let published = match serde_json::to_vec(document) {
+                Ok(payload) => broker.publish(&subject, &payload).await,
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = published {
+                tracing::error!("Failed to publish {} to {}, {} document(s) kept for the next poll: {}",
+                    document.filename, subject, self.buffered.len(), e);
+                self.stats.error_count += 1;
+                return false;
+            }
+            self.buffered.pop_front();
+        }
+        true
+    }
+}
+
+#[async_trait]
+impl DocumentReader for S3DocumentSource {
+    async fn start(&mut self) -> Result<()> {
+        if self.shutdown.is_cancelled() {
+            self.shutdown = CancellationToken::new();
+        }
+        let shutdown = self.shutdown.clone();
+        Service::run(self, shutdown).await
+    }
+    
+    async fn stop(&mut self) -> Result<()> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+    
+    async fn get_next_document(&mut self) -> Result<Option<Document>> {
+        if self.buffered.is_empty() {
+            let documents = self.poll().await?;
+            self.buffered.extend(documents);
+        }
+        let document = self.buffered.pop_front();
+        if self.buffered.is_empty() {
+            self.checkpoint.commit()?;
+        }
+        Ok(document)
+    }
+    
+    async fn get_stats(&self) -> DocumentReaderStats {
+        self.stats.clone()
+    }
+}
+
+#[async_trait]
+impl Service for S3DocumentSource {
+    fn name(&self) -> &str {
+        "s3-document-source"
+    }
+    
+    /// Poll every `poll_interval_ms` until cancelled, publishing what was read
+    ///
+    /// The marker is committed only after everything read was published.
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        tracing::info!("Ingesting s3://{}/{} every {}ms", self.bucket, self.config.prefix, self.config.poll_interval_ms);
+        while !cancel.is_cancelled() {
+            // While the broker is down only the documents already read are
+            // retried, so the buffer does not grow with every poll
+            let polled = if self.buffered.is_empty() { self.poll().await } else { Ok(Vec::new()) };
+            match polled {
+                Ok(documents) => {
+                    self.buffered.extend(documents);
+                    if self.publish_buffered().await {
+                        if let Err(e) = self.checkpoint.commit() {
+                            tracing::error!("Failed to checkpoint s3://{}/{}: {}", self.bucket, self.config.prefix, e);
+                            self.stats.error_count += 1;
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to list s3://{}/{}: {}", self.bucket, self.config.prefix, e);
+                    self.stats.error_count += 1;
+                }
+            }
+            cancellable_sleep(&cancel, Duration::from_millis(self.config.poll_interval_ms)).await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use swarm_core::{Message, MemoryBroker, MemoryDocumentStore, MessageBrokerStats, MessageSubscription};
+    use tempfile::tempdir;
+    
+    /// Memory broker whose publishes fail while it is down
+    #[derive(Default)]
+    struct OutageBroker {
+        inner: MemoryBroker,
+        down: AtomicBool,
+    }
+    
+    #[async_trait]
+    impl MessageBroker for OutageBroker {
+        async fn publish(&self, subject: &str, message: &[u8]) -> Result<()> {
+            if self.down.load(Ordering::SeqCst) {
+                anyhow::bail!("broker unavailable");
+            }
+            self.inner.publish(subject, message).await
+        }
+        
+        async fn subscribe(&self, subject: &str) -> Result<Box<dyn MessageSubscription>> {
+            self.inner.subscribe(subject).await
+        }
+        
+        async fn request(&self, subject: &str, message: &[u8], timeout: Duration) -> Result<Message> {
+            self.inner.request(subject, message, timeout).await
+        }
+        
+        async fn get_stats(&self) -> MessageBrokerStats {
+            self.inner.get_stats().await
+        }
+    }
+    
+    async fn run_briefly(source: &mut S3DocumentSource) {
+        let cancel = CancellationToken::new();
+        let stop = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stop.cancel();
+        });
+        source.run(cancel).await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_new_objects_are_read_once_across_restarts() {
+        let dir = tempdir().unwrap();
+        let store = Arc::new(MemoryDocumentStore::new("s3"));
+        store.put("inbox/2024-03-01-notes.txt", b"meeting notes".to_vec()).await.unwrap();
+        store.put("inbox/2024-03-02-page.html", b"<html><body>hi</body></html>".to_vec()).await.unwrap();
+        store.put("archive/old.txt", b"not ingested".to_vec()).await.unwrap();
+        let config = S3SourceConfig {
+            prefix: "inbox/".to_string(),
+            checkpoint_db_path: Some(dir.path().join("checkpoint")),
+            ..Default::default()
+        };
+        
+        let mut source = S3DocumentSource::with_store(store.clone(), "documents", config.clone()).unwrap();
+        let documents = source.poll().await.unwrap();
+        assert_eq!(documents.iter().map(|document| document.filename.as_str()).collect::<Vec<_>>(), vec!["2024-03-01-notes.txt", "2024-03-02-page.html"]);
+        assert_eq!(documents[0].metadata[S3_BUCKET_KEY], "documents");
+        assert_eq!(documents[0].metadata[S3_KEY_KEY], "inbox/2024-03-01-notes.txt");
+        assert_eq!(documents[0].content, DocumentContent::Text("meeting notes".to_string()));
+        assert_eq!(documents[1].document_type, DocumentType::Html);
+        assert!(source.poll().await.unwrap().is_empty());
+        assert_eq!(source.marker(), None);
+        source.commit().unwrap();
+        
+        store.put("inbox/2024-03-03-more.txt", b"more".to_vec()).await.unwrap();
+        drop(source);
+        let mut source = S3DocumentSource::with_store(store, "documents", config).unwrap();
+        assert_eq!(source.marker(), Some("inbox/2024-03-02-page.html"));
+        assert_eq!(source.poll().await.unwrap()[0].filename, "2024-03-03-more.txt");
+        assert!(source.poll().await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_oversized_objects_are_rejected_and_skipped() {
+        let store = Arc::new(MemoryDocumentStore::new("s3"));
+        store.put("big.bin", vec![0; 64]).await.unwrap();
+        store.put("small.txt", b"ok".to_vec()).await.unwrap();
+        let broker = Arc::new(MemoryBroker::new());
+        let mut rejections = broker.subscribe(REJECTION_SUBJECT).await.unwrap();
+        let config = S3SourceConfig { max_object_size: 16, ..Default::default() };
+        let mut source = S3DocumentSource::with_store(store, "documents", config).unwrap().with_broker(broker, "swarm.documents.incoming");
+        
+        let documents = source.poll().await.unwrap();
+        assert_eq!(documents.len(), 1);
+        source.commit().unwrap();
+        assert_eq!(source.marker(), Some("small.txt"));
+        assert_eq!(source.stats().error_count, 1);
+        let rejection: DocumentRejection = serde_json::from_slice(&rejections.next_message().unwrap().unwrap().payload).unwrap();
+        assert_eq!(rejection.filename, "big.bin");
+    }
+    
+    #[tokio::test]
+    async fn test_marker_waits_for_documents_to_be_published() {
+        let dir = tempdir().unwrap();
+        let store = Arc::new(MemoryDocumentStore::new("s3"));
+        store.put("a.txt", b"first".to_vec()).await.unwrap();
+        store.put("b.txt", b"second".to_vec()).await.unwrap();
+        let broker = Arc::new(OutageBroker::default());
+        broker.down.store(true, Ordering::SeqCst);
+        let mut incoming = broker.subscribe("swarm.documents.incoming").await.unwrap();
+        let config = S3SourceConfig {
+            poll_interval_ms: 10,
+            checkpoint_db_path: Some(dir.path().join("checkpoint")),
+            ..Default::default()
+        };
+        let mut source = S3DocumentSource::with_store(store.clone(), "documents", config).unwrap()
+            .with_broker(broker.clone(), "swarm.documents.incoming");
+        
+        run_briefly(&mut source).await;
+        store.put("c.txt", b"third".to_vec()).await.unwrap();
+        run_briefly(&mut source).await;
+        // Nothing more is read while the broker is down
+        assert_eq!(source.marker(), None);
+        assert_eq!(source.buffered.len(), 2);
+        assert!(incoming.next_message().unwrap().is_none());
+        
+        broker.down.store(false, Ordering::SeqCst);
+        run_briefly(&mut source).await;
+        assert_eq!(source.marker(), Some("c.txt"));
+        let mut published = Vec::new();
+        while let Some(message) = incoming.next_message().unwrap() {
+            published.push(serde_json::from_slice::<Document>(&message.payload).unwrap().filename);
+        }
+        assert_eq!(published, vec!["a.txt", "b.txt", "c.txt"]);
+    }
+}
@@ -0,0 +1,260 @@
+//! S3 Document Store
+//!
+//! `DocumentStore` backed by an S3-compatible object store (AWS S3, MinIO,
+//! Ceph, ...), so documents checked in by one node can be checked out by any
+//! other. Requests go through the AWS SDK with path-style URLs, so the
+//! endpoint may be any S3-compatible server, including one served under a
+//! path prefix. Objects are listed with `ListObjectsV2`.
+
+use super::*;
+use crate::credentials::Secret;
+use async_trait::async_trait;
+use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region, RequestChecksumCalculation};
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::DateTime;
+use swarm_core::{DocumentStore, StoredObject};
+
+/// Connection settings for an S3-compatible bucket
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct S3Config {
+    /// Endpoint URL, e.g. `https://s3.eu-north-1.amazonaws.com`, `http://minio:9000` or `https://proxy/s3`
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: Secret,
+    
+    /// Session token of temporary credentials
+    #[serde(default)]
+    pub session_token: Option<Secret>,
+    
+    /// Storage ID recorded in references to this bucket
+    #[serde(default = "default_storage_id")]
+    pub storage_id: String,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_storage_id() -> String {
+    "s3".to_string()
+}
+
+/// Document store in an S3 bucket
+#[derive(Debug, Clone)]
+pub struct S3DocumentStore {
+    config: S3Config,
+    client: aws_sdk_s3::Client,
+}
+
+impl S3DocumentStore {
+    pub fn new(config: S3Config) -> Self {
+        let credentials = Credentials::new(
+            &config.access_key_id,
+            config.secret_access_key.expose(),
+            config.session_token.as_ref().map(|token| token.expose().to_string()),
+            None,
+            "swarm",
+        );
+        let s3 = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .endpoint_url(endpoint_url(&config.endpoint))
+            .region(Region::new(config.region.clone()))
+            .credentials_provider(credentials)
+            .force_path_style(true)
+            // Checksums only where S3 requires them, which every compatible server accepts
+            .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+            .build();
+        Self { config, client: aws_sdk_s3::Client::from_conf(s3) }
+    }
+    
+    pub fn bucket(&self) -> &str {
+        &self.config.bucket
+    }
+    
+    /// An SDK error with its cause, which the SDK's own message leaves out
+    fn error(&self, path: &str, error: impl std::error::Error) -> anyhow::Error {
+        anyhow::anyhow!("{} {}: {}", self.config.storage_id, path, DisplayErrorContext(error))
+    }
+}
+
+#[async_trait]
+impl DocumentStore for S3DocumentStore {
+    fn storage_id(&self) -> &str {
+        &self.config.storage_id
+    }
+    
+    async fn put(&self, path: &str, bytes: Vec<u8>) -> Result<()> {
+        self.client.put_object().bucket(&self.config.bucket).key(path).body(ByteStream::from(bytes)).send().await
+            .map_err(|e| self.error(path, e))?;
+        Ok(())
+    }
+    
+    async fn get(&self, path: &str) -> Result<Vec<u8>> {
+        let object = self.client.get_object().bucket(&self.config.bucket).key(path).send().await
+            .map_err(|e| self.error(path, e))?;
+        let bytes = object.body.collect().await.map_err(|e| self.error(path, e))?;
+        Ok(bytes.into_bytes().to_vec())
+    }
+    
+    async fn delete(&self, path: &str) -> Result<()> {
+        self.client.delete_object().bucket(&self.config.bucket).key(path).send().await
+            .map_err(|e| self.error(path, e))?;
+        Ok(())
+    }
+    
+    async fn list(&self, prefix: &str, start_after: Option<&str>, limit: usize) -> Result<Vec<StoredObject>> {
+        let mut objects = Vec::new();
+        let mut continuation: Option<String> = None;
+        while objects.len() < limit {
+            let max_keys = (limit - objects.len()).min(1000) as i32;
+            let mut request = self.client.list_objects_v2().bucket(&self.config.bucket).prefix(prefix).max_keys(max_keys);
+            request = match (continuation.take(), start_after) {
+                (Some(token), _) => request.continuation_token(token),
+                (None, Some(after)) => request.start_after(after),
+                (None, None) => request,
+            };
+            let page = request.send().await.map_err(|e| self.error(prefix, e))?;
+            objects.extend(page.contents().iter().filter_map(stored_object));
+            match page.next_continuation_token() {
+                Some(token) if page.is_truncated() == Some(true) => continuation = Some(token.to_string()),
+                _ => break,
+            }
+        }
+        objects.truncate(limit);
+        Ok(objects)
+    }
+}
+
+/// The endpoint with its path ending in `/`, which the SDK needs to put the
+/// bucket under the path instead of appending it to its last segment
+fn endpoint_url(endpoint: &str) -> String {
+    match reqwest::Url::parse(endpoint) {
+        Ok(url) if url.path() != "/" && !url.path().ends_with('/') => format!("{}/", endpoint),
+        _ => endpoint.to_string(),
+    }
+}
+
+/// A listed object, unless S3 left out its key
+fn stored_object(object: &aws_sdk_s3::types::Object) -> Option<StoredObject> {
+    Some(StoredObject {
+        path: object.key()?.to_string(),
+        size_bytes: object.size().unwrap_or(0).max(0) as u64,
+        last_modified: object.last_modified().and_then(|modified| DateTime::from_timestamp(modified.secs(), modified.subsec_nanos())),
+        etag: object.e_tag().map(|etag| etag.trim_matches('"').to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    
+    const LISTING: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+            <Name>documents</Name>
+            <Prefix>inbox/</Prefix>
+            <IsTruncated>false</IsTruncated>
+            <Contents>
+                <Key>inbox/report.pdf</Key>
+                <LastModified>2024-03-01T12:00:00.000Z</LastModified>
+                <ETag>&quot;9b2cf535f27731c974343645a3985328&quot;</ETag>
+                <Size>4096</Size>
+            </Contents>
+            <Contents><Key>inbox/notes.txt</Key><Size>12</Size></Contents>
+        </ListBucketResult>"#;
+    
+    /// Read one request up to the end of its body
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            let read = stream.read(&mut buffer).await.unwrap_or(0);
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request).into_owned();
+            let Some(header_end) = text.find("\r\n\r\n") else {
+                if read == 0 { return text } else { continue }
+            };
+            let length = text.lines()
+                .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|value| value.trim().parse().unwrap_or(0)))
+                .unwrap_or(0);
+            if read == 0 || request.len() >= header_end + 4 + length {
+                return text;
+            }
+        }
+    }
+    
+    /// S3 behind a path prefix, recording the request line and the
+    /// authorization header of every request
+    async fn serve_bucket() -> (String, Arc<Mutex<Vec<(String, String)>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let request = read_request(&mut stream).await;
+                let line = request.lines().next().unwrap_or_default().to_string();
+                let authorization = request.lines()
+                    .find_map(|header| header.to_ascii_lowercase().starts_with("authorization:").then(|| header[14..].trim().to_string()))
+                    .unwrap_or_default();
+                recorded.lock().unwrap().push((line.clone(), authorization));
+                let body = if line.contains("list-type=2") { LISTING } else if line.starts_with("GET") { "meeting notes" } else { "" };
+                let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (format!("http://{}/s3", address), requests)
+    }
+    
+    #[tokio::test]
+    async fn test_requests_go_to_the_endpoint_path() {
+        let (endpoint, requests) = serve_bucket().await;
+        let config: S3Config = serde_json::from_value(serde_json::json!({
+            "endpoint": endpoint,
+            "bucket": "documents",
+            "access_key_id": "AKIDEXAMPLE",
+            "secret_access_key": "secret",
+            "session_token": "token",
+        })).unwrap();
+        assert_eq!((config.region.as_str(), config.storage_id.as_str()), ("us-east-1", "s3"));
+        assert!(!format!("{:?}", config).contains("secret\""));
+        assert_eq!(endpoint_url("http://minio:9000"), "http://minio:9000");
+        assert_eq!(endpoint_url("https://proxy/s3"), "https://proxy/s3/");
+        let store = S3DocumentStore::new(config);
+        
+        store.put("inbox/a b.txt", b"meeting notes".to_vec()).await.unwrap();
+        assert_eq!(store.get("inbox/a b.txt").await.unwrap(), b"meeting notes");
+        let requests = requests.lock().unwrap().clone();
+        assert!(requests[0].0.starts_with("PUT /s3/documents/inbox/a%20b.txt"), "{:?}", requests[0]);
+        assert!(requests[1].0.starts_with("GET /s3/documents/inbox/a%20b.txt"), "{:?}", requests[1]);
+        for (_, authorization) in &requests {
+            assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"), "{}", authorization);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_listings_are_read() {
+        let (endpoint, requests) = serve_bucket().await;
+        let store = S3DocumentStore::new(serde_json::from_value(serde_json::json!({
+            "endpoint": format!("{}/", endpoint),
+            "bucket": "documents",
+            "access_key_id": "AKIDEXAMPLE",
+            "secret_access_key": "secret",
+        })).unwrap());
+        
+        let objects = store.list("inbox/", Some("inbox/a.txt"), 10).await.unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].path, "inbox/report.pdf");
+        assert_eq!(objects[0].size_bytes, 4096);
+        assert_eq!(objects[0].etag.as_deref(), Some("9b2cf535f27731c974343645a3985328"));
+        assert_eq!(objects[0].last_modified.unwrap().to_rfc3339(), "2024-03-01T12:00:00+00:00");
+        let line = &requests.lock().unwrap()[0].0;
+        assert!(line.starts_with("GET /s3/documents"), "{}", line);
+        assert!(line.contains("start-after=inbox%2Fa.txt"), "{}", line);
+    }
+}
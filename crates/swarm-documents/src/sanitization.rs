@@ -0,0 +1,524 @@
+//! HTML Sanitization
+//!
+//! Extracted HTML is shown to users as previews, so it must not carry
+//! scripts, event handlers or `javascript:` links into a UI. The sanitizer
+//! keeps an allow list of tags and attributes (the profile), drops
+//! disallowed tags while keeping their text, removes `script`, `style` and
+//! similar elements together with their content, and checks URL schemes of
+//! links and images. It produces both safe preview markup and clean text.
+
+use super::*;
+
+/// Elements whose content is dropped along with them
+pub const DEFAULT_STRIPPED_ELEMENTS: &[&str] = &[
+    "script", "style", "noscript", "template", "iframe", "frame", "frameset", "object", "embed", "applet",
+    "svg", "math", "head", "title", "textarea", "select", "button",
+];
+
+/// Elements whose content is raw text, ending only at the matching end tag
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title", "xmp", "noscript", "iframe", "noembed"];
+
+/// Elements without an end tag
+const VOID_ELEMENTS: &[&str] = &["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr"];
+
+/// Elements that separate words in the clean text
+pub(crate) const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "figcaption", "figure", "footer",
+    "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "main", "nav", "ol", "p", "pre", "section", "table",
+    "td", "th", "tr", "ul",
+];
+
+/// Tags and attributes kept in preview markup
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AllowList {
+    pub tags: Vec<String>,
+    
+    /// Allowed attributes by tag; the `*` entry applies to every tag
+    pub attributes: HashMap<String, Vec<String>>,
+    
+    /// Schemes allowed in `href` and `src`; relative URLs are always allowed
+    pub url_schemes: Vec<String>,
+}
+
+impl AllowList {
+    fn new(tags: &[&str], attributes: &[(&str, &[&str])], url_schemes: &[&str]) -> Self {
+        Self {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            attributes: attributes.iter()
+                .map(|(tag, names)| (tag.to_string(), names.iter().map(|name| name.to_string()).collect()))
+                .collect(),
+            url_schemes: url_schemes.iter().map(|scheme| scheme.to_string()).collect(),
+        }
+    }
+    
+    pub fn allows_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|allowed| allowed == tag)
+    }
+    
+    pub fn allows_attribute(&self, tag: &str, attribute: &str) -> bool {
+        // Event handlers and inline styles are never safe to pass through
+        if attribute.starts_with("on") || attribute == "style" {
+            return false;
+        }
+        [tag, "*"].iter().any(|key| self.attributes.get(*key).is_some_and(|names| names.iter().any(|name| name == attribute)))
+    }
+    
+    /// Whether a URL is relative or uses an allowed scheme
+    pub fn allows_url(&self, url: &str) -> bool {
+        // Browsers ignore control characters and whitespace inside schemes
+        let normalized: String = url.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect();
+        match normalized.split_once(':') {
+            Some((scheme, _)) if !scheme.contains(['/', '?', '#']) => {
+                self.url_schemes.iter().any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Predefined or custom allow lists
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SanitizationProfile {
+    /// Text formatting only, no links or images
+    Strict,
+    /// Formatting, headings, lists, tables and http(s)/mailto links
+    #[default]
+    Basic,
+    /// Basic plus images, spans, divs and `class` attributes
+    Relaxed,
+    Custom(AllowList),
+}
+
+impl SanitizationProfile {
+    pub fn allow_list(&self) -> AllowList {
+        const STRICT_TAGS: &[&str] = &["b", "strong", "i", "em", "u", "s", "sub", "sup", "p", "br", "ul", "ol", "li", "blockquote", "code", "pre"];
+        const BASIC_TAGS: &[&str] = &[
+            "a", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "table", "thead", "tbody", "tfoot", "tr", "th", "td",
+            "caption", "dl", "dt", "dd", "abbr", "cite", "q", "small", "mark", "del", "ins",
+        ];
+        const RELAXED_TAGS: &[&str] = &["img", "span", "div", "figure", "figcaption", "section", "article", "header", "footer"];
+        
+        let basic_attributes: &[(&str, &[&str])] = &[
+            ("a", &["href", "title"]),
+            ("abbr", &["title"]),
+            ("td", &["colspan", "rowspan"]),
+            ("th", &["colspan", "rowspan", "scope"]),
+        ];
+        match self {
+            SanitizationProfile::Strict => AllowList::new(STRICT_TAGS, &[], &[]),
+            SanitizationProfile::Basic => AllowList::new(&[STRICT_TAGS, BASIC_TAGS].concat(), basic_attributes, &["http", "https", "mailto"]),
+            SanitizationProfile::Relaxed => {
+                let attributes = [basic_attributes, &[("img", &["src", "alt", "title", "width", "height"][..]), ("*", &["class"][..])]].concat();
+                AllowList::new(&[STRICT_TAGS, BASIC_TAGS, RELAXED_TAGS].concat(), &attributes, &["http", "https", "mailto"])
+            }
+            SanitizationProfile::Custom(allow_list) => allow_list.clone(),
+        }
+    }
+}
+
+/// HTML sanitization configuration
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SanitizationConfig {
+    /// Sanitize HTML documents; when disabled, tags are only stripped
+    pub enabled: bool,
+    
+    pub profile: SanitizationProfile,
+    
+    /// Elements removed together with their content
+    pub stripped_elements: Vec<String>,
+    
+    /// `rel` set on kept links, keeping previews from controlling the opener
+    pub link_rel: Option<String>,
+    
+    /// Characters of text kept in the preview markup
+    pub max_preview_chars: usize,
+}
+
+impl Default for SanitizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            profile: SanitizationProfile::default(),
+            stripped_elements: DEFAULT_STRIPPED_ELEMENTS.iter().map(|element| element.to_string()).collect(),
+            link_rel: Some("noopener noreferrer".to_string()),
+            max_preview_chars: 2000,
+        }
+    }
+}
+
+/// Output of sanitizing one HTML document
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SanitizedHtml {
+    /// Safe preview markup, truncated to `max_preview_chars` characters of text
+    pub html: String,
+    
+    /// Text of the document without markup or stripped elements
+    pub text: String,
+    
+    /// Elements dropped, with or without their content
+    pub removed_elements: usize,
+    
+    pub removed_attributes: usize,
+    
+    /// Whether the preview was cut short
+    pub truncated: bool,
+}
+
+/// Sanitizes HTML according to a profile
+#[derive(Debug, Clone)]
+pub struct HtmlSanitizer {
+    config: SanitizationConfig,
+    allow_list: AllowList,
+}
+
+impl Default for HtmlSanitizer {
+    fn default() -> Self {
+        Self::new(SanitizationConfig::default())
+    }
+}
+
+impl HtmlSanitizer {
+    pub fn new(config: SanitizationConfig) -> Self {
+        let allow_list = config.profile.allow_list();
+        Self { config, allow_list }
+    }
+    
+    pub fn config(&self) -> &SanitizationConfig {
+        &self.config
+    }
+    
+    pub fn sanitize(&self, html: &str) -> SanitizedHtml {
+        let mut output = SanitizedHtml::default();
+        let mut text = String::new();
+        let mut open: Vec<String> = Vec::new();
+        // Stripped element being skipped, with the nesting depth of its name
+        let mut skipping: Option<(String, usize)> = None;
+        let mut preview_chars = 0;
+        
+        for token in tokenize(html) {
+            if let Some((name, depth)) = skipping.as_mut() {
+                match &token {
+                    HtmlToken::StartTag { name: tag, self_closing: false, .. } if tag == name && !VOID_ELEMENTS.contains(&tag.as_str()) => *depth += 1,
+                    HtmlToken::EndTag { name: tag } if tag == name => *depth -= 1,
+                    _ => {}
+                }
+                if *depth == 0 {
+                    skipping = None;
+                }
+                continue;
+            }
+            
+            match token {
+                HtmlToken::Text(raw) => {
+                    let decoded = decode_entities(raw);
+                    text.push_str(&decoded);
+                    if !output.truncated {
+                        let remaining = self.config.max_preview_chars - preview_chars;
+                        let kept: String = decoded.chars().take(remaining).collect();
+                        preview_chars += kept.chars().count();
+                        output.truncated = kept.len() < decoded.len();
+                        output.html.push_str(&escape_html(&kept));
+                    }
+                }
+                HtmlToken::StartTag { name, attributes, self_closing } => {
+                    if BLOCK_ELEMENTS.contains(&name.as_str()) {
+                        text.push(' ');
+                    }
+                    let is_void = VOID_ELEMENTS.contains(&name.as_str());
+                    if self.config.stripped_elements.contains(&name) {
+                        output.removed_elements += 1;
+                        if !is_void && !self_closing {
+                            skipping = Some((name, 1));
+                        }
+                        continue;
+                    }
+                    if !self.allow_list.allows_tag(&name) {
+                        output.removed_elements += 1;
+                        continue;
+                    }
+                    if output.truncated {
+                        continue;
+                    }
+                    
+                    output.html.push('<');
+                    output.html.push_str(&name);
+                    for (attribute, value) in attributes {
+                        let url_attribute = attribute == "href" || attribute == "src";
+                        if !self.allow_list.allows_attribute(&name, &attribute) || (url_attribute && !self.allow_list.allows_url(&value)) {
+                            output.removed_attributes += 1;
+                            continue;
+                        }
+                        output.html.push_str(&format!(" {}=\"{}\"", attribute, escape_html(&value)));
+                    }
+                    if let (Some(rel), "a") = (&self.config.link_rel, name.as_str()) {
+                        output.html.push_str(&format!(" rel=\"{}\"", escape_html(rel)));
+                    }
+                    output.html.push('>');
+                    if !is_void {
+                        open.push(name);
+                    }
+                }
+                HtmlToken::EndTag { name } => {
+                    if BLOCK_ELEMENTS.contains(&name.as_str()) {
+                        text.push(' ');
+                    }
+                    // Close any elements left open inside this one; ignore stray end tags
+                    if let Some(position) = open.iter().rposition(|tag| *tag == name) {
+                        for tag in open.drain(position..).rev() {
+                            output.html.push_str(&format!("</{}>", tag));
+                        }
+                    }
+                }
+            }
+        }
+        
+        for tag in open.drain(..).rev() {
+            output.html.push_str(&format!("</{}>", tag));
+        }
+        output.text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        output
+    }
+}
+
+/// One token of an HTML document; names are lowercase
+#[derive(Debug, Clone, PartialEq)]
+enum HtmlToken<'a> {
+    StartTag { name: String, attributes: Vec<(String, String)>, self_closing: bool },
+    EndTag { name: String },
+    Text(&'a str),
+}
+
+/// Lenient HTML tokenizer; comments, doctypes and processing instructions
+/// are dropped, raw text elements are returned as a single text token
+fn tokenize(html: &str) -> Vec<HtmlToken<'_>> {
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    while position < html.len() {
+        let rest = &html[position..];
+        let Some(start) = rest.find('<') else {
+            tokens.push(HtmlToken::Text(rest));
+            break;
+        };
+        if start > 0 {
+            tokens.push(HtmlToken::Text(&rest[..start]));
+        }
+        let tag = &rest[start..];
+        position += start;
+        
+        let next = tag[1..].chars().next();
+        if tag.starts_with("<!--") {
+            position += tag.find("-->").map_or(tag.len(), |end| end + 3);
+        } else if matches!(next, Some('!' | '?')) {
+            position += tag.find('>').map_or(tag.len(), |end| end + 1);
+        } else if tag.starts_with("</") && tag[2..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let name_end = tag[2..].find(|c: char| c.is_whitespace() || c == '/' || c == '>').map_or(tag.len(), |end| end + 2);
+            tokens.push(HtmlToken::EndTag { name: tag[2..name_end].to_ascii_lowercase() });
+            position += tag.find('>').map_or(tag.len(), |end| end + 1);
+        } else if next.is_some_and(|c| c.is_ascii_alphabetic()) {
+            let (token, length) = parse_start_tag(tag);
+            position += length;
+            if let HtmlToken::StartTag { name, self_closing: false, .. } = &token {
+                if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+                    // Raw text runs to the matching end tag, which is tokenized normally
+                    let content = &html[position..];
+                    let end = find_ignore_ascii_case(content, &format!("</{}", name)).unwrap_or(content.len());
+                    tokens.push(token);
+                    if end > 0 {
+                        tokens.push(HtmlToken::Text(&content[..end]));
+                    }
+                    position += end;
+                    continue;
+                }
+            }
+            tokens.push(token);
+        } else {
+            tokens.push(HtmlToken::Text("<"));
+            position += 1;
+        }
+    }
+    tokens
+}
+
+/// Parse a start tag at the beginning of `tag`, returning it and its length
+fn parse_start_tag(tag: &str) -> (HtmlToken<'static>, usize) {
+    let bytes = tag.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'/' && bytes[i] != b'>' {
+        i += 1;
+    }
+    let name = tag[1..i].to_ascii_lowercase();
+    let mut attributes: Vec<(String, String)> = Vec::new();
+    let mut self_closing = false;
+    
+    while i < bytes.len() {
+        match bytes[i] {
+            b'>' => {
+                i += 1;
+                break;
+            }
+            b'/' => {
+                self_closing = bytes.get(i + 1) == Some(&b'>');
+                i += 1;
+            }
+            byte if byte.is_ascii_whitespace() => i += 1,
+            _ => {
+                let name_start = i;
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !matches!(bytes[i], b'=' | b'>' | b'/') {
+                    i += 1;
+                }
+                let attribute = tag[name_start..i].to_ascii_lowercase();
+                while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                let mut value = String::new();
+                if bytes.get(i) == Some(&b'=') {
+                    i += 1;
+                    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                        i += 1;
+                    }
+                    let value_start = i;
+                    match bytes.get(i) {
+                        Some(&quote @ (b'"' | b'\'')) => {
+                            let end = tag[i + 1..].find(quote as char).map_or(tag.len(), |end| end + i + 1);
+                            value = decode_entities(&tag[i + 1..end]);
+                            i = (end + 1).min(tag.len());
+                        }
+                        _ => {
+                            while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                                i += 1;
+                            }
+                            value = decode_entities(&tag[value_start..i]);
+                        }
+                    }
+                }
+                // The first occurrence of a repeated attribute wins, as in browsers
+                if !attributes.iter().any(|(existing, _)| *existing == attribute) {
+                    attributes.push((attribute, value));
+                }
+            }
+        }
+    }
+    (HtmlToken::StartTag { name, attributes, self_closing }, i)
+}
+
+fn find_ignore_ascii_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Decode numeric entities and the named entities common in documents
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..].find(';').filter(|end| *end <= 10).map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "copy" => Some('©'),
+            "reg" => Some('®'),
+            "trade" => Some('™'),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "euro" => Some('€'),
+            _ => entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').map(|decimal| decimal.parse::<u32>().ok()))
+                .flatten()
+                .and_then(char::from_u32),
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Escape text for use in HTML content and quoted attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const UNSAFE_HTML: &str = r#"<html><head><title>T</title><style>p { color: red }</style></head>
+<body><h1 onclick="steal()">Quarterly &amp; annual</h1>
+<script>document.write("<p>injected</p>")</script>
+<p class="lead" style="x">Read the <a href="javascript:alert(1)">report</a> or <a href="https://example.com/r?a=1&amp;b=2" target="_blank">online</a>.
+<img src=x onerror=alert(1)><iframe src="https://evil.example"><p>frame</p></iframe><custom-tag>kept text</custom-tag>
+<SCRIPT type="text/javascript">alert('upper')</SCRIPT><!-- <script>alert(2)</script> --></p></body></html>"#;
+    
+    #[test]
+    fn test_scripts_handlers_and_unsafe_urls_are_removed() {
+        let sanitized = HtmlSanitizer::default().sanitize(UNSAFE_HTML);
+        
+        assert_eq!(sanitized.text, "Quarterly & annual Read the report or online. kept text");
+        for unsafe_fragment in ["script", "alert", "onclick", "onerror", "javascript:", "style", "iframe", "injected", "<img"] {
+            assert!(!sanitized.html.to_lowercase().contains(unsafe_fragment), "{} in {}", unsafe_fragment, sanitized.html);
+        }
+        assert!(sanitized.html.contains("<h1>Quarterly &amp; annual</h1>"));
+        assert!(sanitized.html.contains(r#"<a href="https://example.com/r?a=1&amp;b=2" rel="noopener noreferrer">online</a>"#));
+        assert!(sanitized.html.contains("<a rel=\"noopener noreferrer\">report</a>"));
+        assert!(sanitized.html.ends_with("</p>"));
+        assert!(sanitized.removed_elements >= 8);
+        assert!(sanitized.removed_attributes >= 4);
+    }
+    
+    #[test]
+    fn test_profiles_and_preview_truncation() {
+        let html = r#"<div class="card"><p>Hello <b>world</b></p><img src="https://example.com/a.png" alt="A"><img src="data:image/png;base64,AAAA"></div>"#;
+        
+        let strict = HtmlSanitizer::new(SanitizationConfig { profile: SanitizationProfile::Strict, ..Default::default() }).sanitize(html);
+        assert_eq!(strict.html, "<p>Hello <b>world</b></p>");
+        
+        let relaxed = HtmlSanitizer::new(SanitizationConfig { profile: SanitizationProfile::Relaxed, ..Default::default() }).sanitize(html);
+        assert_eq!(relaxed.html, r#"<div class="card"><p>Hello <b>world</b></p><img src="https://example.com/a.png" alt="A"><img></div>"#);
+        
+        let custom = AllowList { tags: vec!["b".to_string()], ..Default::default() };
+        let truncated = HtmlSanitizer::new(SanitizationConfig {
+            profile: SanitizationProfile::Custom(custom),
+            max_preview_chars: 8,
+            ..Default::default()
+        }).sanitize(html);
+        assert_eq!(truncated.html, "Hello <b>wo</b>");
+        assert!(truncated.truncated);
+        assert_eq!(truncated.text, "Hello world");
+    }
+}
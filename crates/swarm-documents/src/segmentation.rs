@@ -0,0 +1,187 @@
+//! Language-Aware Text Segmentation
+//!
+//! Chinese and Japanese are written without spaces between words, so
+//! whitespace tokenization yields whole sentences as "words" and no usable
+//! keywords. The segmenter is chosen by the detected language: a dictionary
+//! segmenter for Chinese (jieba, enabled by the `cjk` feature) and overlapping
+//! character bigrams for other CJK text.
+
+/// Splits text into words
+pub trait Segmenter: Send + Sync {
+    fn segment(&self, text: &str) -> Vec<String>;
+}
+
+/// Splits on whitespace, trimming surrounding punctuation
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WhitespaceSegmenter;
+
+impl Segmenter for WhitespaceSegmenter {
+    fn segment(&self, text: &str) -> Vec<String> {
+        text.split_whitespace()
+            .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Splits runs of CJK characters into overlapping bigrams; other text is
+/// split on whitespace
+///
+/// Needs no dictionary and works for any CJK language, at the cost of
+/// producing some bigrams that straddle word boundaries.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BigramSegmenter;
+
+impl Segmenter for BigramSegmenter {
+    fn segment(&self, text: &str) -> Vec<String> {
+        let mut words = Vec::new();
+        for token in WhitespaceSegmenter.segment(text) {
+            for (cjk, run) in script_runs(&token) {
+                if !cjk {
+                    let run = run.trim_matches(|c: char| !c.is_alphanumeric());
+                    if !run.is_empty() {
+                        words.push(run.to_string());
+                    }
+                    continue;
+                }
+                let chars: Vec<char> = run.chars().collect();
+                if chars.len() == 1 {
+                    words.push(run.to_string());
+                }
+                words.extend(chars.windows(2).map(|pair| pair.iter().collect::<String>()));
+            }
+        }
+        words
+    }
+}
+
+/// Dictionary-based Chinese segmentation
+#[cfg(feature = "cjk")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JiebaSegmenter;
+
+#[cfg(feature = "cjk")]
+impl Segmenter for JiebaSegmenter {
+    fn segment(&self, text: &str) -> Vec<String> {
+        static JIEBA: std::sync::OnceLock<jieba_rs::Jieba> = std::sync::OnceLock::new();
+        JIEBA.get_or_init(jieba_rs::Jieba::new)
+            .cut(text, false)
+            .into_iter()
+            .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Whether a character belongs to a CJK script (Han, kana or Hangul)
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'     // Hiragana, Katakana
+        | '\u{3400}'..='\u{4DBF}'   // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}'   // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}'   // Hangul syllables
+        | '\u{F900}'..='\u{FAFF}'   // CJK Compatibility Ideographs
+    )
+}
+
+/// Detect Chinese, Japanese or Korean from the script of the text
+///
+/// Returns `None` unless CJK characters make up at least a third of the letters.
+pub fn detect_cjk_language(text: &str) -> Option<&'static str> {
+    let (mut letters, mut han, mut kana, mut hangul) = (0usize, 0usize, 0usize, 0usize);
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        match c {
+            '\u{3040}'..='\u{30FF}' => kana += 1,
+            '\u{AC00}'..='\u{D7AF}' => hangul += 1,
+            c if is_cjk(c) => han += 1,
+            _ => {}
+        }
+    }
+    
+    let cjk = han + kana + hangul;
+    if cjk == 0 || cjk * 3 < letters {
+        None
+    } else if hangul > han + kana {
+        Some("ko")
+    } else if kana > 0 {
+        Some("ja")
+    } else {
+        Some("zh")
+    }
+}
+
+/// Segmenter for a language code
+pub fn segmenter_for_language(language: Option<&str>) -> &'static dyn Segmenter {
+    match language {
+        #[cfg(feature = "cjk")]
+        Some("zh") => &JiebaSegmenter,
+        #[cfg(not(feature = "cjk"))]
+        Some("zh") => &BigramSegmenter,
+        Some("ja") => &BigramSegmenter,
+        // Korean separates words with spaces
+        _ => &WhitespaceSegmenter,
+    }
+}
+
+/// Whether a segmented word is worth counting as a keyword
+///
+/// Latin words need more than three bytes; CJK words at least two characters.
+pub fn is_keyword_candidate(word: &str) -> bool {
+    if word.chars().any(is_cjk) {
+        word.chars().count() >= 2
+    } else {
+        word.len() > 3
+    }
+}
+
+/// Split a token into alternating runs of CJK and non-CJK characters
+fn script_runs(token: &str) -> Vec<(bool, &str)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut current = None;
+    for (index, c) in token.char_indices() {
+        let cjk = is_cjk(c);
+        if current.is_some_and(|run_cjk| run_cjk != cjk) {
+            runs.push((!cjk, &token[start..index]));
+            start = index;
+        }
+        current = Some(cjk);
+    }
+    if let Some(cjk) = current {
+        runs.push((cjk, &token[start..]));
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_detects_cjk_languages() {
+        assert_eq!(detect_cjk_language("自然语言处理是人工智能的一个重要方向"), Some("zh"));
+        assert_eq!(detect_cjk_language("東京でラーメンを食べました"), Some("ja"));
+        assert_eq!(detect_cjk_language("한국어 문장은 띄어쓰기를 합니다"), Some("ko"));
+        assert_eq!(detect_cjk_language("The quick brown fox, 狐"), None);
+    }
+    
+    #[test]
+    fn test_bigram_segmentation() {
+        let words = BigramSegmenter.segment("机器学习 uses GPU加速!");
+        assert_eq!(words, vec!["机器", "器学", "学习", "uses", "GPU", "加速"]);
+        assert!(is_keyword_candidate("学习"));
+        assert!(!is_keyword_candidate("学"));
+        assert!(!is_keyword_candidate("GPU"));
+    }
+    
+    #[test]
+    fn test_segmenter_selection() {
+        let text = "自然语言处理";
+        assert_eq!(segmenter_for_language(Some("en")).segment(text), vec![text]);
+        assert!(segmenter_for_language(Some("ja")).segment(text).contains(&"语言".to_string()));
+        assert!(segmenter_for_language(Some("zh")).segment(text).len() > 1);
+    }
+}
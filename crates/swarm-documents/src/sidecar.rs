@@ -0,0 +1,153 @@
+//! Metadata Sidecars
+//!
+//! Producers can attach business metadata to a document by dropping a JSON
+//! object next to it: `report.pdf.meta.json` describes `report.pdf`. The
+//! reader merges the sidecar's fields into the document metadata, following
+//! a conflict policy for fields the reader sets itself, and never ingests
+//! sidecars as documents of their own.
+
+use super::*;
+use std::collections::hash_map::Entry;
+use std::path::PathBuf;
+
+/// Default sidecar file suffix, appended to the document's file name
+pub const DEFAULT_SIDECAR_SUFFIX: &str = ".meta.json";
+
+/// Document metadata key recording the sidecar that was merged
+pub const SIDECAR_PATH_KEY: &str = "sidecar_path";
+
+/// What to do when a sidecar field is already set on the document
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SidecarConflictPolicy {
+    /// Keep the reader's value and drop the sidecar's
+    #[default]
+    KeepExisting,
+    /// Replace the reader's value with the sidecar's
+    Overwrite,
+    /// Keep both, storing the sidecar's value under `sidecar.<field>`
+    Prefix,
+}
+
+/// Sidecar configuration
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SidecarConfig {
+    /// Look for and merge sidecar files
+    pub enabled: bool,
+    
+    /// Suffix identifying a sidecar, appended to the document's file name
+    pub suffix: String,
+    
+    /// Resolution of fields set both by the reader and the sidecar
+    pub conflict_policy: SidecarConflictPolicy,
+}
+
+impl Default for SidecarConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            suffix: DEFAULT_SIDECAR_SUFFIX.to_string(),
+            conflict_policy: SidecarConflictPolicy::default(),
+        }
+    }
+}
+
+impl SidecarConfig {
+    /// Whether `path` is a sidecar rather than a document
+    pub fn is_sidecar(&self, path: &Path) -> bool {
+        self.enabled && path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.len() > self.suffix.len() && name.ends_with(&self.suffix))
+    }
+    
+    /// Sidecar path of a document, whether or not it exists
+    pub fn sidecar_path(&self, document_path: &Path) -> PathBuf {
+        let mut file_name = document_path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(&self.suffix);
+        document_path.with_file_name(file_name)
+    }
+    
+    /// Read the sidecar of a document, if it has one
+    ///
+    /// A sidecar that is not a JSON object is an error.
+    pub async fn read(&self, document_path: &Path) -> Result<Option<serde_json::Map<String, serde_json::Value>>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        let path = self.sidecar_path(document_path);
+        let bytes = match fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        match serde_json::from_slice(&bytes) {
+            Ok(serde_json::Value::Object(fields)) => Ok(Some(fields)),
+            Ok(_) => anyhow::bail!("sidecar {} is not a JSON object", path.display()),
+            Err(e) => anyhow::bail!("sidecar {} is not valid JSON: {}", path.display(), e),
+        }
+    }
+}
+
+/// Merge sidecar fields into document metadata, returning the conflicting fields
+pub fn merge_sidecar(
+    metadata: &mut HashMap<String, serde_json::Value>,
+    fields: serde_json::Map<String, serde_json::Value>,
+    policy: SidecarConflictPolicy,
+) -> Vec<String> {
+    let mut conflicts = Vec::new();
+    for (field, value) in fields {
+        if let Entry::Vacant(entry) = metadata.entry(field.clone()) {
+            entry.insert(value);
+            continue;
+        }
+        let key = match policy {
+            SidecarConflictPolicy::KeepExisting => None,
+            SidecarConflictPolicy::Overwrite => Some(field.clone()),
+            SidecarConflictPolicy::Prefix => Some(format!("sidecar.{}", field)),
+        };
+        if let Some(key) = key {
+            metadata.insert(key, value);
+        }
+        conflicts.push(field);
+    }
+    conflicts.sort();
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn create_test_metadata() -> HashMap<String, serde_json::Value> {
+        HashMap::from([("file_size".to_string(), serde_json::json!(10))])
+    }
+    
+    fn create_test_fields() -> serde_json::Map<String, serde_json::Value> {
+        serde_json::json!({ "customer": "Acme", "file_size": 99 }).as_object().unwrap().clone()
+    }
+    
+    #[test]
+    fn test_sidecar_paths() {
+        let config = SidecarConfig::default();
+        assert_eq!(config.sidecar_path(Path::new("/in/report.pdf")), Path::new("/in/report.pdf.meta.json"));
+        assert!(config.is_sidecar(Path::new("/in/report.pdf.meta.json")));
+        assert!(!config.is_sidecar(Path::new("/in/report.pdf")));
+        assert!(!config.is_sidecar(Path::new("/in/.meta.json")));
+        assert!(!SidecarConfig { enabled: false, ..Default::default() }.is_sidecar(Path::new("/in/report.pdf.meta.json")));
+    }
+    
+    #[test]
+    fn test_conflict_policies() {
+        let mut kept = create_test_metadata();
+        assert_eq!(merge_sidecar(&mut kept, create_test_fields(), SidecarConflictPolicy::KeepExisting), vec!["file_size"]);
+        assert_eq!((kept["file_size"].clone(), kept["customer"].clone()), (serde_json::json!(10), serde_json::json!("Acme")));
+        
+        let mut overwritten = create_test_metadata();
+        merge_sidecar(&mut overwritten, create_test_fields(), SidecarConflictPolicy::Overwrite);
+        assert_eq!(overwritten["file_size"], serde_json::json!(99));
+        
+        let mut prefixed = create_test_metadata();
+        merge_sidecar(&mut prefixed, create_test_fields(), SidecarConflictPolicy::Prefix);
+        assert_eq!((prefixed["file_size"].clone(), prefixed["sidecar.file_size"].clone()), (serde_json::json!(10), serde_json::json!(99)));
+    }
+}
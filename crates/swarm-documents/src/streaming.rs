@@ -0,0 +1,375 @@
+//! Streaming Text Analysis
+//!
+//! Runs text extraction, keyword counting, language detection and sentiment
+//! analysis incrementally over chunks of a document stream, so multi-GB files
+//! are processed with bounded memory. Only a bounded prefix of the text is
+//! kept as the extracted text.
+
+use super::*;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Words counted towards English language detection
+const ENGLISH_WORDS: [&str; 12] = ["the", "and", "or", "but", "in", "on", "at", "to", "for", "of", "with", "by"];
+
+/// Words counted towards Swedish language detection
+const SWEDISH_WORDS: [&str; 12] = ["och", "att", "det", "som", "en", "på", "är", "av", "för", "med", "till", "den"];
+
+/// Words longer than this are split, bounding memory for text without
+/// spaces (e.g. Chinese)
+const MAX_WORD_BYTES: usize = 4096;
+
+const POSITIVE_WORDS: [&str; 8] = ["good", "great", "excellent", "amazing", "wonderful", "fantastic", "love", "like"];
+const NEGATIVE_WORDS: [&str; 8] = ["bad", "terrible", "awful", "hate", "dislike", "horrible", "worst", "disappointed"];
+
+/// Streaming configuration
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StreamingConfig {
+    /// Bytes read from the stream per chunk
+    pub chunk_size: usize,
+    
+    /// Maximum bytes of text kept as the extracted text
+    pub max_text_bytes: usize,
+    
+    /// Maximum distinct words tracked for keyword extraction; once reached,
+    /// only words already seen are counted
+    pub max_distinct_words: usize,
+}
+
+impl Default for StreamingConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 64 * 1024,
+            max_text_bytes: 1024 * 1024,
+            max_distinct_words: 100_000,
+        }
+    }
+}
+
+/// Incremental text analyzer fed with raw chunks
+#[derive(Debug)]
+pub struct StreamingTextAnalyzer {
+    config: StreamingConfig,
+    strip_markup: bool,
+    
+    /// Trailing bytes of an incomplete UTF-8 sequence
+    pending_bytes: Vec<u8>,
+    
+    /// Word split across chunk boundaries
+    partial_word: String,
+    in_tag: bool,
+    
+    /// Name of the tag being read, lowercase, and whether it is complete
+    tag_name: String,
+    tag_name_done: bool,
+    
+    /// End tag closing the script or style element being skipped, and the
+    /// most recent characters compared against it
+    raw_text_end: Option<&'static str>,
+    raw_text_window: String,
+    
+    text: String,
+    text_truncated: bool,
+    bytes_read: u64,
+    total_words: usize,
+    word_counts: HashMap<String, usize>,
+    english_count: usize,
+    swedish_count: usize,
+    positive_count: usize,
+    negative_count: usize,
+}
+
+impl StreamingTextAnalyzer {
+    pub fn new(config: StreamingConfig) -> Self {
+        Self {
+            config,
+            strip_markup: false,
+            pending_bytes: Vec::new(),
+            partial_word: String::new(),
+            in_tag: false,
+            tag_name: String::new(),
+            tag_name_done: false,
+            raw_text_end: None,
+            raw_text_window: String::new(),
+            text: String::new(),
+            text_truncated: false,
+            bytes_read: 0,
+            total_words: 0,
+            word_counts: HashMap::new(),
+            english_count: 0,
+            swedish_count: 0,
+            positive_count: 0,
+            negative_count: 0,
+        }
+    }
+    
+    /// Skip text inside `<...>` tags and `script` and `style` elements (HTML input)
+    pub fn with_markup_stripping(mut self) -> Self {
+        self.strip_markup = true;
+        self
+    }
+    
+    /// Analyze the next chunk of the stream
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.bytes_read += chunk.len() as u64;
+        self.pending_bytes.extend_from_slice(chunk);
+        
+        let valid_up_to = match std::str::from_utf8(&self.pending_bytes) {
+            Ok(_) => self.pending_bytes.len(),
+            // An incomplete sequence at the end is completed by the next chunk
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => e.valid_up_to() + e.error_len().unwrap_or(0),
+        };
+        let bytes: Vec<u8> = self.pending_bytes.drain(..valid_up_to).collect();
+        let decoded = String::from_utf8_lossy(&bytes).into_owned();
+        for c in decoded.chars() {
+            self.push_char(c);
+        }
+    }
+    
+    fn push_char(&mut self, c: char) {
+        if self.strip_markup {
+            if let Some(end_tag) = self.raw_text_end {
+                self.raw_text_window.push(c.to_ascii_lowercase());
+                while self.raw_text_window.len() > end_tag.len() {
+                    self.raw_text_window.remove(0);
+                }
+                if self.raw_text_window == end_tag {
+                    // The rest of the end tag is skipped like any other tag
+                    self.raw_text_end = None;
+                    self.in_tag = true;
+                    self.tag_name.clear();
+                    self.tag_name_done = true;
+                }
+                return;
+            }
+            match c {
+                '<' => {
+                    self.in_tag = true;
+                    self.tag_name.clear();
+                    self.tag_name_done = false;
+                    return self.end_word();
+                }
+                '>' if self.in_tag => {
+                    self.in_tag = false;
+                    self.raw_text_end = match self.tag_name.as_str() {
+                        "script" => Some("</script"),
+                        "style" => Some("</style"),
+                        _ => None,
+                    };
+                    self.raw_text_window.clear();
+                    return;
+                }
+                _ if self.in_tag => {
+                    if c.is_whitespace() || c == '/' && !self.tag_name.is_empty() || self.tag_name.len() > 8 {
+                        self.tag_name_done = true;
+                    } else if !self.tag_name_done {
+                        self.tag_name.push(c.to_ascii_lowercase());
+                    }
+                    return;
+                }
+                _ => {}
+            }
+        }
+        
+        if c.is_whitespace() {
+            self.end_word();
+        } else {
+            self.partial_word.push(c);
+            if self.partial_word.len() >= MAX_WORD_BYTES {
+                self.end_word();
+            }
+        }
+    }
+    
+    fn end_word(&mut self) {
+        if self.partial_word.is_empty() {
+            return;
+        }
+        let word = std::mem::take(&mut self.partial_word);
+        self.total_words += 1;
+        
+        if !self.text_truncated {
+            let separator = usize::from(!self.text.is_empty());
+            if self.text.len() + separator + word.len() <= self.config.max_text_bytes {
+                if separator == 1 {
+                    self.text.push(' ');
+                }
+                self.text.push_str(&word);
+            } else {
+                self.text_truncated = true;
+            }
+        }
+        
+        let lower = word.to_lowercase();
+        let count_matches = |words: &[&str]| words.iter().map(|w| lower.matches(w).count()).sum::<usize>();
+        self.english_count += count_matches(&ENGLISH_WORDS);
+        self.swedish_count += count_matches(&SWEDISH_WORDS);
+        self.positive_count += count_matches(&POSITIVE_WORDS);
+        self.negative_count += count_matches(&NEGATIVE_WORDS);
+        
+        for keyword in segmenter_for_language(detect_cjk_language(&lower)).segment(&lower) {
+            if !is_keyword_candidate(&keyword) {
+                continue;
+            }
+            if let Some(count) = self.word_counts.get_mut(&keyword) {
+                *count += 1;
+            } else if self.word_counts.len() < self.config.max_distinct_words {
+                self.word_counts.insert(keyword, 1);
+            }
+        }
+    }
+    
+    /// Finish the stream and build the processing result
+    pub fn finish(mut self, options: &DocumentProcessingOptions) -> DocumentProcessingResult {
+        if !self.pending_bytes.is_empty() {
+            let rest = String::from_utf8_lossy(&std::mem::take(&mut self.pending_bytes)).into_owned();
+            for c in rest.chars() {
+                self.push_char(c);
+            }
+        }
+        self.end_word();
+        
+        let mut metadata = HashMap::new();
+        metadata.insert("streamed".to_string(), serde_json::json!(true));
+        metadata.insert("bytes_read".to_string(), serde_json::json!(self.bytes_read));
+        metadata.insert("word_count".to_string(), serde_json::json!(self.total_words));
+        metadata.insert("text_truncated".to_string(), serde_json::json!(self.text_truncated));
+        
+        let language = if self.english_count > self.swedish_count && self.english_count > 0 {
+            "en"
+        } else if self.swedish_count > 0 {
+            "sv"
+        } else {
+            "unknown"
+        };
+        
+        let mut sorted_words: Vec<(String, usize)> = self.word_counts.into_iter().collect();
+        sorted_words.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+        
+        let sentiment = if self.total_words == 0 {
+            0.0
+        } else {
+            (self.positive_count as f32 - self.negative_count as f32) / self.total_words as f32
+        };
+        
+        let mut warnings = Vec::new();
+        if self.text_truncated {
+            warnings.push(ProcessingWarning::new("streaming", "text_truncated",
+                format!("extracted text truncated at {} bytes", self.config.max_text_bytes)));
+        }
+        
+        DocumentProcessingResult {
+            document_id: Uuid::new_v4(),
+            extracted_text: options.extract_text.then_some(self.text),
+            metadata,
+            language: options.detect_language.then(|| language.to_string()),
+            keywords: if options.extract_keywords {
+                sorted_words.into_iter().take(10).map(|(word, _)| word).collect()
+            } else {
+                Vec::new()
+            },
+            sentiment: options.extract_text.then_some(sentiment.clamp(-1.0, 1.0)),
+            classification: None,
+            embeddings: None,
+            processing_time_ms: 0,
+            processed_at: Utc::now(),
+            warnings,
+        }
+    }
+    
+    /// Read a stream to the end in chunks, analyzing each as it arrives
+    pub async fn analyze<R: AsyncRead + Unpin + ?Sized>(mut self, reader: &mut R, options: &DocumentProcessingOptions) -> DocumentResult<DocumentProcessingResult> {
+        let mut buffer = vec![0; self.config.chunk_size.max(1)];
+        loop {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            self.feed(&buffer[..read]);
+        }
+        Ok(self.finish(options))
+    }
+}
+
+/// Document for a file to be processed as a stream
+pub async fn stream_document_from_path(path: &Path) -> DocumentResult<Document> {
+    let metadata = fs::metadata(path).await.map_err(|_| DocumentError::FileNotFound {
+        path: path.to_string_lossy().to_string(),
+    })?;
+    
+    Ok(Document {
+        id: Uuid::new_v4(),
+        filename: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+        document_type: utils::detect_document_type_from_path(path),
+        content: DocumentContent::Stream {
+            source: path.to_string_lossy().to_string(),
+        },
+        metadata: HashMap::new(),
+        created_at: Utc::now(),
+        size_bytes: metadata.len() as usize,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn create_test_analyzer(max_text_bytes: usize) -> StreamingTextAnalyzer {
+        StreamingTextAnalyzer::new(StreamingConfig {
+            chunk_size: 4,
+            max_text_bytes,
+            max_distinct_words: 100,
+        })
+    }
+    
+    #[tokio::test]
+    async fn test_analysis_across_chunk_boundaries() {
+        let text = "Grüße från Göteborg: the streaming parser handles streaming input and streaming output";
+        let result = create_test_analyzer(1024)
+            .analyze(&mut text.as_bytes(), &DocumentProcessingOptions::default())
+            .await
+            .unwrap();
+        
+        // Chunks of 4 bytes split both words and multi-byte characters
+        assert_eq!(result.extracted_text.as_deref(), Some(text));
+        assert_eq!(result.keywords[0], "streaming");
+        assert!(result.keywords.contains(&"göteborg".to_string()));
+        assert_eq!(result.language.as_deref(), Some("en"));
+        assert_eq!(result.metadata["word_count"], 12);
+        assert_eq!(result.metadata["bytes_read"], text.len() as u64);
+    }
+    
+    #[test]
+    fn test_text_and_vocabulary_are_bounded() {
+        let mut analyzer = StreamingTextAnalyzer::new(StreamingConfig {
+            chunk_size: 16,
+            max_text_bytes: 20,
+            max_distinct_words: 2,
+        });
+        analyzer.feed(b"alpha bravo charlie delta alpha echo alpha charlie ");
+        let result = analyzer.finish(&DocumentProcessingOptions::default());
+        
+        assert_eq!(result.extracted_text.as_deref(), Some("alpha bravo charlie"));
+        assert_eq!(result.metadata["text_truncated"], true);
+        assert!(result.has_warning("text_truncated"));
+        assert_eq!(result.keywords, vec!["alpha", "bravo"]);
+    }
+    
+    #[test]
+    fn test_markup_is_stripped_across_chunks() {
+        let mut analyzer = create_test_analyzer(1024).with_markup_stripping();
+        for chunk in ["<htm", "l><p cla", "ss=\"x\">Hello", " wor", "ld</p></html>"] {
+            analyzer.feed(chunk.as_bytes());
+        }
+        let result = analyzer.finish(&DocumentProcessingOptions::default());
+        
+        assert_eq!(result.extracted_text.as_deref(), Some("Hello world"));
+        
+        let mut analyzer = create_test_analyzer(1024).with_markup_stripping();
+        for chunk in ["<sty", "le>p { x: 1 }</ST", "YLE><p>Kept</p><script type=\"a\">if (a <", " b) { steal() }</scr", "ipt >text"] {
+            analyzer.feed(chunk.as_bytes());
+        }
+        let result = analyzer.finish(&DocumentProcessingOptions::default());
+        assert_eq!(result.extracted_text.as_deref(), Some("Kept text"));
+    }
+}
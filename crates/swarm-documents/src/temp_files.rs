@@ -0,0 +1,377 @@
+//! Temporary File Collection
+//!
+//! Spill files, chunk data, converter output and staged export bundles are
+//! allocated in a temp directory through the collector, which records them
+//! in a manifest. Artifacts left behind by a crashed process (tracked by an
+//! earlier instance, or not tracked at all) are swept once older than a TTL,
+//! on startup and periodically.
+
+use super::*;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use swarm_core::{CancellationToken, Service};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// Name of the manifest file in the temp directory
+pub const TEMP_MANIFEST_FILENAME: &str = "_temp_manifest.json";
+
+/// Filename prefix of converter output written without a collector
+pub(crate) const UNTRACKED_CONVERTER_PREFIX: &str = "swarm-convert-";
+
+/// Temp file collection configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TempFileConfig {
+    /// Directory temp artifacts are allocated in
+    pub directory: PathBuf,
+    
+    /// Age after which an orphaned artifact is removed
+    pub ttl_secs: u64,
+    
+    /// Interval between periodic sweeps
+    pub sweep_interval_secs: u64,
+}
+
+impl Default for TempFileConfig {
+    fn default() -> Self {
+        Self {
+            directory: std::env::temp_dir().join("aprio-swarm"),
+            ttl_secs: 3600,
+            sweep_interval_secs: 300,
+        }
+    }
+}
+
+/// What a temp artifact holds
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TempArtifactKind {
+    Spill,
+    Chunk,
+    Conversion,
+    ExportBundle,
+}
+
+impl TempArtifactKind {
+    const ALL: [TempArtifactKind; 4] = [
+        TempArtifactKind::Spill,
+        TempArtifactKind::Chunk,
+        TempArtifactKind::Conversion,
+        TempArtifactKind::ExportBundle,
+    ];
+    
+    fn prefix(&self) -> &'static str {
+        match self {
+            TempArtifactKind::Spill => "spill",
+            TempArtifactKind::Chunk => "chunk",
+            TempArtifactKind::Conversion => "conversion",
+            TempArtifactKind::ExportBundle => "export",
+        }
+    }
+    
+    /// Whether a filename looks like an artifact we allocate, including the
+    /// converter's own output when it runs without a collector
+    fn matches_filename(filename: &str) -> bool {
+        filename.starts_with(UNTRACKED_CONVERTER_PREFIX)
+            || Self::ALL.iter().any(|kind| filename.strip_prefix(kind.prefix()).is_some_and(|rest| rest.starts_with('-')))
+    }
+}
+
+/// A tracked temp artifact
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TempArtifact {
+    pub filename: String,
+    pub kind: TempArtifactKind,
+    
+    /// Collector instance that allocated the artifact
+    pub owner: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Outcome of a sweep
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SweepReport {
+    pub files_removed: u64,
+    pub bytes_reclaimed: u64,
+    
+    /// Artifacts still in use by this instance
+    pub live_artifacts: usize,
+}
+
+/// Allocates temp artifacts and sweeps orphaned ones
+pub struct TempFileCollector {
+    config: TempFileConfig,
+    instance_id: Uuid,
+    artifacts: Mutex<Vec<TempArtifact>>,
+    metrics: Option<Arc<dyn MetricsCollector>>,
+}
+
+impl std::fmt::Debug for TempFileCollector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TempFileCollector")
+            .field("directory", &self.config.directory)
+            .field("instance_id", &self.instance_id)
+            .finish()
+    }
+}
+
+impl TempFileCollector {
+    /// Create the temp directory and load its manifest
+    pub async fn open(config: TempFileConfig) -> DocumentResult<Self> {
+        fs::create_dir_all(&config.directory).await?;
+        let artifacts = match fs::read(config.directory.join(TEMP_MANIFEST_FILENAME)).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring unreadable temp manifest in {}: {}", config.directory.display(), e);
+                Vec::new()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        
+        Ok(Self {
+            config,
+            instance_id: Uuid::new_v4(),
+            artifacts: Mutex::new(artifacts),
+            metrics: None,
+        })
+    }
+    
+    /// Report reclaimed bytes and removed files to a metrics collector
+    pub fn with_metrics(mut self, metrics: Arc<dyn MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    pub fn directory(&self) -> &Path {
+        &self.config.directory
+    }
+    
+    /// Allocate a path for a new artifact; the file itself is created by the caller
+    pub async fn allocate(&self, kind: TempArtifactKind, extension: &str) -> DocumentResult<PathBuf> {
+        let filename = format!("{}-{}.{}", kind.prefix(), Uuid::new_v4().simple(), extension);
+        let mut artifacts = self.artifacts.lock().await;
+        artifacts.push(TempArtifact {
+            filename: filename.clone(),
+            kind,
+            owner: self.instance_id,
+            created_at: Utc::now(),
+        });
+        self.save(&artifacts).await?;
+        Ok(self.config.directory.join(filename))
+    }
+    
+    /// Remove an artifact once it is no longer needed
+    pub async fn release(&self, path: &Path) -> DocumentResult<()> {
+        match fs::remove_file(path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        
+        let filename = path.file_name().map(|name| name.to_string_lossy().into_owned());
+        let mut artifacts = self.artifacts.lock().await;
+        artifacts.retain(|artifact| Some(&artifact.filename) != filename.as_ref());
+        self.save(&artifacts).await
+    }
+    
+    /// Remove orphaned artifacts older than the TTL
+    pub async fn sweep(&self) -> DocumentResult<SweepReport> {
+        self.sweep_at(Utc::now()).await
+    }
+    
+    /// Sweep as if the current time were `now`
+    pub async fn sweep_at(&self, now: DateTime<Utc>) -> DocumentResult<SweepReport> {
+        let cutoff = now - Duration::seconds(self.config.ttl_secs as i64);
+        let mut report = SweepReport::default();
+        let mut artifacts = self.artifacts.lock().await;
+        
+        let mut entries = fs::read_dir(&self.config.directory).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let filename = entry.file_name().to_string_lossy().into_owned();
+            if filename.starts_with(TEMP_MANIFEST_FILENAME) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata().await else { continue };
+            if !metadata.is_file() {
+                continue;
+            }
+            
+            let created_at = match artifacts.iter().find(|artifact| artifact.filename == filename) {
+                Some(artifact) if artifact.owner == self.instance_id => continue,
+                Some(artifact) => artifact.created_at,
+                // Untracked, e.g. a crash between creating the file and recording it;
+                // files we did not name are left alone
+                None if !TempArtifactKind::matches_filename(&filename) => continue,
+                None => metadata.modified().map(DateTime::<Utc>::from).unwrap_or(now),
+            };
+            if created_at > cutoff {
+                continue;
+            }
+            
+            match fs::remove_file(entry.path()).await {
+                Ok(()) => {
+                    tracing::debug!("Removed orphaned temp file {}", entry.path().display());
+                    report.files_removed += 1;
+                    report.bytes_reclaimed += metadata.len();
+                }
+                Err(e) => tracing::warn!("Failed to remove orphaned temp file {}: {}", entry.path().display(), e),
+            }
+        }
+        
+        // Drop expired entries of other instances, whether or not their file still existed
+        artifacts.retain(|artifact| artifact.owner == self.instance_id || artifact.created_at > cutoff);
+        report.live_artifacts = artifacts.iter().filter(|artifact| artifact.owner == self.instance_id).count();
+        self.save(&artifacts).await?;
+        
+        if report.files_removed > 0 {
+            tracing::info!("Reclaimed {} bytes from {} orphaned temp file(s) in {}",
+                report.bytes_reclaimed, report.files_removed, self.config.directory.display());
+        }
+        if let Some(metrics) = &self.metrics {
+            let directory = self.config.directory.to_string_lossy();
+            let tags = [("directory", directory.as_ref())];
+            metrics.increment_counter("temp_gc.sweeps", &tags);
+            metrics.record_metric("temp_gc.files_removed", report.files_removed as f64, &tags);
+            metrics.record_metric("temp_gc.bytes_reclaimed", report.bytes_reclaimed as f64, &tags);
+        }
+        Ok(report)
+    }
+    
+    /// Sweep now and then every `sweep_interval_secs`
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move { self.run(CancellationToken::new()).await })
+    }
+    
+    /// Sweep now and then every `sweep_interval_secs` until cancelled; a
+    /// sweep in progress finishes, so the manifest is never left half written
+    pub async fn run(&self, cancel: CancellationToken) {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(self.config.sweep_interval_secs.max(1)));
+        loop {
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => break,
+                _ = interval.tick() => {
+                    if let Err(e) = self.sweep().await {
+                        tracing::warn!("Temp file sweep of {} failed: {}", self.config.directory.display(), e);
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Replace the manifest atomically
+    async fn save(&self, artifacts: &[TempArtifact]) -> DocumentResult<()> {
+        let manifest_path = self.config.directory.join(TEMP_MANIFEST_FILENAME);
+        let temporary_path = self.config.directory.join(format!("{}.{}", TEMP_MANIFEST_FILENAME, self.instance_id.simple()));
+        fs::write(&temporary_path, serde_json::to_vec(artifacts)?).await?;
+        fs::rename(&temporary_path, &manifest_path).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Service for TempFileCollector {
+    fn name(&self) -> &str {
+        "temp-file-collector"
+    }
+    
+    async fn run(&mut self, cancel: CancellationToken) -> anyhow::Result<()> {
+        TempFileCollector::run(self, cancel).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex as StdMutex;
+    use tempfile::tempdir;
+    
+    /// Metrics collector keeping the last recorded value of each metric
+    #[derive(Default)]
+    struct RecordingMetrics {
+        values: StdMutex<HashMap<String, f64>>,
+    }
+    
+    impl MetricsCollector for RecordingMetrics {
+        fn record_metric(&self, name: &str, value: f64, _tags: &[(&str, &str)]) {
+            self.values.lock().unwrap().insert(name.to_string(), value);
+        }
+        
+        fn increment_counter(&self, name: &str, _tags: &[(&str, &str)]) {
+            *self.values.lock().unwrap().entry(name.to_string()).or_default() += 1.0;
+        }
+        
+        fn record_histogram(&self, name: &str, value: f64, tags: &[(&str, &str)]) {
+            self.record_metric(name, value, tags);
+        }
+        
+        fn get_metrics(&self) -> HashMap<String, MetricValue> {
+            self.values.lock().unwrap().iter()
+                .map(|(name, value)| (name.clone(), MetricValue::Gauge(*value)))
+                .collect()
+        }
+    }
+    
+    fn create_test_config(directory: &Path) -> TempFileConfig {
+        TempFileConfig {
+            directory: directory.to_path_buf(),
+            ttl_secs: 60,
+            sweep_interval_secs: 1,
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_sweep_removes_orphans_of_previous_instance() {
+        let directory = tempdir().unwrap();
+        let crashed = TempFileCollector::open(create_test_config(directory.path())).await.unwrap();
+        let orphan = crashed.allocate(TempArtifactKind::Spill, "bin").await.unwrap();
+        fs::write(&orphan, vec![0u8; 128]).await.unwrap();
+        drop(crashed);
+        
+        let collector = TempFileCollector::open(create_test_config(directory.path())).await.unwrap();
+        let live = collector.allocate(TempArtifactKind::Chunk, "txt").await.unwrap();
+        fs::write(&live, "chunk").await.unwrap();
+        
+        // Within the TTL nothing is removed
+        let report = collector.sweep().await.unwrap();
+        assert_eq!(report.files_removed, 0);
+        assert!(orphan.exists());
+        
+        let report = collector.sweep_at(Utc::now() + Duration::seconds(120)).await.unwrap();
+        assert_eq!(report, SweepReport { files_removed: 1, bytes_reclaimed: 128, live_artifacts: 1 });
+        assert!(!orphan.exists());
+        assert!(live.exists());
+        
+        collector.release(&live).await.unwrap();
+        assert!(!live.exists());
+        assert_eq!(collector.sweep().await.unwrap().live_artifacts, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_sweep_removes_untracked_files_and_reports_metrics() {
+        let directory = tempdir().unwrap();
+        std::fs::write(directory.path().join("spill-0a1b2c.bin"), "left behind").unwrap();
+        std::fs::write(directory.path().join("swarm-convert-3d4e.pdf"), "converted").unwrap();
+        std::fs::write(directory.path().join("notes.txt"), "not ours").unwrap();
+        std::fs::write(directory.path().join("spillover.log"), "not ours either").unwrap();
+        
+        let metrics = Arc::new(RecordingMetrics::default());
+        let collector = TempFileCollector::open(create_test_config(directory.path())).await.unwrap()
+            .with_metrics(metrics.clone());
+        
+        let report = collector.sweep_at(Utc::now() + Duration::seconds(120)).await.unwrap();
+        assert_eq!(report.files_removed, 2);
+        assert_eq!(report.bytes_reclaimed, 20);
+        assert!(directory.path().join(TEMP_MANIFEST_FILENAME).exists());
+        assert!(directory.path().join("notes.txt").exists());
+        assert!(directory.path().join("spillover.log").exists());
+        
+        let recorded = metrics.values.lock().unwrap();
+        assert_eq!(recorded["temp_gc.sweeps"], 1.0);
+        assert_eq!(recorded["temp_gc.bytes_reclaimed"], 20.0);
+    }
+}
@@ -0,0 +1,195 @@
+//! Tesseract OCR Engine
+//!
+//! Runs the `tesseract` command on image bytes piped to its stdin and reads
+//! its TSV output, which lists every recognized word with its block, line,
+//! bounding box and confidence. The tesseract binary and the trained data for
+//! the configured languages must be installed.
+
+use super::*;
+use crate::ocr::{BoundingBox, OcrEngine, OcrOutput, OcrWord};
+use async_trait::async_trait;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Tesseract invocation
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TesseractConfig {
+    /// Path or name of the tesseract binary
+    pub program: String,
+    
+    /// Trained data to recognize with, e.g. `["eng", "swe"]`
+    pub languages: Vec<String>,
+    
+    /// Page segmentation mode (`--psm`), tesseract's default when unset
+    pub page_segmentation_mode: Option<u8>,
+    
+    /// Recognition timeout in milliseconds
+    pub timeout_ms: u64,
+}
+
+impl Default for TesseractConfig {
+    fn default() -> Self {
+        Self {
+            program: "tesseract".to_string(),
+            languages: vec!["eng".to_string()],
+            page_segmentation_mode: None,
+            timeout_ms: 30_000,
+        }
+    }
+}
+
+/// OCR engine running the tesseract command
+#[derive(Debug, Clone, Default)]
+pub struct TesseractEngine {
+    config: TesseractConfig,
+}
+
+impl TesseractEngine {
+    pub fn new(config: TesseractConfig) -> Self {
+        Self { config }
+    }
+    
+    fn args(&self) -> Vec<String> {
+        let mut args = vec!["stdin".to_string(), "stdout".to_string()];
+        if !self.config.languages.is_empty() {
+            args.extend(["-l".to_string(), self.config.languages.join("+")]);
+        }
+        if let Some(mode) = self.config.page_segmentation_mode {
+            args.extend(["--psm".to_string(), mode.to_string()]);
+        }
+        args.push("tsv".to_string());
+        args
+    }
+    
+    async fn run(&self, image: &[u8]) -> DocumentResult<Vec<u8>> {
+        let failed = |reason: String| DocumentError::ProcessingFailed { reason };
+        let mut child = Command::new(&self.config.program)
+            .args(self.args())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| failed(format!("failed to run {}: {}", self.config.program, e)))?;
+        
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let image = image.to_vec();
+        let writer = tokio::spawn(async move {
+            // tesseract may exit without reading all input; its status reports why
+            let _ = stdin.write_all(&image).await;
+        });
+        let output = child.wait_with_output().await?;
+        let _ = writer.await;
+        
+        if !output.status.success() {
+            return Err(failed(format!("{} exited with {}: {}", self.config.program, output.status,
+                String::from_utf8_lossy(&output.stderr).trim())));
+        }
+        Ok(output.stdout)
+    }
+}
+
+#[async_trait]
+impl OcrEngine for TesseractEngine {
+    fn name(&self) -> &str {
+        "tesseract"
+    }
+    
+    async fn recognize(&self, image: &[u8]) -> DocumentResult<OcrOutput> {
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let tsv = tokio::time::timeout(timeout, self.run(image)).await
+            .map_err(|_| DocumentError::ProcessingFailed {
+                reason: format!("tesseract timed out after {}ms", self.config.timeout_ms),
+            })??;
+        
+        let mut output = parse_tsv(&String::from_utf8_lossy(&tsv));
+        // Tesseract does not detect languages; a single configured one is the best hint
+        if let [language] = self.config.languages.as_slice() {
+            output.language = Some(language.clone());
+        }
+        Ok(output)
+    }
+}
+
+/// Words and text from tesseract's TSV output
+///
+/// Columns: level, page, block, paragraph, line, word, left, top, width,
+/// height, confidence, text. Only word rows (level 5) carry text.
+fn parse_tsv(tsv: &str) -> OcrOutput {
+    let mut output = OcrOutput::default();
+    let mut current_line: Option<(u32, u32, u32, u32)> = None;
+    
+    for row in tsv.lines().skip(1) {
+        let columns: Vec<&str> = row.splitn(12, '\t').collect();
+        let [level, page, block, paragraph, line, _, left, top, width, height, confidence, text] = columns[..] else {
+            continue;
+        };
+        let number = |value: &str| value.trim().parse::<u32>().unwrap_or(0);
+        let text = text.trim();
+        if number(level) != 5 || text.is_empty() {
+            continue;
+        }
+        
+        let position = (number(page), number(block), number(paragraph), number(line));
+        match current_line {
+            Some(previous) if previous == position => output.text.push(' '),
+            Some((previous_page, previous_block, ..)) => {
+                let new_block = (previous_page, previous_block) != (position.0, position.1);
+                output.text.push_str(if new_block { "\n\n" } else { "\n" });
+            }
+            None => {}
+        }
+        current_line = Some(position);
+        output.text.push_str(text);
+        output.words.push(OcrWord {
+            text: text.to_string(),
+            confidence: confidence.trim().parse::<f32>().unwrap_or(0.0).max(0.0),
+            bounding_box: BoundingBox {
+                left: number(left),
+                top: number(top),
+                width: number(width),
+                height: number(height),
+            },
+        });
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const HEADER: &str = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext";
+    
+    #[test]
+    fn test_parse_tsv_groups_lines_and_blocks() {
+        let tsv = [
+            HEADER,
+            "1\t1\t0\t0\t0\t0\t0\t0\t800\t600\t-1\t",
+            "5\t1\t1\t1\t1\t1\t10\t20\t60\t18\t96.5\tInvoice",
+            "5\t1\t1\t1\t1\t2\t80\t20\t40\t18\t91\t#42",
+            "5\t1\t1\t1\t2\t1\t10\t50\t50\t18\t88\tTotal:",
+            "5\t1\t1\t1\t2\t2\t70\t50\t8\t18\t-1\t ",
+            "5\t1\t2\t1\t1\t1\t10\t400\t90\t18\t75\tThanks",
+        ].join("\n");
+        let output = parse_tsv(&tsv);
+        
+        assert_eq!(output.text, "Invoice #42\nTotal:\n\nThanks");
+        assert_eq!(output.words.len(), 4);
+        assert_eq!(output.words[0].bounding_box, BoundingBox { left: 10, top: 20, width: 60, height: 18 });
+        assert_eq!(output.words[0].confidence, 96.5);
+    }
+    
+    #[test]
+    fn test_command_line() {
+        let engine = TesseractEngine::new(TesseractConfig {
+            languages: vec!["eng".to_string(), "swe".to_string()],
+            page_segmentation_mode: Some(6),
+            ..Default::default()
+        });
+        assert_eq!(engine.args(), vec!["stdin", "stdout", "-l", "eng+swe", "--psm", "6", "tsv"]);
+        assert_eq!(parse_tsv(HEADER), OcrOutput::default());
+    }
+}
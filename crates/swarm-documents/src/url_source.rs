@@ -0,0 +1,546 @@
+//! URL Document Source
+//!
+//! Fetches documents over HTTP(S). URLs come from the configuration, and,
+//! while the source runs with a broker, from the URL subject, which takes
+//! either a bare URL or a JSON `UrlCandidate` (so crawl candidates can be
+//! fed back). Fetches run concurrently up to a limit, each with a timeout;
+//! connection errors, timeouts and 5xx/429 responses are retried with
+//! backoff, other failures are not. The document type is taken from the
+//! `Content-Type` header, or sniffed from URL and content when the header
+//! names no known type. Fetched documents are published like those of the
+//! directory reader.
+
+use super::*;
+use async_trait::async_trait;
+use futures::stream::{self, Stream, StreamExt};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use reqwest::{StatusCode, Url};
+use std::sync::Arc;
+use std::time::Duration;
+use swarm_core::{cancellable_sleep, CancellationToken, MessageSubscription, RetryPolicy, Service, REJECTION_SUBJECT};
+
+/// Subject URLs to fetch are consumed from
+pub const URL_SUBJECT: &str = "swarm.documents.urls";
+
+/// Document metadata key holding the `Content-Type` a document was served with
+pub const CONTENT_TYPE_KEY: &str = "content_type";
+
+/// How URLs are fetched
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct UrlSourceConfig {
+    /// URLs fetched once when the source starts
+    #[serde(default)]
+    pub urls: Vec<String>,
+    
+    /// Subject further URLs are consumed from while running
+    #[serde(default = "default_url_subject")]
+    pub url_subject: String,
+    
+    #[serde(default = "default_max_concurrent_fetches")]
+    pub max_concurrent_fetches: usize,
+    
+    /// Time a single request may take, including reading the body
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    
+    /// Attempts after the first for failures that may be transient
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    
+    /// Larger responses are rejected
+    #[serde(default = "default_max_document_size")]
+    pub max_document_size: usize,
+    
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+    
+    /// Interval at which the URL subject is checked while idle
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    
+    #[serde(default)]
+    pub id_strategy: DocumentIdStrategy,
+}
+
+fn default_url_subject() -> String {
+    URL_SUBJECT.to_string()
+}
+
+fn default_max_concurrent_fetches() -> usize {
+    4
+}
+
+fn default_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_max_document_size() -> usize {
+    100 * 1024 * 1024
+}
+
+fn default_user_agent() -> String {
+    format!("aprio-swarm/{}", env!("CARGO_PKG_VERSION"))
+}
+
+fn default_poll_interval_ms() -> u64 {
+    100
+}
+
+impl Default for UrlSourceConfig {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            url_subject: default_url_subject(),
+            max_concurrent_fetches: default_max_concurrent_fetches(),
+            timeout_ms: default_timeout_ms(),
+            max_retries: default_max_retries(),
+            retry: RetryPolicy::default(),
+            max_document_size: default_max_document_size(),
+            user_agent: default_user_agent(),
+            poll_interval_ms: default_poll_interval_ms(),
+            id_strategy: DocumentIdStrategy::default(),
+        }
+    }
+}
+
+/// Outcome of one request
+enum Attempt {
+    Fetched(Box<Document>),
+    Retry(anyhow::Error),
+    Fail(anyhow::Error),
+}
+
+/// Fetches documents from URLs
+pub struct UrlDocumentSource {
+    config: UrlSourceConfig,
+    client: reqwest::Client,
+    publisher: Option<(Arc<dyn MessageBroker>, String)>,
+    stats: DocumentReaderStats,
+}
+
+impl UrlDocumentSource {
+    pub fn new(config: UrlSourceConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .user_agent(config.user_agent.clone())
+            .build()?;
+        Ok(Self {
+            config,
+            client,
+            publisher: None,
+            stats: DocumentReaderStats {
+                total_documents_read: 0,
+                documents_per_second: 0.0,
+                error_count: 0,
+                last_read_time: None,
+            },
+        })
+    }
+    
+    /// Publish fetched documents to `subject`, as JSON, and consume the URL subject while running
+    ///
+    /// URLs that are rejected are reported on [`REJECTION_SUBJECT`].
+    pub fn with_broker(mut self, broker: Arc<dyn MessageBroker>, subject: impl Into<String>) -> Self {
+        self.publisher = Some((broker, subject.into()));
+        self
+    }
+    
+    pub fn config(&self) -> &UrlSourceConfig {
+        &self.config
+    }
+    
+    pub fn stats(&self) -> &DocumentReaderStats {
+        &self.stats
+    }
+    
+    /// Fetch a URL, retrying failures that may be transient
+    pub async fn fetch(&self, candidate: &UrlCandidate) -> Result<Document> {
+        let mut retry = 0;
+        loop {
+            match self.fetch_once(candidate).await {
+                Attempt::Fetched(document) => return Ok(*document),
+                Attempt::Retry(e) if retry < self.config.max_retries => {
+                    retry += 1;
+                    let backoff = self.config.retry.backoff(retry).to_std().unwrap_or_default();
+                    tracing::debug!("Retrying {} in {:?} ({}): {}", candidate.url, backoff, retry, e);
+                    tokio::time::sleep(backoff).await;
+                }
+                Attempt::Retry(e) | Attempt::Fail(e) => return Err(e),
+            }
+        }
+    }
+    
+    /// Fetch URLs concurrently, returning each candidate with its outcome in order
+    pub async fn fetch_all(&self, candidates: Vec<UrlCandidate>) -> Vec<(UrlCandidate, Result<Document>)> {
+        self.fetches(candidates).collect().await
+    }
+    
+    /// Fetch URLs concurrently, yielding each candidate with its outcome in
+    /// order as soon as it is fetched
+    pub fn fetches(&self, candidates: Vec<UrlCandidate>) -> impl Stream<Item = (UrlCandidate, Result<Document>)> + '_ {
+        stream::iter(candidates)
+            .map(move |candidate| async move {
+                let fetched = self.fetch(&candidate).await;
+                (candidate, fetched)
+            })
+            .buffered(self.config.max_concurrent_fetches.max(1))
+    }
+    
+    async fn fetch_once(&self, candidate: &UrlCandidate) -> Attempt {
+        let url = match Url::parse(&candidate.url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+            Ok(url) => return Attempt::Fail(anyhow::anyhow!("unsupported scheme {}", url.scheme())),
+            Err(e) => return Attempt::Fail(anyhow::anyhow!("invalid URL {}: {}", candidate.url, e)),
+        };
+        let mut response = match self.client.get(url.clone()).send().await {
+            Ok(response) => response,
+            Err(e) => return Attempt::Retry(e.into()),
+        };
+        let status = response.status();
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Attempt::Retry(anyhow::anyhow!("{} returned {}", url, status));
+        }
+        if !status.is_success() {
+            return Attempt::Fail(anyhow::anyhow!("{} returned {}", url, status));
+        }
+        
+        let filename = filename_for(&url);
+        let too_large = |size_bytes: usize| {
+            let rejection = DocumentRejection::file_too_large(&filename, size_bytes, self.config.max_document_size);
+            Attempt::Fail(DocumentError::Rejected(Box::new(rejection)).into())
+        };
+        let announced = response.headers().get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok()?.parse::<usize>().ok());
+        if let Some(size_bytes) = announced.filter(|size| *size > self.config.max_document_size) {
+            return too_large(size_bytes);
+        }
+        let content_type = response.headers().get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .map(str::to_string);
+        // Bodies without a Content-Length are read in chunks and abandoned
+        // as soon as they grow past the limit
+        let mut bytes = Vec::with_capacity(announced.unwrap_or_default());
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    bytes.extend_from_slice(&chunk);
+                    if bytes.len() > self.config.max_document_size {
+                        return too_large(bytes.len());
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => return Attempt::Retry(e.into()),
+            }
+        }
+        Attempt::Fetched(Box::new(self.document_from_response(candidate, &url, filename, content_type, bytes)))
+    }
+    
+    fn document_from_response(&self, candidate: &UrlCandidate, url: &Url, filename: String, content_type: Option<String>, bytes: Vec<u8>) -> Document {
+        let registry = MimeRegistry::global();
+        let declared = content_type.as_deref()
+            .map(|content_type| registry.document_type_for_mime(content_type))
+            .filter(|document_type| *document_type != DocumentType::Unknown);
+        let (document_type, mime_type) = match (declared, &content_type) {
+            (Some(document_type), Some(content_type)) => (document_type, content_type.split(';').next().unwrap_or_default().trim().to_lowercase()),
+            _ => {
+                let detection = sniff_document_type(Path::new(url.path()), &bytes[..bytes.len().min(SNIFF_LENGTH)]);
+                (detection.document_type, detection.mime_type)
+            }
+        };
+        
+        let size_bytes = bytes.len();
+        let content = match document_type {
+            DocumentType::Text | DocumentType::Html | DocumentType::Markdown => {
+                DocumentContent::Text(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            _ => DocumentContent::Binary(bytes),
+        };
+        let hash = format!("sha256:{}", swarm_core::content_hash(&content));
+        let mut builder = swarm_core::DocumentBuilder::new(filename, content)
+            .document_type(document_type)
+            .source(candidate.url.clone())
+            .id_strategy(self.config.id_strategy)
+            .with_metadata(candidate.document_metadata())
+            .metadata("file_size", serde_json::json!(size_bytes))
+            .metadata("mime_type", serde_json::json!(mime_type))
+            .metadata(CONTENT_HASH_KEY, serde_json::json!(hash));
+        if let Some(content_type) = content_type {
+            builder = builder.metadata(CONTENT_TYPE_KEY, serde_json::json!(content_type));
+        }
+        builder.build()
+    }
+    
+    /// Fetch URLs and publish each document as soon as it is fetched, so
+    /// at most the concurrent fetches are held in memory; returns how many
+    /// were published before the batch ended or `cancel` stopped it
+    async fn ingest(&mut self, candidates: Vec<UrlCandidate>, cancel: &CancellationToken) -> usize {
+        let mut published = 0;
+        let mut stats = self.stats.clone();
+        {
+            let mut fetches = std::pin::pin!(self.fetches(candidates));
+            while let Some((candidate, fetched)) = fetches.next().await {
+                match fetched {
+                    Ok(document) => {
+                        tracing::info!("Fetched {} ({:?}, {} bytes)", candidate.url, document.document_type, document.size_bytes);
+                        stats.total_documents_read += 1;
+                        stats.last_read_time = Some(Utc::now());
+                        if let Some((broker, subject)) = &self.publisher {
+                            if publish_json(broker.as_ref(), subject, &document).await {
+                                published += 1;
+                            } else {
+                                stats.error_count += 1;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to fetch {}: {}", candidate.url, e);
+                        stats.error_count += 1;
+                        let rejection = e.downcast_ref::<DocumentError>().and_then(DocumentError::rejection);
+                        if let (Some((broker, _)), Some(rejection)) = (&self.publisher, rejection) {
+                            if !publish_json(broker.as_ref(), REJECTION_SUBJECT, rejection).await {
+                                stats.error_count += 1;
+                            }
+                        }
+                    }
+                }
+                if cancel.is_cancelled() {
+                    tracing::info!("Stopped fetching URLs, the source is shutting down");
+                    break;
+                }
+            }
+        }
+        self.stats = stats;
+        published
+    }
+}
+
+async fn publish_json(broker: &dyn MessageBroker, subject: &str, payload: &impl serde::Serialize) -> bool {
+    let published = match serde_json::to_vec(payload) {
+        Ok(payload) => broker.publish(subject, &payload).await,
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = published {
+        tracing::error!("Failed to publish to {}: {}", subject, e);
+        return false;
+    }
+    true
+}
+
+/// The URL to fetch named by a message: a `UrlCandidate` as JSON, or a bare URL
+pub fn url_candidate(payload: &[u8]) -> Option<UrlCandidate> {
+    if let Ok(candidate) = serde_json::from_slice::<UrlCandidate>(payload) {
+        return Some(candidate);
+    }
+    let url = std::str::from_utf8(payload).ok()?.trim();
+    (!url.is_empty()).then(|| UrlCandidate {
+        url: url.to_string(),
+        depth: 0,
+        discovered_from: String::new(),
+        link_text: String::new(),
+    })
+}
+
+/// Last path segment of a URL, or its host for the root
+fn filename_for(url: &Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+        .or_else(|| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "document".to_string())
+}
+
+#[async_trait]
+impl Service for UrlDocumentSource {
+    fn name(&self) -> &str {
+        "url-document-source"
+    }
+    
+    /// Fetch the configured URLs, then those arriving on the URL subject until cancelled
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        let mut urls: Option<Box<dyn MessageSubscription>> = match &self.publisher {
+            Some((broker, _)) => Some(broker.subscribe(&self.config.url_subject).await?),
+            None => None,
+        };
+        let configured = self.config.urls.iter().filter_map(|url| url_candidate(url.as_bytes())).collect();
+        self.ingest(configured, &cancel).await;
+        
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+        loop {
+            let mut candidates = Vec::new();
+            if let Some(subscription) = urls.as_mut() {
+                while let Some(message) = subscription.next_message()? {
+                    match url_candidate(&message.payload) {
+                        Some(candidate) => candidates.push(candidate),
+                        None => tracing::warn!("Ignoring malformed URL on {}", message.subject),
+                    }
+                }
+            }
+            if !candidates.is_empty() {
+                self.ingest(candidates, &cancel).await;
+            }
+            if cancellable_sleep(&cancel, poll_interval).await {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use swarm_core::MemoryBroker;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    
+    /// Serve canned responses by path; `/flaky` fails once before succeeding
+    /// and `/stream` sends an endless body without a Content-Length; `/slow`
+    /// answers after a second
+    async fn serve() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let flaky = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let flaky = flaky.clone();
+                tokio::spawn(async move {
+                    let mut request = vec![0; 4096];
+                    let read = stream.read(&mut request).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&request[..read]).into_owned();
+                    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    if path == "/slow" {
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                    if path == "/stream" {
+                        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\n").await;
+                        while stream.write_all(&[b'x'; 1024]).await.is_ok() {}
+                        return;
+                    }
+                    let (status, content_type, body) = match path.as_str() {
+                        "/report" => ("200 OK", "application/pdf", "%PDF-1.4 report"),
+                        "/slow" => ("200 OK", "text/plain", "eventually"),
+                        "/guide.md" => ("200 OK", "application/octet-stream", "# Guide"),
+                        "/page.html" => ("200 OK", "text/html; charset=utf-8", "<html><body>hello</body></html>"),
+                        "/flaky" if flaky.fetch_add(1, Ordering::SeqCst) == 0 => ("503 Service Unavailable", "text/plain", "busy"),
+                        "/flaky" => ("200 OK", "text/plain", "finally"),
+                        _ => ("404 Not Found", "text/plain", "missing"),
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status, content_type, body.len(), body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        format!("http://{}", address)
+    }
+    
+    fn create_test_source() -> UrlDocumentSource {
+        let config = UrlSourceConfig {
+            retry: RetryPolicy { initial_backoff_ms: 10, multiplier: 2.0, max_backoff_ms: 10 },
+            poll_interval_ms: 10,
+            ..Default::default()
+        };
+        UrlDocumentSource::new(config).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_documents_are_typed_by_content_type_and_retried() {
+        let base = serve().await;
+        let source = create_test_source();
+        let candidates = ["/report", "/guide.md", "/page.html", "/flaky", "/missing"]
+            .iter()
+            .map(|path| url_candidate(format!("{}{}", base, path).as_bytes()).unwrap())
+            .collect();
+        let fetched = source.fetch_all(candidates).await;
+        
+        let report = fetched[0].1.as_ref().unwrap();
+        assert_eq!((report.filename.as_str(), &report.document_type), ("report", &DocumentType::Pdf));
+        assert_eq!(report.metadata[SOURCE_URL_KEY], format!("{}/report", base));
+        assert_eq!(fetched[1].1.as_ref().unwrap().document_type, DocumentType::Markdown);
+        let page = fetched[2].1.as_ref().unwrap();
+        assert_eq!(page.document_type, DocumentType::Html);
+        assert_eq!(page.metadata["mime_type"], "text/html");
+        assert_eq!(page.metadata[CONTENT_TYPE_KEY], "text/html; charset=utf-8");
+        assert_eq!(fetched[3].1.as_ref().unwrap().content, DocumentContent::Text("finally".to_string()));
+        assert!(fetched[4].1.as_ref().unwrap_err().to_string().contains("404"));
+    }
+    
+    #[tokio::test]
+    async fn test_bodies_without_length_stop_at_the_size_limit() {
+        let base = serve().await;
+        let mut source = create_test_source();
+        source.config.max_document_size = 8 * 1024;
+        let candidate = url_candidate(format!("{}/stream", base).as_bytes()).unwrap();
+        
+        let error = source.fetch_all(vec![candidate]).await.remove(0).1.unwrap_err();
+        let rejection = error.downcast_ref::<DocumentError>().and_then(DocumentError::rejection).unwrap();
+        assert_eq!(rejection.filename, "stream");
+    }
+    
+    #[tokio::test]
+    async fn test_documents_are_published_as_they_are_fetched() {
+        let base = serve().await;
+        let broker = Arc::new(MemoryBroker::new());
+        let mut incoming = broker.subscribe("swarm.documents.incoming").await.unwrap();
+        let mut source = create_test_source().with_broker(broker.clone(), "swarm.documents.incoming");
+        let candidates = ["/report", "/slow"].iter()
+            .map(|path| url_candidate(format!("{}{}", base, path).as_bytes()).unwrap())
+            .collect();
+        let cancel = CancellationToken::new();
+        let ingesting = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { source.ingest(candidates, &cancel).await }
+        });
+        
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let first: Document = serde_json::from_slice(&incoming.next_message().unwrap().unwrap().payload).unwrap();
+        assert_eq!(first.filename, "report");
+        assert!(incoming.next_message().unwrap().is_none());
+        cancel.cancel();
+        assert_eq!(ingesting.await.unwrap(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_urls_from_the_subject_are_published_as_documents() {
+        let base = serve().await;
+        let broker = Arc::new(MemoryBroker::new());
+        let mut incoming = broker.subscribe("swarm.documents.incoming").await.unwrap();
+        let mut source = create_test_source().with_broker(broker.clone(), "swarm.documents.incoming");
+        source.config.urls = vec![format!("{}/report", base)];
+        let cancel = CancellationToken::new();
+        let running = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { source.run(cancel).await.map(|_| source) }
+        });
+        
+        let candidate = UrlCandidate { url: format!("{}/page.html", base), depth: 1, discovered_from: format!("{}/report", base), link_text: "page".to_string() };
+        let (mut documents, mut requested) = (Vec::new(), false);
+        while documents.len() < 2 {
+            // The source subscribes before it fetches the configured URLs
+            if documents.len() == 1 && !requested {
+                broker.publish(URL_SUBJECT, &serde_json::to_vec(&candidate).unwrap()).await.unwrap();
+                requested = true;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            while let Some(message) = incoming.next_message().unwrap() {
+                documents.push(serde_json::from_slice::<Document>(&message.payload).unwrap());
+            }
+        }
+        cancel.cancel();
+        let source = running.await.unwrap().unwrap();
+        
+        assert_eq!(documents[0].filename, "report");
+        assert_eq!(documents[1].filename, "page.html");
+        assert_eq!(documents[1].metadata[CRAWL_DEPTH_KEY], 1);
+        assert!(source.stats().total_documents_read >= 2);
+    }
+}
@@ -0,0 +1,269 @@
+//! Excel Sheet Extraction
+//!
+//! Reads xlsx workbooks: the sheets listed in the workbook, in order, with
+//! the text of every non-empty cell. Shared and inline strings, numbers,
+//! booleans and cached formula results are extracted as they are stored;
+//! number formats (dates, currencies) are not applied. Each sheet becomes a
+//! block of tab-separated rows under its name, and its used range is
+//! reported as row and column counts.
+
+use super::*;
+use crate::ooxml::{attribute, OoxmlPackage, XmlEvent, XmlReader};
+
+const WORKBOOK_PART: &str = "xl/workbook.xml";
+
+/// Text and dimensions of one sheet
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SheetExtraction {
+    pub name: String,
+    
+    /// Non-empty rows of cell text, in row order
+    #[serde(skip)]
+    pub rows: Vec<Vec<String>>,
+    
+    /// Last used row, counting from 1 (0 for an empty sheet)
+    pub row_count: usize,
+    
+    /// Last used column, counting from 1 (0 for an empty sheet)
+    pub column_count: usize,
+    
+    pub cell_count: usize,
+}
+
+/// Text and sheets extracted from a workbook
+#[derive(Debug, Clone, PartialEq)]
+pub struct XlsxExtraction {
+    /// Sheet names and tab-separated rows, sheets separated by blank lines
+    pub text: String,
+    pub sheets: Vec<SheetExtraction>,
+}
+
+/// Extract sheet names, cell text and dimensions from xlsx bytes
+pub fn extract_xlsx(bytes: &[u8]) -> DocumentResult<XlsxExtraction> {
+    let mut package = OoxmlPackage::open(bytes)?;
+    let workbook = package.read_part(WORKBOOK_PART)?.ok_or_else(|| DocumentError::ProcessingFailed {
+        reason: "invalid xlsx: the package has no workbook".to_string(),
+    })?;
+    let relationships = package.relationships(WORKBOOK_PART)?;
+    let shared_strings = match package.read_part("xl/sharedStrings.xml")? {
+        Some(xml) => parse_shared_strings(&xml),
+        None => Vec::new(),
+    };
+    
+    let mut sheets = Vec::new();
+    for (index, (name, relationship_id)) in sheet_entries(&workbook).into_iter().enumerate() {
+        let part = relationship_id.and_then(|id| relationships.get(&id).cloned())
+            .unwrap_or_else(|| format!("xl/worksheets/sheet{}.xml", index + 1));
+        match package.read_part(&part)? {
+            Some(xml) => sheets.push(parse_sheet(name, &xml, &shared_strings)),
+            None => tracing::debug!("Sheet {} refers to missing part {}", name, part),
+        }
+    }
+    
+    let text = sheets.iter()
+        .filter(|sheet| !sheet.rows.is_empty())
+        .map(|sheet| {
+            let rows: Vec<String> = sheet.rows.iter().map(|row| row.join("\t")).collect();
+            format!("{}\n{}", sheet.name, rows.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Ok(XlsxExtraction { text, sheets })
+}
+
+/// Sheet names and relationship IDs, in workbook order
+fn sheet_entries(workbook: &str) -> Vec<(String, Option<String>)> {
+    XmlReader::new(workbook)
+        .filter_map(|event| match event {
+            XmlEvent::Start { name: "sheet", attributes, .. } => Some((
+                attribute(attributes, "name").map(|name| name.into_owned()).unwrap_or_default(),
+                attribute(attributes, "r:id").map(|id| id.into_owned()),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Shared string table; rich text runs are concatenated, phonetic hints dropped
+fn parse_shared_strings(xml: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut current = String::new();
+    let (mut in_text, mut in_phonetic) = (false, false);
+    for event in XmlReader::new(xml) {
+        match event {
+            XmlEvent::Start { name: "si", empty, .. } => {
+                current.clear();
+                if empty {
+                    strings.push(String::new());
+                }
+            }
+            XmlEvent::End { name: "si" } => strings.push(std::mem::take(&mut current)),
+            XmlEvent::Start { name: "rPh", empty: false, .. } => in_phonetic = true,
+            XmlEvent::End { name: "rPh" } => in_phonetic = false,
+            XmlEvent::Start { name: "t", empty: false, .. } => in_text = true,
+            XmlEvent::End { name: "t" } => in_text = false,
+            XmlEvent::Text(text) if in_text && !in_phonetic => current.push_str(&text),
+            _ => {}
+        }
+    }
+    strings
+}
+
+/// One cell being read
+#[derive(Default)]
+struct CellReader {
+    reference: Option<(usize, usize)>,
+    cell_type: String,
+    value: String,
+    in_value: bool,
+}
+
+fn parse_sheet(name: String, xml: &str, shared_strings: &[String]) -> SheetExtraction {
+    let mut rows: Vec<(usize, Vec<(usize, String)>)> = Vec::new();
+    let (mut row_number, mut next_column) = (0usize, 1usize);
+    let mut cell: Option<CellReader> = None;
+    
+    for event in XmlReader::new(xml) {
+        match event {
+            XmlEvent::Start { name: "row", attributes, .. } => {
+                row_number = attribute(attributes, "r").and_then(|r| r.parse().ok()).unwrap_or(row_number + 1);
+                next_column = 1;
+            }
+            XmlEvent::Start { name: "c", attributes, empty } => {
+                let reference = attribute(attributes, "r").and_then(|r| parse_cell_reference(&r));
+                let reader = CellReader {
+                    reference,
+                    cell_type: attribute(attributes, "t").map(|t| t.into_owned()).unwrap_or_default(),
+                    ..Default::default()
+                };
+                let column = reference.map_or(next_column, |(_, column)| column);
+                next_column = column + 1;
+                if empty {
+                    continue;
+                }
+                cell = Some(reader);
+            }
+            XmlEvent::Start { name: "v" | "t", empty: false, .. } => {
+                if let Some(cell) = cell.as_mut() {
+                    cell.in_value = true;
+                }
+            }
+            XmlEvent::End { name: "v" | "t" } => {
+                if let Some(cell) = cell.as_mut() {
+                    cell.in_value = false;
+                }
+            }
+            XmlEvent::Text(text) => {
+                if let Some(cell) = cell.as_mut().filter(|cell| cell.in_value) {
+                    cell.value.push_str(&text);
+                }
+            }
+            XmlEvent::End { name: "c" } => {
+                let Some(cell) = cell.take() else { continue };
+                let (row, column) = cell.reference.unwrap_or((row_number, next_column - 1));
+                let text = cell_text(&cell.cell_type, cell.value, shared_strings);
+                if text.trim().is_empty() {
+                    continue;
+                }
+                match rows.last_mut() {
+                    Some((last_row, cells)) if *last_row == row => cells.push((column, text)),
+                    _ => rows.push((row, vec![(column, text)])),
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    SheetExtraction {
+        name,
+        row_count: rows.iter().map(|(row, _)| *row).max().unwrap_or(0),
+        column_count: rows.iter().flat_map(|(_, cells)| cells.iter().map(|(column, _)| *column)).max().unwrap_or(0),
+        cell_count: rows.iter().map(|(_, cells)| cells.len()).sum(),
+        rows: rows.into_iter().map(|(_, cells)| cells.into_iter().map(|(_, text)| text).collect()).collect(),
+    }
+}
+
+/// Text of a cell from its type attribute and stored value
+fn cell_text(cell_type: &str, value: String, shared_strings: &[String]) -> String {
+    match cell_type {
+        "s" => value.trim().parse::<usize>().ok()
+            .and_then(|index| shared_strings.get(index).cloned())
+            .unwrap_or_default(),
+        "b" => match value.trim() {
+            "1" => "TRUE".to_string(),
+            _ => "FALSE".to_string(),
+        },
+        _ => value,
+    }
+}
+
+/// Row and column, counting from 1, of a cell reference such as `AB12`
+fn parse_cell_reference(reference: &str) -> Option<(usize, usize)> {
+    let digits = reference.find(|c: char| c.is_ascii_digit())?;
+    let (letters, row) = reference.split_at(digits);
+    if letters.is_empty() {
+        return None;
+    }
+    let column = letters.chars().try_fold(0usize, |column, letter| {
+        letter.is_ascii_alphabetic().then(|| column * 26 + (letter.to_ascii_uppercase() as usize - 'A' as usize + 1))
+    })?;
+    Some((row.parse().ok()?, column))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    
+    /// Minimal workbook with a "Sales" sheet and an empty "Notes" sheet
+    pub(crate) fn create_test_workbook() -> Vec<u8> {
+        let parts = [
+            ("xl/workbook.xml", r#"<?xml version="1.0" encoding="UTF-8"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">
+<sheets><sheet name="Sales" sheetId="1" r:id="rId2"/><sheet name="Notes" sheetId="2" r:id="rId1"/></sheets></workbook>"#),
+            ("xl/_rels/workbook.xml.rels", r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="worksheet" Target="worksheets/sheet2.xml"/>
+<Relationship Id="rId2" Type="worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#),
+            ("xl/sharedStrings.xml", r#"<sst count="3" uniqueCount="3"><si><t>Region</t></si><si><r><t>Reve</t></r><r><t>nue</t></r></si><si><t>North &amp; East</t></si></sst>"#),
+            ("xl/worksheets/sheet1.xml", r#"<worksheet><sheetData>
+<row r="1"><c r="A1" t="s"><v>0</v></c><c r="B1" t="s"><v>1</v></c></row>
+<row r="2"><c r="A2" t="s"><v>2</v></c><c r="B2"><v>1250.5</v></c><c r="D2" t="b"><v>1</v></c></row>
+<row r="4"><c r="A4" t="inlineStr"><is><t>Total</t></is></c><c r="B4"><f>SUM(B2:B3)</f><v>1250.5</v></c><c r="C4"/></row>
+</sheetData></worksheet>"#),
+            ("xl/worksheets/sheet2.xml", r#"<worksheet><sheetData/></worksheet>"#),
+        ];
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, xml) in parts {
+            zip.start_file(name, FileOptions::default()).unwrap();
+            zip.write_all(xml.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+    
+    #[test]
+    fn test_extracts_sheets_in_workbook_order() {
+        let extraction = extract_xlsx(&create_test_workbook()).unwrap();
+        let names: Vec<&str> = extraction.sheets.iter().map(|sheet| sheet.name.as_str()).collect();
+        assert_eq!(names, vec!["Sales", "Notes"]);
+        
+        let sales = &extraction.sheets[0];
+        assert_eq!(sales.rows, vec![
+            vec!["Region", "Revenue"],
+            vec!["North & East", "1250.5", "TRUE"],
+            vec!["Total", "1250.5"],
+        ]);
+        assert_eq!((sales.row_count, sales.column_count, sales.cell_count), (4, 4, 7));
+        assert_eq!(extraction.sheets[1].row_count, 0);
+        assert_eq!(extraction.text, "Sales\nRegion\tRevenue\nNorth & East\t1250.5\tTRUE\nTotal\t1250.5");
+    }
+    
+    #[test]
+    fn test_cell_references_and_invalid_packages() {
+        assert_eq!(parse_cell_reference("A1"), Some((1, 1)));
+        assert_eq!(parse_cell_reference("AB12"), Some((12, 28)));
+        assert_eq!(parse_cell_reference("12"), None);
+        assert!(extract_xlsx(b"not a zip file").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+//! Gateway Client
+//!
+//! Typed client for the gateway's HTTP API, for Rust services that submit
+//! documents from outside the swarm. A request the gateway refuses fails
+//! with the status and the error message the gateway returned; an upload
+//! whose files were all rejected returns the rejections.
+
+use super::*;
+use reqwest::multipart::{Form, Part};
+
+/// Calls a gateway at a base URL such as `http://localhost:8080`
+#[derive(Debug, Clone)]
+pub struct GatewayClient {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+impl GatewayClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+    
+    /// Use a preconfigured HTTP client, e.g. with timeouts or a proxy
+    pub fn with_http_client(base_url: impl Into<String>, http: reqwest::Client) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        Self { base_url, http }
+    }
+    
+    /// Upload files by name and contents; each becomes one tracked document
+    pub async fn upload<I, N>(&self, files: I) -> Result<UploadResponse>
+    where
+        I: IntoIterator<Item = (N, Vec<u8>)>,
+        N: Into<String>,
+    {
+        let form = files.into_iter().fold(Form::new(), |form, (filename, bytes)| {
+            form.part("file", Part::bytes(bytes).file_name(filename.into()))
+        });
+        let response = self.http.post(format!("{}/documents", self.base_url)).multipart(form).send().await?;
+        if response.status() == reqwest::StatusCode::UNPROCESSABLE_ENTITY {
+            return Ok(response.json().await?);
+        }
+        decode(response).await
+    }
+    
+    /// Where an upload is in processing, with its result once there is one
+    pub async fn result(&self, tracking_id: Uuid) -> Result<TrackedDocument> {
+        let response = self.http.get(format!("{}/documents/{}/result", self.base_url, tracking_id)).send().await?;
+        decode(response).await
+    }
+    
+    /// The gateway's OpenAPI document
+    pub async fn openapi(&self) -> Result<utoipa::openapi::OpenApi> {
+        let response = self.http.get(format!("{}{}", self.base_url, OPENAPI_PATH)).send().await?;
+        decode(response).await
+    }
+}
+
+async fn decode<T: serde::de::DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response.json().await?);
+    }
+    let body = response.text().await.unwrap_or_default();
+    let message = match serde_json::from_str::<ErrorResponse>(&body) {
+        Ok(error) => error.error,
+        Err(_) => serde_json::from_str::<DocumentRejection>(&body).map(|rejection| rejection.to_string()).unwrap_or(body),
+    };
+    anyhow::bail!("gateway returned {}: {}", status, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swarm_core::{MemoryBroker, RejectionReason};
+    
+    #[tokio::test]
+    async fn test_client_uploads_and_follows_documents() {
+        let gateway = DocumentGateway::new(GatewayConfig::default(), Arc::new(MemoryBroker::new()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = GatewayClient::new(format!("http://{}/", listener.local_addr().unwrap()));
+        let router = gateway.router();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        
+        let uploaded = client.upload([("notes.txt", b"hello swarm".to_vec()), ("page.html", b"<html></html>".to_vec())]).await.unwrap();
+        assert_eq!(uploaded.documents.iter().map(|document| document.filename.as_str()).collect::<Vec<_>>(), vec!["notes.txt", "page.html"]);
+        
+        let refused = client.upload([("anim.gif", b"GIF89a".to_vec())]).await.unwrap();
+        assert!(refused.documents.is_empty());
+        assert_eq!(refused.rejected[0].reason, RejectionReason::UnsupportedType);
+        
+        let tracked = client.result(uploaded.documents[0].tracking_id).await.unwrap();
+        assert_eq!((tracked.filename.as_str(), tracked.state), ("notes.txt", ProcessingState::Pending));
+        let error = client.result(Uuid::new_v4()).await.unwrap_err().to_string();
+        assert!(error.starts_with("gateway returned 404 Not Found: Unknown tracking id"), "{}", error);
+        
+        let spec = client.openapi().await.unwrap();
+        assert_eq!(spec.info.title, "Swarm Gateway");
+        assert!(spec.paths.paths.contains_key("/documents/{id}/result"));
+    }
+}
@@ -0,0 +1,648 @@
+//! Swarm Gateway - HTTP Document Ingestion
+//!
+//! HTTP front door for clients that cannot talk to the broker themselves.
+//! `POST /documents` takes a multipart upload, turns every file in it into a
+//! `Document` (its type sniffed from content and filename) and publishes it
+//! on the incoming documents subject; the response carries a tracking id per
+//! file, which is the document's id. Every file is checked before any is
+//! published; files that are too large or of an unsupported type come back
+//! as `DocumentRejection`s and are reported on the rejection subject, so a
+//! partly refused upload tells the client exactly which files went through.
+//! `GET /documents/{id}/result` reports
+//! whether the document was processed and, once it was, its result, which
+//! the gateway picks up from the document results subject. Documents go out
+//! through any `MessageBroker`, so the gateway runs against NATS as well as
+//! the in-memory broker. The routes are described by an OpenAPI document at
+//! `GET /openapi.json`, and `GatewayClient` calls them from Rust.
+
+use anyhow::Result;
+use axum::extract::{DefaultBodyLimit, Multipart, Path, State};
+use axum::http::header::CONTENT_LENGTH;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use swarm_comms::{MessageRouter, MessageRoutingConfig, MessageSerializer};
+use swarm_core::prelude::*;
+use swarm_core::{cancellable_sleep, CancellationToken, DocumentRejection, Service, REJECTION_SUBJECT};
+use swarm_documents::{sniff_document_type, DocumentProcessingConfig, CONTENT_HASH_KEY, SNIFF_LENGTH};
+use utoipa::{OpenApi, ToSchema};
+use uuid::Uuid;
+
+pub mod client;
+pub mod openapi;
+pub mod tracking;
+
+pub use client::*;
+pub use openapi::*;
+pub use tracking::*;
+
+/// Document metadata key recording how a document entered the swarm
+pub const SOURCE_KEY: &str = "source";
+
+/// Gateway configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GatewayConfig {
+    /// Address the HTTP server listens on
+    #[serde(default = "default_listen_addr")]
+    pub listen_addr: String,
+    
+    /// Subject uploaded documents are published to
+    #[serde(default = "default_documents_subject")]
+    pub documents_subject: String,
+    
+    /// Subject processing results are read from
+    #[serde(default = "default_results_subject")]
+    pub results_subject: String,
+    
+    /// Largest accepted request body in bytes
+    #[serde(default = "default_max_upload_bytes")]
+    pub max_upload_bytes: usize,
+    
+    /// Largest accepted file in bytes; larger files of an upload are rejected
+    #[serde(default = "default_max_document_bytes")]
+    pub max_document_bytes: usize,
+    
+    /// Document types accepted; files of other types are rejected
+    #[serde(default = "default_supported_types")]
+    pub supported_types: Vec<DocumentType>,
+    
+    /// Uploads whose results can be looked up; older ones are forgotten
+    #[serde(default = "default_max_tracked_documents")]
+    pub max_tracked_documents: usize,
+    
+    /// How often the results subscription is polled in milliseconds
+    #[serde(default = "default_result_poll_interval_ms")]
+    pub result_poll_interval_ms: u64,
+}
+
+fn default_listen_addr() -> String {
+    "0.0.0.0:8080".to_string()
+}
+
+fn default_documents_subject() -> String {
+    MessageRouter::new(MessageRoutingConfig::default()).document_incoming_subject().to_string()
+}
+
+fn default_results_subject() -> String {
+    MessageRouter::new(MessageRoutingConfig::default()).document_results_subject().to_string()
+}
+
+fn default_max_upload_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+fn default_max_document_bytes() -> usize {
+    32 * 1024 * 1024
+}
+
+fn default_supported_types() -> Vec<DocumentType> {
+    DocumentProcessingConfig::default().supported_types
+}
+
+fn default_max_tracked_documents() -> usize {
+    10_000
+}
+
+fn default_result_poll_interval_ms() -> u64 {
+    50
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: default_listen_addr(),
+            documents_subject: default_documents_subject(),
+            results_subject: default_results_subject(),
+            max_upload_bytes: default_max_upload_bytes(),
+            max_document_bytes: default_max_document_bytes(),
+            supported_types: default_supported_types(),
+            max_tracked_documents: default_max_tracked_documents(),
+            result_poll_interval_ms: default_result_poll_interval_ms(),
+        }
+    }
+}
+
+/// Gateway error types, returned to clients as JSON `{"error": ...}`
+#[derive(thiserror::Error, Debug)]
+pub enum GatewayError {
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+    
+    #[error("Unknown tracking id: {0}")]
+    NotFound(Uuid),
+    
+    #[error("Failed to publish document: {0}")]
+    Publish(String),
+    
+    /// Returned as the rejection itself rather than `{"error": ...}`
+    #[error("{0}")]
+    Rejected(Box<DocumentRejection>),
+}
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            GatewayError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            GatewayError::NotFound(_) => StatusCode::NOT_FOUND,
+            GatewayError::Publish(_) => StatusCode::SERVICE_UNAVAILABLE,
+            GatewayError::Rejected(rejection) => return (StatusCode::PAYLOAD_TOO_LARGE, Json(rejection)).into_response(),
+        };
+        (status, Json(ErrorResponse { error: self.to_string() })).into_response()
+    }
+}
+
+/// Body of every failed request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// A file of an upload that was published
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AcceptedDocument {
+    pub tracking_id: Uuid,
+    pub filename: String,
+    #[schema(value_type = String, example = "Pdf")]
+    pub document_type: DocumentType,
+    pub size_bytes: usize,
+}
+
+/// A file of an upload that was accepted but could not be published
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FailedDocument {
+    pub filename: String,
+    pub error: String,
+}
+
+/// Response to `POST /documents`, with the outcome of every file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UploadResponse {
+    pub documents: Vec<AcceptedDocument>,
+    #[serde(default)]
+    #[schema(value_type = Vec<Object>)]
+    pub rejected: Vec<DocumentRejection>,
+    #[serde(default)]
+    pub failed: Vec<FailedDocument>,
+}
+
+impl UploadResponse {
+    /// 202 when every file was published, 207 when only some were and 422
+    /// when every file was rejected
+    pub fn status(&self) -> StatusCode {
+        if self.rejected.is_empty() && self.failed.is_empty() {
+            StatusCode::ACCEPTED
+        } else if self.documents.is_empty() && self.failed.is_empty() {
+            StatusCode::UNPROCESSABLE_ENTITY
+        } else {
+            StatusCode::MULTI_STATUS
+        }
+    }
+}
+
+/// Build the document for an uploaded file
+pub fn document_from_upload(filename: &str, bytes: Vec<u8>) -> Document {
+    document_from_bytes(filename, bytes, "gateway")
+}
+
+/// Build a document from file contents, recording `source` as where it came from
+pub fn document_from_bytes(filename: &str, bytes: Vec<u8>, source: &str) -> Document {
+    let detection = sniff_document_type(std::path::Path::new(filename), &bytes[..bytes.len().min(SNIFF_LENGTH)]);
+    let content = match detection.document_type {
+        DocumentType::Text | DocumentType::Html | DocumentType::Markdown => {
+            DocumentContent::Text(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        _ => DocumentContent::Binary(bytes),
+    };
+    let hash = format!("sha256:{}", swarm_core::content_hash(&content));
+    DocumentBuilder::new(filename, content)
+        .document_type(detection.document_type)
+        .metadata(SOURCE_KEY, serde_json::json!(source))
+        .metadata("mime_type", serde_json::json!(detection.mime_type))
+        .metadata(CONTENT_HASH_KEY, serde_json::json!(hash))
+        .build()
+}
+
+#[derive(Clone)]
+struct GatewayState {
+    broker: Arc<dyn MessageBroker>,
+    tracker: Arc<DocumentTracker>,
+    documents_subject: String,
+    max_document_bytes: usize,
+    max_upload_bytes: usize,
+    supported_types: Arc<[DocumentType]>,
+}
+
+/// HTTP ingestion service
+pub struct DocumentGateway {
+    config: GatewayConfig,
+    broker: Arc<dyn MessageBroker>,
+    tracker: Arc<DocumentTracker>,
+}
+
+impl DocumentGateway {
+    pub fn new(config: GatewayConfig, broker: Arc<dyn MessageBroker>) -> Self {
+        let tracker = Arc::new(DocumentTracker::new(config.max_tracked_documents));
+        Self { config, broker, tracker }
+    }
+    
+    /// Uploads and their results
+    pub fn tracker(&self) -> &Arc<DocumentTracker> {
+        &self.tracker
+    }
+    
+    /// The HTTP routes, for serving or testing without a listener
+    pub fn router(&self) -> Router {
+        let state = GatewayState {
+            broker: self.broker.clone(),
+            tracker: self.tracker.clone(),
+            documents_subject: self.config.documents_subject.clone(),
+            max_document_bytes: self.config.max_document_bytes,
+            max_upload_bytes: self.config.max_upload_bytes,
+            supported_types: self.config.supported_types.clone().into(),
+        };
+        Router::new()
+            .route("/documents", post(upload_documents))
+            .route("/documents/{id}/result", get(document_result))
+            .route(OPENAPI_PATH, get(openapi_document))
+            .layer(DefaultBodyLimit::max(self.config.max_upload_bytes))
+            .with_state(state)
+    }
+    
+    /// Attach results from the results subject to tracked uploads until cancelled
+    pub async fn collect_results(&self, cancel: CancellationToken) -> Result<()> {
+        let mut subscription = self.broker.subscribe(&self.config.results_subject).await?;
+        let interval = Duration::from_millis(self.config.result_poll_interval_ms.max(1));
+        loop {
+            while let Some(message) = subscription.next_message()? {
+                match MessageSerializer::deserialize_task_result(&message) {
+                    Ok(result) => {
+                        let task_id = result.task_id;
+                        if !self.tracker.record_result(result) {
+                            tracing::debug!("Result of task {} belongs to no tracked upload", task_id);
+                        }
+                    }
+                    Err(e) => tracing::warn!("Skipping undecodable result {}: {}", message.id, e),
+                }
+            }
+            if cancellable_sleep(&cancel, interval).await {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Service for DocumentGateway {
+    fn name(&self) -> &str {
+        "gateway"
+    }
+    
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(&self.config.listen_addr).await?;
+        tracing::info!("Gateway listening on {}", listener.local_addr()?);
+        
+        let server = axum::serve(listener, self.router()).with_graceful_shutdown(cancel.clone().cancelled_owned());
+        let (served, collected) = tokio::join!(server, self.collect_results(cancel.clone()));
+        // Either loop ending on its own stops the other as well
+        cancel.cancel();
+        served?;
+        collected
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/documents",
+    tag = "documents",
+    request_body(content = UploadForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 202, description = "Every file was published", body = UploadResponse),
+        (status = 207, description = "Some files were published and others rejected or not published", body = UploadResponse),
+        (status = 400, description = "The upload is malformed or holds no files", body = ErrorResponse),
+        (status = 413, description = "The upload is over the request size limit", body = Object),
+        (status = 422, description = "Every file was rejected", body = UploadResponse),
+        (status = 503, description = "No document could be published", body = ErrorResponse),
+    )
+)]
+async fn upload_documents(State(state): State<GatewayState>, headers: HeaderMap, mut multipart: Multipart) -> Result<(StatusCode, Json<UploadResponse>), GatewayError> {
+    let upload_bytes = headers.get(CONTENT_LENGTH).and_then(|value| value.to_str().ok()?.parse().ok());
+    // Every file is read and checked before any is published
+    let mut documents = Vec::new();
+    let mut rejected = Vec::new();
+    // Requests declared over the limit fail before the first file is named
+    let mut filename = "upload".to_string();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return Err(upload_error(e, &filename, upload_bytes.unwrap_or(0), &state).await),
+        };
+        // Plain form fields carry no file
+        let Some(name) = field.file_name() else {
+            continue;
+        };
+        filename = name.to_string();
+        match read_upload(field, &filename, upload_bytes, &state).await? {
+            Ok(document) => documents.push(document),
+            Err(rejection) => rejected.push(rejection),
+        }
+    }
+    if documents.is_empty() && rejected.is_empty() {
+        return Err(GatewayError::BadRequest("the upload contains no files".to_string()));
+    }
+    for rejection in &rejected {
+        tracing::warn!("Rejected upload: {}", rejection);
+        publish_rejection(&state, rejection).await;
+    }
+    
+    let mut response = UploadResponse { documents: Vec::new(), rejected, failed: Vec::new() };
+    for document in documents {
+        let payload = serde_json::to_vec(&document).map_err(|e| GatewayError::Publish(e.to_string()))?;
+        // Tracked first so a result arriving right after publishing is not missed
+        let tracked = state.tracker.track(&document);
+        if let Err(e) = state.broker.publish(&state.documents_subject, &payload).await {
+            tracing::error!("Failed to publish upload {}: {}", document.filename, e);
+            state.tracker.forget(document.id);
+            response.failed.push(FailedDocument { filename: document.filename, error: e.to_string() });
+            continue;
+        }
+        tracing::info!("Accepted upload {} as document {} ({})", document.filename, document.id, document.document_type);
+        response.documents.push(AcceptedDocument {
+            tracking_id: tracked.tracking_id,
+            filename: tracked.filename,
+            document_type: tracked.document_type,
+            size_bytes: tracked.size_bytes,
+        });
+    }
+    
+    if response.documents.is_empty() && !response.failed.is_empty() {
+        return Err(GatewayError::Publish(response.failed.swap_remove(0).error));
+    }
+    Ok((response.status(), Json(response)))
+}
+
+/// Read one file of an upload, rejecting it once it is over the size limit
+/// or when its type is not supported
+async fn read_upload(mut field: axum::extract::multipart::Field<'_>, filename: &str, upload_bytes: Option<usize>, state: &GatewayState) -> Result<Result<Document, DocumentRejection>, GatewayError> {
+    let mut bytes = Vec::new();
+    let mut size_bytes = 0;
+    loop {
+        let chunk = match field.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => return Err(upload_error(e, filename, upload_bytes.unwrap_or(size_bytes), state).await),
+        };
+        size_bytes += chunk.len();
+        // The rest of an oversized file is only counted, for the rejection
+        if size_bytes <= state.max_document_bytes {
+            bytes.extend_from_slice(&chunk);
+        }
+    }
+    if size_bytes > state.max_document_bytes {
+        return Ok(Err(DocumentRejection::file_too_large(filename, size_bytes, state.max_document_bytes)));
+    }
+    let document = document_from_upload(filename, bytes);
+    if !state.supported_types.contains(&document.document_type) {
+        return Ok(Err(DocumentRejection::unsupported_type(&document, &state.supported_types)));
+    }
+    Ok(Ok(document))
+}
+
+/// A multipart error, which is a rejection of the file being read when the
+/// request went over the body limit; its size is that of the whole request
+/// when the client sent one
+async fn upload_error(error: axum::extract::multipart::MultipartError, filename: &str, size_bytes: usize, state: &GatewayState) -> GatewayError {
+    if error.status() != StatusCode::PAYLOAD_TOO_LARGE {
+        return GatewayError::BadRequest(error.body_text());
+    }
+    let rejection = DocumentRejection::file_too_large(filename, size_bytes, state.max_upload_bytes);
+    tracing::warn!("Rejected upload: {}", rejection);
+    publish_rejection(state, &rejection).await;
+    GatewayError::Rejected(Box::new(rejection))
+}
+
+async fn publish_rejection(state: &GatewayState, rejection: &DocumentRejection) {
+    match serde_json::to_vec(rejection) {
+        Ok(payload) => {
+            if let Err(e) = state.broker.publish(REJECTION_SUBJECT, &payload).await {
+                tracing::warn!("Failed to report rejection of {}: {}", rejection.filename, e);
+            }
+        }
+        Err(e) => tracing::warn!("Failed to serialize rejection of {}: {}", rejection.filename, e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/documents/{id}/result",
+    tag = "documents",
+    params(("id" = Uuid, Path, description = "Tracking id returned by the upload")),
+    responses(
+        (status = 200, description = "The document was processed or failed", body = TrackedDocument),
+        (status = 202, description = "The document is still being processed", body = TrackedDocument),
+        (status = 404, description = "The tracking id is unknown or was forgotten", body = ErrorResponse),
+    )
+)]
+async fn document_result(State(state): State<GatewayState>, Path(tracking_id): Path<Uuid>) -> Result<(StatusCode, Json<TrackedDocument>), GatewayError> {
+    let tracked = state.tracker.get(tracking_id).ok_or(GatewayError::NotFound(tracking_id))?;
+    let status = match tracked.state {
+        ProcessingState::Pending => StatusCode::ACCEPTED,
+        ProcessingState::Completed | ProcessingState::Failed => StatusCode::OK,
+    };
+    Ok((status, Json(tracked)))
+}
+
+async fn openapi_document() -> Json<utoipa::openapi::OpenApi> {
+    Json(GatewayApi::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::body::HttpBody;
+    use http_body_util::BodyExt;
+    use swarm_core::{DocumentProcessingResult, MemoryBroker, RejectionReason, TaskResultData};
+    use tower::ServiceExt;
+    
+    const BOUNDARY: &str = "swarm-boundary";
+    
+    fn multipart_request(files: &[(&str, &str)]) -> Request<Body> {
+        let mut body = String::new();
+        body.push_str(&format!("--{}\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nnot a file\r\n", BOUNDARY));
+        for (filename, content) in files {
+            body.push_str(&format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n{}\r\n",
+                BOUNDARY, filename, content
+            ));
+        }
+        body.push_str(&format!("--{}--\r\n", BOUNDARY));
+        Request::post("/documents")
+            .header("content-type", format!("multipart/form-data; boundary={}", BOUNDARY))
+            .body(Body::from(body))
+            .unwrap()
+    }
+    
+    async fn send<T: serde::de::DeserializeOwned>(router: &Router, request: Request<Body>) -> (StatusCode, T) {
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+    
+    fn result_request(tracking_id: Uuid) -> Request<Body> {
+        Request::get(format!("/documents/{}/result", tracking_id)).body(Body::empty()).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_uploads_are_published_and_tracked_until_processed() {
+        let broker = MemoryBroker::new();
+        let config = GatewayConfig { result_poll_interval_ms: 1, ..Default::default() };
+        let mut incoming = broker.subscribe(&config.documents_subject).await.unwrap();
+        let gateway = Arc::new(DocumentGateway::new(config.clone(), Arc::new(broker.clone())));
+        let router = gateway.router();
+        
+        let (status, response): (_, UploadResponse) = send(&router, multipart_request(&[("notes.txt", "hello swarm")])).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let accepted = &response.documents[0];
+        assert_eq!((accepted.filename.as_str(), &accepted.document_type), ("notes.txt", &DocumentType::Text));
+        
+        let published: Document = serde_json::from_slice(&incoming.next_message().unwrap().unwrap().payload).unwrap();
+        assert_eq!(published.id, accepted.tracking_id);
+        assert_eq!(published.content, DocumentContent::Text("hello swarm".to_string()));
+        
+        let (status, tracked): (_, TrackedDocument) = send(&router, result_request(accepted.tracking_id)).await;
+        assert_eq!((status, tracked.state), (StatusCode::ACCEPTED, ProcessingState::Pending));
+        
+        let cancel = CancellationToken::new();
+        let collector = tokio::spawn({
+            let (gateway, cancel) = (gateway.clone(), cancel.clone());
+            async move { gateway.collect_results(cancel).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let processed = DocumentProcessingResult {
+            document_id: published.id,
+            extracted_text: Some("hello swarm".to_string()),
+            metadata: Default::default(),
+            language: Some("en".to_string()),
+            keywords: Vec::new(),
+            sentiment: None,
+            classification: None,
+            embeddings: None,
+            processing_time_ms: 3,
+            processed_at: Utc::now(),
+            warnings: Vec::new(),
+        };
+        let result = TaskResult {
+            task_id: Uuid::new_v4(),
+            status: TaskStatus::Completed,
+            result: Some(TaskResultData::DocumentProcessing(processed)),
+            error: None,
+            processing_time_ms: 3,
+            completed_at: Utc::now(),
+            metadata: Default::default(),
+        };
+        let message = MessageSerializer::serialize_task_result(&result).unwrap();
+        broker.publish(&config.results_subject, &message.payload).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancel.cancel();
+        collector.await.unwrap().unwrap();
+        
+        let (status, tracked): (_, TrackedDocument) = send(&router, result_request(accepted.tracking_id)).await;
+        assert_eq!((status, tracked.state), (StatusCode::OK, ProcessingState::Completed));
+        assert_eq!(tracked.result, Some(result));
+    }
+    
+    #[tokio::test]
+    async fn test_failed_result_marks_upload_failed() {
+        let gateway = DocumentGateway::new(GatewayConfig::default(), Arc::new(MemoryBroker::new()));
+        let router = gateway.router();
+        let (_, response): (_, UploadResponse) = send(&router, multipart_request(&[("broken.txt", "unreadable")])).await;
+        let tracking_id = response.documents[0].tracking_id;
+        
+        let mut failed = TaskResult {
+            task_id: Uuid::new_v4(),
+            status: TaskStatus::Failed,
+            result: None,
+            error: Some("extraction failed".to_string()),
+            processing_time_ms: 3,
+            completed_at: Utc::now(),
+            metadata: Default::default(),
+        };
+        assert!(!gateway.tracker().record_result(failed.clone()));
+        failed.record_document_id(tracking_id);
+        assert!(gateway.tracker().record_result(failed.clone()));
+        
+        let (status, tracked): (_, TrackedDocument) = send(&router, result_request(tracking_id)).await;
+        assert_eq!((status, tracked.state), (StatusCode::OK, ProcessingState::Failed));
+        assert_eq!(tracked.result, Some(failed));
+    }
+    
+    #[tokio::test]
+    async fn test_files_are_checked_before_any_is_published() {
+        let broker = MemoryBroker::new();
+        let config = GatewayConfig { max_document_bytes: 16, ..Default::default() };
+        let mut incoming = broker.subscribe(&config.documents_subject).await.unwrap();
+        let mut rejections = broker.subscribe(REJECTION_SUBJECT).await.unwrap();
+        let gateway = DocumentGateway::new(config, Arc::new(broker.clone()));
+        let router = gateway.router();
+        
+        let files = [("notes.txt", "hello swarm"), ("long.txt", "far more than sixteen bytes"), ("anim.gif", "GIF89a")];
+        let (status, response): (_, UploadResponse) = send(&router, multipart_request(&files)).await;
+        assert_eq!(status, StatusCode::MULTI_STATUS);
+        assert_eq!(response.documents.iter().map(|document| document.filename.as_str()).collect::<Vec<_>>(), vec!["notes.txt"]);
+        let reasons = response.rejected.iter().map(|rejection| (rejection.filename.as_str(), rejection.reason)).collect::<Vec<_>>();
+        assert_eq!(reasons, vec![("long.txt", RejectionReason::TooLarge), ("anim.gif", RejectionReason::UnsupportedType)]);
+        assert_eq!(response.rejected[0].limit.as_ref().map(|limit| (limit.limit, limit.actual)), Some((16, 27)));
+        
+        let published: Document = serde_json::from_slice(&incoming.next_message().unwrap().unwrap().payload).unwrap();
+        assert_eq!(published.filename, "notes.txt");
+        assert!(incoming.next_message().unwrap().is_none());
+        for expected in &response.rejected {
+            let reported: DocumentRejection = serde_json::from_slice(&rejections.next_message().unwrap().unwrap().payload).unwrap();
+            assert_eq!(&reported, expected);
+        }
+        
+        let (status, response): (_, UploadResponse) = send(&router, multipart_request(&[("anim.gif", "GIF89a")])).await;
+        assert_eq!((status, response.documents.len(), response.rejected.len()), (StatusCode::UNPROCESSABLE_ENTITY, 0, 1));
+    }
+    
+    #[tokio::test]
+    async fn test_uploads_over_the_body_limit_are_rejected_as_documents() {
+        let broker = MemoryBroker::new();
+        let config = GatewayConfig { max_upload_bytes: 256, ..Default::default() };
+        let mut rejections = broker.subscribe(REJECTION_SUBJECT).await.unwrap();
+        let gateway = DocumentGateway::new(config, Arc::new(broker.clone()));
+        
+        let content = "x".repeat(1024);
+        let mut request = multipart_request(&[("huge.txt", &content)]);
+        let size = request.body().size_hint().exact().unwrap();
+        request.headers_mut().insert(CONTENT_LENGTH, size.into());
+        let (status, rejection): (_, DocumentRejection) = send(&gateway.router(), request).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!((rejection.filename.as_str(), rejection.reason), ("upload", RejectionReason::TooLarge));
+        assert_eq!(rejection.limit.map(|limit| (limit.limit, limit.actual)), Some((256, size)));
+        assert!(rejections.next_message().unwrap().is_some());
+        assert!(gateway.tracker().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_bad_requests_are_rejected() {
+        let gateway = DocumentGateway::new(GatewayConfig::default(), Arc::new(MemoryBroker::new()));
+        let router = gateway.router();
+        
+        let (status, error): (_, serde_json::Value) = send(&router, multipart_request(&[])).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["error"], "Bad request: the upload contains no files");
+        
+        let (status, _): (_, serde_json::Value) = send(&router, result_request(Uuid::new_v4())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(gateway.tracker().is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+//! Swarm Gateway
+//!
+//! Serves the HTTP document ingestion API in front of the message broker.
+//!
+//! Usage: `cargo run -p swarm-gateway -- [listen-addr]` (defaults to
+//! `0.0.0.0:8080`). The broker is selected by the `broker` section of the
+//! file named by `SWARM_CONFIG` and `SWARM_BROKER__*` environment variables
+//! (NATS by default); `NATS_URL`, if set, overrides the NATS server. Built
+//! with the `otlp` feature, it exports traces to the collector named by
+//! `OTEL_EXPORTER_OTLP_ENDPOINT`.
+
+use anyhow::Result;
+use swarm_comms::BrokerConfig;
+use swarm_core::{CancellationToken, LayeredConfig, Service, BROKER_SECTION};
+use swarm_gateway::{DocumentGateway, GatewayConfig};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let subscriber = tracing_subscriber::registry().with(LevelFilter::INFO).with(tracing_subscriber::fmt::layer());
+    #[cfg(feature = "otlp")]
+    let (otlp, tracer_provider) = swarm_core::otlp_layer("swarm-gateway")?;
+    #[cfg(feature = "otlp")]
+    let subscriber = subscriber.with(otlp);
+    subscriber.init();
+    
+    let mut config = GatewayConfig::default();
+    if let Some(listen_addr) = std::env::args().nth(1) {
+        config.listen_addr = listen_addr;
+    }
+    let layered = match std::env::var_os("SWARM_CONFIG") {
+        Some(path) => LayeredConfig::from_file(path)?,
+        None => LayeredConfig::new(),
+    };
+    let mut broker: BrokerConfig = layered.with_env("SWARM").section(BROKER_SECTION)?;
+    if let (BrokerConfig::Nats(nats), Ok(url)) = (&mut broker, std::env::var("NATS_URL")) {
+        nats.url = url;
+    }
+    let broker = broker.connect().await?;
+    
+    let cancel = CancellationToken::new();
+    let stopper = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            tracing::info!("Shutting down...");
+            stopper.cancel();
+        }
+    });
+    
+    let served = DocumentGateway::new(config, broker).run(cancel).await;
+    #[cfg(feature = "otlp")]
+    tracer_provider.shutdown()?;
+    served
+}
@@ -0,0 +1,45 @@
+//! OpenAPI Contract
+//!
+//! The gateway's routes as an OpenAPI document generated from the request
+//! handlers, so it cannot drift from what is served. It is served at
+//! `GET /openapi.json` for teams generating their own clients.
+
+use super::*;
+
+/// Path the OpenAPI document is served at
+pub const OPENAPI_PATH: &str = "/openapi.json";
+
+/// The gateway's HTTP API
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Swarm Gateway", description = "HTTP document ingestion for the swarm"),
+    paths(upload_documents, document_result),
+    components(schemas(AcceptedDocument, FailedDocument, UploadResponse, TrackedDocument, ProcessingState, ErrorResponse)),
+    tags((name = "documents", description = "Uploads and their processing results"))
+)]
+pub struct GatewayApi;
+
+/// Multipart body of `POST /documents`; every part with a filename is a document
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub(crate) struct UploadForm {
+    #[schema(value_type = Vec<String>, format = Binary)]
+    files: Vec<Vec<u8>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_spec_describes_every_route() {
+        let spec = serde_json::to_value(GatewayApi::openapi()).unwrap();
+        let upload = &spec["paths"]["/documents"]["post"];
+        assert!(upload["requestBody"]["content"]["multipart/form-data"].is_object());
+        assert_eq!(upload["responses"]["202"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/UploadResponse");
+        let result = &spec["paths"]["/documents/{id}/result"]["get"];
+        assert_eq!(result["parameters"][0]["name"], "id");
+        assert!(result["responses"]["404"].is_object());
+        assert!(spec["components"]["schemas"]["TrackedDocument"]["properties"]["state"].is_object());
+    }
+}
@@ -0,0 +1,119 @@
+//! Document Tracking
+//!
+//! Every accepted upload is tracked by the id of the document it became, so
+//! clients can poll for its result. Results are matched to documents by the
+//! `DOCUMENT_ID_KEY` metadata workers record on every result, failed ones
+//! included, by the document id of a document processing result, or by task
+//! id for results of other kinds. Only the most recent uploads are kept; the oldest are
+//! forgotten once the tracker is full.
+
+use super::*;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Where a tracked document is in processing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingState {
+    Pending,
+    Completed,
+    Failed,
+}
+
+/// A tracked upload and its result, once there is one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TrackedDocument {
+    pub tracking_id: Uuid,
+    pub filename: String,
+    #[schema(value_type = String, example = "Pdf")]
+    pub document_type: DocumentType,
+    pub size_bytes: usize,
+    pub submitted_at: DateTime<Utc>,
+    pub state: ProcessingState,
+    /// The swarm's `TaskResult`, once there is one
+    #[schema(value_type = Option<Object>)]
+    pub result: Option<TaskResult>,
+}
+
+impl TrackedDocument {
+    pub fn new(document: &Document) -> Self {
+        Self {
+            tracking_id: document.id,
+            filename: document.filename.clone(),
+            document_type: document.document_type.clone(),
+            size_bytes: document.size_bytes,
+            submitted_at: Utc::now(),
+            state: ProcessingState::Pending,
+            result: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct TrackerState {
+    documents: HashMap<Uuid, TrackedDocument>,
+    order: VecDeque<Uuid>,
+}
+
+/// Uploads by tracking id, shared by the request handlers and the result listener
+pub struct DocumentTracker {
+    capacity: usize,
+    state: Mutex<TrackerState>,
+}
+
+impl DocumentTracker {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), state: Mutex::default() }
+    }
+    
+    /// Start tracking a published document
+    pub fn track(&self, document: &Document) -> TrackedDocument {
+        let tracked = TrackedDocument::new(document);
+        let mut state = self.state.lock().expect("tracker lock poisoned");
+        if state.documents.insert(tracked.tracking_id, tracked.clone()).is_none() {
+            state.order.push_back(tracked.tracking_id);
+        }
+        while state.order.len() > self.capacity {
+            if let Some(forgotten) = state.order.pop_front() {
+                state.documents.remove(&forgotten);
+            }
+        }
+        tracked
+    }
+    
+    /// Stop tracking a document, e.g. one that could not be published
+    pub fn forget(&self, tracking_id: Uuid) {
+        let mut state = self.state.lock().expect("tracker lock poisoned");
+        if state.documents.remove(&tracking_id).is_some() {
+            state.order.retain(|id| *id != tracking_id);
+        }
+    }
+    
+    pub fn get(&self, tracking_id: Uuid) -> Option<TrackedDocument> {
+        self.state.lock().expect("tracker lock poisoned").documents.get(&tracking_id).cloned()
+    }
+    
+    /// Attach a result to the document it belongs to; returns whether one was tracked
+    pub fn record_result(&self, result: TaskResult) -> bool {
+        let tracking_id = result.document_id().unwrap_or(result.task_id);
+        let mut state = self.state.lock().expect("tracker lock poisoned");
+        let Some(tracked) = state.documents.get_mut(&tracking_id) else {
+            return false;
+        };
+        tracked.state = match result.status {
+            TaskStatus::Completed => ProcessingState::Completed,
+            TaskStatus::Failed | TaskStatus::Cancelled => ProcessingState::Failed,
+            _ => return false,
+        };
+        tracked.result = Some(result);
+        true
+    }
+    
+    pub fn len(&self) -> usize {
+        self.state.lock().expect("tracker lock poisoned").documents.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
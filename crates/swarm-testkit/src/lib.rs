@@ -0,0 +1,20 @@
+//! Swarm Testkit - Pipeline Test Scenarios
+//!
+//! Declarative scenarios for testing document pipelines. A scenario lists
+//! fixtures to ingest and expectations for the results ("5 results, all
+//! with language en, within 10s"). The runner drives the scenario against
+//! an in-memory worker pool or a live swarm reached over NATS and produces
+//! a pass/fail report.
+
+use anyhow::Result;
+use std::sync::Arc;
+use swarm_core::prelude::*;
+use uuid::Uuid;
+
+// Core modules
+pub mod pipeline;
+pub mod scenario;
+
+// Re-export main components
+pub use pipeline::*;
+pub use scenario::*;
@@ -0,0 +1,193 @@
+//! Scenario Pipelines
+//!
+//! A pipeline accepts document tasks and yields their results. The
+//! in-memory pipeline runs a worker pool with the document processor inside
+//! the test process; the NATS pipeline drives a live swarm through the task
+//! assignment and result subjects.
+
+use super::*;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::HashMap;
+use swarm_comms::{MessageRouter, MessageRoutingConfig, MessageSerializer, NatsBroker, NatsConfig, BrokerSubscription};
+use swarm_core::{DocumentProcessingType, TaskResultData};
+use swarm_documents::{DocumentProcessingConfig, SwarmDocumentProcessor};
+use swarm_worker::{ChannelTaskSource, SharedTaskProcessor, SwarmWorker, WorkerPool, WorkerPoolConfig};
+use tokio::sync::{mpsc, Mutex};
+
+/// Something scenarios can be run against
+#[async_trait]
+pub trait ScenarioPipeline: Send + Sync {
+    /// Submit a task for processing
+    async fn submit(&self, task: Task) -> Result<()>;
+    
+    /// Wait for the next result; `None` once no more results can arrive
+    async fn next_result(&self) -> Option<TaskResult>;
+}
+
+/// Worker pool running the document processor in this process
+pub struct InMemoryPipeline {
+    pool: WorkerPool,
+    tasks: mpsc::Sender<Task>,
+    results: Mutex<mpsc::Receiver<TaskResult>>,
+}
+
+impl InMemoryPipeline {
+    /// Start a pool of `workers` document workers with the default configuration
+    pub async fn start(workers: usize) -> Result<Self> {
+        Self::with_config(workers, DocumentProcessingConfig::default()).await
+    }
+    
+    /// Start a pool of `workers` document workers
+    pub async fn with_config(workers: usize, config: DocumentProcessingConfig) -> Result<Self> {
+        let processor: SharedTaskProcessor = Arc::new(DocumentTaskProcessor::new(config));
+        let (tasks, source) = ChannelTaskSource::channel();
+        let pool_config = WorkerPoolConfig {
+            name: "scenario-pipeline".to_string(),
+            size: workers,
+            ..Default::default()
+        };
+        let mut pool = WorkerPool::new(pool_config, move |slot| {
+            Box::new(SwarmWorker::new(worker_config(slot)).with_processor(processor.clone())) as Box<dyn Worker>
+        }, source);
+        let results = pool.take_results().expect("results are taken once");
+        pool.start().await?;
+        
+        Ok(Self {
+            pool,
+            tasks,
+            results: Mutex::new(results),
+        })
+    }
+    
+    /// Stop the worker pool
+    pub async fn shutdown(mut self) -> Result<()> {
+        Ok(self.pool.shutdown().await?)
+    }
+}
+
+#[async_trait]
+impl ScenarioPipeline for InMemoryPipeline {
+    async fn submit(&self, task: Task) -> Result<()> {
+        self.tasks.send(task).await.map_err(|_| anyhow::anyhow!("worker pool is not accepting tasks"))
+    }
+    
+    async fn next_result(&self) -> Option<TaskResult> {
+        self.results.lock().await.recv().await
+    }
+}
+
+/// Live swarm reached over NATS
+pub struct NatsPipeline {
+    broker: NatsBroker,
+    router: MessageRouter,
+    results: Mutex<BrokerSubscription>,
+}
+
+impl NatsPipeline {
+    /// Connect and subscribe to task results
+    pub async fn connect(config: NatsConfig, routing: MessageRoutingConfig) -> Result<Self> {
+        let broker = NatsBroker::new(config).await?;
+        let router = MessageRouter::new(routing);
+        let results = broker.subscribe_to_subject(router.task_results_subject()).await?;
+        Ok(Self {
+            broker,
+            router,
+            results: Mutex::new(results),
+        })
+    }
+}
+
+#[async_trait]
+impl ScenarioPipeline for NatsPipeline {
+    async fn submit(&self, task: Task) -> Result<()> {
+        let message = MessageSerializer::serialize_task(&task)?;
+        self.broker.publish_message(self.router.task_assignment_subject(), &message).await?;
+        Ok(())
+    }
+    
+    async fn next_result(&self) -> Option<TaskResult> {
+        let mut results = self.results.lock().await;
+        loop {
+            let message = results.recv().await?;
+            match MessageSerializer::deserialize_task_result(&message) {
+                Ok(result) => return Some(result),
+                Err(e) => tracing::warn!("Skipping malformed task result on {}: {}", results.subject(), e),
+            }
+        }
+    }
+}
+
+/// Runs document tasks through the document processor
+struct DocumentTaskProcessor {
+    processor: SwarmDocumentProcessor,
+    task_types: Vec<TaskType>,
+}
+
+impl DocumentTaskProcessor {
+    fn new(config: DocumentProcessingConfig) -> Self {
+        let task_types = config.supported_types.iter()
+            .map(|document_type| TaskType::DocumentProcessing {
+                document_type: document_type.clone(),
+                processing_type: DocumentProcessingType::TextExtraction,
+            })
+            .collect();
+        Self {
+            processor: SwarmDocumentProcessor::new(config),
+            task_types,
+        }
+    }
+}
+
+#[async_trait]
+impl TaskProcessor for DocumentTaskProcessor {
+    async fn process(&self, task: &Task) -> Result<TaskResult> {
+        let TaskPayload::Document { document, .. } = &task.payload else {
+            anyhow::bail!("task {} has no document payload", task.id);
+        };
+        
+        let start_time = std::time::Instant::now();
+        let result = self.processor.process_document(document).await?;
+        Ok(TaskResult {
+            task_id: task.id,
+            status: TaskStatus::Completed,
+            result: Some(TaskResultData::DocumentProcessing(result)),
+            error: None,
+            processing_time_ms: start_time.elapsed().as_millis() as u64,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        })
+    }
+    
+    fn supported_task_types(&self) -> &[TaskType] {
+        &self.task_types
+    }
+    
+    fn estimate_processing_time(&self, task: &Task) -> std::time::Duration {
+        match &task.payload {
+            TaskPayload::Document { document, .. } => {
+                swarm_documents::utils::estimate_processing_time(&document.document_type, document.size_bytes)
+            }
+            _ => std::time::Duration::from_secs(1),
+        }
+    }
+}
+
+fn worker_config(slot: usize) -> WorkerConfig {
+    WorkerConfig {
+        id: Uuid::new_v4(),
+        name: format!("scenario-worker-{}", slot),
+        worker_type: WorkerType::Custom { name: "scenario".to_string(), version: "1".to_string() },
+        max_concurrent_tasks: 1,
+        capabilities: Vec::new(),
+        performance_profile: PerformanceProfile {
+            avg_processing_time_ms: 10,
+            memory_usage_mb: 64,
+            cpu_intensity: 0.5,
+            throughput_per_second: 100.0,
+        },
+        health_check_interval_ms: 1000,
+        shutdown_timeout_ms: 5000,
+        metadata: HashMap::new(),
+    }
+}
@@ -0,0 +1,341 @@
+//! Scenario Specs and Runner
+//!
+//! Scenarios are written in YAML:
+//!
+//! ```yaml
+//! name: english fixtures
+//! timeout_ms: 10000
+//! fixtures:
+//!   - path: fixtures/report.txt
+//!   - filename: note.md
+//!     content: "The team shipped the release"
+//! expect:
+//!   results: 2
+//!   language: en
+//! ```
+//!
+//! Fixture paths are relative to the scenario file.
+
+use super::*;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use swarm_core::{DocumentProcessingOptions, DocumentProcessingType, TaskResultData};
+use swarm_documents::utils;
+
+/// A declarative pipeline test
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScenarioSpec {
+    pub name: String,
+    
+    /// Time allowed for all results to arrive
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    
+    pub fixtures: Vec<Fixture>,
+    
+    #[serde(default)]
+    pub expect: Expectations,
+    
+    /// Directory fixture paths are resolved against
+    #[serde(skip)]
+    pub base_dir: Option<PathBuf>,
+}
+
+fn default_timeout_ms() -> u64 {
+    10_000
+}
+
+/// A document ingested by a scenario
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum Fixture {
+    /// File on disk
+    File { path: PathBuf },
+    /// Text given in the spec itself
+    Inline { filename: String, content: String },
+}
+
+/// Expected results; unset fields are not checked
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Expectations {
+    /// Number of results (defaults to one per fixture)
+    pub results: Option<usize>,
+    
+    /// Status of every result (defaults to completed)
+    pub status: Option<TaskStatus>,
+    
+    /// Detected language of every result
+    pub language: Option<String>,
+    
+    /// Keywords that must be extracted from at least one document
+    #[serde(default)]
+    pub keywords_include: Vec<String>,
+}
+
+impl ScenarioSpec {
+    /// Parse a scenario from YAML
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+    
+    /// Load a scenario file, resolving fixture paths relative to it
+    pub async fn from_file(path: &Path) -> Result<Self> {
+        let yaml = tokio::fs::read_to_string(path).await
+            .map_err(|e| anyhow::anyhow!("failed to read scenario {}: {}", path.display(), e))?;
+        let mut spec = Self::from_yaml(&yaml)?;
+        spec.base_dir = path.parent().map(Path::to_path_buf);
+        Ok(spec)
+    }
+    
+    /// Load the fixtures as documents
+    pub async fn documents(&self) -> Result<Vec<Document>> {
+        let mut documents = Vec::with_capacity(self.fixtures.len());
+        for fixture in &self.fixtures {
+            let document = match fixture {
+                Fixture::File { path } => {
+                    let path = match &self.base_dir {
+                        Some(base_dir) if path.is_relative() => base_dir.join(path),
+                        _ => path.clone(),
+                    };
+                    utils::create_document_from_path(&path).await?
+                }
+                Fixture::Inline { filename, content } => {
+                    DocumentBuilder::new(filename.clone(), DocumentContent::Text(content.clone()))
+                        .document_type(utils::detect_document_type_from_path(Path::new(filename)))
+                        .build()
+                }
+            };
+            documents.push(document);
+        }
+        Ok(documents)
+    }
+}
+
+/// Outcome of a scenario run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScenarioReport {
+    pub name: String,
+    pub passed: bool,
+    pub submitted: usize,
+    pub results_received: usize,
+    pub elapsed_ms: u64,
+    
+    /// Unmet expectations, one line each
+    pub failures: Vec<String>,
+}
+
+impl std::fmt::Display for ScenarioReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}: {}/{} results in {}ms",
+            if self.passed { "PASS" } else { "FAIL" },
+            self.name, self.results_received, self.submitted, self.elapsed_ms)?;
+        for failure in &self.failures {
+            write!(f, "\n  - {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs scenarios against a pipeline
+pub struct ScenarioRunner<P> {
+    pipeline: P,
+}
+
+impl<P: ScenarioPipeline> ScenarioRunner<P> {
+    pub fn new(pipeline: P) -> Self {
+        Self { pipeline }
+    }
+    
+    /// The pipeline scenarios run against
+    pub fn pipeline(&self) -> &P {
+        &self.pipeline
+    }
+    
+    pub fn into_pipeline(self) -> P {
+        self.pipeline
+    }
+    
+    /// Ingest the scenario's fixtures and check the results
+    ///
+    /// Errors only if the fixtures cannot be loaded or submitted; unmet
+    /// expectations, including timeouts, are reported as failures.
+    pub async fn run(&self, spec: &ScenarioSpec) -> Result<ScenarioReport> {
+        let documents = spec.documents().await?;
+        let start_time = Instant::now();
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_millis(spec.timeout_ms);
+        
+        let mut filenames = HashMap::new();
+        for document in documents {
+            let task = document_task(document);
+            if let TaskPayload::Document { document, .. } = &task.payload {
+                filenames.insert(task.id, document.filename.clone());
+            }
+            self.pipeline.submit(task).await?;
+        }
+        
+        let mut results = Vec::new();
+        while results.len() < filenames.len() {
+            match tokio::time::timeout_at(deadline, self.pipeline.next_result()).await {
+                Ok(Some(result)) if filenames.contains_key(&result.task_id) => results.push(result),
+                Ok(Some(result)) => tracing::debug!("Ignoring result for task {} outside the scenario", result.task_id),
+                Ok(None) | Err(_) => break,
+            }
+        }
+        
+        let failures = check_expectations(&spec.expect, &filenames, &results);
+        let report = ScenarioReport {
+            name: spec.name.clone(),
+            passed: failures.is_empty(),
+            submitted: filenames.len(),
+            results_received: results.len(),
+            elapsed_ms: start_time.elapsed().as_millis() as u64,
+            failures,
+        };
+        tracing::info!("{}", report);
+        Ok(report)
+    }
+}
+
+/// Task processing a scenario document
+fn document_task(document: Document) -> Task {
+    Task {
+        id: Uuid::new_v4(),
+        task_type: TaskType::DocumentProcessing {
+            document_type: document.document_type.clone(),
+            processing_type: DocumentProcessingType::TextExtraction,
+        },
+        priority: TaskPriority::Normal,
+        status: TaskStatus::Pending,
+        payload: TaskPayload::Document {
+            document,
+            processing_options: DocumentProcessingOptions::default(),
+        },
+        created_at: Utc::now(),
+        deadline: None,
+        retry_count: 0,
+        max_retries: 0,
+        metadata: HashMap::new(),
+    }
+}
+
+fn check_expectations(expect: &Expectations, filenames: &HashMap<Uuid, String>, results: &[TaskResult]) -> Vec<String> {
+    let mut failures = Vec::new();
+    
+    let expected_results = expect.results.unwrap_or(filenames.len());
+    if results.len() != expected_results {
+        failures.push(format!("expected {} results, got {}", expected_results, results.len()));
+    }
+    
+    let expected_status = expect.status.clone().unwrap_or(TaskStatus::Completed);
+    let mut keywords = Vec::new();
+    for result in results {
+        let filename = &filenames[&result.task_id];
+        if result.status != expected_status {
+            let error = result.error.as_deref().map(|e| format!(" ({})", e)).unwrap_or_default();
+            failures.push(format!("{}: expected status {}, got {}{}", filename, expected_status, result.status, error));
+        }
+        
+        let document = match &result.result {
+            Some(TaskResultData::DocumentProcessing(document)) => Some(document),
+            _ => None,
+        };
+        if let Some(language) = &expect.language {
+            let actual = document.and_then(|document| document.language.as_deref());
+            if actual != Some(language.as_str()) {
+                failures.push(format!("{}: expected language {}, got {}", filename, language, actual.unwrap_or("none")));
+            }
+        }
+        keywords.extend(document.into_iter().flat_map(|document| document.keywords.iter()));
+    }
+    
+    for keyword in &expect.keywords_include {
+        if !keywords.contains(&keyword) {
+            failures.push(format!("keyword \"{}\" was not extracted from any document", keyword));
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn create_test_spec(expect: &str) -> ScenarioSpec {
+        ScenarioSpec::from_yaml(&format!(r#"
+name: english notes
+timeout_ms: 5000
+fixtures:
+  - filename: first.txt
+    content: "The pipeline processes the documents and the results are stored"
+  - filename: second.md
+    content: "The workers read the documents for the pipeline"
+expect:
+{}
+"#, expect)).unwrap()
+    }
+    
+    #[test]
+    fn test_parse_scenario_spec() {
+        let spec = ScenarioSpec::from_yaml(r#"
+name: mixed
+fixtures:
+  - path: fixtures/report.txt
+  - filename: note.md
+    content: "hello"
+expect:
+  results: 2
+  status: Failed
+"#).unwrap();
+        
+        assert_eq!(spec.timeout_ms, 10_000);
+        assert_eq!(spec.fixtures[0], Fixture::File { path: PathBuf::from("fixtures/report.txt") });
+        assert!(matches!(&spec.fixtures[1], Fixture::Inline { filename, .. } if filename == "note.md"));
+        assert_eq!(spec.expect.results, Some(2));
+        assert_eq!(spec.expect.status, Some(TaskStatus::Failed));
+    }
+    
+    #[tokio::test]
+    async fn test_scenario_passes_against_in_memory_pipeline() {
+        let spec = create_test_spec("  language: en\n  keywords_include: [pipeline, documents]");
+        let runner = ScenarioRunner::new(InMemoryPipeline::start(2).await.unwrap());
+        
+        let report = runner.run(&spec).await.unwrap();
+        assert!(report.passed, "{}", report);
+        assert_eq!(report.results_received, 2);
+        
+        runner.into_pipeline().shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_unmet_expectations_are_reported() {
+        let spec = create_test_spec("  results: 3\n  language: sv\n  keywords_include: [missing]");
+        let runner = ScenarioRunner::new(InMemoryPipeline::start(1).await.unwrap());
+        
+        let report = runner.run(&spec).await.unwrap();
+        assert!(!report.passed);
+        assert_eq!(report.failures, vec![
+            "expected 3 results, got 2".to_string(),
+            "first.txt: expected language sv, got en".to_string(),
+            "second.md: expected language sv, got en".to_string(),
+            "keyword \"missing\" was not extracted from any document".to_string(),
+        ]);
+        assert!(report.to_string().starts_with("FAIL english notes: 2/2 results"));
+        
+        runner.into_pipeline().shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_bundled_smoke_scenario() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios/smoke.yaml");
+        let spec = ScenarioSpec::from_file(&path).await.unwrap();
+        let runner = ScenarioRunner::new(InMemoryPipeline::start(2).await.unwrap());
+        
+        let report = runner.run(&spec).await.unwrap();
+        assert!(report.passed, "{}", report);
+        
+        runner.into_pipeline().shutdown().await.unwrap();
+    }
+}
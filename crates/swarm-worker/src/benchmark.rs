@@ -0,0 +1,237 @@
+//! Startup Benchmarks
+//!
+//! Performance profiles written by hand are guesses. A worker can measure
+//! itself before it registers instead: each processor runs a few synthetic
+//! tasks of every task type it supports, and the measured per-type latency
+//! and throughput replace the configured profile. Task types without a
+//! synthetic workload (exports, custom tasks) and types whose synthetic
+//! tasks all fail are left unmeasured.
+
+use super::*;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use swarm_core::{BenchmarkReport, DocumentProcessingOptions, TaskTypeBenchmark, TextAnalysisOptions};
+
+const SAMPLE_TEXT: &str = "The quarterly report summarizes revenue growth across all regions. \
+    Customers praised the new onboarding flow, while support tickets about billing dropped sharply. \
+    The engineering team shipped the search upgrade ahead of schedule and reduced query latency. ";
+
+/// Startup benchmark configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StartupBenchmarkConfig {
+    /// Synthetic tasks run per task type
+    pub samples_per_type: usize,
+    
+    /// Approximate size of each synthetic document or text
+    pub sample_size_bytes: usize,
+    
+    /// Time a single synthetic task may take before it counts as failed
+    pub task_timeout_ms: u64,
+}
+
+impl Default for StartupBenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            samples_per_type: 3,
+            sample_size_bytes: 16 * 1024,
+            task_timeout_ms: 5000,
+        }
+    }
+}
+
+/// Run the synthetic tasks of every task type the processors support
+pub async fn run_benchmark(processors: &[SharedTaskProcessor], config: &StartupBenchmarkConfig) -> BenchmarkReport {
+    let mut task_types = Vec::new();
+    for processor in processors {
+        for task_type in processor.supported_task_types() {
+            if task_types.iter().all(|(known, _): &(TaskType, _)| known != task_type) {
+                task_types.push((task_type.clone(), processor.clone()));
+            }
+        }
+    }
+    
+    let mut benchmarks = Vec::new();
+    for (task_type, processor) in task_types {
+        if let Some(benchmark) = benchmark_task_type(&processor, &task_type, config).await {
+            tracing::info!("Benchmarked {}: {:.1}ms average, {} of {} samples failed",
+                task_type, benchmark.avg_latency_ms, benchmark.failures, benchmark.samples);
+            benchmarks.push(benchmark);
+        }
+    }
+    
+    BenchmarkReport {
+        task_types: benchmarks,
+        measured_at: Utc::now(),
+    }
+}
+
+async fn benchmark_task_type(processor: &SharedTaskProcessor, task_type: &TaskType, config: &StartupBenchmarkConfig) -> Option<TaskTypeBenchmark> {
+    let timeout = Duration::from_millis(config.task_timeout_ms);
+    let mut latencies = Vec::new();
+    let mut failures = 0;
+    
+    for sample in 0..config.samples_per_type {
+        let task = synthetic_task(task_type, config.sample_size_bytes, sample)?;
+        let started = Instant::now();
+        match tokio::time::timeout(timeout, processor.process(&task)).await {
+            Ok(Ok(result)) if result.status == TaskStatus::Completed => latencies.push(started.elapsed()),
+            _ => failures += 1,
+        }
+    }
+    
+    let total: Duration = latencies.iter().sum();
+    let avg_latency_ms = if latencies.is_empty() { 0.0 } else { total.as_secs_f64() * 1000.0 / latencies.len() as f64 };
+    let throughput_per_second = if total.is_zero() { 0.0 } else { latencies.len() as f64 / total.as_secs_f64() };
+    Some(TaskTypeBenchmark {
+        task_type: task_type.clone(),
+        samples: config.samples_per_type,
+        failures,
+        avg_latency_ms,
+        throughput_per_second,
+    })
+}
+
+/// A synthetic task of `task_type`, or `None` for types without a synthetic workload
+pub fn synthetic_task(task_type: &TaskType, size_bytes: usize, sample: usize) -> Option<Task> {
+    let text = synthetic_text(size_bytes);
+    let payload = match task_type {
+        TaskType::DocumentProcessing { document_type, .. } => {
+            let content = match document_type {
+                DocumentType::Html => format!("<html><head><title>Benchmark</title></head><body><p>{}</p></body></html>", text),
+                DocumentType::Markdown => format!("# Benchmark\n\n{}", text),
+                _ => text,
+            };
+            TaskPayload::Document {
+                document: Document {
+                    id: Uuid::new_v4(),
+                    filename: format!("benchmark-{}", sample),
+                    document_type: document_type.clone(),
+                    size_bytes: content.len(),
+                    content: DocumentContent::Text(content),
+                    metadata: HashMap::new(),
+                    created_at: Utc::now(),
+                },
+                processing_options: DocumentProcessingOptions::default(),
+            }
+        }
+        TaskType::TextAnalysis { .. } => TaskPayload::Text {
+            content: text,
+            analysis_options: TextAnalysisOptions::default(),
+        },
+        TaskType::VectorIndexing { .. } => TaskPayload::Vector {
+            vectors: (0..8).map(|row| (0..64).map(|column| ((row * 64 + column + sample) % 17) as f32 / 17.0).collect()).collect(),
+            metadata: HashMap::new(),
+        },
+        TaskType::Export { .. } | TaskType::Custom { .. } => return None,
+    };
+    
+    Some(Task {
+        id: Uuid::new_v4(),
+        task_type: task_type.clone(),
+        priority: TaskPriority::Low,
+        status: TaskStatus::Pending,
+        payload,
+        created_at: Utc::now(),
+        deadline: None,
+        retry_count: 0,
+        max_retries: 0,
+        metadata: HashMap::new(),
+    })
+}
+
+fn synthetic_text(size_bytes: usize) -> String {
+    SAMPLE_TEXT.repeat(size_bytes.div_ceil(SAMPLE_TEXT.len()).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use swarm_core::{DocumentProcessingType, ExportFormat, VectorIndexType};
+    
+    /// Sleeps for a fixed time per task, failing for custom payload types
+    struct SleepingProcessor {
+        task_types: Vec<TaskType>,
+        delay: Duration,
+    }
+    
+    #[async_trait]
+    impl TaskProcessor for SleepingProcessor {
+        async fn process(&self, task: &Task) -> Result<TaskResult> {
+            tokio::time::sleep(self.delay).await;
+            if matches!(task.payload, TaskPayload::Vector { .. }) {
+                anyhow::bail!("no index configured");
+            }
+            Ok(TaskResult {
+                task_id: task.id,
+                status: TaskStatus::Completed,
+                result: None,
+                error: None,
+                processing_time_ms: self.delay.as_millis() as u64,
+                completed_at: Utc::now(),
+                metadata: HashMap::new(),
+            })
+        }
+        
+        fn supported_task_types(&self) -> &[TaskType] {
+            &self.task_types
+        }
+        
+        fn estimate_processing_time(&self, _task: &Task) -> Duration {
+            self.delay
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_benchmark_measures_each_supported_type() {
+        let markdown = TaskType::DocumentProcessing {
+            document_type: DocumentType::Markdown,
+            processing_type: DocumentProcessingType::TextExtraction,
+        };
+        let vectors = TaskType::VectorIndexing { index_type: VectorIndexType::DenseEmbedding };
+        let custom = TaskType::Custom { name: "ocr".to_string(), version: "1".to_string() };
+        let processor: SharedTaskProcessor = Arc::new(SleepingProcessor {
+            task_types: vec![markdown.clone(), vectors.clone(), custom],
+            delay: Duration::from_millis(20),
+        });
+        let config = StartupBenchmarkConfig { samples_per_type: 2, ..Default::default() };
+        
+        let report = run_benchmark(&[processor], &config).await;
+        
+        assert_eq!(report.task_types.len(), 2);
+        let measured = report.measured().collect::<Vec<_>>();
+        assert_eq!(measured.len(), 1);
+        assert_eq!(measured[0].task_type, markdown);
+        assert!(measured[0].avg_latency_ms >= 20.0);
+        assert!(measured[0].throughput_per_second <= 50.0);
+        assert_eq!(report.task_types[1].failures, 2);
+        
+        let base = PerformanceProfile {
+            avg_processing_time_ms: 1,
+            memory_usage_mb: 256,
+            cpu_intensity: 0.8,
+            throughput_per_second: 1000.0,
+        };
+        let profile = report.profile(&base).unwrap();
+        assert!(profile.avg_processing_time_ms >= 20);
+        assert_eq!(profile.memory_usage_mb, 256);
+        assert!(report.profile_for(&[vectors], &base).is_none());
+    }
+    
+    #[test]
+    fn test_synthetic_documents_match_their_type() {
+        let html = TaskType::DocumentProcessing {
+            document_type: DocumentType::Html,
+            processing_type: DocumentProcessingType::TextExtraction,
+        };
+        let task = synthetic_task(&html, 1024, 0).unwrap();
+        let TaskPayload::Document { document, .. } = task.payload else {
+            panic!("expected a document payload");
+        };
+        assert!(document.size_bytes >= 1024);
+        assert!(matches!(&document.content, DocumentContent::Text(text) if text.starts_with("<html>")));
+        assert!(synthetic_task(&TaskType::Export { format: ExportFormat::Zip }, 1024, 0).is_none());
+    }
+}
@@ -0,0 +1,79 @@
+//! Swarm Worker - Worker Runtime Implementation
+//!
+//! This crate provides the concrete worker runtime for the Aprio Swarm
+//! system: a `SwarmWorker` that delegates tasks to the task processors of a
+//! `ProcessorRegistry`, and a `WorkerPool` that runs several workers in one process.
+//! With the `wasm` feature, custom processors can be loaded from WebAssembly
+//! modules by a `PluginHost`.
+
+use anyhow::Result;
+use std::sync::Arc;
+use swarm_core::prelude::*;
+use uuid::Uuid;
+
+// Core modules
+pub mod benchmark;
+pub mod memory;
+pub mod network_registration;
+pub mod processor_registry;
+pub mod swarm_worker;
+pub mod task_source;
+pub mod worker_pool;
+#[cfg(feature = "wasm")]
+pub mod wasm_plugins;
+
+// Re-export main components
+pub use benchmark::*;
+pub use memory::*;
+pub use network_registration::*;
+pub use processor_registry::*;
+pub use swarm_worker::*;
+pub use task_source::*;
+pub use worker_pool::*;
+#[cfg(feature = "wasm")]
+pub use wasm_plugins::*;
+
+/// Worker runtime error types
+#[derive(thiserror::Error, Debug)]
+pub enum WorkerError {
+    #[error("No processor registered for task type {task_type} (supported: {})", display_task_types(.supported))]
+    NoProcessor { task_type: TaskType, supported: Vec<TaskType> },
+    
+    #[error("Worker pool is already running")]
+    AlreadyRunning,
+    
+    #[error("Worker pool is not running")]
+    NotRunning,
+    
+    #[error("Shutdown timed out after {timeout_ms}ms")]
+    ShutdownTimeout { timeout_ms: u64 },
+    
+    #[error("Plugin error: {0}")]
+    Plugin(String),
+}
+
+fn display_task_types(task_types: &[TaskType]) -> String {
+    if task_types.is_empty() {
+        return "none".to_string();
+    }
+    task_types.iter().map(|task_type| task_type.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// Result type for worker operations
+pub type WorkerResult<T> = Result<T, WorkerError>;
+
+/// Build a failed task result for a task that could not be processed
+pub fn failed_task_result(task_id: Uuid, error: impl ToString, processing_time_ms: u64) -> TaskResult {
+    TaskResult {
+        task_id,
+        status: TaskStatus::Failed,
+        result: None,
+        error: Some(error.to_string()),
+        processing_time_ms,
+        completed_at: chrono::Utc::now(),
+        metadata: std::collections::HashMap::new(),
+    }
+}
+
+/// Shared handle to a task processor
+pub type SharedTaskProcessor = Arc<dyn TaskProcessor>;
@@ -0,0 +1,131 @@
+//! Memory Watermarks
+//!
+//! Watches the resident memory of the worker process. Above the high
+//! watermark the pool drains: workers finish their current task but stop
+//! taking new ones. Below the low watermark it resumes. The gap between the
+//! two keeps the pool from flapping around a single threshold.
+
+use serde::{Deserialize, Serialize};
+
+/// Memory watermark configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MemoryWatermarkConfig {
+    /// Resident memory at which the pool resumes taking tasks
+    pub low_watermark_mb: u64,
+    
+    /// Resident memory at which the pool stops taking tasks
+    pub high_watermark_mb: u64,
+    
+    /// Interval between memory checks
+    pub check_interval_ms: u64,
+}
+
+impl Default for MemoryWatermarkConfig {
+    fn default() -> Self {
+        Self {
+            low_watermark_mb: 1536,
+            high_watermark_mb: 1792,
+            check_interval_ms: 1000,
+        }
+    }
+}
+
+/// Reads the resident memory of the process
+pub trait MemoryProbe: Send + Sync {
+    /// Resident set size in bytes, if it can be determined
+    fn resident_bytes(&self) -> Option<u64>;
+}
+
+/// Reads `VmRSS` from `/proc/self/status` (Linux only)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessMemoryProbe;
+
+impl MemoryProbe for ProcessMemoryProbe {
+    fn resident_bytes(&self) -> Option<u64> {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+        let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kb * 1024)
+    }
+}
+
+/// Transition between normal operation and draining
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum MemoryPressureEvent {
+    /// Resident memory reached the high watermark; new tasks are refused
+    Draining { resident_mb: u64 },
+    
+    /// Resident memory fell below the low watermark; tasks are taken again
+    Resumed { resident_mb: u64 },
+}
+
+/// Watermark state machine
+#[derive(Debug, Clone)]
+pub struct MemoryWatermarkMonitor {
+    config: MemoryWatermarkConfig,
+    draining: bool,
+}
+
+impl MemoryWatermarkMonitor {
+    pub fn new(config: MemoryWatermarkConfig) -> Self {
+        Self { config, draining: false }
+    }
+    
+    pub fn config(&self) -> &MemoryWatermarkConfig {
+        &self.config
+    }
+    
+    pub fn is_draining(&self) -> bool {
+        self.draining
+    }
+    
+    /// Record a memory reading; returns the transition it caused, if any
+    pub fn observe(&mut self, resident_bytes: u64) -> Option<MemoryPressureEvent> {
+        let resident_mb = resident_bytes / (1024 * 1024);
+        if !self.draining && resident_mb >= self.config.high_watermark_mb {
+            self.draining = true;
+            Some(MemoryPressureEvent::Draining { resident_mb })
+        } else if self.draining && resident_mb < self.config.low_watermark_mb {
+            self.draining = false;
+            Some(MemoryPressureEvent::Resumed { resident_mb })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const MB: u64 = 1024 * 1024;
+    
+    #[test]
+    fn test_watermark_hysteresis() {
+        let mut monitor = MemoryWatermarkMonitor::new(MemoryWatermarkConfig {
+            low_watermark_mb: 100,
+            high_watermark_mb: 200,
+            check_interval_ms: 10,
+        });
+        
+        assert_eq!(monitor.observe(150 * MB), None);
+        assert_eq!(monitor.observe(200 * MB), Some(MemoryPressureEvent::Draining { resident_mb: 200 }));
+        assert!(monitor.is_draining());
+        
+        // Between the watermarks the pool keeps draining
+        assert_eq!(monitor.observe(250 * MB), None);
+        assert_eq!(monitor.observe(150 * MB), None);
+        assert!(monitor.is_draining());
+        
+        assert_eq!(monitor.observe(99 * MB), Some(MemoryPressureEvent::Resumed { resident_mb: 99 }));
+        assert!(!monitor.is_draining());
+        assert_eq!(monitor.observe(150 * MB), None);
+    }
+    
+    #[test]
+    fn test_process_probe_reads_resident_memory() {
+        if cfg!(target_os = "linux") {
+            assert!(ProcessMemoryProbe.resident_bytes().unwrap() > 0);
+        }
+    }
+}
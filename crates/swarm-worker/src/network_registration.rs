@@ -0,0 +1,167 @@
+//! Network Registration
+//!
+//! Joins a coordinator on another machine through the message broker. The
+//! worker's configuration is sent to `swarm.workers.registration`; once the
+//! coordinator acknowledges, its tasks arrive on the assignment subject from
+//! the acknowledgement and are handed out by a `BrokerTaskSource`. Results
+//! and heartbeats are reported back through the registration. A pool given
+//! the registration deregisters when it shuts down.
+
+use super::*;
+use async_trait::async_trait;
+use std::time::Duration;
+use swarm_core::{
+    Heartbeat, MessageBroker, MessageSubscription, RegistrationAck, WorkerDeregistration, WorkerRegistration,
+    TASK_RESULTS_SUBJECT, WORKER_DEREGISTRATION_SUBJECT, WORKER_HEALTH_SUBJECT, WORKER_REGISTRATION_SUBJECT,
+};
+use tokio::sync::Mutex;
+
+/// A worker's accepted registration with a remote coordinator
+pub struct NetworkRegistration {
+    broker: Arc<dyn MessageBroker>,
+    worker_id: Uuid,
+    assignment_subject: String,
+}
+
+impl NetworkRegistration {
+    /// Register a worker, waiting up to `timeout` for the coordinator's acknowledgement
+    pub async fn register(broker: Arc<dyn MessageBroker>, config: &WorkerConfig, timeout: Duration) -> Result<Self> {
+        let registration = WorkerRegistration::new(config.clone());
+        let reply = broker.request(WORKER_REGISTRATION_SUBJECT, &serde_json::to_vec(&registration)?, timeout).await?;
+        let ack: RegistrationAck = serde_json::from_slice(&reply.payload)?;
+        let assignment_subject = match ack.assignment_subject {
+            Some(subject) if ack.accepted => subject,
+            _ => anyhow::bail!("registration of worker {} refused: {}",
+                config.name, ack.reason.as_deref().unwrap_or("no reason given")),
+        };
+        
+        tracing::info!("Worker {} registered, receiving tasks on {}", config.name, assignment_subject);
+        Ok(Self { broker, worker_id: config.id, assignment_subject })
+    }
+    
+    pub fn worker_id(&self) -> Uuid {
+        self.worker_id
+    }
+    
+    /// Subject the coordinator publishes this worker's tasks on
+    pub fn assignment_subject(&self) -> &str {
+        &self.assignment_subject
+    }
+    
+    /// Source handing out the tasks assigned to this worker
+    pub async fn task_source(&self) -> Result<Arc<BrokerTaskSource>> {
+        let subscription = self.broker.subscribe(&self.assignment_subject).await?;
+        Ok(Arc::new(BrokerTaskSource::new(subscription)))
+    }
+    
+    /// Report the result of an assigned task to the coordinator
+    pub async fn report_result(&self, result: &TaskResult) -> Result<()> {
+        self.broker.publish(TASK_RESULTS_SUBJECT, &serde_json::to_vec(result)?).await
+    }
+    
+    /// Tell the coordinator the worker is alive
+    pub async fn send_heartbeat(&self, status: WorkerStatus, current_load: usize) -> Result<()> {
+        let heartbeat = Heartbeat { worker_id: self.worker_id, status, current_load, sent_at: chrono::Utc::now() };
+        self.broker.publish(WORKER_HEALTH_SUBJECT, &serde_json::to_vec(&heartbeat)?).await
+    }
+    
+    /// Tell the coordinator the worker is leaving, so its unfinished tasks are requeued
+    pub async fn deregister(&self, reason: &str) -> Result<()> {
+        let deregistration = WorkerDeregistration { worker_id: self.worker_id, reason: reason.to_string() };
+        self.broker.publish(WORKER_DEREGISTRATION_SUBJECT, &serde_json::to_vec(&deregistration)?).await?;
+        tracing::info!("Worker {} deregistered: {}", self.worker_id, reason);
+        Ok(())
+    }
+}
+
+/// Task source reading tasks from a broker subscription
+///
+/// Messages that are not tasks are skipped. The source is exhausted when the
+/// subscription fails.
+pub struct BrokerTaskSource {
+    subscription: Mutex<Box<dyn MessageSubscription>>,
+    poll_interval: Duration,
+}
+
+impl BrokerTaskSource {
+    pub fn new(subscription: Box<dyn MessageSubscription>) -> Self {
+        Self { subscription: Mutex::new(subscription), poll_interval: Duration::from_millis(10) }
+    }
+    
+    /// Check the subscription at a custom interval while it is empty
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+}
+
+#[async_trait]
+impl TaskSource for BrokerTaskSource {
+    async fn next_task(&self) -> Option<Task> {
+        let mut subscription = self.subscription.lock().await;
+        loop {
+            match subscription.next_message() {
+                Ok(Some(message)) => match serde_json::from_slice(&message.payload) {
+                    Ok(task) => return Some(task),
+                    Err(e) => tracing::warn!("Skipping message on {} that is not a task: {}", message.subject, e),
+                },
+                Ok(None) => tokio::time::sleep(self.poll_interval).await,
+                Err(e) => {
+                    tracing::error!("Task subscription failed: {}", e);
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swarm_worker::tests::{create_test_config, create_test_task};
+    use swarm_core::{CancellationToken, MemoryBroker, RegistrationService, Service, SwarmCoordinator};
+    
+    #[tokio::test]
+    async fn test_worker_registers_receives_tasks_and_deregisters() {
+        let broker = Arc::new(MemoryBroker::new());
+        let mut coordinator = SwarmCoordinator::new();
+        let mut service = RegistrationService::new(broker.clone(), coordinator.accept_registrations());
+        let task = create_test_task("hello");
+        coordinator.submit_task(task.clone());
+        
+        let cancel = CancellationToken::new();
+        let service = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { service.run(cancel).await }
+        });
+        let coordinator = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { coordinator.run(cancel).await.map(|_| coordinator) }
+        });
+        
+        // Give the service time to subscribe
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let config = create_test_config("remote");
+        let registration = NetworkRegistration::register(broker.clone(), &config, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(registration.assignment_subject(), swarm_core::assignment_subject(config.id));
+        let source = registration.task_source().await.unwrap();
+        assert_eq!(source.next_task().await.unwrap().id, task.id);
+        
+        registration.deregister("shutdown").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancel.cancel();
+        service.await.unwrap().unwrap();
+        let coordinator = coordinator.await.unwrap().unwrap();
+        
+        // The unfinished task went back to the queue when the worker left
+        assert_eq!(coordinator.worker_count(), 0);
+        assert_eq!(coordinator.state().pending.iter().map(|task| task.id).collect::<Vec<_>>(), vec![task.id]);
+    }
+    
+    #[tokio::test]
+    async fn test_registration_without_coordinator_fails() {
+        let broker = Arc::new(MemoryBroker::new());
+        let config = create_test_config("remote");
+        assert!(NetworkRegistration::register(broker, &config, Duration::from_millis(50)).await.is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! Processor Registry
+//!
+//! Task processors register for the task types they support, and a worker
+//! looks up the processor of each task by its type. A registry can be built
+//! once at startup and shared by every worker of a pool; registering a
+//! processor for a type that already has one replaces it. Tasks of a type
+//! nobody registered for are rejected with `WorkerError::NoProcessor`, which
+//! names the types the registry does support.
+
+use super::*;
+use std::collections::HashMap;
+
+/// Task processors by the task type they handle
+#[derive(Clone, Default)]
+pub struct ProcessorRegistry {
+    processors: HashMap<TaskType, SharedTaskProcessor>,
+}
+
+impl ProcessorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Register a processor for every task type it supports
+    pub fn with_processor(mut self, processor: SharedTaskProcessor) -> Self {
+        self.register(processor);
+        self
+    }
+    
+    /// Register a processor for every task type it supports
+    pub fn register(&mut self, processor: SharedTaskProcessor) {
+        for task_type in processor.supported_task_types() {
+            self.register_for(task_type.clone(), processor.clone());
+        }
+    }
+    
+    /// Register a processor for a single task type
+    pub fn register_for(&mut self, task_type: TaskType, processor: SharedTaskProcessor) {
+        if self.processors.insert(task_type.clone(), processor).is_some() {
+            tracing::warn!("Replaced the processor registered for {}", task_type);
+        }
+    }
+    
+    pub fn unregister(&mut self, task_type: &TaskType) -> Option<SharedTaskProcessor> {
+        self.processors.remove(task_type)
+    }
+    
+    pub fn get(&self, task_type: &TaskType) -> Option<SharedTaskProcessor> {
+        self.processors.get(task_type).cloned()
+    }
+    
+    /// The processor for `task_type`, or an error naming the supported types
+    pub fn resolve(&self, task_type: &TaskType) -> WorkerResult<SharedTaskProcessor> {
+        self.get(task_type).ok_or_else(|| WorkerError::NoProcessor {
+            task_type: task_type.clone(),
+            supported: self.task_types(),
+        })
+    }
+    
+    pub fn supports(&self, task_type: &TaskType) -> bool {
+        self.processors.contains_key(task_type)
+    }
+    
+    /// Registered task types, ordered by name
+    pub fn task_types(&self) -> Vec<TaskType> {
+        let mut task_types: Vec<TaskType> = self.processors.keys().cloned().collect();
+        task_types.sort_by_key(|task_type| task_type.to_string());
+        task_types
+    }
+    
+    /// Every registered processor once, in task type order
+    pub fn processors(&self) -> Vec<SharedTaskProcessor> {
+        let mut processors: Vec<SharedTaskProcessor> = Vec::new();
+        for task_type in self.task_types() {
+            let processor = &self.processors[&task_type];
+            if !processors.iter().any(|known| Arc::ptr_eq(known, processor)) {
+                processors.push(processor.clone());
+            }
+        }
+        processors
+    }
+    
+    pub fn len(&self) -> usize {
+        self.processors.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.processors.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swarm_worker::tests::{text_task_type, EchoProcessor};
+    use swarm_core::VectorIndexType;
+    
+    #[test]
+    fn test_processors_are_found_by_task_type() {
+        let vectors = TaskType::VectorIndexing { index_type: VectorIndexType::DenseEmbedding };
+        let echo = EchoProcessor::shared();
+        let mut registry = ProcessorRegistry::new().with_processor(echo.clone());
+        registry.register_for(vectors.clone(), echo.clone());
+        
+        assert!(Arc::ptr_eq(&registry.resolve(&text_task_type()).unwrap(), &echo));
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.processors().len(), 1);
+        
+        registry.unregister(&vectors);
+        match registry.resolve(&vectors) {
+            Err(WorkerError::NoProcessor { task_type, supported }) => {
+                assert_eq!((task_type, supported), (vectors, vec![text_task_type()]));
+            }
+            other => panic!("expected NoProcessor, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,570 @@
+//! Swarm Worker Implementation
+//!
+//! This module provides a concrete implementation of the Worker trait that
+//! routes each task to the processor its `ProcessorRegistry` holds for the
+//! task's type.
+
+use super::*;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream};
+use std::time::{Duration, Instant};
+use swarm_core::{BenchmarkReport, BuildInfo, CancellationToken, Heartbeat, ResultCache, SamplingConfig, TraceContext, TraceSampler, BENCHMARK_METADATA_KEY, BUILD_INFO_KEY};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+/// Worker that delegates tasks to registered task processors
+pub struct SwarmWorker {
+    config: WorkerConfig,
+    processors: ProcessorRegistry,
+    success_count: u32,
+    error_count: u32,
+    last_heartbeat: DateTime<Utc>,
+    
+    /// Status and load, shared with the heartbeat task and status observers
+    activity: watch::Sender<Activity>,
+    heartbeat_task: Option<(CancellationToken, JoinHandle<()>)>,
+    sampler: TraceSampler,
+    results: Option<ResultCache>,
+}
+
+/// What a worker is doing right now
+#[derive(Debug, Clone, PartialEq)]
+struct Activity {
+    status: WorkerStatus,
+    current_load: usize,
+}
+
+impl SwarmWorker {
+    /// Create a new worker with no processors
+    ///
+    /// The build the worker runs is added to its metadata under
+    /// [`BUILD_INFO_KEY`] unless the configuration already reports one.
+    pub fn new(mut config: WorkerConfig) -> Self {
+        if !config.metadata.contains_key(BUILD_INFO_KEY) {
+            BuildInfo::current()
+                .with_crate("swarm-worker", env!("CARGO_PKG_VERSION"))
+                .insert_into(&mut config.metadata);
+        }
+        Self {
+            config,
+            processors: ProcessorRegistry::new(),
+            success_count: 0,
+            error_count: 0,
+            last_heartbeat: Utc::now(),
+            activity: watch::Sender::new(Activity { status: WorkerStatus::Idle, current_load: 0 }),
+            heartbeat_task: None,
+            sampler: TraceSampler::default(),
+            results: None,
+        }
+    }
+    
+    /// Sample task tracing with the given configuration (propagated decisions are honoured)
+    pub fn with_tracing_sampling(mut self, config: SamplingConfig) -> Self {
+        self.sampler = TraceSampler::new(config);
+        self
+    }
+    
+    /// Answer redelivered tasks from the last `capacity` completed results
+    pub fn with_result_cache(mut self, capacity: usize) -> Self {
+        self.results = Some(ResultCache::new(capacity));
+        self
+    }
+    
+    /// Register a task processor
+    pub fn with_processor(mut self, processor: SharedTaskProcessor) -> Self {
+        self.processors.register(processor);
+        self
+    }
+    
+    /// Use the processors of a registry, e.g. one shared by a pool
+    pub fn with_registry(mut self, registry: ProcessorRegistry) -> Self {
+        self.processors = registry;
+        self
+    }
+    
+    /// Register a task processor on an existing worker
+    pub fn add_processor(&mut self, processor: SharedTaskProcessor) {
+        self.processors.register(processor);
+    }
+    
+    /// The processors tasks are routed to
+    pub fn processors(&self) -> &ProcessorRegistry {
+        &self.processors
+    }
+    
+    /// Worker configuration
+    pub fn config(&self) -> &WorkerConfig {
+        &self.config
+    }
+    
+    /// The current status, then every change of it until the worker is dropped
+    ///
+    /// Observers that fall behind skip to the latest status.
+    pub fn status_stream(&self) -> impl Stream<Item = WorkerStatus> + Send + 'static {
+        let receiver = self.activity.subscribe();
+        stream::unfold((receiver, None), |(mut receiver, mut last): (watch::Receiver<Activity>, Option<WorkerStatus>)| async move {
+            loop {
+                if last.is_some() && receiver.changed().await.is_err() {
+                    return None;
+                }
+                let status = receiver.borrow_and_update().status.clone();
+                if last.as_ref() != Some(&status) {
+                    last = Some(status.clone());
+                    return Some((status, (receiver, last)));
+                }
+            }
+        })
+    }
+    
+    /// Measure the registered processors and adopt the measured profiles
+    ///
+    /// Call before registering the worker: the measured profile replaces the
+    /// configured one, capabilities get profiles over their own task types,
+    /// and the full report is stored in the metadata under
+    /// [`BENCHMARK_METADATA_KEY`]. Profiles of unmeasured types are kept.
+    pub async fn run_startup_benchmark(&mut self, config: &StartupBenchmarkConfig) -> BenchmarkReport {
+        let report = run_benchmark(&self.processors.processors(), config).await;
+        if let Some(profile) = report.profile(&self.config.performance_profile) {
+            self.config.performance_profile = profile;
+        }
+        for capability in &mut self.config.capabilities {
+            if let Some(profile) = report.profile_for(&capability.supported_task_types, &capability.performance_profile) {
+                capability.performance_profile = profile;
+            }
+        }
+        match serde_json::to_value(&report) {
+            Ok(value) => {
+                self.config.metadata.insert(BENCHMARK_METADATA_KEY.to_string(), value);
+            }
+            Err(e) => tracing::warn!("Failed to record benchmark of worker {}: {}", self.config.name, e),
+        }
+        report
+    }
+    
+    /// Send a heartbeat every `interval` until the worker shuts down or the receiver is gone
+    ///
+    /// The coordinator marks a worker whose heartbeats stop as unavailable.
+    pub fn start_heartbeat(&mut self, sender: mpsc::UnboundedSender<Heartbeat>, interval: Duration) {
+        self.stop_heartbeat();
+        
+        let worker_id = self.config.id;
+        let activity = self.activity.subscribe();
+        let cancel = CancellationToken::new();
+        let stopped = cancel.clone();
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = stopped.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                let Activity { status, current_load } = activity.borrow().clone();
+                let heartbeat = Heartbeat { worker_id, status, current_load, sent_at: Utc::now() };
+                if sender.send(heartbeat).is_err() {
+                    tracing::debug!("Heartbeat receiver for worker {} is gone", worker_id);
+                    break;
+                }
+            }
+        });
+        self.heartbeat_task = Some((cancel, task));
+    }
+    
+    /// Stop sending heartbeats; the heartbeat task exits at its next wait
+    pub fn stop_heartbeat(&mut self) {
+        if let Some((cancel, _)) = self.heartbeat_task.take() {
+            cancel.cancel();
+        }
+    }
+    
+    fn set_activity(&self, status: WorkerStatus, current_load: usize) {
+        let next = Activity { status, current_load };
+        self.activity.send_if_modified(|activity| {
+            let changed = *activity != next;
+            *activity = next;
+            changed
+        });
+    }
+}
+
+#[async_trait]
+impl Worker for SwarmWorker {
+    fn id(&self) -> Uuid {
+        self.config.id
+    }
+    
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+    
+    fn worker_type(&self) -> WorkerType {
+        self.config.worker_type.clone()
+    }
+    
+    fn status(&self) -> WorkerStatus {
+        self.activity.borrow().status.clone()
+    }
+    
+    fn max_concurrent_tasks(&self) -> usize {
+        self.config.max_concurrent_tasks
+    }
+    
+    fn current_load(&self) -> usize {
+        self.activity.borrow().current_load
+    }
+    
+    fn capabilities(&self) -> &[WorkerCapability] {
+        &self.config.capabilities
+    }
+    
+    fn can_handle(&self, task_type: &TaskType) -> bool {
+        self.processors.supports(task_type)
+    }
+    
+    async fn process_task(&mut self, mut task: Task) -> Result<TaskResult> {
+        let processor = self.processors.resolve(&task.task_type)?;
+        if let Some(result) = self.results.as_mut().and_then(|cache| cache.replay(&task)) {
+            tracing::debug!("Worker {} answered task {} from its result cache", self.config.name, task.id);
+            return Ok(result);
+        }
+        
+        self.set_activity(WorkerStatus::Busy, self.current_load() + 1);
+        let start_time = Instant::now();
+        
+        let decision = self.sampler.sample_task(&task);
+        let (trace, span) = match TraceContext::from_task(&task) {
+            Some(parent) => {
+                let (context, span) = parent.with_decision(decision).continue_span("process", task.id);
+                context.record(&mut task.metadata);
+                (Some(context), span)
+            }
+            None => (None, self.sampler.span(decision, "process", task.id)),
+        };
+        let result = processor.process(&task)
+            .instrument(span)
+            .await;
+        
+        let current_load = self.current_load() - 1;
+        self.set_activity(if current_load == 0 { WorkerStatus::Idle } else { WorkerStatus::Busy }, current_load);
+        self.last_heartbeat = Utc::now();
+        
+        match result {
+            Ok(mut result) => {
+                self.success_count += 1;
+                if let Some(cache) = &mut self.results {
+                    cache.insert(&task, &result);
+                }
+                self.sampler.record_result(decision, &mut result);
+                // The coordinator continues the trace from the process span
+                if let Some(context) = trace {
+                    context.record(&mut result.metadata);
+                }
+                tracing::debug!("Worker {} completed task {} in {}ms",
+                    self.config.name, task.id, start_time.elapsed().as_millis());
+                Ok(result)
+            }
+            Err(e) => {
+                self.error_count += 1;
+                self.sampler.span(self.sampler.on_error(decision), "process", task.id).in_scope(|| {
+                    tracing::warn!("Worker {} failed task {}: {}", self.config.name, task.id, e);
+                });
+                Err(e)
+            }
+        }
+    }
+    
+    async fn health_check(&self) -> Result<WorkerHealth> {
+        Ok(WorkerHealth {
+            worker_id: self.config.id,
+            status: self.status(),
+            current_load: self.current_load(),
+            max_capacity: self.config.max_concurrent_tasks,
+            memory_usage_mb: 0,
+            cpu_usage_percent: 0.0,
+            last_heartbeat: self.last_heartbeat,
+            error_count: self.error_count,
+            success_count: self.success_count,
+        })
+    }
+    
+    async fn shutdown(&mut self) -> Result<()> {
+        if let Some((cancel, task)) = self.heartbeat_task.take() {
+            cancel.cancel();
+            let _ = task.await;
+        }
+        self.set_activity(WorkerStatus::Shutdown, self.current_load());
+        tracing::info!("Worker {} shut down", self.config.name);
+        Ok(())
+    }
+}
+
+impl Drop for SwarmWorker {
+    fn drop(&mut self) {
+        self.stop_heartbeat();
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use swarm_core::TextAnalysisType;
+    
+    pub(crate) fn text_task_type() -> TaskType {
+        TaskType::TextAnalysis { analysis_type: TextAnalysisType::KeywordExtraction }
+    }
+    
+    pub(crate) fn create_test_config(name: &str) -> WorkerConfig {
+        WorkerConfig {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            worker_type: WorkerType::TextAnalyzer {
+                supported_analyses: vec![TextAnalysisType::KeywordExtraction],
+            },
+            max_concurrent_tasks: 1,
+            capabilities: Vec::new(),
+            performance_profile: PerformanceProfile {
+                avg_processing_time_ms: 10,
+                memory_usage_mb: 16,
+                cpu_intensity: 0.1,
+                throughput_per_second: 100.0,
+            },
+            health_check_interval_ms: 1000,
+            shutdown_timeout_ms: 1000,
+            metadata: HashMap::new(),
+        }
+    }
+    
+    pub(crate) fn create_test_task(content: &str) -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            task_type: text_task_type(),
+            priority: TaskPriority::Normal,
+            status: TaskStatus::Pending,
+            payload: TaskPayload::Text {
+                content: content.to_string(),
+                analysis_options: Default::default(),
+            },
+            created_at: Utc::now(),
+            deadline: None,
+            retry_count: 0,
+            max_retries: 3,
+            metadata: HashMap::new(),
+        }
+    }
+    
+    /// Processor that completes tasks, fails on "fail" (text or document
+    /// filename) and panics on "panic"
+    pub(crate) struct EchoProcessor {
+        pub(crate) task_types: Vec<TaskType>,
+    }
+    
+    impl EchoProcessor {
+        pub(crate) fn shared() -> SharedTaskProcessor {
+            Arc::new(Self { task_types: vec![text_task_type()] })
+        }
+    }
+    
+    #[async_trait]
+    impl TaskProcessor for EchoProcessor {
+        async fn process(&self, task: &Task) -> Result<TaskResult> {
+            if let TaskPayload::Text { content, .. } = &task.payload {
+                match content.as_str() {
+                    "fail" => anyhow::bail!("processing failed"),
+                    "panic" => panic!("processor panicked"),
+                    _ => {}
+                }
+            }
+            if let TaskPayload::Document { document, .. } = &task.payload {
+                if document.filename == "fail" {
+                    anyhow::bail!("processing failed");
+                }
+            }
+            
+            Ok(TaskResult {
+                task_id: task.id,
+                status: TaskStatus::Completed,
+                result: None,
+                error: None,
+                processing_time_ms: 1,
+                completed_at: Utc::now(),
+                metadata: HashMap::new(),
+            })
+        }
+        
+        fn supported_task_types(&self) -> &[TaskType] {
+            &self.task_types
+        }
+        
+        fn estimate_processing_time(&self, _task: &Task) -> std::time::Duration {
+            std::time::Duration::from_millis(1)
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_process_task_delegates_to_processor() {
+        let mut worker = SwarmWorker::new(create_test_config("echo")).with_processor(EchoProcessor::shared());
+        let task = create_test_task("hello");
+        
+        let result = worker.process_task(task.clone()).await.unwrap();
+        assert_eq!(result.task_id, task.id);
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(worker.status(), WorkerStatus::Idle);
+        assert_eq!(worker.current_load(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_redelivered_task_is_answered_from_cache() {
+        let mut worker = SwarmWorker::new(create_test_config("echo"))
+            .with_processor(EchoProcessor::shared())
+            .with_result_cache(8);
+        let task = create_test_task("hello");
+        
+        let first = worker.process_task(task.clone()).await.unwrap();
+        let redelivered = worker.process_task(task.clone()).await.unwrap();
+        assert_eq!((redelivered.task_id, redelivered.completed_at), (first.task_id, first.completed_at));
+        assert_eq!(worker.health_check().await.unwrap().success_count, 1);
+        
+        assert!(worker.process_task(create_test_task("fail")).await.is_err());
+        assert!(worker.process_task(create_test_task("fail")).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_sampling_decision_is_recorded_on_results() {
+        use swarm_core::{SamplingDecision, TRACE_SAMPLED_KEY};
+        
+        let never = SamplingConfig { sample_percent: 0.0, ..Default::default() };
+        let mut worker = SwarmWorker::new(create_test_config("echo"))
+            .with_processor(EchoProcessor::shared())
+            .with_tracing_sampling(never);
+        
+        let result = worker.process_task(create_test_task("hello")).await.unwrap();
+        assert_eq!(result.metadata[TRACE_SAMPLED_KEY], "0");
+        
+        let mut sampled_upstream = create_test_task("hello");
+        SamplingDecision::Sampled.record(&mut sampled_upstream.metadata);
+        let result = worker.process_task(sampled_upstream).await.unwrap();
+        assert_eq!(result.metadata[TRACE_SAMPLED_KEY], "1");
+        
+        let mut critical = create_test_task("hello");
+        critical.priority = TaskPriority::Critical;
+        let result = worker.process_task(critical).await.unwrap();
+        assert_eq!(result.metadata[TRACE_SAMPLED_KEY], "1");
+    }
+    
+    #[tokio::test]
+    async fn test_startup_benchmark_updates_registered_profile() {
+        let mut config = create_test_config("echo");
+        config.performance_profile.avg_processing_time_ms = 5000;
+        config.capabilities.push(WorkerCapability {
+            name: "keywords".to_string(),
+            version: "1".to_string(),
+            supported_task_types: vec![text_task_type()],
+            max_concurrent_tasks: 1,
+            performance_profile: config.performance_profile.clone(),
+            metadata: HashMap::new(),
+        });
+        let mut worker = SwarmWorker::new(config).with_processor(EchoProcessor::shared());
+        
+        let report = worker.run_startup_benchmark(&StartupBenchmarkConfig::default()).await;
+        
+        assert_eq!(report.measured().count(), 1);
+        let registered = worker.config();
+        assert!(registered.performance_profile.avg_processing_time_ms < 5000);
+        assert!(registered.performance_profile.throughput_per_second > 0.0);
+        assert_eq!(registered.performance_profile.memory_usage_mb, 16);
+        assert_eq!(registered.capabilities[0].performance_profile, registered.performance_profile);
+        assert_eq!(registered.metadata[BENCHMARK_METADATA_KEY]["task_types"][0]["samples"], 3);
+    }
+    
+    #[tokio::test]
+    async fn test_unsupported_task_type() {
+        let mut worker = SwarmWorker::new(create_test_config("empty"));
+        let task = create_test_task("hello");
+        
+        assert!(!worker.can_handle(&task.task_type));
+        let error = worker.process_task(task).await.unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(WorkerError::NoProcessor { supported, .. }) if supported.is_empty()));
+    }
+    
+    #[tokio::test]
+    async fn test_health_counts_successes_and_errors() {
+        let mut worker = SwarmWorker::new(create_test_config("echo")).with_processor(EchoProcessor::shared());
+        worker.process_task(create_test_task("ok")).await.unwrap();
+        assert!(worker.process_task(create_test_task("fail")).await.is_err());
+        
+        let health = worker.health_check().await.unwrap();
+        assert_eq!(health.success_count, 1);
+        assert_eq!(health.error_count, 1);
+        
+        worker.shutdown().await.unwrap();
+        assert_eq!(worker.status(), WorkerStatus::Shutdown);
+    }
+    
+    /// Processor that holds every task until its gate is opened
+    struct GatedProcessor {
+        gate: Arc<tokio::sync::Notify>,
+        task_types: Vec<TaskType>,
+    }
+    
+    #[async_trait]
+    impl TaskProcessor for GatedProcessor {
+        async fn process(&self, task: &Task) -> Result<TaskResult> {
+            self.gate.notified().await;
+            EchoProcessor { task_types: Vec::new() }.process(task).await
+        }
+        
+        fn supported_task_types(&self) -> &[TaskType] {
+            &self.task_types
+        }
+        
+        fn estimate_processing_time(&self, _task: &Task) -> std::time::Duration {
+            std::time::Duration::from_millis(1)
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_status_stream_follows_status_changes() {
+        use futures::StreamExt;
+        
+        let gate = Arc::new(tokio::sync::Notify::new());
+        let processor = Arc::new(GatedProcessor { gate: gate.clone(), task_types: vec![text_task_type()] });
+        let mut worker = SwarmWorker::new(create_test_config("gated")).with_processor(processor);
+        let mut statuses = Box::pin(worker.status_stream());
+        assert_eq!(statuses.next().await, Some(WorkerStatus::Idle));
+        
+        let processing = tokio::spawn(async move {
+            worker.process_task(create_test_task("hello")).await.unwrap();
+            worker
+        });
+        assert_eq!(statuses.next().await, Some(WorkerStatus::Busy));
+        gate.notify_one();
+        let mut worker = processing.await.unwrap();
+        assert_eq!(statuses.next().await, Some(WorkerStatus::Idle));
+        assert_eq!(worker.status(), WorkerStatus::Idle);
+        
+        worker.shutdown().await.unwrap();
+        assert_eq!(statuses.next().await, Some(WorkerStatus::Shutdown));
+        drop(worker);
+        assert_eq!(statuses.next().await, None);
+    }
+    
+    #[tokio::test]
+    async fn test_heartbeats_until_shutdown() {
+        let mut worker = SwarmWorker::new(create_test_config("echo")).with_processor(EchoProcessor::shared());
+        let (sender, mut heartbeats) = mpsc::unbounded_channel();
+        worker.start_heartbeat(sender, Duration::from_millis(10));
+        
+        let heartbeat = heartbeats.recv().await.unwrap();
+        assert_eq!(heartbeat.worker_id, worker.id());
+        assert_eq!(heartbeat.status, WorkerStatus::Idle);
+        heartbeats.recv().await.unwrap();
+        
+        worker.shutdown().await.unwrap();
+        while heartbeats.try_recv().is_ok() {}
+        // The heartbeat task has exited and dropped its sender
+        assert!(heartbeats.try_recv().is_err_and(|e| e == mpsc::error::TryRecvError::Disconnected));
+    }
+}
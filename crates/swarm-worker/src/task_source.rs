@@ -0,0 +1,41 @@
+//! Task Sources
+//!
+//! A task source hands out tasks to the workers of a pool. Every worker pulls
+//! from the same source, so each task is delivered to exactly one worker.
+
+use super::*;
+use async_trait::async_trait;
+use swarm_core::DEFAULT_TASK_QUEUE_CAPACITY;
+use tokio::sync::{mpsc, Mutex};
+
+/// Shared source of tasks for a worker pool
+#[async_trait]
+pub trait TaskSource: Send + Sync {
+    /// Wait for the next task; `None` once the source is exhausted
+    async fn next_task(&self) -> Option<Task>;
+}
+
+/// Task source backed by a bounded channel
+pub struct ChannelTaskSource {
+    receiver: Mutex<mpsc::Receiver<Task>>,
+}
+
+impl ChannelTaskSource {
+    /// Create a source with the default capacity together with the sender used to submit tasks
+    pub fn channel() -> (mpsc::Sender<Task>, Arc<Self>) {
+        Self::bounded(DEFAULT_TASK_QUEUE_CAPACITY)
+    }
+    
+    /// Create a source holding at most `capacity` tasks; senders wait while it is full
+    pub fn bounded(capacity: usize) -> (mpsc::Sender<Task>, Arc<Self>) {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        (sender, Arc::new(Self { receiver: Mutex::new(receiver) }))
+    }
+}
+
+#[async_trait]
+impl TaskSource for ChannelTaskSource {
+    async fn next_task(&self) -> Option<Task> {
+        self.receiver.lock().await.recv().await
+    }
+}
@@ -0,0 +1,291 @@
+//! WASM Plugins
+//!
+//! Custom task processors can be shipped as WebAssembly modules and loaded
+//! at runtime, without rebuilding the worker. A plugin is registered under a
+//! name and version and handles `TaskType::Custom` tasks with the same name
+//! and version. Modules import nothing and export:
+//!
+//! - `memory`: the linear memory input and output live in
+//! - `alloc(len: i32) -> i32`: reserve `len` bytes for the input
+//! - `process(ptr: i32, len: i32) -> i64`: process the input, returning the
+//!   output location as `ptr << 32 | len`, or a negative error code
+//!
+//! The input is the data of a custom payload, or any other payload as JSON;
+//! the output becomes the task's custom result. Every task runs in a fresh
+//! instance with a fuel budget, so a plugin that loops forever fails its
+//! task instead of hanging the worker.
+
+use super::*;
+use anyhow::{anyhow, bail};
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use swarm_core::TaskResultData;
+use wasmtime::{Config, Engine, ExternType, Instance, Module, Store};
+
+/// Fuel a plugin may burn per task, roughly one unit per instruction
+pub const DEFAULT_PLUGIN_FUEL: u64 = 10_000_000_000;
+
+/// Result metadata key naming the plugin that processed a task
+pub const PLUGIN_KEY: &str = "plugin";
+
+/// Format of the custom results plugins produce
+pub const PLUGIN_RESULT_FORMAT: &str = "raw";
+
+/// A compiled plugin module
+#[derive(Clone)]
+pub struct WasmPlugin {
+    name: String,
+    version: String,
+    engine: Engine,
+    module: Module,
+    fuel: u64,
+    task_types: Vec<TaskType>,
+}
+
+impl WasmPlugin {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+    
+    /// `name@version`
+    pub fn id(&self) -> String {
+        format!("{}@{}", self.name, self.version)
+    }
+    
+    /// Run the plugin on `input` in a fresh instance
+    pub fn run(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut store = Store::new(&self.engine, ());
+        store.set_fuel(self.fuel)?;
+        let instance = Instance::new(&mut store, &self.module, &[])?;
+        let memory = instance.get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("plugin {} exports no memory", self.id()))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let process = instance.get_typed_func::<(i32, i32), i64>(&mut store, "process")?;
+        
+        let len = i32::try_from(input.len()).map_err(|_| anyhow!("input of {} bytes is too large", input.len()))?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, ptr as u32 as usize, input)?;
+        let packed = process.call(&mut store, (ptr, len))?;
+        if packed < 0 {
+            bail!("plugin {} failed with code {}", self.id(), packed);
+        }
+        
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        let mut output = vec![0; out_len];
+        memory.read(&store, out_ptr, &mut output)?;
+        Ok(output)
+    }
+}
+
+#[async_trait]
+impl TaskProcessor for WasmPlugin {
+    async fn process(&self, task: &Task) -> Result<TaskResult> {
+        let start_time = Instant::now();
+        let input = match &task.payload {
+            TaskPayload::Custom { data, .. } => data.clone(),
+            payload => serde_json::to_vec(payload)?,
+        };
+        
+        let plugin = self.clone();
+        let output = tokio::task::spawn_blocking(move || plugin.run(&input)).await??;
+        
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert(PLUGIN_KEY.to_string(), serde_json::Value::String(self.id()));
+        Ok(TaskResult {
+            task_id: task.id,
+            status: TaskStatus::Completed,
+            result: Some(TaskResultData::Custom { data: output, format: PLUGIN_RESULT_FORMAT.to_string() }),
+            error: None,
+            processing_time_ms: start_time.elapsed().as_millis() as u64,
+            completed_at: chrono::Utc::now(),
+            metadata,
+        })
+    }
+    
+    fn supported_task_types(&self) -> &[TaskType] {
+        &self.task_types
+    }
+    
+    fn estimate_processing_time(&self, _task: &Task) -> Duration {
+        Duration::from_millis(10)
+    }
+}
+
+/// Loads plugin modules and keeps them by name and version
+pub struct PluginHost {
+    engine: Engine,
+    fuel: u64,
+    plugins: BTreeMap<(String, String), Arc<WasmPlugin>>,
+}
+
+impl PluginHost {
+    pub fn new() -> WorkerResult<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(|e| WorkerError::Plugin(e.to_string()))?;
+        Ok(Self { engine, fuel: DEFAULT_PLUGIN_FUEL, plugins: BTreeMap::new() })
+    }
+    
+    /// Fuel budget of each task for plugins loaded from now on
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = fuel;
+        self
+    }
+    
+    /// Compile a module and register it, replacing a plugin of the same name and version
+    pub fn load(&mut self, name: &str, version: &str, wasm: &[u8]) -> WorkerResult<Arc<WasmPlugin>> {
+        let plugin_error = |reason: String| WorkerError::Plugin(format!("{}@{}: {}", name, version, reason));
+        let module = Module::new(&self.engine, wasm).map_err(|e| plugin_error(e.to_string()))?;
+        if module.imports().len() > 0 {
+            return Err(plugin_error("plugins must not import anything".to_string()));
+        }
+        for (export, is_memory) in [("memory", true), ("alloc", false), ("process", false)] {
+            match module.get_export(export) {
+                Some(ExternType::Memory(_)) if is_memory => {}
+                Some(ExternType::Func(_)) if !is_memory => {}
+                _ => return Err(plugin_error(format!("missing export `{}`", export))),
+            }
+        }
+        
+        let plugin = Arc::new(WasmPlugin {
+            name: name.to_string(),
+            version: version.to_string(),
+            engine: self.engine.clone(),
+            module,
+            fuel: self.fuel,
+            task_types: vec![TaskType::Custom { name: name.to_string(), version: version.to_string() }],
+        });
+        tracing::info!("Loaded plugin {}", plugin.id());
+        self.plugins.insert((name.to_string(), version.to_string()), plugin.clone());
+        Ok(plugin)
+    }
+    
+    /// Load a module from a file
+    pub fn load_file(&mut self, name: &str, version: &str, path: impl AsRef<Path>) -> WorkerResult<Arc<WasmPlugin>> {
+        let path = path.as_ref();
+        let wasm = std::fs::read(path)
+            .map_err(|e| WorkerError::Plugin(format!("failed to read {}: {}", path.display(), e)))?;
+        self.load(name, version, &wasm)
+    }
+    
+    /// Load every `<name>@<version>.wasm` file in a directory
+    ///
+    /// Files named otherwise are skipped; a module that fails to load fails the call.
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> WorkerResult<Vec<Arc<WasmPlugin>>> {
+        let dir = dir.as_ref();
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| WorkerError::Plugin(format!("failed to read {}: {}", dir.display(), e)))?;
+        let mut paths: Vec<_> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+        paths.sort();
+        
+        let mut loaded = Vec::new();
+        for path in paths {
+            if path.extension().is_none_or(|extension| extension != "wasm") {
+                continue;
+            }
+            let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+            match stem.rsplit_once('@') {
+                Some((name, version)) if !name.is_empty() && !version.is_empty() => {
+                    loaded.push(self.load_file(name, version, &path)?);
+                }
+                _ => tracing::warn!("Skipping {}: plugin files are named <name>@<version>.wasm", path.display()),
+            }
+        }
+        Ok(loaded)
+    }
+    
+    pub fn get(&self, name: &str, version: &str) -> Option<Arc<WasmPlugin>> {
+        self.plugins.get(&(name.to_string(), version.to_string())).cloned()
+    }
+    
+    pub fn unload(&mut self, name: &str, version: &str) -> Option<Arc<WasmPlugin>> {
+        self.plugins.remove(&(name.to_string(), version.to_string()))
+    }
+    
+    /// Loaded plugins ordered by name and version
+    pub fn plugins(&self) -> impl Iterator<Item = &Arc<WasmPlugin>> {
+        self.plugins.values()
+    }
+    
+    /// Loaded plugins as processors to register on a worker
+    pub fn processors(&self) -> Vec<SharedTaskProcessor> {
+        self.plugins.values().map(|plugin| plugin.clone() as SharedTaskProcessor).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swarm_worker::tests::create_test_config;
+    
+    const ECHO: &str = r#"(module
+        (memory (export "memory") 1)
+        (func (export "alloc") (param i32) (result i32) i32.const 1024)
+        (func (export "process") (param $ptr i32) (param $len i32) (result i64)
+            (i64.or
+                (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                (i64.extend_i32_u (local.get $len)))))"#;
+    
+    const SPIN: &str = r#"(module
+        (memory (export "memory") 1)
+        (func (export "alloc") (param i32) (result i32) i32.const 0)
+        (func (export "process") (param i32 i32) (result i64) (loop $spin (br $spin)) i64.const 0))"#;
+    
+    fn custom_task(name: &str, version: &str, data: &[u8]) -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            task_type: TaskType::Custom { name: name.to_string(), version: version.to_string() },
+            priority: TaskPriority::Normal,
+            status: TaskStatus::Pending,
+            payload: TaskPayload::Custom { data: data.to_vec(), format: "raw".to_string() },
+            created_at: chrono::Utc::now(),
+            deadline: None,
+            retry_count: 0,
+            max_retries: 0,
+            metadata: std::collections::HashMap::new(),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_worker_runs_loaded_plugin() {
+        let mut host = PluginHost::new().unwrap();
+        host.load("echo", "1.0", &wat::parse_str(ECHO).unwrap()).unwrap();
+        let mut worker = SwarmWorker::new(create_test_config("plugins"));
+        for processor in host.processors() {
+            worker.add_processor(processor);
+        }
+        
+        let result = worker.process_task(custom_task("echo", "1.0", b"hello")).await.unwrap();
+        assert_eq!(result.result, Some(TaskResultData::Custom { data: b"hello".to_vec(), format: "raw".to_string() }));
+        assert_eq!(result.metadata[PLUGIN_KEY], "echo@1.0");
+        assert!(worker.process_task(custom_task("echo", "2.0", b"hello")).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_runaway_plugin_runs_out_of_fuel() {
+        let mut host = PluginHost::new().unwrap().with_fuel(100_000);
+        let plugin = host.load("spin", "1", &wat::parse_str(SPIN).unwrap()).unwrap();
+        assert!(plugin.process(&custom_task("spin", "1", b"")).await.is_err());
+    }
+    
+    #[test]
+    fn test_modules_without_the_abi_are_rejected() {
+        let mut host = PluginHost::new().unwrap();
+        let no_alloc = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+        assert!(matches!(host.load("broken", "1", &no_alloc), Err(WorkerError::Plugin(_))));
+        assert!(host.load("garbage", "1", b"not wasm").is_err());
+        
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("echo@1.2.wasm"), wat::parse_str(ECHO).unwrap()).unwrap();
+        std::fs::write(dir.path().join("README.md"), "plugins").unwrap();
+        let loaded = host.load_dir(dir.path()).unwrap();
+        assert_eq!(loaded.iter().map(|plugin| plugin.id()).collect::<Vec<_>>(), vec!["echo@1.2"]);
+        assert!(host.get("echo", "1.2").is_some());
+    }
+}
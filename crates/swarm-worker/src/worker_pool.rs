@@ -0,0 +1,779 @@
+//! Worker Pool
+//!
+//! Runs a fixed number of workers inside one process, all pulling from a
+//! shared task source.
+
+use super::*;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use swarm_core::{CancellationToken, Service, Sli, SliRecorder};
+use tokio::sync::{mpsc, watch, RwLock};
+use tokio::task::JoinHandle;
+
+/// Factory creating the worker for a pool slot
+pub type WorkerFactory = Arc<dyn Fn(usize) -> Box<dyn Worker> + Send + Sync>;
+
+/// What to do when a worker panics
+///
+/// Either way, the task the worker was holding is reported as failed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Leave the slot empty
+    Never,
+    /// Replace the worker, up to `max_restarts` times per slot
+    OnPanic { max_restarts: u32, backoff_ms: u64 },
+}
+
+/// Worker pool configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerPoolConfig {
+    /// Pool name used in logs
+    pub name: String,
+    
+    /// Number of workers
+    pub size: usize,
+    
+    /// Restart policy for panicking workers
+    pub restart_policy: RestartPolicy,
+    
+    /// Time to wait for workers to finish on shutdown
+    pub shutdown_timeout_ms: u64,
+    
+    /// Drain the pool under memory pressure (disabled when unset)
+    ///
+    /// No tasks are handed out while the process is above the high watermark.
+    #[serde(default)]
+    pub memory_watermarks: Option<MemoryWatermarkConfig>,
+    
+    /// Results held before workers wait to hand in more
+    #[serde(default = "swarm_core::backpressure::default_result_queue_capacity")]
+    pub result_queue_capacity: usize,
+}
+
+impl Default for WorkerPoolConfig {
+    fn default() -> Self {
+        Self {
+            name: "worker-pool".to_string(),
+            size: 4,
+            restart_policy: RestartPolicy::OnPanic {
+                max_restarts: 5,
+                backoff_ms: 1000,
+            },
+            shutdown_timeout_ms: 30000,
+            memory_watermarks: None,
+            result_queue_capacity: swarm_core::DEFAULT_RESULT_QUEUE_CAPACITY,
+        }
+    }
+}
+
+/// Health of a single pool slot
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct WorkerSlotHealth {
+    /// Slot index
+    pub slot: usize,
+    
+    /// Whether a worker is currently running in this slot
+    pub alive: bool,
+    
+    /// Number of times the worker was replaced after a panic
+    pub restarts: u32,
+    
+    /// Tasks completed by this slot
+    pub tasks_completed: u64,
+    
+    /// Tasks failed by this slot (including tasks lost to panics)
+    pub tasks_failed: u64,
+    
+    /// Task currently being processed
+    pub current_task: Option<Uuid>,
+    
+    /// Latest health report from the worker
+    pub health: Option<WorkerHealth>,
+}
+
+/// Aggregated pool health
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PoolHealth {
+    pub pool_size: usize,
+    pub live_workers: usize,
+    pub total_restarts: u32,
+    pub current_load: usize,
+    pub max_capacity: usize,
+    pub tasks_completed: u64,
+    pub tasks_failed: u64,
+    #[serde(default)]
+    pub draining: bool,
+    /// Results waiting to be taken, out of `result_queue_capacity`
+    #[serde(default)]
+    pub result_queue_depth: usize,
+    pub workers: Vec<WorkerSlotHealth>,
+}
+
+impl PoolHealth {
+    /// All slots have a running worker
+    pub fn is_healthy(&self) -> bool {
+        self.live_workers == self.pool_size
+    }
+}
+
+type SlotStates = Arc<RwLock<Vec<WorkerSlotHealth>>>;
+
+/// Pool of workers sharing a task source
+pub struct WorkerPool {
+    config: WorkerPoolConfig,
+    factory: WorkerFactory,
+    source: Arc<dyn TaskSource>,
+    slots: SlotStates,
+    result_sender: mpsc::Sender<TaskResult>,
+    result_receiver: Option<mpsc::Receiver<TaskResult>>,
+    shutdown_sender: Option<watch::Sender<bool>>,
+    supervisors: Vec<JoinHandle<()>>,
+    memory_probe: Arc<dyn MemoryProbe>,
+    metrics: Option<Arc<dyn MetricsCollector>>,
+    draining: Arc<watch::Sender<bool>>,
+    memory_event_sender: mpsc::UnboundedSender<MemoryPressureEvent>,
+    memory_event_receiver: Option<mpsc::UnboundedReceiver<MemoryPressureEvent>>,
+    memory_monitor: Option<JoinHandle<()>>,
+    registration: Option<NetworkRegistration>,
+}
+
+impl WorkerPool {
+    /// Create a pool; workers are created by `factory` when the pool starts
+    pub fn new<F>(config: WorkerPoolConfig, factory: F, source: Arc<dyn TaskSource>) -> Self
+    where
+        F: Fn(usize) -> Box<dyn Worker> + Send + Sync + 'static,
+    {
+        let (result_sender, result_receiver) = mpsc::channel(config.result_queue_capacity.max(1));
+        let (memory_event_sender, memory_event_receiver) = mpsc::unbounded_channel();
+        let slots = (0..config.size)
+            .map(|slot| WorkerSlotHealth { slot, ..Default::default() })
+            .collect();
+        
+        Self {
+            config,
+            factory: Arc::new(factory),
+            source,
+            slots: Arc::new(RwLock::new(slots)),
+            result_sender,
+            result_receiver: Some(result_receiver),
+            shutdown_sender: None,
+            supervisors: Vec::new(),
+            memory_probe: Arc::new(ProcessMemoryProbe),
+            metrics: None,
+            draining: Arc::new(watch::channel(false).0),
+            memory_event_sender,
+            memory_event_receiver: Some(memory_event_receiver),
+            memory_monitor: None,
+            registration: None,
+        }
+    }
+    
+    /// Read process memory from a custom probe instead of `/proc`
+    pub fn with_memory_probe(mut self, probe: Arc<dyn MemoryProbe>) -> Self {
+        self.memory_probe = probe;
+        self
+    }
+    
+    /// Report processing durations, memory usage and drain transitions to a metrics collector
+    ///
+    /// Processing durations are recorded as an SLI.
+    pub fn with_metrics(mut self, metrics: Arc<dyn MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    /// Deregister from a remote coordinator when the pool shuts down
+    pub fn with_network_registration(mut self, registration: NetworkRegistration) -> Self {
+        self.registration = Some(registration);
+        self
+    }
+    
+    /// Take the receiver for task results (only available once)
+    ///
+    /// Workers stop once `result_queue_capacity` results are waiting, until
+    /// some are received.
+    pub fn take_results(&mut self) -> Option<mpsc::Receiver<TaskResult>> {
+        self.result_receiver.take()
+    }
+    
+    /// Take the receiver for memory pressure transitions (only available once)
+    pub fn take_memory_events(&mut self) -> Option<mpsc::UnboundedReceiver<MemoryPressureEvent>> {
+        self.memory_event_receiver.take()
+    }
+    
+    /// Whether the pool is refusing new tasks because of memory pressure
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+    
+    /// Whether the pool has been started and not shut down
+    pub fn is_running(&self) -> bool {
+        self.shutdown_sender.is_some()
+    }
+    
+    /// Start all workers
+    pub async fn start(&mut self) -> WorkerResult<()> {
+        if self.is_running() {
+            return Err(WorkerError::AlreadyRunning);
+        }
+        
+        let (shutdown_sender, shutdown_receiver) = watch::channel(false);
+        for slot in 0..self.config.size {
+            let supervisor = SlotSupervisor {
+                slot,
+                pool_name: self.config.name.clone(),
+                restart_policy: self.config.restart_policy.clone(),
+                factory: self.factory.clone(),
+                source: self.source.clone(),
+                slots: self.slots.clone(),
+                results: self.result_sender.clone(),
+                sli: SliRecorder::from_option(self.metrics.clone()),
+                draining: self.draining.subscribe(),
+                shutdown: shutdown_receiver.clone(),
+            };
+            self.supervisors.push(tokio::spawn(supervisor.run()));
+        }
+        
+        if let Some(config) = self.config.memory_watermarks.clone() {
+            let monitor = MemoryMonitorTask {
+                pool_name: self.config.name.clone(),
+                monitor: MemoryWatermarkMonitor::new(config),
+                probe: self.memory_probe.clone(),
+                metrics: self.metrics.clone(),
+                draining: self.draining.clone(),
+                events: self.memory_event_sender.clone(),
+                shutdown: shutdown_receiver,
+            };
+            self.memory_monitor = Some(tokio::spawn(monitor.run()));
+        }
+        self.shutdown_sender = Some(shutdown_sender);
+        
+        tracing::info!("Worker pool {} started with {} workers", self.config.name, self.config.size);
+        Ok(())
+    }
+    
+    /// Aggregate health across all slots
+    pub async fn health(&self) -> PoolHealth {
+        let workers = self.slots.read().await.clone();
+        PoolHealth {
+            pool_size: workers.len(),
+            live_workers: workers.iter().filter(|w| w.alive).count(),
+            total_restarts: workers.iter().map(|w| w.restarts).sum(),
+            current_load: workers.iter().filter(|w| w.current_task.is_some()).count(),
+            max_capacity: workers.iter().filter(|w| w.alive).filter_map(|w| w.health.as_ref()).map(|h| h.max_capacity).sum(),
+            tasks_completed: workers.iter().map(|w| w.tasks_completed).sum(),
+            tasks_failed: workers.iter().map(|w| w.tasks_failed).sum(),
+            draining: self.is_draining(),
+            result_queue_depth: self.result_sender.max_capacity() - self.result_sender.capacity(),
+            workers,
+        }
+    }
+    
+    /// Signal all workers to stop and wait for them to finish their current task
+    pub async fn shutdown(&mut self) -> WorkerResult<()> {
+        let shutdown_sender = self.shutdown_sender.take().ok_or(WorkerError::NotRunning)?;
+        let _ = shutdown_sender.send(true);
+        if let Some(monitor) = self.memory_monitor.take() {
+            monitor.abort();
+        }
+        self.draining.send_replace(false);
+        
+        let timeout = Duration::from_millis(self.config.shutdown_timeout_ms);
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut timed_out = false;
+        
+        for mut supervisor in self.supervisors.drain(..) {
+            if tokio::time::timeout_at(deadline, &mut supervisor).await.is_err() {
+                supervisor.abort();
+                timed_out = true;
+            }
+        }
+        
+        if let Some(registration) = &self.registration {
+            if let Err(e) = registration.deregister("worker pool shut down").await {
+                tracing::warn!("Worker pool {} failed to deregister: {}", self.config.name, e);
+            }
+        }
+        
+        if timed_out {
+            tracing::warn!("Worker pool {} did not stop within {}ms", self.config.name, self.config.shutdown_timeout_ms);
+            return Err(WorkerError::ShutdownTimeout { timeout_ms: self.config.shutdown_timeout_ms });
+        }
+        
+        tracing::info!("Worker pool {} shut down", self.config.name);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Service for WorkerPool {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+    
+    /// Start the pool, and shut it down gracefully once cancelled
+    async fn run(&mut self, cancel: CancellationToken) -> Result<()> {
+        self.start().await?;
+        cancel.cancelled().await;
+        Ok(self.shutdown().await?)
+    }
+}
+
+/// Keeps one pool slot populated with a worker
+struct SlotSupervisor {
+    slot: usize,
+    pool_name: String,
+    restart_policy: RestartPolicy,
+    factory: WorkerFactory,
+    source: Arc<dyn TaskSource>,
+    slots: SlotStates,
+    results: mpsc::Sender<TaskResult>,
+    sli: SliRecorder,
+    draining: watch::Receiver<bool>,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl SlotSupervisor {
+    async fn run(self) {
+        let mut restarts = 0;
+        
+        loop {
+            let worker = (self.factory)(self.slot);
+            self.slots.write().await[self.slot].alive = true;
+            
+            let run = tokio::spawn(run_worker(
+                self.slot,
+                worker,
+                self.source.clone(),
+                self.slots.clone(),
+                ResultReporter { pool_name: self.pool_name.clone(), results: self.results.clone(), sli: self.sli.clone() },
+                self.draining.clone(),
+                self.shutdown.clone(),
+            ));
+            let outcome = run.await;
+            
+            let lost_task = {
+                let mut slots = self.slots.write().await;
+                let state = &mut slots[self.slot];
+                state.alive = false;
+                state.current_task.take()
+            };
+            
+            let error = match outcome {
+                Ok(()) => return,
+                Err(e) if e.is_panic() => e,
+                Err(_) => return,
+            };
+            
+            tracing::error!("Worker in pool {} slot {} panicked: {}", self.pool_name, self.slot, error);
+            if let Some(task_id) = lost_task {
+                self.slots.write().await[self.slot].tasks_failed += 1;
+                let _ = self.results.send(failed_task_result(task_id, "worker panicked", 0)).await;
+            }
+            
+            let backoff_ms = match &self.restart_policy {
+                RestartPolicy::OnPanic { max_restarts, backoff_ms } if restarts < *max_restarts => *backoff_ms,
+                _ => {
+                    tracing::error!("Worker pool {} slot {} will not be restarted", self.pool_name, self.slot);
+                    return;
+                }
+            };
+            
+            restarts += 1;
+            self.slots.write().await[self.slot].restarts = restarts;
+            
+            let mut shutdown = self.shutdown.clone();
+            if *shutdown.borrow() {
+                return;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(backoff_ms)) => {}
+                _ = shutdown.changed() => return,
+            }
+            
+            tracing::info!("Restarting worker in pool {} slot {} (restart {})", self.pool_name, self.slot, restarts);
+        }
+    }
+}
+
+/// Samples process memory and toggles draining at the watermarks
+struct MemoryMonitorTask {
+    pool_name: String,
+    monitor: MemoryWatermarkMonitor,
+    probe: Arc<dyn MemoryProbe>,
+    metrics: Option<Arc<dyn MetricsCollector>>,
+    draining: Arc<watch::Sender<bool>>,
+    events: mpsc::UnboundedSender<MemoryPressureEvent>,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl MemoryMonitorTask {
+    async fn run(mut self) {
+        let period = Duration::from_millis(self.monitor.config().check_interval_ms.max(1));
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = self.shutdown.changed() => return,
+            }
+            
+            let Some(resident_bytes) = self.probe.resident_bytes() else { continue };
+            let tags = [("pool", self.pool_name.as_str())];
+            if let Some(metrics) = &self.metrics {
+                metrics.record_metric("worker.memory.resident_mb", (resident_bytes / (1024 * 1024)) as f64, &tags);
+            }
+            
+            let Some(event) = self.monitor.observe(resident_bytes) else { continue };
+            match &event {
+                MemoryPressureEvent::Draining { resident_mb } => {
+                    tracing::warn!("Worker pool {} draining at {}MB resident memory", self.pool_name, resident_mb);
+                    if let Some(metrics) = &self.metrics {
+                        metrics.increment_counter("worker.memory.drain_started", &tags);
+                    }
+                }
+                MemoryPressureEvent::Resumed { resident_mb } => {
+                    tracing::info!("Worker pool {} resumed at {}MB resident memory", self.pool_name, resident_mb);
+                    if let Some(metrics) = &self.metrics {
+                        metrics.increment_counter("worker.memory.drain_resumed", &tags);
+                    }
+                }
+            }
+            self.draining.send_replace(self.monitor.is_draining());
+            let _ = self.events.send(event);
+        }
+    }
+}
+
+/// Hands task results back to the pool and records how long they took
+struct ResultReporter {
+    pool_name: String,
+    results: mpsc::Sender<TaskResult>,
+    sli: SliRecorder,
+}
+
+impl ResultReporter {
+    async fn report(&self, task_type: &TaskType, result: TaskResult, start_time: Instant) {
+        let status = format!("{:?}", result.status).to_lowercase();
+        self.sli.observe_elapsed(Sli::ProcessingDuration, start_time,
+            &[("pool", &self.pool_name), ("task_type", &task_type.to_string()), ("status", &status)]);
+        
+        let task_id = result.task_id;
+        if self.results.send(result).await.is_err() {
+            tracing::debug!("Result receiver dropped, discarding result for task {}", task_id);
+        }
+    }
+}
+
+/// Pull tasks from the source until shutdown or the source is exhausted
+async fn run_worker(
+    slot: usize,
+    mut worker: Box<dyn Worker>,
+    source: Arc<dyn TaskSource>,
+    slots: SlotStates,
+    results: ResultReporter,
+    mut draining: watch::Receiver<bool>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    record_health(slot, worker.as_ref(), &slots).await;
+    
+    loop {
+        if *shutdown.borrow() {
+            break;
+        }
+        
+        if *draining.borrow_and_update() {
+            tokio::select! {
+                biased;
+                _ = shutdown.changed() => break,
+                _ = draining.wait_for(|draining| !*draining) => continue,
+            }
+        }
+        
+        let task = tokio::select! {
+            biased;
+            _ = shutdown.changed() => break,
+            _ = draining.changed() => continue,
+            task = source.next_task() => task,
+        };
+        let Some(task) = task else { break };
+        
+        let task_id = task.id;
+        let task_type = task.task_type.clone();
+        let document_id = task.document_id();
+        slots.write().await[slot].current_task = Some(task_id);
+        let start_time = Instant::now();
+        
+        let mut result = match worker.process_task(task).await {
+            Ok(result) => {
+                slots.write().await[slot].tasks_completed += 1;
+                result
+            }
+            Err(e) => {
+                slots.write().await[slot].tasks_failed += 1;
+                failed_task_result(task_id, e, start_time.elapsed().as_millis() as u64)
+            }
+        };
+        // Failed results carry no document, so consumers match on the id
+        if let Some(document_id) = document_id {
+            result.record_document_id(document_id);
+        }
+        
+        slots.write().await[slot].current_task = None;
+        record_health(slot, worker.as_ref(), &slots).await;
+        
+        // A full result channel holds the worker back, unless the pool shuts down
+        tokio::select! {
+            biased;
+            _ = results.report(&task_type, result, start_time) => {}
+            _ = shutdown.changed() => {
+                tracing::warn!("Worker pool {} shut down with a full result channel, discarding result for task {}", results.pool_name, task_id);
+                break;
+            }
+        }
+    }
+    
+    if let Err(e) = worker.shutdown().await {
+        tracing::warn!("Worker {} failed to shut down cleanly: {}", worker.name(), e);
+    }
+    record_health(slot, worker.as_ref(), &slots).await;
+}
+
+async fn record_health(slot: usize, worker: &dyn Worker, slots: &SlotStates) {
+    match worker.health_check().await {
+        Ok(health) => slots.write().await[slot].health = Some(health),
+        Err(e) => tracing::warn!("Health check failed for worker {}: {}", worker.name(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swarm_worker::tests::{create_test_config, create_test_task, EchoProcessor};
+    
+    fn create_test_pool(size: usize, restart_policy: RestartPolicy) -> (mpsc::Sender<Task>, WorkerPool) {
+        let (sender, source) = ChannelTaskSource::channel();
+        let config = WorkerPoolConfig {
+            name: "test-pool".to_string(),
+            size,
+            restart_policy,
+            shutdown_timeout_ms: 1000,
+            memory_watermarks: None,
+            result_queue_capacity: 16,
+        };
+        let pool = WorkerPool::new(config, |slot| {
+            Box::new(SwarmWorker::new(create_test_config(&format!("worker-{}", slot)))
+                .with_processor(EchoProcessor::shared())) as Box<dyn Worker>
+        }, source);
+        (sender, pool)
+    }
+    
+    #[tokio::test]
+    async fn test_pool_processes_tasks() {
+        let metrics = Arc::new(swarm_core::MemoryMetricsCollector::new());
+        let (sender, pool) = create_test_pool(3, RestartPolicy::Never);
+        let mut pool = pool.with_metrics(metrics.clone());
+        let mut results = pool.take_results().unwrap();
+        pool.start().await.unwrap();
+        
+        for i in 0..10 {
+            sender.send(create_test_task(&format!("task {}", i))).await.unwrap();
+        }
+        sender.send(create_test_task("fail")).await.unwrap();
+        
+        let mut completed = 0;
+        let mut failed = 0;
+        for _ in 0..11 {
+            match results.recv().await.unwrap().status {
+                TaskStatus::Completed => completed += 1,
+                _ => failed += 1,
+            }
+        }
+        assert_eq!((completed, failed), (10, 1));
+        assert_eq!(metrics.histogram(Sli::ProcessingDuration.name()).len(), 11);
+        assert_eq!(metrics.counter(swarm_core::TASKS_PROCESSED_COUNTER), 11);
+        
+        let health = pool.health().await;
+        assert!(health.is_healthy());
+        assert_eq!(health.tasks_completed, 10);
+        assert_eq!(health.tasks_failed, 1);
+        
+        pool.shutdown().await.unwrap();
+        assert_eq!(pool.health().await.live_workers, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_failed_results_name_their_document() {
+        let (sender, mut pool) = create_test_pool(1, RestartPolicy::Never);
+        let mut results = pool.take_results().unwrap();
+        pool.start().await.unwrap();
+        
+        let document = swarm_core::DocumentBuilder::new("fail", DocumentContent::Text("unreadable".to_string())).build();
+        let task = Task {
+            payload: TaskPayload::Document { document: document.clone(), processing_options: Default::default() },
+            ..create_test_task("document")
+        };
+        sender.send(task.clone()).await.unwrap();
+        let result = results.recv().await.unwrap();
+        assert_eq!((result.task_id, result.status.clone()), (task.id, TaskStatus::Failed));
+        assert_eq!(result.document_id(), Some(document.id));
+        
+        sender.send(create_test_task("text")).await.unwrap();
+        assert_eq!(results.recv().await.unwrap().document_id(), None);
+        
+        pool.shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_pool_restarts_panicked_worker() {
+        let policy = RestartPolicy::OnPanic { max_restarts: 2, backoff_ms: 1 };
+        let (sender, mut pool) = create_test_pool(1, policy);
+        let mut results = pool.take_results().unwrap();
+        pool.start().await.unwrap();
+        
+        let panicking = create_test_task("panic");
+        sender.send(panicking.clone()).await.unwrap();
+        let result = results.recv().await.unwrap();
+        assert_eq!(result.task_id, panicking.id);
+        assert_eq!(result.status, TaskStatus::Failed);
+        
+        sender.send(create_test_task("after restart")).await.unwrap();
+        assert_eq!(results.recv().await.unwrap().status, TaskStatus::Completed);
+        
+        let health = pool.health().await;
+        assert_eq!(health.total_restarts, 1);
+        assert!(health.is_healthy());
+        
+        pool.shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_pool_without_restart_loses_slot() {
+        let (sender, mut pool) = create_test_pool(2, RestartPolicy::Never);
+        let mut results = pool.take_results().unwrap();
+        pool.start().await.unwrap();
+        
+        sender.send(create_test_task("panic")).await.unwrap();
+        assert_eq!(results.recv().await.unwrap().status, TaskStatus::Failed);
+        
+        let health = pool.health().await;
+        assert_eq!(health.live_workers, 1);
+        assert!(!health.is_healthy());
+        
+        pool.shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_pool_start_and_shutdown_state() {
+        let (_sender, mut pool) = create_test_pool(1, RestartPolicy::Never);
+        assert!(matches!(pool.shutdown().await, Err(WorkerError::NotRunning)));
+        
+        pool.start().await.unwrap();
+        assert!(matches!(pool.start().await, Err(WorkerError::AlreadyRunning)));
+        
+        pool.shutdown().await.unwrap();
+        assert!(!pool.is_running());
+    }
+    
+    #[tokio::test]
+    async fn test_run_shuts_down_when_cancelled() {
+        let (sender, mut pool) = create_test_pool(2, RestartPolicy::Never);
+        let mut results = pool.take_results().unwrap();
+        let cancel = CancellationToken::new();
+        let token = cancel.clone();
+        let run = tokio::spawn(async move { Service::run(&mut pool, token).await.map(|_| pool) });
+        
+        sender.send(create_test_task("before cancel")).await.unwrap();
+        assert_eq!(results.recv().await.unwrap().status, TaskStatus::Completed);
+        cancel.cancel();
+        
+        let pool = run.await.unwrap().unwrap();
+        assert!(!pool.is_running());
+        assert_eq!(pool.health().await.live_workers, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_full_result_channel_holds_workers_back() {
+        let (sender, source) = ChannelTaskSource::bounded(1);
+        let config = WorkerPoolConfig {
+            name: "test-pool".to_string(),
+            size: 1,
+            restart_policy: RestartPolicy::Never,
+            shutdown_timeout_ms: 1000,
+            memory_watermarks: None,
+            result_queue_capacity: 1,
+        };
+        let mut pool = WorkerPool::new(config, |slot| {
+            Box::new(SwarmWorker::new(create_test_config(&format!("worker-{}", slot)))
+                .with_processor(EchoProcessor::shared())) as Box<dyn Worker>
+        }, source);
+        let mut results = pool.take_results().unwrap();
+        pool.start().await.unwrap();
+        
+        // One result waits in the channel, one in the worker and one in the task source
+        for i in 0..3 {
+            sender.send(create_test_task(&format!("task {}", i))).await.unwrap();
+        }
+        let blocked = tokio::time::timeout(Duration::from_millis(50), sender.send(create_test_task("blocked"))).await;
+        assert!(blocked.is_err());
+        let health = pool.health().await;
+        assert_eq!((health.result_queue_depth, health.tasks_completed), (1, 2));
+        
+        for _ in 0..3 {
+            assert_eq!(results.recv().await.unwrap().status, TaskStatus::Completed);
+        }
+        pool.shutdown().await.unwrap();
+    }
+    
+    struct FixedMemoryProbe(std::sync::atomic::AtomicU64);
+    
+    impl MemoryProbe for FixedMemoryProbe {
+        fn resident_bytes(&self) -> Option<u64> {
+            Some(self.0.load(std::sync::atomic::Ordering::SeqCst) * 1024 * 1024)
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_pool_drains_under_memory_pressure() {
+        let (sender, source) = ChannelTaskSource::channel();
+        let config = WorkerPoolConfig {
+            name: "test-pool".to_string(),
+            size: 2,
+            restart_policy: RestartPolicy::Never,
+            shutdown_timeout_ms: 1000,
+            memory_watermarks: Some(MemoryWatermarkConfig {
+                low_watermark_mb: 100,
+                high_watermark_mb: 200,
+                check_interval_ms: 5,
+            }),
+            result_queue_capacity: 16,
+        };
+        let probe = Arc::new(FixedMemoryProbe(std::sync::atomic::AtomicU64::new(250)));
+        let mut pool = WorkerPool::new(config, |slot| {
+            Box::new(SwarmWorker::new(create_test_config(&format!("worker-{}", slot)))
+                .with_processor(EchoProcessor::shared())) as Box<dyn Worker>
+        }, source).with_memory_probe(probe.clone());
+        let mut results = pool.take_results().unwrap();
+        let mut events = pool.take_memory_events().unwrap();
+        pool.start().await.unwrap();
+        
+        assert_eq!(events.recv().await.unwrap(), MemoryPressureEvent::Draining { resident_mb: 250 });
+        assert!(pool.health().await.draining);
+        
+        sender.send(create_test_task("held back")).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(50), results.recv()).await.is_err());
+        
+        // Between the watermarks the pool stays drained
+        probe.0.store(150, std::sync::atomic::Ordering::SeqCst);
+        assert!(tokio::time::timeout(Duration::from_millis(50), results.recv()).await.is_err());
+        
+        probe.0.store(50, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(events.recv().await.unwrap(), MemoryPressureEvent::Resumed { resident_mb: 50 });
+        assert_eq!(results.recv().await.unwrap().status, TaskStatus::Completed);
+        assert!(!pool.is_draining());
+        
+        pool.shutdown().await.unwrap();
+    }
+}
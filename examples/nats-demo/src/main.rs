@@ -9,7 +9,7 @@
 
 use swarm_core::prelude::*;
 use swarm_documents::{SwarmDocumentProcessor, DocumentProcessingConfig};
-use swarm_comms::{NatsBroker, NatsConfig, MessageSerializer, MessageValidator};
+use swarm_comms::{NatsBroker, NatsConfig, MessageRouter, MessageRoutingConfig, MessageSerializer, MessageValidator};
 use anyhow::Result;
 use std::collections::HashMap;
 use tokio::time::{sleep, Duration};
@@ -36,11 +36,19 @@ async fn main() -> Result<()> {
         connection_timeout_ms: 5000,
         max_reconnect_attempts: 5,
         reconnect_delay_ms: 1000,
+        max_reconnect_delay_ms: 30_000,
         max_message_size: 1024 * 1024, // 1MB
         enable_tls: false,
         tls_cert_path: None,
         tls_key_path: None,
         tls_ca_path: None,
+        publisher: Default::default(),
+        batch_mode: Default::default(),
+        tracing_sampling: Default::default(),
+        credentials: None,
+        permissions: None,
+        expired_messages: Default::default(),
+        subscription_liveness: Default::default(),
     };
     
     println!("📡 NATS Config:");
@@ -120,7 +128,8 @@ async fn run_nats_demo(broker: NatsBroker) -> Result<()> {
     println!();
     
     // Publish to NATS
-    broker.publish_message(&message.subject, &message).await?;
+    let router = MessageRouter::new(MessageRoutingConfig::default());
+    broker.publish_message(router.document_incoming_subject(), &message).await?;
     println!("✅ Document published to NATS successfully!");
     println!();
     
@@ -128,8 +137,8 @@ async fn run_nats_demo(broker: NatsBroker) -> Result<()> {
     println!("📥 Step 4: Subscribing to Messages");
     println!("─────────────────────────────────────────────");
     
-    let mut receiver = broker.subscribe_to_subject("swarm.documents.incoming").await?;
-    println!("✅ Subscribed to swarm.documents.incoming");
+    let mut receiver = broker.subscribe_to_subject(router.document_incoming_subject()).await?;
+    println!("✅ Subscribed to {}", router.document_incoming_subject());
     println!();
     
     // Step 5: Process received message
@@ -189,6 +198,11 @@ async fn run_nats_demo(broker: NatsBroker) -> Result<()> {
     println!("   Messages received: {}", stats.messages_received);
     println!("   Active subscriptions: {}", stats.active_subscriptions);
     println!("   Connection status: {}", if stats.is_connected { "Connected" } else { "Disconnected" });
+    for (subject, subject_stats) in &stats.subjects {
+        println!("   {}: sent {}, received {}, pending {}, lag {}ms",
+            subject, subject_stats.messages_sent, subject_stats.messages_received,
+            subject_stats.pending, subject_stats.last_lag_ms);
+    }
     println!();
     
     println!("🎉 NATS Demo Complete!");
@@ -3,7 +3,7 @@
 //! Publishes documents to NATS for processing by workers.
 
 use swarm_core::prelude::*;
-use swarm_comms::{NatsBroker, NatsConfig, MessageSerializer};
+use swarm_comms::{NatsBroker, NatsConfig, MessageRouter, MessageRoutingConfig, MessageSerializer};
 use anyhow::Result;
 use std::collections::HashMap;
 use tokio::time::{sleep, Duration};
@@ -20,10 +20,11 @@ async fn main() -> Result<()> {
     // Connect to NATS
     let config = NatsConfig::default();
     let broker = NatsBroker::new(config).await?;
+    let router = MessageRouter::new(MessageRoutingConfig::default());
     println!("✅ Connected to NATS");
     
     // Create test documents
-    let documents = vec![
+    let documents = [
         create_document("report.pdf", DocumentType::Pdf, "PDF content here"),
         create_document("notes.txt", DocumentType::Text, "This is a text document with some content"),
         create_document("data.docx", DocumentType::Word, "Word document content"),
@@ -33,7 +34,7 @@ async fn main() -> Result<()> {
     
     for (i, document) in documents.iter().enumerate() {
         let message = MessageSerializer::serialize_document(document)?;
-        broker.publish_message("swarm.documents.incoming", &message).await?;
+        broker.publish_message(router.document_incoming_subject(), &message).await?;
         
         println!("✅ Published document {}: {}", i + 1, document.filename);
         sleep(Duration::from_millis(500)).await;
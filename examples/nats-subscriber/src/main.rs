@@ -4,7 +4,7 @@
 
 use swarm_core::prelude::*;
 use swarm_core::TaskResultData;
-use swarm_comms::{NatsBroker, NatsConfig, MessageSerializer};
+use swarm_comms::{NatsBroker, NatsConfig, MessageSerializer, MessageRouter, MessageRoutingConfig};
 use swarm_documents::{SwarmDocumentProcessor, DocumentProcessingConfig};
 use anyhow::Result;
 use tokio::time::{sleep, Duration};
@@ -28,9 +28,10 @@ async fn main() -> Result<()> {
     let processor = SwarmDocumentProcessor::new(processor_config);
     println!("✅ Document processor ready");
     
-    // Subscribe to documents
-    let mut receiver = broker.subscribe_to_subject("swarm.documents.incoming").await?;
-    println!("✅ Subscribed to swarm.documents.incoming");
+    // Subscribe to documents, sharing them with other subscribers in the worker queue group
+    let router = MessageRouter::new(MessageRoutingConfig::default());
+    let mut receiver = broker.subscribe_queue(router.document_incoming_subject(), router.document_worker_queue()).await?;
+    println!("✅ Subscribed to {} (queue group: {})", router.document_incoming_subject(), router.document_worker_queue());
     println!("🔄 Waiting for documents...");
     
     let mut processed_count = 0;
@@ -63,8 +64,8 @@ async fn main() -> Result<()> {
                 metadata: std::collections::HashMap::new(),
             })?;
             
-            broker.publish_message("swarm.documents.results", &result_message).await?;
-            println!("   📤 Published result to swarm.documents.results");
+            broker.publish_message(router.document_results_subject(), &result_message).await?;
+            println!("   📤 Published result to {}", router.document_results_subject());
             println!();
         }
         
@@ -9,6 +9,7 @@ use swarm_core::prelude::*;
 use swarm_documents::{SwarmDocumentProcessor, SwarmDocumentReader, DocumentProcessingConfig};
 use swarm_documents::document_reader::DocumentReaderConfig;
 use anyhow::Result;
+use futures::StreamExt;
 use std::path::PathBuf;
 use tracing::Level;
 
@@ -84,6 +85,8 @@ async fn main() -> Result<()> {
         recursive_scan: false,
         include_patterns: vec!["*".to_string()],
         exclude_patterns: vec![".*".to_string()],
+        id_strategy: DocumentIdStrategy::Random,
+        ..Default::default()
     };
     
     let mut reader = SwarmDocumentReader::new(reader_config);
@@ -97,8 +100,15 @@ async fn main() -> Result<()> {
     println!("📖 Step 5: Testing Document Reading");
     println!("─────────────────────────────────────────────");
     
-    // Try to read a document
-    if let Some(doc) = reader.get_next_document().await? {
+    // Try to read a document from the reader's stream
+    let first_document = {
+        let mut documents = Box::pin(reader.stream());
+        tokio::time::timeout(std::time::Duration::from_millis(500), documents.next()).await
+            .ok()
+            .flatten()
+            .transpose()?
+    };
+    if let Some(doc) = first_document {
         println!("✅ Document read successfully!");
         println!("📄 Filename: {}", doc.filename);
         println!("📊 Document type: {:?}", doc.document_type);
@@ -0,0 +1,241 @@
+//! Single-Node Swarm
+//!
+//! Runs the coordinator, a document reader and a worker pool in one process
+//! under a supervisor. All components are configured from one JSON file and
+//! talk through the supervisor's in-memory broker: the reader publishes the
+//! documents it finds, the workers process them and publish the results.
+//! The coordinator returns as soon as it has no workers or tasks, which the
+//! supervisor records as a clean stop rather than a failure.
+//!
+//! Usage: `cargo run -p single-node -- [config.json]`
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use swarm_core::prelude::*;
+use swarm_core::{CancellationToken, ComponentContext, DocumentProcessingOptions, DocumentProcessingType, MessageSubscription, Service, Supervisor, SupervisorConfig, TaskResultData};
+use swarm_documents::document_reader::DocumentReaderConfig;
+use swarm_documents::{DocumentProcessingConfig, SwarmDocumentProcessor, SwarmDocumentReader};
+use swarm_worker::{SharedTaskProcessor, SwarmWorker, TaskSource, WorkerPool, WorkerPoolConfig};
+use tokio::sync::{mpsc, Mutex};
+use tracing::Level;
+use uuid::Uuid;
+
+const DEFAULT_CONFIG: &str = "examples/single-node/single-node.json";
+const DOCUMENTS_SUBJECT: &str = "documents.discovered";
+const RESULTS_SUBJECT: &str = "results.completed";
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_max_level(Level::INFO)
+        .init();
+    
+    let path = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_CONFIG.to_string());
+    let config = SupervisorConfig::from_file(&path)?;
+    tracing::info!("Starting single-node swarm from {}", path);
+    
+    let mut supervisor = Supervisor::new(config)
+        .with_factory("coordinator", |_| Ok(Box::new(SwarmCoordinator::new()) as Box<dyn Service>))
+        .with_factory("workers", worker_pool)
+        .with_factory("reader", |context| {
+            let config: DocumentReaderConfig = context.settings()?;
+            let reader = SwarmDocumentReader::new(config).with_broker(context.broker.clone(), DOCUMENTS_SUBJECT);
+            Ok(Box::new(reader) as Box<dyn Service>)
+        });
+    
+    let cancel = CancellationToken::new();
+    let stopper = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            tracing::info!("Shutting down...");
+            stopper.cancel();
+        }
+    });
+    
+    supervisor.run(cancel).await
+}
+
+/// Settings of the `workers` component
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct WorkerSettings {
+    pool: WorkerPoolConfig,
+    processing: DocumentProcessingConfig,
+}
+
+/// Worker pool processing the documents published by the reader
+fn worker_pool(context: &ComponentContext) -> Result<Box<dyn Service>> {
+    let settings: WorkerSettings = context.settings()?;
+    let processor: SharedTaskProcessor = Arc::new(DocumentTaskProcessor::new(settings.processing));
+    let source = Arc::new(BrokerTaskSource::new(context.broker.clone(), DOCUMENTS_SUBJECT));
+    let mut pool = WorkerPool::new(settings.pool, move |slot| {
+        Box::new(SwarmWorker::new(worker_config(slot)).with_processor(processor.clone())) as Box<dyn Worker>
+    }, source);
+    
+    let results = pool.take_results().expect("results are taken once");
+    tokio::spawn(publish_results(results, context.broker.clone()));
+    Ok(Box::new(pool))
+}
+
+/// Forward pool results to the broker until the pool is dropped
+async fn publish_results(mut results: mpsc::Receiver<TaskResult>, broker: Arc<dyn MessageBroker>) {
+    while let Some(result) = results.recv().await {
+        tracing::info!("Task {} finished: {:?} in {}ms", result.task_id, result.status, result.processing_time_ms);
+        match serde_json::to_vec(&result) {
+            Ok(payload) => {
+                if let Err(e) = broker.publish(RESULTS_SUBJECT, &payload).await {
+                    tracing::error!("Failed to publish result {}: {}", result.task_id, e);
+                }
+            }
+            Err(e) => tracing::error!("Failed to serialize result {}: {}", result.task_id, e),
+        }
+    }
+}
+
+/// Turns documents published on a subject into document tasks
+struct BrokerTaskSource {
+    broker: Arc<dyn MessageBroker>,
+    subject: String,
+    subscription: Mutex<Option<Box<dyn MessageSubscription>>>,
+}
+
+impl BrokerTaskSource {
+    fn new(broker: Arc<dyn MessageBroker>, subject: &str) -> Self {
+        Self {
+            broker,
+            subject: subject.to_string(),
+            subscription: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl TaskSource for BrokerTaskSource {
+    async fn next_task(&self) -> Option<Task> {
+        let mut subscription = self.subscription.lock().await;
+        if subscription.is_none() {
+            match self.broker.subscribe(&self.subject).await {
+                Ok(subscribed) => *subscription = Some(subscribed),
+                Err(e) => {
+                    tracing::error!("Failed to subscribe to {}: {}", self.subject, e);
+                    return None;
+                }
+            }
+        }
+        
+        loop {
+            match subscription.as_mut()?.next_message() {
+                Ok(Some(message)) => match serde_json::from_slice::<Document>(&message.payload) {
+                    Ok(document) => return Some(document_task(document)),
+                    Err(e) => tracing::warn!("Skipping malformed document on {}: {}", self.subject, e),
+                },
+                Ok(None) => tokio::time::sleep(POLL_INTERVAL).await,
+                Err(e) => {
+                    tracing::error!("Subscription to {} failed: {}", self.subject, e);
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Runs document tasks through the document processor
+struct DocumentTaskProcessor {
+    processor: SwarmDocumentProcessor,
+    task_types: Vec<TaskType>,
+}
+
+impl DocumentTaskProcessor {
+    fn new(config: DocumentProcessingConfig) -> Self {
+        let task_types = config.supported_types.iter()
+            .map(|document_type| TaskType::DocumentProcessing {
+                document_type: document_type.clone(),
+                processing_type: DocumentProcessingType::TextExtraction,
+            })
+            .collect();
+        Self {
+            processor: SwarmDocumentProcessor::new(config),
+            task_types,
+        }
+    }
+}
+
+#[async_trait]
+impl TaskProcessor for DocumentTaskProcessor {
+    async fn process(&self, task: &Task) -> Result<TaskResult> {
+        let TaskPayload::Document { document, .. } = &task.payload else {
+            anyhow::bail!("task {} has no document payload", task.id);
+        };
+        
+        let start_time = std::time::Instant::now();
+        let result = self.processor.process_document(document).await?;
+        Ok(TaskResult {
+            task_id: task.id,
+            status: TaskStatus::Completed,
+            result: Some(TaskResultData::DocumentProcessing(result)),
+            error: None,
+            processing_time_ms: start_time.elapsed().as_millis() as u64,
+            completed_at: Utc::now(),
+            metadata: HashMap::new(),
+        })
+    }
+    
+    fn supported_task_types(&self) -> &[TaskType] {
+        &self.task_types
+    }
+    
+    fn estimate_processing_time(&self, task: &Task) -> Duration {
+        match &task.payload {
+            TaskPayload::Document { document, .. } => {
+                swarm_documents::utils::estimate_processing_time(&document.document_type, document.size_bytes)
+            }
+            _ => Duration::from_secs(1),
+        }
+    }
+}
+
+fn document_task(document: Document) -> Task {
+    Task {
+        id: Uuid::new_v4(),
+        task_type: TaskType::DocumentProcessing {
+            document_type: document.document_type.clone(),
+            processing_type: DocumentProcessingType::TextExtraction,
+        },
+        priority: TaskPriority::Normal,
+        status: TaskStatus::Pending,
+        payload: TaskPayload::Document {
+            document,
+            processing_options: DocumentProcessingOptions::default(),
+        },
+        created_at: Utc::now(),
+        deadline: None,
+        retry_count: 0,
+        max_retries: 0,
+        metadata: HashMap::new(),
+    }
+}
+
+fn worker_config(slot: usize) -> WorkerConfig {
+    WorkerConfig {
+        id: Uuid::new_v4(),
+        name: format!("single-node-worker-{}", slot),
+        worker_type: WorkerType::DocumentProcessor { supported_types: Vec::new() },
+        max_concurrent_tasks: 1,
+        capabilities: Vec::new(),
+        performance_profile: PerformanceProfile {
+            avg_processing_time_ms: 100,
+            memory_usage_mb: 64,
+            cpu_intensity: 0.5,
+            throughput_per_second: 10.0,
+        },
+        health_check_interval_ms: 1000,
+        shutdown_timeout_ms: 5000,
+        metadata: HashMap::new(),
+    }
+}